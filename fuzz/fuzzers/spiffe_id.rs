@@ -0,0 +1,11 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate rustls;
+
+use rustls::client::SpiffeId;
+use rustls::pki_types::CertificateDer;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = SpiffeId::from_certificate(&CertificateDer::from(data));
+});
@@ -0,0 +1,407 @@
+//! Composite ML-DSA signatures, from [draft-ietf-lamps-pq-composite-sigs].
+//!
+//! A composite signature pairs ML-DSA-65 with a traditional signature algorithm,
+//! and is only valid if both component signatures verify.  Both components sign
+//! the same message representative, which binds the signature to the composite
+//! algorithm in use; the ML-DSA component additionally uses the algorithm's label
+//! as its context string.
+//!
+//! Composite public keys are the concatenation of the ML-DSA public key and the
+//! traditional public key (an uncompressed point for ECDSA, or the raw Ed25519 key).
+//! Signatures are the concatenation of the ML-DSA signature and the traditional
+//! signature (DER-encoded for ECDSA).  Private keys are PKCS#8-encoded, and contain
+//! the ML-DSA seed followed by the traditional private key (an RFC 5915 `ECPrivateKey`
+//! for ECDSA, or the raw Ed25519 seed).  Keys and certificates are identified by the
+//! draft's `id-MLDSA65-ECDSA-P256-SHA512` and `id-MLDSA65-Ed25519-SHA512` OIDs.
+//!
+//! The ML-DSA component is implemented with the RustCrypto [`ml-dsa`] crate, because
+//! aws-lc-rs does not yet support ML-DSA context strings.  Signing uses the deterministic
+//! variant of ML-DSA.
+//!
+//! # TLS
+//!
+//! No TLS signature schemes have been assigned for composite ML-DSA, so
+//! [`ML_DSA_65_ECDSA_P256_SHA512`] and [`ML_DSA_65_ED25519_SHA512`] are taken
+//! from the private use range.  Like ML-DSA, these are only intended for use in
+//! TLS1.3.  rustls itself does not know these schemes, so cannot exclude them from
+//! TLS1.2 client authentication: configurations using composite keys for client
+//! authentication should only enable TLS1.3.  (A server never signs with a composite
+//! key in TLS1.2, because such keys have no TLS1.2 `SignatureAlgorithm`.)
+//!
+//! [draft-ietf-lamps-pq-composite-sigs]: https://datatracker.ietf.org/doc/draft-ietf-lamps-pq-composite-sigs/
+//! [`ml-dsa`]: https://crates.io/crates/ml-dsa
+
+use core::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use aws_lc_rs::digest;
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::{
+    self, ECDSA_P256_SHA256_ASN1, ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair, Ed25519KeyPair,
+    KeyPair, UnparsedPublicKey,
+};
+use ml_dsa::{EncodedVerifyingKey, ExpandedSigningKey, MlDsa65, Seed, VerifyingKey};
+use rustls::pki_types::{
+    AlgorithmIdentifier, InvalidSignature, PrivatePkcs8KeyDer, SignatureVerificationAlgorithm,
+    SubjectPublicKeyInfoDer,
+};
+use rustls::sign::{Signer, SigningKey};
+use rustls::{Error, SignatureAlgorithm, SignatureScheme};
+
+/// The TLS signature scheme for composite ML-DSA-65 and ECDSA P-256 signatures.
+///
+/// This is from the private use range: see the module docs.
+pub const ML_DSA_65_ECDSA_P256_SHA512: SignatureScheme = SignatureScheme::Unknown(0xfe2d);
+
+/// The TLS signature scheme for composite ML-DSA-65 and Ed25519 signatures.
+///
+/// This is from the private use range: see the module docs.
+pub const ML_DSA_65_ED25519_SHA512: SignatureScheme = SignatureScheme::Unknown(0xfe30);
+
+/// Composite ML-DSA-65 and ECDSA P-256 signatures, with a SHA-512 pre-hash.
+pub static MLDSA65_ECDSA_P256_SHA512: &dyn SignatureVerificationAlgorithm = &MLDSA65_ECDSA_P256;
+
+/// Composite ML-DSA-65 and Ed25519 signatures, with a SHA-512 pre-hash.
+pub static MLDSA65_ED25519_SHA512: &dyn SignatureVerificationAlgorithm = &MLDSA65_ED25519;
+
+/// Load a composite ML-DSA private key, in PKCS#8 format.
+///
+/// Keys for all the composite algorithms supported by this crate are accepted.
+pub fn any_composite_type(der: &PrivatePkcs8KeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
+    for alg in [&MLDSA65_ECDSA_P256, &MLDSA65_ED25519] {
+        if let Ok(key) = CompositeSigningKey::new(der, alg) {
+            return Ok(Arc::new(key));
+        }
+    }
+
+    Err(Error::General(
+        "failed to parse composite ML-DSA private key as PKCS#8".into(),
+    ))
+}
+
+static MLDSA65_ECDSA_P256: CompositeAlgorithm = CompositeAlgorithm {
+    scheme: ML_DSA_65_ECDSA_P256_SHA512,
+    label: b"COMPSIG-MLDSA65-ECDSA-P256-SHA512",
+    // id-MLDSA65-ECDSA-P256-SHA512 (1.3.6.1.5.5.7.6.45), with absent parameters.
+    alg_id: AlgorithmIdentifier::from_slice(&[
+        0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x06, 0x2d,
+    ]),
+    traditional: Traditional::EcdsaP256,
+};
+
+static MLDSA65_ED25519: CompositeAlgorithm = CompositeAlgorithm {
+    scheme: ML_DSA_65_ED25519_SHA512,
+    label: b"COMPSIG-MLDSA65-Ed25519-SHA512",
+    // id-MLDSA65-Ed25519-SHA512 (1.3.6.1.5.5.7.6.48), with absent parameters.
+    alg_id: AlgorithmIdentifier::from_slice(&[
+        0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x06, 0x30,
+    ]),
+    traditional: Traditional::Ed25519,
+};
+
+struct CompositeAlgorithm {
+    scheme: SignatureScheme,
+    label: &'static [u8],
+    alg_id: AlgorithmIdentifier,
+    traditional: Traditional,
+}
+
+impl CompositeAlgorithm {
+    /// Compute the message representative `M'`, which is what both components sign.
+    ///
+    /// This is `Prefix || Label || len(ctx) || ctx || SHA512(M)`; TLS uses an empty `ctx`.
+    fn message_representative(&self, message: &[u8]) -> Vec<u8> {
+        let mut m = Vec::with_capacity(PREFIX.len() + self.label.len() + 1 + 64);
+        m.extend_from_slice(PREFIX);
+        m.extend_from_slice(self.label);
+        m.push(0);
+        m.extend_from_slice(digest::digest(&digest::SHA512, message).as_ref());
+        m
+    }
+}
+
+impl SignatureVerificationAlgorithm for CompositeAlgorithm {
+    fn verify_signature(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), InvalidSignature> {
+        if public_key.len() <= ML_DSA_65_PUBLIC_KEY_LEN
+            || signature.len() <= ML_DSA_65_SIGNATURE_LEN
+        {
+            return Err(InvalidSignature);
+        }
+
+        let (ml_dsa_key, traditional_key) = public_key.split_at(ML_DSA_65_PUBLIC_KEY_LEN);
+        let (ml_dsa_sig, traditional_sig) = signature.split_at(ML_DSA_65_SIGNATURE_LEN);
+        let m = self.message_representative(message);
+
+        let ml_dsa = match (
+            EncodedVerifyingKey::<MlDsa65>::try_from(ml_dsa_key),
+            ml_dsa::Signature::<MlDsa65>::try_from(ml_dsa_sig),
+        ) {
+            (Ok(key), Ok(sig)) => {
+                VerifyingKey::decode(&key).verify_with_context(&m, self.label, &sig)
+            }
+            _ => false,
+        };
+        let traditional = match self.traditional {
+            Traditional::EcdsaP256 => {
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, traditional_key)
+                    .verify(&m, traditional_sig)
+            }
+            Traditional::Ed25519 => UnparsedPublicKey::new(&signature::ED25519, traditional_key)
+                .verify(&m, traditional_sig),
+        };
+
+        match (ml_dsa, traditional) {
+            (true, Ok(())) => Ok(()),
+            _ => Err(InvalidSignature),
+        }
+    }
+
+    fn public_key_alg_id(&self) -> AlgorithmIdentifier {
+        self.alg_id
+    }
+
+    fn signature_alg_id(&self) -> AlgorithmIdentifier {
+        self.alg_id
+    }
+}
+
+impl Debug for CompositeAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeAlgorithm")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Traditional {
+    EcdsaP256,
+    Ed25519,
+}
+
+/// A `SigningKey` for exactly one composite ML-DSA algorithm.
+struct CompositeSigningKey {
+    key: Arc<CompositeKeyPair>,
+}
+
+impl CompositeSigningKey {
+    fn new(der: &PrivatePkcs8KeyDer<'_>, alg: &'static CompositeAlgorithm) -> Result<Self, ()> {
+        let private_key = pkcs8_private_key(der.secret_pkcs8_der(), &alg.alg_id).ok_or(())?;
+        if private_key.len() <= ML_DSA_SEED_LEN {
+            return Err(());
+        }
+        let (seed, traditional) = private_key.split_at(ML_DSA_SEED_LEN);
+
+        let ml_dsa = Box::new(ExpandedSigningKey::from_seed(
+            &Seed::try_from(seed).map_err(|_| ())?,
+        ));
+        let traditional = match alg.traditional {
+            Traditional::EcdsaP256 => TraditionalKeyPair::EcdsaP256(
+                EcdsaKeyPair::from_private_key_der(&ECDSA_P256_SHA256_ASN1_SIGNING, traditional)
+                    .map_err(|_| ())?,
+            ),
+            Traditional::Ed25519 => TraditionalKeyPair::Ed25519(
+                Ed25519KeyPair::from_seed_unchecked(traditional).map_err(|_| ())?,
+            ),
+        };
+
+        Ok(Self {
+            key: Arc::new(CompositeKeyPair {
+                alg,
+                ml_dsa,
+                traditional,
+            }),
+        })
+    }
+}
+
+impl SigningKey for CompositeSigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if offered.contains(&self.key.alg.scheme) {
+            Some(Box::new(CompositeSigner {
+                key: self.key.clone(),
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        let mut public_key = self
+            .key
+            .ml_dsa
+            .verifying_key()
+            .encode()
+            .to_vec();
+        public_key.extend_from_slice(match &self.key.traditional {
+            TraditionalKeyPair::EcdsaP256(key) => key.public_key().as_ref(),
+            TraditionalKeyPair::Ed25519(key) => key.public_key().as_ref(),
+        });
+
+        // SubjectPublicKeyInfo  ::=  SEQUENCE  {
+        //    algorithm            AlgorithmIdentifier,
+        //    subjectPublicKey     BIT STRING  }
+        let mut bit_string = vec![0];
+        bit_string.extend_from_slice(&public_key);
+        let mut spki = der_encode(SEQUENCE, self.key.alg.alg_id.as_ref());
+        spki.extend_from_slice(&der_encode(BIT_STRING, &bit_string));
+
+        Some(SubjectPublicKeyInfoDer::from(der_encode(SEQUENCE, &spki)))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        // There is no `SignatureAlgorithm` (which is only meaningful for TLS1.2) for composites.
+        SignatureAlgorithm::Unknown(0)
+    }
+}
+
+impl Debug for CompositeSigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeSigningKey")
+            .field("scheme", &self.key.alg.scheme)
+            .finish()
+    }
+}
+
+struct CompositeSigner {
+    key: Arc<CompositeKeyPair>,
+}
+
+impl Signer for CompositeSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let m = self
+            .key
+            .alg
+            .message_representative(message);
+
+        let mut sig = self
+            .key
+            .ml_dsa
+            .sign_deterministic(&m, self.key.alg.label)
+            .map_err(|_| Error::General("signing failed".into()))?
+            .encode()
+            .to_vec();
+
+        match &self.key.traditional {
+            TraditionalKeyPair::EcdsaP256(key) => sig.extend_from_slice(
+                key.sign(&SystemRandom::new(), &m)
+                    .map_err(|_| Error::General("signing failed".into()))?
+                    .as_ref(),
+            ),
+            TraditionalKeyPair::Ed25519(key) => sig.extend_from_slice(key.sign(&m).as_ref()),
+        }
+
+        Ok(sig)
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.key.alg.scheme
+    }
+}
+
+impl Debug for CompositeSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeSigner")
+            .field("scheme", &self.key.alg.scheme)
+            .finish()
+    }
+}
+
+struct CompositeKeyPair {
+    alg: &'static CompositeAlgorithm,
+    ml_dsa: Box<ExpandedSigningKey<MlDsa65>>,
+    traditional: TraditionalKeyPair,
+}
+
+enum TraditionalKeyPair {
+    EcdsaP256(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+/// Return the `privateKey` contents of a PKCS#8 `OneAsymmetricKey`, if its
+/// algorithm is `alg_id`.
+fn pkcs8_private_key<'a>(der: &'a [u8], alg_id: &AlgorithmIdentifier) -> Option<&'a [u8]> {
+    // OneAsymmetricKey ::= SEQUENCE {
+    //    version                   Version,
+    //    privateKeyAlgorithm       PrivateKeyAlgorithmIdentifier,
+    //    privateKey                PrivateKey,
+    //    attributes            [0] Attributes OPTIONAL,
+    //    ...,
+    //    [[2: publicKey        [1] PublicKey OPTIONAL ]],
+    //    ...
+    // }
+    let mut input = der;
+    let mut key = der_decode(&mut input, SEQUENCE)?;
+    if !input.is_empty() {
+        return None;
+    }
+
+    match der_decode(&mut key, INTEGER)? {
+        [0] | [1] => {}
+        _ => return None,
+    }
+
+    if der_decode(&mut key, SEQUENCE)? != alg_id.as_ref() {
+        return None;
+    }
+
+    der_decode(&mut key, OCTET_STRING)
+}
+
+/// Read a DER element with the given `tag` from the front of `input`,
+/// returning its contents.
+fn der_decode<'a>(input: &mut &'a [u8], tag: u8) -> Option<&'a [u8]> {
+    let &[actual, first, ref rest @ ..] = *input else {
+        return None;
+    };
+    if actual != tag {
+        return None;
+    }
+
+    let (len, rest) = match (first, rest) {
+        (0x00..=0x7f, rest) => (usize::from(first), rest),
+        (0x81, [len, rest @ ..]) if *len >= 0x80 => (usize::from(*len), rest),
+        (0x82, [hi, lo, rest @ ..]) if *hi != 0 => {
+            (usize::from(u16::from_be_bytes([*hi, *lo])), rest)
+        }
+        _ => return None,
+    };
+    if rest.len() < len {
+        return None;
+    }
+
+    let (contents, rest) = rest.split_at(len);
+    *input = rest;
+    Some(contents)
+}
+
+fn der_encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match contents.len() {
+        len @ 0..=0x7f => out.push(len as u8),
+        len @ 0x80..=0xff => out.extend_from_slice(&[0x81, len as u8]),
+        len => {
+            out.push(0x82);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const SEQUENCE: u8 = 0x30;
+
+/// The domain separator which begins every composite message representative.
+const PREFIX: &[u8] = b"CompositeAlgorithmSignatures2025";
+
+const ML_DSA_SEED_LEN: usize = 32;
+const ML_DSA_65_PUBLIC_KEY_LEN: usize = 1952;
+const ML_DSA_65_SIGNATURE_LEN: usize = 3309;
@@ -4,21 +4,35 @@
 //!
 //! - `aws-lc-rs-unstable`: adds support for three variants of the experimental ML-DSA signature
 //!   algorithm.
+//! - `composite-ml-dsa`: adds support for experimental composite ML-DSA signatures, which pair
+//!   ML-DSA-65 with ECDSA P-256 or Ed25519, following draft-ietf-lamps-pq-composite-sigs.
+//!   The TLS signature schemes are from the private use range: see the [`composite`] module
+//!   for details.  This feature implies `aws-lc-rs-unstable`, and requires Rust 1.85 or later.
 //!
 //! Before rustls 0.23.22, this crate additionally provided support for the ML-KEM key exchange
 //! (both "pure" and hybrid variants), but these have been moved to the rustls crate itself.
 //! In rustls 0.23.22 and later, you can use rustls' `prefer-post-quantum` feature to determine
 //! whether the ML-KEM key exchange is preferred over non-post-quantum key exchanges.
 
+#[cfg(feature = "composite-ml-dsa")]
+use std::sync::Arc;
+
 #[cfg(feature = "aws-lc-rs-unstable")]
 use rustls::SignatureScheme;
 use rustls::crypto::CryptoProvider;
 #[cfg(feature = "aws-lc-rs-unstable")]
 use rustls::crypto::WebPkiSupportedAlgorithms;
 pub use rustls::crypto::aws_lc_rs::kx_group::{MLKEM768, X25519MLKEM768};
+#[cfg(feature = "composite-ml-dsa")]
+use rustls::pki_types::PrivateKeyDer;
+#[cfg(feature = "composite-ml-dsa")]
+use rustls::sign::SigningKey;
 #[cfg(feature = "aws-lc-rs-unstable")]
 use webpki::aws_lc_rs as webpki_algs;
 
+#[cfg(feature = "composite-ml-dsa")]
+pub mod composite;
+
 pub fn provider() -> CryptoProvider {
     #[cfg_attr(not(feature = "aws-lc-rs-unstable"), allow(unused_mut))]
     let mut provider = rustls::crypto::aws_lc_rs::default_provider();
@@ -26,9 +40,34 @@ pub fn provider() -> CryptoProvider {
     {
         provider.signature_verification_algorithms = SUPPORTED_SIG_ALGS;
     }
+    #[cfg(feature = "composite-ml-dsa")]
+    {
+        provider.key_provider = &KeyProvider;
+    }
     provider
 }
 
+/// Loads composite ML-DSA keys, and otherwise defers to the aws-lc-rs provider.
+#[cfg(feature = "composite-ml-dsa")]
+#[derive(Debug)]
+struct KeyProvider;
+
+#[cfg(feature = "composite-ml-dsa")]
+impl rustls::crypto::KeyProvider for KeyProvider {
+    fn load_private_key(
+        &self,
+        key_der: PrivateKeyDer<'static>,
+    ) -> Result<Arc<dyn SigningKey>, rustls::Error> {
+        if let PrivateKeyDer::Pkcs8(pkcs8) = &key_der {
+            if let Ok(key) = composite::any_composite_type(pkcs8) {
+                return Ok(key);
+            }
+        }
+
+        rustls::crypto::aws_lc_rs::sign::any_supported_type(&key_der)
+    }
+}
+
 /// Keep in sync with the `SUPPORTED_SIG_ALGS` in `rustls::crypto::aws_lc_rs`.
 #[cfg(feature = "aws-lc-rs-unstable")]
 static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms {
@@ -56,6 +95,10 @@ static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms
         webpki_algs::ML_DSA_65,
         #[cfg(feature = "aws-lc-rs-unstable")]
         webpki_algs::ML_DSA_87,
+        #[cfg(feature = "composite-ml-dsa")]
+        composite::MLDSA65_ECDSA_P256_SHA512,
+        #[cfg(feature = "composite-ml-dsa")]
+        composite::MLDSA65_ED25519_SHA512,
     ],
     mapping: &[
         // Note: for TLS1.2 the curve is not fixed by SignatureScheme. For TLS1.3 it is.
@@ -110,5 +153,15 @@ static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms
         (SignatureScheme::ML_DSA_65, &[webpki_algs::ML_DSA_65]),
         #[cfg(feature = "aws-lc-rs-unstable")]
         (SignatureScheme::ML_DSA_87, &[webpki_algs::ML_DSA_87]),
+        #[cfg(feature = "composite-ml-dsa")]
+        (
+            composite::ML_DSA_65_ECDSA_P256_SHA512,
+            &[composite::MLDSA65_ECDSA_P256_SHA512],
+        ),
+        #[cfg(feature = "composite-ml-dsa")]
+        (
+            composite::ML_DSA_65_ED25519_SHA512,
+            &[composite::MLDSA65_ED25519_SHA512],
+        ),
     ],
 };
@@ -0,0 +1,309 @@
+#![cfg(feature = "composite-ml-dsa")]
+
+use std::sync::Arc;
+
+use aws_lc_rs::encoding::AsDer;
+use aws_lc_rs::signature::{
+    ECDSA_P256_SHA256_ASN1_SIGNING, ED25519, EcdsaKeyPair, UnparsedPublicKey,
+};
+use aws_lc_rs::{digest, rand};
+use ml_dsa::MlDsa65;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{
+    CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, SubjectPublicKeyInfoDer,
+    UnixTime,
+};
+use rustls::server::AlwaysResolvesServerRawPublicKeys;
+use rustls::sign::{CertifiedKey, Signer, SigningKey};
+use rustls::version::TLS13;
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, Error, ServerConfig, SignatureAlgorithm,
+    SignatureScheme,
+};
+use rustls_post_quantum::composite;
+use rustls_test::{ErrorFromPeer, do_handshake, do_handshake_until_error, make_pair_for_configs};
+
+#[test]
+fn composite_ml_dsa_handshake() {
+    for (scheme, key) in [
+        (
+            composite::ML_DSA_65_ECDSA_P256_SHA512,
+            ml_dsa_65_ecdsa_p256_key(),
+        ),
+        (composite::ML_DSA_65_ED25519_SHA512, ml_dsa_65_ed25519_key()),
+    ] {
+        println!("{scheme:?}");
+        let provider = rustls_post_quantum::provider();
+        let key = provider
+            .key_provider
+            .load_private_key(key)
+            .unwrap();
+        assert!(key.choose_scheme(&[scheme]).is_some());
+
+        let (client_config, server_config) = raw_key_configs(&provider, key);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
+    }
+}
+
+#[test]
+fn composite_ml_dsa_requires_both_signatures() {
+    for key in [ml_dsa_65_ecdsa_p256_key(), ml_dsa_65_ed25519_key()] {
+        let provider = rustls_post_quantum::provider();
+        let key = provider
+            .key_provider
+            .load_private_key(key)
+            .unwrap();
+
+        for corrupt in [Corrupt::MlDsa, Corrupt::Traditional] {
+            println!("{key:?} {corrupt:?}");
+            let key = Arc::new(CorruptingKey {
+                inner: key.clone(),
+                corrupt,
+            });
+            let (client_config, server_config) = raw_key_configs(&provider, key);
+            let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+            assert_eq!(
+                do_handshake_until_error(&mut client, &mut server),
+                Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                    CertificateError::BadSignature
+                )))
+            );
+        }
+    }
+}
+
+#[test]
+fn composite_ml_dsa_signature_follows_draft() {
+    let provider = rustls_post_quantum::provider();
+    let key = provider
+        .key_provider
+        .load_private_key(ml_dsa_65_ed25519_key())
+        .unwrap();
+    let signer = key
+        .choose_scheme(&[composite::ML_DSA_65_ED25519_SHA512])
+        .unwrap();
+    let sig = signer.sign(b"hello").unwrap();
+    let (ml_dsa_sig, ed25519_sig) = sig.split_at(3309);
+
+    // The public key is the ML-DSA-65 key followed by the Ed25519 key, at the end of the SPKI.
+    let spki = key.public_key().unwrap();
+    let (ml_dsa_key, ed25519_key) = spki[spki.len() - 1952 - 32..].split_at(1952);
+
+    // M' = Prefix || Label || len(ctx) || ctx || SHA512(M), with an empty ctx.
+    let label = b"COMPSIG-MLDSA65-Ed25519-SHA512";
+    let mut m = b"CompositeAlgorithmSignatures2025".to_vec();
+    m.extend_from_slice(label);
+    m.push(0);
+    m.extend_from_slice(digest::digest(&digest::SHA512, b"hello").as_ref());
+
+    // The ML-DSA component uses the label as its context string.
+    let ml_dsa_key = ml_dsa::VerifyingKey::<MlDsa65>::decode(&ml_dsa_key.try_into().unwrap());
+    let ml_dsa_sig = ml_dsa::Signature::<MlDsa65>::try_from(ml_dsa_sig).unwrap();
+    assert!(ml_dsa_key.verify_with_context(&m, label, &ml_dsa_sig));
+    assert!(!ml_dsa_key.verify_with_context(&m, b"", &ml_dsa_sig));
+
+    UnparsedPublicKey::new(&ED25519, ed25519_key)
+        .verify(&m, ed25519_sig)
+        .unwrap();
+}
+
+#[test]
+fn composite_ml_dsa_key_is_rejected_for_other_algorithm() {
+    let mut der = ml_dsa_65_ecdsa_p256_key()
+        .secret_der()
+        .to_vec();
+    // Relabel the key as MLDSA65-Ed25519-SHA512.
+    let oid = der
+        .windows(MLDSA65_ECDSA_P256_SHA512_OID.len())
+        .position(|w| w == MLDSA65_ECDSA_P256_SHA512_OID)
+        .unwrap();
+    der[oid..oid + MLDSA65_ED25519_SHA512_OID.len()].copy_from_slice(MLDSA65_ED25519_SHA512_OID);
+
+    assert!(composite::any_composite_type(&PrivatePkcs8KeyDer::from(der)).is_err());
+}
+
+fn raw_key_configs(
+    provider: &CryptoProvider,
+    key: Arc<dyn SigningKey>,
+) -> (ClientConfig, ServerConfig) {
+    let spki = key.public_key().unwrap().to_vec();
+    let server_config = ServerConfig::builder_with_provider(provider.clone().into())
+        .with_protocol_versions(&[&TLS13])
+        .unwrap()
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(AlwaysResolvesServerRawPublicKeys::new(Arc::new(
+            CertifiedKey::new_unchecked(vec![CertificateDer::from(spki.clone())], key),
+        ))));
+
+    let client_config = ClientConfig::builder_with_provider(provider.clone().into())
+        .with_protocol_versions(&[&TLS13])
+        .unwrap()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(RawPublicKeyVerifier {
+            spki,
+            algorithms: provider.signature_verification_algorithms,
+        }))
+        .with_no_client_auth();
+
+    (client_config, server_config)
+}
+
+/// Accepts exactly one raw public key.
+#[derive(Debug)]
+struct RawPublicKeyVerifier {
+    spki: Vec<u8>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for RawPublicKeyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        match end_entity.as_ref() == self.spki {
+            true => Ok(ServerCertVerified::assertion()),
+            false => Err(Error::General("unexpected raw public key".into())),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        rustls::crypto::verify_tls13_signature_with_raw_key(
+            message,
+            &SubjectPublicKeyInfoDer::from(cert.as_ref()),
+            dss,
+            &self.algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+
+    fn request_ocsp_response(&self) -> bool {
+        false
+    }
+
+    fn requires_raw_public_keys(&self) -> bool {
+        true
+    }
+}
+
+/// Damages one component of the signatures made by `inner`.
+#[derive(Debug)]
+struct CorruptingKey {
+    inner: Arc<dyn SigningKey>,
+    corrupt: Corrupt,
+}
+
+impl SigningKey for CorruptingKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        Some(Box::new(CorruptingSigner {
+            inner: self.inner.choose_scheme(offered)?,
+            corrupt: self.corrupt,
+        }))
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        self.inner.public_key()
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.inner.algorithm()
+    }
+}
+
+#[derive(Debug)]
+struct CorruptingSigner {
+    inner: Box<dyn Signer>,
+    corrupt: Corrupt,
+}
+
+impl Signer for CorruptingSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut sig = self.inner.sign(message)?;
+        match self.corrupt {
+            Corrupt::MlDsa => sig[0] ^= 1,
+            // The ML-DSA-65 signature is the first 3309 bytes.
+            Corrupt::Traditional => sig[3309 + 8] ^= 1,
+        }
+        Ok(sig)
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.inner.scheme()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Corrupt {
+    MlDsa,
+    Traditional,
+}
+
+fn ml_dsa_65_ecdsa_p256_key() -> PrivateKeyDer<'static> {
+    let ecdsa = EcdsaKeyPair::generate(&ECDSA_P256_SHA256_ASN1_SIGNING).unwrap();
+    let mut private_key = ml_dsa_seed();
+    private_key.extend_from_slice(
+        ecdsa
+            .private_key()
+            .as_der()
+            .unwrap()
+            .as_ref(),
+    );
+    composite_pkcs8(MLDSA65_ECDSA_P256_SHA512_OID, &private_key)
+}
+
+fn ml_dsa_65_ed25519_key() -> PrivateKeyDer<'static> {
+    let mut private_key = ml_dsa_seed();
+    private_key.extend_from_slice(&[0; 32]);
+    rand::fill(&mut private_key[32..]).unwrap();
+    composite_pkcs8(MLDSA65_ED25519_SHA512_OID, &private_key)
+}
+
+fn ml_dsa_seed() -> Vec<u8> {
+    let mut seed = vec![0; 32];
+    rand::fill(&mut seed).unwrap();
+    seed
+}
+
+fn composite_pkcs8(oid: &[u8], private_key: &[u8]) -> PrivateKeyDer<'static> {
+    let mut key = vec![0x02, 0x01, 0x00];
+    key.extend_from_slice(&der(0x30, &der(0x06, oid)));
+    key.extend_from_slice(&der(0x04, private_key));
+    PrivateKeyDer::Pkcs8(der(0x30, &key).into())
+}
+
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match u8::try_from(contents.len()).unwrap() {
+        len @ 0..=0x7f => out.push(len),
+        len => out.extend_from_slice(&[0x81, len]),
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+// id-MLDSA65-ECDSA-P256-SHA512 (1.3.6.1.5.5.7.6.45)
+const MLDSA65_ECDSA_P256_SHA512_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x06, 0x2d];
+// id-MLDSA65-Ed25519-SHA512 (1.3.6.1.5.5.7.6.48)
+const MLDSA65_ED25519_SHA512_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x06, 0x30];
@@ -1,10 +1,10 @@
 use std::fs::File;
 
-use rustls::crypto::aws_lc_rs;
 use rustls::crypto::hpke::{
     Hpke, HpkeAead, HpkeKdf, HpkeKem, HpkePrivateKey, HpkePublicKey, HpkeSuite,
     HpkeSymmetricCipherSuite,
 };
+use rustls::crypto::{aws_lc_rs, ring, rustcrypto};
 use serde::Deserialize;
 
 /// Confirm open/seal operations work using the test vectors from [RFC 9180 Appendix A].
@@ -78,41 +78,70 @@ impl TestVector {
             return None;
         }
 
-        match (
-            Self::lookup_suite(self.suite(), aws_lc_rs::hpke::ALL_SUPPORTED_SUITES),
-            Self::lookup_suite(self.suite(), provider_example::hpke::ALL_SUPPORTED_SUITES),
-        ) {
-            // Both providers support the suite. Test against themselves, and each other.
-            (Some(aws_suite), Some(hpke_rs_suite)) => Some(vec![
-                (aws_suite, aws_suite),
-                (hpke_rs_suite, hpke_rs_suite),
-                (aws_suite, hpke_rs_suite),
-                (hpke_rs_suite, aws_suite),
-            ]),
-
-            // aws-lc-rs supported the suite, not hpke-rs, test against itself
-            (Some(aws_suite), None) => Some(vec![(aws_suite, aws_suite)]),
-
-            // hpke-rs supported the suite, not AWS-LC-RS, test against itself
-            //
-            // Note: presently there are no suites hpke-rs supports that aws-lc-rs doesn't. This
-            //       is future-proofing.
-            (None, Some(hpke_rs_suite)) => Some(vec![(hpke_rs_suite, hpke_rs_suite)]),
-
-            // Neither provider supported the suite - nothing to do.
-            (None, None) => None,
+        let suites = provider_suites(self.suite());
+        if suites.is_empty() {
+            // No provider supported the suite - nothing to do.
+            return None;
         }
+
+        // Test each provider against itself, and each other.
+        Some(all_pairs(&suites))
     }
+}
 
-    fn lookup_suite(
-        suite: HpkeSuite,
-        supported: &[&'static dyn Hpke],
-    ) -> Option<&'static dyn Hpke> {
+/// Confirm that each provider can open what every other provider seals, for every suite
+/// that more than one provider supports.
+#[test]
+fn cross_provider_round_trip() {
+    for suite in provider_example::hpke::ALL_SUPPORTED_SUITES
+        .iter()
+        .chain(aws_lc_rs::hpke::ALL_SUPPORTED_SUITES)
+        .chain(ring::hpke::ALL_SUPPORTED_SUITES)
+        .chain(rustcrypto::hpke::ALL_SUPPORTED_SUITES)
+    {
+        let suites = provider_suites(suite.suite());
+        for (sealer, opener) in all_pairs(&suites) {
+            let (pk_r, sk_r) = opener.generate_key_pair().unwrap();
+            let (enc, ciphertext) = sealer
+                .seal(b"info", b"aad", b"plaintext", &pk_r)
+                .unwrap();
+
+            let plaintext = opener
+                .open(&enc, b"info", b"aad", &ciphertext, &sk_r)
+                .unwrap();
+            assert_eq!(plaintext, b"plaintext", "{sealer:?} -> {opener:?}");
+        }
+    }
+}
+
+/// The implementations of `suite` from each provider that supports it.
+fn provider_suites(suite: HpkeSuite) -> Vec<&'static dyn Hpke> {
+    [
+        aws_lc_rs::hpke::ALL_SUPPORTED_SUITES,
+        ring::hpke::ALL_SUPPORTED_SUITES,
+        rustcrypto::hpke::ALL_SUPPORTED_SUITES,
+        provider_example::hpke::ALL_SUPPORTED_SUITES,
+    ]
+    .into_iter()
+    .filter_map(|supported| {
         supported
             .iter()
             .find(|s| s.suite() == suite)
             .copied()
-    }
+    })
+    .collect()
+}
+
+/// Every (sealer, opener) combination of `suites`.
+fn all_pairs(suites: &[&'static dyn Hpke]) -> Vec<(&'static dyn Hpke, &'static dyn Hpke)> {
+    suites
+        .iter()
+        .flat_map(|&sealer| {
+            suites
+                .iter()
+                .map(move |&opener| (sealer, opener))
+        })
+        .collect()
 }
 
 fn test_vectors() -> Vec<TestVector> {
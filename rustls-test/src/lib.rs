@@ -210,6 +210,70 @@ embed_files! {
     (RSA_4096_END_KEY, "rsa-4096", "end.key");
     (RSA_4096_INTER_CERT, "rsa-4096", "inter.cert");
     (RSA_4096_INTER_KEY, "rsa-4096", "inter.key");
+
+    (ML_DSA_44_END_PEM_SPKI, "ml-dsa-44", "end.spki.pem");
+    (ML_DSA_44_CLIENT_PEM_SPKI, "ml-dsa-44", "client.spki.pem");
+    (ML_DSA_44_CA_CERT, "ml-dsa-44", "ca.cert");
+    (ML_DSA_44_CA_DER, "ml-dsa-44", "ca.der");
+    (ML_DSA_44_CA_KEY, "ml-dsa-44", "ca.key");
+    (ML_DSA_44_CLIENT_CERT, "ml-dsa-44", "client.cert");
+    (ML_DSA_44_CLIENT_CHAIN, "ml-dsa-44", "client.chain");
+    (ML_DSA_44_CLIENT_FULLCHAIN, "ml-dsa-44", "client.fullchain");
+    (ML_DSA_44_CLIENT_KEY, "ml-dsa-44", "client.key");
+    (ML_DSA_44_END_CERT, "ml-dsa-44", "end.cert");
+    (ML_DSA_44_END_CHAIN, "ml-dsa-44", "end.chain");
+    (ML_DSA_44_END_FULLCHAIN, "ml-dsa-44", "end.fullchain");
+    (ML_DSA_44_END_KEY, "ml-dsa-44", "end.key");
+    (ML_DSA_44_INTER_CERT, "ml-dsa-44", "inter.cert");
+    (ML_DSA_44_INTER_KEY, "ml-dsa-44", "inter.key");
+
+    (ML_DSA_65_END_PEM_SPKI, "ml-dsa-65", "end.spki.pem");
+    (ML_DSA_65_CLIENT_PEM_SPKI, "ml-dsa-65", "client.spki.pem");
+    (ML_DSA_65_CA_CERT, "ml-dsa-65", "ca.cert");
+    (ML_DSA_65_CA_DER, "ml-dsa-65", "ca.der");
+    (ML_DSA_65_CA_KEY, "ml-dsa-65", "ca.key");
+    (ML_DSA_65_CLIENT_CERT, "ml-dsa-65", "client.cert");
+    (ML_DSA_65_CLIENT_CHAIN, "ml-dsa-65", "client.chain");
+    (ML_DSA_65_CLIENT_FULLCHAIN, "ml-dsa-65", "client.fullchain");
+    (ML_DSA_65_CLIENT_KEY, "ml-dsa-65", "client.key");
+    (ML_DSA_65_END_CERT, "ml-dsa-65", "end.cert");
+    (ML_DSA_65_END_CHAIN, "ml-dsa-65", "end.chain");
+    (ML_DSA_65_END_FULLCHAIN, "ml-dsa-65", "end.fullchain");
+    (ML_DSA_65_END_KEY, "ml-dsa-65", "end.key");
+    (ML_DSA_65_INTER_CERT, "ml-dsa-65", "inter.cert");
+    (ML_DSA_65_INTER_KEY, "ml-dsa-65", "inter.key");
+
+    (ML_DSA_87_END_PEM_SPKI, "ml-dsa-87", "end.spki.pem");
+    (ML_DSA_87_CLIENT_PEM_SPKI, "ml-dsa-87", "client.spki.pem");
+    (ML_DSA_87_CA_CERT, "ml-dsa-87", "ca.cert");
+    (ML_DSA_87_CA_DER, "ml-dsa-87", "ca.der");
+    (ML_DSA_87_CA_KEY, "ml-dsa-87", "ca.key");
+    (ML_DSA_87_CLIENT_CERT, "ml-dsa-87", "client.cert");
+    (ML_DSA_87_CLIENT_CHAIN, "ml-dsa-87", "client.chain");
+    (ML_DSA_87_CLIENT_FULLCHAIN, "ml-dsa-87", "client.fullchain");
+    (ML_DSA_87_CLIENT_KEY, "ml-dsa-87", "client.key");
+    (ML_DSA_87_END_CERT, "ml-dsa-87", "end.cert");
+    (ML_DSA_87_END_CHAIN, "ml-dsa-87", "end.chain");
+    (ML_DSA_87_END_FULLCHAIN, "ml-dsa-87", "end.fullchain");
+    (ML_DSA_87_END_KEY, "ml-dsa-87", "end.key");
+    (ML_DSA_87_INTER_CERT, "ml-dsa-87", "inter.cert");
+    (ML_DSA_87_INTER_KEY, "ml-dsa-87", "inter.key");
+
+    (ED448_END_PEM_SPKI, "ed448", "end.spki.pem");
+    (ED448_CLIENT_PEM_SPKI, "ed448", "client.spki.pem");
+    (ED448_CA_CERT, "ed448", "ca.cert");
+    (ED448_CA_DER, "ed448", "ca.der");
+    (ED448_CA_KEY, "ed448", "ca.key");
+    (ED448_CLIENT_CERT, "ed448", "client.cert");
+    (ED448_CLIENT_CHAIN, "ed448", "client.chain");
+    (ED448_CLIENT_FULLCHAIN, "ed448", "client.fullchain");
+    (ED448_CLIENT_KEY, "ed448", "client.key");
+    (ED448_END_CERT, "ed448", "end.cert");
+    (ED448_END_CHAIN, "ed448", "end.chain");
+    (ED448_END_FULLCHAIN, "ed448", "end.fullchain");
+    (ED448_END_KEY, "ed448", "end.key");
+    (ED448_INTER_CERT, "ed448", "inter.cert");
+    (ED448_INTER_KEY, "ed448", "inter.key");
 }
 
 pub fn transfer(
@@ -232,7 +296,13 @@ pub fn transfer(
         let mut offs = 0;
         loop {
             let from_buf: &mut dyn io::Read = &mut &buf[offs..sz];
-            offs += right.read_tls(from_buf).unwrap();
+            match right.read_tls(from_buf) {
+                Ok(n) => offs += n,
+                // `read_tls` will not buffer several large handshake messages
+                // (eg. post-quantum certificate chains) without them being processed.
+                Err(_) if right.process_new_packets().is_ok() => {}
+                Err(err) => panic!("{err:?}"),
+            }
             if sz == offs {
                 break;
             }
@@ -313,6 +383,14 @@ pub enum KeyType {
     EcdsaP384,
     EcdsaP521,
     Ed25519,
+    /// Only usable in TLS1.3, and only with the aws-lc-rs provider's
+    /// `aws-lc-rs-unstable` feature.
+    MlDsa44,
+    MlDsa65,
+    MlDsa87,
+    /// Only usable with the RustCrypto provider, or with the `curve448`
+    /// crate feature.
+    Ed448,
 }
 
 static ALL_KEY_TYPES: &[KeyType] = &[
@@ -346,7 +424,7 @@ impl KeyType {
         }
     }
 
-    fn bytes_for(&self, part: &str) -> &'static [u8] {
+    pub fn bytes_for(&self, part: &str) -> &'static [u8] {
         match self {
             Self::Rsa2048 => bytes_for("rsa-2048", part),
             Self::Rsa3072 => bytes_for("rsa-3072", part),
@@ -355,6 +433,10 @@ impl KeyType {
             Self::EcdsaP384 => bytes_for("ecdsa-p384", part),
             Self::EcdsaP521 => bytes_for("ecdsa-p521", part),
             Self::Ed25519 => bytes_for("eddsa", part),
+            Self::MlDsa44 => bytes_for("ml-dsa-44", part),
+            Self::MlDsa65 => bytes_for("ml-dsa-65", part),
+            Self::MlDsa87 => bytes_for("ml-dsa-87", part),
+            Self::Ed448 => bytes_for("ed448", part),
         }
     }
 
@@ -473,6 +555,10 @@ impl KeyType {
             Self::EcdsaP384 => b"0\x211\x1f0\x1d\x06\x03U\x04\x03\x0c\x16ponytown ECDSA p384 CA",
             Self::EcdsaP521 => b"0\x211\x1f0\x1d\x06\x03U\x04\x03\x0c\x16ponytown ECDSA p521 CA",
             Self::Ed25519 => b"0\x1c1\x1a0\x18\x06\x03U\x04\x03\x0c\x11ponytown EdDSA CA",
+            Self::MlDsa44 => b"0\x201\x1e0\x1c\x06\x03U\x04\x03\x0c\x15ponytown ML-DSA-44 CA",
+            Self::MlDsa65 => b"0\x201\x1e0\x1c\x06\x03U\x04\x03\x0c\x15ponytown ML-DSA-65 CA",
+            Self::MlDsa87 => b"0\x201\x1e0\x1c\x06\x03U\x04\x03\x0c\x15ponytown ML-DSA-87 CA",
+            Self::Ed448 => b"0\x1c1\x1a0\x18\x06\x03U\x04\x03\x0c\x11ponytown ED448 CA",
         }
     }
 }
@@ -1158,6 +1244,7 @@ pub struct MockServerVerifier {
     expected_ocsp_response: Option<Vec<u8>>,
     requires_raw_public_keys: bool,
     raw_public_key_algorithms: Option<WebPkiSupportedAlgorithms>,
+    root_hint_subjects: Option<Arc<[DistinguishedName]>>,
 }
 
 impl ServerCertVerifier for MockServerVerifier {
@@ -1226,6 +1313,10 @@ impl ServerCertVerifier for MockServerVerifier {
     fn requires_raw_public_keys(&self) -> bool {
         self.requires_raw_public_keys
     }
+
+    fn root_hint_subjects(&self) -> Option<Arc<[DistinguishedName]>> {
+        self.root_hint_subjects.clone()
+    }
 }
 
 impl MockServerVerifier {
@@ -1271,6 +1362,20 @@ impl MockServerVerifier {
         }
     }
 
+    pub fn offers_signature_schemes(signature_schemes: Vec<SignatureScheme>) -> Self {
+        Self {
+            signature_schemes,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_root_hint_subjects(self, subjects: Vec<DistinguishedName>) -> Self {
+        Self {
+            root_hint_subjects: Some(subjects.into()),
+            ..self
+        }
+    }
+
     pub fn expects_raw_public_keys(provider: &CryptoProvider) -> Self {
         Self {
             requires_raw_public_keys: true,
@@ -1297,6 +1402,7 @@ impl Default for MockServerVerifier {
             expected_ocsp_response: None,
             requires_raw_public_keys: false,
             raw_public_key_algorithms: None,
+            root_hint_subjects: None,
         }
     }
 }
@@ -1307,6 +1413,7 @@ pub struct MockClientVerifier {
     pub subjects: Arc<[DistinguishedName]>,
     pub mandatory: bool,
     pub offered_schemes: Option<Vec<SignatureScheme>>,
+    pub signature_error: Option<Error>,
     expect_raw_public_keys: bool,
     raw_public_key_algorithms: Option<WebPkiSupportedAlgorithms>,
     parent: Arc<dyn ClientCertVerifier>,
@@ -1326,6 +1433,7 @@ impl MockClientVerifier {
             subjects: Arc::from(get_client_root_store(kt).subjects()),
             mandatory: true,
             offered_schemes: None,
+            signature_error: None,
             expect_raw_public_keys: false,
             raw_public_key_algorithms: Some(provider.signature_verification_algorithms),
         }
@@ -1356,7 +1464,9 @@ impl ClientCertVerifier for MockClientVerifier {
         cert: &CertificateDer<'_>,
         dss: &DigitallySignedStruct,
     ) -> Result<HandshakeSignatureValid, Error> {
-        if self.expect_raw_public_keys {
+        if let Some(error) = &self.signature_error {
+            Err(error.clone())
+        } else if self.expect_raw_public_keys {
             Ok(HandshakeSignatureValid::assertion())
         } else {
             self.parent
@@ -1370,7 +1480,9 @@ impl ClientCertVerifier for MockClientVerifier {
         cert: &CertificateDer<'_>,
         dss: &DigitallySignedStruct,
     ) -> Result<HandshakeSignatureValid, Error> {
-        if self.expect_raw_public_keys {
+        if let Some(error) = &self.signature_error {
+            Err(error.clone())
+        } else if self.expect_raw_public_keys {
             verify_tls13_signature_with_raw_key(
                 message,
                 &SubjectPublicKeyInfoDer::from(cert.as_ref()),
@@ -5,7 +5,7 @@ use pki_types::{CertificateDer, PrivateKeyDer};
 
 use super::client_conn::Resumption;
 use crate::builder::{ConfigBuilder, WantsVerifier};
-use crate::client::{ClientConfig, EchMode, ResolvesClientCert, handy};
+use crate::client::{ClientConfig, ClientHelloProfile, EchMode, ResolvesClientCert, handy};
 use crate::error::Error;
 use crate::key_log::NoKeyLog;
 use crate::sign::{CertifiedKey, SingleCertAndKey};
@@ -73,6 +73,7 @@ impl ConfigBuilder<ClientConfig, WantsVerifier> {
                 versions: self.state.versions,
                 verifier,
                 client_ech_mode: self.state.client_ech_mode,
+                client_hello_profile: None,
             },
             provider: self.provider,
             time_provider: self.time_provider,
@@ -113,6 +114,7 @@ pub(super) mod danger {
                     versions: self.cfg.state.versions,
                     verifier,
                     client_ech_mode: self.cfg.state.client_ech_mode,
+                    client_hello_profile: None,
                 },
                 provider: self.cfg.provider,
                 time_provider: self.cfg.time_provider,
@@ -131,9 +133,26 @@ pub struct WantsClientCert {
     versions: versions::EnabledVersions,
     verifier: Arc<dyn verify::ServerCertVerifier>,
     client_ech_mode: Option<EchMode>,
+    client_hello_profile: Option<ClientHelloProfile>,
 }
 
 impl ConfigBuilder<ClientConfig, WantsClientCert> {
+    /// Fix the shape of `ClientHello` messages sent by the client.
+    ///
+    /// This fails if `profile` names a cipher suite or key exchange group not supported by
+    /// the crypto provider (or a cipher suite for a disabled protocol version), names a
+    /// signature scheme not supported by the certificate verifier, names any item more than
+    /// once, or has an invalid extension order.  See [`ClientHelloProfile`] for details.
+    pub fn with_client_hello_profile(mut self, profile: ClientHelloProfile) -> Result<Self, Error> {
+        profile.validate(
+            &self.provider,
+            &self.state.versions,
+            self.state.verifier.as_ref(),
+        )?;
+        self.state.client_hello_profile = Some(profile);
+        Ok(self)
+    }
+
     /// Sets a single certificate chain and matching private key for use
     /// in client authentication.
     ///
@@ -179,7 +198,9 @@ impl ConfigBuilder<ClientConfig, WantsClientCert> {
             cert_compressors: compress::default_cert_compressors().to_vec(),
             cert_compression_cache: Arc::new(compress::CompressionCache::default()),
             cert_decompressors: compress::default_cert_decompressors().to_vec(),
+            custom_extensions: Vec::new(),
             ech_mode: self.state.client_ech_mode,
+            client_hello_profile: self.state.client_hello_profile,
         }
     }
 }
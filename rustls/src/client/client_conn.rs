@@ -10,16 +10,17 @@ use super::hs::{self, ClientHelloInput};
 #[cfg(feature = "std")]
 use crate::WantsVerifier;
 use crate::builder::ConfigBuilder;
-use crate::client::{EchMode, EchStatus};
+use crate::client::{ClientConnectionOptions, ClientHelloProfile, EchMode, EchStatus};
 use crate::common_state::{CommonState, Protocol, Side};
 use crate::conn::{ConnectionCore, UnbufferedConnectionCommon};
 use crate::crypto::{CryptoProvider, SupportedKxGroup};
+use crate::custom_extension::{self, CustomExtension};
 use crate::enums::{CipherSuite, ProtocolVersion, SignatureScheme};
 use crate::error::Error;
 use crate::kernel::KernelConnection;
 use crate::log::trace;
 use crate::msgs::enums::NamedGroup;
-use crate::msgs::handshake::ClientExtensionsInput;
+use crate::msgs::handshake::{ClientExtensionsInput, TransportParameters};
 use crate::msgs::persist;
 use crate::suites::{ExtractedSecrets, SupportedCipherSuite};
 use crate::sync::Arc;
@@ -278,8 +279,17 @@ pub struct ClientConfig {
     /// a cache that does no caching.
     pub cert_compression_cache: Arc<compress::CompressionCache>,
 
+    /// Application-defined extensions to send and receive.
+    ///
+    /// See [`CustomExtension`] for which messages carry these.  The default
+    /// is empty.
+    pub custom_extensions: Vec<Arc<dyn CustomExtension>>,
+
     /// How to offer Encrypted Client Hello (ECH). The default is to not offer ECH.
     pub(super) ech_mode: Option<EchMode>,
+
+    /// The shape of `ClientHello` messages.  The default is to not use a profile.
+    pub(super) client_hello_profile: Option<ClientHelloProfile>,
 }
 
 impl ClientConfig {
@@ -371,9 +381,14 @@ impl ClientConfig {
     /// is concerned only with cryptography, whereas this _also_ covers TLS-level
     /// configuration that NIST recommends, as well as ECH HPKE suites if applicable.
     pub fn fips(&self) -> bool {
+        self.fips_with_ech(self.ech_mode.as_ref())
+    }
+
+    /// Like [`ClientConfig::fips()`], but for a connection using `ech_mode`.
+    pub(super) fn fips_with_ech(&self, ech_mode: Option<&EchMode>) -> bool {
         let mut is_fips = self.provider.fips() && self.require_ems;
 
-        if let Some(ech_mode) = &self.ech_mode {
+        if let Some(ech_mode) = ech_mode {
             is_fips = is_fips && ech_mode.fips();
         }
 
@@ -637,9 +652,9 @@ mod connection {
 
     use pki_types::ServerName;
 
-    use super::{ClientConnectionData, ClientExtensionsInput};
+    use super::ClientConnectionData;
     use crate::ClientConfig;
-    use crate::client::EchStatus;
+    use crate::client::{ClientConnectionOptions, EchStatus};
     use crate::common_state::Protocol;
     use crate::conn::{ConnectionCommon, ConnectionCore};
     use crate::error::Error;
@@ -710,7 +725,7 @@ mod connection {
         /// we behave in the TLS protocol, `name` is the
         /// name of the server we want to talk to.
         pub fn new(config: Arc<ClientConfig>, name: ServerName<'static>) -> Result<Self, Error> {
-            Self::new_with_alpn(config.clone(), name, config.alpn_protocols.clone())
+            Self::new_with_options(config, name, ClientConnectionOptions::default())
         }
 
         /// Make a new ClientConnection with custom ALPN protocols.
@@ -718,12 +733,26 @@ mod connection {
             config: Arc<ClientConfig>,
             name: ServerName<'static>,
             alpn_protocols: Vec<Vec<u8>>,
+        ) -> Result<Self, Error> {
+            Self::new_with_options(
+                config,
+                name,
+                ClientConnectionOptions::new().with_alpn_protocols(alpn_protocols),
+            )
+        }
+
+        /// Make a new ClientConnection, overriding parts of `config` with `options`.
+        pub fn new_with_options(
+            config: Arc<ClientConfig>,
+            name: ServerName<'static>,
+            options: ClientConnectionOptions,
         ) -> Result<Self, Error> {
             Ok(Self {
                 inner: ConnectionCommon::from(ConnectionCore::for_client(
                     config,
                     name,
-                    ClientExtensionsInput::from_alpn(alpn_protocols),
+                    options,
+                    None,
                     Protocol::Tcp,
                 )?),
             })
@@ -847,15 +876,58 @@ impl ConnectionCore<ClientConnectionData> {
     pub(crate) fn for_client(
         config: Arc<ClientConfig>,
         name: ServerName<'static>,
-        extra_exts: ClientExtensionsInput<'static>,
+        options: ClientConnectionOptions,
+        transport_parameters: Option<TransportParameters<'static>>,
         proto: Protocol,
     ) -> Result<Self, Error> {
+        let ClientConnectionOptions {
+            alpn_protocols,
+            server_name_indication,
+            verification_name,
+            client_auth_cert_resolver,
+            ech_mode,
+            session_key,
+        } = options;
+        let ech_mode = ech_mode.or_else(|| config.ech_mode.clone());
+        if matches!(ech_mode, Some(EchMode::Enable(_)))
+            && !config.supports_version(ProtocolVersion::TLSv1_3)
+        {
+            return Err(Error::General(
+                "ECH requires TLS1.3, which is not enabled".into(),
+            ));
+        }
+
         let mut common_state = CommonState::new(Side::Client);
         common_state.set_max_fragment_size(config.max_fragment_size)?;
+        custom_extension::validate(&config.custom_extensions)?;
         common_state.protocol = proto;
         common_state.enable_secret_extraction = config.enable_secret_extraction;
-        common_state.fips = config.fips();
-        let mut data = ClientConnectionData::new();
+        common_state.fips = config.fips_with_ech(ech_mode.as_ref());
+        #[cfg(feature = "tracing")]
+        let _span = {
+            common_state
+                .span
+                .record("server_name", tracing::field::debug(&name));
+            common_state.span.clone().entered()
+        };
+
+        let sni = server_name_indication.unwrap_or_else(|| match &name {
+            ServerName::DnsName(dns_name) if config.enable_sni => Some(dns_name.to_owned()),
+            _ => None,
+        });
+        let verification_name = verification_name.unwrap_or(name);
+        let session_key = session_key.unwrap_or_else(|| verification_name.clone());
+        let mut data = ClientConnectionData::new(
+            verification_name,
+            client_auth_cert_resolver.unwrap_or_else(|| config.client_auth_cert_resolver.clone()),
+        );
+
+        let extra_exts = ClientExtensionsInput {
+            transport_parameters,
+            ..ClientExtensionsInput::from_alpn(
+                alpn_protocols.unwrap_or_else(|| config.alpn_protocols.clone()),
+            )
+        };
 
         let mut cx = hs::ClientContext {
             common: &mut common_state,
@@ -864,7 +936,8 @@ impl ConnectionCore<ClientConnectionData> {
             sendable_plaintext: None,
         };
 
-        let input = ClientHelloInput::new(name, &extra_exts, &mut cx, config)?;
+        let input =
+            ClientHelloInput::new(session_key, sni, ech_mode, &extra_exts, &mut cx, config)?;
         let state = input.start_handshake(extra_exts, &mut cx)?;
         Ok(Self::new(state, data, common_state))
     }
@@ -886,11 +959,7 @@ impl UnbufferedClientConnection {
     /// Make a new ClientConnection. `config` controls how we behave in the TLS protocol, `name` is
     /// the name of the server we want to talk to.
     pub fn new(config: Arc<ClientConfig>, name: ServerName<'static>) -> Result<Self, Error> {
-        Self::new_with_extensions(
-            config.clone(),
-            name,
-            ClientExtensionsInput::from_alpn(config.alpn_protocols.clone()),
-        )
+        Self::new_with_options(config, name, ClientConnectionOptions::default())
     }
 
     /// Make a new UnbufferedClientConnection with custom ALPN protocols.
@@ -899,23 +968,25 @@ impl UnbufferedClientConnection {
         name: ServerName<'static>,
         alpn_protocols: Vec<Vec<u8>>,
     ) -> Result<Self, Error> {
-        Self::new_with_extensions(
+        Self::new_with_options(
             config,
             name,
-            ClientExtensionsInput::from_alpn(alpn_protocols),
+            ClientConnectionOptions::new().with_alpn_protocols(alpn_protocols),
         )
     }
 
-    fn new_with_extensions(
+    /// Make a new UnbufferedClientConnection, overriding parts of `config` with `options`.
+    pub fn new_with_options(
         config: Arc<ClientConfig>,
         name: ServerName<'static>,
-        extensions: ClientExtensionsInput<'static>,
+        options: ClientConnectionOptions,
     ) -> Result<Self, Error> {
         Ok(Self {
             inner: UnbufferedConnectionCommon::from(ConnectionCore::for_client(
                 config,
                 name,
-                extensions,
+                options,
+                None,
                 Protocol::Tcp,
             )?),
         })
@@ -1052,13 +1123,22 @@ impl std::error::Error for EarlyDataError {}
 pub struct ClientConnectionData {
     pub(super) early_data: EarlyData,
     pub(super) ech_status: EchStatus,
+    /// The name the server's certificate is verified against.
+    pub(super) verification_name: ServerName<'static>,
+    /// Either `ClientConfig::client_auth_cert_resolver`, or its per-connection override.
+    pub(super) client_auth_cert_resolver: Arc<dyn ResolvesClientCert>,
 }
 
 impl ClientConnectionData {
-    fn new() -> Self {
+    fn new(
+        verification_name: ServerName<'static>,
+        client_auth_cert_resolver: Arc<dyn ResolvesClientCert>,
+    ) -> Self {
         Self {
             early_data: EarlyData::new(),
             ech_status: EchStatus::NotOffered,
+            verification_name,
+            client_auth_cert_resolver,
         }
     }
 }
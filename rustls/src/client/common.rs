@@ -4,7 +4,9 @@ use alloc::vec::Vec;
 use super::ResolvesClientCert;
 use crate::log::{debug, trace};
 use crate::msgs::enums::ExtensionType;
-use crate::msgs::handshake::{CertificateChain, DistinguishedName, ProtocolName, ServerExtensions};
+use crate::msgs::handshake::{
+    CertificateChain, DistinguishedName, ProtocolName, ServerExtensions, UnknownExtension,
+};
 use crate::sync::Arc;
 use crate::{SignatureScheme, compress, sign};
 
@@ -61,7 +63,7 @@ impl ClientHelloDetails {
             received_exts
                 .unknown_extensions
                 .iter()
-                .map(|ext| ExtensionType::from(*ext)),
+                .map(|ext| ext.typ),
         );
         for ext_type in extensions {
             if !self.sent_extensions.contains(&ext_type) && !allowed_unsolicited.contains(&ext_type)
@@ -84,6 +86,8 @@ pub(super) enum ClientAuthDetails {
         signer: Box<dyn sign::Signer>,
         auth_context_tls13: Option<Vec<u8>>,
         compressor: Option<&'static dyn compress::CertCompressor>,
+        /// Custom extensions for the end-entity certificate entry.
+        custom_extensions: Vec<UnknownExtension>,
     },
 }
 
@@ -109,6 +113,7 @@ impl ClientAuthDetails {
                     signer,
                     auth_context_tls13,
                     compressor,
+                    custom_extensions: Vec::new(),
                 };
             }
         }
@@ -2,7 +2,7 @@ use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use pki_types::{DnsName, EchConfigListBytes, ServerName};
+use pki_types::{DnsName, EchConfigListBytes};
 use subtle::ConstantTimeEq;
 
 use crate::CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV;
@@ -19,7 +19,8 @@ use crate::msgs::handshake::{
     ClientExtensions, ClientHelloPayload, EchConfigContents, EchConfigPayload, Encoding,
     EncryptedClientHello, EncryptedClientHelloOuter, HandshakeMessagePayload, HandshakePayload,
     HelloRetryRequest, HpkeKeyConfig, HpkeSymmetricCipherSuite, PresharedKeyBinder,
-    PresharedKeyOffer, Random, ServerHelloPayload, ServerNamePayload,
+    PresharedKeyOffer, Random, ServerHelloPayload, ServerNamePayload, is_grease,
+    trim_hostname_trailing_dot_for_sni,
 };
 use crate::msgs::message::{Message, MessagePayload};
 use crate::msgs::persist;
@@ -28,8 +29,8 @@ use crate::tls13::key_schedule::{
     KeyScheduleEarly, KeyScheduleHandshakeStart, server_ech_hrr_confirmation_secret,
 };
 use crate::{
-    AlertDescription, ClientConfig, CommonState, EncryptedClientHelloError, Error, PeerMisbehaved,
-    ProtocolVersion, RejectedEch, Tls13CipherSuite,
+    AlertDescription, ClientConfig, CommonState, EncryptedClientHelloError, Error, KeyLog,
+    PeerMisbehaved, ProtocolVersion, RejectedEch, Tls13CipherSuite,
 };
 
 /// Controls how Encrypted Client Hello (ECH) is used in a client handshake.
@@ -126,18 +127,35 @@ impl EchConfig {
 
     pub(super) fn state(
         &self,
-        server_name: ServerName<'static>,
+        inner_sni: Option<DnsName<'static>>,
+        client_auth_enabled: bool,
+        outer_random: &Random,
         config: &ClientConfig,
     ) -> Result<EchState, Error> {
-        EchState::new(
+        let state = EchState::new(
             self,
-            server_name.clone(),
-            config
-                .client_auth_cert_resolver
-                .has_certs(),
+            inner_sni,
+            client_auth_enabled,
             config.provider.secure_random,
-            config.enable_sni,
-        )
+        )?;
+        self.log_secrets(&state, outer_random, &*config.key_log);
+        Ok(state)
+    }
+
+    /// Log the HPKE shared secret and the ECH configuration used to seal the inner hello.
+    ///
+    /// Together these allow the inner hello to be decrypted.  As that is not possible
+    /// without first decrypting it, the values are identified by the outer hello's random.
+    fn log_secrets(&self, state: &EchState, outer_random: &Random, key_log: &dyn KeyLog) {
+        if key_log.will_log("ECH_SECRET") {
+            if let Some(secret) = state.sender.shared_secret() {
+                key_log.log("ECH_SECRET", &outer_random.0, secret);
+            }
+        }
+
+        if key_log.will_log("ECH_CONFIG") {
+            key_log.log("ECH_CONFIG", &outer_random.0, &self.config.get_encoding());
+        }
     }
 
     /// Compute the HPKE `SetupBaseS` `info` parameter for this ECH configuration.
@@ -157,7 +175,10 @@ impl EchConfig {
     ) -> Result<Self, Error> {
         // Note: we name the index var _i because if the log feature is disabled
         //       it is unused.
-        #[cfg_attr(not(feature = "log"), allow(clippy::unused_enumerate_index))]
+        #[cfg_attr(
+            not(any(feature = "log", feature = "tracing")),
+            allow(clippy::unused_enumerate_index)
+        )]
         for (_i, config) in ech_configs.iter().enumerate() {
             let contents = match config {
                 EchConfigPayload::V18(contents) => contents,
@@ -239,7 +260,7 @@ impl EchGreaseConfig {
     pub(crate) fn grease_ext(
         &self,
         secure_random: &'static dyn SecureRandom,
-        inner_name: ServerName<'static>,
+        inner_sni: Option<DnsName<'static>>,
         outer_hello: &ClientHelloPayload,
     ) -> Result<EncryptedClientHello, Error> {
         trace!("Preparing GREASE ECH extension");
@@ -267,10 +288,9 @@ impl EchGreaseConfig {
                 }),
                 suite: self.suite,
             },
-            inner_name,
+            inner_sni,
             false,
             secure_random,
-            false, // Does not matter if we enable/disable SNI here. Inner hello is not used.
         )?;
 
         // Construct an inner hello using the outer hello - this allows us to know the size of
@@ -334,8 +354,8 @@ pub(crate) struct EchState {
     sender: Box<dyn HpkeSealer>,
     // The ID of the ECH configuration we've chosen - this is included in the outer ECH extension.
     config_id: u8,
-    // The private server name we'll use for the inner protected hello.
-    inner_name: ServerName<'static>,
+    // The private server name indication we'll send in the inner protected hello, if any.
+    inner_sni: Option<DnsName<'static>>,
     // The advertised maximum name length from the ECH configuration we've chosen - this is used
     // for padding calculations.
     maximum_name_length: u8,
@@ -345,8 +365,6 @@ pub(crate) struct EchState {
     // A secret encapsulated to the public key of the remote server. This is included in the
     // outer ECH extension for non-retry outer hello messages.
     enc: EncapsulatedSecret,
-    // Whether the inner client hello should contain a server name indication (SNI) extension.
-    enable_sni: bool,
     // The extensions sent in the inner hello.
     sent_extensions: Vec<ExtensionType>,
 }
@@ -354,10 +372,9 @@ pub(crate) struct EchState {
 impl EchState {
     pub(crate) fn new(
         config: &EchConfig,
-        inner_name: ServerName<'static>,
+        inner_sni: Option<DnsName<'static>>,
         client_auth_enabled: bool,
         secure_random: &'static dyn SecureRandom,
-        enable_sni: bool,
     ) -> Result<Self, Error> {
         let EchConfigPayload::V18(config_contents) = &config.config else {
             // the public EchConfig::new() constructor ensures we only have supported
@@ -383,7 +400,7 @@ impl EchState {
             secure_random,
             sender,
             config_id: key_config.config_id,
-            inner_name,
+            inner_sni,
             outer_name: config_contents.public_name.clone(),
             maximum_name_length: config_contents.maximum_name_length,
             cipher_suite: config.suite.suite().sym,
@@ -391,14 +408,13 @@ impl EchState {
             inner_hello_random: Random::new(secure_random)?,
             inner_hello_transcript,
             early_data_key_schedule: None,
-            enable_sni,
             sent_extensions: Vec::new(),
         })
     }
 
     /// Construct a ClientHelloPayload offering ECH.
     ///
-    /// An outer hello, with a protected inner hello for the `inner_name` will be returned, and the
+    /// An outer hello, with a protected inner hello for the `inner_sni` will be returned, and the
     /// ECH context will be updated to reflect the inner hello that was offered.
     ///
     /// If `retry_req` is `Some`, then the outer hello will be constructed for a hello retry request.
@@ -418,6 +434,12 @@ impl EchState {
         // Construct the encoded inner hello and update the transcript.
         let encoded_inner_hello = self.encode_inner_hello(&outer_hello, retry_req, resuming);
 
+        // Custom extensions are only sent in the inner hello, but GREASE extensions
+        // (from a `ClientHelloProfile`) are sent in both.
+        outer_hello
+            .unknown_extensions
+            .retain(|ext| is_grease(u16::from(ext.typ)));
+
         // Complete the ClientHelloOuterAAD with an ech extension, the payload should be a placeholder
         // of size L, all zeroes. L == length of encrypting encoded client hello inner w/ the selected
         // HPKE AEAD. (sum of plaintext + tag length, typically).
@@ -476,6 +498,11 @@ impl EchState {
         server_hello_encoded: &Payload<'_>,
         hash: &'static dyn Hash,
     ) -> Result<Option<EchAccepted>, Error> {
+        let server_name = self
+            .inner_sni
+            .as_ref()
+            .map(trim_hostname_trailing_dot_for_sni);
+
         // Start the inner transcript hash now that we know the hash algorithm to use.
         let inner_transcript = self
             .inner_hello_transcript
@@ -506,6 +533,7 @@ impl EchState {
                 Ok(Some(EchAccepted {
                     transcript: inner_transcript,
                     random: self.inner_hello_random,
+                    server_name,
                     sent_extensions: self.sent_extensions,
                 }))
             }
@@ -621,12 +649,7 @@ impl EchState {
         // See Section 6.1 rule 4.
         inner_hello.encrypted_client_hello = Some(EncryptedClientHello::Inner);
 
-        let inner_sni = match &self.inner_name {
-            // The inner hello only gets a SNI value if enable_sni is true and the inner name
-            // is a domain name (not an IP address).
-            ServerName::DnsName(dns_name) if self.enable_sni => Some(dns_name),
-            _ => None,
-        };
+        let inner_sni = self.inner_sni.as_ref();
 
         // Now we consider each of the outer hello's extensions - we can either:
         // 1. Omit the extension if it isn't appropriate (e.g. is a TLS 1.2 extension).
@@ -670,6 +693,9 @@ impl EchState {
         // block of to-be-compressed extensions.
         inner_hello.contiguous_extensions = compressed_exts.clone();
 
+        // Custom extensions are not otherwise handled above.
+        inner_hello.unknown_extensions = outer_hello.unknown_extensions.clone();
+
         // Note which extensions we're sending in the inner hello. This may differ from
         // the outer hello (e.g. the inner hello may omit SNI while the outer hello will
         // always have the ECH cover name in SNI).
@@ -707,8 +733,8 @@ impl EchState {
         let max_name_len = self.maximum_name_length;
         let max_name_len = if max_name_len > 0 { max_name_len } else { 255 };
 
-        let padding_len = match &self.inner_name {
-            ServerName::DnsName(name) => {
+        let padding_len = match &self.inner_sni {
+            Some(name) => {
                 // name.len() = D
                 // max(0, L - D)
                 core::cmp::max(
@@ -716,7 +742,7 @@ impl EchState {
                     max_name_len.saturating_sub(name.as_ref().len() as u8) as usize,
                 )
             }
-            _ => {
+            None => {
                 // L + 9
                 // "This is the length of a "server_name" extension with an L-byte name."
                 // We widen to usize here to avoid overflowing u8 + u8.
@@ -848,6 +874,7 @@ pub(crate) struct EchAccepted {
     pub(crate) transcript: HandshakeHash,
     pub(crate) random: Random,
     pub(crate) sent_extensions: Vec<ExtensionType>,
+    pub(crate) server_name: Option<DnsName<'static>>,
 }
 
 pub(crate) fn fatal_alert_required(
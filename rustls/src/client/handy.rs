@@ -1,6 +1,10 @@
+use alloc::vec::Vec;
+
 use pki_types::ServerName;
 
+use crate::crypto::signer::IssuedKey;
 use crate::enums::SignatureScheme;
+use crate::error::Error;
 use crate::msgs::persist;
 use crate::sync::Arc;
 use crate::{NamedGroup, client, sign};
@@ -236,6 +240,66 @@ impl client::ResolvesClientCert for AlwaysResolvesClientRawPublicKeys {
     }
 }
 
+/// A `ResolvesClientCert` implementation that chooses among several identities.
+///
+/// Identities are kept in the order they are added, which is their priority order.
+/// When the server requests a client certificate, this resolves to the first
+/// identity that:
+///
+/// - has a key usable with one of the signature schemes the server supports, and
+/// - has a certificate in its chain issued by one of the certificate authorities
+///   named by the server, if the server names any.
+///
+/// If no identity satisfies these, no client certificate is sent.  The server
+/// may then reject the handshake, if it requires client authentication.
+#[derive(Debug, Default)]
+pub struct MultiIdentityClientCertResolver {
+    identities: Vec<IssuedKey>,
+}
+
+impl MultiIdentityClientCertResolver {
+    /// Create a new and empty (i.e., knows no identities) resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an identity, with lower priority than those already added.
+    ///
+    /// This function fails if the certificate chain is syntactically faulty.
+    pub fn add(&mut self, ck: sign::CertifiedKey) -> Result<(), Error> {
+        self.identities
+            .push(IssuedKey::new(ck)?);
+        Ok(())
+    }
+}
+
+impl client::ResolvesClientCert for MultiIdentityClientCertResolver {
+    fn resolve(
+        &self,
+        root_hint_subjects: &[&[u8]],
+        sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<sign::CertifiedKey>> {
+        self.identities
+            .iter()
+            .filter(|identity| {
+                root_hint_subjects.is_empty()
+                    || identity.issued_by_any(root_hint_subjects.iter().copied())
+            })
+            .find(|identity| {
+                identity
+                    .key
+                    .key
+                    .choose_scheme(sigschemes)
+                    .is_some()
+            })
+            .map(|identity| identity.key.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        !self.identities.is_empty()
+    }
+}
+
 #[cfg(test)]
 #[macro_rules_attribute::apply(test_for_each_provider)]
 mod tests {
@@ -4,11 +4,11 @@ use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::Deref;
 
-use pki_types::ServerName;
+use pki_types::{DnsName, ServerName};
 
 use super::{ResolvesClientCert, Tls12Resumption};
 use crate::SupportedCipherSuite;
-#[cfg(feature = "log")]
+#[cfg(any(feature = "log", feature = "tracing"))]
 use crate::bs_debug;
 use crate::check::inappropriate_handshake_message;
 use crate::client::client_conn::ClientConnectionData;
@@ -18,6 +18,7 @@ use crate::client::{ClientConfig, EchMode, EchStatus, tls13};
 use crate::common_state::{CommonState, HandshakeKind, KxState, State};
 use crate::conn::ConnectionRandoms;
 use crate::crypto::{ActiveKeyExchange, KeyExchangeAlgorithm};
+use crate::custom_extension::{self, ExtensionContext};
 use crate::enums::{
     AlertDescription, CertificateType, CipherSuite, ContentType, HandshakeType, ProtocolVersion,
 };
@@ -25,6 +26,7 @@ use crate::error::{Error, PeerIncompatible, PeerMisbehaved};
 use crate::hash_hs::HandshakeHashBuffer;
 use crate::log::{debug, trace};
 use crate::msgs::base::Payload;
+use crate::msgs::codec::Codec;
 use crate::msgs::enums::{Compression, ExtensionType};
 use crate::msgs::handshake::{
     CertificateStatusRequest, ClientExtensions, ClientExtensionsInput, ClientHelloPayload,
@@ -70,13 +72,19 @@ pub(crate) struct ClientHelloInput {
     pub(super) sent_tls13_fake_ccs: bool,
     pub(super) hello: ClientHelloDetails,
     pub(super) session_id: SessionId,
+    /// The key under which sessions for this server are stored.
     pub(super) server_name: ServerName<'static>,
+    /// The server name indication to send, if any.
+    pub(super) sni: Option<DnsName<'static>>,
+    pub(super) ech_mode: Option<EchMode>,
     pub(super) prev_ech_ext: Option<EncryptedClientHello>,
 }
 
 impl ClientHelloInput {
     pub(super) fn new(
         server_name: ServerName<'static>,
+        sni: Option<DnsName<'static>>,
+        ech_mode: Option<EchMode>,
         extra_exts: &ClientExtensionsInput<'_>,
         cx: &mut ClientContext<'_>,
         config: Arc<ClientConfig>,
@@ -128,6 +136,8 @@ impl ClientHelloInput {
             hello,
             session_id,
             server_name,
+            sni,
+            ech_mode,
             prev_ech_ext: None,
             config,
         })
@@ -138,12 +148,12 @@ impl ClientHelloInput {
         extra_exts: ClientExtensionsInput<'static>,
         cx: &mut ClientContext<'_>,
     ) -> NextStateOrError<'static> {
-        let mut transcript_buffer = HandshakeHashBuffer::new();
-        if self
-            .config
+        let client_auth_enabled = cx
+            .data
             .client_auth_cert_resolver
-            .has_certs()
-        {
+            .has_certs();
+        let mut transcript_buffer = HandshakeHashBuffer::new();
+        if client_auth_enabled {
             transcript_buffer.set_client_auth_enabled();
         }
 
@@ -157,10 +167,13 @@ impl ClientHelloInput {
             None
         };
 
-        let ech_state = match self.config.ech_mode.as_ref() {
-            Some(EchMode::Enable(ech_config)) => {
-                Some(ech_config.state(self.server_name.clone(), &self.config)?)
-            }
+        let ech_state = match self.ech_mode.as_ref() {
+            Some(EchMode::Enable(ech_config)) => Some(ech_config.state(
+                self.sni.clone(),
+                client_auth_enabled,
+                &self.random,
+                &self.config,
+            )?),
             _ => None,
         };
 
@@ -255,22 +268,20 @@ fn emit_client_hello_for_retry(
         exts.ec_point_formats = Some(SupportedEcPointFormats::default());
     }
 
-    exts.server_name = match (ech_state.as_ref(), config.enable_sni) {
+    exts.server_name = match (ech_state.as_ref(), &input.sni) {
         // If we have ECH state we have a "cover name" to send in the outer hello
         // as the SNI domain name. This happens unconditionally so we ignore the
-        // `enable_sni` value. That will be used later to decide what to do for
+        // `sni` value. That will be used later to decide what to do for
         // the protected inner hello's SNI.
         (Some(ech_state), _) => Some(ServerNamePayload::from(&ech_state.outer_name)),
 
-        // If we have no ECH state, and SNI is enabled, try to use the input server_name
-        // for the SNI domain name.
-        (None, true) => match &input.server_name {
-            ServerName::DnsName(dns_name) => Some(ServerNamePayload::from(dns_name)),
-            _ => None,
-        },
-
-        // If we have no ECH state, and SNI is not enabled, there's nothing to do.
-        (None, false) => None,
+        // If we have no ECH state, send the SNI value, if any.
+        (None, Some(sni)) => Some(ServerNamePayload::from(sni)),
+        (None, None) => None,
+    };
+    cx.common.sni = match &exts.server_name {
+        Some(ServerNamePayload::SingleDnsName(dns_name)) => Some(dns_name.to_owned()),
+        _ => None,
     };
 
     if let Some(key_share) = &key_share {
@@ -328,7 +339,8 @@ fn emit_client_hello_for_retry(
             false
         };
 
-    if config
+    if cx
+        .data
         .client_auth_cert_resolver
         .only_raw_public_keys()
     {
@@ -342,6 +354,13 @@ fn emit_client_hello_for_retry(
         exts.server_certificate_types = Some(vec![CertificateType::RawPublicKey]);
     }
 
+    exts.unknown_extensions = custom_extension::emit(
+        cx.common,
+        &config.custom_extensions,
+        ExtensionContext::ClientHello,
+        None,
+    );
+
     // If this is a second client hello we're constructing in response to an HRR, and
     // we've rejected ECH or sent GREASE ECH, then we need to carry forward the
     // exact same ECH extension we used in the first hello.
@@ -351,6 +370,10 @@ fn emit_client_hello_for_retry(
         }
     }
 
+    if let Some(profile) = &config.client_hello_profile {
+        profile.apply(&mut exts);
+    }
+
     // Do we have a SessionID or ticket cached for this host?
     let tls13_session = prepare_resumption(&input.resuming, &mut exts, suite, cx, config);
 
@@ -373,6 +396,10 @@ fn emit_client_hello_for_retry(
         cipher_suites.push(CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV);
     }
 
+    if let Some(profile) = &config.client_hello_profile {
+        cipher_suites = profile.cipher_suites(cipher_suites);
+    }
+
     let mut chp_payload = ClientHelloPayload {
         client_version: ProtocolVersion::TLSv1_2,
         random: input.random,
@@ -382,13 +409,13 @@ fn emit_client_hello_for_retry(
         extensions: exts,
     };
 
-    let ech_grease_ext = config
+    let ech_grease_ext = input
         .ech_mode
         .as_ref()
         .and_then(|mode| match mode {
             EchMode::Grease(cfg) => Some(cfg.grease_ext(
                 config.provider.secure_random,
-                input.server_name.clone(),
+                input.sni.clone(),
                 &chp_payload,
             )),
             _ => None,
@@ -422,6 +449,21 @@ fn emit_client_hello_for_retry(
         _ => {}
     }
 
+    // The ECH inner hello is padded separately, and the outer hello cannot be changed once
+    // it has been used as associated data for the inner hello.
+    if let (Some(profile), false) = (
+        &config.client_hello_profile,
+        cx.data.ech_status == EchStatus::Offered,
+    ) {
+        // Account for the handshake message header.
+        let encoded_len = chp_payload.get_encoding().len() + 4;
+        if let Some(padding) = profile.padding(encoded_len) {
+            chp_payload
+                .unknown_extensions
+                .push(padding);
+        }
+    }
+
     // Note what extensions we sent.
     input.hello.sent_extensions = chp_payload.collect_used();
 
@@ -631,29 +673,35 @@ pub(super) fn process_server_cert_type_extension(
     config: &ClientConfig,
     server_cert_extension: Option<&CertificateType>,
 ) -> Result<Option<(ExtensionType, CertificateType)>, Error> {
-    process_cert_type_extension(
+    let negotiated = process_cert_type_extension(
         common,
         config
             .verifier
             .requires_raw_public_keys(),
         server_cert_extension.copied(),
         ExtensionType::ServerCertificateType,
-    )
+    )?;
+    if let Some((_, cert_type)) = negotiated {
+        common.server_certificate_type = cert_type;
+    }
+    Ok(negotiated)
 }
 
 pub(super) fn process_client_cert_type_extension(
     common: &mut CommonState,
-    config: &ClientConfig,
+    resolver: &dyn ResolvesClientCert,
     client_cert_extension: Option<&CertificateType>,
 ) -> Result<Option<(ExtensionType, CertificateType)>, Error> {
-    process_cert_type_extension(
+    let negotiated = process_cert_type_extension(
         common,
-        config
-            .client_auth_cert_resolver
-            .only_raw_public_keys(),
+        resolver.only_raw_public_keys(),
         client_cert_extension.copied(),
         ExtensionType::ClientCertificateType,
-    )
+    )?;
+    if let Some((_, cert_type)) = negotiated {
+        common.client_certificate_type = cert_type;
+    }
+    Ok(negotiated)
 }
 
 impl State<ClientConnectionData> for ExpectServerHello {
@@ -683,7 +731,8 @@ impl State<ClientConnectionData> for ExpectServerHello {
 
         let version = match server_version {
             TLSv1_3 if tls13_supported => TLSv1_3,
-            TLSv1_2 if config.supports_version(TLSv1_2) => {
+            // TLS1.2 is not offered when ECH is, so the server cannot select it.
+            TLSv1_2 if config.supports_version(TLSv1_2) && self.ech_state.is_none() => {
                 if cx.data.early_data.is_enabled() && cx.common.early_traffic {
                     // The client must fail with a dedicated error code if the server
                     // responds with TLS 1.2 when offering 0-RTT.
@@ -786,7 +835,7 @@ impl State<ClientConnectionData> for ExpectServerHello {
                 });
             }
             _ => {
-                debug!("Using ciphersuite {suite:?}");
+                debug!(suite = ?suite.suite(), "using ciphersuite");
                 self.suite = Some(suite);
                 cx.common.suite = Some(suite);
             }
@@ -1107,7 +1156,7 @@ impl ClientSessionValue {
                     .map(ClientSessionValue::Tls12)
             })
             .and_then(|resuming| {
-                resuming.compatible_config(&config.verifier, &config.client_auth_cert_resolver)
+                resuming.compatible_config(&config.verifier, &cx.data.client_auth_cert_resolver)
             })
             .and_then(|resuming| {
                 let now = config
@@ -0,0 +1,109 @@
+use alloc::vec::Vec;
+
+use pki_types::{DnsName, ServerName};
+
+use super::client_conn::ResolvesClientCert;
+use super::ech::EchMode;
+use crate::sync::Arc;
+
+/// Options which override parts of a [`ClientConfig`] for a single connection.
+///
+/// By default, a client connection takes everything from its `ClientConfig`, and uses the
+/// server name it is created with to verify the server's certificate, as the server name
+/// indication (SNI), and to store and look up sessions for resumption.  Each of these
+/// can be overridden here, without cloning the `ClientConfig`.
+///
+/// Use these with [`ClientConnection::new_with_options()`],
+/// [`UnbufferedClientConnection::new_with_options()`] or
+/// [`quic::ClientConnection::new_with_options()`].
+///
+/// [`ClientConfig`]: super::ClientConfig
+/// [`ClientConnection::new_with_options()`]: super::ClientConnection::new_with_options
+/// [`UnbufferedClientConnection::new_with_options()`]: super::UnbufferedClientConnection::new_with_options
+/// [`quic::ClientConnection::new_with_options()`]: crate::quic::ClientConnection::new_with_options
+#[derive(Clone, Debug, Default)]
+pub struct ClientConnectionOptions {
+    pub(super) alpn_protocols: Option<Vec<Vec<u8>>>,
+    pub(super) server_name_indication: Option<Option<DnsName<'static>>>,
+    pub(super) verification_name: Option<ServerName<'static>>,
+    pub(super) client_auth_cert_resolver: Option<Arc<dyn ResolvesClientCert>>,
+    pub(super) ech_mode: Option<EchMode>,
+    pub(super) session_key: Option<ServerName<'static>>,
+}
+
+impl ClientConnectionOptions {
+    /// Create options which override nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer `alpn_protocols`, instead of [`ClientConfig::alpn_protocols`].
+    ///
+    /// [`ClientConfig::alpn_protocols`]: super::ClientConfig::alpn_protocols
+    pub fn with_alpn_protocols(mut self, alpn_protocols: Vec<Vec<u8>>) -> Self {
+        self.alpn_protocols = Some(alpn_protocols);
+        self
+    }
+
+    /// Send `name` as the server name indication.
+    ///
+    /// This applies even if [`ClientConfig::enable_sni`] is false, or the connection's
+    /// server name is an IP address.  With Encrypted Client Hello, `name` is sent in the
+    /// inner hello.
+    ///
+    /// [`ClientConfig::enable_sni`]: super::ClientConfig::enable_sni
+    pub fn with_server_name_indication(mut self, name: DnsName<'static>) -> Self {
+        self.server_name_indication = Some(Some(name));
+        self
+    }
+
+    /// Send no server name indication, even if [`ClientConfig::enable_sni`] is true.
+    ///
+    /// With Encrypted Client Hello, the outer hello still carries the public name of
+    /// the ECH configuration.
+    ///
+    /// [`ClientConfig::enable_sni`]: super::ClientConfig::enable_sni
+    pub fn without_server_name_indication(mut self) -> Self {
+        self.server_name_indication = Some(None);
+        self
+    }
+
+    /// Verify the server's certificate against `name`, rather than the connection's
+    /// server name.
+    pub fn with_verification_name(mut self, name: ServerName<'static>) -> Self {
+        self.verification_name = Some(name);
+        self
+    }
+
+    /// Use `resolver` for client authentication, instead of
+    /// [`ClientConfig::client_auth_cert_resolver`].
+    ///
+    /// [`ClientConfig::client_auth_cert_resolver`]: super::ClientConfig::client_auth_cert_resolver
+    pub fn with_client_cert_resolver(mut self, resolver: Arc<dyn ResolvesClientCert>) -> Self {
+        self.client_auth_cert_resolver = Some(resolver);
+        self
+    }
+
+    /// Use `mode` for Encrypted Client Hello, instead of the mode configured with
+    /// [`ConfigBuilder::with_ech()`].
+    ///
+    /// If `mode` enables ECH, only TLS1.3 is offered on the connection, and making the
+    /// connection fails if the `ClientConfig` does not support TLS1.3.
+    ///
+    /// [`ConfigBuilder::with_ech()`]: crate::ConfigBuilder::with_ech
+    pub fn with_ech_mode(mut self, mode: EchMode) -> Self {
+        self.ech_mode = Some(mode);
+        self
+    }
+
+    /// Store and look up sessions for resumption under `key`, rather than the
+    /// connection's server name.
+    ///
+    /// By default, sessions are stored under the name used to verify the server's
+    /// certificate.  Resumed sessions are not verified again, so `key` should only be
+    /// shared by connections which verify the server against the same name.
+    pub fn with_session_key(mut self, key: ServerName<'static>) -> Self {
+        self.session_key = Some(key);
+        self
+    }
+}
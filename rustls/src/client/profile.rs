@@ -0,0 +1,238 @@
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::crypto::CryptoProvider;
+use crate::enums::{CipherSuite, SignatureScheme};
+use crate::error::Error;
+use crate::msgs::base::Payload;
+use crate::msgs::enums::{ExtensionType, NamedGroup};
+use crate::msgs::handshake::{ClientExtensions, UnknownExtension, is_grease};
+use crate::verify::ServerCertVerifier;
+use crate::versions::EnabledVersions;
+
+/// Fixes the shape of the `ClientHello` messages sent by a client.
+///
+/// By default, rustls offers every cipher suite, key exchange group and
+/// signature scheme supported by the configured [`CryptoProvider`] and
+/// verifier, in their configured order, and sends extensions in a random
+/// order.  A profile instead pins each of these lists, so that the
+/// `ClientHello` is reproducible, or resembles that of another implementation.
+///
+/// Each list is optional: `None` keeps the default behaviour for that list.
+/// [GREASE] values may appear anywhere in the lists, and are sent verbatim at
+/// that position.  A GREASE extension type in [`ClientHelloProfile::extension_order`]
+/// is sent with an empty body.
+///
+/// Other values are only sent if they are usable on the connection: for example,
+/// TLS1.2 cipher suites are not offered on a QUIC connection.
+///
+/// Set a profile with [`ConfigBuilder::with_client_hello_profile()`], which
+/// rejects profiles that name items the configuration does not support.
+///
+/// [GREASE]: https://datatracker.ietf.org/doc/html/rfc8701
+/// [`ConfigBuilder::with_client_hello_profile()`]: crate::ConfigBuilder::with_client_hello_profile
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientHelloProfile {
+    /// The cipher suites to offer, in order.
+    ///
+    /// If TLS1.2 is offered and this does not include
+    /// `TLS_EMPTY_RENEGOTIATION_INFO_SCSV`, that is sent last.
+    pub cipher_suites: Option<Vec<CipherSuite>>,
+
+    /// The key exchange groups to offer, in order.
+    ///
+    /// The first of these supported by the provider is used for the initial
+    /// key share, unless a previous connection to the server chose another.
+    pub named_groups: Option<Vec<NamedGroup>>,
+
+    /// The signature schemes to offer, in order.
+    pub signature_schemes: Option<Vec<SignatureScheme>>,
+
+    /// The order of extensions, by extension type.
+    ///
+    /// Extensions named here which are not sent on a given connection are
+    /// skipped.  Extensions sent but not named here follow those named here.
+    /// The `pre_shared_key` extension (41) may only be named last, and the
+    /// `ech_outer_extensions` extension (64768) may not be named.
+    ///
+    /// The `padding` extension (21) is sent at its position here, if
+    /// [`ClientHelloProfile::pad_to`] requires it.
+    pub extension_order: Option<Vec<u16>>,
+
+    /// Pad the `ClientHello` to at least this many bytes, including the handshake
+    /// message header.
+    ///
+    /// This uses the [padding extension].  The extension takes at least four bytes, so
+    /// a `ClientHello` that is one to three bytes short of this is sent unpadded.  Padding
+    /// is not applied to a `ClientHello` that offers Encrypted Client Hello (ECH), which
+    /// is padded separately.
+    ///
+    /// [padding extension]: https://datatracker.ietf.org/doc/html/rfc7685
+    pub pad_to: Option<u16>,
+}
+
+impl ClientHelloProfile {
+    /// Check this profile only names items which are supported by `provider`,
+    /// `versions` and `verifier`, and that `extension_order` is valid.
+    pub(super) fn validate(
+        &self,
+        provider: &CryptoProvider,
+        versions: &EnabledVersions,
+        verifier: &dyn ServerCertVerifier,
+    ) -> Result<(), Error> {
+        if let Some(cipher_suites) = &self.cipher_suites {
+            check_list("cipher suite", cipher_suites, |cs| {
+                *cs == CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV
+                    || provider
+                        .cipher_suites
+                        .iter()
+                        .any(|scs| scs.suite() == *cs && versions.contains(scs.version().version()))
+            })?;
+
+            if !cipher_suites.iter().any(|cs| {
+                !is_grease(u16::from(*cs)) && *cs != CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV
+            }) {
+                return Err(Error::General(
+                    "client hello profile offers no cipher suites".into(),
+                ));
+            }
+        }
+
+        if let Some(named_groups) = &self.named_groups {
+            check_list("named group", named_groups, |group| {
+                provider
+                    .kx_groups
+                    .iter()
+                    .any(|skxg| skxg.name() == *group)
+            })?;
+        }
+
+        if let Some(signature_schemes) = &self.signature_schemes {
+            let supported = verifier.supported_verify_schemes();
+            check_list("signature scheme", signature_schemes, |scheme| {
+                supported.contains(scheme)
+            })?;
+        }
+
+        if let Some(extension_order) = &self.extension_order {
+            check_list("extension type", extension_order, |_| true)?;
+
+            if let Some(position) = extension_order
+                .iter()
+                .position(|typ| ExtensionType::from(*typ) == ExtensionType::PreSharedKey)
+            {
+                if position != extension_order.len() - 1 {
+                    return Err(Error::General(
+                        "client hello profile must order pre_shared_key extension last".into(),
+                    ));
+                }
+            }
+
+            if extension_order.contains(&u16::from(
+                ExtensionType::EncryptedClientHelloOuterExtensions,
+            )) {
+                return Err(Error::General(
+                    "client hello profile cannot order ech_outer_extensions extension".into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the offered `cipher_suites`, if this profile specifies them.
+    pub(super) fn cipher_suites(&self, offered: Vec<CipherSuite>) -> Vec<CipherSuite> {
+        let Some(profile) = &self.cipher_suites else {
+            return offered;
+        };
+
+        let mut cipher_suites = pick(profile, &offered);
+        if offered.contains(&CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV)
+            && !cipher_suites.contains(&CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV)
+        {
+            cipher_suites.push(CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV);
+        }
+        cipher_suites
+    }
+
+    /// Apply the lists and extension order of this profile to `exts`.
+    pub(super) fn apply(&self, exts: &mut ClientExtensions<'_>) {
+        if let (Some(profile), Some(offered)) = (&self.named_groups, &mut exts.named_groups) {
+            *offered = pick(profile, offered);
+        }
+
+        if let (Some(profile), Some(offered)) =
+            (&self.signature_schemes, &mut exts.signature_schemes)
+        {
+            *offered = pick(profile, offered);
+        }
+
+        if let Some(extension_order) = &self.extension_order {
+            exts.unknown_extensions.extend(
+                extension_order
+                    .iter()
+                    .filter(|typ| is_grease(**typ))
+                    .map(|typ| UnknownExtension {
+                        typ: ExtensionType::from(*typ),
+                        payload: Payload::new(Vec::new()),
+                    }),
+            );
+            exts.fixed_order = Some(
+                extension_order
+                    .iter()
+                    .map(|typ| ExtensionType::from(*typ))
+                    .collect(),
+            );
+        }
+    }
+
+    /// The padding extension body needed to bring a `ClientHello` of
+    /// `encoded_len` bytes up to `pad_to`, if any.
+    pub(super) fn padding(&self, encoded_len: usize) -> Option<UnknownExtension> {
+        let short_by = usize::from(self.pad_to?).checked_sub(encoded_len)?;
+
+        // The extension type and length take four bytes, so a `ClientHello` less than
+        // that short of `pad_to` would overshoot it: leave it as it is.
+        Some(UnknownExtension {
+            typ: ExtensionType::Padding,
+            payload: Payload::new(alloc::vec![0; short_by.checked_sub(4)?]),
+        })
+    }
+}
+
+/// Select items of `profile` which are GREASE or `offered`, in `profile` order.
+fn pick<T: Copy + PartialEq + Into<u16>>(profile: &[T], offered: &[T]) -> Vec<T> {
+    profile
+        .iter()
+        .filter(|item| is_grease((**item).into()) || offered.contains(item))
+        .copied()
+        .collect()
+}
+
+/// Check `list` has no duplicates, and each item is GREASE or `supported`.
+fn check_list<T: Copy + Into<u16>>(
+    what: &str,
+    list: &[T],
+    supported: impl Fn(&T) -> bool,
+) -> Result<(), Error> {
+    for (i, item) in list.iter().enumerate() {
+        let value = (*item).into();
+        if list[..i]
+            .iter()
+            .any(|prev| (*prev).into() == value)
+        {
+            return Err(Error::General(format!(
+                "client hello profile names {what} {value:#06x} more than once"
+            )));
+        }
+
+        if !is_grease(value) && !supported(item) {
+            return Err(Error::General(format!(
+                "client hello profile names unsupported {what} {value:#06x}"
+            )));
+        }
+    }
+
+    Ok(())
+}
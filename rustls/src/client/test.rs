@@ -1,4 +1,8 @@
-#![cfg(any(feature = "ring", feature = "aws-lc-rs"))]
+#![cfg(any(
+    feature = "ring",
+    feature = "aws-lc-rs",
+    feature = "rustcrypto-rsa-signing"
+))]
 use core::sync::atomic::{AtomicBool, Ordering};
 use std::prelude::v1::*;
 use std::vec;
@@ -177,6 +181,65 @@ mod tests {
         );
     }
 
+    #[cfg(any(feature = "aws-lc-rs", feature = "ring-hpke", feature = "rustcrypto"))]
+    #[test]
+    fn test_ech_override_requires_tls13() {
+        let config =
+            ClientConfig::builder_with_provider(super::provider::default_provider().into())
+                .with_protocol_versions(&[&version::TLS12])
+                .unwrap()
+                .with_root_certificates(roots())
+                .with_no_client_auth();
+
+        let result = ClientConnection::new_with_options(
+            config.into(),
+            ServerName::try_from("localhost").unwrap(),
+            ech_options(),
+        );
+        assert!(matches!(result, Err(Error::General(_))));
+    }
+
+    #[cfg(any(feature = "aws-lc-rs", feature = "ring-hpke", feature = "rustcrypto"))]
+    #[test]
+    fn test_ech_client_rejects_tls12_server_hello() {
+        let config = Arc::new(
+            ClientConfig::builder_with_provider(super::provider::default_provider().into())
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots())
+                .with_no_client_auth(),
+        );
+        let mut conn = ClientConnection::new_with_options(
+            config.clone(),
+            ServerName::try_from("localhost").unwrap(),
+            ech_options(),
+        )
+        .unwrap();
+        let mut sent = Vec::new();
+        conn.write_tls(&mut sent).unwrap();
+
+        let sh = Message {
+            version: ProtocolVersion::TLSv1_2,
+            payload: MessagePayload::handshake(HandshakeMessagePayload(
+                HandshakePayload::ServerHello(ServerHelloPayload {
+                    random: Random::new(config.provider.secure_random).unwrap(),
+                    compression_method: Compression::Null,
+                    cipher_suite: CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+                    legacy_version: ProtocolVersion::TLSv1_2,
+                    session_id: SessionId::empty(),
+                    extensions: Box::new(ServerExtensions::default()),
+                }),
+            )),
+        };
+        conn.read_tls(&mut sh.into_wire_bytes().as_slice())
+            .unwrap();
+
+        assert_eq!(
+            conn.process_new_packets(),
+            Err(PeerIncompatible::ServerTlsVersionIsDisabledByOurConfig.into())
+        );
+    }
+
     #[test]
     fn cas_extension_in_client_hello_if_server_verifier_requests_it() {
         let cas_sending_server_verifier =
@@ -687,6 +750,66 @@ fn hybrid_kx_component_share_not_offered_unless_supported_separately() {
     assert_eq!(key_shares[0].group, NamedGroup::X25519MLKEM768);
 }
 
+#[cfg(feature = "ring-mlkem")]
+#[test]
+fn ring_hybrid_kx_component_share_offered_if_supported_separately() {
+    use crate::crypto::ring;
+    let provider = CryptoProvider {
+        kx_groups: vec![ring::kx_group::X25519MLKEM768, ring::kx_group::X25519],
+        ..ring::default_provider()
+    };
+    let ch = client_hello_sent_for_config(
+        ClientConfig::builder_with_provider(provider.into())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots())
+            .with_no_client_auth(),
+    )
+    .unwrap();
+
+    let key_shares = ch
+        .extensions
+        .key_shares
+        .as_ref()
+        .unwrap();
+    assert_eq!(key_shares.len(), 2);
+    assert_eq!(key_shares[0].group, NamedGroup::X25519MLKEM768);
+    assert_eq!(key_shares[1].group, NamedGroup::X25519);
+    // The X25519 share is the trailing component of the hybrid share.
+    assert!(
+        key_shares[0]
+            .payload
+            .0
+            .ends_with(&key_shares[1].payload.0)
+    );
+}
+
+#[cfg(feature = "ring-mlkem")]
+#[test]
+fn ring_hybrid_kx_component_share_not_offered_unless_supported_separately() {
+    use crate::crypto::ring;
+    let provider = CryptoProvider {
+        kx_groups: vec![ring::kx_group::X25519MLKEM768],
+        ..ring::default_provider()
+    };
+    let ch = client_hello_sent_for_config(
+        ClientConfig::builder_with_provider(provider.into())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots())
+            .with_no_client_auth(),
+    )
+    .unwrap();
+
+    let key_shares = ch
+        .extensions
+        .key_shares
+        .as_ref()
+        .unwrap();
+    assert_eq!(key_shares.len(), 1);
+    assert_eq!(key_shares[0].group, NamedGroup::X25519MLKEM768);
+}
+
 fn client_hello_sent_for_config(config: ClientConfig) -> Result<ClientHelloPayload, Error> {
     let mut conn =
         ClientConnection::new(config.into(), ServerName::try_from("localhost").unwrap())?;
@@ -718,3 +841,35 @@ fn roots() -> RootCertStore {
     .unwrap();
     r
 }
+
+/// Connection options enabling ECH with the HPKE suites of whichever built-in provider
+/// offers them.
+///
+/// *ring* only has HPKE with the `ring-hpke` feature.
+#[cfg(any(feature = "aws-lc-rs", feature = "ring-hpke", feature = "rustcrypto"))]
+fn ech_options() -> crate::client::ClientConnectionOptions {
+    use pki_types::EchConfigListBytes;
+
+    use crate::client::{ClientConnectionOptions, EchConfig, EchMode};
+    #[cfg(feature = "aws-lc-rs")]
+    use crate::crypto::aws_lc_rs::hpke;
+    #[cfg(all(feature = "ring-hpke", not(feature = "aws-lc-rs")))]
+    use crate::crypto::ring::hpke;
+    #[cfg(all(
+        feature = "rustcrypto",
+        not(any(feature = "aws-lc-rs", feature = "ring-hpke"))
+    ))]
+    use crate::crypto::rustcrypto::hpke;
+
+    ClientConnectionOptions::new().with_ech_mode(EchMode::Enable(
+        EchConfig::new(
+            EchConfigListBytes::from(
+                &include_bytes!(
+                    "../../tests/data/DHKEM_P256_HKDF_SHA256-HKDF_SHA256-AES_128_GCM-echconfigs.bin"
+                )[..],
+            ),
+            hpke::ALL_SUPPORTED_SUITES,
+        )
+        .unwrap(),
+    ))
+}
@@ -17,7 +17,9 @@ use crate::common_state::{CommonState, HandshakeKind, KxState, Side, State};
 use crate::conn::ConnectionRandoms;
 use crate::conn::kernel::{Direction, KernelContext, KernelState};
 use crate::crypto::KeyExchangeAlgorithm;
-use crate::enums::{AlertDescription, ContentType, HandshakeType, ProtocolVersion};
+use crate::enums::{
+    AlertDescription, ContentType, HandshakeType, ProtocolVersion, SignatureScheme,
+};
 use crate::error::{Error, InvalidMessage, PeerIncompatible, PeerMisbehaved};
 use crate::hash_hs::HandshakeHash;
 use crate::log::{debug, trace, warn};
@@ -119,6 +121,7 @@ mod server_hello {
                     )
                 });
             }
+            cx.common.extended_master_secret = using_ems;
 
             // Might the server send a ticket?
             let must_issue_new_ticket = if server_hello
@@ -497,7 +500,10 @@ impl State<ClientConnectionData> for ExpectServerKx<'_> {
         kx.params.encode(&mut kx_params);
         let server_kx = ServerKxDetails::new(kx_params, kx.dss);
 
-        #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+        #[cfg_attr(
+            not(any(feature = "log", feature = "tracing")),
+            allow(unused_variables)
+        )]
         {
             match &kx.params {
                 ServerKeyExchangeParams::Ecdh(ecdhe) => {
@@ -755,7 +761,7 @@ struct ExpectCertificateRequest<'a> {
 impl State<ClientConnectionData> for ExpectCertificateRequest<'_> {
     fn handle<'m>(
         mut self: Box<Self>,
-        _cx: &mut ClientContext<'_>,
+        cx: &mut ClientContext<'_>,
         m: Message<'m>,
     ) -> hs::NextStateOrError<'m>
     where
@@ -775,14 +781,21 @@ impl State<ClientConnectionData> for ExpectCertificateRequest<'_> {
         // We ignore certreq.certtypes as a result, since the information it contains
         // is entirely duplicated in certreq.sigschemes.
 
+        let sigschemes = certreq
+            .sigschemes
+            .iter()
+            .copied()
+            .filter(SignatureScheme::supported_in_tls12)
+            .collect::<Vec<_>>();
+
         const NO_CONTEXT: Option<Vec<u8>> = None; // TLS 1.2 doesn't use a context.
         let no_compression = None; // or compression
         let client_auth = ClientAuthDetails::resolve(
-            self.config
+            cx.data
                 .client_auth_cert_resolver
                 .as_ref(),
             Some(&certreq.canames),
-            &certreq.sigschemes,
+            &sigschemes,
             NO_CONTEXT,
             no_compression,
         );
@@ -864,7 +877,7 @@ impl State<ClientConnectionData> for ExpectServerDone<'_> {
         cx.common.check_aligned_handshake()?;
 
         trace!("Server cert is {:?}", st.server_cert.cert_chain);
-        debug!("Server DNS name is {:?}", st.server_name);
+        debug!("Server DNS name is {:?}", cx.data.verification_name);
 
         let suite = st.suite;
 
@@ -895,7 +908,7 @@ impl State<ClientConnectionData> for ExpectServerDone<'_> {
             .verify_server_cert(
                 end_entity,
                 intermediates,
-                &st.server_name,
+                &cx.data.verification_name,
                 &st.server_cert.ocsp_response,
                 now,
             )
@@ -903,6 +916,8 @@ impl State<ClientConnectionData> for ExpectServerDone<'_> {
                 cx.common
                     .send_cert_verify_error_alert(err)
             })?;
+        cx.common.peer_verified_path = cert_verified.verified_path().cloned();
+        cx.common.peer_spiffe_id = cert_verified.spiffe_id().cloned();
 
         // 2.
         // Build up the contents of the signed message.
@@ -926,13 +941,16 @@ impl State<ClientConnectionData> for ExpectServerDone<'_> {
                 return Err(PeerMisbehaved::SignedKxWithWrongAlgorithm.into());
             }
 
-            st.config
+            let sig_verified = st
+                .config
                 .verifier
                 .verify_tls12_signature(&message, end_entity, sig)
                 .map_err(|err| {
                     cx.common
                         .send_cert_verify_error_alert(err)
-                })?
+                })?;
+            cx.common.peer_signature_scheme = Some(sig.scheme);
+            sig_verified
         };
         cx.common.peer_certificates = Some(st.server_cert.cert_chain.into_owned());
 
@@ -941,6 +959,7 @@ impl State<ClientConnectionData> for ExpectServerDone<'_> {
             let certs = match client_auth {
                 ClientAuthDetails::Empty { .. } => CertificateChain::default(),
                 ClientAuthDetails::Verify { certkey, .. } => {
+                    cx.common.local_certified_key = Some(certkey.clone());
                     CertificateChain(certkey.cert_chain.clone())
                 }
             };
@@ -1234,7 +1253,7 @@ impl ExpectFinished {
                 .clone()
                 .unwrap_or_default(),
             &self.config.verifier,
-            &self.config.client_auth_cert_resolver,
+            &cx.data.client_auth_cert_resolver,
             now,
             lifetime,
             self.using_ems,
@@ -11,7 +11,7 @@ use super::hs::{ClientContext, ClientHelloInput, ClientSessionValue};
 use crate::check::inappropriate_handshake_message;
 use crate::client::common::{ClientAuthDetails, ClientHelloDetails, ServerCertDetails};
 use crate::client::ech::{self, EchState, EchStatus};
-use crate::client::{ClientConfig, ClientSessionStore, hs};
+use crate::client::{ClientConfig, ClientSessionStore, ResolvesClientCert, hs};
 use crate::common_state::{
     CommonState, HandshakeFlightTls13, HandshakeKind, KxState, Protocol, Side, State,
 };
@@ -19,6 +19,7 @@ use crate::conn::ConnectionRandoms;
 use crate::conn::kernel::{Direction, KernelContext, KernelState};
 use crate::crypto::hash::Hash;
 use crate::crypto::{ActiveKeyExchange, SharedSecret};
+use crate::custom_extension::{self, ExtensionContext};
 use crate::enums::{
     AlertDescription, ContentType, HandshakeType, ProtocolVersion, SignatureScheme,
 };
@@ -33,7 +34,7 @@ use crate::msgs::handshake::{
     CERTIFICATE_MAX_SIZE_LIMIT, CertificatePayloadTls13, ClientExtensions, EchConfigPayload,
     HandshakeMessagePayload, HandshakePayload, KeyShareEntry, NewSessionTicketPayloadTls13,
     PresharedKeyBinder, PresharedKeyIdentity, PresharedKeyOffer, ServerExtensions,
-    ServerHelloPayload,
+    ServerHelloPayload, UnknownExtension,
 };
 use crate::msgs::message::{Message, MessagePayload};
 use crate::msgs::persist::{self, Retrieved};
@@ -216,6 +217,7 @@ impl Tls13Handler for Handler {
                         .add_message(server_hello_msg);
                     transcript = accepted.transcript;
                     randoms.client = accepted.random.0;
+                    cx.common.sni = accepted.server_name;
                     hello.sent_extensions = accepted.sent_extensions;
                     EchStatus::Accepted
                 }
@@ -345,6 +347,15 @@ pub(super) fn initial_key_share(
         .store
         .kx_hint(server_name)
         .and_then(|group_name| config.find_kx_group(group_name, ProtocolVersion::TLSv1_3))
+        .or_else(|| {
+            config
+                .client_hello_profile
+                .as_ref()?
+                .named_groups
+                .as_ref()?
+                .iter()
+                .find_map(|group| config.find_kx_group(*group, ProtocolVersion::TLSv1_3))
+        })
         .unwrap_or_else(|| {
             config
                 .provider
@@ -483,7 +494,11 @@ fn validate_encrypted_extensions(
     hello: &ClientHelloDetails,
     exts: &ServerExtensions<'_>,
 ) -> Result<(), Error> {
-    if hello.server_sent_unsolicited_extensions(exts, &[]) {
+    // Custom extensions are checked in more detail later.
+    let custom_extensions = common
+        .custom_extensions
+        .sent(ExtensionContext::ClientHello);
+    if hello.server_sent_unsolicited_extensions(exts, &custom_extensions) {
         return Err(common.send_fatal_alert(
             AlertDescription::UnsupportedExtension,
             PeerMisbehaved::UnsolicitedEncryptedExtension,
@@ -529,6 +544,13 @@ impl State<ClientConnectionData> for ExpectEncryptedExtensions {
         self.transcript.add_message(&m);
 
         validate_encrypted_extensions(cx.common, &self.hello, exts)?;
+        custom_extension::receive(
+            cx.common,
+            &self.config.custom_extensions,
+            ExtensionContext::EncryptedExtensions,
+            &exts.unknown_extensions,
+            Some(ExtensionContext::ClientHello),
+        )?;
         hs::process_alpn_protocol(
             cx.common,
             &self.hello.alpn_protocols,
@@ -538,7 +560,9 @@ impl State<ClientConnectionData> for ExpectEncryptedExtensions {
         )?;
         hs::process_client_cert_type_extension(
             cx.common,
-            &self.config,
+            cx.data
+                .client_auth_cert_resolver
+                .as_ref(),
             exts.client_certificate_type.as_ref(),
         )?;
         hs::process_server_cert_type_extension(
@@ -582,7 +606,10 @@ impl State<ClientConnectionData> for ExpectEncryptedExtensions {
                 let was_early_traffic = cx.common.early_traffic;
                 if was_early_traffic {
                     match exts.early_data_ack {
-                        Some(()) => cx.data.early_data.accepted(),
+                        Some(()) => {
+                            cx.data.early_data.accepted();
+                            cx.common.early_data_accepted = true;
+                        }
                         None => {
                             cx.data.early_data.rejected();
                             cx.common.early_traffic = false;
@@ -941,8 +968,16 @@ impl State<ClientConnectionData> for ExpectCertificateRequest {
             })
             .cloned();
 
-        let client_auth = ClientAuthDetails::resolve(
-            self.config
+        custom_extension::receive(
+            cx.common,
+            &self.config.custom_extensions,
+            ExtensionContext::CertificateRequest,
+            &certreq.extensions.unknown_extensions,
+            None,
+        )?;
+
+        let mut client_auth = ClientAuthDetails::resolve(
+            cx.data
                 .client_auth_cert_resolver
                 .as_ref(),
             certreq
@@ -954,6 +989,18 @@ impl State<ClientConnectionData> for ExpectCertificateRequest {
             compat_compressor,
         );
 
+        if let ClientAuthDetails::Verify {
+            custom_extensions, ..
+        } = &mut client_auth
+        {
+            *custom_extensions = custom_extension::emit(
+                cx.common,
+                &self.config.custom_extensions,
+                ExtensionContext::Certificate,
+                Some(&certreq.extensions.unknown_extensions),
+            );
+        }
+
         Ok(if self.offered_cert_compression {
             Box::new(ExpectCertificateOrCompressedCertificate {
                 config: self.config,
@@ -1126,6 +1173,13 @@ impl State<ClientConnectionData> for ExpectCertificate {
             ));
         }
 
+        custom_extension::receive_certificate(
+            cx.common,
+            &self.config.custom_extensions,
+            &cert_chain,
+            ExtensionContext::ClientHello,
+        )?;
+
         let end_entity_ocsp = cert_chain.end_entity_ocsp().to_vec();
         let server_cert = ServerCertDetails::new(
             cert_chain
@@ -1197,7 +1251,7 @@ impl State<ClientConnectionData> for ExpectCertificateVerify<'_> {
             .verify_server_cert(
                 end_entity,
                 intermediates,
-                &self.server_name,
+                &cx.data.verification_name,
                 &self.server_cert.ocsp_response,
                 now,
             )
@@ -1205,6 +1259,8 @@ impl State<ClientConnectionData> for ExpectCertificateVerify<'_> {
                 cx.common
                     .send_cert_verify_error_alert(err)
             })?;
+        cx.common.peer_verified_path = cert_verified.verified_path().cloned();
+        cx.common.peer_spiffe_id = cert_verified.spiffe_id().cloned();
 
         // 2. Verify their signature on the handshake.
         let handshake_hash = self.transcript.current_hash();
@@ -1222,6 +1278,7 @@ impl State<ClientConnectionData> for ExpectCertificateVerify<'_> {
             })?;
 
         cx.common.peer_certificates = Some(self.server_cert.cert_chain.into_owned());
+        cx.common.peer_signature_scheme = Some(cert_verify.scheme);
         self.transcript.add_message(&m);
 
         Ok(Box::new(ExpectFinished {
@@ -1257,17 +1314,19 @@ fn emit_compressed_certificate_tls13(
     flight: &mut HandshakeFlightTls13<'_>,
     certkey: &CertifiedKey,
     auth_context: Option<Vec<u8>>,
+    custom_extensions: Vec<UnknownExtension>,
     compressor: &dyn compress::CertCompressor,
     config: &ClientConfig,
 ) {
     let mut cert_payload = CertificatePayloadTls13::new(certkey.cert_chain.iter(), None);
     cert_payload.context = PayloadU8::new(auth_context.clone().unwrap_or_default());
+    cert_payload.add_end_entity_extensions(custom_extensions.clone());
 
     let Ok(compressed) = config
         .cert_compression_cache
         .compression_for(compressor, &cert_payload)
     else {
-        return emit_certificate_tls13(flight, Some(certkey), auth_context);
+        return emit_certificate_tls13(flight, Some(certkey), auth_context, custom_extensions);
     };
 
     flight.add(HandshakeMessagePayload(
@@ -1279,12 +1338,14 @@ fn emit_certificate_tls13(
     flight: &mut HandshakeFlightTls13<'_>,
     certkey: Option<&CertifiedKey>,
     auth_context: Option<Vec<u8>>,
+    custom_extensions: Vec<UnknownExtension>,
 ) {
     let certs = certkey
         .map(|ck| ck.cert_chain.as_ref())
         .unwrap_or(&[][..]);
     let mut cert_payload = CertificatePayloadTls13::new(certs.iter(), None);
     cert_payload.context = PayloadU8::new(auth_context.unwrap_or_default());
+    cert_payload.add_end_entity_extensions(custom_extensions);
 
     flight.add(HandshakeMessagePayload(HandshakePayload::CertificateTls13(
         cert_payload,
@@ -1394,7 +1455,7 @@ impl State<ClientConnectionData> for ExpectFinished {
                 ClientAuthDetails::Empty {
                     auth_context_tls13: auth_context,
                 } => {
-                    emit_certificate_tls13(&mut flight, None, auth_context);
+                    emit_certificate_tls13(&mut flight, None, auth_context, Vec::new());
                 }
                 ClientAuthDetails::Verify {
                     auth_context_tls13: auth_context,
@@ -1402,26 +1463,34 @@ impl State<ClientConnectionData> for ExpectFinished {
                 } if cx.data.ech_status == EchStatus::Rejected => {
                     // If ECH was offered, and rejected, we MUST respond with
                     // an empty certificate message.
-                    emit_certificate_tls13(&mut flight, None, auth_context);
+                    emit_certificate_tls13(&mut flight, None, auth_context, Vec::new());
                 }
                 ClientAuthDetails::Verify {
                     certkey,
                     signer,
                     auth_context_tls13: auth_context,
                     compressor,
+                    custom_extensions,
                 } => {
                     if let Some(compressor) = compressor {
                         emit_compressed_certificate_tls13(
                             &mut flight,
                             &certkey,
                             auth_context,
+                            custom_extensions,
                             compressor,
                             &st.config,
                         );
                     } else {
-                        emit_certificate_tls13(&mut flight, Some(&certkey), auth_context);
+                        emit_certificate_tls13(
+                            &mut flight,
+                            Some(&certkey),
+                            auth_context,
+                            custom_extensions,
+                        );
                     }
                     emit_certverify_tls13(&mut flight, signer.as_ref())?;
+                    cx.common.local_certified_key = Some(certkey);
                 }
             }
         }
@@ -1463,6 +1532,10 @@ impl State<ClientConnectionData> for ExpectFinished {
             config: st.config.clone(),
             session_storage: st.config.resumption.store.clone(),
             server_name: st.server_name,
+            client_auth_cert_resolver: cx
+                .data
+                .client_auth_cert_resolver
+                .clone(),
             suite: st.suite,
             key_schedule,
             resumption,
@@ -1489,6 +1562,7 @@ struct ExpectTraffic {
     config: Arc<ClientConfig>,
     session_storage: Arc<dyn ClientSessionStore>,
     server_name: ServerName<'static>,
+    client_auth_cert_resolver: Arc<dyn ResolvesClientCert>,
     suite: &'static Tls13CipherSuite,
     key_schedule: KeyScheduleTraffic,
     resumption: KeyScheduleResumption,
@@ -1518,7 +1592,7 @@ impl ExpectTraffic {
                 .cloned()
                 .unwrap_or_default(),
             &self.config.verifier,
-            &self.config.client_auth_cert_resolver,
+            &self.client_auth_cert_resolver,
             now,
             nst.lifetime,
             nst.age_add,
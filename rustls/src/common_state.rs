@@ -1,18 +1,23 @@
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
-use pki_types::CertificateDer;
+use pki_types::{CertificateDer, DnsName};
 
+#[cfg(any(feature = "log", feature = "tracing"))]
+use crate::bs_debug;
 use crate::conn::kernel::KernelState;
 use crate::crypto::SupportedKxGroup;
-use crate::enums::{AlertDescription, ContentType, HandshakeType, ProtocolVersion};
+use crate::custom_extension::{CustomExtensionState, ExtensionContext};
+use crate::enums::{
+    AlertDescription, CertificateType, ContentType, HandshakeType, ProtocolVersion, SignatureScheme,
+};
 use crate::error::{Error, InvalidMessage, PeerMisbehaved};
 use crate::hash_hs::HandshakeHash;
 use crate::log::{debug, error, warn};
 use crate::msgs::alert::AlertMessagePayload;
 use crate::msgs::base::Payload;
 use crate::msgs::codec::Codec;
-use crate::msgs::enums::{AlertLevel, KeyUpdateRequest};
+use crate::msgs::enums::{AlertLevel, KeyUpdateRequest, NamedGroup};
 use crate::msgs::fragmenter::MessageFragmenter;
 use crate::msgs::handshake::{CertificateChain, HandshakeMessagePayload, ProtocolName};
 use crate::msgs::message::{
@@ -20,10 +25,14 @@ use crate::msgs::message::{
     PlainMessage,
 };
 use crate::record_layer::PreEncryptAction;
+use crate::sign::CertifiedKey;
 use crate::suites::{PartiallyExtractedSecrets, SupportedCipherSuite};
+use crate::sync::Arc;
 use crate::tls12::ConnectionSecrets;
 use crate::unbuffered::{EncryptError, InsufficientSizeError};
 use crate::vecbuf::ChunkVecBuffer;
+use crate::verify::VerifiedPath;
+use crate::webpki::SpiffeId;
 use crate::{quic, record_layer};
 
 /// Connection state common to both client and server connections.
@@ -47,6 +56,16 @@ pub struct CommonState {
     #[cfg(feature = "std")]
     pub(crate) has_seen_eof: bool,
     pub(crate) peer_certificates: Option<CertificateChain<'static>>,
+    pub(crate) peer_verified_path: Option<VerifiedPath>,
+    pub(crate) peer_spiffe_id: Option<SpiffeId>,
+    pub(crate) peer_signature_scheme: Option<SignatureScheme>,
+    pub(crate) local_certified_key: Option<Arc<CertifiedKey>>,
+    pub(crate) extended_master_secret: bool,
+    pub(crate) client_certificate_type: CertificateType,
+    pub(crate) server_certificate_type: CertificateType,
+    pub(crate) early_data_accepted: bool,
+    pub(crate) sni: Option<DnsName<'static>>,
+    pub(crate) custom_extensions: CustomExtensionState,
     message_fragmenter: MessageFragmenter,
     pub(crate) received_plaintext: ChunkVecBuffer,
     pub(crate) sendable_tls: ChunkVecBuffer,
@@ -60,6 +79,9 @@ pub struct CommonState {
     pub(crate) refresh_traffic_keys_pending: bool,
     pub(crate) fips: bool,
     pub(crate) tls13_tickets_received: u32,
+    /// Span covering this connection, entered while processing handshake messages.
+    #[cfg(feature = "tracing")]
+    pub(crate) span: tracing::Span,
 }
 
 impl CommonState {
@@ -82,6 +104,16 @@ impl CommonState {
             #[cfg(feature = "std")]
             has_seen_eof: false,
             peer_certificates: None,
+            peer_verified_path: None,
+            peer_spiffe_id: None,
+            peer_signature_scheme: None,
+            local_certified_key: None,
+            extended_master_secret: false,
+            client_certificate_type: CertificateType::X509,
+            server_certificate_type: CertificateType::X509,
+            early_data_accepted: false,
+            sni: None,
+            custom_extensions: CustomExtensionState::default(),
             message_fragmenter: MessageFragmenter::default(),
             received_plaintext: ChunkVecBuffer::new(Some(DEFAULT_RECEIVED_PLAINTEXT_LIMIT)),
             sendable_tls: ChunkVecBuffer::new(Some(DEFAULT_BUFFER_LIMIT)),
@@ -93,6 +125,12 @@ impl CommonState {
             refresh_traffic_keys_pending: false,
             fips: false,
             tls13_tickets_received: 0,
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "tls_connection",
+                side = ?side,
+                server_name = tracing::field::Empty,
+            ),
         }
     }
 
@@ -139,6 +177,30 @@ impl CommonState {
         self.peer_certificates.as_deref()
     }
 
+    /// Retrieves the certificate path built when verifying the peer's certificates.
+    ///
+    /// This is `None` until the peer's certificates are verified, for resumed handshakes,
+    /// and if the certificate verifier does not report the path it verified.
+    /// [`WebPkiServerVerifier`] and [`WebPkiClientVerifier`] report their paths.
+    ///
+    /// [`WebPkiServerVerifier`]: crate::client::WebPkiServerVerifier
+    /// [`WebPkiClientVerifier`]: crate::server::WebPkiClientVerifier
+    pub fn peer_verified_path(&self) -> Option<&VerifiedPath> {
+        self.peer_verified_path.as_ref()
+    }
+
+    /// Retrieves the SPIFFE ID of the peer, as authenticated by a [`SpiffeServerVerifier`]
+    /// or [`SpiffeClientVerifier`].
+    ///
+    /// This is `None` until the peer's certificate is verified, for resumed handshakes,
+    /// and if the peer's certificate was verified by any other verifier.
+    ///
+    /// [`SpiffeServerVerifier`]: crate::client::SpiffeServerVerifier
+    /// [`SpiffeClientVerifier`]: crate::server::SpiffeClientVerifier
+    pub fn peer_spiffe_id(&self) -> Option<&SpiffeId> {
+        self.peer_spiffe_id.as_ref()
+    }
+
     /// Retrieves the protocol agreed with the peer via ALPN.
     ///
     /// A return value of `None` after handshake completion
@@ -188,6 +250,54 @@ impl CommonState {
         self.handshake_kind
     }
 
+    /// Retrieves the payload of a custom extension received from the peer.
+    ///
+    /// `extension_type` must be registered as a [`CustomExtension`] for `context`.
+    /// This returns `None` if the peer did not send the extension in that message,
+    /// or that message has not yet been received.
+    ///
+    /// [`CustomExtension`]: crate::CustomExtension
+    pub fn received_custom_extension(
+        &self,
+        context: ExtensionContext,
+        extension_type: u16,
+    ) -> Option<&[u8]> {
+        self.custom_extensions
+            .received(context, extension_type)
+    }
+
+    /// Takes a snapshot of everything negotiated with the peer so far.
+    ///
+    /// This is most useful once the handshake is complete; before then some
+    /// fields will not yet be filled in.  See [`ConnectionInfo`].
+    pub fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            side: self.side,
+            protocol_version: self.negotiated_version,
+            cipher_suite: self.suite,
+            key_exchange_group: self
+                .negotiated_key_exchange_group()
+                .map(|group| group.name()),
+            handshake_kind: self.handshake_kind,
+            alpn_protocol: self
+                .alpn_protocol
+                .as_ref()
+                .map(|protocol| protocol.as_ref().to_vec()),
+            server_name: self.sni.clone(),
+            peer_certificates: self
+                .peer_certificates
+                .as_ref()
+                .map(|chain| chain.to_vec()),
+            peer_verified_path: self.peer_verified_path.clone(),
+            peer_signature_scheme: self.peer_signature_scheme,
+            local_certified_key: self.local_certified_key.clone(),
+            extended_master_secret: self.extended_master_secret,
+            client_certificate_type: self.client_certificate_type,
+            server_certificate_type: self.server_certificate_type,
+            early_data_accepted: self.early_data_accepted,
+        }
+    }
+
     pub(crate) fn is_tls13(&self) -> bool {
         matches!(self.negotiated_version, Some(ProtocolVersion::TLSv1_3))
     }
@@ -415,6 +525,14 @@ impl CommonState {
     ///
     /// Also flush `sendable_plaintext` if it is `Some`.
     pub(crate) fn start_traffic(&mut self, sendable_plaintext: &mut Option<&mut ChunkVecBuffer>) {
+        debug!(
+            version = ?self.negotiated_version,
+            suite = ?self.suite.map(|s| s.suite()),
+            group = ?self.negotiated_key_exchange_group().map(|g| g.name()),
+            handshake_kind = ?self.handshake_kind,
+            alpn = ?self.alpn_protocol.as_deref().map(bs_debug::BsDebug),
+            "handshake complete"
+        );
         self.may_receive_application_data = true;
         self.start_outgoing_traffic(sendable_plaintext);
     }
@@ -503,7 +621,7 @@ impl CommonState {
     }
 
     fn send_warning_alert(&mut self, desc: AlertDescription) {
-        warn!("Sending warning alert {desc:?}");
+        warn!(alert.level = "warning", alert.description = ?desc, "sending alert");
         self.send_warning_alert_no_log(desc);
     }
 
@@ -536,12 +654,21 @@ impl CommonState {
             // Some implementations send pointless `user_canceled` alerts, don't log them
             // in release mode (https://bugs.openjdk.org/browse/JDK-8323517).
             if alert.description != AlertDescription::UserCanceled || cfg!(debug_assertions) {
-                warn!("TLS alert warning received: {alert:?}");
+                warn!(
+                    alert.level = "warning",
+                    alert.description = ?alert.description,
+                    "received alert"
+                );
             }
 
             return Ok(());
         }
 
+        debug!(
+            alert.level = ?alert.level,
+            alert.description = ?alert.description,
+            "received alert"
+        );
         Err(err)
     }
 
@@ -562,6 +689,7 @@ impl CommonState {
         err: impl Into<Error>,
     ) -> Error {
         debug_assert!(!self.sent_fatal_alert);
+        debug!(alert.level = "fatal", alert.description = ?desc, "sending alert");
         let m = Message::build_alert(AlertLevel::Fatal, desc);
         self.send_msg(m, self.record_layer.is_encrypting());
         self.sent_fatal_alert = true;
@@ -579,7 +707,11 @@ impl CommonState {
         if self.sent_fatal_alert {
             return;
         }
-        debug!("Sending warning alert {:?}", AlertDescription::CloseNotify);
+        debug!(
+            alert.level = "warning",
+            alert.description = ?AlertDescription::CloseNotify,
+            "sending alert"
+        );
         self.sent_fatal_alert = true;
         self.has_sent_close_notify = true;
         self.send_warning_alert_no_log(AlertDescription::CloseNotify);
@@ -802,6 +934,104 @@ pub enum HandshakeKind {
     Resumed,
 }
 
+/// A snapshot of the security parameters negotiated on a connection.
+///
+/// This is returned by [`CommonState::connection_info()`] and
+/// [`KernelConnection::connection_info()`], and gathers in one place the
+/// values otherwise available from individual getters, plus some that are
+/// not exposed elsewhere.
+///
+/// [`KernelConnection::connection_info()`]: crate::kernel::KernelConnection::connection_info
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ConnectionInfo {
+    /// Which side of the connection this is.
+    pub side: Side,
+
+    /// The protocol version agreed with the peer.
+    ///
+    /// See [`CommonState::protocol_version()`].
+    pub protocol_version: Option<ProtocolVersion>,
+
+    /// The cipher suite agreed with the peer.
+    ///
+    /// See [`CommonState::negotiated_cipher_suite()`].
+    pub cipher_suite: Option<SupportedCipherSuite>,
+
+    /// The key exchange group agreed with the peer.
+    ///
+    /// See [`CommonState::negotiated_key_exchange_group()`] for when this is `None`.
+    pub key_exchange_group: Option<NamedGroup>,
+
+    /// Which kind of handshake was performed.
+    ///
+    /// See [`CommonState::handshake_kind()`].
+    pub handshake_kind: Option<HandshakeKind>,
+
+    /// The protocol agreed with the peer via ALPN.
+    ///
+    /// See [`CommonState::alpn_protocol()`].
+    pub alpn_protocol: Option<Vec<u8>>,
+
+    /// The server name indication.
+    ///
+    /// For clients this is the name that was sent in the `server_name` extension.  If
+    /// ECH was accepted this is the name from the inner `ClientHello`, otherwise
+    /// it is the name that was sent in the clear.
+    ///
+    /// For servers this is the name that the client sent.
+    ///
+    /// `None` if no `server_name` extension was sent.
+    pub server_name: Option<DnsName<'static>>,
+
+    /// The certificate chain or raw public key presented by the peer.
+    ///
+    /// See [`CommonState::peer_certificates()`].
+    pub peer_certificates: Option<Vec<CertificateDer<'static>>>,
+
+    /// The certificate path built when verifying the peer's certificates.
+    ///
+    /// See [`CommonState::peer_verified_path()`].
+    pub peer_verified_path: Option<VerifiedPath>,
+
+    /// The signature scheme the peer used to prove possession of its private key.
+    ///
+    /// This is `None` for resumed handshakes, or if the peer did not authenticate.
+    pub peer_signature_scheme: Option<SignatureScheme>,
+
+    /// The certificate and key we selected to authenticate ourselves to the peer.
+    ///
+    /// For servers, this is the value returned by the configured
+    /// [`ResolvesServerCert`][crate::server::ResolvesServerCert].  For clients this
+    /// is the value returned by the configured
+    /// [`ResolvesClientCert`][crate::client::ResolvesClientCert] if the server asked
+    /// for client authentication and a usable certificate was found.
+    ///
+    /// This is `None` for resumed handshakes, where no certificate is sent.
+    pub local_certified_key: Option<Arc<CertifiedKey>>,
+
+    /// Whether the TLS1.2 extended master secret extension ([RFC 7627]) was used.
+    ///
+    /// This is always `false` for TLS1.3, where the key schedule already
+    /// includes the handshake transcript.
+    ///
+    /// [RFC 7627]: https://datatracker.ietf.org/doc/html/rfc7627
+    pub extended_master_secret: bool,
+
+    /// The type of certificate used by the client.
+    ///
+    /// This is [`CertificateType::X509`] unless raw public keys were negotiated.
+    pub client_certificate_type: CertificateType,
+
+    /// The type of certificate used by the server.
+    ///
+    /// This is [`CertificateType::X509`] unless raw public keys were negotiated.
+    pub server_certificate_type: CertificateType,
+
+    /// Whether the server accepted TLS1.3 early data (0-RTT).
+    pub early_data_accepted: bool,
+}
+
 /// Values of this structure are returned from [`Connection::process_new_packets`]
 /// and tell the caller the current I/O state of the TLS connection.
 ///
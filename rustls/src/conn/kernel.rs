@@ -57,7 +57,7 @@ use alloc::boxed::Box;
 use core::marker::PhantomData;
 
 use crate::client::ClientConnectionData;
-use crate::common_state::Protocol;
+use crate::common_state::{ConnectionInfo, Protocol};
 use crate::msgs::codec::Codec;
 use crate::msgs::handshake::{CertificateChain, NewSessionTicketPayloadTls13};
 use crate::quic::Quic;
@@ -79,12 +79,14 @@ pub struct KernelConnection<Data> {
     negotiated_version: ProtocolVersion,
     protocol: Protocol,
     suite: SupportedCipherSuite,
+    info: ConnectionInfo,
 
     _data: PhantomData<Data>,
 }
 
 impl<Data> KernelConnection<Data> {
     pub(crate) fn new(state: Box<dyn KernelState>, common: CommonState) -> Result<Self, Error> {
+        let info = common.connection_info();
         Ok(Self {
             state,
 
@@ -97,6 +99,7 @@ impl<Data> KernelConnection<Data> {
             suite: common
                 .suite
                 .ok_or(Error::HandshakeNotComplete)?,
+            info,
 
             _data: PhantomData,
         })
@@ -112,6 +115,13 @@ impl<Data> KernelConnection<Data> {
         self.negotiated_version
     }
 
+    /// Retrieves a snapshot of the parameters negotiated during the handshake.
+    ///
+    /// See [`CommonState::connection_info()`].
+    pub fn connection_info(&self) -> ConnectionInfo {
+        self.info.clone()
+    }
+
     /// Update the traffic secret used for encrypting messages sent to the peer.
     ///
     /// Returns the new traffic secret and initial sequence number to use.
@@ -45,6 +45,14 @@ impl<Data> UnbufferedConnectionCommon<Data> {
         mut early_data_available: impl FnMut(&mut Self) -> bool,
         early_data_state: impl FnOnce(&'c mut Self, &'i mut [u8]) -> ConnectionState<'c, 'i, Data>,
     ) -> UnbufferedStatus<'c, 'i, Data> {
+        #[cfg(feature = "tracing")]
+        let _span = self
+            .core
+            .common_state
+            .span
+            .clone()
+            .entered();
+
         let mut buffer = DeframerSliceBuffer::new(incoming_tls);
         let mut buffer_progress = self.core.hs_deframer.progress();
 
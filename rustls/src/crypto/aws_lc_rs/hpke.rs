@@ -3,20 +3,20 @@ use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
 
 use aws_lc_rs::aead::{
-    self, Aad, BoundKey, NONCE_LEN, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey,
+    self, Aad, BoundKey, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey,
 };
 use aws_lc_rs::agreement;
 use aws_lc_rs::cipher::{AES_128_KEY_LEN, AES_256_KEY_LEN};
 use aws_lc_rs::digest::{SHA256_OUTPUT_LEN, SHA384_OUTPUT_LEN, SHA512_OUTPUT_LEN};
 use aws_lc_rs::encoding::{AsBigEndian, Curve25519SeedBin, EcPrivateKeyBin};
-use zeroize::Zeroize;
 
 use crate::crypto::aws_lc_rs::hmac::{HMAC_SHA256, HMAC_SHA384, HMAC_SHA512};
 use crate::crypto::aws_lc_rs::unspecified_err;
+use crate::crypto::hpke::schedule::{KemSharedSecret, KeySchedule, extract_and_expand};
 use crate::crypto::hpke::{
     EncapsulatedSecret, Hpke, HpkeOpener, HpkePrivateKey, HpkePublicKey, HpkeSealer, HpkeSuite,
 };
-use crate::crypto::tls13::{HkdfExpander, HkdfPrkExtract, HkdfUsingHmac, expand};
+use crate::crypto::tls13::{HkdfPrkExtract, HkdfUsingHmac};
 use crate::msgs::enums::{HpkeAead, HpkeKdf, HpkeKem};
 use crate::msgs::handshake::HpkeSymmetricCipherSuite;
 #[cfg(feature = "std")]
@@ -258,62 +258,11 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeAwsLcRs<KEY_SIZE, KDF_SIZ
     ///
     /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1
     fn key_schedule(
-        &self,
-        shared_secret: KemSharedSecret<KDF_SIZE>,
-        info: &[u8],
-    ) -> Result<KeySchedule<KEY_SIZE>, Error> {
-        // Note: we use an empty IKM for the `psk_id_hash` and `secret` labelled extractions because
-        // there is no PSK ID in base mode HPKE.
-
-        let suite_id = LabeledSuiteId::Hpke(self.suite);
-        let psk_id_hash = labeled_extract_for_prk(self.hkdf, suite_id, None, Label::PskIdHash, &[]);
-        let info_hash = labeled_extract_for_prk(self.hkdf, suite_id, None, Label::InfoHash, info);
-        let key_schedule_context = [
-            &[0][..], // base mode (0x00)
-            &psk_id_hash,
-            &info_hash,
-        ]
-        .concat();
-
-        let key = AeadKey(self.key_schedule_labeled_expand::<KEY_SIZE>(
-            &shared_secret,
-            &key_schedule_context,
-            Label::Key,
-        ));
-
-        let base_nonce = self.key_schedule_labeled_expand::<NONCE_LEN>(
-            &shared_secret,
-            &key_schedule_context,
-            Label::BaseNonce,
-        );
-
-        Ok(KeySchedule {
-            aead: self.aead,
-            key,
-            base_nonce,
-            seq_num: 0,
-        })
-    }
-
-    fn key_schedule_labeled_expand<const L: usize>(
         &self,
         shared_secret: &KemSharedSecret<KDF_SIZE>,
-        key_schedule_context: &[u8],
-        label: Label,
-    ) -> [u8; L] {
-        let suite_id = LabeledSuiteId::Hpke(self.suite);
-        labeled_expand::<L>(
-            suite_id,
-            labeled_extract_for_expand(
-                self.hkdf,
-                suite_id,
-                Some(&shared_secret.0),
-                Label::Secret,
-                &[],
-            ),
-            label,
-            key_schedule_context,
-        )
+        info: &[u8],
+    ) -> KeySchedule<KEY_SIZE> {
+        KeySchedule::new(self.suite, self.hkdf, shared_secret, info)
     }
 }
 
@@ -391,9 +340,11 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for HpkeAwsLcRs<KEY_SIZ
     }
 }
 
-/// Adapts a [KeySchedule] and [AeadKey] for the role of a [HpkeSealer].
+/// Adapts a [KeySchedule] and AEAD algorithm for the role of a [HpkeSealer].
 struct Sealer<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    aead: &'static aead::Algorithm,
     key_schedule: KeySchedule<KEY_SIZE>,
+    shared_secret: KemSharedSecret<KDF_SIZE>,
 }
 
 impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Sealer<KEY_SIZE, KDF_SIZE> {
@@ -411,8 +362,15 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Sealer<KEY_SIZE, KDF_SIZE> {
         //                            default_psk, default_psk_id)
 
         let (shared_secret, enc) = suite.dh_kem.encap(pub_key)?;
-        let key_schedule = suite.key_schedule(shared_secret, info)?;
-        Ok((enc, Self { key_schedule }))
+        let key_schedule = suite.key_schedule(&shared_secret, info);
+        Ok((
+            enc,
+            Self {
+                aead: suite.aead,
+                key_schedule,
+                shared_secret,
+            },
+        ))
     }
 
     /// A **test only** constructor that uses a pre-specified ephemeral agreement private key
@@ -427,8 +385,15 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Sealer<KEY_SIZE, KDF_SIZE> {
         let (shared_secret, enc) = suite
             .dh_kem
             .test_only_encap(pub_key, sk_e)?;
-        let key_schedule = suite.key_schedule(shared_secret, info)?;
-        Ok((enc, Self { key_schedule }))
+        let key_schedule = suite.key_schedule(&shared_secret, info);
+        Ok((
+            enc,
+            Self {
+                aead: suite.aead,
+                key_schedule,
+                shared_secret,
+            },
+        ))
     }
 }
 
@@ -439,8 +404,7 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeSealer for Sealer<KEY_SIZ
         //   self.IncrementSeq()
         //   return ct
 
-        let key = UnboundKey::new(self.key_schedule.aead, &self.key_schedule.key.0)
-            .map_err(unspecified_err)?;
+        let key = UnboundKey::new(self.aead, self.key_schedule.key()).map_err(unspecified_err)?;
         let mut sealing_key = SealingKey::new(key, &mut self.key_schedule);
 
         let mut in_out_buffer = Vec::from(plaintext);
@@ -450,6 +414,10 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeSealer for Sealer<KEY_SIZ
 
         Ok(in_out_buffer)
     }
+
+    fn shared_secret(&self) -> Option<&[u8]> {
+        Some(&self.shared_secret.0)
+    }
 }
 
 impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for Sealer<KEY_SIZE, KDF_SIZE> {
@@ -458,8 +426,9 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for Sealer<KEY_SIZE, KD
     }
 }
 
-/// Adapts a [KeySchedule] and [AeadKey] for the role of a [HpkeOpener].
+/// Adapts a [KeySchedule] and AEAD algorithm for the role of a [HpkeOpener].
 struct Opener<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    aead: &'static aead::Algorithm,
     key_schedule: KeySchedule<KEY_SIZE>,
 }
 
@@ -478,7 +447,8 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Opener<KEY_SIZE, KDF_SIZE> {
         //   return KeyScheduleR(mode_base, shared_secret, info,
         //                       default_psk, default_psk_id)
         Ok(Self {
-            key_schedule: suite.key_schedule(suite.dh_kem.decap(enc, secret_key)?, info)?,
+            aead: suite.aead,
+            key_schedule: suite.key_schedule(&suite.dh_kem.decap(enc, secret_key)?, info),
         })
     }
 }
@@ -492,8 +462,7 @@ impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeOpener for Opener<KEY_SIZ
         //   self.IncrementSeq()
         //   return pt
 
-        let key = UnboundKey::new(self.key_schedule.aead, &self.key_schedule.key.0)
-            .map_err(unspecified_err)?;
+        let key = UnboundKey::new(self.aead, self.key_schedule.key()).map_err(unspecified_err)?;
         let mut opening_key = OpeningKey::new(key, &mut self.key_schedule);
 
         let mut in_out_buffer = Vec::from(ciphertext);
@@ -581,10 +550,7 @@ impl<const KDF_SIZE: usize> DhKem<KDF_SIZE> {
         })
         .map_err(unspecified_err)?;
 
-        Ok((
-            KemSharedSecret(shared_secret),
-            EncapsulatedSecret(enc.as_ref().into()),
-        ))
+        Ok((shared_secret, EncapsulatedSecret(enc.as_ref().into())))
     }
 
     /// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
@@ -621,26 +587,11 @@ impl<const KDF_SIZE: usize> DhKem<KDF_SIZE> {
         })
         .map_err(unspecified_err)?;
 
-        Ok(KemSharedSecret(shared_secret))
+        Ok(shared_secret)
     }
 
-    /// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
-    ///
-    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
-    fn extract_and_expand(&self, dh: &[u8], kem_context: &[u8]) -> [u8; KDF_SIZE] {
-        // def ExtractAndExpand(dh, kem_context):
-        //   eae_prk = LabeledExtract("", "eae_prk", dh)
-        //   shared_secret = LabeledExpand(eae_prk, "shared_secret",
-        //                                 kem_context, Nsecret)
-        //   return shared_secret
-
-        let suite_id = LabeledSuiteId::Kem(self.id);
-        labeled_expand(
-            suite_id,
-            labeled_extract_for_expand(self.hkdf, suite_id, None, Label::EaePrk, dh),
-            Label::SharedSecret,
-            kem_context,
-        )
+    fn extract_and_expand(&self, dh: &[u8], kem_context: &[u8]) -> KemSharedSecret<KDF_SIZE> {
+        extract_and_expand(self.id, self.hkdf, dh, kem_context)
     }
 }
 
@@ -726,205 +677,15 @@ fn generate_key_pair(
     Ok((public_key, private_key))
 }
 
-/// KeySchedule holds the derived AEAD key, base nonce, and seq number
-/// common to both a [Sealer] and [Opener].
-struct KeySchedule<const KEY_SIZE: usize> {
-    aead: &'static aead::Algorithm,
-    key: AeadKey<KEY_SIZE>,
-    base_nonce: [u8; NONCE_LEN],
-    seq_num: u32,
-}
-
-impl<const KEY_SIZE: usize> KeySchedule<KEY_SIZE> {
-    /// See [RFC 9180 §5.2 "Encryption and Decryption"][0].
-    ///
-    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.2
-    fn compute_nonce(&self) -> [u8; NONCE_LEN] {
-        // def Context<ROLE>.ComputeNonce(seq):
-        //   seq_bytes = I2OSP(seq, Nn)
-        //   return xor(self.base_nonce, seq_bytes)
-
-        // Each new N-byte nonce is conceptually two parts:
-        //   * N-4 bytes of the base nonce (0s in `nonce` to XOR in as-is).
-        //   * 4 bytes derived from the sequence number XOR the base nonce.
-        let mut nonce = [0; NONCE_LEN];
-        let seq_bytes = self.seq_num.to_be_bytes();
-        nonce[NONCE_LEN - seq_bytes.len()..].copy_from_slice(&seq_bytes);
-
-        for (n, &b) in nonce.iter_mut().zip(&self.base_nonce) {
-            *n ^= b;
-        }
-
-        nonce
-    }
-
-    /// See [RFC 9180 §5.2 "Encryption and Decryption"][0].
-    ///
-    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.2
-    fn increment_seq_num(&mut self) -> Result<(), aws_lc_rs::error::Unspecified> {
-        // def Context<ROLE>.IncrementSeq():
-        //   if self.seq >= (1 << (8*Nn)) - 1:
-        //     raise MessageLimitReachedError
-        //   self.seq += 1
-
-        // Determine the maximum sequence number using the AEAD nonce's length in bits.
-        // Do this as an u128 to prevent overflowing.
-        let max_seq_num = (1u128 << (NONCE_LEN * 8)) - 1;
-
-        // Promote the u32 sequence number to an u128 and compare against the maximum allowed
-        // sequence number.
-        if u128::from(self.seq_num) >= max_seq_num {
-            return Err(aws_lc_rs::error::Unspecified);
-        }
-
-        self.seq_num += 1;
-        Ok(())
-    }
-}
-
 impl<const KEY_SIZE: usize> NonceSequence for &mut KeySchedule<KEY_SIZE> {
     fn advance(&mut self) -> Result<Nonce, aws_lc_rs::error::Unspecified> {
-        let nonce = self.compute_nonce();
-        self.increment_seq_num()?;
+        let nonce = self
+            .next_nonce()
+            .map_err(|_| aws_lc_rs::error::Unspecified)?;
         Nonce::try_assume_unique_for_key(&nonce)
     }
 }
 
-/// See [RFC 9180 §4 "Cryptographic Dependencies"][0].
-///
-/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4
-fn labeled_extract_for_expand(
-    hkdf: &'static dyn HkdfPrkExtract,
-    suite_id: LabeledSuiteId,
-    salt: Option<&[u8]>,
-    label: Label,
-    ikm: &[u8],
-) -> Box<dyn HkdfExpander> {
-    // def LabeledExtract(salt, label, ikm):
-    //   labeled_ikm = concat("HPKE-v1", suite_id, label, ikm)
-    //   return Extract(salt, labeled_ikm)
-
-    let labeled_ikm = [&b"HPKE-v1"[..], &suite_id.encoded(), label.as_ref(), ikm].concat();
-    hkdf.extract_from_secret(salt, &labeled_ikm)
-}
-
-/// See [RFC 9180 §4 "Cryptographic Dependencies"][0].
-///
-/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4
-fn labeled_extract_for_prk(
-    hkdf: &'static dyn HkdfPrkExtract,
-    suite_id: LabeledSuiteId,
-    salt: Option<&[u8]>,
-    label: Label,
-    ikm: &[u8],
-) -> Vec<u8> {
-    // def LabeledExtract(salt, label, ikm):
-    //   labeled_ikm = concat("HPKE-v1", suite_id, label, ikm)
-    //   return Extract(salt, labeled_ikm)
-
-    let labeled_ikm = [&b"HPKE-v1"[..], &suite_id.encoded(), label.as_ref(), ikm].concat();
-    hkdf.extract_prk_from_secret(salt, &labeled_ikm)
-}
-
-/// See [RFC 9180 §4 "Cryptographic Dependencies"][0].
-///
-/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4
-fn labeled_expand<const L: usize>(
-    suite_id: LabeledSuiteId,
-    expander: Box<dyn HkdfExpander>,
-    label: Label,
-    kem_context: &[u8],
-) -> [u8; L] {
-    // def LabeledExpand(prk, label, info, L):
-    //   labeled_info = concat(I2OSP(L, 2), "HPKE-v1", suite_id,
-    //                         label, info)
-    //   return Expand(prk, labeled_info, L)
-
-    let output_len = u16::to_be_bytes(L as u16);
-    let info = &[
-        &output_len[..],
-        b"HPKE-v1",
-        &suite_id.encoded(),
-        label.as_ref(),
-        kem_context,
-    ];
-
-    expand(&*expander, info)
-}
-
-/// Label describes the possible labels for use with [labeled_extract_for_expand] and [labeled_expand].
-#[derive(Debug)]
-enum Label {
-    PskIdHash,
-    InfoHash,
-    Secret,
-    Key,
-    BaseNonce,
-    EaePrk,
-    SharedSecret,
-}
-
-impl AsRef<[u8]> for Label {
-    fn as_ref(&self) -> &[u8] {
-        match self {
-            Self::PskIdHash => b"psk_id_hash",
-            Self::InfoHash => b"info_hash",
-            Self::Secret => b"secret",
-            Self::Key => b"key",
-            Self::BaseNonce => b"base_nonce",
-            Self::EaePrk => b"eae_prk",
-            Self::SharedSecret => b"shared_secret",
-        }
-    }
-}
-
-/// LabeledSuiteId describes the possible suite ID values for use with [labeled_extract_for_expand] and
-/// [labeled_expand].
-#[derive(Debug, Copy, Clone)]
-enum LabeledSuiteId {
-    Hpke(HpkeSuite),
-    Kem(HpkeKem),
-}
-
-impl LabeledSuiteId {
-    /// The suite ID encoding depends on the context of use. In the general HPKE context,
-    /// we use a "HPKE" prefix and encode the entire ciphersuite. In the KEM context we use a
-    /// "KEM" prefix and only encode the KEM ID.
-    ///
-    /// See the bottom of [RFC 9180 §4](https://www.rfc-editor.org/rfc/rfc9180.html#section-4)
-    /// for more information.
-    fn encoded(&self) -> Vec<u8> {
-        match self {
-            Self::Hpke(suite) => [
-                &b"HPKE"[..],
-                &u16::from(suite.kem).to_be_bytes(),
-                &u16::from(suite.sym.kdf_id).to_be_bytes(),
-                &u16::from(suite.sym.aead_id).to_be_bytes(),
-            ]
-            .concat(),
-            Self::Kem(kem) => [&b"KEM"[..], &u16::from(*kem).to_be_bytes()].concat(),
-        }
-    }
-}
-
-/// A newtype wrapper for an unbound AEAD key.
-struct AeadKey<const KEY_LEN: usize>([u8; KEY_LEN]);
-
-impl<const KEY_LEN: usize> Drop for AeadKey<KEY_LEN> {
-    fn drop(&mut self) {
-        self.0.zeroize()
-    }
-}
-
-/// A newtype wrapper for a DH KEM shared secret.
-struct KemSharedSecret<const KDF_LEN: usize>([u8; KDF_LEN]);
-
-impl<const KDF_LEN: usize> Drop for KemSharedSecret<KDF_LEN> {
-    fn drop(&mut self) {
-        self.0.zeroize();
-    }
-}
-
 fn key_rejected_err(_e: aws_lc_rs::error::KeyRejected) -> Error {
     #[cfg(feature = "std")]
     {
@@ -159,6 +159,8 @@ pub static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgori
         webpki_algs::ECDSA_P521_SHA384,
         webpki_algs::ECDSA_P521_SHA512,
         webpki_algs::ED25519,
+        #[cfg(all(feature = "curve448", not(feature = "fips")))]
+        &crate::crypto::curve448::Ed448Verify,
         webpki_algs::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
         webpki_algs::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
         webpki_algs::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
@@ -192,6 +194,11 @@ pub static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgori
             &[webpki_algs::ECDSA_P521_SHA512],
         ),
         (SignatureScheme::ED25519, &[webpki_algs::ED25519]),
+        #[cfg(all(feature = "curve448", not(feature = "fips")))]
+        (
+            SignatureScheme::ED448,
+            &[&crate::crypto::curve448::Ed448Verify],
+        ),
         (
             SignatureScheme::RSA_PSS_SHA512,
             &[webpki_algs::RSA_PSS_2048_8192_SHA512_LEGACY_KEY],
@@ -226,12 +233,15 @@ pub static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgori
 pub mod kx_group {
     pub use super::kx::{SECP256R1, SECP384R1, X25519};
     pub use super::pq::{MLKEM768, SECP256R1MLKEM768, X25519MLKEM768};
+    #[cfg(feature = "curve448")]
+    pub use crate::crypto::curve448::X448;
 }
 
 /// A list of the default key exchange groups supported by this provider.
 ///
 /// This does not contain MLKEM768; by default MLKEM768 is only offered
-/// in hybrid with X25519.
+/// in hybrid with X25519.  X448 (with the `curve448` crate feature) is
+/// also not offered by default.
 pub static DEFAULT_KX_GROUPS: &[&dyn SupportedKxGroup] = &[
     kx_group::X25519MLKEM768,
     kx_group::X25519,
@@ -246,6 +256,8 @@ pub static ALL_KX_GROUPS: &[&dyn SupportedKxGroup] = &[
     kx_group::X25519,
     kx_group::SECP256R1,
     kx_group::SECP384R1,
+    #[cfg(feature = "curve448")]
+    kx_group::X448,
     kx_group::MLKEM768,
 ];
 
@@ -3,7 +3,7 @@ use alloc::vec::Vec;
 
 use aws_lc_rs::kem;
 
-use super::INVALID_KEY_SHARE;
+use crate::crypto::pq::INVALID_KEY_SHARE;
 use crate::crypto::{ActiveKeyExchange, CompletedKeyExchange, SharedSecret, SupportedKxGroup};
 use crate::{Error, NamedGroup};
 
@@ -1,8 +1,8 @@
+use crate::NamedGroup;
 use crate::crypto::SupportedKxGroup;
 use crate::crypto::aws_lc_rs::kx_group;
-use crate::{Error, NamedGroup, PeerMisbehaved};
+use crate::crypto::pq::{MLKEM768_CIPHERTEXT_LEN, MLKEM768_ENCAP_LEN, X25519_LEN, hybrid};
 
-mod hybrid;
 mod mlkem;
 
 /// This is the [X25519MLKEM768] key exchange.
@@ -40,9 +40,4 @@ pub static SECP256R1MLKEM768: &dyn SupportedKxGroup = &hybrid::Hybrid {
 /// [MLKEM]: https://datatracker.ietf.org/doc/draft-connolly-tls-mlkem-key-agreement
 pub static MLKEM768: &dyn SupportedKxGroup = &mlkem::MlKem768;
 
-const INVALID_KEY_SHARE: Error = Error::PeerMisbehaved(PeerMisbehaved::InvalidKeyShare);
-
-const X25519_LEN: usize = 32;
 const SECP256R1_LEN: usize = 65;
-const MLKEM768_CIPHERTEXT_LEN: usize = 1088;
-const MLKEM768_ENCAP_LEN: usize = 1184;
@@ -6,10 +6,18 @@ use alloc::vec::Vec;
 use alloc::{format, vec};
 use core::fmt::{self, Debug, Formatter};
 
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+use pki_types::AlgorithmIdentifier;
 use pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, SubjectPublicKeyInfoDer, alg_id};
 
 use super::ring_like::rand::SystemRandom;
 use super::ring_like::signature::{self, EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair};
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+use super::ring_like::unstable::signature::{
+    MLDSA_44_SIGNING, MLDSA_65_SIGNING, MLDSA_87_SIGNING, PqdsaKeyPair, PqdsaSigningAlgorithm,
+};
+#[cfg(feature = "curve448")]
+use crate::crypto::curve448::Ed448SigningKey;
 use crate::crypto::signer::{Signer, SigningKey, public_key_to_spki};
 use crate::enums::{SignatureAlgorithm, SignatureScheme};
 use crate::error::Error;
@@ -30,13 +38,23 @@ pub fn any_supported_type(der: &PrivateKeyDer<'_>) -> Result<Arc<dyn SigningKey>
         if let Ok(eddsa) = any_eddsa_type(pkcs8) {
             return Ok(eddsa);
         }
+
+        #[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+        if let Ok(ml_dsa) = any_ml_dsa_type(pkcs8) {
+            return Ok(ml_dsa);
+        }
     }
 
-    Err(Error::General(
-        "failed to parse private key as RSA, ECDSA, or EdDSA".into(),
-    ))
+    Err(Error::General(format!(
+        "failed to parse private key as {SUPPORTED_KEY_TYPES}"
+    )))
 }
 
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+const SUPPORTED_KEY_TYPES: &str = "RSA, ECDSA, EdDSA, or ML-DSA";
+#[cfg(not(all(feature = "aws-lc-rs-unstable", not(feature = "fips"))))]
+const SUPPORTED_KEY_TYPES: &str = "RSA, ECDSA, or EdDSA";
+
 /// Parse `der` as any ECDSA key type, returning the first which works.
 ///
 /// Both SEC1 (PEM section starting with 'BEGIN EC PRIVATE KEY') and PKCS8
@@ -73,18 +91,57 @@ pub fn any_ecdsa_type(der: &PrivateKeyDer<'_>) -> Result<Arc<dyn SigningKey>, Er
 
 /// Parse `der` as any EdDSA key type, returning the first which works.
 ///
-/// Note that, at the time of writing, Ed25519 does not have wide support
-/// in browsers.  It is also not supported by the WebPKI, because the
-/// CA/Browser Forum Baseline Requirements do not support it for publicly
+/// Ed25519 keys are supported, as are Ed448 keys with the `curve448` crate feature.
+///
+/// Note that, at the time of writing, Ed25519 and Ed448 do not have wide support
+/// in browsers.  They are also not supported by the WebPKI, because the
+/// CA/Browser Forum Baseline Requirements do not support them for publicly
 /// trusted certificates.
 pub fn any_eddsa_type(der: &PrivatePkcs8KeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
-    // TODO: Add support for Ed448
+    #[cfg(feature = "curve448")]
+    if let Ok(ed448) = Ed448SigningKey::new(der) {
+        return Ok(Arc::new(ed448));
+    }
+
     Ok(Arc::new(Ed25519SigningKey::new(
         der,
         SignatureScheme::ED25519,
     )?))
 }
 
+/// Parse `der` as any ML-DSA key type, returning the first which works.
+///
+/// ML-DSA keys are only usable in TLS1.3.  Note that the ML-DSA support
+/// in aws-lc-rs is currently experimental.
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+pub fn any_ml_dsa_type(der: &PrivatePkcs8KeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
+    for (scheme, sigalg, alg_id) in [
+        (
+            SignatureScheme::ML_DSA_44,
+            &MLDSA_44_SIGNING,
+            alg_id::ML_DSA_44,
+        ),
+        (
+            SignatureScheme::ML_DSA_65,
+            &MLDSA_65_SIGNING,
+            alg_id::ML_DSA_65,
+        ),
+        (
+            SignatureScheme::ML_DSA_87,
+            &MLDSA_87_SIGNING,
+            alg_id::ML_DSA_87,
+        ),
+    ] {
+        if let Ok(ml_dsa) = MlDsaSigningKey::new(der, scheme, sigalg, alg_id) {
+            return Ok(Arc::new(ml_dsa));
+        }
+    }
+
+    Err(Error::General(
+        "failed to parse ML-DSA private key as PKCS#8".into(),
+    ))
+}
+
 /// A `SigningKey` for RSA-PKCS1 or RSA-PSS.
 ///
 /// This is used by the test suite, so it must be `pub`, but it isn't part of
@@ -394,6 +451,95 @@ impl Debug for Ed25519Signer {
     }
 }
 
+/// A `SigningKey` for exactly one ML-DSA parameter set.
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+struct MlDsaSigningKey {
+    key: Arc<PqdsaKeyPair>,
+    scheme: SignatureScheme,
+    alg_id: AlgorithmIdentifier,
+}
+
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+impl MlDsaSigningKey {
+    fn new(
+        der: &PrivatePkcs8KeyDer<'_>,
+        scheme: SignatureScheme,
+        sigalg: &'static PqdsaSigningAlgorithm,
+        alg_id: AlgorithmIdentifier,
+    ) -> Result<Self, ()> {
+        let key_pair = PqdsaKeyPair::from_pkcs8(sigalg, der.secret_pkcs8_der()).map_err(|_| ())?;
+        Ok(Self {
+            key: Arc::new(key_pair),
+            scheme,
+            alg_id,
+        })
+    }
+}
+
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+impl SigningKey for MlDsaSigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if offered.contains(&self.scheme) {
+            Some(Box::new(MlDsaSigner {
+                key: self.key.clone(),
+                scheme: self.scheme,
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        Some(public_key_to_spki(&self.alg_id, self.key.public_key()))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.scheme.algorithm()
+    }
+}
+
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+impl Debug for MlDsaSigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MlDsaSigningKey")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+struct MlDsaSigner {
+    key: Arc<PqdsaKeyPair>,
+    scheme: SignatureScheme,
+}
+
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+impl Signer for MlDsaSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut sig = vec![0; self.key.algorithm().signature_len()];
+        self.key
+            .sign(message, &mut sig)
+            .map(|len| {
+                sig.truncate(len);
+                sig
+            })
+            .map_err(|_| Error::General("signing failed".into()))
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+impl Debug for MlDsaSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MlDsaSigner")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::format;
@@ -574,6 +720,17 @@ mod tests {
         assert!(any_ecdsa_type(&key).is_err());
     }
 
+    #[cfg(feature = "curve448")]
+    #[test]
+    fn can_load_ed448_pkcs8() {
+        let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/ed448key.der")[..]);
+        let k = any_eddsa_type(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "Ed448SigningKey { algorithm: ED448 }");
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_err());
+    }
+
     #[test]
     fn can_sign_eddsa() {
         let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/eddsakey.der")[..]);
@@ -598,6 +755,55 @@ mod tests {
         assert_eq!(s.sign(b"hello").unwrap().len(), 64);
     }
 
+    #[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+    #[test]
+    fn can_load_and_sign_ml_dsa() {
+        for (der, scheme, spki_alg_id, sig_len) in [
+            (
+                &include_bytes!("../../testdata/mldsa44key.der")[..],
+                SignatureScheme::ML_DSA_44,
+                alg_id::ML_DSA_44,
+                2420,
+            ),
+            (
+                &include_bytes!("../../testdata/mldsa65key.der")[..],
+                SignatureScheme::ML_DSA_65,
+                alg_id::ML_DSA_65,
+                3309,
+            ),
+            (
+                &include_bytes!("../../testdata/mldsa87key.der")[..],
+                SignatureScheme::ML_DSA_87,
+                alg_id::ML_DSA_87,
+                4627,
+            ),
+        ] {
+            let key = PrivatePkcs8KeyDer::from(der);
+            assert!(any_eddsa_type(&key).is_err());
+            let k = any_ml_dsa_type(&key).unwrap();
+            assert_eq!(
+                format!("{k:?}"),
+                format!("MlDsaSigningKey {{ scheme: {scheme:?} }}")
+            );
+            assert!(any_supported_type(&PrivateKeyDer::Pkcs8(key)).is_ok());
+
+            let spki = k.public_key().unwrap();
+            assert!(
+                spki.as_ref()
+                    .windows(spki_alg_id.len())
+                    .any(|w| w == spki_alg_id.as_ref())
+            );
+
+            assert!(
+                k.choose_scheme(&[SignatureScheme::ED25519])
+                    .is_none()
+            );
+            let s = k.choose_scheme(&[scheme]).unwrap();
+            assert_eq!(s.scheme(), scheme);
+            assert_eq!(s.sign(b"hello").unwrap().len(), sig_len);
+        }
+    }
+
     #[test]
     fn can_load_rsa2048_pkcs8() {
         let key =
@@ -660,9 +866,9 @@ mod tests {
         let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(&b"invalid"[..]));
         assert_eq!(
             any_supported_type(&key).err(),
-            Some(Error::General(
-                "failed to parse private key as RSA, ECDSA, or EdDSA".into()
-            ))
+            Some(Error::General(format!(
+                "failed to parse private key as {SUPPORTED_KEY_TYPES}"
+            )))
         );
         assert_eq!(
             any_ecdsa_type(&key).err(),
@@ -110,6 +110,8 @@ pub static TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384: SupportedCipherSuite =
     });
 
 static TLS12_ECDSA_SCHEMES: &[SignatureScheme] = &[
+    #[cfg(feature = "curve448")]
+    SignatureScheme::ED448,
     SignatureScheme::ED25519,
     SignatureScheme::ECDSA_NISTP521_SHA512,
     SignatureScheme::ECDSA_NISTP384_SHA384,
@@ -0,0 +1,462 @@
+//! X448 key exchange and Ed448 signatures, using the pure-Rust [`crrl`] crate.
+//!
+//! Neither *ring* nor aws-lc-rs implement curve448, so this is shared by all the
+//! built-in providers: the RustCrypto provider always uses it, and the *ring* and
+//! aws-lc-rs providers use it with the `curve448` crate feature.
+//!
+//! `crrl` is used rather than RustCrypto's `ed448-goldilocks`.  The last stable
+//! release of `ed448-goldilocks` (0.9) does not implement Ed448 signatures; the
+//! releases that do are pre-releases, which need Rust 1.85 and pre-release versions
+//! of the RustCrypto trait crates that conflict with those used by the rest of the
+//! RustCrypto provider.  `crrl` is a stable release whose only dependency is
+//! `rand_core`, which the RustCrypto provider already uses.
+//!
+//! [`crrl`]: https://crates.io/crates/crrl
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use pki_types::{
+    AlgorithmIdentifier, InvalidSignature, PrivatePkcs8KeyDer, SignatureVerificationAlgorithm,
+    SubjectPublicKeyInfoDer,
+};
+use rand_core::{OsRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::crypto::signer::{Signer, SigningKey, public_key_to_spki};
+use crate::crypto::{ActiveKeyExchange, SharedSecret, SupportedKxGroup};
+use crate::enums::{SignatureAlgorithm, SignatureScheme};
+use crate::error::{Error, PeerMisbehaved};
+use crate::msgs::enums::NamedGroup;
+use crate::sync::Arc;
+
+/// Ephemeral ECDH on curve448 (see RFC7748)
+pub static X448: &dyn SupportedKxGroup = &X448Group;
+
+struct X448Group;
+
+impl SupportedKxGroup for X448Group {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let mut priv_key = Zeroizing::new([0u8; X448_LEN]);
+        OsRng
+            .try_fill_bytes(&mut priv_key[..])
+            .map_err(|_| Error::FailedToGetRandomBytes)?;
+        let pub_key = crrl::x448::x448_base(&priv_key);
+        Ok(Box::new(X448KeyExchange { priv_key, pub_key }))
+    }
+
+    fn name(&self) -> NamedGroup {
+        NamedGroup::X448
+    }
+
+    fn fips(&self) -> bool {
+        // As for X25519, curve448 is not approved for key agreement.
+        false
+    }
+}
+
+impl Debug for X448Group {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+struct X448KeyExchange {
+    priv_key: Zeroizing<[u8; X448_LEN]>,
+    pub_key: [u8; X448_LEN],
+}
+
+impl ActiveKeyExchange for X448KeyExchange {
+    fn complete(self: Box<Self>, peer: &[u8]) -> Result<SharedSecret, Error> {
+        let peer: &[u8; X448_LEN] = peer
+            .try_into()
+            .map_err(|_| PeerMisbehaved::InvalidKeyShare)?;
+        let shared = Zeroizing::new(crrl::x448::x448(peer, &self.priv_key));
+
+        // Reject low-order points, which would yield an all-zero shared secret.
+        // <https://datatracker.ietf.org/doc/html/rfc8446#section-7.4.2>
+        if shared.iter().all(|b| *b == 0) {
+            return Err(PeerMisbehaved::InvalidKeyShare.into());
+        }
+
+        Ok(SharedSecret::from(&shared[..]))
+    }
+
+    fn group(&self) -> NamedGroup {
+        NamedGroup::X448
+    }
+
+    fn pub_key(&self) -> &[u8] {
+        &self.pub_key
+    }
+}
+
+/// ED448 signatures according to RFC 8410
+pub(crate) struct Ed448Verify;
+
+impl SignatureVerificationAlgorithm for Ed448Verify {
+    fn verify_signature(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), InvalidSignature> {
+        let key = crrl::ed448::PublicKey::decode(public_key).ok_or(InvalidSignature)?;
+        match key.verify_raw(signature, message) {
+            true => Ok(()),
+            false => Err(InvalidSignature),
+        }
+    }
+
+    fn public_key_alg_id(&self) -> AlgorithmIdentifier {
+        ED448_ALG_ID
+    }
+
+    fn signature_alg_id(&self) -> AlgorithmIdentifier {
+        ED448_ALG_ID
+    }
+
+    fn fips(&self) -> bool {
+        // `crrl` is not FIPS-validated.
+        false
+    }
+}
+
+impl Debug for Ed448Verify {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("ED448")
+    }
+}
+
+/// A `SigningKey` for Ed448, which uses exactly the `ED448` scheme.
+pub(crate) struct Ed448SigningKey {
+    key: Arc<crrl::ed448::PrivateKey>,
+}
+
+impl Ed448SigningKey {
+    /// Make a new `Ed448SigningKey` from a DER encoding in PKCS#8 format.
+    ///
+    /// Both PKCS#8 v1 and v2 are accepted.  A public key included in a v2 encoding
+    /// must match the private key.
+    pub(crate) fn new(der: &PrivatePkcs8KeyDer<'_>) -> Result<Self, Error> {
+        let (seed, public_key) = parse_pkcs8(der.secret_pkcs8_der()).ok_or_else(|| {
+            Error::General("failed to parse Ed448 private key: invalid encoding".into())
+        })?;
+
+        let key = crrl::ed448::PrivateKey::decode(seed).ok_or_else(|| {
+            Error::General("failed to parse Ed448 private key: invalid seed".into())
+        })?;
+
+        match public_key {
+            Some(public_key) if public_key != key.public_key.encode() => Err(Error::General(
+                "failed to parse Ed448 private key: mismatched public key".into(),
+            )),
+            _ => Ok(Self { key: Arc::new(key) }),
+        }
+    }
+}
+
+impl SigningKey for Ed448SigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if offered.contains(&SignatureScheme::ED448) {
+            Some(Box::new(Ed448Signer {
+                key: self.key.clone(),
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        Some(public_key_to_spki(
+            &ED448_ALG_ID,
+            self.key.public_key.encode(),
+        ))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::ED448
+    }
+}
+
+impl Debug for Ed448SigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed448SigningKey")
+            .field("algorithm", &self.algorithm())
+            .finish()
+    }
+}
+
+struct Ed448Signer {
+    key: Arc<crrl::ed448::PrivateKey>,
+}
+
+impl Signer for Ed448Signer {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self.key.sign_raw(message).to_vec())
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::ED448
+    }
+}
+
+impl Debug for Ed448Signer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed448Signer")
+            .field("scheme", &self.scheme())
+            .finish()
+    }
+}
+
+/// Return the seed, and the public key if present, from a PKCS#8 Ed448 private key.
+fn parse_pkcs8(der: &[u8]) -> Option<(&[u8], Option<&[u8]>)> {
+    // OneAsymmetricKey ::= SEQUENCE {
+    //    version                   Version,
+    //    privateKeyAlgorithm       PrivateKeyAlgorithmIdentifier,
+    //    privateKey                PrivateKey,
+    //    attributes            [0] Attributes OPTIONAL,
+    //    ...,
+    //    [[2: publicKey        [1] PublicKey OPTIONAL ]],
+    //    ...
+    // }
+    let mut input = der;
+    let mut key = der_element(&mut input, SEQUENCE)?;
+    if !input.is_empty() {
+        return None;
+    }
+
+    let version = der_element(&mut key, INTEGER)?;
+    if der_element(&mut key, SEQUENCE)? != ED448_ALG_ID.as_ref() {
+        return None;
+    }
+
+    // CurvePrivateKey ::= OCTET STRING (RFC 8410)
+    let mut private_key = der_element(&mut key, OCTET_STRING)?;
+    let seed = der_element(&mut private_key, OCTET_STRING)?;
+    if !private_key.is_empty() || seed.len() != ED448_SEED_LEN {
+        return None;
+    }
+
+    if key.first() == Some(&ATTRIBUTES) {
+        der_element(&mut key, ATTRIBUTES)?;
+    }
+
+    let public_key = match (version, key) {
+        ([0] | [1], []) => None,
+        ([1], _) => match der_element(&mut key, PUBLIC_KEY)? {
+            [0, public_key @ ..] if key.is_empty() => Some(public_key),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some((seed, public_key))
+}
+
+/// Read a DER element with the given `tag` from the front of `input`,
+/// returning its contents.
+fn der_element<'a>(input: &mut &'a [u8], tag: u8) -> Option<&'a [u8]> {
+    let &[actual, first, ref rest @ ..] = *input else {
+        return None;
+    };
+    if actual != tag {
+        return None;
+    }
+
+    let (len, rest) = match (first, rest) {
+        (0x00..=0x7f, rest) => (usize::from(first), rest),
+        (0x81, [len, rest @ ..]) if *len >= 0x80 => (usize::from(*len), rest),
+        (0x82, [hi, lo, rest @ ..]) if *hi != 0 => {
+            (usize::from(u16::from_be_bytes([*hi, *lo])), rest)
+        }
+        _ => return None,
+    };
+    if rest.len() < len {
+        return None;
+    }
+
+    let (contents, rest) = rest.split_at(len);
+    *input = rest;
+    Some(contents)
+}
+
+/// `id-Ed448` (1.3.101.113), with absent parameters.  See RFC 8410.
+const ED448_ALG_ID: AlgorithmIdentifier =
+    AlgorithmIdentifier::from_slice(&[0x06, 0x03, 0x2b, 0x65, 0x71]);
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const SEQUENCE: u8 = 0x30;
+const ATTRIBUTES: u8 = 0xa0;
+const PUBLIC_KEY: u8 = 0x81;
+
+const ED448_SEED_LEN: usize = 57;
+const X448_LEN: usize = 56;
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    #[test]
+    fn kxgroup_fmt_yields_name() {
+        assert_eq!("X448", format!("{X448:?}"));
+    }
+
+    #[test]
+    fn x448_agrees() {
+        let a = X448.start().unwrap();
+        let b = X448.start().unwrap();
+        let a_pub = a.pub_key().to_vec();
+        let b_pub = b.pub_key().to_vec();
+        assert_eq!(
+            a.complete(&b_pub)
+                .unwrap()
+                .secret_bytes(),
+            b.complete(&a_pub)
+                .unwrap()
+                .secret_bytes()
+        );
+    }
+
+    #[test]
+    fn x448_rejects_low_order_point() {
+        let kx = X448.start().unwrap();
+        assert_eq!(
+            kx.complete(&[0u8; 56]).err(),
+            Some(PeerMisbehaved::InvalidKeyShare.into())
+        );
+    }
+
+    #[test]
+    fn x448_rejects_wrong_length() {
+        let kx = X448.start().unwrap();
+        assert_eq!(
+            kx.complete(&[1u8; 32]).err(),
+            Some(PeerMisbehaved::InvalidKeyShare.into())
+        );
+    }
+
+    #[test]
+    fn x448_rfc7748_test_vector() {
+        // <https://datatracker.ietf.org/doc/html/rfc7748#section-6.2>
+        let alice_priv = Zeroizing::new(hex::<56>(
+            "9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28dd9c9baf574a9419744897391006382a6f127ab1d9ac2d8c0a598726b",
+        ));
+        let alice_pub = hex::<56>(
+            "9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c22c5d9bbc836647241d953d40c5b12da88120d53177f80e532c41fa0",
+        );
+        let bob_pub = hex::<56>(
+            "3eb7a829b0cd20f5bcfc0b599b6feccf6da4627107bdb0d4f345b43027d8b972fc3e34fb4232a13ca706dcb57aec3dae07bdc1c67bf33609",
+        );
+        let shared = hex::<56>(
+            "07fff4181ac6cc95ec1c16a94a0f74d12da232ce40a77552281d282bb60c0b56fd2464c335543936521c24403085d59a449a5037514a879d",
+        );
+
+        let kx = Box::new(X448KeyExchange {
+            pub_key: crrl::x448::x448_base(&alice_priv),
+            priv_key: alice_priv,
+        });
+        assert_eq!(kx.pub_key(), &alice_pub[..]);
+        assert_eq!(
+            kx.complete(&bob_pub)
+                .unwrap()
+                .secret_bytes(),
+            &shared[..]
+        );
+    }
+
+    #[test]
+    fn ed448_rfc8032_test_vector() {
+        // "1 octet" from <https://datatracker.ietf.org/doc/html/rfc8032#section-7.4>
+        let public_key = hex::<57>(
+            "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c0866aea01eb00742802b8438ea4cb82169c235160627b4c3a9480",
+        );
+        let signature = hex::<114>(
+            "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f4352541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cbcee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0ff3348ab21aa4adafd1d234441cf807c03a00",
+        );
+
+        assert!(
+            Ed448Verify
+                .verify_signature(&public_key, &[0x03], &signature)
+                .is_ok()
+        );
+        assert!(
+            Ed448Verify
+                .verify_signature(&public_key, &[0x04], &signature)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn can_sign_ed448() {
+        let key = PrivatePkcs8KeyDer::from(&include_bytes!("../testdata/ed448key.der")[..]);
+
+        let k = Ed448SigningKey::new(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "Ed448SigningKey { algorithm: ED448 }");
+        assert_eq!(k.algorithm(), SignatureAlgorithm::ED448);
+
+        assert!(
+            k.choose_scheme(&[SignatureScheme::ED25519])
+                .is_none()
+        );
+        let s = k
+            .choose_scheme(&[SignatureScheme::ED448])
+            .unwrap();
+        assert_eq!(format!("{s:?}"), "Ed448Signer { scheme: ED448 }");
+        assert_eq!(s.scheme(), SignatureScheme::ED448);
+        let sig = s.sign(b"hello").unwrap();
+        assert_eq!(sig.len(), 114);
+
+        assert!(
+            Ed448Verify
+                .verify_signature(&k.key.public_key.encode(), b"hello", &sig)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn ed448_pkcs8_v2() {
+        let v1 = include_bytes!("../testdata/ed448key.der");
+        let public_key = Ed448SigningKey::new(&PrivatePkcs8KeyDer::from(&v1[..]))
+            .unwrap()
+            .key
+            .public_key
+            .encode();
+
+        for (public_key, ok) in [(public_key, true), ([0u8; 57], false)] {
+            // version 1, the same private key, and a `[1] publicKey`.
+            let mut v2 = Vec::from(&v1[2..]);
+            v2[2] = 1;
+            v2.extend_from_slice(&[PUBLIC_KEY, 58, 0]);
+            v2.extend_from_slice(&public_key);
+            let v2 = [&[SEQUENCE, 0x81, v2.len() as u8][..], &v2].concat();
+
+            assert_eq!(
+                Ed448SigningKey::new(&PrivatePkcs8KeyDer::from(v2)).is_ok(),
+                ok
+            );
+        }
+    }
+
+    #[test]
+    fn ed448_rejects_other_keys() {
+        for der in [
+            &include_bytes!("../testdata/eddsakey.der")[..],
+            &include_bytes!("../testdata/nistp256key.pkcs8.der")[..],
+        ] {
+            assert!(Ed448SigningKey::new(&PrivatePkcs8KeyDer::from(der)).is_err());
+        }
+    }
+
+    fn hex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut out = [0u8; N];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        out
+    }
+}
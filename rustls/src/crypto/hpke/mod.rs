@@ -8,6 +8,12 @@ use crate::Error;
 pub use crate::msgs::enums::{HpkeAead, HpkeKdf, HpkeKem};
 pub use crate::msgs::handshake::HpkeSymmetricCipherSuite;
 
+/// The [RFC 9180] key schedule shared by the built-in providers.
+///
+/// [RFC 9180]: https://www.rfc-editor.org/rfc/rfc9180.html
+#[cfg(any(feature = "aws-lc-rs", feature = "ring-hpke", feature = "rustcrypto"))]
+pub(crate) mod schedule;
+
 /// An HPKE suite, specifying a key encapsulation mechanism and a symmetric cipher suite.
 #[allow(clippy::exhaustive_structs)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -96,6 +102,19 @@ pub trait HpkeSealer: Debug + Send + Sync + 'static {
     /// Seal the provided `plaintext` with additional data `aad`, returning
     /// ciphertext.
     fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Return the KEM shared secret this context was set up with, if available.
+    ///
+    /// This is used to log the `ECH_SECRET` value through [`KeyLog`], so that
+    /// an encrypted client hello can be decrypted by external tools.  It is only
+    /// called if the configured `KeyLog` wants that value.
+    ///
+    /// The default implementation returns `None`, in which case nothing is logged.
+    ///
+    /// [`KeyLog`]: crate::KeyLog
+    fn shared_secret(&self) -> Option<&[u8]> {
+        None
+    }
 }
 
 /// An HPKE opener context.
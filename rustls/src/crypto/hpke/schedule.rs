@@ -0,0 +1,272 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use zeroize::Zeroize;
+
+use super::HpkeSuite;
+use crate::Error;
+use crate::crypto::cipher::NONCE_LEN;
+use crate::crypto::tls13::{HkdfExpander, HkdfPrkExtract, expand};
+use crate::msgs::enums::HpkeKem;
+
+/// KeySchedule holds the derived AEAD key, base nonce, and seq number
+/// common to both the sending and receiving context of a suite.
+///
+/// This is the provider-independent half of an HPKE suite: everything here is
+/// computed with the suite's [`HkdfPrkExtract`], leaving only the KEM and the AEAD
+/// to the provider.
+pub(crate) struct KeySchedule<const KEY_SIZE: usize> {
+    key: AeadKey<KEY_SIZE>,
+    base_nonce: [u8; NONCE_LEN],
+    seq_num: u32,
+}
+
+impl<const KEY_SIZE: usize> KeySchedule<KEY_SIZE> {
+    /// See [RFC 9180 §5.1 "Creating the Encryption Context"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1
+    pub(crate) fn new<const KDF_SIZE: usize>(
+        suite: HpkeSuite,
+        hkdf: &'static dyn HkdfPrkExtract,
+        shared_secret: &KemSharedSecret<KDF_SIZE>,
+        info: &[u8],
+    ) -> Self {
+        // Note: we use an empty IKM for the `psk_id_hash` and `secret` labelled extractions because
+        // there is no PSK ID in base mode HPKE.
+
+        let suite_id = LabeledSuiteId::Hpke(suite);
+        let psk_id_hash = labeled_extract_for_prk(hkdf, suite_id, None, Label::PskIdHash, &[]);
+        let info_hash = labeled_extract_for_prk(hkdf, suite_id, None, Label::InfoHash, info);
+        let key_schedule_context = [
+            &[0][..], // base mode (0x00)
+            &psk_id_hash,
+            &info_hash,
+        ]
+        .concat();
+
+        let secret = || {
+            labeled_extract_for_expand(hkdf, suite_id, Some(&shared_secret.0), Label::Secret, &[])
+        };
+
+        Self {
+            key: AeadKey(labeled_expand(
+                suite_id,
+                secret(),
+                Label::Key,
+                &key_schedule_context,
+            )),
+            base_nonce: labeled_expand(suite_id, secret(), Label::BaseNonce, &key_schedule_context),
+            seq_num: 0,
+        }
+    }
+
+    /// The derived AEAD key.
+    pub(crate) fn key(&self) -> &[u8; KEY_SIZE] {
+        &self.key.0
+    }
+
+    /// Return the nonce for the current sequence number, and advance the sequence number.
+    pub(crate) fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error> {
+        let nonce = self.compute_nonce();
+        self.increment_seq_num()?;
+        Ok(nonce)
+    }
+
+    /// See [RFC 9180 §5.2 "Encryption and Decryption"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.2
+    pub(crate) fn compute_nonce(&self) -> [u8; NONCE_LEN] {
+        // def Context<ROLE>.ComputeNonce(seq):
+        //   seq_bytes = I2OSP(seq, Nn)
+        //   return xor(self.base_nonce, seq_bytes)
+
+        // Each new N-byte nonce is conceptually two parts:
+        //   * N-4 bytes of the base nonce (0s in `nonce` to XOR in as-is).
+        //   * 4 bytes derived from the sequence number XOR the base nonce.
+        let mut nonce = [0; NONCE_LEN];
+        let seq_bytes = self.seq_num.to_be_bytes();
+        nonce[NONCE_LEN - seq_bytes.len()..].copy_from_slice(&seq_bytes);
+
+        for (n, &b) in nonce.iter_mut().zip(&self.base_nonce) {
+            *n ^= b;
+        }
+
+        nonce
+    }
+
+    /// See [RFC 9180 §5.2 "Encryption and Decryption"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.2
+    pub(crate) fn increment_seq_num(&mut self) -> Result<(), Error> {
+        // def Context<ROLE>.IncrementSeq():
+        //   if self.seq >= (1 << (8*Nn)) - 1:
+        //     raise MessageLimitReachedError
+        //   self.seq += 1
+
+        // Our sequence number is narrower than the nonce, so it runs out first.
+        self.seq_num = self
+            .seq_num
+            .checked_add(1)
+            .ok_or_else(|| Error::General("HPKE message limit reached".into()))?;
+        Ok(())
+    }
+}
+
+/// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+pub(crate) fn extract_and_expand<const KDF_SIZE: usize>(
+    kem: HpkeKem,
+    hkdf: &'static dyn HkdfPrkExtract,
+    dh: &[u8],
+    kem_context: &[u8],
+) -> KemSharedSecret<KDF_SIZE> {
+    // def ExtractAndExpand(dh, kem_context):
+    //   eae_prk = LabeledExtract("", "eae_prk", dh)
+    //   shared_secret = LabeledExpand(eae_prk, "shared_secret",
+    //                                 kem_context, Nsecret)
+    //   return shared_secret
+
+    let suite_id = LabeledSuiteId::Kem(kem);
+    KemSharedSecret(labeled_expand(
+        suite_id,
+        labeled_extract_for_expand(hkdf, suite_id, None, Label::EaePrk, dh),
+        Label::SharedSecret,
+        kem_context,
+    ))
+}
+
+/// See [RFC 9180 §4 "Cryptographic Dependencies"][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4
+fn labeled_extract_for_expand(
+    hkdf: &'static dyn HkdfPrkExtract,
+    suite_id: LabeledSuiteId,
+    salt: Option<&[u8]>,
+    label: Label,
+    ikm: &[u8],
+) -> Box<dyn HkdfExpander> {
+    // def LabeledExtract(salt, label, ikm):
+    //   labeled_ikm = concat("HPKE-v1", suite_id, label, ikm)
+    //   return Extract(salt, labeled_ikm)
+
+    let labeled_ikm = [&b"HPKE-v1"[..], &suite_id.encoded(), label.as_ref(), ikm].concat();
+    hkdf.extract_from_secret(salt, &labeled_ikm)
+}
+
+/// See [RFC 9180 §4 "Cryptographic Dependencies"][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4
+fn labeled_extract_for_prk(
+    hkdf: &'static dyn HkdfPrkExtract,
+    suite_id: LabeledSuiteId,
+    salt: Option<&[u8]>,
+    label: Label,
+    ikm: &[u8],
+) -> Vec<u8> {
+    // def LabeledExtract(salt, label, ikm):
+    //   labeled_ikm = concat("HPKE-v1", suite_id, label, ikm)
+    //   return Extract(salt, labeled_ikm)
+
+    let labeled_ikm = [&b"HPKE-v1"[..], &suite_id.encoded(), label.as_ref(), ikm].concat();
+    hkdf.extract_prk_from_secret(salt, &labeled_ikm)
+}
+
+/// See [RFC 9180 §4 "Cryptographic Dependencies"][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4
+fn labeled_expand<const L: usize>(
+    suite_id: LabeledSuiteId,
+    expander: Box<dyn HkdfExpander>,
+    label: Label,
+    kem_context: &[u8],
+) -> [u8; L] {
+    // def LabeledExpand(prk, label, info, L):
+    //   labeled_info = concat(I2OSP(L, 2), "HPKE-v1", suite_id,
+    //                         label, info)
+    //   return Expand(prk, labeled_info, L)
+
+    let output_len = u16::to_be_bytes(L as u16);
+    let info = &[
+        &output_len[..],
+        b"HPKE-v1",
+        &suite_id.encoded(),
+        label.as_ref(),
+        kem_context,
+    ];
+
+    expand(&*expander, info)
+}
+
+/// Label describes the possible labels for use with [labeled_extract_for_expand] and [labeled_expand].
+#[derive(Debug)]
+enum Label {
+    PskIdHash,
+    InfoHash,
+    Secret,
+    Key,
+    BaseNonce,
+    EaePrk,
+    SharedSecret,
+}
+
+impl AsRef<[u8]> for Label {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Self::PskIdHash => b"psk_id_hash",
+            Self::InfoHash => b"info_hash",
+            Self::Secret => b"secret",
+            Self::Key => b"key",
+            Self::BaseNonce => b"base_nonce",
+            Self::EaePrk => b"eae_prk",
+            Self::SharedSecret => b"shared_secret",
+        }
+    }
+}
+
+/// LabeledSuiteId describes the possible suite ID values for use with [labeled_extract_for_expand] and
+/// [labeled_expand].
+#[derive(Debug, Copy, Clone)]
+enum LabeledSuiteId {
+    Hpke(HpkeSuite),
+    Kem(HpkeKem),
+}
+
+impl LabeledSuiteId {
+    /// The suite ID encoding depends on the context of use. In the general HPKE context,
+    /// we use a "HPKE" prefix and encode the entire ciphersuite. In the KEM context we use a
+    /// "KEM" prefix and only encode the KEM ID.
+    ///
+    /// See the bottom of [RFC 9180 §4](https://www.rfc-editor.org/rfc/rfc9180.html#section-4)
+    /// for more information.
+    fn encoded(&self) -> Vec<u8> {
+        match self {
+            Self::Hpke(suite) => [
+                &b"HPKE"[..],
+                &u16::from(suite.kem).to_be_bytes(),
+                &u16::from(suite.sym.kdf_id).to_be_bytes(),
+                &u16::from(suite.sym.aead_id).to_be_bytes(),
+            ]
+            .concat(),
+            Self::Kem(kem) => [&b"KEM"[..], &u16::from(*kem).to_be_bytes()].concat(),
+        }
+    }
+}
+
+/// A newtype wrapper for an unbound AEAD key.
+struct AeadKey<const KEY_LEN: usize>([u8; KEY_LEN]);
+
+impl<const KEY_LEN: usize> Drop for AeadKey<KEY_LEN> {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+/// A newtype wrapper for a DH KEM shared secret.
+pub(crate) struct KemSharedSecret<const KDF_LEN: usize>(pub(crate) [u8; KDF_LEN]);
+
+impl<const KDF_LEN: usize> Drop for KemSharedSecret<KDF_LEN> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
@@ -29,6 +29,14 @@ pub mod ring;
 #[cfg(feature = "aws-lc-rs")]
 pub mod aws_lc_rs;
 
+/// Pure-Rust CryptoProvider, using the RustCrypto crates.
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
+
+/// X448 and Ed448, shared by the built-in providers.
+#[cfg(feature = "curve448")]
+pub(crate) mod curve448;
+
 /// TLS message encryption/decryption interfaces.
 pub mod cipher;
 
@@ -47,17 +55,25 @@ pub mod tls13;
 /// Hybrid public key encryption (RFC 9180).
 pub mod hpke;
 
+/// Post-quantum key exchange shared by the built-in providers.
+#[cfg(any(feature = "aws-lc-rs", feature = "ring-mlkem", feature = "rustcrypto"))]
+pub(crate) mod pq;
+
 // Message signing interfaces. Re-exported under rustls::sign. Kept crate-internal here to
 // avoid having two import paths to the same types.
 pub(crate) mod signer;
 
+// Reloadable certificate resolver.  Re-exported under rustls::sign.
+#[cfg(feature = "std")]
+pub(crate) mod reloading;
+
 pub use crate::msgs::handshake::KeyExchangeAlgorithm;
 pub use crate::rand::GetRandomFailed;
 pub use crate::suites::CipherSuiteCommon;
 
 /// Controls core cryptography used by rustls.
 ///
-/// This crate comes with two built-in options, provided as
+/// This crate comes with three built-in options, provided as
 /// `CryptoProvider` structures:
 ///
 /// - [`crypto::aws_lc_rs::default_provider`]: (behind the `aws_lc_rs` crate feature).
@@ -65,6 +81,9 @@ pub use crate::suites::CipherSuiteCommon;
 ///   crate.  The `fips` crate feature makes this option use FIPS140-3-approved cryptography.
 /// - [`crypto::ring::default_provider`]: (behind the `ring` crate feature).
 ///   This provider uses the [*ring*](https://github.com/briansmith/ring) crate.
+/// - [`crypto::rustcrypto::default_provider`]: (behind the `rustcrypto` crate feature).
+///   This provider is written entirely in Rust, using the [RustCrypto](https://github.com/RustCrypto)
+///   crates.  It is suitable for targets which cannot build C or assembly.
 ///
 /// This structure provides defaults. Everything in it can be overridden at
 /// runtime by replacing field values as needed.
@@ -247,7 +266,7 @@ impl CryptoProvider {
         let provider = Self::from_crate_features()
             .expect(r###"
 Could not automatically determine the process-level CryptoProvider from Rustls crate features.
-Call CryptoProvider::install_default() before this point to select a provider manually, or make sure exactly one of the 'aws-lc-rs' and 'ring' features is enabled.
+Call CryptoProvider::install_default() before this point to select a provider manually, or make sure exactly one of the 'aws-lc-rs', 'ring' and 'rustcrypto' features is enabled.
 See the documentation of the CryptoProvider type for more information.
             "###);
         // Ignore the error resulting from us losing a race, and accept the outcome.
@@ -265,6 +284,7 @@ See the documentation of the CryptoProvider type for more information.
         #[cfg(all(
             feature = "ring",
             not(feature = "aws-lc-rs"),
+            not(feature = "rustcrypto"),
             not(feature = "custom-provider")
         ))]
         {
@@ -274,12 +294,23 @@ See the documentation of the CryptoProvider type for more information.
         #[cfg(all(
             feature = "aws-lc-rs",
             not(feature = "ring"),
+            not(feature = "rustcrypto"),
             not(feature = "custom-provider")
         ))]
         {
             return Some(aws_lc_rs::default_provider());
         }
 
+        #[cfg(all(
+            feature = "rustcrypto",
+            not(feature = "aws-lc-rs"),
+            not(feature = "ring"),
+            not(feature = "custom-provider")
+        ))]
+        {
+            return Some(rustcrypto::default_provider());
+        }
+
         #[allow(unreachable_code)]
         None
     }
@@ -0,0 +1,134 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore};
+use rand_core::OsRng;
+
+use crate::crypto::pq::INVALID_KEY_SHARE;
+use crate::crypto::{ActiveKeyExchange, CompletedKeyExchange, SharedSecret, SupportedKxGroup};
+use crate::{Error, NamedGroup};
+
+type EncapsulationKey = <ml_kem::MlKem768 as KemCore>::EncapsulationKey;
+type DecapsulationKey = <ml_kem::MlKem768 as KemCore>::DecapsulationKey;
+
+#[derive(Debug)]
+pub(crate) struct MlKem768;
+
+impl SupportedKxGroup for MlKem768 {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let (decaps_key, encaps_key) = ml_kem::MlKem768::generate(&mut OsRng);
+
+        Ok(Box::new(Active {
+            decaps_key: Box::new(decaps_key),
+            encaps_key_bytes: encaps_key.as_bytes().to_vec(),
+        }))
+    }
+
+    fn start_and_complete(&self, client_share: &[u8]) -> Result<CompletedKeyExchange, Error> {
+        let encoded =
+            Encoded::<EncapsulationKey>::try_from(client_share).map_err(|_| INVALID_KEY_SHARE)?;
+        let encaps_key = EncapsulationKey::from_bytes(&encoded);
+
+        // FIPS 203 section 7.2 "modulus check": the encapsulation key must
+        // survive a decode/encode round trip unchanged.
+        if encaps_key.as_bytes() != encoded {
+            return Err(INVALID_KEY_SHARE);
+        }
+
+        let (ciphertext, shared_secret) = encaps_key
+            .encapsulate(&mut OsRng)
+            .map_err(|_| INVALID_KEY_SHARE)?;
+
+        Ok(CompletedKeyExchange {
+            group: self.name(),
+            pub_key: Vec::from(&ciphertext[..]),
+            secret: SharedSecret::from(&shared_secret[..]),
+        })
+    }
+
+    fn name(&self) -> NamedGroup {
+        NamedGroup::MLKEM768
+    }
+
+    fn fips(&self) -> bool {
+        // The `ml-kem` crate is not FIPS-validated.
+        false
+    }
+}
+
+struct Active {
+    decaps_key: Box<DecapsulationKey>,
+    encaps_key_bytes: Vec<u8>,
+}
+
+impl ActiveKeyExchange for Active {
+    // The received 'peer_pub_key' is actually the ML-KEM ciphertext,
+    // which when decapsulated with our `decaps_key` produces the shared
+    // secret.
+    fn complete(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+        let ciphertext = Ciphertext::<ml_kem::MlKem768>::try_from(peer_pub_key)
+            .map_err(|_| INVALID_KEY_SHARE)?;
+
+        let shared_secret = self
+            .decaps_key
+            .decapsulate(&ciphertext)
+            .map_err(|_| INVALID_KEY_SHARE)?;
+
+        Ok(SharedSecret::from(&shared_secret[..]))
+    }
+
+    fn pub_key(&self) -> &[u8] {
+        &self.encaps_key_bytes
+    }
+
+    fn group(&self) -> NamedGroup {
+        NamedGroup::MLKEM768
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let client = MlKem768.start().unwrap();
+        let server = MlKem768
+            .start_and_complete(client.pub_key())
+            .unwrap();
+        assert_eq!(server.pub_key.len(), super::super::MLKEM768_CIPHERTEXT_LEN);
+        assert_eq!(
+            client
+                .complete(&server.pub_key)
+                .unwrap()
+                .secret_bytes(),
+            server.secret.secret_bytes()
+        );
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        assert!(
+            MlKem768
+                .start_and_complete(&[0u8; 100])
+                .is_err()
+        );
+        let client = MlKem768.start().unwrap();
+        assert!(client.complete(&[0u8; 100]).is_err());
+    }
+
+    #[test]
+    fn rejects_unreduced_encapsulation_key() {
+        // Every coefficient 0xfff is >= q, so fails the modulus check.
+        let client_share = vec![0xffu8; super::super::MLKEM768_ENCAP_LEN];
+        assert_eq!(
+            MlKem768
+                .start_and_complete(&client_share)
+                .err(),
+            Some(INVALID_KEY_SHARE)
+        );
+    }
+}
@@ -0,0 +1,13 @@
+use crate::{Error, PeerMisbehaved};
+
+/// Hybrid key exchange, combining a classical and a post-quantum group.
+pub(crate) mod hybrid;
+/// ML-KEM using the pure-Rust `ml-kem` crate.
+#[cfg(any(feature = "ring-mlkem", feature = "rustcrypto"))]
+pub(crate) mod mlkem;
+
+pub(crate) const INVALID_KEY_SHARE: Error = Error::PeerMisbehaved(PeerMisbehaved::InvalidKeyShare);
+
+pub(crate) const X25519_LEN: usize = 32;
+pub(crate) const MLKEM768_CIPHERTEXT_LEN: usize = 1088;
+pub(crate) const MLKEM768_ENCAP_LEN: usize = 1184;
@@ -0,0 +1,196 @@
+use alloc::format;
+use core::time::Duration;
+use std::path::{Path, PathBuf};
+
+use pki_types::pem::PemObject;
+use pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+
+use super::CryptoProvider;
+use super::signer::CertifiedKey;
+use crate::client::ResolvesClientCert;
+use crate::enums::SignatureScheme;
+use crate::error::Error;
+use crate::lock::Mutex;
+use crate::log::{debug, warn};
+use crate::server::{ClientHello, ResolvesServerCert};
+use crate::sync::Arc;
+use crate::time_provider::TimeProvider;
+
+/// A certificate chain and private key loaded from PEM files, which can be
+/// reloaded while in use.
+///
+/// This can be used as both a [`ResolvesServerCert`] and a [`ResolvesClientCert`].
+/// It always resolves to the most recently loaded certificate chain and key.
+///
+/// The private key is loaded with the [`KeyProvider`] of the given [`CryptoProvider`],
+/// and must match the end-entity certificate (see [`CertifiedKey::keys_match()`]).
+/// If a reload fails, for example because the files are mid-way through being
+/// replaced, the previously loaded chain and key remain in use.
+///
+/// Files are reloaded by calling [`PemFileCertAndKey::reload()`], or
+/// periodically if configured with [`PemFileCertAndKey::with_reload_interval()`].
+///
+/// [`KeyProvider`]: crate::crypto::KeyProvider
+#[derive(Debug)]
+pub struct PemFileCertAndKey {
+    cert_chain_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    interval: Option<(Duration, Arc<dyn TimeProvider>)>,
+    state: Mutex<State>,
+}
+
+impl PemFileCertAndKey {
+    /// Load a certificate chain and private key from PEM files.
+    ///
+    /// `cert_chain_path` should contain the end-entity certificate, followed by
+    /// any intermediate certificates.  `key_path` should contain the private key,
+    /// in PKCS#1, PKCS#8 or SEC1 format.
+    ///
+    /// This fails if either file cannot be loaded, or the key does not match
+    /// the end-entity certificate.
+    pub fn new(
+        cert_chain_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, Error> {
+        let cert_chain_path = cert_chain_path.into();
+        let key_path = key_path.into();
+        let current = load(&cert_chain_path, &key_path, &provider)?;
+
+        Ok(Self {
+            cert_chain_path,
+            key_path,
+            provider,
+            interval: None,
+            state: Mutex::new(State {
+                current: Arc::new(current),
+                last_loaded: None,
+            }),
+        })
+    }
+
+    /// Reload the files when resolving, if `interval` has passed since they
+    /// were last loaded.
+    ///
+    /// `time_provider` gives the current time.  Reloading happens synchronously,
+    /// in the handshake that notices the interval has passed.
+    pub fn with_reload_interval(
+        mut self,
+        interval: Duration,
+        time_provider: Arc<dyn TimeProvider>,
+    ) -> Self {
+        if let Some(mut state) = self.state.lock() {
+            state.last_loaded = time_provider.current_time();
+        }
+        self.interval = Some((interval, time_provider));
+        self
+    }
+
+    /// Reload the certificate chain and private key from their files.
+    ///
+    /// On error, the previously loaded chain and key remain in use.
+    pub fn reload(&self) -> Result<(), Error> {
+        let new = load(&self.cert_chain_path, &self.key_path, &self.provider)?;
+        let Some(mut state) = self.state.lock() else {
+            return Err(Error::General("certificate reloader lock poisoned".into()));
+        };
+        state.current = Arc::new(new);
+        debug!("reloaded {:?}", self.cert_chain_path);
+        Ok(())
+    }
+
+    /// The certificate chain and private key currently in use.
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        self.reload_if_due();
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .current
+            .clone()
+    }
+
+    fn reload_if_due(&self) {
+        let Some((interval, time_provider)) = &self.interval else {
+            return;
+        };
+        let Some(now) = time_provider.current_time() else {
+            return;
+        };
+
+        {
+            let Some(mut state) = self.state.lock() else {
+                return;
+            };
+            match state.last_loaded {
+                Some(last) if !is_due(last, now, *interval) => return,
+                // Only one caller should reload: others continue with the current chain and key.
+                _ => state.last_loaded = Some(now),
+            }
+        }
+
+        #[cfg_attr(
+            not(any(feature = "log", feature = "tracing")),
+            allow(unused_variables)
+        )]
+        if let Err(err) = self.reload() {
+            warn!(
+                "failed to reload {:?}, keeping previous certificate: {err}",
+                self.cert_chain_path
+            );
+        }
+    }
+}
+
+impl ResolvesServerCert for PemFileCertAndKey {
+    fn resolve(&self, _client_hello: &ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+impl ResolvesClientCert for PemFileCertAndKey {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    current: Arc<CertifiedKey>,
+    last_loaded: Option<UnixTime>,
+}
+
+fn is_due(last: UnixTime, now: UnixTime, interval: Duration) -> bool {
+    now.as_secs()
+        .saturating_sub(last.as_secs())
+        >= interval.as_secs()
+}
+
+fn load(
+    cert_chain_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, Error> {
+    let cert_chain = CertificateDer::pem_file_iter(cert_chain_path)
+        .and_then(|iter| iter.collect::<Result<_, _>>())
+        .map_err(|err| Error::General(format!("cannot load {cert_chain_path:?}: {err}")))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|err| Error::General(format!("cannot load {key_path:?}: {err}")))?;
+
+    let certified_key = CertifiedKey::new_unchecked(
+        cert_chain,
+        provider
+            .key_provider
+            .load_private_key(key)?,
+    );
+    certified_key.keys_match()?;
+    Ok(certified_key)
+}
@@ -0,0 +1,843 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use zeroize::Zeroize;
+
+use super::hmac::HMAC_SHA256;
+use super::ring_like::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use super::ring_like::rand::{SecureRandom, SystemRandom};
+use crate::Error;
+use crate::crypto::hpke::schedule::{KemSharedSecret, KeySchedule, extract_and_expand};
+use crate::crypto::hpke::{
+    EncapsulatedSecret, Hpke, HpkeOpener, HpkePrivateKey, HpkePublicKey, HpkeSealer, HpkeSuite,
+};
+use crate::crypto::tls13::{HkdfPrkExtract, HkdfUsingHmac};
+use crate::msgs::enums::{HpkeAead, HpkeKdf, HpkeKem};
+use crate::msgs::handshake::HpkeSymmetricCipherSuite;
+
+/// Default [RFC 9180] Hybrid Public Key Encryption (HPKE) suites supported by *ring* cryptography.
+pub static ALL_SUPPORTED_SUITES: &[&dyn Hpke] = &[
+    DH_KEM_P256_HKDF_SHA256_AES_128,
+    DH_KEM_P256_HKDF_SHA256_AES_256,
+    DH_KEM_P256_HKDF_SHA256_CHACHA20_POLY1305,
+    DH_KEM_X25519_HKDF_SHA256_AES_128,
+    DH_KEM_X25519_HKDF_SHA256_AES_256,
+    DH_KEM_X25519_HKDF_SHA256_CHACHA20_POLY1305,
+];
+
+/// HPKE suite using ECDH P-256 for agreement, HKDF SHA-256 for key derivation, and AEAD AES-128-GCM
+/// for symmetric encryption.
+pub static DH_KEM_P256_HKDF_SHA256_AES_128: &HpkeRing<AES_128_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRing {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P256_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_128_GCM,
+            },
+        },
+        dh_kem: DH_KEM_P256_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: &aead::AES_128_GCM,
+    };
+
+/// HPKE suite using ECDH P-256 for agreement, HKDF SHA-256 for key derivation and AEAD AES-256-GCM
+/// for symmetric encryption.
+pub static DH_KEM_P256_HKDF_SHA256_AES_256: &HpkeRing<AES_256_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRing {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P256_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_256_GCM,
+            },
+        },
+        dh_kem: DH_KEM_P256_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: &aead::AES_256_GCM,
+    };
+
+/// HPKE suite using ECDH P-256 for agreement, HKDF SHA-256 for key derivation, and AEAD
+/// CHACHA20-POLY-1305 for symmetric encryption.
+pub static DH_KEM_P256_HKDF_SHA256_CHACHA20_POLY1305: &HpkeRing<CHACHA_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRing {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P256_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::CHACHA20_POLY_1305,
+            },
+        },
+        dh_kem: DH_KEM_P256_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: &aead::CHACHA20_POLY1305,
+    };
+
+/// HPKE suite using ECDH X25519 for agreement, HKDF SHA-256 for key derivation, and AEAD AES-128-GCM
+/// for symmetric encryption.
+pub static DH_KEM_X25519_HKDF_SHA256_AES_128: &HpkeRing<AES_128_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRing {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_128_GCM,
+            },
+        },
+        dh_kem: DH_KEM_X25519_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: &aead::AES_128_GCM,
+    };
+
+/// HPKE suite using ECDH X25519 for agreement, HKDF SHA-256 for key derivation, and AEAD AES-256-GCM
+/// for symmetric encryption.
+pub static DH_KEM_X25519_HKDF_SHA256_AES_256: &HpkeRing<AES_256_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRing {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_256_GCM,
+            },
+        },
+        dh_kem: DH_KEM_X25519_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: &aead::AES_256_GCM,
+    };
+
+/// HPKE suite using ECDH X25519 for agreement, HKDF SHA-256 for key derivation, and AEAD
+/// CHACHA20-POLY-1305 for symmetric encryption.
+pub static DH_KEM_X25519_HKDF_SHA256_CHACHA20_POLY1305: &HpkeRing<
+    CHACHA_KEY_LEN,
+    SHA256_OUTPUT_LEN,
+> = &HpkeRing {
+    suite: HpkeSuite {
+        kem: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+        sym: HpkeSymmetricCipherSuite {
+            kdf_id: HpkeKdf::HKDF_SHA256,
+            aead_id: HpkeAead::CHACHA20_POLY_1305,
+        },
+    },
+    dh_kem: DH_KEM_X25519_HKDF_SHA256,
+    hkdf: HKDF_HMAC_SHA256,
+    aead: &aead::CHACHA20_POLY1305,
+};
+
+/// `HpkeRing` holds the concrete instantiations of the algorithms specified by the [HpkeSuite].
+pub struct HpkeRing<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    suite: HpkeSuite,
+    dh_kem: &'static DhKem<KDF_SIZE>,
+    hkdf: &'static dyn HkdfPrkExtract,
+    aead: &'static aead::Algorithm,
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeRing<KEY_SIZE, KDF_SIZE> {
+    /// See [RFC 9180 §5.1 "Creating the Encryption Context"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1
+    fn key_schedule(
+        &self,
+        shared_secret: &KemSharedSecret<KDF_SIZE>,
+        info: &[u8],
+    ) -> KeySchedule<KEY_SIZE> {
+        KeySchedule::new(self.suite, self.hkdf, shared_secret, info)
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Hpke for HpkeRing<KEY_SIZE, KDF_SIZE> {
+    fn seal(
+        &self,
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Vec<u8>), Error> {
+        let (encap, mut sealer) = self.setup_sealer(info, pub_key)?;
+        Ok((encap, sealer.seal(aad, plaintext)?))
+    }
+
+    fn setup_sealer(
+        &self,
+        info: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Box<dyn HpkeSealer + 'static>), Error> {
+        let (encap, sealer) = Sealer::new(self, info, pub_key)?;
+        Ok((encap, Box::new(sealer)))
+    }
+
+    fn open(
+        &self,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Vec<u8>, Error> {
+        self.setup_opener(enc, info, secret_key)?
+            .open(aad, ciphertext)
+    }
+
+    fn setup_opener(
+        &self,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Box<dyn HpkeOpener + 'static>, Error> {
+        Ok(Box::new(Opener::new(self, enc, info, secret_key)?))
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+
+    fn generate_key_pair(&self) -> Result<(HpkePublicKey, HpkePrivateKey), Error> {
+        self.dh_kem
+            .algorithm
+            .generate_key_pair()
+    }
+
+    fn suite(&self) -> HpkeSuite {
+        self.suite
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for HpkeRing<KEY_SIZE, KDF_SIZE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.suite.fmt(f)
+    }
+}
+
+/// Adapts a [KeySchedule] and AEAD algorithm for the role of a [HpkeSealer].
+struct Sealer<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    aead: &'static aead::Algorithm,
+    key_schedule: KeySchedule<KEY_SIZE>,
+    shared_secret: KemSharedSecret<KDF_SIZE>,
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Sealer<KEY_SIZE, KDF_SIZE> {
+    /// See [RFC 9180 §5.1.1 "Encryption to a Public Key"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1.1
+    fn new(
+        suite: &HpkeRing<KEY_SIZE, KDF_SIZE>,
+        info: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Self), Error> {
+        // def SetupBaseS(pkR, info):
+        //   shared_secret, enc = Encap(pkR)
+        //   return enc, KeyScheduleS(mode_base, shared_secret, info,
+        //                            default_psk, default_psk_id)
+
+        let (shared_secret, enc) = suite.dh_kem.encap(pub_key)?;
+        let key_schedule = suite.key_schedule(&shared_secret, info);
+        Ok((
+            enc,
+            Self {
+                aead: suite.aead,
+                key_schedule,
+                shared_secret,
+            },
+        ))
+    }
+
+    /// A **test only** constructor that uses a pre-specified ephemeral agreement private key
+    /// instead of one that is randomly generated.
+    #[cfg(test)]
+    fn test_only_new(
+        suite: &HpkeRing<KEY_SIZE, KDF_SIZE>,
+        info: &[u8],
+        pub_key: &HpkePublicKey,
+        sk_e: &[u8],
+    ) -> Result<(EncapsulatedSecret, Self), Error> {
+        let (shared_secret, enc) = suite
+            .dh_kem
+            .test_only_encap(pub_key, sk_e)?;
+        let key_schedule = suite.key_schedule(&shared_secret, info);
+        Ok((
+            enc,
+            Self {
+                aead: suite.aead,
+                key_schedule,
+                shared_secret,
+            },
+        ))
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeSealer for Sealer<KEY_SIZE, KDF_SIZE> {
+    fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        // def ContextS.Seal(aad, pt):
+        //   ct = Seal(self.key, self.ComputeNonce(self.seq), aad, pt)
+        //   self.IncrementSeq()
+        //   return ct
+
+        let key = aead_key(self.aead, &self.key_schedule);
+        let nonce = self.key_schedule.next_nonce()?;
+
+        let mut in_out_buffer = Vec::from(plaintext);
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut in_out_buffer,
+        )
+        .map_err(|_| Error::EncryptError)?;
+
+        Ok(in_out_buffer)
+    }
+
+    fn shared_secret(&self) -> Option<&[u8]> {
+        Some(&self.shared_secret.0)
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for Sealer<KEY_SIZE, KDF_SIZE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sealer").finish()
+    }
+}
+
+/// Adapts a [KeySchedule] and AEAD algorithm for the role of a [HpkeOpener].
+struct Opener<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    aead: &'static aead::Algorithm,
+    key_schedule: KeySchedule<KEY_SIZE>,
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Opener<KEY_SIZE, KDF_SIZE> {
+    /// See [RFC 9180 §5.1.1 "Encryption to a Public Key"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1.1
+    fn new(
+        suite: &HpkeRing<KEY_SIZE, KDF_SIZE>,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Self, Error> {
+        // def SetupBaseR(enc, skR, info):
+        //   shared_secret = Decap(enc, skR)
+        //   return KeyScheduleR(mode_base, shared_secret, info,
+        //                       default_psk, default_psk_id)
+        Ok(Self {
+            aead: suite.aead,
+            key_schedule: suite.key_schedule(&suite.dh_kem.decap(enc, secret_key)?, info),
+        })
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeOpener for Opener<KEY_SIZE, KDF_SIZE> {
+    fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        // def ContextR.Open(aad, ct):
+        //   pt = Open(self.key, self.ComputeNonce(self.seq), aad, ct)
+        //   if pt == OpenError:
+        //     raise OpenError
+        //   self.IncrementSeq()
+        //   return pt
+
+        let key = aead_key(self.aead, &self.key_schedule);
+        let nonce = self.key_schedule.compute_nonce();
+
+        let mut in_out_buffer = Vec::from(ciphertext);
+        let plaintext_len = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut in_out_buffer,
+            )
+            .map_err(|_| Error::DecryptError)?
+            .len();
+        self.key_schedule.increment_seq_num()?;
+
+        in_out_buffer.truncate(plaintext_len);
+        Ok(in_out_buffer)
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for Opener<KEY_SIZE, KDF_SIZE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Opener").finish()
+    }
+}
+
+/// A Diffie-Hellman (DH) based Key Encapsulation Mechanism (KEM).
+///
+/// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+struct DhKem<const KDF_SIZE: usize> {
+    id: HpkeKem,
+    algorithm: DhAlgorithm,
+    hkdf: &'static dyn HkdfPrkExtract,
+}
+
+impl<const KDF_SIZE: usize> DhKem<KDF_SIZE> {
+    /// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+    fn encap(
+        &self,
+        recipient: &HpkePublicKey,
+    ) -> Result<(KemSharedSecret<KDF_SIZE>, EncapsulatedSecret), Error> {
+        // def Encap(pkR):
+        //   skE, pkE = GenerateKeyPair()
+
+        let (pk_e, sk_e) = self.algorithm.generate_key_pair()?;
+        self.encap_impl(recipient, &sk_e, pk_e)
+    }
+
+    /// A test-only encap operation that uses a fixed `test_only_ske` instead of generating
+    /// one randomly.
+    #[cfg(test)]
+    fn test_only_encap(
+        &self,
+        recipient: &HpkePublicKey,
+        test_only_ske: &[u8],
+    ) -> Result<(KemSharedSecret<KDF_SIZE>, EncapsulatedSecret), Error> {
+        // For test contexts only, we accept a static sk_e as an argument.
+        let sk_e = HpkePrivateKey::from(test_only_ske.to_vec());
+        let pk_e = HpkePublicKey(
+            self.algorithm
+                .public_key(test_only_ske)?,
+        );
+        self.encap_impl(recipient, &sk_e, pk_e)
+    }
+
+    fn encap_impl(
+        &self,
+        recipient: &HpkePublicKey,
+        sk_e: &HpkePrivateKey,
+        pk_e: HpkePublicKey,
+    ) -> Result<(KemSharedSecret<KDF_SIZE>, EncapsulatedSecret), Error> {
+        // def Encap(pkR):
+        //   skE, pkE = GenerateKeyPair()
+        //   dh = DH(skE, pkR)
+        //   enc = SerializePublicKey(pkE)
+        //
+        //   pkRm = SerializePublicKey(pkR)
+        //   kem_context = concat(enc, pkRm)
+        //
+        //   shared_secret = ExtractAndExpand(dh, kem_context)
+        //   return shared_secret, enc
+
+        let enc = pk_e.0;
+        let kem_context = [&enc[..], &recipient.0].concat();
+
+        let dh = self
+            .algorithm
+            .agree(sk_e.secret_bytes(), &recipient.0)?;
+
+        Ok((
+            self.extract_and_expand(&dh.0, &kem_context),
+            EncapsulatedSecret(enc),
+        ))
+    }
+
+    /// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+    fn decap(
+        &self,
+        enc: &EncapsulatedSecret,
+        recipient: &HpkePrivateKey,
+    ) -> Result<KemSharedSecret<KDF_SIZE>, Error> {
+        // def Decap(enc, skR):
+        //   pkE = DeserializePublicKey(enc)
+        //   dh = DH(skR, pkE)
+        //
+        //   pkRm = SerializePublicKey(pk(skR))
+        //   kem_context = concat(enc, pkRm)
+        //
+        //   shared_secret = ExtractAndExpand(dh, kem_context)
+        //   return shared_secret
+
+        let pk_rm = self
+            .algorithm
+            .public_key(recipient.secret_bytes())?;
+        let kem_context = [&enc.0[..], &pk_rm].concat();
+
+        let dh = self
+            .algorithm
+            .agree(recipient.secret_bytes(), &enc.0)?;
+
+        Ok(self.extract_and_expand(&dh.0, &kem_context))
+    }
+
+    fn extract_and_expand(&self, dh: &[u8], kem_context: &[u8]) -> KemSharedSecret<KDF_SIZE> {
+        extract_and_expand(self.id, self.hkdf, dh, kem_context)
+    }
+}
+
+static DH_KEM_P256_HKDF_SHA256: &DhKem<SHA256_OUTPUT_LEN> = &DhKem {
+    id: HpkeKem::DHKEM_P256_HKDF_SHA256,
+    algorithm: DhAlgorithm::P256,
+    hkdf: HKDF_HMAC_SHA256,
+};
+
+static DH_KEM_X25519_HKDF_SHA256: &DhKem<SHA256_OUTPUT_LEN> = &DhKem {
+    id: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+    algorithm: DhAlgorithm::X25519,
+    hkdf: HKDF_HMAC_SHA256,
+};
+
+/// The Diffie-Hellman function underlying a [DhKem].
+///
+/// *ring* only offers ephemeral agreement keys, but a recipient must be able to load its
+/// private key from bytes.  So the group arithmetic is done by the pure-Rust `x25519-dalek`
+/// and `p256` crates, with *ring* supplying the randomness.
+///
+/// Private keys are the raw fixed-length scalar (big-endian for P-256);
+/// public keys are the `SerializePublicKey` encoding from [RFC 9180 §7.1.1][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-7.1.1
+#[derive(Clone, Copy)]
+enum DhAlgorithm {
+    X25519,
+    P256,
+}
+
+impl DhAlgorithm {
+    fn generate_key_pair(self) -> Result<(HpkePublicKey, HpkePrivateKey), Error> {
+        let rng = SystemRandom::new();
+        let mut private_key = [0u8; 32];
+
+        // Every 32-byte string is a valid X25519 private key.  For P-256 a random
+        // string is outside [1, n) with negligible probability; retry if so.
+        let private_key = loop {
+            rng.fill(&mut private_key)
+                .map_err(|_| Error::FailedToGetRandomBytes)?;
+            let candidate = HpkePrivateKey::from(private_key.to_vec());
+            if let Ok(public_key) = self.public_key(candidate.secret_bytes()) {
+                private_key.zeroize();
+                break (HpkePublicKey(public_key), candidate);
+            }
+        };
+        Ok(private_key)
+    }
+
+    fn public_key(self, private_key: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::X25519 => {
+                let private_key = x25519_private_key(private_key)?;
+                Ok(x25519_dalek::PublicKey::from(&private_key)
+                    .as_bytes()
+                    .to_vec())
+            }
+            Self::P256 => Ok(p256_private_key(private_key)?
+                .public_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec()),
+        }
+    }
+
+    fn agree(self, private_key: &[u8], public_key: &[u8]) -> Result<DhSecret, Error> {
+        match self {
+            Self::X25519 => {
+                let private_key = x25519_private_key(private_key)?;
+                let public_key: [u8; 32] = public_key
+                    .try_into()
+                    .map_err(|_| invalid_public_key())?;
+                let shared = private_key.diffie_hellman(&x25519_dalek::PublicKey::from(public_key));
+
+                // RFC 9180 §7.1.4: "senders and recipients MUST check whether the shared
+                // secret is the all-zero value and abort if so".
+                if !shared.was_contributory() {
+                    return Err(invalid_public_key());
+                }
+                Ok(DhSecret(shared.as_bytes().to_vec()))
+            }
+            Self::P256 => {
+                let private_key = p256_private_key(private_key)?;
+
+                // Only the uncompressed SEC1 encoding is a valid `SerializePublicKey` output.
+                if public_key.len() != 65 || public_key.first() != Some(&0x04) {
+                    return Err(invalid_public_key());
+                }
+                let public_key = p256::PublicKey::from_sec1_bytes(public_key)
+                    .map_err(|_| invalid_public_key())?;
+
+                let shared = p256::ecdh::diffie_hellman(
+                    private_key.to_nonzero_scalar(),
+                    public_key.as_affine(),
+                );
+                Ok(DhSecret(shared.raw_secret_bytes().to_vec()))
+            }
+        }
+    }
+}
+
+fn x25519_private_key(private_key: &[u8]) -> Result<x25519_dalek::StaticSecret, Error> {
+    let private_key: [u8; 32] = private_key
+        .try_into()
+        .map_err(|_| invalid_private_key())?;
+    Ok(x25519_dalek::StaticSecret::from(private_key))
+}
+
+fn p256_private_key(private_key: &[u8]) -> Result<p256::SecretKey, Error> {
+    // `SecretKey::from_slice` pads short inputs, but RFC 9180 requires the
+    // fixed-length `Nsk` encoding.
+    if private_key.len() != 32 {
+        return Err(invalid_private_key());
+    }
+    p256::SecretKey::from_slice(private_key).map_err(|_| invalid_private_key())
+}
+
+fn invalid_private_key() -> Error {
+    Error::General("invalid HPKE private key".into())
+}
+
+fn invalid_public_key() -> Error {
+    Error::General("invalid HPKE public key".into())
+}
+
+/// Binds the key derived by `key_schedule` to the suite's AEAD.
+fn aead_key<const KEY_SIZE: usize>(
+    aead: &'static aead::Algorithm,
+    key_schedule: &KeySchedule<KEY_SIZE>,
+) -> LessSafeKey {
+    // safety: `KEY_SIZE` is the key length of `aead`, so this unwrap is safe.
+    LessSafeKey::new(UnboundKey::new(aead, key_schedule.key()).unwrap())
+}
+
+/// A newtype wrapper for the raw output of a Diffie-Hellman agreement.
+struct DhSecret(Vec<u8>);
+
+impl Drop for DhSecret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+// *ring* does not export its AEAD key lengths, so we copy them here.
+const AES_128_KEY_LEN: usize = 16;
+const AES_256_KEY_LEN: usize = 32;
+const CHACHA_KEY_LEN: usize = 32;
+const SHA256_OUTPUT_LEN: usize = 32;
+
+static HKDF_HMAC_SHA256: &HkdfUsingHmac<'static> = &HkdfUsingHmac(&HMAC_SHA256);
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec};
+
+    use super::*;
+
+    #[test]
+    fn smoke_test() {
+        for suite in ALL_SUPPORTED_SUITES {
+            _ = format!("{suite:?}"); // HpkeRing suites should be Debug.
+
+            // We should be able to generate a random keypair.
+            let (pk, sk) = suite.generate_key_pair().unwrap();
+
+            // Info value corresponds to the first RFC 9180 base mode test vector.
+            let info = &[
+                0x4f, 0x64, 0x65, 0x20, 0x6f, 0x6e, 0x20, 0x61, 0x20, 0x47, 0x72, 0x65, 0x63, 0x69,
+                0x61, 0x6e, 0x20, 0x55, 0x72, 0x6e,
+            ][..];
+
+            // We should be able to set up a sealer.
+            let (enc, mut sealer) = suite.setup_sealer(info, &pk).unwrap();
+
+            _ = format!("{sealer:?}"); // Sealer should be Debug.
+
+            // Setting up a sealer with an invalid public key should fail.
+            let bad_setup_res = suite.setup_sealer(info, &HpkePublicKey(vec![]));
+            assert!(matches!(bad_setup_res.unwrap_err(), Error::General(_)));
+
+            // We should be able to seal some plaintext.
+            let aad = &[0xC0, 0xFF, 0xEE];
+            let pt = &[0xF0, 0x0D];
+            let ct = sealer.seal(aad, pt).unwrap();
+
+            // We should be able to set up an opener.
+            let mut opener = suite
+                .setup_opener(&enc, info, &sk)
+                .unwrap();
+            _ = format!("{opener:?}"); // Opener should be Debug.
+
+            // Setting up an opener with an invalid private key should fail.
+            let bad_key_res = suite.setup_opener(&enc, info, &HpkePrivateKey::from(vec![]));
+            assert!(matches!(bad_key_res.unwrap_err(), Error::General(_)));
+
+            // Opening the plaintext should work with the correct opener and aad.
+            let pt_prime = opener.open(aad, &ct).unwrap();
+            assert_eq!(pt_prime, pt);
+
+            // Opening the plaintext with the correct opener and wrong aad should fail.
+            let open_res = opener.open(&[0x0], &ct);
+            assert_eq!(open_res.unwrap_err(), Error::DecryptError);
+
+            // Opening the plaintext with the wrong opener should fail.
+            let mut sk_rm_prime = sk.secret_bytes().to_vec();
+            sk_rm_prime[10] ^= 0xFF; // Corrupt a byte of the private key.
+            let mut opener_two = suite
+                .setup_opener(&enc, info, &HpkePrivateKey::from(sk_rm_prime))
+                .unwrap();
+            let open_res = opener_two.open(aad, &ct);
+            assert_eq!(open_res.unwrap_err(), Error::DecryptError);
+        }
+    }
+
+    #[test]
+    fn x25519_rejects_low_order_public_key() {
+        let res = DH_KEM_X25519_HKDF_SHA256_AES_128.setup_sealer(b"", &HpkePublicKey(vec![0; 32]));
+        assert!(matches!(res.unwrap_err(), Error::General(_)));
+    }
+
+    #[test]
+    fn p256_rejects_compressed_public_key() {
+        let (pk, _) = DH_KEM_P256_HKDF_SHA256_AES_128
+            .generate_key_pair()
+            .unwrap();
+        let mut compressed = pk.0[..33].to_vec();
+        compressed[0] = 0x02 | (pk.0[64] & 1);
+        let res = DH_KEM_P256_HKDF_SHA256_AES_128.setup_sealer(b"", &HpkePublicKey(compressed));
+        assert!(matches!(res.unwrap_err(), Error::General(_)));
+    }
+}
+
+#[cfg(test)]
+mod rfc_tests {
+    use alloc::string::String;
+    use std::fs::File;
+    use std::println;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    /// Confirm open/seal operations work using the test vectors from [RFC 9180 Appendix A].
+    ///
+    /// [RFC 9180 Appendix A]: https://www.rfc-editor.org/rfc/rfc9180#TestVectors
+    #[test]
+    fn check_test_vectors() {
+        for (idx, vec) in test_vectors().into_iter().enumerate() {
+            let Some(hpke) = vec.applicable() else {
+                println!("skipping inapplicable vector {idx}");
+                continue;
+            };
+
+            println!("testing vector {idx}");
+            let pk_r = HpkePublicKey(hex::decode(vec.pk_rm).unwrap());
+            let sk_r = HpkePrivateKey::from(hex::decode(vec.sk_rm).unwrap());
+            let sk_em = hex::decode(vec.sk_em).unwrap();
+            let info = hex::decode(vec.info).unwrap();
+            let expected_enc = hex::decode(vec.enc).unwrap();
+
+            let (enc, mut sealer) = hpke
+                .setup_test_sealer(&info, &pk_r, &sk_em)
+                .unwrap();
+            assert_eq!(enc.0, expected_enc);
+
+            let mut opener = hpke
+                .setup_opener(&enc, &info, &sk_r)
+                .unwrap();
+
+            for test_encryption in vec.encryptions {
+                let aad = hex::decode(test_encryption.aad).unwrap();
+                let pt = hex::decode(test_encryption.pt).unwrap();
+                let expected_ct = hex::decode(test_encryption.ct).unwrap();
+
+                let ciphertext = sealer.seal(&aad, &pt).unwrap();
+                assert_eq!(ciphertext, expected_ct);
+
+                let plaintext = opener.open(&aad, &ciphertext).unwrap();
+                assert_eq!(plaintext, pt);
+            }
+        }
+    }
+
+    trait TestHpke: Hpke {
+        fn setup_test_sealer(
+            &self,
+            info: &[u8],
+            pub_key: &HpkePublicKey,
+            sk_em: &[u8],
+        ) -> Result<(EncapsulatedSecret, Box<dyn HpkeSealer + 'static>), Error>;
+    }
+
+    impl<const KEY_SIZE: usize, const KDF_SIZE: usize> TestHpke for HpkeRing<KEY_SIZE, KDF_SIZE> {
+        fn setup_test_sealer(
+            &self,
+            info: &[u8],
+            pub_key: &HpkePublicKey,
+            sk_em: &[u8],
+        ) -> Result<(EncapsulatedSecret, Box<dyn HpkeSealer + 'static>), Error> {
+            let (encap, sealer) = Sealer::test_only_new(self, info, pub_key, sk_em)?;
+            Ok((encap, Box::new(sealer)))
+        }
+    }
+
+    static TEST_SUITES: &[&dyn TestHpke] = &[
+        DH_KEM_P256_HKDF_SHA256_AES_128,
+        DH_KEM_P256_HKDF_SHA256_AES_256,
+        DH_KEM_P256_HKDF_SHA256_CHACHA20_POLY1305,
+        DH_KEM_X25519_HKDF_SHA256_AES_128,
+        DH_KEM_X25519_HKDF_SHA256_AES_256,
+        DH_KEM_X25519_HKDF_SHA256_CHACHA20_POLY1305,
+    ];
+
+    #[derive(Deserialize, Debug)]
+    struct TestVector {
+        mode: u8,
+        kem_id: u16,
+        kdf_id: u16,
+        aead_id: u16,
+        info: String,
+        #[serde(rename(deserialize = "pkRm"))]
+        pk_rm: String,
+        #[serde(rename(deserialize = "skRm"))]
+        sk_rm: String,
+        #[serde(rename(deserialize = "skEm"))]
+        sk_em: String,
+        enc: String,
+        encryptions: Vec<TestEncryption>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct TestEncryption {
+        aad: String,
+        pt: String,
+        ct: String,
+    }
+
+    impl TestVector {
+        fn suite(&self) -> HpkeSuite {
+            HpkeSuite {
+                kem: HpkeKem::from(self.kem_id),
+                sym: HpkeSymmetricCipherSuite {
+                    kdf_id: HpkeKdf::from(self.kdf_id),
+                    aead_id: HpkeAead::from(self.aead_id),
+                },
+            }
+        }
+
+        fn applicable(&self) -> Option<&'static dyn TestHpke> {
+            // Only base mode test vectors for supported suites are applicable.
+            if self.mode != 0 {
+                return None;
+            }
+
+            Self::lookup_suite(self.suite(), TEST_SUITES)
+        }
+
+        fn lookup_suite(
+            suite: HpkeSuite,
+            supported: &[&'static dyn TestHpke],
+        ) -> Option<&'static dyn TestHpke> {
+            supported
+                .iter()
+                .find(|s| s.suite() == suite)
+                .copied()
+        }
+    }
+
+    fn test_vectors() -> Vec<TestVector> {
+        serde_json::from_reader(
+            &mut File::open("../rustls-provider-test/tests/rfc-9180-test-vectors.json")
+                .expect("failed to open test vectors data file"),
+        )
+        .expect("failed to deserialize test vectors")
+    }
+}
@@ -11,12 +11,18 @@ use crate::suites::SupportedCipherSuite;
 use crate::sync::Arc;
 use crate::webpki::WebPkiSupportedAlgorithms;
 
+/// Hybrid public key encryption (RFC 9180).
+#[cfg(feature = "ring-hpke")]
+pub mod hpke;
 /// Using software keys for authentication.
 pub mod sign;
 
 pub(crate) mod hash;
 pub(crate) mod hmac;
 pub(crate) mod kx;
+/// Post-quantum secure algorithms.
+#[cfg(feature = "ring-mlkem")]
+pub(crate) mod pq;
 pub(crate) mod quic;
 #[cfg(feature = "std")]
 pub(crate) mod ticketer;
@@ -101,6 +107,8 @@ static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms
         webpki_algs::ECDSA_P384_SHA256,
         webpki_algs::ECDSA_P384_SHA384,
         webpki_algs::ED25519,
+        #[cfg(feature = "curve448")]
+        &crate::crypto::curve448::Ed448Verify,
         webpki_algs::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
         webpki_algs::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
         webpki_algs::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
@@ -128,6 +136,11 @@ static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms
             ],
         ),
         (SignatureScheme::ED25519, &[webpki_algs::ED25519]),
+        #[cfg(feature = "curve448")]
+        (
+            SignatureScheme::ED448,
+            &[&crate::crypto::curve448::Ed448Verify],
+        ),
         (
             SignatureScheme::RSA_PSS_SHA512,
             &[webpki_algs::RSA_PSS_2048_8192_SHA512_LEGACY_KEY],
@@ -161,14 +174,33 @@ static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms
 /// [`DEFAULT_KX_GROUPS`] is provided as an array of this provider's defaults.
 pub mod kx_group {
     pub use super::kx::{SECP256R1, SECP384R1, X25519};
+    #[cfg(feature = "ring-mlkem")]
+    pub use super::pq::{MLKEM768, X25519MLKEM768};
+    #[cfg(feature = "curve448")]
+    pub use crate::crypto::curve448::X448;
 }
 
 /// A list of the default key exchange groups supported by this provider.
-pub static DEFAULT_KX_GROUPS: &[&dyn SupportedKxGroup] = ALL_KX_GROUPS;
+///
+/// This does not contain the post-quantum groups, which are not implemented
+/// by *ring* itself.  With the `ring-mlkem` crate feature, use [`ALL_KX_GROUPS`]
+/// or `kx_group::X25519MLKEM768` to opt in to them.  X448 (with the `curve448`
+/// crate feature) is also not offered by default.
+pub static DEFAULT_KX_GROUPS: &[&dyn SupportedKxGroup] =
+    &[kx_group::X25519, kx_group::SECP256R1, kx_group::SECP384R1];
 
 /// A list of all the key exchange groups supported by this provider.
-pub static ALL_KX_GROUPS: &[&dyn SupportedKxGroup] =
-    &[kx_group::X25519, kx_group::SECP256R1, kx_group::SECP384R1];
+pub static ALL_KX_GROUPS: &[&dyn SupportedKxGroup] = &[
+    #[cfg(feature = "ring-mlkem")]
+    kx_group::X25519MLKEM768,
+    kx_group::X25519,
+    kx_group::SECP256R1,
+    kx_group::SECP384R1,
+    #[cfg(feature = "curve448")]
+    kx_group::X448,
+    #[cfg(feature = "ring-mlkem")]
+    kx_group::MLKEM768,
+];
 
 #[cfg(feature = "std")]
 pub use ticketer::Ticketer;
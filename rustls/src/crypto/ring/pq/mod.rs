@@ -0,0 +1,27 @@
+use crate::NamedGroup;
+use crate::crypto::SupportedKxGroup;
+use crate::crypto::pq::{MLKEM768_CIPHERTEXT_LEN, MLKEM768_ENCAP_LEN, X25519_LEN, hybrid, mlkem};
+use crate::crypto::ring::kx_group;
+
+/// This is the [X25519MLKEM768] key exchange.
+///
+/// *ring* does not implement ML-KEM, so the post-quantum half uses the
+/// pure-Rust [`ml-kem`](https://crates.io/crates/ml-kem) crate.
+///
+/// [X25519MLKEM768]: <https://datatracker.ietf.org/doc/draft-ietf-tls-ecdhe-mlkem/>
+pub static X25519MLKEM768: &dyn SupportedKxGroup = &hybrid::Hybrid {
+    classical: kx_group::X25519,
+    post_quantum: MLKEM768,
+    name: NamedGroup::X25519MLKEM768,
+    layout: hybrid::Layout {
+        classical_share_len: X25519_LEN,
+        post_quantum_client_share_len: MLKEM768_ENCAP_LEN,
+        post_quantum_server_share_len: MLKEM768_CIPHERTEXT_LEN,
+        post_quantum_first: true,
+    },
+};
+
+/// This is the [MLKEM] key exchange.
+///
+/// [MLKEM]: https://datatracker.ietf.org/doc/draft-connolly-tls-mlkem-key-agreement
+pub static MLKEM768: &dyn SupportedKxGroup = &mlkem::MlKem768;
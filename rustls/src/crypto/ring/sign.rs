@@ -10,6 +10,8 @@ use pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, SubjectPublicKeyInfoDer, alg_
 
 use super::ring_like::rand::{SecureRandom, SystemRandom};
 use super::ring_like::signature::{self, EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair};
+#[cfg(feature = "curve448")]
+use crate::crypto::curve448::Ed448SigningKey;
 use crate::crypto::signer::{Signer, SigningKey, public_key_to_spki};
 use crate::enums::{SignatureAlgorithm, SignatureScheme};
 use crate::error::Error;
@@ -66,12 +68,18 @@ pub fn any_ecdsa_type(der: &PrivateKeyDer<'_>) -> Result<Arc<dyn SigningKey>, Er
 
 /// Parse `der` as any EdDSA key type, returning the first which works.
 ///
-/// Note that, at the time of writing, Ed25519 does not have wide support
-/// in browsers.  It is also not supported by the WebPKI, because the
-/// CA/Browser Forum Baseline Requirements do not support it for publicly
+/// Ed25519 keys are supported, as are Ed448 keys with the `curve448` crate feature.
+///
+/// Note that, at the time of writing, Ed25519 and Ed448 do not have wide support
+/// in browsers.  They are also not supported by the WebPKI, because the
+/// CA/Browser Forum Baseline Requirements do not support them for publicly
 /// trusted certificates.
 pub fn any_eddsa_type(der: &PrivatePkcs8KeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
-    // TODO: Add support for Ed448
+    #[cfg(feature = "curve448")]
+    if let Ok(ed448) = Ed448SigningKey::new(der) {
+        return Ok(Arc::new(ed448));
+    }
+
     Ok(Arc::new(Ed25519SigningKey::new(
         der,
         SignatureScheme::ED25519,
@@ -550,6 +558,17 @@ mod tests {
         assert!(any_ecdsa_type(&key).is_err());
     }
 
+    #[cfg(feature = "curve448")]
+    #[test]
+    fn can_load_ed448_pkcs8() {
+        let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/ed448key.der")[..]);
+        let k = any_eddsa_type(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "Ed448SigningKey { algorithm: ED448 }");
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_err());
+    }
+
     #[test]
     fn can_sign_eddsa() {
         let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/eddsakey.der")[..]);
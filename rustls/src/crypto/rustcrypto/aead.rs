@@ -0,0 +1,184 @@
+use alloc::boxed::Box;
+use core::ops::Deref;
+
+use aes::Aes128;
+use aes_gcm::aead::consts::{U8, U12, U16};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use ccm::Ccm;
+use chacha20poly1305::ChaCha20Poly1305;
+
+use crate::crypto::cipher::NONCE_LEN;
+
+/// An AEAD algorithm implemented by the RustCrypto crates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+    Aes128Ccm,
+    /// AES-128-CCM with a truncated, 8-byte tag.
+    Aes128Ccm8,
+}
+
+impl Algorithm {
+    pub(crate) fn key_len(&self) -> usize {
+        match self {
+            Self::Aes128Gcm | Self::Aes128Ccm | Self::Aes128Ccm8 => 16,
+            Self::Aes256Gcm | Self::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    pub(crate) fn tag_len(&self) -> usize {
+        match self {
+            Self::Aes128Ccm8 => 8,
+            _ => MAX_TAG_LEN,
+        }
+    }
+}
+
+/// A key for one of the [`Algorithm`]s.
+///
+/// This mirrors the shape of *ring*'s `LessSafeKey`: the caller is
+/// responsible for never reusing a nonce.
+pub(crate) struct Key(KeyInner);
+
+enum KeyInner {
+    // The AES key schedules are large, so keep them off the stack.
+    Aes128Gcm(Box<Aes128Gcm>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+    Aes128Ccm(Box<Aes128Ccm>),
+    Aes128Ccm8(Box<Aes128Ccm8>),
+}
+
+impl Key {
+    /// Make a new key.
+    ///
+    /// Panics if `key` is not `alg.key_len()` bytes.
+    pub(crate) fn new(alg: Algorithm, key: &[u8]) -> Self {
+        // safety: the caller arranges that `key` is `key_len()` in bytes, so these
+        // unwraps are safe.
+        Self(match alg {
+            Algorithm::Aes128Gcm => {
+                KeyInner::Aes128Gcm(Box::new(Aes128Gcm::new_from_slice(key).unwrap()))
+            }
+            Algorithm::Aes256Gcm => {
+                KeyInner::Aes256Gcm(Box::new(Aes256Gcm::new_from_slice(key).unwrap()))
+            }
+            Algorithm::ChaCha20Poly1305 => {
+                KeyInner::ChaCha20Poly1305(ChaCha20Poly1305::new_from_slice(key).unwrap())
+            }
+            Algorithm::Aes128Ccm => {
+                KeyInner::Aes128Ccm(Box::new(Aes128Ccm::new_from_slice(key).unwrap()))
+            }
+            Algorithm::Aes128Ccm8 => {
+                KeyInner::Aes128Ccm8(Box::new(Aes128Ccm8::new_from_slice(key).unwrap()))
+            }
+        })
+    }
+
+    pub(crate) fn algorithm(&self) -> Algorithm {
+        match &self.0 {
+            KeyInner::Aes128Gcm(_) => Algorithm::Aes128Gcm,
+            KeyInner::Aes256Gcm(_) => Algorithm::Aes256Gcm,
+            KeyInner::ChaCha20Poly1305(_) => Algorithm::ChaCha20Poly1305,
+            KeyInner::Aes128Ccm(_) => Algorithm::Aes128Ccm,
+            KeyInner::Aes128Ccm8(_) => Algorithm::Aes128Ccm8,
+        }
+    }
+
+    /// Encrypt `in_out` in place, returning the authentication tag.
+    pub(crate) fn seal_in_place_separate_tag(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        in_out: &mut [u8],
+    ) -> Result<Tag, ()> {
+        let nonce = GenericArray::from_slice(nonce);
+        let mut tag = Tag {
+            bytes: [0; MAX_TAG_LEN],
+            len: self.algorithm().tag_len(),
+        };
+        let out = &mut tag.bytes[..tag.len];
+        match &self.0 {
+            KeyInner::Aes128Gcm(k) => k
+                .encrypt_in_place_detached(nonce, aad, in_out)
+                .map(|t| out.copy_from_slice(&t)),
+            KeyInner::Aes256Gcm(k) => k
+                .encrypt_in_place_detached(nonce, aad, in_out)
+                .map(|t| out.copy_from_slice(&t)),
+            KeyInner::ChaCha20Poly1305(k) => k
+                .encrypt_in_place_detached(nonce, aad, in_out)
+                .map(|t| out.copy_from_slice(&t)),
+            KeyInner::Aes128Ccm(k) => k
+                .encrypt_in_place_detached(nonce, aad, in_out)
+                .map(|t| out.copy_from_slice(&t)),
+            KeyInner::Aes128Ccm8(k) => k
+                .encrypt_in_place_detached(nonce, aad, in_out)
+                .map(|t| out.copy_from_slice(&t)),
+        }
+        .map_err(|_| ())?;
+        Ok(tag)
+    }
+
+    /// Decrypt `in_out` in place, where the final `algorithm().tag_len()` bytes
+    /// are the authentication tag.
+    ///
+    /// On success, returns the plaintext (a prefix of `in_out`).
+    pub(crate) fn open_in_place<'a>(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        in_out: &'a mut [u8],
+    ) -> Result<&'a mut [u8], ()> {
+        let plain_len = in_out
+            .len()
+            .checked_sub(self.algorithm().tag_len())
+            .ok_or(())?;
+        let (in_out, tag) = in_out.split_at_mut(plain_len);
+        let nonce = GenericArray::from_slice(nonce);
+        match &self.0 {
+            KeyInner::Aes128Gcm(k) => {
+                k.decrypt_in_place_detached(nonce, aad, in_out, GenericArray::from_slice(tag))
+            }
+            KeyInner::Aes256Gcm(k) => {
+                k.decrypt_in_place_detached(nonce, aad, in_out, GenericArray::from_slice(tag))
+            }
+            KeyInner::ChaCha20Poly1305(k) => {
+                k.decrypt_in_place_detached(nonce, aad, in_out, GenericArray::from_slice(tag))
+            }
+            KeyInner::Aes128Ccm(k) => {
+                k.decrypt_in_place_detached(nonce, aad, in_out, GenericArray::from_slice(tag))
+            }
+            KeyInner::Aes128Ccm8(k) => {
+                k.decrypt_in_place_detached(nonce, aad, in_out, GenericArray::from_slice(tag))
+            }
+        }
+        .map_err(|_| ())?;
+        Ok(in_out)
+    }
+}
+
+/// An authentication tag produced by [`Key::seal_in_place_separate_tag`].
+pub(crate) struct Tag {
+    bytes: [u8; MAX_TAG_LEN],
+    len: usize,
+}
+
+impl Deref for Tag {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// AES-128-CCM, with a 16-byte tag and 12-byte nonce, as used in TLS1.3 (RFC 8446).
+type Aes128Ccm = Ccm<Aes128, U16, U12>;
+
+/// AES-128-CCM, with an 8-byte tag and 12-byte nonce, as used in TLS1.3 (RFC 8446).
+type Aes128Ccm8 = Ccm<Aes128, U8, U12>;
+
+const MAX_TAG_LEN: usize = 16;
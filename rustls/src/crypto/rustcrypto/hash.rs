@@ -0,0 +1,54 @@
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+
+use sha2::Digest;
+
+use crate::crypto;
+use crate::msgs::enums::HashAlgorithm;
+
+pub(crate) static SHA256: Hash<sha2::Sha256> = Hash(HashAlgorithm::SHA256, PhantomData);
+pub(crate) static SHA384: Hash<sha2::Sha384> = Hash(HashAlgorithm::SHA384, PhantomData);
+
+pub(crate) struct Hash<D>(HashAlgorithm, PhantomData<D>);
+
+impl<D: Digest + Clone + Send + Sync + 'static> crypto::hash::Hash for Hash<D> {
+    fn start(&self) -> Box<dyn crypto::hash::Context> {
+        Box::new(Context(D::new()))
+    }
+
+    fn hash(&self, bytes: &[u8]) -> crypto::hash::Output {
+        crypto::hash::Output::new(&D::digest(bytes)[..])
+    }
+
+    fn output_len(&self) -> usize {
+        <D as Digest>::output_size()
+    }
+
+    fn algorithm(&self) -> HashAlgorithm {
+        self.0
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+struct Context<D>(D);
+
+impl<D: Digest + Clone + Send + Sync + 'static> crypto::hash::Context for Context<D> {
+    fn fork_finish(&self) -> crypto::hash::Output {
+        crypto::hash::Output::new(&self.0.clone().finalize()[..])
+    }
+
+    fn fork(&self) -> Box<dyn crypto::hash::Context> {
+        Box::new(Self(self.0.clone()))
+    }
+
+    fn finish(self: Box<Self>) -> crypto::hash::Output {
+        crypto::hash::Output::new(&self.0.finalize()[..])
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
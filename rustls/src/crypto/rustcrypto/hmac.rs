@@ -0,0 +1,45 @@
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+
+use ::hmac::Mac;
+use ::hmac::digest::{KeyInit, OutputSizeUser};
+
+use crate::crypto;
+
+pub(crate) static HMAC_SHA256: Hmac<::hmac::Hmac<sha2::Sha256>> = Hmac(PhantomData);
+pub(crate) static HMAC_SHA384: Hmac<::hmac::Hmac<sha2::Sha384>> = Hmac(PhantomData);
+
+pub(crate) struct Hmac<M>(PhantomData<M>);
+
+impl<M: Mac + KeyInit + Clone + Send + Sync + 'static> crypto::hmac::Hmac for Hmac<M> {
+    fn with_key(&self, key: &[u8]) -> Box<dyn crypto::hmac::Key> {
+        // HMAC accepts keys of any length, so this cannot fail.
+        Box::new(Key(<M as KeyInit>::new_from_slice(key).unwrap()))
+    }
+
+    fn hash_output_len(&self) -> usize {
+        <M as OutputSizeUser>::output_size()
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+struct Key<M>(M);
+
+impl<M: Mac + Clone + Send + Sync> crypto::hmac::Key for Key<M> {
+    fn sign_concat(&self, first: &[u8], middle: &[&[u8]], last: &[u8]) -> crypto::hmac::Tag {
+        let mut ctx = self.0.clone();
+        ctx.update(first);
+        for d in middle {
+            ctx.update(d);
+        }
+        ctx.update(last);
+        crypto::hmac::Tag::new(&ctx.finalize().into_bytes()[..])
+    }
+
+    fn tag_len(&self) -> usize {
+        <M as OutputSizeUser>::output_size()
+    }
+}
@@ -0,0 +1,733 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use p256::elliptic_curve::generic_array::typenum::Unsigned;
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint};
+use p256::elliptic_curve::{AffinePoint, CurveArithmetic, FieldBytesSize, PublicKey, SecretKey};
+use rand_core::OsRng;
+use zeroize::Zeroize;
+
+use super::aead;
+use super::hmac::{HMAC_SHA256, HMAC_SHA384};
+use crate::Error;
+use crate::crypto::hpke::schedule::{KemSharedSecret, KeySchedule, extract_and_expand};
+use crate::crypto::hpke::{
+    EncapsulatedSecret, Hpke, HpkeOpener, HpkePrivateKey, HpkePublicKey, HpkeSealer, HpkeSuite,
+};
+use crate::crypto::tls13::{HkdfPrkExtract, HkdfUsingHmac};
+use crate::msgs::enums::{HpkeAead, HpkeKdf, HpkeKem};
+use crate::msgs::handshake::HpkeSymmetricCipherSuite;
+
+/// Default [RFC 9180] Hybrid Public Key Encryption (HPKE) suites supported by RustCrypto cryptography.
+pub static ALL_SUPPORTED_SUITES: &[&dyn Hpke] = &[
+    DH_KEM_P256_HKDF_SHA256_AES_128,
+    DH_KEM_P256_HKDF_SHA256_AES_256,
+    DH_KEM_P256_HKDF_SHA256_CHACHA20_POLY1305,
+    DH_KEM_P384_HKDF_SHA384_AES_128,
+    DH_KEM_P384_HKDF_SHA384_AES_256,
+    DH_KEM_P384_HKDF_SHA384_CHACHA20_POLY1305,
+    DH_KEM_X25519_HKDF_SHA256_AES_128,
+    DH_KEM_X25519_HKDF_SHA256_AES_256,
+    DH_KEM_X25519_HKDF_SHA256_CHACHA20_POLY1305,
+];
+
+/// HPKE suite using ECDH P-256 for agreement, HKDF SHA-256 for key derivation, and AEAD AES-128-GCM
+/// for symmetric encryption.
+pub static DH_KEM_P256_HKDF_SHA256_AES_128: &HpkeRustCrypto<AES_128_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRustCrypto {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P256_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_128_GCM,
+            },
+        },
+        dh_kem: DH_KEM_P256_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: aead::Algorithm::Aes128Gcm,
+    };
+
+/// HPKE suite using ECDH P-256 for agreement, HKDF SHA-256 for key derivation and AEAD AES-256-GCM
+/// for symmetric encryption.
+pub static DH_KEM_P256_HKDF_SHA256_AES_256: &HpkeRustCrypto<AES_256_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRustCrypto {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P256_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_256_GCM,
+            },
+        },
+        dh_kem: DH_KEM_P256_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: aead::Algorithm::Aes256Gcm,
+    };
+
+/// HPKE suite using ECDH P-256 for agreement, HKDF SHA-256 for key derivation, and AEAD
+/// CHACHA20-POLY-1305 for symmetric encryption.
+pub static DH_KEM_P256_HKDF_SHA256_CHACHA20_POLY1305: &HpkeRustCrypto<
+    CHACHA_KEY_LEN,
+    SHA256_OUTPUT_LEN,
+> = &HpkeRustCrypto {
+    suite: HpkeSuite {
+        kem: HpkeKem::DHKEM_P256_HKDF_SHA256,
+        sym: HpkeSymmetricCipherSuite {
+            kdf_id: HpkeKdf::HKDF_SHA256,
+            aead_id: HpkeAead::CHACHA20_POLY_1305,
+        },
+    },
+    dh_kem: DH_KEM_P256_HKDF_SHA256,
+    hkdf: HKDF_HMAC_SHA256,
+    aead: aead::Algorithm::ChaCha20Poly1305,
+};
+
+/// HPKE suite using ECDH P-384 for agreement, HKDF SHA-384 for key derivation, and AEAD AES-128-GCM
+/// for symmetric encryption.
+pub static DH_KEM_P384_HKDF_SHA384_AES_128: &HpkeRustCrypto<AES_128_KEY_LEN, SHA384_OUTPUT_LEN> =
+    &HpkeRustCrypto {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P384_HKDF_SHA384,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA384,
+                aead_id: HpkeAead::AES_128_GCM,
+            },
+        },
+        dh_kem: DH_KEM_P384_HKDF_SHA384,
+        hkdf: HKDF_HMAC_SHA384,
+        aead: aead::Algorithm::Aes128Gcm,
+    };
+
+/// HPKE suite using ECDH P-384 for agreement, HKDF SHA-384 for key derivation, and AEAD AES-256-GCM
+/// for symmetric encryption.
+pub static DH_KEM_P384_HKDF_SHA384_AES_256: &HpkeRustCrypto<AES_256_KEY_LEN, SHA384_OUTPUT_LEN> =
+    &HpkeRustCrypto {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_P384_HKDF_SHA384,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA384,
+                aead_id: HpkeAead::AES_256_GCM,
+            },
+        },
+        dh_kem: DH_KEM_P384_HKDF_SHA384,
+        hkdf: HKDF_HMAC_SHA384,
+        aead: aead::Algorithm::Aes256Gcm,
+    };
+
+/// HPKE suite using ECDH P-384 for agreement, HKDF SHA-384 for key derivation, and AEAD
+/// CHACHA20-POLY-1305 for symmetric encryption.
+pub static DH_KEM_P384_HKDF_SHA384_CHACHA20_POLY1305: &HpkeRustCrypto<
+    CHACHA_KEY_LEN,
+    SHA384_OUTPUT_LEN,
+> = &HpkeRustCrypto {
+    suite: HpkeSuite {
+        kem: HpkeKem::DHKEM_P384_HKDF_SHA384,
+        sym: HpkeSymmetricCipherSuite {
+            kdf_id: HpkeKdf::HKDF_SHA384,
+            aead_id: HpkeAead::CHACHA20_POLY_1305,
+        },
+    },
+    dh_kem: DH_KEM_P384_HKDF_SHA384,
+    hkdf: HKDF_HMAC_SHA384,
+    aead: aead::Algorithm::ChaCha20Poly1305,
+};
+
+/// HPKE suite using ECDH X25519 for agreement, HKDF SHA-256 for key derivation, and AEAD AES-128-GCM
+/// for symmetric encryption.
+pub static DH_KEM_X25519_HKDF_SHA256_AES_128: &HpkeRustCrypto<AES_128_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRustCrypto {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_128_GCM,
+            },
+        },
+        dh_kem: DH_KEM_X25519_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: aead::Algorithm::Aes128Gcm,
+    };
+
+/// HPKE suite using ECDH X25519 for agreement, HKDF SHA-256 for key derivation, and AEAD AES-256-GCM
+/// for symmetric encryption.
+pub static DH_KEM_X25519_HKDF_SHA256_AES_256: &HpkeRustCrypto<AES_256_KEY_LEN, SHA256_OUTPUT_LEN> =
+    &HpkeRustCrypto {
+        suite: HpkeSuite {
+            kem: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdf::HKDF_SHA256,
+                aead_id: HpkeAead::AES_256_GCM,
+            },
+        },
+        dh_kem: DH_KEM_X25519_HKDF_SHA256,
+        hkdf: HKDF_HMAC_SHA256,
+        aead: aead::Algorithm::Aes256Gcm,
+    };
+
+/// HPKE suite using ECDH X25519 for agreement, HKDF SHA-256 for key derivation, and AEAD
+/// CHACHA20-POLY-1305 for symmetric encryption.
+pub static DH_KEM_X25519_HKDF_SHA256_CHACHA20_POLY1305: &HpkeRustCrypto<
+    CHACHA_KEY_LEN,
+    SHA256_OUTPUT_LEN,
+> = &HpkeRustCrypto {
+    suite: HpkeSuite {
+        kem: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+        sym: HpkeSymmetricCipherSuite {
+            kdf_id: HpkeKdf::HKDF_SHA256,
+            aead_id: HpkeAead::CHACHA20_POLY_1305,
+        },
+    },
+    dh_kem: DH_KEM_X25519_HKDF_SHA256,
+    hkdf: HKDF_HMAC_SHA256,
+    aead: aead::Algorithm::ChaCha20Poly1305,
+};
+
+/// `HpkeRustCrypto` holds the concrete instantiations of the algorithms specified by the [HpkeSuite].
+pub struct HpkeRustCrypto<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    suite: HpkeSuite,
+    dh_kem: &'static DhKem<KDF_SIZE>,
+    hkdf: &'static dyn HkdfPrkExtract,
+    aead: aead::Algorithm,
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeRustCrypto<KEY_SIZE, KDF_SIZE> {
+    /// See [RFC 9180 §5.1 "Creating the Encryption Context"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1
+    fn key_schedule(
+        &self,
+        shared_secret: &KemSharedSecret<KDF_SIZE>,
+        info: &[u8],
+    ) -> KeySchedule<KEY_SIZE> {
+        KeySchedule::new(self.suite, self.hkdf, shared_secret, info)
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Hpke for HpkeRustCrypto<KEY_SIZE, KDF_SIZE> {
+    fn seal(
+        &self,
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Vec<u8>), Error> {
+        let (encap, mut sealer) = self.setup_sealer(info, pub_key)?;
+        Ok((encap, sealer.seal(aad, plaintext)?))
+    }
+
+    fn setup_sealer(
+        &self,
+        info: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Box<dyn HpkeSealer + 'static>), Error> {
+        let (encap, sealer) = Sealer::new(self, info, pub_key)?;
+        Ok((encap, Box::new(sealer)))
+    }
+
+    fn open(
+        &self,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Vec<u8>, Error> {
+        self.setup_opener(enc, info, secret_key)?
+            .open(aad, ciphertext)
+    }
+
+    fn setup_opener(
+        &self,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Box<dyn HpkeOpener + 'static>, Error> {
+        Ok(Box::new(Opener::new(self, enc, info, secret_key)?))
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+
+    fn generate_key_pair(&self) -> Result<(HpkePublicKey, HpkePrivateKey), Error> {
+        self.dh_kem
+            .algorithm
+            .generate_key_pair()
+    }
+
+    fn suite(&self) -> HpkeSuite {
+        self.suite
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for HpkeRustCrypto<KEY_SIZE, KDF_SIZE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.suite.fmt(f)
+    }
+}
+
+/// Adapts a [KeySchedule] and AEAD algorithm for the role of a [HpkeSealer].
+struct Sealer<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    aead: aead::Algorithm,
+    key_schedule: KeySchedule<KEY_SIZE>,
+    shared_secret: KemSharedSecret<KDF_SIZE>,
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Sealer<KEY_SIZE, KDF_SIZE> {
+    /// See [RFC 9180 §5.1.1 "Encryption to a Public Key"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1.1
+    fn new(
+        suite: &HpkeRustCrypto<KEY_SIZE, KDF_SIZE>,
+        info: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Self), Error> {
+        // def SetupBaseS(pkR, info):
+        //   shared_secret, enc = Encap(pkR)
+        //   return enc, KeyScheduleS(mode_base, shared_secret, info,
+        //                            default_psk, default_psk_id)
+
+        let (shared_secret, enc) = suite.dh_kem.encap(pub_key)?;
+        let key_schedule = suite.key_schedule(&shared_secret, info);
+        Ok((
+            enc,
+            Self {
+                aead: suite.aead,
+                key_schedule,
+                shared_secret,
+            },
+        ))
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeSealer for Sealer<KEY_SIZE, KDF_SIZE> {
+    fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        // def ContextS.Seal(aad, pt):
+        //   ct = Seal(self.key, self.ComputeNonce(self.seq), aad, pt)
+        //   self.IncrementSeq()
+        //   return ct
+
+        let key = aead::Key::new(self.aead, self.key_schedule.key());
+        let nonce = self.key_schedule.next_nonce()?;
+
+        let mut in_out_buffer = Vec::from(plaintext);
+        let tag = key
+            .seal_in_place_separate_tag(&nonce, aad, &mut in_out_buffer)
+            .map_err(|_| Error::EncryptError)?;
+        in_out_buffer.extend_from_slice(&tag);
+
+        Ok(in_out_buffer)
+    }
+
+    fn shared_secret(&self) -> Option<&[u8]> {
+        Some(&self.shared_secret.0)
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for Sealer<KEY_SIZE, KDF_SIZE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sealer").finish()
+    }
+}
+
+/// Adapts a [KeySchedule] and AEAD algorithm for the role of a [HpkeOpener].
+struct Opener<const KEY_SIZE: usize, const KDF_SIZE: usize> {
+    aead: aead::Algorithm,
+    key_schedule: KeySchedule<KEY_SIZE>,
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Opener<KEY_SIZE, KDF_SIZE> {
+    /// See [RFC 9180 §5.1.1 "Encryption to a Public Key"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-5.1.1
+    fn new(
+        suite: &HpkeRustCrypto<KEY_SIZE, KDF_SIZE>,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Self, Error> {
+        // def SetupBaseR(enc, skR, info):
+        //   shared_secret = Decap(enc, skR)
+        //   return KeyScheduleR(mode_base, shared_secret, info,
+        //                       default_psk, default_psk_id)
+        Ok(Self {
+            aead: suite.aead,
+            key_schedule: suite.key_schedule(&suite.dh_kem.decap(enc, secret_key)?, info),
+        })
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> HpkeOpener for Opener<KEY_SIZE, KDF_SIZE> {
+    fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        // def ContextR.Open(aad, ct):
+        //   pt = Open(self.key, self.ComputeNonce(self.seq), aad, ct)
+        //   if pt == OpenError:
+        //     raise OpenError
+        //   self.IncrementSeq()
+        //   return pt
+
+        if ciphertext.len() < self.aead.tag_len() {
+            return Err(Error::DecryptError);
+        }
+
+        let key = aead::Key::new(self.aead, self.key_schedule.key());
+        let nonce = self.key_schedule.compute_nonce();
+
+        let mut in_out_buffer = Vec::from(ciphertext);
+        let plaintext_len = key
+            .open_in_place(&nonce, aad, &mut in_out_buffer)
+            .map_err(|_| Error::DecryptError)?
+            .len();
+        self.key_schedule.increment_seq_num()?;
+
+        in_out_buffer.truncate(plaintext_len);
+        Ok(in_out_buffer)
+    }
+}
+
+impl<const KEY_SIZE: usize, const KDF_SIZE: usize> Debug for Opener<KEY_SIZE, KDF_SIZE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Opener").finish()
+    }
+}
+
+/// A Diffie-Hellman (DH) based Key Encapsulation Mechanism (KEM).
+///
+/// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+struct DhKem<const KDF_SIZE: usize> {
+    id: HpkeKem,
+    algorithm: DhAlgorithm,
+    hkdf: &'static dyn HkdfPrkExtract,
+}
+
+impl<const KDF_SIZE: usize> DhKem<KDF_SIZE> {
+    /// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+    fn encap(
+        &self,
+        recipient: &HpkePublicKey,
+    ) -> Result<(KemSharedSecret<KDF_SIZE>, EncapsulatedSecret), Error> {
+        // def Encap(pkR):
+        //   skE, pkE = GenerateKeyPair()
+        //   dh = DH(skE, pkR)
+        //   enc = SerializePublicKey(pkE)
+        //
+        //   pkRm = SerializePublicKey(pkR)
+        //   kem_context = concat(enc, pkRm)
+        //
+        //   shared_secret = ExtractAndExpand(dh, kem_context)
+        //   return shared_secret, enc
+
+        let (enc, sk_e) = self.algorithm.generate_key_pair()?;
+        let kem_context = [&enc.0[..], &recipient.0].concat();
+
+        let dh = self
+            .algorithm
+            .agree(sk_e.secret_bytes(), &recipient.0)?;
+
+        Ok((
+            self.extract_and_expand(&dh.0, &kem_context),
+            EncapsulatedSecret(enc.0),
+        ))
+    }
+
+    /// See [RFC 9180 §4.1 "DH-Based KEM (DHKEM)"][0].
+    ///
+    /// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-4.1
+    fn decap(
+        &self,
+        enc: &EncapsulatedSecret,
+        recipient: &HpkePrivateKey,
+    ) -> Result<KemSharedSecret<KDF_SIZE>, Error> {
+        // def Decap(enc, skR):
+        //   pkE = DeserializePublicKey(enc)
+        //   dh = DH(skR, pkE)
+        //
+        //   pkRm = SerializePublicKey(pk(skR))
+        //   kem_context = concat(enc, pkRm)
+        //
+        //   shared_secret = ExtractAndExpand(dh, kem_context)
+        //   return shared_secret
+
+        let pk_rm = self
+            .algorithm
+            .public_key(recipient.secret_bytes())?;
+        let kem_context = [&enc.0[..], &pk_rm].concat();
+
+        let dh = self
+            .algorithm
+            .agree(recipient.secret_bytes(), &enc.0)?;
+
+        Ok(self.extract_and_expand(&dh.0, &kem_context))
+    }
+
+    fn extract_and_expand(&self, dh: &[u8], kem_context: &[u8]) -> KemSharedSecret<KDF_SIZE> {
+        extract_and_expand(self.id, self.hkdf, dh, kem_context)
+    }
+}
+
+static DH_KEM_P256_HKDF_SHA256: &DhKem<SHA256_OUTPUT_LEN> = &DhKem {
+    id: HpkeKem::DHKEM_P256_HKDF_SHA256,
+    algorithm: DhAlgorithm::P256,
+    hkdf: HKDF_HMAC_SHA256,
+};
+
+static DH_KEM_P384_HKDF_SHA384: &DhKem<SHA384_OUTPUT_LEN> = &DhKem {
+    id: HpkeKem::DHKEM_P384_HKDF_SHA384,
+    algorithm: DhAlgorithm::P384,
+    hkdf: HKDF_HMAC_SHA384,
+};
+
+static DH_KEM_X25519_HKDF_SHA256: &DhKem<SHA256_OUTPUT_LEN> = &DhKem {
+    id: HpkeKem::DHKEM_X25519_HKDF_SHA256,
+    algorithm: DhAlgorithm::X25519,
+    hkdf: HKDF_HMAC_SHA256,
+};
+
+/// The Diffie-Hellman function underlying a [DhKem].
+///
+/// Private keys are the raw fixed-length scalar (big-endian for the NIST curves);
+/// public keys are the `SerializePublicKey` encoding from [RFC 9180 §7.1.1][0].
+///
+/// [0]: https://www.rfc-editor.org/rfc/rfc9180.html#section-7.1.1
+#[derive(Clone, Copy)]
+enum DhAlgorithm {
+    X25519,
+    P256,
+    P384,
+}
+
+impl DhAlgorithm {
+    fn generate_key_pair(self) -> Result<(HpkePublicKey, HpkePrivateKey), Error> {
+        let private_key = match self {
+            Self::X25519 => x25519_dalek::StaticSecret::random_from_rng(OsRng)
+                .to_bytes()
+                .to_vec(),
+            Self::P256 => SecretKey::<p256::NistP256>::random(&mut OsRng)
+                .to_bytes()
+                .to_vec(),
+            Self::P384 => SecretKey::<p384::NistP384>::random(&mut OsRng)
+                .to_bytes()
+                .to_vec(),
+        };
+        let private_key = HpkePrivateKey::from(private_key);
+        let public_key = HpkePublicKey(self.public_key(private_key.secret_bytes())?);
+        Ok((public_key, private_key))
+    }
+
+    fn public_key(self, private_key: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::X25519 => {
+                let private_key = x25519_private_key(private_key)?;
+                Ok(x25519_dalek::PublicKey::from(&private_key)
+                    .as_bytes()
+                    .to_vec())
+            }
+            Self::P256 => ec_public_key::<p256::NistP256>(private_key),
+            Self::P384 => ec_public_key::<p384::NistP384>(private_key),
+        }
+    }
+
+    fn agree(self, private_key: &[u8], public_key: &[u8]) -> Result<DhSecret, Error> {
+        match self {
+            Self::X25519 => {
+                let private_key = x25519_private_key(private_key)?;
+                let public_key: [u8; 32] = public_key
+                    .try_into()
+                    .map_err(|_| invalid_public_key())?;
+                let shared = private_key.diffie_hellman(&x25519_dalek::PublicKey::from(public_key));
+
+                // RFC 9180 §7.1.4: "senders and recipients MUST check whether the shared
+                // secret is the all-zero value and abort if so".
+                if !shared.was_contributory() {
+                    return Err(invalid_public_key());
+                }
+                Ok(DhSecret(shared.as_bytes().to_vec()))
+            }
+            Self::P256 => ec_agree::<p256::NistP256>(private_key, public_key),
+            Self::P384 => ec_agree::<p384::NistP384>(private_key, public_key),
+        }
+    }
+}
+
+fn x25519_private_key(private_key: &[u8]) -> Result<x25519_dalek::StaticSecret, Error> {
+    let private_key: [u8; 32] = private_key
+        .try_into()
+        .map_err(|_| invalid_private_key())?;
+    Ok(x25519_dalek::StaticSecret::from(private_key))
+}
+
+fn ec_private_key<C>(private_key: &[u8]) -> Result<SecretKey<C>, Error>
+where
+    C: CurveArithmetic,
+{
+    // `SecretKey::from_slice` pads short inputs, but RFC 9180 requires the
+    // fixed-length `Nsk` encoding.
+    if private_key.len() != FieldBytesSize::<C>::USIZE {
+        return Err(invalid_private_key());
+    }
+    SecretKey::<C>::from_slice(private_key).map_err(|_| invalid_private_key())
+}
+
+fn ec_public_key<C>(private_key: &[u8]) -> Result<Vec<u8>, Error>
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    Ok(ec_private_key::<C>(private_key)?
+        .public_key()
+        .to_encoded_point(false)
+        .as_bytes()
+        .to_vec())
+}
+
+fn ec_agree<C>(private_key: &[u8], public_key: &[u8]) -> Result<DhSecret, Error>
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    let private_key = ec_private_key::<C>(private_key)?;
+
+    // Only the uncompressed SEC1 encoding is a valid `SerializePublicKey` output.
+    if public_key.len() != 1 + 2 * FieldBytesSize::<C>::USIZE || public_key.first() != Some(&0x04) {
+        return Err(invalid_public_key());
+    }
+    let public_key =
+        PublicKey::<C>::from_sec1_bytes(public_key).map_err(|_| invalid_public_key())?;
+
+    let shared = p256::elliptic_curve::ecdh::diffie_hellman(
+        private_key.to_nonzero_scalar(),
+        public_key.as_affine(),
+    );
+    Ok(DhSecret(shared.raw_secret_bytes().to_vec()))
+}
+
+fn invalid_private_key() -> Error {
+    Error::General("invalid HPKE private key".into())
+}
+
+fn invalid_public_key() -> Error {
+    Error::General("invalid HPKE public key".into())
+}
+
+/// A newtype wrapper for the raw output of a Diffie-Hellman agreement.
+struct DhSecret(Vec<u8>);
+
+impl Drop for DhSecret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+const AES_128_KEY_LEN: usize = 16;
+const AES_256_KEY_LEN: usize = 32;
+const CHACHA_KEY_LEN: usize = 32;
+const SHA256_OUTPUT_LEN: usize = 32;
+const SHA384_OUTPUT_LEN: usize = 48;
+
+static HKDF_HMAC_SHA256: &HkdfUsingHmac<'static> = &HkdfUsingHmac(&HMAC_SHA256);
+static HKDF_HMAC_SHA384: &HkdfUsingHmac<'static> = &HkdfUsingHmac(&HMAC_SHA384);
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec};
+
+    use super::*;
+
+    #[test]
+    fn smoke_test() {
+        for suite in ALL_SUPPORTED_SUITES {
+            _ = format!("{suite:?}"); // HpkeRustCrypto suites should be Debug.
+
+            // We should be able to generate a random keypair.
+            let (pk, sk) = suite.generate_key_pair().unwrap();
+
+            // Info value corresponds to the first RFC 9180 base mode test vector.
+            let info = &[
+                0x4f, 0x64, 0x65, 0x20, 0x6f, 0x6e, 0x20, 0x61, 0x20, 0x47, 0x72, 0x65, 0x63, 0x69,
+                0x61, 0x6e, 0x20, 0x55, 0x72, 0x6e,
+            ][..];
+
+            // We should be able to set up a sealer.
+            let (enc, mut sealer) = suite.setup_sealer(info, &pk).unwrap();
+
+            _ = format!("{sealer:?}"); // Sealer should be Debug.
+
+            // Setting up a sealer with an invalid public key should fail.
+            let bad_setup_res = suite.setup_sealer(info, &HpkePublicKey(vec![]));
+            assert!(matches!(bad_setup_res.unwrap_err(), Error::General(_)));
+
+            // We should be able to seal some plaintext.
+            let aad = &[0xC0, 0xFF, 0xEE];
+            let pt = &[0xF0, 0x0D];
+            let ct = sealer.seal(aad, pt).unwrap();
+
+            // We should be able to set up an opener.
+            let mut opener = suite
+                .setup_opener(&enc, info, &sk)
+                .unwrap();
+            _ = format!("{opener:?}"); // Opener should be Debug.
+
+            // Setting up an opener with an invalid private key should fail.
+            let bad_key_res = suite.setup_opener(&enc, info, &HpkePrivateKey::from(vec![]));
+            assert!(matches!(bad_key_res.unwrap_err(), Error::General(_)));
+
+            // Opening the plaintext should work with the correct opener and aad.
+            let pt_prime = opener.open(aad, &ct).unwrap();
+            assert_eq!(pt_prime, pt);
+
+            // Opening the plaintext with the correct opener and wrong aad should fail.
+            let open_res = opener.open(&[0x0], &ct);
+            assert_eq!(open_res.unwrap_err(), Error::DecryptError);
+
+            // Opening the plaintext with the wrong opener should fail.
+            let mut sk_rm_prime = sk.secret_bytes().to_vec();
+            sk_rm_prime[10] ^= 0xFF; // Corrupt a byte of the private key.
+            let mut opener_two = suite
+                .setup_opener(&enc, info, &HpkePrivateKey::from(sk_rm_prime))
+                .unwrap();
+            let open_res = opener_two.open(aad, &ct);
+            assert_eq!(open_res.unwrap_err(), Error::DecryptError);
+        }
+    }
+
+    #[test]
+    fn sequence_of_messages() {
+        for suite in ALL_SUPPORTED_SUITES {
+            let (pk, sk) = suite.generate_key_pair().unwrap();
+            let (enc, mut sealer) = suite
+                .setup_sealer(b"info", &pk)
+                .unwrap();
+            let mut opener = suite
+                .setup_opener(&enc, b"info", &sk)
+                .unwrap();
+
+            for i in 0u8..4 {
+                let ct = sealer.seal(&[i], &[i; 3]).unwrap();
+                assert_eq!(opener.open(&[i], &ct).unwrap(), [i; 3]);
+            }
+        }
+    }
+
+    #[test]
+    fn x25519_rejects_low_order_public_key() {
+        let res = DH_KEM_X25519_HKDF_SHA256_AES_128.setup_sealer(b"", &HpkePublicKey(vec![0; 32]));
+        assert!(matches!(res.unwrap_err(), Error::General(_)));
+    }
+
+    #[test]
+    fn nist_curve_rejects_compressed_public_key() {
+        let (pk, _) = DH_KEM_P256_HKDF_SHA256_AES_128
+            .generate_key_pair()
+            .unwrap();
+        let mut compressed = pk.0[..33].to_vec();
+        compressed[0] = 0x02 | (pk.0[64] & 1);
+        let res = DH_KEM_P256_HKDF_SHA256_AES_128.setup_sealer(b"", &HpkePublicKey(compressed));
+        assert!(matches!(res.unwrap_err(), Error::General(_)));
+    }
+}
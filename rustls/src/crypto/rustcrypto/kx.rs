@@ -0,0 +1,222 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use p256::elliptic_curve::ecdh::EphemeralSecret;
+use p256::elliptic_curve::sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint};
+use p256::elliptic_curve::{AffinePoint, CurveArithmetic, FieldBytesSize, PublicKey};
+use rand_core::OsRng;
+
+use crate::crypto::{ActiveKeyExchange, SharedSecret, SupportedKxGroup};
+use crate::error::{Error, PeerMisbehaved};
+use crate::msgs::enums::NamedGroup;
+
+/// Ephemeral ECDH on curve25519 (see RFC7748)
+pub static X25519: &dyn SupportedKxGroup = &X25519Group;
+
+/// Ephemeral ECDH on secp256r1 (aka NIST-P256)
+pub static SECP256R1: &dyn SupportedKxGroup = &EcdhGroup::<p256::NistP256> {
+    name: NamedGroup::secp256r1,
+    _curve: PhantomData,
+};
+
+/// Ephemeral ECDH on secp384r1 (aka NIST-P384)
+pub static SECP384R1: &dyn SupportedKxGroup = &EcdhGroup::<p384::NistP384> {
+    name: NamedGroup::secp384r1,
+    _curve: PhantomData,
+};
+
+struct X25519Group;
+
+impl SupportedKxGroup for X25519Group {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let priv_key = x25519_dalek::EphemeralSecret::random_from_rng(OsRng);
+        let pub_key = x25519_dalek::PublicKey::from(&priv_key);
+        Ok(Box::new(X25519KeyExchange { priv_key, pub_key }))
+    }
+
+    fn name(&self) -> NamedGroup {
+        NamedGroup::X25519
+    }
+
+    fn fips(&self) -> bool {
+        // "Curves that are included in SP 800-186 but not included in SP 800-56Arev3 are
+        //  not approved for key agreement."
+        false
+    }
+}
+
+impl fmt::Debug for X25519Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+struct X25519KeyExchange {
+    priv_key: x25519_dalek::EphemeralSecret,
+    pub_key: x25519_dalek::PublicKey,
+}
+
+impl ActiveKeyExchange for X25519KeyExchange {
+    fn complete(self: Box<Self>, peer: &[u8]) -> Result<SharedSecret, Error> {
+        let peer: [u8; 32] = peer
+            .try_into()
+            .map_err(|_| PeerMisbehaved::InvalidKeyShare)?;
+        let shared = self
+            .priv_key
+            .diffie_hellman(&x25519_dalek::PublicKey::from(peer));
+
+        // Reject low-order points, which would yield an all-zero shared secret.
+        // <https://datatracker.ietf.org/doc/html/rfc8446#section-7.4.2>
+        if !shared.was_contributory() {
+            return Err(PeerMisbehaved::InvalidKeyShare.into());
+        }
+
+        Ok(SharedSecret::from(&shared.as_bytes()[..]))
+    }
+
+    fn group(&self) -> NamedGroup {
+        NamedGroup::X25519
+    }
+
+    fn pub_key(&self) -> &[u8] {
+        self.pub_key.as_bytes()
+    }
+}
+
+/// A key-exchange group over one of the NIST curves.
+struct EcdhGroup<C> {
+    name: NamedGroup,
+    _curve: PhantomData<C>,
+}
+
+impl<C> SupportedKxGroup for EcdhGroup<C>
+where
+    C: CurveArithmetic + Send + Sync + 'static,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let priv_key = EphemeralSecret::<C>::random(&mut OsRng);
+        let pub_key = priv_key
+            .public_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec();
+
+        Ok(Box::new(EcdhKeyExchange {
+            name: self.name,
+            priv_key,
+            pub_key,
+        }))
+    }
+
+    fn name(&self) -> NamedGroup {
+        self.name
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+impl<C> fmt::Debug for EcdhGroup<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+/// An in-progress key exchange.  This has the algorithm,
+/// our private key, and our public key.
+struct EcdhKeyExchange<C: CurveArithmetic> {
+    name: NamedGroup,
+    priv_key: EphemeralSecret<C>,
+    pub_key: Vec<u8>,
+}
+
+impl<C> ActiveKeyExchange for EcdhKeyExchange<C>
+where
+    C: CurveArithmetic + Send + Sync,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    fn complete(self: Box<Self>, peer: &[u8]) -> Result<SharedSecret, Error> {
+        // TLS only permits the uncompressed encoding; `from_sec1_bytes` also
+        // accepts other encodings, so check the shape of the point first.
+        // <https://datatracker.ietf.org/doc/html/rfc8446#section-4.2.8.2>
+        if peer.len() != self.pub_key.len() || !matches!(peer.first(), Some(0x04)) {
+            return Err(PeerMisbehaved::InvalidKeyShare.into());
+        }
+
+        let peer =
+            PublicKey::<C>::from_sec1_bytes(peer).map_err(|_| PeerMisbehaved::InvalidKeyShare)?;
+        let shared = self.priv_key.diffie_hellman(&peer);
+        Ok(SharedSecret::from(&shared.raw_secret_bytes()[..]))
+    }
+
+    fn group(&self) -> NamedGroup {
+        self.name
+    }
+
+    fn pub_key(&self) -> &[u8] {
+        &self.pub_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::format;
+
+    use super::*;
+
+    #[test]
+    fn kxgroup_fmt_yields_name() {
+        assert_eq!("X25519", format!("{:?}", X25519));
+        assert_eq!("secp256r1", format!("{:?}", SECP256R1));
+    }
+
+    #[test]
+    fn x25519_rejects_low_order_point() {
+        let kx = X25519.start().unwrap();
+        assert_eq!(
+            kx.complete(&[0u8; 32]).err(),
+            Some(PeerMisbehaved::InvalidKeyShare.into())
+        );
+    }
+
+    #[test]
+    fn ecdh_rejects_compressed_point() {
+        for group in [SECP256R1, SECP384R1] {
+            let peer = group.start().unwrap();
+            let mut compressed = peer.pub_key().to_vec();
+            let y_is_odd = compressed.last().unwrap() & 1;
+            compressed[0] = 0x02 | y_is_odd;
+            compressed.truncate(1 + (compressed.len() - 1) / 2);
+
+            let kx = group.start().unwrap();
+            assert_eq!(
+                kx.complete(&compressed).err(),
+                Some(PeerMisbehaved::InvalidKeyShare.into())
+            );
+        }
+    }
+
+    #[test]
+    fn ecdh_agrees() {
+        for group in [X25519, SECP256R1, SECP384R1] {
+            let a = group.start().unwrap();
+            let b = group.start().unwrap();
+            let a_pub = a.pub_key().to_vec();
+            let b_pub = b.pub_key().to_vec();
+            assert_eq!(
+                a.complete(&b_pub)
+                    .unwrap()
+                    .secret_bytes(),
+                b.complete(&a_pub)
+                    .unwrap()
+                    .secret_bytes()
+            );
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use pki_types::PrivateKeyDer;
+use rand_core::{OsRng, RngCore};
+
+use crate::Error;
+use crate::crypto::{CryptoProvider, KeyProvider, SecureRandom, SupportedKxGroup};
+use crate::enums::SignatureScheme;
+use crate::rand::GetRandomFailed;
+use crate::sign::SigningKey;
+use crate::suites::SupportedCipherSuite;
+use crate::sync::Arc;
+use crate::webpki::WebPkiSupportedAlgorithms;
+
+/// Hybrid public key encryption (RFC 9180).
+pub mod hpke;
+/// Using software keys for authentication.
+pub mod sign;
+/// Signature verification algorithms for use with webpki.
+pub mod verify;
+
+pub(crate) mod aead;
+pub(crate) mod hash;
+pub(crate) mod hmac;
+pub(crate) mod kx;
+/// Post-quantum secure algorithms.
+pub(crate) mod pq;
+pub(crate) mod quic;
+#[cfg(feature = "std")]
+pub(crate) mod ticketer;
+pub(crate) mod tls12;
+pub(crate) mod tls13;
+
+/// A `CryptoProvider` backed by the pure-Rust [RustCrypto] crates.
+///
+/// This also supports X448 key exchange and Ed448 signatures, using the curve448
+/// implementation from [`crrl`].  (The other built-in providers only support these
+/// with the `curve448` crate feature.)
+///
+/// # Security
+///
+/// RSA private keys can only be loaded with the `rustcrypto-rsa-signing` crate feature.
+/// RSA signatures are made with the [`rsa`] crate, which is affected by
+/// [RUSTSEC-2023-0071] (the "Marvin Attack"): its private key operations are not
+/// constant-time, so an attacker who can measure the time taken to make many
+/// signatures may be able to recover the private key.  Prefer ECDSA or EdDSA keys
+/// with this provider, particularly where timing can be observed over a network.
+/// Verifying RSA signatures is not affected.
+///
+/// [RustCrypto]: https://github.com/RustCrypto
+/// [`crrl`]: https://crates.io/crates/crrl
+/// [`rsa`]: https://crates.io/crates/rsa
+/// [RUSTSEC-2023-0071]: https://rustsec.org/advisories/RUSTSEC-2023-0071
+pub fn default_provider() -> CryptoProvider {
+    CryptoProvider {
+        cipher_suites: DEFAULT_CIPHER_SUITES.to_vec(),
+        kx_groups: DEFAULT_KX_GROUPS.to_vec(),
+        signature_verification_algorithms: SUPPORTED_SIG_ALGS,
+        secure_random: &RustCrypto,
+        key_provider: &RustCrypto,
+    }
+}
+
+/// Default crypto provider.
+#[derive(Debug)]
+struct RustCrypto;
+
+impl SecureRandom for RustCrypto {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), GetRandomFailed> {
+        OsRng
+            .try_fill_bytes(buf)
+            .map_err(|_| GetRandomFailed)
+    }
+}
+
+impl KeyProvider for RustCrypto {
+    fn load_private_key(
+        &self,
+        key_der: PrivateKeyDer<'static>,
+    ) -> Result<Arc<dyn SigningKey>, Error> {
+        sign::any_supported_type(&key_der)
+    }
+}
+
+/// The cipher suite configuration that an application should use by default.
+///
+/// This will be [`ALL_CIPHER_SUITES`] sans any supported cipher suites that
+/// shouldn't be enabled by most applications.
+///
+/// This does not contain the AES-CCM suites, which are only useful for
+/// constrained devices.
+pub static DEFAULT_CIPHER_SUITES: &[SupportedCipherSuite] = &[
+    // TLS1.3 suites
+    tls13::TLS13_AES_256_GCM_SHA384,
+    tls13::TLS13_AES_128_GCM_SHA256,
+    tls13::TLS13_CHACHA20_POLY1305_SHA256,
+    // TLS1.2 suites
+    tls12::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+    tls12::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+    tls12::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+    tls12::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+    tls12::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+    tls12::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+];
+
+/// A list of all the cipher suites supported by the rustls RustCrypto provider.
+pub static ALL_CIPHER_SUITES: &[SupportedCipherSuite] = &[
+    // TLS1.3 suites
+    tls13::TLS13_AES_256_GCM_SHA384,
+    tls13::TLS13_AES_128_GCM_SHA256,
+    tls13::TLS13_CHACHA20_POLY1305_SHA256,
+    tls13::TLS13_AES_128_CCM_SHA256,
+    tls13::TLS13_AES_128_CCM_8_SHA256,
+    // TLS1.2 suites
+    tls12::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+    tls12::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+    tls12::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+    tls12::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+    tls12::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+    tls12::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+];
+
+/// All defined cipher suites supported by the RustCrypto provider appear in this module.
+pub mod cipher_suite {
+    pub use super::tls12::{
+        TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256, TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+        TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256, TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+        TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384, TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+    };
+    pub use super::tls13::{
+        TLS13_AES_128_CCM_8_SHA256, TLS13_AES_128_CCM_SHA256, TLS13_AES_128_GCM_SHA256,
+        TLS13_AES_256_GCM_SHA384, TLS13_CHACHA20_POLY1305_SHA256,
+    };
+}
+
+/// A `WebPkiSupportedAlgorithms` value that reflects the signature verification
+/// algorithms implemented in [`verify`].
+pub static SUPPORTED_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms {
+    all: &[
+        verify::ECDSA_P256_SHA256,
+        verify::ECDSA_P256_SHA384,
+        verify::ECDSA_P384_SHA256,
+        verify::ECDSA_P384_SHA384,
+        verify::ED25519,
+        verify::ED448,
+        verify::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+        verify::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+        verify::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+        verify::RSA_PKCS1_2048_8192_SHA256,
+        verify::RSA_PKCS1_2048_8192_SHA384,
+        verify::RSA_PKCS1_2048_8192_SHA512,
+        verify::RSA_PKCS1_2048_8192_SHA256_ABSENT_PARAMS,
+        verify::RSA_PKCS1_2048_8192_SHA384_ABSENT_PARAMS,
+        verify::RSA_PKCS1_2048_8192_SHA512_ABSENT_PARAMS,
+    ],
+    mapping: &[
+        // Note: for TLS1.2 the curve is not fixed by SignatureScheme. For TLS1.3 it is.
+        (
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            &[verify::ECDSA_P384_SHA384, verify::ECDSA_P256_SHA384],
+        ),
+        (
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            &[verify::ECDSA_P256_SHA256, verify::ECDSA_P384_SHA256],
+        ),
+        (SignatureScheme::ED25519, &[verify::ED25519]),
+        (SignatureScheme::ED448, &[verify::ED448]),
+        (
+            SignatureScheme::RSA_PSS_SHA512,
+            &[verify::RSA_PSS_2048_8192_SHA512_LEGACY_KEY],
+        ),
+        (
+            SignatureScheme::RSA_PSS_SHA384,
+            &[verify::RSA_PSS_2048_8192_SHA384_LEGACY_KEY],
+        ),
+        (
+            SignatureScheme::RSA_PSS_SHA256,
+            &[verify::RSA_PSS_2048_8192_SHA256_LEGACY_KEY],
+        ),
+        (
+            SignatureScheme::RSA_PKCS1_SHA512,
+            &[verify::RSA_PKCS1_2048_8192_SHA512],
+        ),
+        (
+            SignatureScheme::RSA_PKCS1_SHA384,
+            &[verify::RSA_PKCS1_2048_8192_SHA384],
+        ),
+        (
+            SignatureScheme::RSA_PKCS1_SHA256,
+            &[verify::RSA_PKCS1_2048_8192_SHA256],
+        ),
+    ],
+};
+
+/// All defined key exchange groups supported by the RustCrypto provider appear in this module.
+///
+/// [`ALL_KX_GROUPS`] is provided as an array of all of these values.
+/// [`DEFAULT_KX_GROUPS`] is provided as an array of this provider's defaults.
+pub mod kx_group {
+    pub use super::kx::{SECP256R1, SECP384R1, X25519};
+    pub use super::pq::{MLKEM768, SECP256R1MLKEM768, X25519MLKEM768};
+    pub use crate::crypto::curve448::X448;
+}
+
+/// A list of the default key exchange groups supported by this provider.
+///
+/// This does not contain MLKEM768; by default MLKEM768 is only offered
+/// in hybrid with X25519.  X448 is also not offered by default.
+pub static DEFAULT_KX_GROUPS: &[&dyn SupportedKxGroup] = &[
+    kx_group::X25519MLKEM768,
+    kx_group::X25519,
+    kx_group::SECP256R1,
+    kx_group::SECP384R1,
+];
+
+/// A list of all the key exchange groups supported by this provider.
+pub static ALL_KX_GROUPS: &[&dyn SupportedKxGroup] = &[
+    kx_group::X25519MLKEM768,
+    kx_group::SECP256R1MLKEM768,
+    kx_group::X25519,
+    kx_group::SECP256R1,
+    kx_group::SECP384R1,
+    kx_group::X448,
+    kx_group::MLKEM768,
+];
+
+#[cfg(feature = "std")]
+pub use ticketer::Ticketer;
+
+pub(super) fn fips() -> bool {
+    false
+}
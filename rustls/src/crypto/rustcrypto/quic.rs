@@ -0,0 +1,271 @@
+use alloc::boxed::Box;
+
+use aes::cipher::consts::U16;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+
+use super::aead;
+use crate::crypto::cipher::{AeadKey, Iv, Nonce};
+use crate::error::Error;
+use crate::quic;
+
+/// A QUIC header protection algorithm.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc9001#section-5.4>
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum HeaderProtectionAlgorithm {
+    Aes128,
+    Aes256,
+    ChaCha20,
+}
+
+pub(crate) struct HeaderProtectionKey(HeaderProtectionKeyInner);
+
+enum HeaderProtectionKeyInner {
+    // The AES key schedules are large, so keep them off the stack.
+    Aes128(Box<aes::Aes128>),
+    Aes256(Box<aes::Aes256>),
+    ChaCha20([u8; 32]),
+}
+
+impl HeaderProtectionKey {
+    pub(crate) fn new(key: AeadKey, alg: HeaderProtectionAlgorithm) -> Self {
+        // safety: the caller arranges that `key` is `aead_key_len()` in bytes, so these
+        // unwraps are safe.
+        let key = key.as_ref();
+        Self(match alg {
+            HeaderProtectionAlgorithm::Aes128 => HeaderProtectionKeyInner::Aes128(Box::new(
+                aes::Aes128::new_from_slice(key).unwrap(),
+            )),
+            HeaderProtectionAlgorithm::Aes256 => HeaderProtectionKeyInner::Aes256(Box::new(
+                aes::Aes256::new_from_slice(key).unwrap(),
+            )),
+            HeaderProtectionAlgorithm::ChaCha20 => {
+                HeaderProtectionKeyInner::ChaCha20(key.try_into().unwrap())
+            }
+        })
+    }
+
+    fn new_mask(&self, sample: &[u8]) -> Result<[u8; 5], ()> {
+        let sample: &[u8; SAMPLE_LEN] = sample.try_into().map_err(|_| ())?;
+        let mut mask = [0u8; 5];
+
+        match &self.0 {
+            // <https://datatracker.ietf.org/doc/html/rfc9001#section-5.4.3>
+            HeaderProtectionKeyInner::Aes128(key) => aes_mask(&**key, sample, &mut mask),
+            HeaderProtectionKeyInner::Aes256(key) => aes_mask(&**key, sample, &mut mask),
+            // <https://datatracker.ietf.org/doc/html/rfc9001#section-5.4.4>
+            HeaderProtectionKeyInner::ChaCha20(key) => {
+                let (counter, nonce) = sample.split_at(4);
+                let counter = u32::from_le_bytes(counter.try_into().unwrap());
+                let mut cipher = chacha20::ChaCha20::new(key.into(), nonce.into());
+                cipher.seek(u64::from(counter) * 64);
+                cipher.apply_keystream(&mut mask);
+            }
+        }
+
+        Ok(mask)
+    }
+
+    fn xor_in_place(
+        &self,
+        sample: &[u8],
+        first: &mut u8,
+        packet_number: &mut [u8],
+        masked: bool,
+    ) -> Result<(), Error> {
+        // This implements "Header Protection Application" almost verbatim.
+        // <https://datatracker.ietf.org/doc/html/rfc9001#section-5.4.1>
+
+        let mask = self
+            .new_mask(sample)
+            .map_err(|_| Error::General("sample of invalid length".into()))?;
+
+        // The `unwrap()` will not panic because `new_mask` returns a
+        // non-empty result.
+        let (first_mask, pn_mask) = mask.split_first().unwrap();
+
+        // It is OK for the `mask` to be longer than `packet_number`,
+        // but a valid `packet_number` will never be longer than `mask`.
+        if packet_number.len() > pn_mask.len() {
+            return Err(Error::General("packet number too long".into()));
+        }
+
+        // Infallible from this point on. Before this point, `first` and
+        // `packet_number` are unchanged.
+
+        const LONG_HEADER_FORM: u8 = 0x80;
+        let bits = match *first & LONG_HEADER_FORM == LONG_HEADER_FORM {
+            true => 0x0f,  // Long header: 4 bits masked
+            false => 0x1f, // Short header: 5 bits masked
+        };
+
+        let first_plain = match masked {
+            // When unmasking, use the packet length bits after unmasking
+            true => *first ^ (first_mask & bits),
+            // When masking, use the packet length bits before masking
+            false => *first,
+        };
+        let pn_len = (first_plain & 0x03) as usize + 1;
+
+        *first ^= first_mask & bits;
+        for (dst, m) in packet_number
+            .iter_mut()
+            .zip(pn_mask)
+            .take(pn_len)
+        {
+            *dst ^= m;
+        }
+
+        Ok(())
+    }
+}
+
+impl quic::HeaderProtectionKey for HeaderProtectionKey {
+    fn encrypt_in_place(
+        &self,
+        sample: &[u8],
+        first: &mut u8,
+        packet_number: &mut [u8],
+    ) -> Result<(), Error> {
+        self.xor_in_place(sample, first, packet_number, false)
+    }
+
+    fn decrypt_in_place(
+        &self,
+        sample: &[u8],
+        first: &mut u8,
+        packet_number: &mut [u8],
+    ) -> Result<(), Error> {
+        self.xor_in_place(sample, first, packet_number, true)
+    }
+
+    #[inline]
+    fn sample_len(&self) -> usize {
+        SAMPLE_LEN
+    }
+}
+
+fn aes_mask(
+    key: &impl BlockEncrypt<BlockSize = U16>,
+    sample: &[u8; SAMPLE_LEN],
+    mask: &mut [u8; 5],
+) {
+    let mut block = aes::Block::from(*sample);
+    key.encrypt_block(&mut block);
+    mask.copy_from_slice(&block[..5]);
+}
+
+const SAMPLE_LEN: usize = 16;
+
+pub(crate) struct PacketKey {
+    /// Encrypts or decrypts a packet's payload
+    key: aead::Key,
+    /// Computes unique nonces for each packet
+    iv: Iv,
+    /// Confidentiality limit (see [`quic::PacketKey::confidentiality_limit`])
+    confidentiality_limit: u64,
+    /// Integrity limit (see [`quic::PacketKey::integrity_limit`])
+    integrity_limit: u64,
+}
+
+impl PacketKey {
+    pub(crate) fn new(
+        key: AeadKey,
+        iv: Iv,
+        confidentiality_limit: u64,
+        integrity_limit: u64,
+        aead_algorithm: aead::Algorithm,
+    ) -> Self {
+        Self {
+            key: aead::Key::new(aead_algorithm, key.as_ref()),
+            iv,
+            confidentiality_limit,
+            integrity_limit,
+        }
+    }
+}
+
+impl quic::PacketKey for PacketKey {
+    fn encrypt_in_place(
+        &self,
+        packet_number: u64,
+        header: &[u8],
+        payload: &mut [u8],
+    ) -> Result<quic::Tag, Error> {
+        let nonce = Nonce::new(&self.iv, packet_number).0;
+        let tag = self
+            .key
+            .seal_in_place_separate_tag(&nonce, header, payload)
+            .map_err(|_| Error::EncryptError)?;
+        Ok(quic::Tag::from(tag.as_ref()))
+    }
+
+    /// Decrypt a QUIC packet
+    ///
+    /// Takes the packet `header`, which is used as the additional authenticated data, and the
+    /// `payload`, which includes the authentication tag.
+    ///
+    /// If the return value is `Ok`, the decrypted payload can be found in `payload`, up to the
+    /// length found in the return value.
+    fn decrypt_in_place<'a>(
+        &self,
+        packet_number: u64,
+        header: &[u8],
+        payload: &'a mut [u8],
+    ) -> Result<&'a [u8], Error> {
+        let nonce = Nonce::new(&self.iv, packet_number).0;
+        let plain = self
+            .key
+            .open_in_place(&nonce, header, payload)
+            .map_err(|_| Error::DecryptError)?;
+        Ok(plain)
+    }
+
+    /// Tag length for the underlying AEAD algorithm
+    #[inline]
+    fn tag_len(&self) -> usize {
+        self.key.algorithm().tag_len()
+    }
+
+    /// Confidentiality limit (see [`quic::PacketKey::confidentiality_limit`])
+    fn confidentiality_limit(&self) -> u64 {
+        self.confidentiality_limit
+    }
+
+    /// Integrity limit (see [`quic::PacketKey::integrity_limit`])
+    fn integrity_limit(&self) -> u64 {
+        self.integrity_limit
+    }
+}
+
+pub(crate) struct KeyBuilder {
+    pub(crate) packet_alg: aead::Algorithm,
+    pub(crate) header_alg: HeaderProtectionAlgorithm,
+    pub(crate) confidentiality_limit: u64,
+    pub(crate) integrity_limit: u64,
+}
+
+impl quic::Algorithm for KeyBuilder {
+    fn packet_key(&self, key: AeadKey, iv: Iv) -> Box<dyn quic::PacketKey> {
+        Box::new(PacketKey::new(
+            key,
+            iv,
+            self.confidentiality_limit,
+            self.integrity_limit,
+            self.packet_alg,
+        ))
+    }
+
+    fn header_protection_key(&self, key: AeadKey) -> Box<dyn quic::HeaderProtectionKey> {
+        Box::new(HeaderProtectionKey::new(key, self.header_alg))
+    }
+
+    fn aead_key_len(&self) -> usize {
+        self.packet_alg.key_len()
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
@@ -0,0 +1,762 @@
+use alloc::boxed::Box;
+use alloc::format;
+#[cfg(feature = "rustcrypto-rsa-signing")]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, SubjectPublicKeyInfoDer, alg_id};
+use rand_core::OsRng;
+#[cfg(feature = "rustcrypto-rsa-signing")]
+use rsa::RsaPrivateKey;
+#[cfg(feature = "rustcrypto-rsa-signing")]
+use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPublicKey};
+use rsa::pkcs8::DecodePrivateKey;
+#[cfg(feature = "rustcrypto-rsa-signing")]
+use rsa::traits::PublicKeyParts;
+#[cfg(feature = "rustcrypto-rsa-signing")]
+use sha2::{Sha256, Sha384, Sha512};
+use signature::{RandomizedSigner, SignatureEncoding};
+
+use crate::crypto::curve448::Ed448SigningKey;
+use crate::crypto::signer::{Signer, SigningKey, public_key_to_spki};
+use crate::enums::{SignatureAlgorithm, SignatureScheme};
+use crate::error::Error;
+use crate::sync::Arc;
+
+/// Parse `der` as any supported key encoding/type, returning
+/// the first which works.
+///
+/// RSA keys are only accepted with the `rustcrypto-rsa-signing` crate feature, because
+/// signing with them is affected by RUSTSEC-2023-0071: see
+/// [`default_provider()`](super::default_provider#security).
+pub fn any_supported_type(der: &PrivateKeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
+    #[cfg(feature = "rustcrypto-rsa-signing")]
+    if let Ok(rsa) = RsaSigningKey::new(der) {
+        return Ok(Arc::new(rsa));
+    }
+
+    if let Ok(ecdsa) = any_ecdsa_type(der) {
+        return Ok(ecdsa);
+    }
+
+    if let PrivateKeyDer::Pkcs8(pkcs8) = der {
+        if let Ok(eddsa) = any_eddsa_type(pkcs8) {
+            return Ok(eddsa);
+        }
+    }
+
+    Err(Error::General(format!(
+        "failed to parse private key as {SUPPORTED_KEY_TYPES}"
+    )))
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+const SUPPORTED_KEY_TYPES: &str = "RSA, ECDSA, or EdDSA";
+#[cfg(not(feature = "rustcrypto-rsa-signing"))]
+const SUPPORTED_KEY_TYPES: &str = "ECDSA or EdDSA";
+
+/// Parse `der` as any ECDSA key type, returning the first which works.
+///
+/// Both SEC1 (PEM section starting with 'BEGIN EC PRIVATE KEY') and PKCS8
+/// (PEM section starting with 'BEGIN PRIVATE KEY') encodings are supported.
+pub fn any_ecdsa_type(der: &PrivateKeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
+    if let Ok(ecdsa_p256) = EcdsaSigningKey::new(der, SignatureScheme::ECDSA_NISTP256_SHA256) {
+        return Ok(Arc::new(ecdsa_p256));
+    }
+
+    if let Ok(ecdsa_p384) = EcdsaSigningKey::new(der, SignatureScheme::ECDSA_NISTP384_SHA384) {
+        return Ok(Arc::new(ecdsa_p384));
+    }
+
+    Err(Error::General(
+        "failed to parse ECDSA private key as PKCS#8 or SEC1".into(),
+    ))
+}
+
+/// Parse `der` as any EdDSA key type, returning the first which works.
+///
+/// Both Ed25519 and Ed448 keys are supported.
+///
+/// Note that, at the time of writing, Ed25519 and Ed448 do not have wide support
+/// in browsers.  They are also not supported by the WebPKI, because the
+/// CA/Browser Forum Baseline Requirements do not support them for publicly
+/// trusted certificates.
+pub fn any_eddsa_type(der: &PrivatePkcs8KeyDer<'_>) -> Result<Arc<dyn SigningKey>, Error> {
+    if let Ok(ed448) = Ed448SigningKey::new(der) {
+        return Ok(Arc::new(ed448));
+    }
+
+    Ok(Arc::new(Ed25519SigningKey::new(
+        der,
+        SignatureScheme::ED25519,
+    )?))
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+/// A `SigningKey` for RSA-PKCS1 or RSA-PSS.
+///
+/// This is used by the test suite, so it must be `pub`, but it isn't part of
+/// the public, stable, API.
+#[doc(hidden)]
+pub struct RsaSigningKey {
+    key: Arc<RsaPrivateKey>,
+    public_key: Vec<u8>,
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+const MIN_RSA_MODULUS_BITS: usize = 2048;
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+static ALL_RSA_SCHEMES: &[SignatureScheme] = &[
+    SignatureScheme::RSA_PSS_SHA512,
+    SignatureScheme::RSA_PSS_SHA384,
+    SignatureScheme::RSA_PSS_SHA256,
+    SignatureScheme::RSA_PKCS1_SHA512,
+    SignatureScheme::RSA_PKCS1_SHA384,
+    SignatureScheme::RSA_PKCS1_SHA256,
+];
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+impl RsaSigningKey {
+    /// Make a new `RsaSigningKey` from a DER encoding, in either
+    /// PKCS#1 or PKCS#8 format.
+    pub fn new(der: &PrivateKeyDer<'_>) -> Result<Self, Error> {
+        let key = match der {
+            PrivateKeyDer::Pkcs1(pkcs1) => {
+                RsaPrivateKey::from_pkcs1_der(pkcs1.secret_pkcs1_der()).map_err(|e| e.to_string())
+            }
+            PrivateKeyDer::Pkcs8(pkcs8) => {
+                RsaPrivateKey::from_pkcs8_der(pkcs8.secret_pkcs8_der()).map_err(|e| e.to_string())
+            }
+            _ => {
+                return Err(Error::General(
+                    "failed to parse RSA private key as either PKCS#1 or PKCS#8".into(),
+                ));
+            }
+        }
+        .map_err(|e| Error::General(format!("failed to parse RSA private key: {e}")))?;
+
+        if key.n().bits() < MIN_RSA_MODULUS_BITS {
+            return Err(Error::General(
+                "failed to parse RSA private key: key too small".into(),
+            ));
+        }
+
+        let public_key = key
+            .to_public_key()
+            .to_pkcs1_der()
+            .map_err(|e| Error::General(format!("failed to encode RSA public key: {e}")))?
+            .into_vec();
+
+        Ok(Self {
+            key: Arc::new(key),
+            public_key,
+        })
+    }
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+impl SigningKey for RsaSigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        ALL_RSA_SCHEMES
+            .iter()
+            .find(|scheme| offered.contains(scheme))
+            .map(|scheme| RsaSigner::new(self.key.clone(), *scheme))
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        Some(public_key_to_spki(
+            &alg_id::RSA_ENCRYPTION,
+            &self.public_key,
+        ))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::RSA
+    }
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+impl Debug for RsaSigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RsaSigningKey")
+            .field("algorithm", &self.algorithm())
+            .finish()
+    }
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+struct RsaSigner {
+    key: Arc<RsaPrivateKey>,
+    scheme: SignatureScheme,
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+impl RsaSigner {
+    fn new(key: Arc<RsaPrivateKey>, scheme: SignatureScheme) -> Box<dyn Signer> {
+        Box::new(Self { key, scheme })
+    }
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+impl Signer for RsaSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        use rsa::{pkcs1v15, pss};
+
+        let key = RsaPrivateKey::clone(&self.key);
+        match self.scheme {
+            SignatureScheme::RSA_PKCS1_SHA256 => pkcs1v15::SigningKey::<Sha256>::new(key)
+                .try_sign_with_rng(&mut OsRng, message)
+                .map(|sig| sig.to_vec()),
+            SignatureScheme::RSA_PKCS1_SHA384 => pkcs1v15::SigningKey::<Sha384>::new(key)
+                .try_sign_with_rng(&mut OsRng, message)
+                .map(|sig| sig.to_vec()),
+            SignatureScheme::RSA_PKCS1_SHA512 => pkcs1v15::SigningKey::<Sha512>::new(key)
+                .try_sign_with_rng(&mut OsRng, message)
+                .map(|sig| sig.to_vec()),
+            SignatureScheme::RSA_PSS_SHA256 => pss::BlindedSigningKey::<Sha256>::new(key)
+                .try_sign_with_rng(&mut OsRng, message)
+                .map(|sig| sig.to_vec()),
+            SignatureScheme::RSA_PSS_SHA384 => pss::BlindedSigningKey::<Sha384>::new(key)
+                .try_sign_with_rng(&mut OsRng, message)
+                .map(|sig| sig.to_vec()),
+            SignatureScheme::RSA_PSS_SHA512 => pss::BlindedSigningKey::<Sha512>::new(key)
+                .try_sign_with_rng(&mut OsRng, message)
+                .map(|sig| sig.to_vec()),
+            _ => unreachable!(),
+        }
+        .map_err(|_| Error::General("signing failed".to_string()))
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+impl Debug for RsaSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RsaSigner")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
+/// A SigningKey that uses exactly one TLS-level SignatureScheme.
+///
+/// Compare this to RsaSigningKey, which for a particular key is
+/// willing to sign with several algorithms.  This is quite poor
+/// cryptography practice, but is necessary because a given RSA key
+/// is expected to work in TLS1.2 (PKCS#1 signatures) and TLS1.3
+/// (PSS signatures) -- nobody is willing to obtain certificates for
+/// different protocol versions.
+///
+/// Currently this is only implemented for ECDSA keys.
+struct EcdsaSigningKey {
+    key: Arc<EcdsaKey>,
+    public_key: Vec<u8>,
+    scheme: SignatureScheme,
+}
+
+enum EcdsaKey {
+    P256(p256::ecdsa::SigningKey),
+    P384(p384::ecdsa::SigningKey),
+}
+
+impl EcdsaSigningKey {
+    /// Make a new `ECDSASigningKey` from a DER encoding in PKCS#8 or SEC1
+    /// format, expecting a key usable with precisely the given signature
+    /// scheme.
+    fn new(der: &PrivateKeyDer<'_>, scheme: SignatureScheme) -> Result<Self, ()> {
+        let key = match (scheme, der) {
+            (SignatureScheme::ECDSA_NISTP256_SHA256, PrivateKeyDer::Sec1(sec1)) => {
+                p256::SecretKey::from_sec1_der(sec1.secret_sec1_der())
+                    .map(|key| EcdsaKey::P256(key.into()))
+                    .map_err(|_| ())?
+            }
+            (SignatureScheme::ECDSA_NISTP256_SHA256, PrivateKeyDer::Pkcs8(pkcs8)) => {
+                p256::ecdsa::SigningKey::from_pkcs8_der(pkcs8.secret_pkcs8_der())
+                    .map(EcdsaKey::P256)
+                    .map_err(|_| ())?
+            }
+            (SignatureScheme::ECDSA_NISTP384_SHA384, PrivateKeyDer::Sec1(sec1)) => {
+                p384::SecretKey::from_sec1_der(sec1.secret_sec1_der())
+                    .map(|key| EcdsaKey::P384(key.into()))
+                    .map_err(|_| ())?
+            }
+            (SignatureScheme::ECDSA_NISTP384_SHA384, PrivateKeyDer::Pkcs8(pkcs8)) => {
+                p384::ecdsa::SigningKey::from_pkcs8_der(pkcs8.secret_pkcs8_der())
+                    .map(EcdsaKey::P384)
+                    .map_err(|_| ())?
+            }
+            _ => return Err(()),
+        };
+
+        let public_key = match &key {
+            EcdsaKey::P256(key) => key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+            EcdsaKey::P384(key) => key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+        };
+
+        Ok(Self {
+            key: Arc::new(key),
+            public_key,
+            scheme,
+        })
+    }
+}
+
+impl SigningKey for EcdsaSigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if offered.contains(&self.scheme) {
+            Some(Box::new(EcdsaSigner {
+                key: self.key.clone(),
+                scheme: self.scheme,
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        let id = match *self.key {
+            EcdsaKey::P256(_) => alg_id::ECDSA_P256,
+            EcdsaKey::P384(_) => alg_id::ECDSA_P384,
+        };
+
+        Some(public_key_to_spki(&id, &self.public_key))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.scheme.algorithm()
+    }
+}
+
+impl Debug for EcdsaSigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EcdsaSigningKey")
+            .field("algorithm", &self.algorithm())
+            .finish()
+    }
+}
+
+struct EcdsaSigner {
+    key: Arc<EcdsaKey>,
+    scheme: SignatureScheme,
+}
+
+impl Signer for EcdsaSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        match &*self.key {
+            EcdsaKey::P256(key) => {
+                RandomizedSigner::<p256::ecdsa::DerSignature>::try_sign_with_rng(
+                    key, &mut OsRng, message,
+                )
+                .map(|sig| sig.to_vec())
+            }
+            EcdsaKey::P384(key) => {
+                RandomizedSigner::<p384::ecdsa::DerSignature>::try_sign_with_rng(
+                    key, &mut OsRng, message,
+                )
+                .map(|sig| sig.to_vec())
+            }
+        }
+        .map_err(|_| Error::General("signing failed".into()))
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+impl Debug for EcdsaSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EcdsaSigner")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
+/// A SigningKey that uses exactly one TLS-level SignatureScheme.
+///
+/// Compare this to RsaSigningKey, which for a particular key is
+/// willing to sign with several algorithms.  This is quite poor
+/// cryptography practice, but is necessary because a given RSA key
+/// is expected to work in TLS1.2 (PKCS#1 signatures) and TLS1.3
+/// (PSS signatures) -- nobody is willing to obtain certificates for
+/// different protocol versions.
+///
+/// Currently this is only implemented for Ed25519 keys.
+struct Ed25519SigningKey {
+    key: Arc<ed25519_dalek::SigningKey>,
+    scheme: SignatureScheme,
+}
+
+impl Ed25519SigningKey {
+    /// Make a new `Ed25519SigningKey` from a DER encoding in PKCS#8 format,
+    /// expecting a key usable with precisely the given signature scheme.
+    fn new(der: &PrivatePkcs8KeyDer<'_>, scheme: SignatureScheme) -> Result<Self, Error> {
+        match ed25519_dalek::SigningKey::from_pkcs8_der(der.secret_pkcs8_der()) {
+            Ok(key) => Ok(Self {
+                key: Arc::new(key),
+                scheme,
+            }),
+            Err(e) => Err(Error::General(format!(
+                "failed to parse Ed25519 private key: {e}"
+            ))),
+        }
+    }
+}
+
+impl SigningKey for Ed25519SigningKey {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if offered.contains(&self.scheme) {
+            Some(Box::new(Ed25519Signer {
+                key: self.key.clone(),
+                scheme: self.scheme,
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        Some(public_key_to_spki(
+            &alg_id::ED25519,
+            self.key.verifying_key().as_bytes(),
+        ))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.scheme.algorithm()
+    }
+}
+
+impl Debug for Ed25519SigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed25519SigningKey")
+            .field("algorithm", &self.algorithm())
+            .finish()
+    }
+}
+
+struct Ed25519Signer {
+    key: Arc<ed25519_dalek::SigningKey>,
+    scheme: SignatureScheme,
+}
+
+impl Signer for Ed25519Signer {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        use ed25519_dalek::Signer as _;
+
+        Ok(self.key.sign(message).to_vec())
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+}
+
+impl Debug for Ed25519Signer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed25519Signer")
+            .field("scheme", &self.scheme)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    #[cfg(feature = "rustcrypto-rsa-signing")]
+    use pki_types::PrivatePkcs1KeyDer;
+    use pki_types::PrivateSec1KeyDer;
+
+    use super::*;
+
+    #[test]
+    fn can_load_ecdsa_nistp256_pkcs8() {
+        let key =
+            PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/nistp256key.pkcs8.der")[..]);
+        assert!(any_eddsa_type(&key).is_err());
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_ok());
+    }
+
+    #[test]
+    fn can_load_ecdsa_nistp256_sec1() {
+        let key = PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(
+            &include_bytes!("../../testdata/nistp256key.der")[..],
+        ));
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_ok());
+    }
+
+    #[test]
+    fn can_sign_ecdsa_nistp256() {
+        let key = PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(
+            &include_bytes!("../../testdata/nistp256key.der")[..],
+        ));
+
+        let k = any_supported_type(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "EcdsaSigningKey { algorithm: ECDSA }");
+        assert_eq!(k.algorithm(), SignatureAlgorithm::ECDSA);
+
+        assert!(
+            k.choose_scheme(&[SignatureScheme::RSA_PKCS1_SHA256])
+                .is_none()
+        );
+        assert!(
+            k.choose_scheme(&[SignatureScheme::ECDSA_NISTP384_SHA384])
+                .is_none()
+        );
+        let s = k
+            .choose_scheme(&[SignatureScheme::ECDSA_NISTP256_SHA256])
+            .unwrap();
+        assert_eq!(
+            format!("{s:?}"),
+            "EcdsaSigner { scheme: ECDSA_NISTP256_SHA256 }"
+        );
+        assert_eq!(s.scheme(), SignatureScheme::ECDSA_NISTP256_SHA256);
+        // nb. signature is variable length and asn.1-encoded
+        let sig = s.sign(b"hello").unwrap();
+        assert!(sig.starts_with(&[0x30]));
+
+        let k = EcdsaSigningKey::new(&key, SignatureScheme::ECDSA_NISTP256_SHA256).unwrap();
+        assert_verifies(super::super::verify::ECDSA_P256_SHA256, &k.public_key, &sig);
+    }
+
+    #[test]
+    fn can_load_ecdsa_nistp384_pkcs8() {
+        let key =
+            PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/nistp384key.pkcs8.der")[..]);
+        assert!(any_eddsa_type(&key).is_err());
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_ok());
+    }
+
+    #[test]
+    fn can_load_ecdsa_nistp384_sec1() {
+        let key = PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(
+            &include_bytes!("../../testdata/nistp384key.der")[..],
+        ));
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_ok());
+    }
+
+    #[test]
+    fn can_sign_ecdsa_nistp384() {
+        let key = PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(
+            &include_bytes!("../../testdata/nistp384key.der")[..],
+        ));
+
+        let k = any_supported_type(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "EcdsaSigningKey { algorithm: ECDSA }");
+        assert_eq!(k.algorithm(), SignatureAlgorithm::ECDSA);
+
+        assert!(
+            k.choose_scheme(&[SignatureScheme::RSA_PKCS1_SHA256])
+                .is_none()
+        );
+        assert!(
+            k.choose_scheme(&[SignatureScheme::ECDSA_NISTP256_SHA256])
+                .is_none()
+        );
+        let s = k
+            .choose_scheme(&[SignatureScheme::ECDSA_NISTP384_SHA384])
+            .unwrap();
+        assert_eq!(
+            format!("{s:?}"),
+            "EcdsaSigner { scheme: ECDSA_NISTP384_SHA384 }"
+        );
+        assert_eq!(s.scheme(), SignatureScheme::ECDSA_NISTP384_SHA384);
+        // nb. signature is variable length and asn.1-encoded
+        let sig = s.sign(b"hello").unwrap();
+        assert!(sig.starts_with(&[0x30]));
+
+        let k = EcdsaSigningKey::new(&key, SignatureScheme::ECDSA_NISTP384_SHA384).unwrap();
+        assert_verifies(super::super::verify::ECDSA_P384_SHA384, &k.public_key, &sig);
+    }
+
+    #[test]
+    fn can_load_eddsa_pkcs8() {
+        let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/eddsakey.der")[..]);
+        assert!(any_eddsa_type(&key).is_ok());
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_err());
+    }
+
+    #[test]
+    fn can_sign_eddsa() {
+        let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/eddsakey.der")[..]);
+
+        let k = any_eddsa_type(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "Ed25519SigningKey { algorithm: ED25519 }");
+        assert_eq!(k.algorithm(), SignatureAlgorithm::ED25519);
+
+        assert!(
+            k.choose_scheme(&[SignatureScheme::RSA_PKCS1_SHA256])
+                .is_none()
+        );
+        assert!(
+            k.choose_scheme(&[SignatureScheme::ECDSA_NISTP256_SHA256])
+                .is_none()
+        );
+        let s = k
+            .choose_scheme(&[SignatureScheme::ED25519])
+            .unwrap();
+        assert_eq!(format!("{s:?}"), "Ed25519Signer { scheme: ED25519 }");
+        assert_eq!(s.scheme(), SignatureScheme::ED25519);
+        let sig = s.sign(b"hello").unwrap();
+        assert_eq!(sig.len(), 64);
+
+        let k = Ed25519SigningKey::new(&key, SignatureScheme::ED25519).unwrap();
+        assert_verifies(
+            super::super::verify::ED25519,
+            k.key.verifying_key().as_bytes(),
+            &sig,
+        );
+    }
+
+    #[test]
+    fn can_load_ed448_pkcs8() {
+        let key = PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/ed448key.der")[..]);
+        assert!(any_eddsa_type(&key).is_ok());
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_err());
+    }
+
+    #[test]
+    fn can_load_rsa2048_pkcs8() {
+        let key =
+            PrivatePkcs8KeyDer::from(&include_bytes!("../../testdata/rsa2048key.pkcs8.der")[..]);
+        assert!(any_eddsa_type(&key).is_err());
+        let key = PrivateKeyDer::Pkcs8(key);
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_err());
+    }
+
+    #[cfg(feature = "rustcrypto-rsa-signing")]
+    #[test]
+    fn can_load_rsa2048_pkcs1() {
+        let key = PrivateKeyDer::Pkcs1(PrivatePkcs1KeyDer::from(
+            &include_bytes!("../../testdata/rsa2048key.pkcs1.der")[..],
+        ));
+        assert!(any_supported_type(&key).is_ok());
+        assert!(any_ecdsa_type(&key).is_err());
+    }
+
+    #[cfg(feature = "rustcrypto-rsa-signing")]
+    #[test]
+    fn can_sign_rsa2048() {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+            &include_bytes!("../../testdata/rsa2048key.pkcs8.der")[..],
+        ));
+
+        let k = any_supported_type(&key).unwrap();
+        assert_eq!(format!("{k:?}"), "RsaSigningKey { algorithm: RSA }");
+        assert_eq!(k.algorithm(), SignatureAlgorithm::RSA);
+
+        assert!(
+            k.choose_scheme(&[SignatureScheme::ECDSA_NISTP256_SHA256])
+                .is_none()
+        );
+        assert!(
+            k.choose_scheme(&[SignatureScheme::ED25519])
+                .is_none()
+        );
+
+        let s = k
+            .choose_scheme(&[SignatureScheme::RSA_PSS_SHA256])
+            .unwrap();
+        assert_eq!(format!("{s:?}"), "RsaSigner { scheme: RSA_PSS_SHA256 }");
+        assert_eq!(s.scheme(), SignatureScheme::RSA_PSS_SHA256);
+        assert_eq!(s.sign(b"hello").unwrap().len(), 256);
+
+        use super::super::verify;
+        let public_key = RsaSigningKey::new(&key)
+            .unwrap()
+            .public_key;
+        for (scheme, alg) in [
+            (
+                SignatureScheme::RSA_PKCS1_SHA256,
+                verify::RSA_PKCS1_2048_8192_SHA256,
+            ),
+            (
+                SignatureScheme::RSA_PKCS1_SHA384,
+                verify::RSA_PKCS1_2048_8192_SHA384,
+            ),
+            (
+                SignatureScheme::RSA_PKCS1_SHA512,
+                verify::RSA_PKCS1_2048_8192_SHA512,
+            ),
+            (
+                SignatureScheme::RSA_PSS_SHA256,
+                verify::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+            ),
+            (
+                SignatureScheme::RSA_PSS_SHA384,
+                verify::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+            ),
+            (
+                SignatureScheme::RSA_PSS_SHA512,
+                verify::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+            ),
+        ] {
+            let sig = k
+                .choose_scheme(&[scheme])
+                .unwrap()
+                .sign(b"hello")
+                .unwrap();
+            assert_verifies(alg, &public_key, &sig);
+        }
+    }
+
+    #[test]
+    fn cannot_load_invalid_pkcs8_encoding() {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(&b"invalid"[..]));
+        assert_eq!(
+            any_supported_type(&key).err(),
+            Some(Error::General(format!(
+                "failed to parse private key as {SUPPORTED_KEY_TYPES}"
+            )))
+        );
+        assert_eq!(
+            any_ecdsa_type(&key).err(),
+            Some(Error::General(
+                "failed to parse ECDSA private key as PKCS#8 or SEC1".into()
+            ))
+        );
+        #[cfg(feature = "rustcrypto-rsa-signing")]
+        assert!(matches!(
+            RsaSigningKey::new(&key).err(),
+            Some(Error::General(msg)) if msg.starts_with("failed to parse RSA private key: ")
+        ));
+    }
+
+    fn assert_verifies(
+        alg: &dyn pki_types::SignatureVerificationAlgorithm,
+        public_key: &[u8],
+        sig: &[u8],
+    ) {
+        alg.verify_signature(public_key, b"hello", sig)
+            .unwrap();
+        assert!(
+            alg.verify_signature(public_key, b"goodbye", sig)
+                .is_err()
+        );
+    }
+}
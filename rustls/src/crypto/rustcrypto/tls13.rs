@@ -0,0 +1,245 @@
+use alloc::boxed::Box;
+
+use super::aead;
+use crate::crypto::cipher::{
+    AeadKey, InboundOpaqueMessage, Iv, MessageDecrypter, MessageEncrypter, Nonce,
+    Tls13AeadAlgorithm, UnsupportedOperationError, make_tls13_aad,
+};
+use crate::crypto::tls13::HkdfUsingHmac;
+use crate::enums::{CipherSuite, ContentType, ProtocolVersion};
+use crate::error::Error;
+use crate::msgs::message::{
+    InboundPlainMessage, OutboundOpaqueMessage, OutboundPlainMessage, PrefixedPayload,
+};
+use crate::suites::{CipherSuiteCommon, ConnectionTrafficSecrets, SupportedCipherSuite};
+use crate::tls13::Tls13CipherSuite;
+use crate::version::TLS13_VERSION;
+
+/// The TLS1.3 ciphersuite TLS_CHACHA20_POLY1305_SHA256
+pub static TLS13_CHACHA20_POLY1305_SHA256: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(TLS13_CHACHA20_POLY1305_SHA256_INTERNAL);
+
+pub(crate) static TLS13_CHACHA20_POLY1305_SHA256_INTERNAL: &Tls13CipherSuite = &Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_CHACHA20_POLY1305_SHA256,
+        hash_provider: &super::hash::SHA256,
+        // ref: <https://www.ietf.org/archive/id/draft-irtf-cfrg-aead-limits-08.html#section-5.2.1>
+        confidentiality_limit: u64::MAX,
+    },
+    protocol_version: TLS13_VERSION,
+    hkdf_provider: &HkdfUsingHmac(&super::hmac::HMAC_SHA256),
+    aead_alg: &Tls13Aead(aead::Algorithm::ChaCha20Poly1305),
+    quic: Some(&super::quic::KeyBuilder {
+        packet_alg: aead::Algorithm::ChaCha20Poly1305,
+        header_alg: super::quic::HeaderProtectionAlgorithm::ChaCha20,
+        // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-6.6>
+        confidentiality_limit: u64::MAX,
+        // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-6.6>
+        integrity_limit: 1 << 36,
+    }),
+};
+
+/// The TLS1.3 ciphersuite TLS_AES_256_GCM_SHA384
+pub static TLS13_AES_256_GCM_SHA384: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(&Tls13CipherSuite {
+        common: CipherSuiteCommon {
+            suite: CipherSuite::TLS13_AES_256_GCM_SHA384,
+            hash_provider: &super::hash::SHA384,
+            confidentiality_limit: 1 << 24,
+        },
+        protocol_version: TLS13_VERSION,
+        hkdf_provider: &HkdfUsingHmac(&super::hmac::HMAC_SHA384),
+        aead_alg: &Tls13Aead(aead::Algorithm::Aes256Gcm),
+        quic: Some(&super::quic::KeyBuilder {
+            packet_alg: aead::Algorithm::Aes256Gcm,
+            header_alg: super::quic::HeaderProtectionAlgorithm::Aes256,
+            // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-b.1.1>
+            confidentiality_limit: 1 << 23,
+            // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-b.1.2>
+            integrity_limit: 1 << 52,
+        }),
+    });
+
+/// The TLS1.3 ciphersuite TLS_AES_128_GCM_SHA256
+pub static TLS13_AES_128_GCM_SHA256: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(TLS13_AES_128_GCM_SHA256_INTERNAL);
+
+pub(crate) static TLS13_AES_128_GCM_SHA256_INTERNAL: &Tls13CipherSuite = &Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_AES_128_GCM_SHA256,
+        hash_provider: &super::hash::SHA256,
+        confidentiality_limit: 1 << 24,
+    },
+    protocol_version: TLS13_VERSION,
+    hkdf_provider: &HkdfUsingHmac(&super::hmac::HMAC_SHA256),
+    aead_alg: &Tls13Aead(aead::Algorithm::Aes128Gcm),
+    quic: Some(&super::quic::KeyBuilder {
+        packet_alg: aead::Algorithm::Aes128Gcm,
+        header_alg: super::quic::HeaderProtectionAlgorithm::Aes128,
+        // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-b.1.1>
+        confidentiality_limit: 1 << 23,
+        // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-b.1.2>
+        integrity_limit: 1 << 52,
+    }),
+};
+
+/// The TLS1.3 ciphersuite TLS_AES_128_CCM_SHA256
+///
+/// This is not included in [`super::DEFAULT_CIPHER_SUITES`], and must be
+/// explicitly enabled.
+pub static TLS13_AES_128_CCM_SHA256: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(&Tls13CipherSuite {
+        common: CipherSuiteCommon {
+            suite: CipherSuite::TLS13_AES_128_CCM_SHA256,
+            hash_provider: &super::hash::SHA256,
+            // CCM makes two AES calls per block, so this is half of the AES-GCM limit.
+            // ref: <https://www.ietf.org/archive/id/draft-irtf-cfrg-aead-limits-08.html#section-5.3>
+            confidentiality_limit: 1 << 23,
+        },
+        protocol_version: TLS13_VERSION,
+        hkdf_provider: &HkdfUsingHmac(&super::hmac::HMAC_SHA256),
+        aead_alg: &Tls13Aead(aead::Algorithm::Aes128Ccm),
+        quic: Some(&super::quic::KeyBuilder {
+            packet_alg: aead::Algorithm::Aes128Ccm,
+            header_alg: super::quic::HeaderProtectionAlgorithm::Aes128,
+            // 2^21.5, ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-b.2>
+            confidentiality_limit: 2_965_820,
+            // 2^21.5, ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-b.2>
+            integrity_limit: 2_965_820,
+        }),
+    });
+
+/// The TLS1.3 ciphersuite TLS_AES_128_CCM_8_SHA256
+///
+/// This has a truncated, 64-bit authentication tag, and is intended only for
+/// constrained devices.  It is not included in [`super::DEFAULT_CIPHER_SUITES`],
+/// and must be explicitly enabled.
+pub static TLS13_AES_128_CCM_8_SHA256: SupportedCipherSuite =
+    SupportedCipherSuite::Tls13(&Tls13CipherSuite {
+        common: CipherSuiteCommon {
+            suite: CipherSuite::TLS13_AES_128_CCM_8_SHA256,
+            hash_provider: &super::hash::SHA256,
+            // The shorter tag does not affect confidentiality, so this is the same as AES-CCM.
+            // There is no integrity limit for TCP-TLS: a single forgery attempt ends the
+            // connection.
+            // ref: <https://www.ietf.org/archive/id/draft-irtf-cfrg-aead-limits-08.html#section-5.3>
+            confidentiality_limit: 1 << 23,
+        },
+        protocol_version: TLS13_VERSION,
+        hkdf_provider: &HkdfUsingHmac(&super::hmac::HMAC_SHA256),
+        aead_alg: &Tls13Aead(aead::Algorithm::Aes128Ccm8),
+        // Not usable with QUIC, where the integrity limit of a 64-bit tag is too low.
+        // ref: <https://datatracker.ietf.org/doc/html/rfc9001#section-5.3>
+        quic: None,
+    });
+
+struct Tls13Aead(aead::Algorithm);
+
+impl Tls13AeadAlgorithm for Tls13Aead {
+    fn encrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageEncrypter> {
+        Box::new(Tls13MessageEncrypter {
+            enc_key: aead::Key::new(self.0, key.as_ref()),
+            iv,
+        })
+    }
+
+    fn decrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageDecrypter> {
+        Box::new(Tls13MessageDecrypter {
+            dec_key: aead::Key::new(self.0, key.as_ref()),
+            iv,
+        })
+    }
+
+    fn key_len(&self) -> usize {
+        self.0.key_len()
+    }
+
+    fn extract_keys(
+        &self,
+        key: AeadKey,
+        iv: Iv,
+    ) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        Ok(match self.0 {
+            aead::Algorithm::Aes128Gcm => ConnectionTrafficSecrets::Aes128Gcm { key, iv },
+            aead::Algorithm::Aes256Gcm => ConnectionTrafficSecrets::Aes256Gcm { key, iv },
+            aead::Algorithm::ChaCha20Poly1305 => {
+                ConnectionTrafficSecrets::Chacha20Poly1305 { key, iv }
+            }
+            aead::Algorithm::Aes128Ccm | aead::Algorithm::Aes128Ccm8 => {
+                return Err(UnsupportedOperationError);
+            }
+        })
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+struct Tls13MessageEncrypter {
+    enc_key: aead::Key,
+    iv: Iv,
+}
+
+struct Tls13MessageDecrypter {
+    dec_key: aead::Key,
+    iv: Iv,
+}
+
+impl MessageEncrypter for Tls13MessageEncrypter {
+    fn encrypt(
+        &mut self,
+        msg: OutboundPlainMessage<'_>,
+        seq: u64,
+    ) -> Result<OutboundOpaqueMessage, Error> {
+        let total_len = self.encrypted_payload_len(msg.payload.len());
+        let mut payload = PrefixedPayload::with_capacity(total_len);
+
+        let nonce = Nonce::new(&self.iv, seq).0;
+        let aad = make_tls13_aad(total_len);
+        payload.extend_from_chunks(&msg.payload);
+        payload.extend_from_slice(&msg.typ.to_array());
+
+        let tag = self
+            .enc_key
+            .seal_in_place_separate_tag(&nonce, &aad, payload.as_mut())
+            .map_err(|_| Error::EncryptError)?;
+        payload.extend_from_slice(&tag);
+
+        Ok(OutboundOpaqueMessage::new(
+            ContentType::ApplicationData,
+            // Note: all TLS 1.3 application data records use TLSv1_2 (0x0303) as the legacy record
+            // protocol version, see https://www.rfc-editor.org/rfc/rfc8446#section-5.1
+            ProtocolVersion::TLSv1_2,
+            payload,
+        ))
+    }
+
+    fn encrypted_payload_len(&self, payload_len: usize) -> usize {
+        payload_len + 1 + self.enc_key.algorithm().tag_len()
+    }
+}
+
+impl MessageDecrypter for Tls13MessageDecrypter {
+    fn decrypt<'a>(
+        &mut self,
+        mut msg: InboundOpaqueMessage<'a>,
+        seq: u64,
+    ) -> Result<InboundPlainMessage<'a>, Error> {
+        let payload = &mut msg.payload;
+        if payload.len() < self.dec_key.algorithm().tag_len() {
+            return Err(Error::DecryptError);
+        }
+
+        let nonce = Nonce::new(&self.iv, seq).0;
+        let aad = make_tls13_aad(payload.len());
+        let plain_len = self
+            .dec_key
+            .open_in_place(&nonce, &aad, payload)
+            .map_err(|_| Error::DecryptError)?
+            .len();
+
+        payload.truncate(plain_len);
+        msg.into_tls13_unpadded_message()
+    }
+}
@@ -0,0 +1,341 @@
+use core::fmt;
+
+use pki_types::{AlgorithmIdentifier, InvalidSignature, SignatureVerificationAlgorithm, alg_id};
+use rsa::RsaPublicKey;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::traits::PublicKeyParts;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use signature::Verifier;
+use signature::hazmat::PrehashVerifier;
+
+use crate::crypto::curve448::Ed448Verify;
+
+/// ECDSA signatures using the P-256 curve and SHA-256.
+pub static ECDSA_P256_SHA256: &dyn SignatureVerificationAlgorithm = &EcdsaVerify {
+    curve: Curve::P256,
+    hash: Hash::Sha256,
+    public_key_alg_id: alg_id::ECDSA_P256,
+    signature_alg_id: alg_id::ECDSA_SHA256,
+};
+
+/// ECDSA signatures using the P-256 curve and SHA-384. Deprecated.
+pub static ECDSA_P256_SHA384: &dyn SignatureVerificationAlgorithm = &EcdsaVerify {
+    curve: Curve::P256,
+    hash: Hash::Sha384,
+    public_key_alg_id: alg_id::ECDSA_P256,
+    signature_alg_id: alg_id::ECDSA_SHA384,
+};
+
+/// ECDSA signatures using the P-384 curve and SHA-256. Deprecated.
+pub static ECDSA_P384_SHA256: &dyn SignatureVerificationAlgorithm = &EcdsaVerify {
+    curve: Curve::P384,
+    hash: Hash::Sha256,
+    public_key_alg_id: alg_id::ECDSA_P384,
+    signature_alg_id: alg_id::ECDSA_SHA256,
+};
+
+/// ECDSA signatures using the P-384 curve and SHA-384.
+pub static ECDSA_P384_SHA384: &dyn SignatureVerificationAlgorithm = &EcdsaVerify {
+    curve: Curve::P384,
+    hash: Hash::Sha384,
+    public_key_alg_id: alg_id::ECDSA_P384,
+    signature_alg_id: alg_id::ECDSA_SHA384,
+};
+
+/// ED25519 signatures according to RFC 8410
+pub static ED25519: &dyn SignatureVerificationAlgorithm = &Ed25519Verify;
+
+/// ED448 signatures according to RFC 8410
+pub static ED448: &dyn SignatureVerificationAlgorithm = &Ed448Verify;
+
+/// RSA PKCS#1 1.5 signatures using SHA-256 for keys of 2048-8192 bits.
+pub static RSA_PKCS1_2048_8192_SHA256: &dyn SignatureVerificationAlgorithm = &RsaVerify {
+    padding: RsaPadding::Pkcs1,
+    hash: Hash::Sha256,
+    signature_alg_id: alg_id::RSA_PKCS1_SHA256,
+};
+
+/// RSA PKCS#1 1.5 signatures using SHA-384 for keys of 2048-8192 bits.
+pub static RSA_PKCS1_2048_8192_SHA384: &dyn SignatureVerificationAlgorithm = &RsaVerify {
+    padding: RsaPadding::Pkcs1,
+    hash: Hash::Sha384,
+    signature_alg_id: alg_id::RSA_PKCS1_SHA384,
+};
+
+/// RSA PKCS#1 1.5 signatures using SHA-512 for keys of 2048-8192 bits.
+pub static RSA_PKCS1_2048_8192_SHA512: &dyn SignatureVerificationAlgorithm = &RsaVerify {
+    padding: RsaPadding::Pkcs1,
+    hash: Hash::Sha512,
+    signature_alg_id: alg_id::RSA_PKCS1_SHA512,
+};
+
+/// RSA PKCS#1 1.5 signatures using SHA-256 for keys of 2048-8192 bits,
+/// with illegally absent AlgorithmIdentifier parameters.
+///
+/// RFC 4055 says on sha256WithRSAEncryption and company:
+///
+/// >   When any of these four object identifiers appears within an
+/// >   AlgorithmIdentifier, the parameters MUST be NULL.  Implementations
+/// >   MUST accept the parameters being absent as well as present.
+///
+/// This algorithm covers the absent case, [`RSA_PKCS1_2048_8192_SHA256`] covers
+/// the present case.
+pub static RSA_PKCS1_2048_8192_SHA256_ABSENT_PARAMS: &dyn SignatureVerificationAlgorithm =
+    &RsaVerify {
+        padding: RsaPadding::Pkcs1,
+        hash: Hash::Sha256,
+        signature_alg_id: AlgorithmIdentifier::from_slice(&[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+        ]),
+    };
+
+/// RSA PKCS#1 1.5 signatures using SHA-384 for keys of 2048-8192 bits,
+/// with illegally absent AlgorithmIdentifier parameters.
+///
+/// See [`RSA_PKCS1_2048_8192_SHA256_ABSENT_PARAMS`] for more details.
+pub static RSA_PKCS1_2048_8192_SHA384_ABSENT_PARAMS: &dyn SignatureVerificationAlgorithm =
+    &RsaVerify {
+        padding: RsaPadding::Pkcs1,
+        hash: Hash::Sha384,
+        signature_alg_id: AlgorithmIdentifier::from_slice(&[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c,
+        ]),
+    };
+
+/// RSA PKCS#1 1.5 signatures using SHA-512 for keys of 2048-8192 bits,
+/// with illegally absent AlgorithmIdentifier parameters.
+///
+/// See [`RSA_PKCS1_2048_8192_SHA256_ABSENT_PARAMS`] for more details.
+pub static RSA_PKCS1_2048_8192_SHA512_ABSENT_PARAMS: &dyn SignatureVerificationAlgorithm =
+    &RsaVerify {
+        padding: RsaPadding::Pkcs1,
+        hash: Hash::Sha512,
+        signature_alg_id: AlgorithmIdentifier::from_slice(&[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d,
+        ]),
+    };
+
+/// RSA PSS signatures using SHA-256 for keys of 2048-8192 bits and of
+/// type rsaEncryption; see [RFC 4055 Section 1.2].
+///
+/// [RFC 4055 Section 1.2]: https://tools.ietf.org/html/rfc4055#section-1.2
+pub static RSA_PSS_2048_8192_SHA256_LEGACY_KEY: &dyn SignatureVerificationAlgorithm = &RsaVerify {
+    padding: RsaPadding::Pss,
+    hash: Hash::Sha256,
+    signature_alg_id: alg_id::RSA_PSS_SHA256,
+};
+
+/// RSA PSS signatures using SHA-384 for keys of 2048-8192 bits and of
+/// type rsaEncryption; see [RFC 4055 Section 1.2].
+///
+/// [RFC 4055 Section 1.2]: https://tools.ietf.org/html/rfc4055#section-1.2
+pub static RSA_PSS_2048_8192_SHA384_LEGACY_KEY: &dyn SignatureVerificationAlgorithm = &RsaVerify {
+    padding: RsaPadding::Pss,
+    hash: Hash::Sha384,
+    signature_alg_id: alg_id::RSA_PSS_SHA384,
+};
+
+/// RSA PSS signatures using SHA-512 for keys of 2048-8192 bits and of
+/// type rsaEncryption; see [RFC 4055 Section 1.2].
+///
+/// [RFC 4055 Section 1.2]: https://tools.ietf.org/html/rfc4055#section-1.2
+pub static RSA_PSS_2048_8192_SHA512_LEGACY_KEY: &dyn SignatureVerificationAlgorithm = &RsaVerify {
+    padding: RsaPadding::Pss,
+    hash: Hash::Sha512,
+    signature_alg_id: alg_id::RSA_PSS_SHA512,
+};
+
+#[derive(Clone, Copy, Debug)]
+enum Curve {
+    P256,
+    P384,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Hash {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum RsaPadding {
+    Pkcs1,
+    Pss,
+}
+
+struct EcdsaVerify {
+    curve: Curve,
+    hash: Hash,
+    public_key_alg_id: AlgorithmIdentifier,
+    signature_alg_id: AlgorithmIdentifier,
+}
+
+impl EcdsaVerify {
+    fn verify_prehash(
+        &self,
+        public_key: &[u8],
+        prehash: &[u8],
+        signature: &[u8],
+    ) -> Result<(), signature::Error> {
+        match self.curve {
+            Curve::P256 => {
+                let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?;
+                let sig = p256::ecdsa::Signature::from_der(signature)?;
+                key.verify_prehash(prehash, &sig)
+            }
+            Curve::P384 => {
+                let key = p384::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?;
+                let sig = p384::ecdsa::Signature::from_der(signature)?;
+                key.verify_prehash(prehash, &sig)
+            }
+        }
+    }
+}
+
+impl SignatureVerificationAlgorithm for EcdsaVerify {
+    fn verify_signature(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), InvalidSignature> {
+        // Only the uncompressed point encoding is accepted.
+        if !matches!(public_key.first(), Some(0x04)) {
+            return Err(InvalidSignature);
+        }
+
+        let result = match self.hash {
+            Hash::Sha256 => self.verify_prehash(public_key, &Sha256::digest(message), signature),
+            Hash::Sha384 => self.verify_prehash(public_key, &Sha384::digest(message), signature),
+            Hash::Sha512 => self.verify_prehash(public_key, &Sha512::digest(message), signature),
+        };
+        result.map_err(|_| InvalidSignature)
+    }
+
+    fn public_key_alg_id(&self) -> AlgorithmIdentifier {
+        self.public_key_alg_id
+    }
+
+    fn signature_alg_id(&self) -> AlgorithmIdentifier {
+        self.signature_alg_id
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+impl fmt::Debug for EcdsaVerify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ECDSA_{:?}_{:?}", self.curve, self.hash)
+    }
+}
+
+struct Ed25519Verify;
+
+impl SignatureVerificationAlgorithm for Ed25519Verify {
+    fn verify_signature(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), InvalidSignature> {
+        let public_key = public_key
+            .try_into()
+            .map_err(|_| InvalidSignature)?;
+        let key =
+            ed25519_dalek::VerifyingKey::from_bytes(public_key).map_err(|_| InvalidSignature)?;
+        let sig = ed25519_dalek::Signature::from_slice(signature).map_err(|_| InvalidSignature)?;
+        key.verify(message, &sig)
+            .map_err(|_| InvalidSignature)
+    }
+
+    fn public_key_alg_id(&self) -> AlgorithmIdentifier {
+        alg_id::ED25519
+    }
+
+    fn signature_alg_id(&self) -> AlgorithmIdentifier {
+        alg_id::ED25519
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+impl fmt::Debug for Ed25519Verify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ED25519")
+    }
+}
+
+struct RsaVerify {
+    padding: RsaPadding,
+    hash: Hash,
+    signature_alg_id: AlgorithmIdentifier,
+}
+
+impl RsaVerify {
+    fn verify_with_key(
+        &self,
+        key: RsaPublicKey,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), signature::Error> {
+        use rsa::{pkcs1v15, pss};
+
+        match (self.padding, self.hash) {
+            (RsaPadding::Pkcs1, Hash::Sha256) => pkcs1v15::VerifyingKey::<Sha256>::new(key)
+                .verify(message, &pkcs1v15::Signature::try_from(signature)?),
+            (RsaPadding::Pkcs1, Hash::Sha384) => pkcs1v15::VerifyingKey::<Sha384>::new(key)
+                .verify(message, &pkcs1v15::Signature::try_from(signature)?),
+            (RsaPadding::Pkcs1, Hash::Sha512) => pkcs1v15::VerifyingKey::<Sha512>::new(key)
+                .verify(message, &pkcs1v15::Signature::try_from(signature)?),
+            (RsaPadding::Pss, Hash::Sha256) => pss::VerifyingKey::<Sha256>::new(key)
+                .verify(message, &pss::Signature::try_from(signature)?),
+            (RsaPadding::Pss, Hash::Sha384) => pss::VerifyingKey::<Sha384>::new(key)
+                .verify(message, &pss::Signature::try_from(signature)?),
+            (RsaPadding::Pss, Hash::Sha512) => pss::VerifyingKey::<Sha512>::new(key)
+                .verify(message, &pss::Signature::try_from(signature)?),
+        }
+    }
+}
+
+impl SignatureVerificationAlgorithm for RsaVerify {
+    fn verify_signature(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), InvalidSignature> {
+        let key = RsaPublicKey::from_pkcs1_der(public_key).map_err(|_| InvalidSignature)?;
+        if !(MIN_RSA_MODULUS_BITS..=MAX_RSA_MODULUS_BITS).contains(&key.n().bits()) {
+            return Err(InvalidSignature);
+        }
+
+        self.verify_with_key(key, message, signature)
+            .map_err(|_| InvalidSignature)
+    }
+
+    fn public_key_alg_id(&self) -> AlgorithmIdentifier {
+        alg_id::RSA_ENCRYPTION
+    }
+
+    fn signature_alg_id(&self) -> AlgorithmIdentifier {
+        self.signature_alg_id
+    }
+
+    fn fips(&self) -> bool {
+        super::fips()
+    }
+}
+
+impl fmt::Debug for RsaVerify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RSA_{:?}_2048_8192_{:?}", self.padding, self.hash)
+    }
+}
+
+const MIN_RSA_MODULUS_BITS: usize = 2048;
+const MAX_RSA_MODULUS_BITS: usize = 8192;
@@ -8,6 +8,7 @@ use super::CryptoProvider;
 use crate::client::ResolvesClientCert;
 use crate::enums::{SignatureAlgorithm, SignatureScheme};
 use crate::error::{Error, InconsistentKeys};
+use crate::msgs::handshake::DistinguishedName;
 use crate::server::{ClientHello, ParsedCertificate, ResolvesServerCert};
 use crate::sync::Arc;
 use crate::x509;
@@ -243,7 +244,64 @@ impl CertifiedKey {
     }
 }
 
-#[cfg_attr(not(any(feature = "aws-lc-rs", feature = "ring")), allow(dead_code))]
+/// A [`CertifiedKey`], with the issuer of each certificate in its chain.
+///
+/// This is for resolvers which choose among several keys, using the certificate
+/// authorities named by the peer.
+#[derive(Debug)]
+pub(crate) struct IssuedKey {
+    pub(crate) key: Arc<CertifiedKey>,
+    issuers: Vec<DistinguishedName>,
+}
+
+impl IssuedKey {
+    /// Check the certificate chain is parsable, and collect its issuers.
+    ///
+    /// This is not security-sensitive: it is the local peer attempting to
+    /// detect accidental misconfiguration.
+    pub(crate) fn new(key: CertifiedKey) -> Result<Self, Error> {
+        // Check the end-entity certificate even if the chain has no other certificates.
+        ParsedCertificate::try_from(key.end_entity_cert()?)?;
+
+        let issuers = key
+            .cert_chain
+            .iter()
+            .map(|cert| {
+                ParsedCertificate::try_from(cert)
+                    .map(|cert| DistinguishedName::in_sequence(cert.0.issuer()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            key: Arc::new(key),
+            issuers,
+        })
+    }
+
+    /// A raw public key, which has no issuers.
+    #[cfg(any(feature = "std", feature = "hashbrown"))]
+    pub(crate) fn raw_public_key(key: CertifiedKey) -> Result<Self, Error> {
+        key.end_entity_cert()?;
+        Ok(Self {
+            key: Arc::new(key),
+            issuers: Vec::new(),
+        })
+    }
+
+    /// Return true if any certificate in the chain was issued by one of `names`.
+    pub(crate) fn issued_by_any<'a>(&self, names: impl IntoIterator<Item = &'a [u8]>) -> bool {
+        names.into_iter().any(|name| {
+            self.issuers
+                .iter()
+                .any(|issuer| issuer.as_ref() == name)
+        })
+    }
+}
+
+#[cfg_attr(
+    not(any(feature = "aws-lc-rs", feature = "ring", feature = "rustcrypto")),
+    allow(dead_code)
+)]
 pub(crate) fn public_key_to_spki(
     alg_id: &AlgorithmIdentifier,
     public_key: impl AsRef<[u8]>,
@@ -0,0 +1,264 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::common_state::CommonState;
+use crate::enums::AlertDescription;
+use crate::error::{Error, InvalidMessage, PeerMisbehaved};
+use crate::msgs::base::Payload;
+use crate::msgs::enums::ExtensionType;
+use crate::msgs::handshake::{CertificatePayloadTls13, UnknownExtension};
+use crate::sync::Arc;
+
+/// The handshake messages which can carry a [`CustomExtension`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionContext {
+    /// Sent by the client in its `ClientHello`, in all protocol versions.
+    ///
+    /// If Encrypted Client Hello is offered, the extension is sent only in
+    /// the encrypted inner `ClientHello`.
+    ClientHello,
+
+    /// Sent by a TLS1.3 server in `EncryptedExtensions`, in response to the
+    /// same extension in the client's `ClientHello`.
+    EncryptedExtensions,
+
+    /// Sent by a TLS1.3 server in a `CertificateRequest`.
+    CertificateRequest,
+
+    /// Sent in the end-entity entry of a TLS1.3 `Certificate` message, in
+    /// response to the same extension in the peer's `ClientHello` (for servers)
+    /// or `CertificateRequest` (for clients).
+    Certificate,
+}
+
+/// An application-defined TLS extension.
+///
+/// Register these with [`ClientConfig::custom_extensions`] or
+/// [`ServerConfig::custom_extensions`].  rustls then sends the extension in
+/// each message named by [`CustomExtension::contexts()`], for which
+/// [`CustomExtension::send()`] produces a payload.
+///
+/// Received payloads are passed to [`CustomExtension::receive()`], and can
+/// later be retrieved with [`CommonState::received_custom_extension()`].
+/// Extensions received in the peer's `ClientHello` or `CertificateRequest`
+/// which are not registered for that context are ignored, as for any other
+/// unknown extension.  Any other unregistered or unsolicited extension is a
+/// fatal error.
+///
+/// The extension type must not be one that rustls implements or otherwise
+/// names, and each extension type may only be registered once.  Otherwise
+/// creating a connection fails.
+///
+/// [`ClientConfig::custom_extensions`]: crate::ClientConfig::custom_extensions
+/// [`ServerConfig::custom_extensions`]: crate::ServerConfig::custom_extensions
+pub trait CustomExtension: Debug + Send + Sync {
+    /// The extension type codepoint.
+    fn extension_type(&self) -> u16;
+
+    /// The messages this extension may be sent and received in.
+    fn contexts(&self) -> &[ExtensionContext];
+
+    /// Produce the payload to send in `context`, or `None` to omit the extension.
+    ///
+    /// `peer_payload` is the payload of this extension received from the peer,
+    /// to which this is a response.  It is always `None` for
+    /// [`ExtensionContext::ClientHello`] and [`ExtensionContext::CertificateRequest`].
+    /// For other contexts, this is only called if the peer sent the extension.
+    fn send(&self, context: ExtensionContext, peer_payload: Option<&[u8]>) -> Option<Vec<u8>>;
+
+    /// Consume the payload of this extension, received from the peer in `context`.
+    ///
+    /// Returning an error aborts the handshake.
+    fn receive(&self, context: ExtensionContext, payload: &[u8]) -> Result<(), Error> {
+        let _ = (context, payload);
+        Ok(())
+    }
+}
+
+/// Custom extensions sent and received on one connection.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CustomExtensionState {
+    sent: Vec<(ExtensionContext, ExtensionType)>,
+    received: Vec<(ExtensionContext, ExtensionType, Vec<u8>)>,
+}
+
+impl CustomExtensionState {
+    pub(crate) fn received(&self, context: ExtensionContext, typ: u16) -> Option<&[u8]> {
+        let typ = ExtensionType::from(typ);
+        self.received
+            .iter()
+            .find(|(c, t, _)| *c == context && *t == typ)
+            .map(|(_, _, payload)| payload.as_slice())
+    }
+
+    /// Extension types we sent in `context`.
+    pub(crate) fn sent(&self, context: ExtensionContext) -> Vec<ExtensionType> {
+        self.sent
+            .iter()
+            .filter(|(c, _)| *c == context)
+            .map(|(_, t)| *t)
+            .collect()
+    }
+}
+
+/// Checks the extension types of `registered` are unique and unknown to rustls.
+pub(crate) fn validate(registered: &[Arc<dyn CustomExtension>]) -> Result<(), Error> {
+    for (i, ext) in registered.iter().enumerate() {
+        let typ = ext.extension_type();
+        if !matches!(ExtensionType::from(typ), ExtensionType::Unknown(_)) {
+            return Err(Error::General(alloc::format!(
+                "custom extension type {typ:#06x} is reserved"
+            )));
+        }
+
+        if registered[..i]
+            .iter()
+            .any(|prev| prev.extension_type() == typ)
+        {
+            return Err(Error::General(alloc::format!(
+                "custom extension type {typ:#06x} registered more than once"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Produce the custom extensions to send in `context`.
+///
+/// If `peer` is `Some`, only extensions present in it are sent, as a response.
+pub(crate) fn emit(
+    common: &mut CommonState,
+    registered: &[Arc<dyn CustomExtension>],
+    context: ExtensionContext,
+    peer: Option<&[UnknownExtension]>,
+) -> Vec<UnknownExtension> {
+    common
+        .custom_extensions
+        .sent
+        .retain(|(c, _)| *c != context);
+
+    let mut out = Vec::new();
+    for ext in registered_for(registered, context) {
+        let typ = ExtensionType::from(ext.extension_type());
+        let peer_payload = match peer {
+            Some(peer) => match peer.iter().find(|p| p.typ == typ) {
+                Some(p) => Some(p.payload.bytes()),
+                None => continue,
+            },
+            None => None,
+        };
+
+        if let Some(payload) = ext.send(context, peer_payload) {
+            common
+                .custom_extensions
+                .sent
+                .push((context, typ));
+            out.push(UnknownExtension {
+                typ,
+                payload: Payload::new(payload),
+            });
+        }
+    }
+
+    out
+}
+
+/// Process the unknown extensions `received` from the peer in `context`.
+///
+/// If `solicited_by` is `None`, extensions which are not registered for `context`
+/// are ignored.  Otherwise, every extension must be registered for `context`, and
+/// have been sent by us in `solicited_by`.
+pub(crate) fn receive(
+    common: &mut CommonState,
+    registered: &[Arc<dyn CustomExtension>],
+    context: ExtensionContext,
+    received: &[UnknownExtension],
+    solicited_by: Option<ExtensionContext>,
+) -> Result<(), Error> {
+    common
+        .custom_extensions
+        .received
+        .retain(|(c, _, _)| *c != context);
+
+    for ext in received {
+        let handler = registered_for(registered, context)
+            .find(|reg| ExtensionType::from(reg.extension_type()) == ext.typ);
+
+        let handler = match (handler, solicited_by) {
+            (Some(handler), Some(solicited_by))
+                if common
+                    .custom_extensions
+                    .sent
+                    .contains(&(solicited_by, ext.typ)) =>
+            {
+                handler
+            }
+            (Some(handler), None) => handler,
+            (_, None) => continue,
+            (_, Some(_)) => return Err(unsolicited(common, context)),
+        };
+
+        if let Err(err) = handler.receive(context, ext.payload.bytes()) {
+            return Err(common.send_fatal_alert(AlertDescription::IllegalParameter, err));
+        }
+
+        common
+            .custom_extensions
+            .received
+            .push((context, ext.typ, ext.payload.bytes().to_vec()));
+    }
+
+    Ok(())
+}
+
+/// Process the custom extensions in a received TLS1.3 `Certificate` message.
+///
+/// These are only allowed in the end-entity entry, in response to `solicited_by`.
+pub(crate) fn receive_certificate(
+    common: &mut CommonState,
+    registered: &[Arc<dyn CustomExtension>],
+    certificate: &CertificatePayloadTls13<'_>,
+    solicited_by: ExtensionContext,
+) -> Result<(), Error> {
+    let mut entries = certificate.entries.iter();
+    let end_entity = match entries.next() {
+        Some(entry) => &entry.extensions.unknown_extensions[..],
+        None => &[],
+    };
+
+    if entries.any(|entry| {
+        !entry
+            .extensions
+            .unknown_extensions
+            .is_empty()
+    }) {
+        return Err(unsolicited(common, ExtensionContext::Certificate));
+    }
+
+    receive(
+        common,
+        registered,
+        ExtensionContext::Certificate,
+        end_entity,
+        Some(solicited_by),
+    )
+}
+
+fn unsolicited(common: &mut CommonState, context: ExtensionContext) -> Error {
+    let err = match context {
+        ExtensionContext::Certificate => InvalidMessage::UnknownCertificateExtension.into(),
+        _ => Error::from(PeerMisbehaved::UnsolicitedEncryptedExtension),
+    };
+    common.send_fatal_alert(AlertDescription::UnsupportedExtension, err)
+}
+
+fn registered_for(
+    registered: &[Arc<dyn CustomExtension>],
+    context: ExtensionContext,
+) -> impl Iterator<Item = &Arc<dyn CustomExtension>> {
+    registered
+        .iter()
+        .filter(move |ext| ext.contexts().contains(&context))
+}
@@ -544,6 +544,16 @@ impl SignatureScheme {
             SignatureAlgorithm::Anonymous | SignatureAlgorithm::RSA | SignatureAlgorithm::DSA
         )
     }
+
+    /// Whether a particular `SignatureScheme` is allowed for TLS protocol signatures
+    /// in TLS1.2.
+    ///
+    /// ML-DSA is only defined for use in TLS1.3.
+    ///
+    /// See <https://datatracker.ietf.org/doc/html/draft-ietf-tls-mldsa-00#section-3>
+    pub(crate) fn supported_in_tls12(&self) -> bool {
+        !matches!(self, Self::ML_DSA_44 | Self::ML_DSA_65 | Self::ML_DSA_87)
+    }
 }
 
 enum_builder! {
@@ -653,5 +663,22 @@ mod tests {
         assert!(SignatureScheme::from(0x081a).supported_in_tls13());
         assert!(SignatureScheme::from(0x081b).supported_in_tls13());
         assert!(SignatureScheme::from(0x081c).supported_in_tls13());
+
+        // ml-dsa
+        assert!(SignatureScheme::ML_DSA_44.supported_in_tls13());
+        assert!(SignatureScheme::ML_DSA_65.supported_in_tls13());
+        assert!(SignatureScheme::ML_DSA_87.supported_in_tls13());
+    }
+
+    #[test]
+    fn tls12_signature_restrictions() {
+        assert!(!SignatureScheme::ML_DSA_44.supported_in_tls12());
+        assert!(!SignatureScheme::ML_DSA_65.supported_in_tls12());
+        assert!(!SignatureScheme::ML_DSA_87.supported_in_tls12());
+
+        assert!(SignatureScheme::RSA_PKCS1_SHA256.supported_in_tls12());
+        assert!(SignatureScheme::ECDSA_NISTP256_SHA256.supported_in_tls12());
+        assert!(SignatureScheme::ED25519.supported_in_tls12());
+        assert!(SignatureScheme::RSA_PSS_SHA256.supported_in_tls12());
     }
 }
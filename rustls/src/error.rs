@@ -501,6 +501,15 @@ pub enum CertificateError {
         presented: Vec<ExtendedKeyPurpose>,
     },
 
+    /// The certificate does not assert a certificate policy required by the application.
+    MissingCertificatePolicy {
+        /// Certificate policy OID that was required by the application.
+        required: Vec<usize>,
+        /// Certificate policy OIDs that were asserted by the certificate in the peer's
+        /// path which lacks the required policy.
+        presented: Vec<Vec<usize>>,
+    },
+
     /// The OCSP response provided to the verifier was invalid.
     ///
     /// This should be returned from [`ServerCertVerifier::verify_server_cert()`]
@@ -515,6 +524,31 @@ pub enum CertificateError {
     /// reasons.
     ApplicationVerificationFailure,
 
+    /// The certificate chain is valid, but none of its certificates has a
+    /// public key matching the pins configured for the server.
+    ///
+    /// See [`SpkiPinningVerifier`].
+    ///
+    /// [`SpkiPinningVerifier`]: crate::client::SpkiPinningVerifier
+    PinMismatch,
+
+    /// The certificate is not a valid SPIFFE X509-SVID.
+    ///
+    /// It does not have exactly one URI subject alternative name, that name is not
+    /// a valid SPIFFE ID, or the certificate is a CA certificate.
+    ///
+    /// See [`SpiffeId::from_certificate()`].
+    ///
+    /// [`SpiffeId::from_certificate()`]: crate::client::SpiffeId::from_certificate
+    InvalidSvid,
+
+    /// The server's public key differs from the one previously recorded for its name.
+    ///
+    /// See [`TofuVerifier`].
+    ///
+    /// [`TofuVerifier`]: crate::client::TofuVerifier
+    KeyChanged,
+
     /// Any other error.
     ///
     /// This can be used by custom verifiers to expose the underlying error
@@ -610,8 +644,21 @@ impl PartialEq<Self> for CertificateError {
                     presented: right_presented,
                 },
             ) => (left_required, left_presented) == (right_required, right_presented),
+            (
+                MissingCertificatePolicy {
+                    required: left_required,
+                    presented: left_presented,
+                },
+                MissingCertificatePolicy {
+                    required: right_required,
+                    presented: right_presented,
+                },
+            ) => (left_required, left_presented) == (right_required, right_presented),
             (InvalidOcspResponse, InvalidOcspResponse) => true,
             (ApplicationVerificationFailure, ApplicationVerificationFailure) => true,
+            (PinMismatch, PinMismatch) => true,
+            (InvalidSvid, InvalidSvid) => true,
+            (KeyChanged, KeyChanged) => true,
             (UnknownRevocationStatus, UnknownRevocationStatus) => true,
             (ExpiredRevocationList, ExpiredRevocationList) => true,
             (
@@ -639,7 +686,10 @@ impl From<CertificateError> for AlertDescription {
             BadEncoding
             | UnhandledCriticalExtension
             | NotValidForName
-            | NotValidForNameContext { .. } => Self::BadCertificate,
+            | NotValidForNameContext { .. }
+            | PinMismatch
+            | InvalidSvid
+            | KeyChanged => Self::BadCertificate,
             // RFC 5246/RFC 8446
             // certificate_expired
             //  A certificate has expired or **is not currently valid**.
@@ -659,7 +709,9 @@ impl From<CertificateError> for AlertDescription {
             | UnsupportedSignatureAlgorithm
             | UnsupportedSignatureAlgorithmContext { .. }
             | UnsupportedSignatureAlgorithmForPublicKeyContext { .. } => Self::DecryptError,
-            InvalidPurpose | InvalidPurposeContext { .. } => Self::UnsupportedCertificate,
+            InvalidPurpose | InvalidPurposeContext { .. } | MissingCertificatePolicy { .. } => {
+                Self::UnsupportedCertificate
+            }
             ApplicationVerificationFailure => Self::AccessDenied,
             // RFC 5246/RFC 8446
             // certificate_unknown
@@ -760,11 +812,37 @@ impl fmt::Display for CertificateError {
                 Ok(())
             }
 
+            Self::MissingCertificatePolicy {
+                required,
+                presented,
+            } => {
+                write!(f, "certificate does not assert required policy ")?;
+                write_oid(f, required)?;
+                write!(f, ", asserts ")?;
+                for (i, policy) in presented.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_oid(f, policy)?;
+                }
+                Ok(())
+            }
+
             other => write!(f, "{other:?}"),
         }
     }
 }
 
+fn write_oid(f: &mut fmt::Formatter<'_>, values: &[usize]) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ".")?;
+        }
+        write!(f, "{value}")?;
+    }
+    Ok(())
+}
+
 impl From<CertificateError> for Error {
     #[inline]
     fn from(e: CertificateError) -> Self {
@@ -1283,6 +1361,21 @@ mod tests {
             ApplicationVerificationFailure
         );
         assert_eq!(InvalidOcspResponse, InvalidOcspResponse);
+        assert_eq!(PinMismatch, PinMismatch);
+        assert_eq!(InvalidSvid, InvalidSvid);
+        assert_eq!(KeyChanged, KeyChanged);
+        let policy = MissingCertificatePolicy {
+            required: vec![1, 2, 3],
+            presented: vec![vec![1, 2, 4]],
+        };
+        assert_eq!(policy, policy);
+        assert_ne!(
+            policy,
+            MissingCertificatePolicy {
+                required: vec![1, 2, 3],
+                presented: vec![],
+            }
+        );
         let other = Other(OtherError(
             #[cfg(feature = "std")]
             Arc::from(Box::from("")),
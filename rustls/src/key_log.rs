@@ -33,6 +33,15 @@ pub trait KeyLog: Debug + Send + Sync {
     ///   from the client in a TLSv1.3 session.
     /// - `EXPORTER_SECRET`: `secret` is the post-handshake exporter secret
     ///   in a TLSv1.3 session.
+    /// - `ECH_SECRET`: `secret` is the HPKE shared secret used to encrypt the
+    ///   inner ClientHello when offering encrypted client hello (ECH).
+    /// - `ECH_CONFIG`: `secret` is the encoded `ECHConfig` used to encrypt the
+    ///   inner ClientHello when offering ECH.  This is not secret, but is needed
+    ///   alongside `ECH_SECRET` to decrypt the inner ClientHello.
+    ///
+    /// For `ECH_SECRET` and `ECH_CONFIG`, `client_random` is that of the outer
+    /// ClientHello.  For all other labels it is that of the ClientHello that was
+    /// used for the handshake, which is the inner ClientHello if ECH was accepted.
     ///
     /// These strings are selected to match the NSS key log format:
     /// <https://nss-crypto.org/reference/security/nss/legacy/key_log_format/index.html>
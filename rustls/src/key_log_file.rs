@@ -25,7 +25,10 @@ impl KeyLogFileInner {
             };
         };
 
-        #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+        #[cfg_attr(
+            not(any(feature = "log", feature = "tracing")),
+            allow(unused_variables)
+        )]
         let file = match OpenOptions::new()
             .append(true)
             .create(true)
@@ -93,7 +96,10 @@ impl KeyLogFile {
 
 impl KeyLog for KeyLogFile {
     fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
-        #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+        #[cfg_attr(
+            not(any(feature = "log", feature = "tracing")),
+            allow(unused_variables)
+        )]
         match self
             .0
             .lock()
@@ -119,6 +125,8 @@ impl Debug for KeyLogFile {
 
 #[cfg(all(test, target_os = "linux"))]
 mod tests {
+    use std::format;
+
     use super::*;
 
     fn init() {
@@ -149,6 +157,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_writes_nss_key_log_format() {
+        init();
+        let path = std::env::temp_dir().join(format!("rustls-keylog-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut inner = KeyLogFileInner::new(Some(path.clone().into()));
+        inner
+            .try_write("ECH_SECRET", &[0xaa, 0x01], &[0xbb, 0x02])
+            .unwrap();
+        inner
+            .try_write("ECH_CONFIG", &[0xaa, 0x01], &[0xfe, 0x0d, 0x00])
+            .unwrap();
+        drop(inner);
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "ECH_SECRET aa01 bb02\nECH_CONFIG aa01 fe0d00\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_env_var_cannot_be_written() {
         init();
@@ -20,6 +20,10 @@
 //! [`ring`] is also available via the `ring` crate feature: see
 //! [the supported `ring` target platforms][ring-target-platforms].
 //!
+//! For targets that cannot build C or assembly, a provider written entirely in Rust is available
+//! via the `rustcrypto` crate feature.  It uses the [RustCrypto](https://github.com/RustCrypto)
+//! crates for cryptography, plus [`crrl`](https://crates.io/crates/crrl) for curve448.
+//!
 //! By providing a custom instance of the [`crypto::CryptoProvider`] struct, you
 //! can replace all cryptography dependencies of rustls.  This is a route to being portable
 //! to a wider set of architectures and environments, or compliance requirements.  See the
@@ -45,10 +49,11 @@
 //!
 //! #### Built-in providers
 //!
-//! Rustls ships with two built-in providers controlled by associated crate features:
+//! Rustls ships with three built-in providers controlled by associated crate features:
 //!
 //!   * [`aws-lc-rs`] - available with the `aws-lc-rs` crate feature enabled
 //!   * [`ring`] - available with the `ring` crate feature enabled
+//!   * [`RustCrypto`] - available with the `rustcrypto` crate feature enabled
 //!
 //! See the documentation for [`crypto::CryptoProvider`] for details on how providers are
 //! selected.
@@ -288,11 +293,43 @@
 //!   Note that aws-lc-rs has additional build-time dependencies like cmake.
 //!   See [the documentation](https://aws.github.io/aws-lc-rs/requirements/index.html) for details.
 //!
+//! - `aws-lc-rs-unstable`: enables the `aws-lc-rs` crate feature, plus algorithms that
+//!   aws-lc-rs only offers through its unstable API.  Currently this lets the aws-lc-rs
+//!   provider load ML-DSA private keys, for signing in TLS1.3 only.  ML-DSA signatures are
+//!   not verified by default: to accept ML-DSA certificates, add ML-DSA to the provider's
+//!   `signature_verification_algorithms`, as `rustls-post-quantum` does.
+//!   Not available with `fips`.
+//!
 //! - `ring`: makes the rustls crate depend on the *ring* crate for cryptography.
 //!   Use `rustls::crypto::ring::default_provider().install_default()` to
 //!   use it as the default `CryptoProvider`, or provide it explicitly
 //!   when making a `ClientConfig` or `ServerConfig`.
 //!
+//! - `ring-hpke`: enables the `ring` crate feature, plus HPKE (for ECH) in the *ring*
+//!   provider.  *ring* cannot load X25519 or P-256 private keys, so this also makes the
+//!   rustls crate depend on the pure-Rust `x25519-dalek` and `p256` crates.
+//!
+//! - `ring-mlkem`: enables the `ring` crate feature, plus the `X25519MLKEM768` and `MLKEM768`
+//!   key exchange groups in the *ring* provider.  *ring* has no ML-KEM, so this makes the
+//!   rustls crate depend on the pure-Rust `ml-kem` crate.
+//!
+//! - `rustcrypto`: makes the rustls crate depend on the pure-Rust RustCrypto crates for
+//!   cryptography.  Use `rustls::crypto::rustcrypto::default_provider().install_default()` to
+//!   use it as the default `CryptoProvider`, or provide it explicitly
+//!   when making a `ClientConfig` or `ServerConfig`.  It verifies RSA signatures, but
+//!   cannot load RSA private keys unless `rustcrypto-rsa-signing` is also enabled.
+//!
+//! - `rustcrypto-rsa-signing`: enables the `rustcrypto` crate feature, and lets its provider
+//!   load RSA private keys.  RSA signing with this provider is affected by an unfixed timing
+//!   side-channel: see `rustls::crypto::rustcrypto::default_provider()`.
+//!
+//! - `curve448`: adds the `X448` key exchange group (in `ALL_KX_GROUPS`, but not offered by
+//!   default) and Ed448 signing and verification to the *ring* and aws-lc-rs providers.
+//!   Neither implements curve448, so this makes the rustls crate depend on the pure-Rust
+//!   [`crrl`](https://crates.io/crates/crrl) crate, which the `rustcrypto` provider always
+//!   uses for these.  None of this is FIPS-approved: with `fips`, Ed448 signatures are not
+//!   verified by default.
+//!
 //! - `fips`: enable support for FIPS140-3-approved cryptography, via the [`aws-lc-rs`] crate.
 //!   This feature enables the `aws-lc-rs` crate feature, which makes the rustls crate depend
 //!   on [aws-lc-rs](https://github.com/aws/aws-lc-rs).  It also changes the default
@@ -300,7 +337,7 @@
 //!
 //!   See [manual::_06_fips] for more details.
 //!
-//! - `custom-provider`: disables implicit use of built-in providers (`aws-lc-rs` or `ring`). This forces
+//! - `custom-provider`: disables implicit use of built-in providers (`aws-lc-rs`, `ring` or `rustcrypto`). This forces
 //!   applications to manually install one, for instance, when using a custom `CryptoProvider`.
 //!
 //! - `log` (enabled by default): make the rustls crate depend on the `log` crate.
@@ -308,6 +345,16 @@
 //!   and protocol-level errors at `warn!` and `error!` level.  The log messages do not
 //!   contain secret key data, and so are safe to archive without affecting session security.
 //!
+//! - `tracing`: make the rustls crate depend on the `tracing` crate.  The messages described
+//!   for the `log` feature are emitted as `tracing` events instead, and each connection gets
+//!   a span that is entered while it processes handshake messages.  The span records the
+//!   server name: on the client, when the connection is created, and on the server, once the
+//!   `ClientHello` is received.  Key events, such as handshake completion and alerts, carry
+//!   structured fields (negotiated version, cipher suite, key exchange group, alert description,
+//!   etc); without this feature, these fields are appended to the `log` message.  If the `log`
+//!   feature is also enabled, events are forwarded to `log` when no `tracing` subscriber is
+//!   installed.
+//!
 //! - `brotli`: uses the `brotli` crate for RFC8879 certificate compression support.
 //!
 //! - `zlib`: uses the `zlib-rs` crate for RFC8879 certificate compression support.
@@ -377,11 +424,47 @@ use crate::crypto::CryptoProvider;
 #[allow(unused_extern_crates)]
 extern crate test;
 
-// log for logging (optional).
-#[cfg(feature = "log")]
-use log;
+// log for logging (optional).  Events may be written in the `tracing` style, with
+// `key = ?value` fields before a message without arguments: the fields are then
+// appended to the message.
+#[cfg(all(feature = "log", not(feature = "tracing")))]
+mod log {
+    macro_rules! event {
+        ($level:ident, [$($fields:tt)*], $($key:ident).+ = ?$value:expr, $($rest:tt)+) => {
+            $crate::log::event!($level, [$($fields)* ($($key).+, "{:?}", $value)], $($rest)+)
+        };
+        ($level:ident, [$($fields:tt)*], $($key:ident).+ = %$value:expr, $($rest:tt)+) => {
+            $crate::log::event!($level, [$($fields)* ($($key).+, "{}", $value)], $($rest)+)
+        };
+        ($level:ident, [$($fields:tt)*], $($key:ident).+ = $value:expr, $($rest:tt)+) => {
+            $crate::log::event!($level, [$($fields)* ($($key).+, "{}", $value)], $($rest)+)
+        };
+        ($level:ident, [], $($arg:tt)+) => {
+            ::log::$level!($($arg)+)
+        };
+        ($level:ident, [$(($($key:ident).+, $fmt:literal, $value:expr))+], $message:literal) => {
+            ::log::$level!(
+                concat!($message, ":" $(, " ", stringify!($($key).+), "=", $fmt)+)
+                $(, $value)+
+            )
+        };
+    }
+
+    macro_rules! trace    ( ($($tt:tt)*) => { $crate::log::event!(trace, [], $($tt)*) } );
+    macro_rules! debug    ( ($($tt:tt)*) => { $crate::log::event!(debug, [], $($tt)*) } );
+    macro_rules! error    ( ($($tt:tt)*) => { $crate::log::event!(error, [], $($tt)*) } );
+    macro_rules! _warn    ( ($($tt:tt)*) => { $crate::log::event!(warn, [], $($tt)*) } );
+    pub(crate) use {_warn as warn, debug, error, event, trace};
+}
+
+// tracing for structured logging (optional).  When enabled, this takes
+// over the messages that would otherwise go through `log`.
+#[cfg(feature = "tracing")]
+mod log {
+    pub(crate) use tracing::{debug, error, trace, warn};
+}
 
-#[cfg(not(feature = "log"))]
+#[cfg(not(any(feature = "log", feature = "tracing")))]
 mod log {
     macro_rules! trace    ( ($($tt:tt)*) => {{}} );
     macro_rules! debug    ( ($($tt:tt)*) => {{}} );
@@ -411,6 +494,7 @@ pub mod compress;
 mod conn;
 /// Crypto provider interface.
 pub mod crypto;
+mod custom_extension;
 mod error;
 mod hash_hs;
 #[cfg(any(feature = "std", feature = "hashbrown"))]
@@ -428,7 +512,7 @@ mod verifybench;
 mod x509;
 #[macro_use]
 mod check;
-#[cfg(feature = "log")]
+#[cfg(any(feature = "log", feature = "tracing"))]
 mod bs_debug;
 mod builder;
 mod enums;
@@ -512,10 +596,11 @@ pub mod unbuffered {
 
 // The public interface is:
 pub use crate::builder::{ConfigBuilder, ConfigSide, WantsVerifier, WantsVersions};
-pub use crate::common_state::{CommonState, HandshakeKind, IoState, Side};
+pub use crate::common_state::{CommonState, ConnectionInfo, HandshakeKind, IoState, Side};
 #[cfg(feature = "std")]
 pub use crate::conn::{Connection, Reader, Writer};
 pub use crate::conn::{ConnectionCommon, SideData, kernel};
+pub use crate::custom_extension::{CustomExtension, ExtensionContext};
 pub use crate::enums::{
     AlertDescription, CertificateCompressionAlgorithm, CipherSuite, ContentType, HandshakeType,
     ProtocolVersion, SignatureAlgorithm, SignatureScheme,
@@ -540,7 +625,7 @@ pub use crate::suites::{
 pub use crate::ticketer::TicketRotator;
 pub use crate::tls12::Tls12CipherSuite;
 pub use crate::tls13::Tls13CipherSuite;
-pub use crate::verify::DigitallySignedStruct;
+pub use crate::verify::{DigitallySignedStruct, VerifiedPath};
 pub use crate::versions::{ALL_VERSIONS, DEFAULT_VERSIONS, SupportedProtocolVersion};
 pub use crate::webpki::RootCertStore;
 
@@ -552,6 +637,8 @@ pub mod client {
     mod ech;
     pub(super) mod handy;
     mod hs;
+    mod options;
+    mod profile;
     #[cfg(test)]
     mod test;
     mod tls12;
@@ -565,9 +652,11 @@ pub mod client {
     #[cfg(feature = "std")]
     pub use client_conn::{ClientConnection, WriteEarlyData};
     pub use ech::{EchConfig, EchGreaseConfig, EchMode, EchStatus};
-    pub use handy::AlwaysResolvesClientRawPublicKeys;
     #[cfg(any(feature = "std", feature = "hashbrown"))]
     pub use handy::ClientSessionMemoryCache;
+    pub use handy::{AlwaysResolvesClientRawPublicKeys, MultiIdentityClientCertResolver};
+    pub use options::ClientConnectionOptions;
+    pub use profile::ClientHelloProfile;
 
     /// Dangerous configuration that should be audited and used with extreme care.
     pub mod danger {
@@ -580,8 +669,12 @@ pub mod client {
     pub(crate) use tls13::{TLS13_HANDLER, Tls13Handler};
 
     pub use crate::msgs::persist::{Tls12ClientSessionValue, Tls13ClientSessionValue};
+    #[cfg(feature = "std")]
+    pub use crate::webpki::{CrlSource, FileTofuStore, IntermediatePool, MemoryTofuStore};
     pub use crate::webpki::{
-        ServerCertVerifierBuilder, VerifierBuilderError, WebPkiServerVerifier,
+        InvalidSpiffeId, ServerCertVerifierBuilder, SpiffeId, SpiffeIdAuthorizer,
+        SpiffeServerVerifier, SpiffeVerifierBuilder, SpkiPinningVerifier, SpkiPins, TofuOutcome,
+        TofuStore, TofuVerifier, VerifierBuilderError, WebPkiServerVerifier,
         verify_server_cert_signed_by_trust_anchor, verify_server_name,
     };
 }
@@ -594,6 +687,7 @@ pub use client::ClientConnection;
 pub mod server {
     pub(crate) mod builder;
     mod common;
+    mod fingerprint;
     pub(crate) mod handy;
     mod hs;
     mod server_conn;
@@ -603,14 +697,17 @@ pub mod server {
     mod tls13;
 
     pub use builder::WantsServerCert;
-    #[cfg(any(feature = "std", feature = "hashbrown"))]
-    pub use handy::ResolvesServerCertUsingSni;
+    pub use fingerprint::ClientHelloFingerprint;
+    #[cfg(feature = "std")]
+    pub use handy::AcmeTlsAlpnResolver;
     #[cfg(any(feature = "std", feature = "hashbrown"))]
     pub use handy::ServerSessionMemoryCache;
     pub use handy::{AlwaysResolvesServerRawPublicKeys, NoServerSessionStorage};
+    #[cfg(any(feature = "std", feature = "hashbrown"))]
+    pub use handy::{MultiKeySniResolver, ResolvesServerCertUsingSni};
     pub use server_conn::{
-        Accepted, ClientHello, ProducesTickets, ResolvesServerCert, ServerConfig,
-        ServerConnectionData, StoresServerSessions, UnbufferedServerConnection,
+        ACME_TLS_ALPN_PROTOCOL, Accepted, ClientHello, ProducesTickets, ResolvesServerCert,
+        ServerConfig, ServerConnectionData, StoresServerSessions, UnbufferedServerConnection,
     };
     #[cfg(feature = "std")]
     pub use server_conn::{AcceptedAlert, Acceptor, ReadEarlyData, ServerConnection};
@@ -618,8 +715,12 @@ pub mod server {
     pub use crate::enums::CertificateType;
     pub use crate::verify::NoClientAuth;
     pub use crate::webpki::{
-        ClientCertVerifierBuilder, ParsedCertificate, VerifierBuilderError, WebPkiClientVerifier,
+        ClientCertVerifierBuilder, InvalidSpiffeId, ParsedCertificate, SpiffeClientVerifier,
+        SpiffeId, SpiffeIdAuthorizer, SpiffeVerifierBuilder, VerifierBuilderError,
+        WebPkiClientVerifier,
     };
+    #[cfg(feature = "std")]
+    pub use crate::webpki::{CrlSource, IntermediatePool};
 
     /// Dangerous configuration that should be audited and used with extreme care.
     pub mod danger {
@@ -651,6 +752,8 @@ pub mod pki_types {
 
 /// Message signing interfaces.
 pub mod sign {
+    #[cfg(feature = "std")]
+    pub use crate::crypto::reloading::PemFileCertAndKey;
     pub use crate::crypto::signer::{CertifiedKey, Signer, SigningKey, SingleCertAndKey};
 }
 
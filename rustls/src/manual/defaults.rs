@@ -29,8 +29,12 @@ it's _much_ more likely that both peers will have good quality implementations.
 ### About the post-quantum-secure key exchange `X25519MLKEM768`
 
 [`X25519MLKEM768`] -- a hybrid[^1], post-quantum-secure[^2] key exchange
-algorithm -- is available when using the aws-lc-rs provider, set as the highest-priority key
-exchange algorithm by default.
+algorithm -- is available when using the aws-lc-rs or RustCrypto providers, set as the
+highest-priority key exchange algorithm by default.
+
+With the `ring-mlkem` crate feature, the *ring* provider also offers
+[`X25519MLKEM768`][ring-x25519mlkem768] (using a pure-Rust ML-KEM implementation,
+as *ring* has none), but does not enable it by default.
 
 [X25519MLKEM768] is pre-standardization, but is now widely deployed,
 for example, by [Chrome] and [Cloudflare].
@@ -59,6 +63,7 @@ by default out of conservatism.
 [X25519MLKEM768]: <https://datatracker.ietf.org/doc/draft-ietf-tls-ecdhe-mlkem/>
 [`X25519MLKEM768`]: crate::crypto::aws_lc_rs::kx_group::X25519MLKEM768
 [`MLKEM768`]: crate::crypto::aws_lc_rs::kx_group::MLKEM768
+[ring-x25519mlkem768]: crate::crypto::ring::kx_group::X25519MLKEM768
 [FIPS203]: <https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.203.pdf>
 [Chrome]: <https://security.googleblog.com/2024/09/a-new-path-for-kyber-on-web.html>
 [Cloudflare]: <https://blog.cloudflare.com/pq-2024/#ml-kem-768-and-x25519>
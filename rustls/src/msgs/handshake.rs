@@ -1,6 +1,6 @@
 use alloc::boxed::Box;
 use alloc::collections::BTreeSet;
-#[cfg(feature = "log")]
+#[cfg(any(feature = "log", feature = "tracing"))]
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -203,6 +203,13 @@ impl UnknownExtension {
         let payload = Payload::read(r).into_owned();
         Self { typ, payload }
     }
+
+    /// Encode the extension type, followed by the length-prefixed payload.
+    fn encode_extension(&self, bytes: &mut Vec<u8>) {
+        self.typ.encode(bytes);
+        self.payload
+            .encode(LengthPrefixedBuffer::new(ListLength::U16, bytes).buf);
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -826,7 +833,7 @@ extension_struct! {
     ///
     /// Some extensions have an empty value and are represented with Option<()>.
     ///
-    /// Unknown extensions are dropped during parsing.
+    /// Unknown extensions are collected in `unknown_extensions` during parsing.
     pub(crate) struct ClientExtensions<'a> {
         /// Requested server name indication (RFC6066)
         ExtensionType::ServerName =>
@@ -919,8 +926,17 @@ extension_struct! {
         /// Order randomization seed.
         pub(crate) order_seed: u16,
 
+        /// A fixed extension order, which replaces randomization if present.
+        pub(crate) fixed_order: Option<Vec<ExtensionType>>,
+
         /// Extensions that must appear contiguously.
         pub(crate) contiguous_extensions: Vec<ExtensionType>,
+
+        /// Extensions not otherwise handled by this struct.
+        ///
+        /// These are either received extensions unknown to rustls, or
+        /// custom extensions to send.
+        pub(crate) unknown_extensions: Vec<UnknownExtension>,
     }
 }
 
@@ -950,7 +966,9 @@ impl ClientExtensions<'_> {
             encrypted_client_hello,
             encrypted_client_hello_outer,
             order_seed,
+            fixed_order,
             contiguous_extensions,
+            unknown_extensions,
         } = self;
         ClientExtensions {
             server_name: server_name.map(|x| x.into_owned()),
@@ -976,11 +994,17 @@ impl ClientExtensions<'_> {
             encrypted_client_hello,
             encrypted_client_hello_outer,
             order_seed,
+            fixed_order,
             contiguous_extensions,
+            unknown_extensions,
         }
     }
 
     pub(crate) fn used_extensions_in_encoding_order(&self) -> Vec<ExtensionType> {
+        if let Some(fixed_order) = &self.fixed_order {
+            return self.used_extensions_in_fixed_order(fixed_order);
+        }
+
         let mut exts = self.order_insensitive_extensions_in_random_order();
         exts.extend(&self.contiguous_extensions);
 
@@ -999,6 +1023,42 @@ impl ClientExtensions<'_> {
         exts
     }
 
+    /// Returns extensions in the order given by `fixed_order`.
+    ///
+    /// Present extensions which are named in `fixed_order` come first, in that order.
+    /// Other extensions follow in the order of `collect_used()`, then any unknown
+    /// extensions.  ECH and PSK extensions which `fixed_order` does not name are
+    /// still last, and PSK is always last.
+    fn used_extensions_in_fixed_order(&self, fixed_order: &[ExtensionType]) -> Vec<ExtensionType> {
+        const LAST: &[ExtensionType] = &[
+            ExtensionType::EncryptedClientHelloOuterExtensions,
+            ExtensionType::EncryptedClientHello,
+            ExtensionType::PreSharedKey,
+        ];
+
+        let mut used = self.collect_used();
+        used.extend(
+            self.unknown_extensions
+                .iter()
+                .map(|ext| ext.typ),
+        );
+
+        let mut exts = fixed_order
+            .iter()
+            .filter(|ext| **ext != ExtensionType::PreSharedKey && used.contains(ext))
+            .copied()
+            .collect::<Vec<_>>();
+        exts.extend(
+            used.iter()
+                .filter(|ext| !fixed_order.contains(ext) && !LAST.contains(ext)),
+        );
+        exts.extend(LAST.iter().filter(|ext| {
+            used.contains(ext)
+                && (**ext == ExtensionType::PreSharedKey || !fixed_order.contains(ext))
+        }));
+        exts
+    }
+
     /// Returns extensions which don't need a specific order, in randomized order.
     ///
     /// Extensions are encoded in three portions:
@@ -1006,6 +1066,7 @@ impl ClientExtensions<'_> {
     /// - First, extensions not otherwise dealt with by other cases.
     ///   These are encoded in random order, controlled by `self.order_seed`,
     ///   and this is the set of extensions returned by this function.
+    ///   It includes any `self.unknown_extensions`.
     ///
     /// - Second, extensions named in `self.contiguous_extensions`, in the order
     ///   given by that field.
@@ -1014,6 +1075,11 @@ impl ClientExtensions<'_> {
     ///   are required to be last by the standard.
     fn order_insensitive_extensions_in_random_order(&self) -> Vec<ExtensionType> {
         let mut order = self.collect_used();
+        order.extend(
+            self.unknown_extensions
+                .iter()
+                .map(|ext| ext.typ),
+        );
 
         // Remove extensions which have specific order requirements.
         order.retain(|ext| {
@@ -1045,6 +1111,13 @@ impl<'a> Codec<'a> for ClientExtensions<'a> {
         let body = LengthPrefixedBuffer::new(ListLength::U16, bytes);
         for item in order {
             self.encode_one(item, body.buf);
+            if let Some(ext) = self
+                .unknown_extensions
+                .iter()
+                .find(|ext| ext.typ == item)
+            {
+                ext.encode_extension(body.buf);
+            }
         }
     }
 
@@ -1057,12 +1130,17 @@ impl<'a> Codec<'a> for ClientExtensions<'a> {
         }
 
         let mut checker = DuplicateExtensionChecker::new();
+        let mut unknown_extensions = Vec::new();
 
         let len = usize::from(u16::read(r)?);
         let mut sub = r.sub(len)?;
 
         while sub.any_left() {
-            let typ = out.read_one(&mut sub, |unknown| checker.check(unknown))?;
+            let typ = out.read_one(&mut sub, |typ, body| {
+                checker.check(typ)?;
+                unknown_extensions.push(UnknownExtension::read(typ, body));
+                Ok(())
+            })?;
 
             // PreSharedKey offer must come last
             if typ == ExtensionType::PreSharedKey && sub.any_left() {
@@ -1070,11 +1148,12 @@ impl<'a> Codec<'a> for ClientExtensions<'a> {
             }
         }
 
+        out.unknown_extensions = unknown_extensions;
         Ok(out)
     }
 }
 
-fn trim_hostname_trailing_dot_for_sni(dns_name: &DnsName<'_>) -> DnsName<'static> {
+pub(crate) fn trim_hostname_trailing_dot_for_sni(dns_name: &DnsName<'_>) -> DnsName<'static> {
     let dns_name_str = dns_name.as_ref();
 
     // RFC6066: "The hostname is represented as a byte string using
@@ -1178,7 +1257,8 @@ extension_struct! {
         ExtensionType::EncryptedClientHello =>
             pub(crate) encrypted_client_hello_ack: Option<ServerEncryptedClientHello>,
     } + {
-        pub(crate) unknown_extensions: BTreeSet<u16>,
+        /// Extensions not otherwise handled by this struct.
+        pub(crate) unknown_extensions: Vec<UnknownExtension>,
     }
 }
 
@@ -1230,20 +1310,29 @@ impl<'a> Codec<'a> for ServerExtensions<'a> {
         for ext in Self::ALL_EXTENSIONS {
             self.encode_one(*ext, extensions.buf);
         }
+
+        for ext in &self.unknown_extensions {
+            ext.encode_extension(extensions.buf);
+        }
     }
 
     fn read(r: &mut Reader<'a>) -> Result<Self, InvalidMessage> {
         let mut out = Self::default();
         let mut checker = DuplicateExtensionChecker::new();
+        let mut unknown_extensions = Vec::new();
 
         let len = usize::from(u16::read(r)?);
         let mut sub = r.sub(len)?;
 
         while sub.any_left() {
-            out.read_one(&mut sub, |unknown| checker.check(unknown))?;
+            out.read_one(&mut sub, |typ, body| {
+                checker.check(typ)?;
+                unknown_extensions.push(UnknownExtension::read(typ, body));
+                Ok(())
+            })?;
         }
 
-        out.unknown_extensions = checker.0;
+        out.unknown_extensions = unknown_extensions;
         Ok(out)
     }
 }
@@ -1441,7 +1530,7 @@ impl<'a> Codec<'a> for HelloRetryRequestExtensions<'a> {
         let mut sub = r.sub(len)?;
 
         while sub.any_left() {
-            let typ = out.read_one(&mut sub, |_unk| {
+            let typ = out.read_one(&mut sub, |_unk, _| {
                 Err(InvalidMessage::UnknownHelloRetryRequestExtension)
             })?;
 
@@ -1656,6 +1745,9 @@ extension_struct! {
     pub(crate) struct CertificateExtensions<'a> {
         ExtensionType::StatusRequest =>
             pub(crate) status: Option<CertificateStatus<'a>>,
+    } + {
+        /// Extensions not otherwise handled by this struct.
+        pub(crate) unknown_extensions: Vec<UnknownExtension>,
     }
 }
 
@@ -1663,6 +1755,7 @@ impl CertificateExtensions<'_> {
     fn into_owned(self) -> CertificateExtensions<'static> {
         CertificateExtensions {
             status: self.status.map(|s| s.into_owned()),
+            unknown_extensions: self.unknown_extensions,
         }
     }
 }
@@ -1674,20 +1767,31 @@ impl<'a> Codec<'a> for CertificateExtensions<'a> {
         for ext in Self::ALL_EXTENSIONS {
             self.encode_one(*ext, extensions.buf);
         }
+
+        for ext in &self.unknown_extensions {
+            ext.encode_extension(extensions.buf);
+        }
     }
 
     fn read(r: &mut Reader<'a>) -> Result<Self, InvalidMessage> {
         let mut out = Self::default();
+        let mut checker = DuplicateExtensionChecker::new();
+        let mut unknown_extensions = Vec::new();
 
         let len = usize::from(u16::read(r)?);
         let mut sub = r.sub(len)?;
 
+        // Unknown extensions are rejected later, unless they are expected
+        // custom extensions.
         while sub.any_left() {
-            out.read_one(&mut sub, |_unk| {
-                Err(InvalidMessage::UnknownCertificateExtension)
+            out.read_one(&mut sub, |typ, body| {
+                checker.check(typ)?;
+                unknown_extensions.push(UnknownExtension::read(typ, body));
+                Ok(())
             })?;
         }
 
+        out.unknown_extensions = unknown_extensions;
         Ok(out)
     }
 }
@@ -1782,6 +1886,16 @@ impl<'a> CertificatePayloadTls13<'a> {
         }
     }
 
+    /// Add `extensions` to the end-entity entry, if there is one.
+    pub(crate) fn add_end_entity_extensions(&mut self, extensions: Vec<UnknownExtension>) {
+        if let Some(entry) = self.entries.first_mut() {
+            entry
+                .extensions
+                .unknown_extensions
+                .extend(extensions);
+        }
+    }
+
     pub(crate) fn into_owned(self) -> CertificatePayloadTls13<'static> {
         CertificatePayloadTls13 {
             context: self.context,
@@ -2206,6 +2320,9 @@ extension_struct! {
 
         ExtensionType::CompressCertificate =>
             pub(crate) certificate_compression_algorithms: Option<Vec<CertificateCompressionAlgorithm>>,
+    } + {
+        /// Extensions not otherwise handled by this struct.
+        pub(crate) unknown_extensions: Vec<UnknownExtension>,
     }
 }
 
@@ -2216,19 +2333,29 @@ impl Codec<'_> for CertificateRequestExtensions {
         for ext in Self::ALL_EXTENSIONS {
             self.encode_one(*ext, extensions.buf);
         }
+
+        for ext in &self.unknown_extensions {
+            ext.encode_extension(extensions.buf);
+        }
     }
 
     fn read(r: &mut Reader<'_>) -> Result<Self, InvalidMessage> {
         let mut out = Self::default();
 
         let mut checker = DuplicateExtensionChecker::new();
+        let mut unknown_extensions = Vec::new();
 
         let len = usize::from(u16::read(r)?);
         let mut sub = r.sub(len)?;
 
         while sub.any_left() {
-            out.read_one(&mut sub, |unknown| checker.check(unknown))?;
+            out.read_one(&mut sub, |typ, body| {
+                checker.check(typ)?;
+                unknown_extensions.push(UnknownExtension::read(typ, body));
+                Ok(())
+            })?;
         }
+        out.unknown_extensions = unknown_extensions;
 
         if out
             .signature_algorithms
@@ -2328,7 +2455,7 @@ impl Codec<'_> for NewSessionTicketExtensions {
         let mut sub = r.sub(len)?;
 
         while sub.any_left() {
-            out.read_one(&mut sub, |unknown| checker.check(unknown))?;
+            out.read_one(&mut sub, |unknown, _| checker.check(unknown))?;
         }
 
         Ok(out)
@@ -3079,6 +3206,11 @@ pub(crate) enum Encoding {
     EchInnerHello { to_compress: Vec<ExtensionType> },
 }
 
+/// See <https://datatracker.ietf.org/doc/html/rfc8701#section-2>.
+pub(crate) fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
 fn has_duplicates<I: IntoIterator<Item = E>, E: Into<T>, T: Eq + Ord>(iter: I) -> bool {
     let mut seen = BTreeSet::new();
 
@@ -21,7 +21,7 @@ use super::handshake::{
     ProtocolName, PskKeyExchangeModes, Random, ServerDhParams, ServerEcdhParams,
     ServerEncryptedClientHello, ServerExtensions, ServerHelloPayload, ServerKeyExchange,
     ServerKeyExchangeParams, ServerKeyExchangePayload, ServerNamePayload, SessionId,
-    SingleProtocolName, SupportedEcPointFormats, SupportedProtocolVersions,
+    SingleProtocolName, SupportedEcPointFormats, SupportedProtocolVersions, UnknownExtension,
 };
 use crate::enums::{
     CertificateCompressionAlgorithm, CertificateType, CipherSuite, HandshakeType, ProtocolVersion,
@@ -456,6 +456,48 @@ fn client_extensions_ordering() {
     }
 }
 
+#[test]
+fn client_extensions_fixed_ordering() {
+    let exts = ClientExtensions {
+        early_data_request: Some(()),
+        extended_master_secret_request: Some(()),
+        preshared_key_offer: Some(PresharedKeyOffer {
+            identities: vec![],
+            binders: vec![],
+        }),
+        encrypted_client_hello: Some(EncryptedClientHello::Inner),
+        renegotiation_info: Some(PayloadU8::empty()),
+        unknown_extensions: vec![UnknownExtension {
+            typ: ExtensionType::Unknown(0x0a0a),
+            payload: Payload::Borrowed(&[]),
+        }],
+        fixed_order: Some(vec![
+            ExtensionType::Unknown(0x0a0a),
+            ExtensionType::EncryptedClientHello,
+            ExtensionType::Cookie,
+            ExtensionType::EarlyData,
+            ExtensionType::PreSharedKey,
+        ]),
+        ..Default::default()
+    };
+
+    for seed in [0, 1, 0xffff] {
+        let mut exts = exts.clone();
+        exts.order_seed = seed;
+        assert_eq!(
+            exts.used_extensions_in_encoding_order(),
+            vec![
+                ExtensionType::Unknown(0x0a0a),
+                ExtensionType::EncryptedClientHello,
+                ExtensionType::EarlyData,
+                ExtensionType::ExtendedMasterSecret,
+                ExtensionType::RenegotiationInfo,
+                ExtensionType::PreSharedKey,
+            ]
+        );
+    }
+}
+
 #[test]
 fn test_truncated_psk_offer() {
     let ext = PresharedKeyOffer {
@@ -912,6 +954,7 @@ fn sample_client_hello_payload() -> ClientHelloPayload {
             certificate_compression_algorithms: Some(vec![CertificateCompressionAlgorithm::Brotli]),
             encrypted_client_hello: Some(EncryptedClientHello::Inner),
             encrypted_client_hello_outer: Some(vec![ExtensionType::SCT]),
+            unknown_extensions: vec![sample_unknown_extension()],
             ..Default::default()
         }),
     }
@@ -942,7 +985,7 @@ fn sample_server_hello_payload() -> ServerHelloPayload {
             transport_parameters: Some(Payload::new(vec![1, 2, 3])),
             client_certificate_type: Some(CertificateType::RawPublicKey),
             server_certificate_type: Some(CertificateType::RawPublicKey),
-            unknown_extensions: Default::default(),
+            unknown_extensions: vec![sample_unknown_extension()],
         }),
     }
 }
@@ -1062,6 +1105,7 @@ fn sample_certificate_payload_tls13() -> CertificatePayloadTls13<'static> {
                 status: Some(CertificateStatus {
                     ocsp_response: PayloadU24(Payload::new(vec![1, 2, 3])),
                 }),
+                unknown_extensions: vec![sample_unknown_extension()],
             },
         }],
     }
@@ -1118,10 +1162,18 @@ fn sample_certificate_request_payload_tls13() -> CertificateRequestPayloadTls13
             signature_algorithms: Some(vec![SignatureScheme::ECDSA_NISTP256_SHA256]),
             authority_names: Some(vec![DistinguishedName::from(vec![1, 2, 3])]),
             certificate_compression_algorithms: Some(vec![CertificateCompressionAlgorithm::Zlib]),
+            unknown_extensions: vec![sample_unknown_extension()],
         },
     }
 }
 
+fn sample_unknown_extension() -> UnknownExtension {
+    UnknownExtension {
+        typ: ExtensionType::Unknown(0xfe00),
+        payload: Payload::new(vec![1, 2, 3]),
+    }
+}
+
 fn sample_new_session_ticket_payload() -> NewSessionTicketPayload {
     NewSessionTicketPayload {
         lifetime_hint: 1234,
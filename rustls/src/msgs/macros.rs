@@ -154,18 +154,19 @@ macro_rules! extension_struct {
         impl<'a> $struct_name$(<$struct_lt>)* {
             /// Reads one extension typ, length and body from `r`.
             ///
-            /// Unhandled extensions (according to `read_extension_body()` are inserted into `unknown_extensions`)
+            /// Unhandled extensions (according to `read_extension_body()`) are passed
+            /// to `unknown`, along with their body.
             fn read_one(
                 &mut self,
                 r: &mut Reader<'a>,
-                mut unknown: impl FnMut(ExtensionType) -> Result<(), InvalidMessage>,
+                mut unknown: impl FnMut(ExtensionType, &mut Reader<'a>) -> Result<(), InvalidMessage>,
             ) -> Result<ExtensionType, InvalidMessage> {
                 let typ = ExtensionType::read(r)?;
                 let len = usize::from(u16::read(r)?);
                 let mut ext_body = r.sub(len)?;
                 match self.read_extension_body(typ, &mut ext_body)? {
                     true => ext_body.expect_empty(stringify!($struct_name))?,
-                    false => unknown(typ)?,
+                    false => unknown(typ, &mut ext_body)?,
 
                 };
                 Ok(typ)
@@ -175,7 +176,7 @@ macro_rules! extension_struct {
             ///
             /// Returns `true` if handled, `false` otherwise.
             ///
-            /// `r` is fully consumed if `typ` is unhandled.
+            /// `r` is left untouched if `typ` is unhandled.
             fn read_extension_body(
                 &mut self,
                 typ: ExtensionType,
@@ -186,11 +187,8 @@ macro_rules! extension_struct {
                       $item_id => Self::read_once(r, $item_id, &mut self.$item_slot)?,
                    )*
 
-                   // read and ignore unhandled extensions
-                   _ => {
-                       r.rest();
-                       return Ok(false);
-                   }
+                   // leave unhandled extensions to the caller
+                   _ => return Ok(false),
                 }
 
                 Ok(true)
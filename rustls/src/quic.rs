@@ -24,16 +24,14 @@ mod connection {
     use pki_types::{DnsName, ServerName};
 
     use super::{DirectionalKeys, KeyChange, Version};
-    use crate::client::{ClientConfig, ClientConnectionData};
+    use crate::client::{ClientConfig, ClientConnectionData, ClientConnectionOptions};
     use crate::common_state::{CommonState, DEFAULT_BUFFER_LIMIT, Protocol};
     use crate::conn::{ConnectionCore, SideData};
     use crate::enums::{AlertDescription, ContentType, ProtocolVersion};
     use crate::error::Error;
     use crate::msgs::base::Payload;
     use crate::msgs::deframer::buffers::{DeframerVecBuffer, Locator};
-    use crate::msgs::handshake::{
-        ClientExtensionsInput, ServerExtensionsInput, TransportParameters,
-    };
+    use crate::msgs::handshake::{ServerExtensionsInput, TransportParameters};
     use crate::msgs::message::InboundPlainMessage;
     use crate::server::{ServerConfig, ServerConnectionData};
     use crate::sync::Arc;
@@ -167,12 +165,12 @@ mod connection {
             name: ServerName<'static>,
             params: Vec<u8>,
         ) -> Result<Self, Error> {
-            Self::new_with_alpn(
-                config.clone(),
+            Self::new_with_options(
+                config,
                 quic_version,
                 name,
                 params,
-                config.alpn_protocols.clone(),
+                ClientConnectionOptions::default(),
             )
         }
 
@@ -183,6 +181,23 @@ mod connection {
             name: ServerName<'static>,
             params: Vec<u8>,
             alpn_protocols: Vec<Vec<u8>>,
+        ) -> Result<Self, Error> {
+            Self::new_with_options(
+                config,
+                quic_version,
+                name,
+                params,
+                ClientConnectionOptions::new().with_alpn_protocols(alpn_protocols),
+            )
+        }
+
+        /// Make a new QUIC ClientConnection, overriding parts of `config` with `options`.
+        pub fn new_with_options(
+            config: Arc<ClientConfig>,
+            quic_version: Version,
+            name: ServerName<'static>,
+            params: Vec<u8>,
+            options: ClientConnectionOptions,
         ) -> Result<Self, Error> {
             if !config.supports_version(ProtocolVersion::TLSv1_3) {
                 return Err(Error::General(
@@ -196,15 +211,17 @@ mod connection {
                 ));
             }
 
-            let exts = ClientExtensionsInput {
-                transport_parameters: Some(match quic_version {
-                    Version::V1 | Version::V2 => TransportParameters::Quic(Payload::new(params)),
-                }),
-
-                ..ClientExtensionsInput::from_alpn(alpn_protocols)
+            let transport_parameters = match quic_version {
+                Version::V1 | Version::V2 => TransportParameters::Quic(Payload::new(params)),
             };
 
-            let mut inner = ConnectionCore::for_client(config, name, exts, Protocol::Quic)?;
+            let mut inner = ConnectionCore::for_client(
+                config,
+                name,
+                options,
+                Some(transport_parameters),
+                Protocol::Quic,
+            )?;
             inner.common_state.quic.version = quic_version;
             Ok(Self {
                 inner: inner.into(),
@@ -1,21 +1,28 @@
 use pki_types::CertificateDer;
 
 use crate::sign;
+use crate::sync::Arc;
 
 /// ActiveCertifiedKey wraps [`sign::CertifiedKey`] and tracks OSCP state in a single handshake.
 pub(crate) struct ActiveCertifiedKey<'a> {
-    key: &'a sign::CertifiedKey,
+    key: &'a Arc<sign::CertifiedKey>,
     ocsp: Option<&'a [u8]>,
 }
 
 impl ActiveCertifiedKey<'_> {
-    pub(super) fn from_certified_key(key: &sign::CertifiedKey) -> ActiveCertifiedKey<'_> {
+    pub(super) fn from_certified_key(key: &Arc<sign::CertifiedKey>) -> ActiveCertifiedKey<'_> {
         ActiveCertifiedKey {
             key,
             ocsp: key.ocsp.as_deref(),
         }
     }
 
+    /// Get the underlying certified key
+    #[inline]
+    pub(super) fn get_certified_key(&self) -> &Arc<sign::CertifiedKey> {
+        self.key
+    }
+
     /// Get the certificate chain
     #[inline]
     pub(super) fn get_cert(&self) -> &[CertificateDer<'static>] {
@@ -0,0 +1,340 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::crypto::hash::{Hash, HashAlgorithm};
+use crate::msgs::codec::{Codec, Reader};
+use crate::msgs::handshake::is_grease;
+
+/// Fingerprints of a received `ClientHello`.
+///
+/// This records the parts of the `ClientHello` that feed into the
+/// [JA3] and [JA4] fingerprinting schemes, exactly as the client sent them:
+/// extensions are kept in their original order, including unknown and
+/// [GREASE] values.  GREASE values are removed when computing the fingerprints,
+/// as both schemes require.
+///
+/// Only the first `ClientHello` on a connection is fingerprinted: a second
+/// `ClientHello` sent in response to a `HelloRetryRequest` is ignored.
+///
+/// [JA3]: https://github.com/salesforce/ja3
+/// [JA4]: https://github.com/FoxIO-LLC/ja4/blob/main/technical_details/JA4.md
+/// [GREASE]: https://datatracker.ietf.org/doc/html/rfc8701
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientHelloFingerprint {
+    quic: bool,
+    legacy_version: u16,
+    cipher_suites: Vec<u16>,
+    extensions: Vec<u16>,
+    named_groups: Vec<u16>,
+    ec_point_formats: Vec<u8>,
+    signature_schemes: Vec<u16>,
+    supported_versions: Vec<u16>,
+    alpn: Option<Vec<u8>>,
+}
+
+impl ClientHelloFingerprint {
+    /// Record the fingerprintable parts of a `ClientHello` handshake message.
+    ///
+    /// `encoded` is the whole handshake message, including its header.  This
+    /// has already been parsed successfully, so a truncated message is not
+    /// expected; should it happen, the parts read so far are kept.
+    pub(crate) fn new(encoded: &[u8], quic: bool) -> Self {
+        let mut fp = Self {
+            quic,
+            ..Self::default()
+        };
+        fp.read(&mut Reader::init(encoded));
+        fp
+    }
+
+    fn read(&mut self, r: &mut Reader<'_>) -> Option<()> {
+        // handshake type and length
+        r.take(4)?;
+        self.legacy_version = u16::read(r).ok()?;
+        // random
+        r.take(32)?;
+        let session_id_len = u8::read(r).ok()?;
+        r.take(session_id_len as usize)?;
+        self.cipher_suites = read_u16s(&mut u16_prefixed(r)?)?;
+        let compression_len = u8::read(r).ok()?;
+        r.take(compression_len as usize)?;
+
+        if !r.any_left() {
+            return Some(());
+        }
+
+        let mut exts = u16_prefixed(r)?;
+        while exts.any_left() {
+            let typ = u16::read(&mut exts).ok()?;
+            let mut body = u16_prefixed(&mut exts)?;
+            self.extensions.push(typ);
+
+            match typ {
+                SUPPORTED_GROUPS => self.named_groups = read_u16s(&mut u16_prefixed(&mut body)?)?,
+                EC_POINT_FORMATS => {
+                    let len = u8::read(&mut body).ok()?;
+                    self.ec_point_formats = body.take(len as usize)?.to_vec();
+                }
+                SIGNATURE_ALGORITHMS => {
+                    self.signature_schemes = read_u16s(&mut u16_prefixed(&mut body)?)?;
+                }
+                ALPN => {
+                    let mut protocols = u16_prefixed(&mut body)?;
+                    let len = u8::read(&mut protocols).ok()?;
+                    self.alpn = Some(protocols.take(len as usize)?.to_vec());
+                }
+                SUPPORTED_VERSIONS => {
+                    let len = u8::read(&mut body).ok()?;
+                    self.supported_versions = read_u16s(&mut body.sub(len as usize).ok()?)?;
+                }
+                _ => {}
+            }
+        }
+
+        Some(())
+    }
+
+    /// The extension types in the order the client sent them.
+    ///
+    /// This includes GREASE values and extensions unknown to rustls.
+    pub fn extensions(&self) -> &[u16] {
+        &self.extensions
+    }
+
+    /// The JA3 fingerprint string.
+    ///
+    /// This is `SSLVersion,Ciphers,Extensions,EllipticCurves,EllipticCurvePointFormats`,
+    /// with each list of decimal values joined by `-`.
+    ///
+    /// JA3 fingerprints are conventionally reported as the MD5 digest of this
+    /// string.  None of the crypto providers shipped with rustls implement MD5,
+    /// so computing the digest is left to the caller.
+    pub fn ja3(&self) -> String {
+        fn join<T: Into<u16> + Copy>(values: &[T]) -> String {
+            let mut out = String::new();
+            for value in values
+                .iter()
+                .map(|v| (*v).into())
+                .filter(|v| !is_grease(*v))
+            {
+                if !out.is_empty() {
+                    out.push('-');
+                }
+                let _ = write!(out, "{value}");
+            }
+            out
+        }
+
+        format!(
+            "{},{},{},{},{}",
+            self.legacy_version,
+            join(&self.cipher_suites),
+            join(&self.extensions),
+            join(&self.named_groups),
+            join(&self.ec_point_formats),
+        )
+    }
+
+    /// The JA4 fingerprint, for example `t13d1516h2_8daaf6152771_e5627efa2ab1`.
+    ///
+    /// `sha256` is used to hash the cipher suite and extension lists.  Returns
+    /// `None` if it is not a SHA-256 implementation.
+    pub fn ja4(&self, sha256: &dyn Hash) -> Option<String> {
+        if sha256.algorithm() != HashAlgorithm::SHA256 {
+            return None;
+        }
+
+        let truncated_hash = |input: &str| -> String {
+            if input.is_empty() {
+                return "000000000000".into();
+            }
+            let mut out = String::new();
+            for byte in &sha256.hash(input.as_bytes()).as_ref()[..6] {
+                let _ = write!(out, "{byte:02x}");
+            }
+            out
+        };
+
+        let (ciphers, extensions) = self.ja4_lists();
+        Some(format!(
+            "{}_{}_{}",
+            self.ja4_a(),
+            truncated_hash(&ciphers),
+            truncated_hash(&extensions),
+        ))
+    }
+
+    /// The raw JA4 fingerprint (known as `JA4_r`), with the cipher suite and extension
+    /// lists included verbatim rather than hashed.
+    pub fn ja4_r(&self) -> String {
+        let (ciphers, extensions) = self.ja4_lists();
+        format!("{}_{ciphers}_{extensions}", self.ja4_a())
+    }
+
+    fn ja4_a(&self) -> String {
+        let version = self
+            .supported_versions
+            .iter()
+            .copied()
+            .filter(|v| !is_grease(*v))
+            .max()
+            .unwrap_or(self.legacy_version);
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            0x0200 => "s2",
+            0xfeff => "d1",
+            0xfefd => "d2",
+            0xfefc => "d3",
+            _ => "00",
+        };
+
+        let sni = match self.extensions.contains(&SERVER_NAME) {
+            true => 'd',
+            false => 'i',
+        };
+
+        let count = |values: &[u16]| {
+            values
+                .iter()
+                .filter(|v| !is_grease(**v))
+                .count()
+                .min(99)
+        };
+
+        let alpn = match self.alpn.as_deref() {
+            Some([first, .., last])
+                if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() =>
+            {
+                format!("{}{}", *first as char, *last as char)
+            }
+            Some([only]) if only.is_ascii_alphanumeric() => format!("{0}{0}", *only as char),
+            Some(value @ [first, ..]) => {
+                let last = value[value.len() - 1];
+                format!("{:x}{:x}", first >> 4, last & 0x0f)
+            }
+            _ => "00".into(),
+        };
+
+        format!(
+            "{}{version}{sni}{:02}{:02}{alpn}",
+            match self.quic {
+                true => 'q',
+                false => 't',
+            },
+            count(&self.cipher_suites),
+            count(&self.extensions),
+        )
+    }
+
+    fn ja4_lists(&self) -> (String, String) {
+        fn sorted_hex(values: &[u16], exclude: &[u16]) -> String {
+            let mut values = values
+                .iter()
+                .copied()
+                .filter(|v| !is_grease(*v) && !exclude.contains(v))
+                .collect::<Vec<_>>();
+            values.sort_unstable();
+            hex_list(&values)
+        }
+
+        let ciphers = sorted_hex(&self.cipher_suites, &[]);
+        let mut extensions = sorted_hex(&self.extensions, &[SERVER_NAME, ALPN]);
+        let signature_schemes = self
+            .signature_schemes
+            .iter()
+            .copied()
+            .filter(|v| !is_grease(*v))
+            .collect::<Vec<_>>();
+        if !extensions.is_empty() && !signature_schemes.is_empty() {
+            extensions.push('_');
+            extensions.push_str(&hex_list(&signature_schemes));
+        }
+
+        (ciphers, extensions)
+    }
+}
+
+fn hex_list(values: &[u16]) -> String {
+    let mut out = String::new();
+    for value in values {
+        if !out.is_empty() {
+            out.push(',');
+        }
+        let _ = write!(out, "{value:04x}");
+    }
+    out
+}
+
+fn u16_prefixed<'a>(r: &mut Reader<'a>) -> Option<Reader<'a>> {
+    let len = u16::read(r).ok()?;
+    r.sub(len as usize).ok()
+}
+
+fn read_u16s(r: &mut Reader<'_>) -> Option<Vec<u16>> {
+    let mut out = Vec::with_capacity(r.left() / 2);
+    while r.any_left() {
+        out.push(u16::read(r).ok()?);
+    }
+    Some(out)
+}
+
+const SERVER_NAME: u16 = 0x0000;
+const SUPPORTED_GROUPS: u16 = 0x000a;
+const EC_POINT_FORMATS: u16 = 0x000b;
+const SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const ALPN: u16 = 0x0010;
+const SUPPORTED_VERSIONS: u16 = 0x002b;
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use super::*;
+
+    #[test]
+    fn grease_values() {
+        assert!(is_grease(0x0a0a));
+        assert!(is_grease(0xfafa));
+        assert!(!is_grease(0x0a1a));
+        assert!(!is_grease(0x1301));
+    }
+
+    #[test]
+    fn ja3_and_ja4_r() {
+        let fp = ClientHelloFingerprint {
+            quic: false,
+            legacy_version: 0x0303,
+            cipher_suites: vec![0x2a2a, 0x1301, 0x1302, 0xc02b],
+            extensions: vec![0x3a3a, 0x0000, 0x0010, 0x000a, 0x000b, 0x000d, 0x002b],
+            named_groups: vec![0x4a4a, 0x001d, 0x0017],
+            ec_point_formats: vec![0],
+            signature_schemes: vec![0x0403, 0x0804],
+            supported_versions: vec![0x5a5a, 0x0304, 0x0303],
+            alpn: Some(b"h2".to_vec()),
+        };
+
+        assert_eq!(fp.ja3(), "771,4865-4866-49195,0-16-10-11-13-43,29-23,0");
+        assert_eq!(
+            fp.ja4_r(),
+            "t13d0306h2_1301,1302,c02b_000a,000b,000d,002b_0403,0804"
+        );
+    }
+
+    #[test]
+    fn ja4_alpn_variants() {
+        let mut fp = ClientHelloFingerprint::default();
+        assert!(fp.ja4_a().ends_with("00"));
+        fp.alpn = Some(b"http/1.1".to_vec());
+        assert!(fp.ja4_a().ends_with("h1"));
+        fp.alpn = Some(b"h".to_vec());
+        assert!(fp.ja4_a().ends_with("hh"));
+        fp.alpn = Some(vec![0xab, 0xcd]);
+        assert!(fp.ja4_a().ends_with("ad"));
+    }
+}
@@ -277,6 +277,7 @@ mod sni_resolver {
                         cipher_suites: &[],
                         certificate_authorities: None,
                         named_groups: None,
+                        fingerprint: None,
                     })
                     .is_none()
             );
@@ -299,6 +300,7 @@ mod sni_resolver {
                         cipher_suites: &[],
                         certificate_authorities: None,
                         named_groups: None,
+                        fingerprint: None,
                     })
                     .is_none()
             );
@@ -309,6 +311,368 @@ mod sni_resolver {
 #[cfg(any(feature = "std", feature = "hashbrown"))]
 pub use sni_resolver::ResolvesServerCertUsingSni;
 
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+mod multi_key_resolver {
+    use alloc::format;
+    use alloc::vec::Vec;
+    use core::fmt::Debug;
+
+    use pki_types::{DnsName, ServerName};
+
+    use crate::crypto::signer::IssuedKey;
+    use crate::enums::CertificateType;
+    use crate::error::{CertificateError, Error};
+    use crate::hash_map::HashMap;
+    use crate::server::ClientHello;
+    use crate::sync::Arc;
+    use crate::webpki::{ParsedCertificate, verify_server_name};
+    use crate::{server, sign};
+
+    /// Something that resolves to one of several cert chains/keys, based on
+    /// the client-supplied server name (via SNI) and the client's capabilities.
+    ///
+    /// Each name can have several cert chains/keys, for example one with an RSA
+    /// key and one with an ECDSA key.  They are looked up by:
+    ///
+    /// - the exact server name, then
+    /// - a wildcard name covering the server name, then
+    /// - the default cert chains/keys, which are also used if the client does not
+    ///   send SNI.
+    ///
+    /// Among the cert chains/keys for the first of these that matches, this picks
+    /// one issued by a certificate authority named by the client (see
+    /// [`ClientHello::certificate_authorities()`]), if any.  It then picks the one
+    /// whose key can be used with the signature scheme the client prefers most
+    /// (see [`ClientHello::signature_schemes()`]), so clients which prefer ECDSA
+    /// or Ed25519 get those keys, while others get RSA keys.  Ties go to the
+    /// cert chain/key added first.
+    ///
+    /// A resolver holds either X.509 certificate chains (made with [`Self::new()`])
+    /// or [RFC 7250] raw public keys (made with [`Self::new_raw_public_keys()`]),
+    /// because the certificate type is negotiated before a key is chosen.  Nothing
+    /// is resolved for a client that does not accept that certificate type (see
+    /// [`ClientHello::server_cert_types()`]).
+    ///
+    /// [RFC 7250]: https://tools.ietf.org/html/rfc7250
+    #[derive(Debug, Default)]
+    pub struct MultiKeySniResolver {
+        exact: HashMap<DnsName<'static>, Vec<IssuedKey>>,
+        wildcard: HashMap<DnsName<'static>, Vec<IssuedKey>>,
+        default: Vec<IssuedKey>,
+        raw_public_keys: bool,
+    }
+
+    impl MultiKeySniResolver {
+        /// Create a new and empty (i.e., knows no certificates) resolver.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Create a new and empty resolver for raw public keys.
+        ///
+        /// Each `sign::CertifiedKey` added to this must have a `cert_chain` of one
+        /// `SubjectPublicKeyInfo`, as for [`AlwaysResolvesServerRawPublicKeys`].
+        /// Raw public keys do not name the server, so names are not checked when
+        /// they are added.
+        ///
+        /// [`AlwaysResolvesServerRawPublicKeys`]: server::AlwaysResolvesServerRawPublicKeys
+        pub fn new_raw_public_keys() -> Self {
+            Self {
+                raw_public_keys: true,
+                ..Self::default()
+            }
+        }
+
+        /// Add a `sign::CertifiedKey` to be used for the exact SNI `name`.
+        ///
+        /// This function fails if the `name` is not valid for the supplied certificate, or if
+        /// the certificate chain is syntactically faulty.
+        pub fn add(&mut self, name: DnsName<'static>, ck: sign::CertifiedKey) -> Result<(), Error> {
+            let wrapped = ServerName::DnsName(name);
+            if !self.raw_public_keys {
+                verify_server_name(
+                    &ParsedCertificate::try_from(ck.end_entity_cert()?)?,
+                    &wrapped,
+                )?;
+            }
+            let entry = self.entry(ck)?;
+
+            let ServerName::DnsName(name) = wrapped else {
+                unreachable!()
+            };
+
+            self.exact
+                .entry(name)
+                .or_default()
+                .push(entry);
+            Ok(())
+        }
+
+        /// Add a `sign::CertifiedKey` to be used for any SNI name with one more label than
+        /// `parent`.
+        ///
+        /// For example, if `parent` is `example.com`, this is used for `www.example.com`
+        /// but not for `example.com` or `a.b.example.com`, like a certificate for
+        /// `*.example.com`.
+        ///
+        /// This function fails if the certificate is not valid for `*.<parent>`, or if
+        /// the certificate chain is syntactically faulty.
+        pub fn add_wildcard(
+            &mut self,
+            parent: DnsName<'static>,
+            ck: sign::CertifiedKey,
+        ) -> Result<(), Error> {
+            if !self.raw_public_keys {
+                // This is not security-sensitive: it is the *server* attempting to
+                // detect accidental misconfiguration.
+                let wildcard = format!("*.{}", parent.as_ref());
+                let end_entity = ParsedCertificate::try_from(ck.end_entity_cert()?)?;
+                if !end_entity
+                    .0
+                    .valid_dns_names()
+                    .any(|name| name.eq_ignore_ascii_case(&wildcard))
+                {
+                    return Err(Error::InvalidCertificate(CertificateError::NotValidForName));
+                }
+            }
+
+            let entry = self.entry(ck)?;
+            self.wildcard
+                .entry(parent)
+                .or_default()
+                .push(entry);
+            Ok(())
+        }
+
+        /// Add a `sign::CertifiedKey` to be used if no other name matches, or the client
+        /// does not send SNI.
+        ///
+        /// This function fails if the certificate chain is syntactically faulty.
+        pub fn add_default(&mut self, ck: sign::CertifiedKey) -> Result<(), Error> {
+            let entry = self.entry(ck)?;
+            self.default.push(entry);
+            Ok(())
+        }
+
+        fn entry(&self, ck: sign::CertifiedKey) -> Result<IssuedKey, Error> {
+            match self.raw_public_keys {
+                true => IssuedKey::raw_public_key(ck),
+                false => IssuedKey::new(ck),
+            }
+        }
+
+        fn cert_type(&self) -> CertificateType {
+            match self.raw_public_keys {
+                true => CertificateType::RawPublicKey,
+                false => CertificateType::X509,
+            }
+        }
+
+        fn candidates(&self, name: Option<&DnsName<'_>>) -> &[IssuedKey] {
+            let Some(name) = name else {
+                return &self.default;
+            };
+
+            let name = name.to_owned();
+            if let Some(entries) = self.exact.get(&name) {
+                return entries;
+            }
+
+            let parent = name
+                .as_ref()
+                .split_once('.')
+                .and_then(|(_, parent)| DnsName::try_from(parent).ok())
+                .map(|parent| parent.to_owned());
+            match parent.and_then(|parent| self.wildcard.get(&parent)) {
+                Some(entries) => entries,
+                None => &self.default,
+            }
+        }
+    }
+
+    impl server::ResolvesServerCert for MultiKeySniResolver {
+        fn resolve(&self, client_hello: &ClientHello<'_>) -> Option<Arc<sign::CertifiedKey>> {
+            // A client which sends no server certificate type extension only accepts X.509.
+            let offered = client_hello
+                .server_cert_types()
+                .unwrap_or(&[CertificateType::X509]);
+            if !offered.contains(&self.cert_type()) {
+                return None;
+            }
+
+            let candidates = self.candidates(client_hello.server_name());
+
+            let from_named_issuer = client_hello
+                .certificate_authorities()
+                .map(|authorities| {
+                    candidates
+                        .iter()
+                        .filter(|entry| {
+                            entry.issued_by_any(
+                                authorities
+                                    .iter()
+                                    .map(|authority| authority.as_ref()),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+
+            let candidates = match from_named_issuer.is_empty() {
+                true => candidates.iter().collect(),
+                false => from_named_issuer,
+            };
+
+            // Rank each by the client's preference for the signature schemes its key supports.
+            let schemes = client_hello.signature_schemes();
+            candidates
+                .into_iter()
+                .filter_map(|entry| {
+                    schemes
+                        .iter()
+                        .position(|scheme| {
+                            entry
+                                .key
+                                .key
+                                .choose_scheme(&[*scheme])
+                                .is_some()
+                        })
+                        .map(|rank| (rank, entry))
+                })
+                .min_by_key(|(rank, _)| *rank)
+                .map(|(_, entry)| entry.key.clone())
+        }
+
+        fn only_raw_public_keys(&self) -> bool {
+            self.raw_public_keys
+        }
+    }
+}
+
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+pub use multi_key_resolver::MultiKeySniResolver;
+
+#[cfg(feature = "std")]
+mod acme_resolver {
+    use core::fmt::Debug;
+
+    use pki_types::{DnsName, ServerName};
+
+    use crate::error::Error;
+    use crate::hash_map::HashMap;
+    use crate::lock::Mutex;
+    use crate::server::{ACME_TLS_ALPN_PROTOCOL, ClientHello};
+    use crate::sync::Arc;
+    use crate::webpki::{ParsedCertificate, verify_server_name};
+    use crate::{server, sign};
+
+    /// Answers ACME TLS-ALPN-01 challenges, and otherwise defers to another resolver.
+    ///
+    /// [RFC 8737] allows an ACME server to validate control of a domain by connecting
+    /// to it and offering only the `acme-tls/1` ALPN protocol.  The TLS server must
+    /// answer with a self-signed certificate for the domain, which carries the
+    /// `acmeIdentifier` extension given by the ACME challenge.
+    ///
+    /// The application creates such a certificate for each pending challenge, and
+    /// registers it with [`AcmeTlsAlpnResolver::add_challenge()`].  A `ClientHello`
+    /// offering `acme-tls/1`, with a server name that has a registered challenge,
+    /// is answered with that certificate (see
+    /// [`ResolvesServerCert::resolve_acme_tls_alpn_challenge()`]).  Any other
+    /// `ClientHello` is passed to the inner resolver.
+    ///
+    /// [RFC 8737]: https://datatracker.ietf.org/doc/html/rfc8737
+    /// [`ResolvesServerCert::resolve_acme_tls_alpn_challenge()`]: server::ResolvesServerCert::resolve_acme_tls_alpn_challenge
+    #[derive(Debug)]
+    pub struct AcmeTlsAlpnResolver {
+        inner: Arc<dyn server::ResolvesServerCert>,
+        challenges: Mutex<HashMap<DnsName<'static>, Arc<sign::CertifiedKey>>>,
+    }
+
+    impl AcmeTlsAlpnResolver {
+        /// Create a new resolver with no pending challenges, which uses `inner` for
+        /// all other handshakes.
+        pub fn new(inner: Arc<dyn server::ResolvesServerCert>) -> Self {
+            Self {
+                inner,
+                challenges: Mutex::new(HashMap::new()),
+            }
+        }
+
+        /// Answer challenges for `name` with `ck`, replacing any existing challenge
+        /// for `name`.
+        ///
+        /// This function fails if the `name` is not valid for the supplied certificate, or if
+        /// the certificate is syntactically faulty.
+        pub fn add_challenge(
+            &self,
+            name: DnsName<'static>,
+            ck: sign::CertifiedKey,
+        ) -> Result<(), Error> {
+            let wrapped = ServerName::DnsName(name);
+            ck.end_entity_cert()
+                .and_then(ParsedCertificate::try_from)
+                .and_then(|cert| verify_server_name(&cert, &wrapped))?;
+
+            let ServerName::DnsName(name) = wrapped else {
+                unreachable!()
+            };
+
+            if let Some(mut challenges) = self.challenges.lock() {
+                challenges.insert(name, Arc::new(ck));
+            }
+            Ok(())
+        }
+
+        /// Stop answering challenges for `name`.
+        ///
+        /// Returns true if there was a challenge for `name`.
+        pub fn remove_challenge(&self, name: &DnsName<'_>) -> bool {
+            self.challenges
+                .lock()
+                .map(|mut challenges| {
+                    challenges
+                        .remove(&name.to_owned())
+                        .is_some()
+                })
+                .unwrap_or_default()
+        }
+    }
+
+    impl server::ResolvesServerCert for AcmeTlsAlpnResolver {
+        fn resolve(&self, client_hello: &ClientHello<'_>) -> Option<Arc<sign::CertifiedKey>> {
+            // Challenges are answered by `resolve_acme_tls_alpn_challenge()`, which the
+            // handshake calls first.  Answering them here as well would let a challenge
+            // added between the two calls be sent without selecting `acme-tls/1`.
+            self.inner.resolve(client_hello)
+        }
+
+        fn only_raw_public_keys(&self) -> bool {
+            self.inner.only_raw_public_keys()
+        }
+
+        fn resolve_acme_tls_alpn_challenge(
+            &self,
+            client_hello: &ClientHello<'_>,
+        ) -> Option<Arc<sign::CertifiedKey>> {
+            let offers_acme = client_hello
+                .alpn()?
+                .any(|protocol| protocol == ACME_TLS_ALPN_PROTOCOL);
+            if !offers_acme {
+                return None;
+            }
+
+            let name = client_hello.server_name()?.to_owned();
+            self.challenges
+                .lock()?
+                .get(&name)
+                .cloned()
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use acme_resolver::AcmeTlsAlpnResolver;
+
 #[cfg(test)]
 mod tests {
     use std::vec;
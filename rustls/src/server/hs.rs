@@ -4,11 +4,12 @@ use alloc::vec::Vec;
 
 use pki_types::DnsName;
 
-use super::server_conn::ServerConnectionData;
+use super::server_conn::{ACME_TLS_ALPN_PROTOCOL, ServerConnectionData};
 use super::tls12;
 use crate::common_state::{KxState, Protocol, State};
 use crate::conn::ConnectionRandoms;
 use crate::crypto::SupportedKxGroup;
+use crate::custom_extension::{self, ExtensionContext};
 use crate::enums::{
     AlertDescription, CertificateType, CipherSuite, HandshakeType, ProtocolVersion,
     SignatureAlgorithm, SignatureScheme,
@@ -25,7 +26,7 @@ use crate::msgs::handshake::{
 use crate::msgs::message::{Message, MessagePayload};
 use crate::msgs::persist;
 use crate::server::common::ActiveCertifiedKey;
-use crate::server::{ClientHello, ServerConfig, tls13};
+use crate::server::{ClientHello, ClientHelloFingerprint, ServerConfig, tls13};
 use crate::sync::Arc;
 use crate::{SupportedCipherSuite, suites};
 
@@ -86,14 +87,19 @@ impl ExtensionProcessing {
         // ALPN
         let our_protocols = &config.alpn_protocols;
         if let Some(their_protocols) = &hello.protocols {
-            cx.common.alpn_protocol = our_protocols
-                .iter()
-                .find(|ours| {
-                    their_protocols
-                        .iter()
-                        .any(|theirs| theirs.as_ref() == ours.as_slice())
-                })
-                .map(|bytes| ProtocolName::from(bytes.clone()));
+            cx.common.alpn_protocol = if cx.data.acme_tls_alpn_challenge {
+                // RFC 8737 section 3: the server must select `acme-tls/1`
+                Some(ProtocolName::from(ACME_TLS_ALPN_PROTOCOL.to_vec()))
+            } else {
+                our_protocols
+                    .iter()
+                    .find(|ours| {
+                        their_protocols
+                            .iter()
+                            .any(|theirs| theirs.as_ref() == ours.as_slice())
+                    })
+                    .map(|bytes| ProtocolName::from(bytes.clone()))
+            };
             if let Some(selected_protocol) = &cx.common.alpn_protocol {
                 debug!("Chosen ALPN protocol {selected_protocol:?}");
 
@@ -162,6 +168,14 @@ impl ExtensionProcessing {
         self.validate_server_cert_type_extension(hello, config, cx)?;
         self.validate_client_cert_type_extension(hello, config, cx)?;
 
+        custom_extension::receive(
+            cx.common,
+            &config.custom_extensions,
+            ExtensionContext::ClientHello,
+            &hello.unknown_extensions,
+            None,
+        )?;
+
         Ok(())
     }
 
@@ -170,6 +184,7 @@ impl ExtensionProcessing {
         config: &ServerConfig,
         hello: &ClientHelloPayload,
         using_ems: bool,
+        acme_tls_alpn_challenge: bool,
     ) {
         // Renegotiation.
         // (We don't do reneg at all, but would support the secure version if we did.)
@@ -186,8 +201,8 @@ impl ExtensionProcessing {
 
         // Tickets:
         // If we get any SessionTicket extension and have tickets enabled,
-        // we send an ack.
-        if hello.session_ticket.is_some() && config.ticketer.enabled() {
+        // we send an ack.  ACME TLS-ALPN-01 connections are never resumed.
+        if hello.session_ticket.is_some() && config.ticketer.enabled() && !acme_tls_alpn_challenge {
             self.send_ticket = true;
             self.extensions.session_ticket_ack = Some(());
         }
@@ -271,9 +286,11 @@ impl ExtensionProcessing {
         match raw_key_negotation_result {
             Ok((ExtensionType::ClientCertificateType, cert_type)) => {
                 self.extensions.client_certificate_type = Some(cert_type);
+                cx.common.client_certificate_type = cert_type;
             }
             Ok((ExtensionType::ServerCertificateType, cert_type)) => {
                 self.extensions.server_certificate_type = Some(cert_type);
+                cx.common.server_certificate_type = cert_type;
             }
             Err(err) => {
                 return Err(cx
@@ -414,13 +431,27 @@ impl ExpectClientHello {
                 cipher_suites: &client_hello.cipher_suites,
                 certificate_authorities,
                 named_groups: client_hello.named_groups.as_deref(),
+                fingerprint: cx
+                    .data
+                    .client_hello_fingerprint
+                    .as_deref(),
             };
             trace!("Resolving server certificate: {client_hello:#?}");
 
-            let certkey = self
+            let certkey = match self
                 .config
                 .cert_resolver
-                .resolve(&client_hello);
+                .resolve_acme_tls_alpn_challenge(&client_hello)
+            {
+                Some(certkey) => {
+                    cx.data.acme_tls_alpn_challenge = true;
+                    Some(certkey)
+                }
+                None => self
+                    .config
+                    .cert_resolver
+                    .resolve(&client_hello),
+            };
 
             certkey.ok_or_else(|| {
                 cx.common.send_fatal_alert(
@@ -447,7 +478,11 @@ impl ExpectClientHello {
                     .send_fatal_alert(AlertDescription::HandshakeFailure, incompat)
             })?;
 
-        debug!("decided upon suite {suite:?}");
+        debug!(
+            suite = ?suite.suite(),
+            group = ?skxg.name(),
+            "decided upon suite"
+        );
         cx.common.suite = Some(suite);
         cx.common.kx_state = KxState::Start(skxg);
 
@@ -686,6 +721,15 @@ pub(super) fn process_client_hello<'m>(
         require_handshake_msg!(m, HandshakeType::ClientHello, HandshakePayload::ClientHello)?;
     trace!("we got a clienthello {client_hello:?}");
 
+    if !done_retry {
+        if let MessagePayload::Handshake { encoded, .. } = &m.payload {
+            cx.data.client_hello_fingerprint = Some(Box::new(ClientHelloFingerprint::new(
+                encoded.bytes(),
+                cx.common.is_quic(),
+            )));
+        }
+    }
+
     if !client_hello
         .compression_methods
         .contains(&Compression::Null)
@@ -731,6 +775,11 @@ pub(super) fn process_client_hello<'m>(
         // The SNI hostname is immutable once set.
         assert!(cx.data.sni.is_none());
         cx.data.sni = Some(sni.clone());
+        cx.common.sni = Some(sni.clone());
+        #[cfg(feature = "tracing")]
+        cx.common
+            .span
+            .record("server_name", tracing::field::debug(sni));
     } else if cx.data.sni != sni {
         return Err(PeerMisbehaved::ServerNameDifferedOnRetry.into());
     }
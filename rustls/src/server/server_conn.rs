@@ -9,6 +9,7 @@ use std::io;
 
 use pki_types::{DnsName, UnixTime};
 
+use super::fingerprint::ClientHelloFingerprint;
 use super::hs;
 #[cfg(feature = "std")]
 use crate::WantsVerifier;
@@ -20,6 +21,7 @@ use crate::conn::{ConnectionCommon, ConnectionCore, UnbufferedConnectionCommon};
 #[cfg(doc)]
 use crate::crypto;
 use crate::crypto::CryptoProvider;
+use crate::custom_extension::{self, CustomExtension};
 use crate::enums::{CertificateType, CipherSuite, ProtocolVersion, SignatureScheme};
 use crate::error::Error;
 use crate::kernel::KernelConnection;
@@ -132,8 +134,33 @@ pub trait ResolvesServerCert: Debug + Send + Sync {
     fn only_raw_public_keys(&self) -> bool {
         false
     }
+
+    /// Choose a certificate chain and matching key to answer `client_hello` as an
+    /// ACME TLS-ALPN-01 challenge, as defined in [RFC 8737].
+    ///
+    /// This is called before [`Self::resolve()`], which is only called if this
+    /// returns `None`.  If this returns a key, the `acme-tls/1` ALPN protocol is
+    /// selected, regardless of [`ServerConfig::alpn_protocols`], and the session is
+    /// neither resumed nor made resumable.  The application should close such
+    /// connections once the handshake completes.
+    ///
+    /// The default implementation returns `None`.
+    ///
+    /// [RFC 8737]: https://datatracker.ietf.org/doc/html/rfc8737
+    fn resolve_acme_tls_alpn_challenge(
+        &self,
+        client_hello: &ClientHello<'_>,
+    ) -> Option<Arc<sign::CertifiedKey>> {
+        let _ = client_hello;
+        None
+    }
 }
 
+/// The ALPN protocol name used by ACME TLS-ALPN-01 challenges ([RFC 8737]).
+///
+/// [RFC 8737]: https://datatracker.ietf.org/doc/html/rfc8737#section-6.2
+pub const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
 /// A struct representing the received Client Hello
 #[derive(Debug)]
 pub struct ClientHello<'a> {
@@ -148,6 +175,7 @@ pub struct ClientHello<'a> {
     /// [certificate_authorities]: https://datatracker.ietf.org/doc/html/rfc8446#section-4.2.4
     pub(super) certificate_authorities: Option<&'a [DistinguishedName]>,
     pub(super) named_groups: Option<&'a [NamedGroup]>,
+    pub(super) fingerprint: Option<&'a ClientHelloFingerprint>,
 }
 
 impl<'a> ClientHello<'a> {
@@ -238,6 +266,14 @@ impl<'a> ClientHello<'a> {
     pub fn named_groups(&self) -> Option<&'a [NamedGroup]> {
         self.named_groups
     }
+
+    /// Get the JA3 and JA4 fingerprints of this ClientHello.
+    ///
+    /// This is the fingerprint of the first ClientHello on the connection, even
+    /// when this is a ClientHello sent in response to a HelloRetryRequest.
+    pub fn fingerprint(&self) -> Option<&'a ClientHelloFingerprint> {
+        self.fingerprint
+    }
 }
 
 /// Common configuration for a set of server sessions.
@@ -443,6 +479,12 @@ pub struct ServerConfig {
     ///
     /// [RFC8779]: https://datatracker.ietf.org/doc/rfc8879/
     pub cert_decompressors: Vec<&'static dyn compress::CertDecompressor>,
+
+    /// Application-defined extensions to send and receive.
+    ///
+    /// See [`CustomExtension`] for which messages carry these.  The default
+    /// is empty.
+    pub custom_extensions: Vec<Arc<dyn CustomExtension>>,
 }
 
 impl ServerConfig {
@@ -581,8 +623,8 @@ mod connection {
     use pki_types::DnsName;
 
     use super::{
-        Accepted, Accepting, EarlyDataState, ServerConfig, ServerConnectionData,
-        ServerExtensionsInput,
+        Accepted, Accepting, ClientHelloFingerprint, EarlyDataState, ServerConfig,
+        ServerConnectionData, ServerExtensionsInput,
     };
     use crate::common_state::{CommonState, Context, Side};
     use crate::conn::{ConnectionCommon, ConnectionCore};
@@ -652,6 +694,17 @@ mod connection {
             self.inner.core.data.sni.as_ref()
         }
 
+        /// Retrieves the JA3 and JA4 fingerprints of the client's first ClientHello.
+        ///
+        /// Returns `None` until the ClientHello has been received.
+        pub fn client_hello_fingerprint(&self) -> Option<&ClientHelloFingerprint> {
+            self.inner
+                .core
+                .data
+                .client_hello_fingerprint
+                .as_deref()
+        }
+
         /// Application-controlled portion of the resumption ticket supplied by the client, if any.
         ///
         /// Recovered from the prior session's `set_resumption_data`. Integrity is guaranteed by rustls.
@@ -1016,6 +1069,12 @@ impl Accepted {
                 .certificate_authority_names
                 .as_deref(),
             named_groups: payload.named_groups.as_deref(),
+            fingerprint: self
+                .connection
+                .core
+                .data
+                .client_hello_fingerprint
+                .as_deref(),
         };
 
         trace!("Accepted::client_hello(): {ch:#?}");
@@ -1041,6 +1100,10 @@ impl Accepted {
             return Err((err, AcceptedAlert::empty()));
         }
 
+        if let Err(err) = custom_extension::validate(&config.custom_extensions) {
+            return Err((err, AcceptedAlert::empty()));
+        }
+
         self.connection.enable_secret_extraction = config.enable_secret_extraction;
 
         let state = hs::ExpectClientHello::new(config, ServerExtensionsInput::default());
@@ -1192,6 +1255,7 @@ impl ConnectionCore<ServerConnectionData> {
     ) -> Result<Self, Error> {
         let mut common = CommonState::new(Side::Server);
         common.set_max_fragment_size(config.max_fragment_size)?;
+        custom_extension::validate(&config.custom_extensions)?;
         common.enable_secret_extraction = config.enable_secret_extraction;
         common.fips = config.fips();
         Ok(Self::new(
@@ -1218,6 +1282,8 @@ pub struct ServerConnectionData {
     pub(super) received_resumption_data: Option<Vec<u8>>,
     pub(super) resumption_data: Vec<u8>,
     pub(super) early_data: EarlyDataState,
+    pub(super) client_hello_fingerprint: Option<Box<ClientHelloFingerprint>>,
+    pub(super) acme_tls_alpn_challenge: bool,
 }
 
 impl crate::conn::SideData for ServerConnectionData {}
@@ -67,17 +67,19 @@ mod client_hello {
             chm: &Message<'_>,
             client_hello: &ClientHelloPayload,
             selected_kxg: &'static dyn SupportedKxGroup,
-            sigschemes_ext: Vec<SignatureScheme>,
+            mut sigschemes_ext: Vec<SignatureScheme>,
             tls13_enabled: bool,
         ) -> hs::NextStateOrError<'static> {
             // -- TLS1.2 only from hereon in --
             cch.transcript.add_message(chm);
+            sigschemes_ext.retain(SignatureScheme::supported_in_tls12);
 
             if client_hello
                 .extended_master_secret_request
                 .is_some()
             {
                 cch.using_ems = true;
+                cx.common.extended_master_secret = true;
             } else if cch.config.require_ems {
                 return Err(cx.common.send_fatal_alert(
                     AlertDescription::HandshakeFailure,
@@ -162,7 +164,11 @@ mod client_hello {
                 });
 
             if let Some(data) = resume_data {
-                return cch.start_resumption(cx, client_hello, &client_hello.session_id, data);
+                if cx.data.acme_tls_alpn_challenge {
+                    debug!("Not resuming ACME TLS-ALPN-01 challenge");
+                } else {
+                    return cch.start_resumption(cx, client_hello, &client_hello.session_id, data);
+                }
             }
 
             // Now we have chosen a ciphersuite, we can make kx decisions.
@@ -180,7 +186,7 @@ mod client_hello {
             let mut ocsp_response = server_key.get_ocsp();
 
             // If we're not offered a ticket or a potential session ID, allocate a session ID.
-            if !cch.config.session_storage.can_cache() {
+            if !cch.config.session_storage.can_cache() || cx.data.acme_tls_alpn_challenge {
                 cch.session_id = SessionId::empty();
             } else if cch.session_id.is_empty() && !ticket_received {
                 cch.session_id = SessionId::random(cch.config.provider.secure_random)?;
@@ -205,6 +211,7 @@ mod client_hello {
                 cch.extra_exts,
             )?;
             emit_certificate(&mut flight, server_key.get_cert());
+            cx.common.local_certified_key = Some(server_key.get_certified_key().clone());
             if let Some(ocsp_response) = ocsp_response {
                 emit_cert_status(&mut flight, ocsp_response);
             }
@@ -371,7 +378,7 @@ mod client_hello {
             hello,
             resumedata.map(|r| &r.common),
         )?;
-        ep.process_tls12(config, hello, using_ems);
+        ep.process_tls12(config, hello, using_ems, cx.data.acme_tls_alpn_challenge);
 
         let sh = HandshakeMessagePayload(HandshakePayload::ServerHello(ServerHelloPayload {
             legacy_version: ProtocolVersion::TLSv1_2,
@@ -444,7 +451,8 @@ mod client_hello {
             return Ok(false);
         }
 
-        let verify_schemes = client_auth.supported_verify_schemes();
+        let mut verify_schemes = client_auth.supported_verify_schemes();
+        verify_schemes.retain(SignatureScheme::supported_in_tls12);
 
         let names = config
             .verifier
@@ -523,13 +531,16 @@ impl State<ServerConnectionData> for ExpectCertificate {
             Some((end_entity, intermediates)) => {
                 let now = self.config.current_time()?;
 
-                self.config
+                let cert_verified = self
+                    .config
                     .verifier
                     .verify_client_cert(end_entity, intermediates, now)
                     .map_err(|err| {
                         cx.common
                             .send_cert_verify_error_alert(err)
                     })?;
+                cx.common.peer_verified_path = cert_verified.verified_path().cloned();
+                cx.common.peer_spiffe_id = cert_verified.spiffe_id().cloned();
 
                 Some(cert_chain)
             }
@@ -672,14 +683,14 @@ impl State<ServerConnectionData> for ExpectCertificateVerify<'_> {
     where
         Self: 'm,
     {
-        let rc = {
+        let (scheme, rc) = {
             let sig = require_handshake_msg!(
                 m,
                 HandshakeType::CertificateVerify,
                 HandshakePayload::CertificateVerify
             )?;
 
-            match self.transcript.take_handshake_buf() {
+            let rc = match self.transcript.take_handshake_buf() {
                 Some(msgs) => {
                     let certs = &self.client_cert;
                     self.config
@@ -697,7 +708,8 @@ impl State<ServerConnectionData> for ExpectCertificateVerify<'_> {
                         Error::General("client authentication not set up".into()),
                     ));
                 }
-            }
+            };
+            (sig.scheme, rc)
         };
 
         if let Err(e) = rc {
@@ -707,6 +719,7 @@ impl State<ServerConnectionData> for ExpectCertificateVerify<'_> {
         }
 
         trace!("client CertificateVerify OK");
+        cx.common.peer_signature_scheme = Some(scheme);
         cx.common.peer_certificates = Some(self.client_cert.into_owned());
 
         self.transcript.add_message(&m);
@@ -917,7 +930,10 @@ impl State<ServerConnectionData> for ExpectFinished {
                 .config
                 .session_storage
                 .put(self.session_id.as_ref().to_vec(), value.get_encoding());
-            #[cfg_attr(not(feature = "log"), allow(clippy::if_same_then_else))]
+            #[cfg_attr(
+                not(any(feature = "log", feature = "tracing")),
+                allow(clippy::if_same_then_else)
+            )]
             if worked {
                 debug!("Session saved");
             } else {
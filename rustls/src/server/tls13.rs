@@ -15,6 +15,7 @@ use crate::common_state::{
 };
 use crate::conn::ConnectionRandoms;
 use crate::conn::kernel::{Direction, KernelContext, KernelState};
+use crate::custom_extension::{self, ExtensionContext};
 use crate::enums::{AlertDescription, ContentType, HandshakeType, ProtocolVersion};
 use crate::error::{Error, InvalidMessage, PeerIncompatible, PeerMisbehaved};
 use crate::hash_hs::HandshakeHash;
@@ -51,7 +52,7 @@ mod client_hello {
     use crate::msgs::handshake::{
         CertificatePayloadTls13, CertificateRequestExtensions, CertificateRequestPayloadTls13,
         ClientHelloPayload, HelloRetryRequest, HelloRetryRequestExtensions, KeyShareEntry, Random,
-        ServerExtensions, ServerExtensionsInput, ServerHelloPayload, SessionId,
+        ServerExtensions, ServerExtensionsInput, ServerHelloPayload, SessionId, UnknownExtension,
     };
     use crate::sealed::Sealed;
     use crate::server::common::ActiveCertifiedKey;
@@ -264,6 +265,13 @@ mod client_hello {
                 cch.send_tickets = cch.config.send_tls13_tickets;
             }
 
+            if cx.data.acme_tls_alpn_challenge {
+                debug!("Not resuming ACME TLS-ALPN-01 challenge");
+                cch.send_tickets = 0;
+                chosen_psk_index = None;
+                resumedata = None;
+            }
+
             if let Some(resume) = &resumedata {
                 cx.data.received_resumption_data = Some(resume.common.application_data.0.clone());
                 cx.common
@@ -312,19 +320,32 @@ mod client_hello {
             )?;
 
             let doing_client_auth = if full_handshake {
-                let client_auth = emit_certificate_req_tls13(&mut flight, &cch.config)?;
+                let client_auth = emit_certificate_req_tls13(&mut flight, cx.common, &cch.config)?;
 
+                let custom_extensions = custom_extension::emit(
+                    cx.common,
+                    &cch.config.custom_extensions,
+                    ExtensionContext::Certificate,
+                    Some(&client_hello.unknown_extensions),
+                );
                 if let Some(compressor) = cert_compressor {
                     emit_compressed_certificate_tls13(
                         &mut flight,
                         &cch.config,
                         server_key.get_cert(),
                         ocsp_response,
+                        custom_extensions,
                         compressor,
                     );
                 } else {
-                    emit_certificate_tls13(&mut flight, server_key.get_cert(), ocsp_response);
+                    emit_certificate_tls13(
+                        &mut flight,
+                        server_key.get_cert(),
+                        ocsp_response,
+                        custom_extensions,
+                    );
                 }
+                cx.common.local_certified_key = Some(server_key.get_certified_key().clone());
                 emit_certificate_verify_tls13(
                     &mut flight,
                     cx.common,
@@ -357,6 +378,7 @@ mod client_hello {
                     cx.data
                         .early_data
                         .accept(cch.config.max_early_data_size as usize);
+                    cx.common.early_data_accepted = true;
                 }
             }
 
@@ -715,6 +737,13 @@ mod client_hello {
             ep.extensions.early_data_ack = Some(());
         }
 
+        ep.extensions.unknown_extensions = custom_extension::emit(
+            cx.common,
+            &config.custom_extensions,
+            ExtensionContext::EncryptedExtensions,
+            Some(&hello.unknown_extensions),
+        );
+
         let ee = HandshakeMessagePayload(HandshakePayload::EncryptedExtensions(ep.extensions));
 
         trace!("sending encrypted extensions {ee:?}");
@@ -724,6 +753,7 @@ mod client_hello {
 
     fn emit_certificate_req_tls13(
         flight: &mut HandshakeFlightTls13<'_>,
+        common: &mut CommonState,
         config: &ServerConfig,
     ) -> Result<bool, Error> {
         if !config.verifier.offer_client_auth() {
@@ -755,6 +785,12 @@ mod client_hello {
                     [] => None,
                     authorities => Some(authorities.to_vec()),
                 },
+                unknown_extensions: custom_extension::emit(
+                    common,
+                    &config.custom_extensions,
+                    ExtensionContext::CertificateRequest,
+                    None,
+                ),
             },
         };
 
@@ -769,10 +805,11 @@ mod client_hello {
         flight: &mut HandshakeFlightTls13<'_>,
         cert_chain: &[CertificateDer<'static>],
         ocsp_response: Option<&[u8]>,
+        custom_extensions: Vec<UnknownExtension>,
     ) {
-        let cert = HandshakeMessagePayload(HandshakePayload::CertificateTls13(
-            CertificatePayloadTls13::new(cert_chain.iter(), ocsp_response),
-        ));
+        let mut payload = CertificatePayloadTls13::new(cert_chain.iter(), ocsp_response);
+        payload.add_end_entity_extensions(custom_extensions);
+        let cert = HandshakeMessagePayload(HandshakePayload::CertificateTls13(payload));
 
         trace!("sending certificate {cert:?}");
         flight.add(cert);
@@ -783,15 +820,17 @@ mod client_hello {
         config: &ServerConfig,
         cert_chain: &[CertificateDer<'static>],
         ocsp_response: Option<&[u8]>,
+        custom_extensions: Vec<UnknownExtension>,
         cert_compressor: &'static dyn CertCompressor,
     ) {
-        let payload = CertificatePayloadTls13::new(cert_chain.iter(), ocsp_response);
+        let mut payload = CertificatePayloadTls13::new(cert_chain.iter(), ocsp_response);
+        payload.add_end_entity_extensions(custom_extensions.clone());
 
         let Ok(entry) = config
             .cert_compression_cache
             .compression_for(cert_compressor, &payload)
         else {
-            return emit_certificate_tls13(flight, cert_chain, ocsp_response);
+            return emit_certificate_tls13(flight, cert_chain, ocsp_response, custom_extensions);
         };
 
         let c = HandshakeMessagePayload(HandshakePayload::CompressedCertificate(
@@ -1076,8 +1115,8 @@ impl State<ServerConnectionData> for ExpectCertificate {
             HandshakePayload::CertificateTls13
         )?;
 
-        // We don't send any CertificateRequest extensions, so any extensions
-        // here are illegal.
+        // We don't send any CertificateRequest extensions which solicit these,
+        // so any extensions here are illegal other than solicited custom extensions.
         if certp
             .entries
             .iter()
@@ -1086,6 +1125,13 @@ impl State<ServerConnectionData> for ExpectCertificate {
             return Err(PeerMisbehaved::UnsolicitedCertExtension.into());
         }
 
+        custom_extension::receive_certificate(
+            cx.common,
+            &self.config.custom_extensions,
+            &certp,
+            ExtensionContext::CertificateRequest,
+        )?;
+
         let client_cert = certp.into_certificate_chain();
 
         let mandatory = self
@@ -1114,13 +1160,16 @@ impl State<ServerConnectionData> for ExpectCertificate {
 
         let now = self.config.current_time()?;
 
-        self.config
+        let cert_verified = self
+            .config
             .verifier
             .verify_client_cert(end_entity, intermediates, now)
             .map_err(|err| {
                 cx.common
                     .send_cert_verify_error_alert(err)
             })?;
+        cx.common.peer_verified_path = cert_verified.verified_path().cloned();
+        cx.common.peer_spiffe_id = cert_verified.spiffe_id().cloned();
 
         Ok(Box::new(ExpectCertificateVerify {
             config: self.config,
@@ -1155,7 +1204,7 @@ impl State<ServerConnectionData> for ExpectCertificateVerify {
     where
         Self: 'm,
     {
-        let rc = {
+        let (scheme, rc) = {
             let sig = require_handshake_msg!(
                 m,
                 HandshakeType::CertificateVerify,
@@ -1166,9 +1215,11 @@ impl State<ServerConnectionData> for ExpectCertificateVerify {
             let certs = &self.client_cert;
             let msg = construct_client_verify_message(&handshake_hash);
 
-            self.config
+            let rc = self
+                .config
                 .verifier
-                .verify_tls13_signature(msg.as_ref(), &certs[0], sig)
+                .verify_tls13_signature(msg.as_ref(), &certs[0], sig);
+            (sig.scheme, rc)
         };
 
         if let Err(e) = rc {
@@ -1178,6 +1229,7 @@ impl State<ServerConnectionData> for ExpectCertificateVerify {
         }
 
         trace!("client CertificateVerify OK");
+        cx.common.peer_signature_scheme = Some(scheme);
         cx.common.peer_certificates = Some(self.client_cert);
 
         self.transcript.add_message(&m);
@@ -44,6 +44,10 @@ pub struct CipherSuiteCommon {
     ///
     /// For chacha20-poly1305 implementations, this should be set to `u64::MAX`:
     /// see <https://www.ietf.org/archive/id/draft-irtf-cfrg-aead-limits-08.html#section-5.2.1>
+    ///
+    /// For AES-CCM implementations (including AES-CCM-8), this should be set to 2<sup>23</sup>,
+    /// as CCM makes two block cipher calls per block:
+    /// see <https://www.ietf.org/archive/id/draft-irtf-cfrg-aead-limits-08.html#section-5.3>
     pub confidentiality_limit: u64,
 }
 
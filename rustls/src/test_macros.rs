@@ -3,8 +3,8 @@
 /// Instantiate the given test functions once for each built-in provider.
 ///
 /// The selected provider module is bound as `provider`; you can rely on this
-/// having the union of the items common to the `crypto::ring` and
-/// `crypto::aws_lc_rs` modules.
+/// having the union of the items common to the `crypto::ring`,
+/// `crypto::aws_lc_rs` and `crypto::rustcrypto` modules.
 #[cfg(test)]
 macro_rules! test_for_each_provider {
     ($($tt:tt)+) => {
@@ -23,14 +23,23 @@ macro_rules! test_for_each_provider {
             use super::*;
             $($tt)+
         }
+
+        // The tests use RSA keys, which this provider can only load with this feature.
+        #[cfg(feature = "rustcrypto-rsa-signing")]
+        mod test_with_rustcrypto {
+            use crate::crypto::rustcrypto as provider;
+            #[allow(unused_imports)]
+            use super::*;
+            $($tt)+
+        }
     };
 }
 
 /// Instantiate the given benchmark functions once for each built-in provider.
 ///
 /// The selected provider module is bound as `provider`; you can rely on this
-/// having the union of the items common to the `crypto::ring` and
-/// `crypto::aws_lc_rs` modules.
+/// having the union of the items common to the `crypto::ring`,
+/// `crypto::aws_lc_rs` and `crypto::rustcrypto` modules.
 #[cfg(bench)]
 macro_rules! bench_for_each_provider {
     ($($tt:tt)+) => {
@@ -49,6 +58,14 @@ macro_rules! bench_for_each_provider {
             use super::*;
             $($tt)+
         }
+
+        #[cfg(feature = "rustcrypto")]
+        mod bench_with_rustcrypto {
+            use crate::crypto::rustcrypto as provider;
+            #[allow(unused_imports)]
+            use super::*;
+            $($tt)+
+        }
     };
 }
 
@@ -107,7 +107,7 @@ impl TicketRotator {
         self.state.read().ok()
     }
 
-    #[cfg(any(feature = "aws-lc-rs", feature = "ring"))]
+    #[cfg(any(feature = "aws-lc-rs", feature = "ring", feature = "rustcrypto"))]
     pub(crate) const SIX_HOURS: u32 = 6 * 60 * 60;
 }
 
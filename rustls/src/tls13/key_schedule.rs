@@ -1040,9 +1040,7 @@ impl SecretKind {
 mod tests {
     use core::fmt::Debug;
     use std::prelude::v1::*;
-    use std::vec;
 
-    use super::provider::ring_like::aead;
     use super::provider::tls13::{
         TLS13_AES_128_GCM_SHA256_INTERNAL, TLS13_CHACHA20_POLY1305_SHA256_INTERNAL,
     };
@@ -1227,8 +1225,6 @@ mod tests {
         let log = Log(expected_traffic_secret);
         let traffic_secret = ks.derive_logged_secret(kind, hash, &log, &[0; 32]);
 
-        // Since we can't test key equality, we test the output of sealing with the key instead.
-        let aead_alg = &aead::AES_128_GCM;
         let expander = TLS13_AES_128_GCM_SHA256_INTERNAL
             .hkdf_provider
             .expander_for_okm(&traffic_secret);
@@ -1236,28 +1232,11 @@ mod tests {
             expander.as_ref(),
             TLS13_AES_128_GCM_SHA256_INTERNAL.aead_alg,
         );
-        let key = aead::UnboundKey::new(aead_alg, key.as_ref()).unwrap();
-        let seal_output = seal_zeroes(key);
-        let expected_key = aead::UnboundKey::new(aead_alg, expected_key).unwrap();
-        let expected_seal_output = seal_zeroes(expected_key);
-        assert_eq!(seal_output, expected_seal_output);
-        assert!(seal_output.len() >= 48); // Sanity check.
+        assert_eq!(key.as_ref(), expected_key);
 
         let iv = derive_traffic_iv(expander.as_ref());
         assert_eq!(iv.as_ref(), expected_iv);
     }
-
-    fn seal_zeroes(key: aead::UnboundKey) -> Vec<u8> {
-        let key = aead::LessSafeKey::new(key);
-        let mut seal_output = vec![0; 32];
-        key.seal_in_place_append_tag(
-            aead::Nonce::assume_unique_for_key([0; aead::NONCE_LEN]),
-            aead::Aad::empty(),
-            &mut seal_output,
-        )
-        .unwrap();
-        seal_output
-    }
 }
 
 #[cfg(all(test, bench))]
@@ -1,7 +1,7 @@
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
-use pki_types::{CertificateDer, ServerName, UnixTime};
+use pki_types::{CertificateDer, ServerName, TrustAnchor, UnixTime};
 
 use crate::enums::SignatureScheme;
 use crate::error::{Error, InvalidMessage};
@@ -9,6 +9,7 @@ use crate::msgs::base::PayloadU16;
 use crate::msgs::codec::{Codec, Reader};
 use crate::msgs::handshake::DistinguishedName;
 use crate::sync::Arc;
+use crate::webpki::SpiffeId;
 
 // Marker types.  These are used to bind the fact some verification
 // (certificate chain or handshake signature) has taken place into
@@ -40,27 +41,139 @@ impl FinishedMessageVerified {
     }
 }
 
-/// Zero-sized marker type representing verification of a server cert chain.
+/// Marker type representing verification of a server cert chain.
+///
+/// This may carry the [`VerifiedPath`] that was built during verification.
 #[allow(unreachable_pub)]
 #[derive(Debug)]
-pub struct ServerCertVerified(());
+pub struct ServerCertVerified {
+    path: Option<VerifiedPath>,
+    spiffe_id: Option<SpiffeId>,
+}
 
 #[allow(unreachable_pub)]
 impl ServerCertVerified {
     /// Make a `ServerCertVerified`
     pub fn assertion() -> Self {
-        Self(())
+        Self {
+            path: None,
+            spiffe_id: None,
+        }
+    }
+
+    /// Make a `ServerCertVerified` carrying the certificate path that was verified.
+    pub fn with_verified_path(path: VerifiedPath) -> Self {
+        Self {
+            path: Some(path),
+            spiffe_id: None,
+        }
+    }
+
+    /// The certificate path that was verified, if known.
+    pub fn verified_path(&self) -> Option<&VerifiedPath> {
+        self.path.as_ref()
+    }
+
+    /// Record the SPIFFE ID authenticated by a SPIFFE verifier.
+    pub(crate) fn with_spiffe_id(mut self, id: SpiffeId) -> Self {
+        self.spiffe_id = Some(id);
+        self
+    }
+
+    /// The SPIFFE ID authenticated during verification, if any.
+    pub(crate) fn spiffe_id(&self) -> Option<&SpiffeId> {
+        self.spiffe_id.as_ref()
     }
 }
 
-/// Zero-sized marker type representing verification of a client cert chain.
+/// Marker type representing verification of a client cert chain.
+///
+/// This may carry the [`VerifiedPath`] that was built during verification.
 #[derive(Debug)]
-pub struct ClientCertVerified(());
+pub struct ClientCertVerified {
+    path: Option<VerifiedPath>,
+    spiffe_id: Option<SpiffeId>,
+}
 
 impl ClientCertVerified {
     /// Make a `ClientCertVerified`
     pub fn assertion() -> Self {
-        Self(())
+        Self {
+            path: None,
+            spiffe_id: None,
+        }
+    }
+
+    /// Make a `ClientCertVerified` carrying the certificate path that was verified.
+    pub fn with_verified_path(path: VerifiedPath) -> Self {
+        Self {
+            path: Some(path),
+            spiffe_id: None,
+        }
+    }
+
+    /// The certificate path that was verified, if known.
+    pub fn verified_path(&self) -> Option<&VerifiedPath> {
+        self.path.as_ref()
+    }
+
+    /// Record the SPIFFE ID authenticated by a SPIFFE verifier.
+    pub(crate) fn with_spiffe_id(mut self, id: SpiffeId) -> Self {
+        self.spiffe_id = Some(id);
+        self
+    }
+
+    /// The SPIFFE ID authenticated during verification, if any.
+    pub(crate) fn spiffe_id(&self) -> Option<&SpiffeId> {
+        self.spiffe_id.as_ref()
+    }
+}
+
+/// A certificate path from an end-entity certificate to a trust anchor, as built and
+/// verified by a certificate verifier.
+///
+/// Unlike [`CommonState::peer_certificates()`], this contains only the certificates
+/// that were used, which need not all have been sent by the peer, and it identifies the
+/// trust anchor that terminated the path.
+///
+/// [`CommonState::peer_certificates()`]: crate::CommonState::peer_certificates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedPath {
+    end_entity: CertificateDer<'static>,
+    intermediates: Vec<CertificateDer<'static>>,
+    trust_anchor: TrustAnchor<'static>,
+}
+
+impl VerifiedPath {
+    /// Make a `VerifiedPath`.
+    ///
+    /// `intermediates` are ordered from the issuer of `end_entity` towards `trust_anchor`.
+    pub fn new(
+        end_entity: CertificateDer<'static>,
+        intermediates: Vec<CertificateDer<'static>>,
+        trust_anchor: TrustAnchor<'static>,
+    ) -> Self {
+        Self {
+            end_entity,
+            intermediates,
+            trust_anchor,
+        }
+    }
+
+    /// The end-entity certificate.
+    pub fn end_entity(&self) -> &CertificateDer<'static> {
+        &self.end_entity
+    }
+
+    /// The intermediate certificates, ordered from the issuer of the end-entity
+    /// certificate towards the trust anchor.
+    pub fn intermediates(&self) -> &[CertificateDer<'static>] {
+        &self.intermediates
+    }
+
+    /// The trust anchor which terminated the path.
+    pub fn trust_anchor(&self) -> &TrustAnchor<'static> {
+        &self.trust_anchor
     }
 }
 
@@ -376,7 +489,7 @@ fn assertions_are_debug() {
 
     assert_eq!(
         format!("{:?}", ClientCertVerified::assertion()),
-        "ClientCertVerified(())"
+        "ClientCertVerified { path: None, spiffe_id: None }"
     );
     assert_eq!(
         format!("{:?}", HandshakeSignatureValid::assertion()),
@@ -388,6 +501,6 @@ fn assertions_are_debug() {
     );
     assert_eq!(
         format!("{:?}", ServerCertVerified::assertion()),
-        "ServerCertVerified(())"
+        "ServerCertVerified { path: None, spiffe_id: None }"
     );
 }
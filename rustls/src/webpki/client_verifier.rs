@@ -1,12 +1,18 @@
 use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::time::Duration;
 
 use pki_types::{CertificateDer, CertificateRevocationListDer, UnixTime};
-use webpki::{CertRevocationList, ExpirationPolicy, RevocationCheckDepth, UnknownStatusPolicy};
-
+use webpki::{ExpirationPolicy, RevocationCheckDepth, UnknownStatusPolicy};
+
+#[cfg(feature = "std")]
+use super::crls::CrlSource;
+use super::crls::{CrlIndex, Revocation, Updatable};
+#[cfg(feature = "std")]
+use super::intermediates::IntermediatePool;
+use super::usage::UsagePolicy;
 use super::{VerifierBuilderError, pki_error};
 #[cfg(doc)]
-use crate::ConfigBuilder;
-#[cfg(doc)]
 use crate::crypto;
 use crate::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
 #[cfg(doc)]
@@ -16,8 +22,11 @@ use crate::verify::{
     ClientCertVerified, ClientCertVerifier, DigitallySignedStruct, HandshakeSignatureValid,
     NoClientAuth,
 };
-use crate::webpki::parse_crls;
-use crate::webpki::verify::{ParsedCertificate, verify_tls12_signature, verify_tls13_signature};
+use crate::webpki::verify::{
+    ParsedCertificate, owned_path, verify_tls12_signature, verify_tls13_signature,
+};
+#[cfg(doc)]
+use crate::{CertificateError, ConfigBuilder};
 use crate::{DistinguishedName, Error, RootCertStore, SignatureScheme};
 
 /// A builder for configuring a `webpki` client certificate verifier.
@@ -26,12 +35,17 @@ use crate::{DistinguishedName, Error, RootCertStore, SignatureScheme};
 #[derive(Debug, Clone)]
 pub struct ClientCertVerifierBuilder {
     roots: Arc<RootCertStore>,
-    root_hint_subjects: Vec<DistinguishedName>,
+    root_hints: RootHints,
     crls: Vec<CertificateRevocationListDer<'static>>,
+    #[cfg(feature = "std")]
+    crl_source: Option<(Arc<dyn CrlSource>, Duration)>,
+    #[cfg(feature = "std")]
+    intermediate_pool: Option<Arc<IntermediatePool>>,
     revocation_check_depth: RevocationCheckDepth,
     unknown_revocation_policy: UnknownStatusPolicy,
     revocation_expiration_policy: ExpirationPolicy,
     anon_policy: AnonymousClientPolicy,
+    usage: UsagePolicy,
     supported_algs: WebPkiSupportedAlgorithms,
 }
 
@@ -41,13 +55,21 @@ impl ClientCertVerifierBuilder {
         supported_algs: WebPkiSupportedAlgorithms,
     ) -> Self {
         Self {
-            root_hint_subjects: roots.subjects(),
             roots,
+            root_hints: RootHints {
+                from_roots: true,
+                extra: Vec::new(),
+            },
             crls: Vec::new(),
+            #[cfg(feature = "std")]
+            crl_source: None,
+            #[cfg(feature = "std")]
+            intermediate_pool: None,
             anon_policy: AnonymousClientPolicy::Deny,
             revocation_check_depth: RevocationCheckDepth::Chain,
             unknown_revocation_policy: UnknownStatusPolicy::Deny,
             revocation_expiration_policy: ExpirationPolicy::Ignore,
+            usage: UsagePolicy::client_auth(),
             supported_algs,
         }
     }
@@ -62,7 +84,10 @@ impl ClientCertVerifierBuilder {
     /// See [`ClientCertVerifier::root_hint_subjects`] for more information on
     /// circumstances where you may want to clear the default hint subjects.
     pub fn clear_root_hint_subjects(mut self) -> Self {
-        self.root_hint_subjects = Vec::default();
+        self.root_hints = RootHints {
+            from_roots: false,
+            extra: Vec::new(),
+        };
         self
     }
 
@@ -79,7 +104,7 @@ impl ClientCertVerifierBuilder {
         mut self,
         subjects: impl IntoIterator<Item = DistinguishedName>,
     ) -> Self {
-        self.root_hint_subjects.extend(subjects);
+        self.root_hints.extra.extend(subjects);
         self
     }
 
@@ -104,6 +129,33 @@ impl ClientCertVerifierBuilder {
         self
     }
 
+    /// Verify the revocation state of presented client certificates against CRLs
+    /// fetched from `source`, in addition to any given with [`with_crls`][Self::with_crls].
+    ///
+    /// CRLs are fetched when the verifier is built, and then again when verifying
+    /// a certificate if `refresh_interval` has passed since they were last fetched.
+    /// See [`CrlSource`] for more details.
+    #[cfg(feature = "std")]
+    pub fn with_crl_source(
+        mut self,
+        source: Arc<dyn CrlSource>,
+        refresh_interval: Duration,
+    ) -> Self {
+        self.crl_source = Some((source, refresh_interval));
+        self
+    }
+
+    /// Complete the chains presented by clients with certificates from `pool`.
+    ///
+    /// Pooled certificates are used as intermediates during path building, so that
+    /// clients which omit intermediates from their chain can still be verified.  They are
+    /// never used as trust anchors.  See [`IntermediatePool`] for more details.
+    #[cfg(feature = "std")]
+    pub fn with_intermediate_pool(mut self, pool: Arc<IntermediatePool>) -> Self {
+        self.intermediate_pool = Some(pool);
+        self
+    }
+
     /// Only check the end entity certificate revocation status when using CRLs.
     ///
     /// If CRLs are provided using [`with_crls`][Self::with_crls] only check the end entity
@@ -153,6 +205,55 @@ impl ClientCertVerifierBuilder {
         self
     }
 
+    /// Require client certificates to allow each of `oids` as an extended key usage,
+    /// instead of only `id-kp-clientAuth`.
+    ///
+    /// Each OID is given as its DER-encoded value, without the tag and length: for example,
+    /// `&[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02]` for `id-kp-clientAuth`.
+    ///
+    /// As specified in [RFC 5280], certificates without an extended key usage extension
+    /// may be used for any purpose.  If the extension is present in the end-entity
+    /// certificate, it must contain all of `oids`; if it is present in an intermediate
+    /// certificate, it must contain the first of `oids`.  Otherwise verification fails
+    /// with [`CertificateError::InvalidPurposeContext`].
+    ///
+    /// [RFC 5280]: https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.12
+    pub fn with_required_extended_key_usages(
+        mut self,
+        oids: impl IntoIterator<Item = &'static [u8]>,
+    ) -> Self {
+        self.usage.set_ekus(oids);
+        self
+    }
+
+    /// Accept `anyExtendedKeyUsage` in place of the required extended key usages.
+    ///
+    /// By default, an end-entity certificate whose extended key usage extension contains
+    /// `anyExtendedKeyUsage` must still contain the required extended key usages.  With
+    /// this option, it need not; but any intermediate certificates with an extended key
+    /// usage extension must then also contain `anyExtendedKeyUsage`.
+    pub fn allow_any_extended_key_usage(mut self) -> Self {
+        self.usage.allow_any_eku();
+        self
+    }
+
+    /// Require client certificates to assert each of `oids` as a certificate policy.
+    ///
+    /// OIDs are encoded as for [`with_required_extended_key_usages`][Self::with_required_extended_key_usages].
+    /// Calling this multiple times adds to the required policies.
+    ///
+    /// Each certificate in the verified path, other than the trust anchor, must assert the
+    /// policies itself: policy mappings and constraints, and `anyPolicy`, are not considered.
+    /// If a policy is missing, verification fails with
+    /// [`CertificateError::MissingCertificatePolicy`].
+    pub fn with_required_certificate_policies(
+        mut self,
+        oids: impl IntoIterator<Item = &'static [u8]>,
+    ) -> Self {
+        self.usage.add_policies(oids);
+        self
+    }
+
     /// Build a client certificate verifier. The built verifier will be used for the server to offer
     /// client certificate authentication, to control how offered client certificates are validated,
     /// and to determine what to do with anonymous clients that do not respond to the client
@@ -169,21 +270,44 @@ impl ClientCertVerifierBuilder {
     /// This function will return a [`VerifierBuilderError`] if:
     /// 1. No trust anchors have been provided.
     /// 2. DER encoded CRLs have been provided that can not be parsed successfully.
-    pub fn build(self) -> Result<Arc<dyn ClientCertVerifier>, VerifierBuilderError> {
+    /// 3. A CRL source has been provided, and CRLs could not be fetched from it.
+    /// 4. An empty set of required extended key usages has been provided.
+    pub fn build(self) -> Result<Arc<WebPkiClientVerifier>, VerifierBuilderError> {
         if self.roots.is_empty() {
             return Err(VerifierBuilderError::NoRootAnchors);
         }
+        if !self.usage.has_ekus() {
+            return Err(VerifierBuilderError::NoExtendedKeyUsages);
+        }
 
-        Ok(Arc::new(WebPkiClientVerifier::new(
-            self.roots,
-            Arc::from(self.root_hint_subjects),
-            parse_crls(self.crls)?,
+        let revocation = Revocation::new(
+            CrlIndex::parse(self.crls)?,
             self.revocation_check_depth,
             self.unknown_revocation_policy,
             self.revocation_expiration_policy,
+        );
+        #[cfg(feature = "std")]
+        let revocation = match self.crl_source {
+            Some((source, interval)) => revocation
+                .with_source(source, interval, UnixTime::now())
+                .map_err(VerifierBuilderError::CrlSourceFailed)?,
+            None => revocation,
+        };
+
+        #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+        let mut verifier = WebPkiClientVerifier::new(
+            self.roots,
+            self.root_hints,
+            revocation,
             self.anon_policy,
+            self.usage,
             self.supported_algs,
-        )))
+        );
+        #[cfg(feature = "std")]
+        {
+            verifier.intermediate_pool = self.intermediate_pool;
+        }
+        Ok(Arc::new(verifier))
     }
 }
 
@@ -250,13 +374,15 @@ impl ClientCertVerifierBuilder {
 /// [^1]: <https://github.com/rustls/webpki>
 #[derive(Debug)]
 pub struct WebPkiClientVerifier {
-    roots: Arc<RootCertStore>,
-    root_hint_subjects: Arc<[DistinguishedName]>,
-    crls: Vec<CertRevocationList<'static>>,
-    revocation_check_depth: RevocationCheckDepth,
-    unknown_revocation_policy: UnknownStatusPolicy,
-    revocation_expiration_policy: ExpirationPolicy,
+    roots: Updatable<RootCertStore>,
+    #[cfg(feature = "std")]
+    root_hints: RootHints,
+    root_hint_subjects: Updatable<[DistinguishedName]>,
+    revocation: Revocation,
     anonymous_policy: AnonymousClientPolicy,
+    usage: UsagePolicy,
+    #[cfg(feature = "std")]
+    intermediate_pool: Option<Arc<IntermediatePool>>,
     supported_algs: WebPkiSupportedAlgorithms,
 }
 
@@ -307,38 +433,77 @@ impl WebPkiClientVerifier {
     /// Construct a new `WebpkiClientVerifier`.
     ///
     /// * `roots` is a list of trust anchors to use for certificate validation.
-    /// * `root_hint_subjects` is a list of distinguished names to use for hinting acceptable
+    /// * `root_hints` controls the distinguished names to use for hinting acceptable
     ///   certificate authority subjects to a client.
-    /// * `crls` is a `Vec` of owned certificate revocation lists (CRLs) to use for
+    /// * `revocation` controls how certificate revocation lists (CRLs) are used for
     ///   client certificate validation.
-    /// * `revocation_check_depth` controls which certificates have their revocation status checked
-    ///   when `crls` are provided.
-    /// * `unknown_revocation_policy` controls how certificates with an unknown revocation status
-    ///   are handled when `crls` are provided.
     /// * `anonymous_policy` controls whether client authentication is required, or if anonymous
     ///   clients can connect.
+    /// * `usage` is the extended key usage and certificate policy requirements for
+    ///   client certificates.
     /// * `supported_algs` specifies which signature verification algorithms should be used.
-    pub(crate) fn new(
+    fn new(
         roots: Arc<RootCertStore>,
-        root_hint_subjects: Arc<[DistinguishedName]>,
-        crls: Vec<CertRevocationList<'static>>,
-        revocation_check_depth: RevocationCheckDepth,
-        unknown_revocation_policy: UnknownStatusPolicy,
-        revocation_expiration_policy: ExpirationPolicy,
+        root_hints: RootHints,
+        revocation: Revocation,
         anonymous_policy: AnonymousClientPolicy,
+        usage: UsagePolicy,
         supported_algs: WebPkiSupportedAlgorithms,
     ) -> Self {
         Self {
-            roots,
-            root_hint_subjects,
-            crls,
-            revocation_check_depth,
-            unknown_revocation_policy,
-            revocation_expiration_policy,
+            root_hint_subjects: Updatable::new(root_hints.for_roots(&roots)),
+            roots: Updatable::new(roots),
+            #[cfg(feature = "std")]
+            root_hints,
+            revocation,
             anonymous_policy,
+            usage,
+            #[cfg(feature = "std")]
+            intermediate_pool: None,
             supported_algs,
         }
     }
+
+    /// Replace the trust anchors used to verify client certificates.
+    ///
+    /// Unless [`ClientCertVerifierBuilder::clear_root_hint_subjects()`] was used, the
+    /// root hint subjects sent to clients are also replaced by the subjects of `roots`,
+    /// followed by any given to [`ClientCertVerifierBuilder::add_root_hint_subjects()`].
+    ///
+    /// This takes effect for verifications that start after it returns; verifications
+    /// in progress continue with the previous trust anchors.
+    ///
+    /// This fails if `roots` is empty.
+    #[cfg(feature = "std")]
+    pub fn update_roots(&self, roots: Arc<RootCertStore>) -> Result<(), VerifierBuilderError> {
+        if roots.is_empty() {
+            return Err(VerifierBuilderError::NoRootAnchors);
+        }
+
+        self.root_hint_subjects
+            .set(self.root_hints.for_roots(&roots));
+        self.roots.set(roots);
+        Ok(())
+    }
+
+    /// Replace the certificate revocation lists (CRLs) used to check the revocation
+    /// state of client certificates.
+    ///
+    /// This replaces all CRLs previously given to [`ClientCertVerifierBuilder::with_crls()`]
+    /// or this function, but not those fetched from a [`CrlSource`].  It takes effect for
+    /// verifications that start after it returns.  If any CRL cannot be parsed, this fails
+    /// and the previous CRLs remain in use.
+    ///
+    /// Giving no CRLs disables revocation checking, unless a [`CrlSource`] is also in use.
+    #[cfg(feature = "std")]
+    pub fn update_crls(
+        &self,
+        crls: impl IntoIterator<Item = CertificateRevocationListDer<'static>>,
+    ) -> Result<(), VerifierBuilderError> {
+        self.revocation
+            .update(CrlIndex::parse(crls.into_iter().collect())?);
+        Ok(())
+    }
 }
 
 impl ClientCertVerifier for WebPkiClientVerifier {
@@ -354,7 +519,7 @@ impl ClientCertVerifier for WebPkiClientVerifier {
     }
 
     fn root_hint_subjects(&self) -> Arc<[DistinguishedName]> {
-        self.root_hint_subjects.clone()
+        self.root_hint_subjects.get()
     }
 
     fn verify_client_cert(
@@ -364,36 +529,52 @@ impl ClientCertVerifier for WebPkiClientVerifier {
         now: UnixTime,
     ) -> Result<ClientCertVerified, Error> {
         let cert = ParsedCertificate::try_from(end_entity)?;
-
-        let crl_refs = self.crls.iter().collect::<Vec<_>>();
-
-        let revocation = if self.crls.is_empty() {
-            None
-        } else {
-            Some(
-                webpki::RevocationOptionsBuilder::new(&crl_refs)
-                    // Note: safe to unwrap here - new is only fallible if no CRLs are provided
-                    //       and we verify this above.
-                    .unwrap()
-                    .with_depth(self.revocation_check_depth)
-                    .with_status_policy(self.unknown_revocation_policy)
-                    .with_expiration_policy(self.revocation_expiration_policy)
-                    .build(),
-            )
+        let roots = self.roots.get();
+
+        #[cfg(feature = "std")]
+        let pooled = self
+            .intermediate_pool
+            .as_ref()
+            .map(|pool| pool.certs());
+        #[cfg(feature = "std")]
+        let extended = pooled
+            .as_ref()
+            .map(|pooled| IntermediatePool::extend(intermediates, pooled));
+        #[cfg(feature = "std")]
+        let intermediates = extended
+            .as_deref()
+            .unwrap_or(intermediates);
+
+        #[cfg(feature = "std")]
+        let learn = |path: &webpki::VerifiedPath<'_>| {
+            if let Some(pool) = &self.intermediate_pool {
+                pool.learn(path);
+            }
         };
-
-        cert.0
-            .verify_for_usage(
-                self.supported_algs.all,
-                &self.roots.roots,
-                intermediates,
-                now,
-                webpki::KeyUsage::client_auth(),
-                revocation,
-                None,
-            )
-            .map_err(pki_error)
-            .map(|_| ClientCertVerified::assertion())
+        #[cfg(not(feature = "std"))]
+        let learn = |_: &webpki::VerifiedPath<'_>| {};
+
+        self.usage
+            .check(&cert, |usage| {
+                self.revocation
+                    .check(&cert, intermediates, now, |revocation| {
+                        cert.0.verify_for_usage(
+                            self.supported_algs.all,
+                            &roots.roots,
+                            intermediates,
+                            now,
+                            usage,
+                            revocation,
+                            None,
+                        )
+                    })
+                    .map(|path| {
+                        learn(&path);
+                        owned_path(&path)
+                    })
+                    .map_err(pki_error)
+            })
+            .map(ClientCertVerified::with_verified_path)
     }
 
     fn verify_tls12_signature(
@@ -419,6 +600,27 @@ impl ClientCertVerifier for WebPkiClientVerifier {
     }
 }
 
+/// Controls the root hint subjects of a [WebPkiClientVerifier].
+#[derive(Debug, Clone)]
+struct RootHints {
+    /// Whether the subjects of the trust anchors are included.
+    from_roots: bool,
+    /// Further subjects, following those of the trust anchors.
+    extra: Vec<DistinguishedName>,
+}
+
+impl RootHints {
+    fn for_roots(&self, roots: &RootCertStore) -> Arc<[DistinguishedName]> {
+        let mut subjects = if self.from_roots {
+            roots.subjects()
+        } else {
+            Vec::new()
+        };
+        subjects.extend(self.extra.iter().cloned());
+        Arc::from(subjects)
+    }
+}
+
 /// Controls how the [WebPkiClientVerifier] handles anonymous clients.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum AnonymousClientPolicy {
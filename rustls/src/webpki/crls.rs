@@ -0,0 +1,301 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::PoisonError;
+
+use pki_types::{CertificateDer, CertificateRevocationListDer, UnixTime};
+use webpki::{
+    CertRevocationList, ExpirationPolicy, RevocationCheckDepth, RevocationOptions,
+    UnknownStatusPolicy,
+};
+
+use super::parse_crls;
+use super::verify::ParsedCertificate;
+use crate::error::CertRevocationListError;
+#[cfg(feature = "std")]
+use crate::error::Error;
+#[cfg(feature = "std")]
+use crate::lock::Mutex;
+#[cfg(feature = "std")]
+use crate::log::{debug, warn};
+use crate::sync::Arc;
+
+/// A source of certificate revocation lists (CRLs), which are refreshed periodically.
+///
+/// Use this with [`ServerCertVerifierBuilder::with_crl_source()`] or
+/// [`ClientCertVerifierBuilder::with_crl_source()`].  The verifier fetches CRLs from
+/// the source when it is built, and again when verifying a certificate if the refresh
+/// interval has passed since the last fetch.  Fetching happens synchronously, in the
+/// handshake that notices the interval has passed.
+///
+/// Fetched CRLs are parsed once, and then shared by all verifications until the next
+/// fetch.  If a later fetch fails, or returns CRLs that cannot be parsed, the previously
+/// fetched CRLs remain in use.
+///
+/// [`ServerCertVerifierBuilder::with_crl_source()`]: crate::client::ServerCertVerifierBuilder::with_crl_source
+/// [`ClientCertVerifierBuilder::with_crl_source()`]: crate::server::ClientCertVerifierBuilder::with_crl_source
+#[cfg(feature = "std")]
+pub trait CrlSource: Debug + Send + Sync {
+    /// Return the current set of DER-encoded CRLs.
+    ///
+    /// These replace all CRLs previously returned by this source.
+    fn crls(&self) -> Result<Vec<CertificateRevocationListDer<'static>>, Error>;
+}
+
+/// Revocation checking configuration and state shared by the webpki verifiers.
+#[derive(Debug)]
+pub(super) struct Revocation {
+    crls: Updatable<CrlIndex>,
+    #[cfg(feature = "std")]
+    source: Option<RefreshedCrls>,
+    pub(super) depth: RevocationCheckDepth,
+    pub(super) unknown_status_policy: UnknownStatusPolicy,
+    pub(super) expiration_policy: ExpirationPolicy,
+}
+
+impl Revocation {
+    pub(super) fn new(
+        crls: CrlIndex,
+        depth: RevocationCheckDepth,
+        unknown_status_policy: UnknownStatusPolicy,
+        expiration_policy: ExpirationPolicy,
+    ) -> Self {
+        Self {
+            crls: Updatable::new(Arc::new(crls)),
+            #[cfg(feature = "std")]
+            source: None,
+            depth,
+            unknown_status_policy,
+            expiration_policy,
+        }
+    }
+
+    /// Also use CRLs fetched from `source`, first fetching them now.
+    #[cfg(feature = "std")]
+    pub(super) fn with_source(
+        mut self,
+        source: Arc<dyn CrlSource>,
+        interval: Duration,
+        now: UnixTime,
+    ) -> Result<Self, Error> {
+        let crls = source
+            .crls()
+            .and_then(|crls| Ok(CrlIndex::parse(crls)?))?;
+        self.source = Some(RefreshedCrls {
+            source,
+            interval,
+            crls: Updatable::new(Arc::new(crls)),
+            last_refresh: Mutex::new(now),
+        });
+        Ok(self)
+    }
+
+    /// Replace the CRLs which were not fetched from a [`CrlSource`].
+    #[cfg(feature = "std")]
+    pub(super) fn update(&self, crls: CrlIndex) {
+        self.crls.set(Arc::new(crls));
+    }
+
+    /// Call `f` with the revocation options for verifying `end_entity` and `intermediates`
+    /// at `now`, or `None` if no CRLs are configured.
+    pub(super) fn check<T>(
+        &self,
+        end_entity: &ParsedCertificate<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+        f: impl FnOnce(Option<RevocationOptions<'_>>) -> T,
+    ) -> T {
+        let crls = self.crls.get();
+        #[cfg(feature = "std")]
+        let fetched = self
+            .source
+            .as_ref()
+            .map(|source| source.get(now));
+        #[cfg(not(feature = "std"))]
+        let fetched: Option<Arc<CrlIndex>> = {
+            let _ = now;
+            None
+        };
+
+        let indices = [Some(&crls), fetched.as_ref()];
+        let indices = indices.iter().flatten();
+
+        let mut issuers = Vec::with_capacity(intermediates.len() + 1);
+        issuers.push(end_entity.0.issuer());
+        let parsed_intermediates = intermediates
+            .iter()
+            .map(ParsedCertificate::try_from)
+            .collect::<Result<Vec<_>, _>>();
+
+        // If an intermediate cannot be parsed, path building will reject it.
+        let mut crl_refs = Vec::new();
+        if let Ok(parsed) = &parsed_intermediates {
+            issuers.extend(
+                parsed
+                    .iter()
+                    .map(|cert| cert.0.issuer()),
+            );
+            for index in indices.clone() {
+                index.relevant_to(&issuers, &mut crl_refs);
+            }
+        }
+
+        // If no CRL is relevant, webpki must still be given some, so that the revocation
+        // status of the chain is unknown rather than unchecked.
+        if crl_refs.is_empty() {
+            for index in indices {
+                crl_refs.extend(index.crls.iter());
+            }
+        }
+
+        if crl_refs.is_empty() {
+            return f(None);
+        }
+
+        f(Some(
+            webpki::RevocationOptionsBuilder::new(&crl_refs)
+                // Note: safe to unwrap here - new is only fallible if no CRLs are provided
+                //       and we verify this above.
+                .unwrap()
+                .with_depth(self.depth)
+                .with_status_policy(self.unknown_status_policy)
+                .with_expiration_policy(self.expiration_policy)
+                .build(),
+        ))
+    }
+}
+
+/// Parsed certificate revocation lists, indexed by issuer.
+#[derive(Debug, Default)]
+pub(super) struct CrlIndex {
+    /// Sorted by issuer.
+    crls: Vec<CertRevocationList<'static>>,
+}
+
+impl CrlIndex {
+    pub(super) fn parse(
+        crls: Vec<CertificateRevocationListDer<'_>>,
+    ) -> Result<Self, CertRevocationListError> {
+        let mut crls = parse_crls(crls)?;
+        crls.sort_by(|a, b| a.issuer().cmp(b.issuer()));
+        Ok(Self { crls })
+    }
+
+    /// Add the CRLs issued by any of `issuers` to `out`.
+    fn relevant_to<'a>(
+        &'a self,
+        issuers: &[&[u8]],
+        out: &mut Vec<&'a CertRevocationList<'static>>,
+    ) {
+        for issuer in issuers {
+            let start = self
+                .crls
+                .partition_point(|crl| crl.issuer() < *issuer);
+            let matching = self.crls[start..]
+                .iter()
+                .take_while(|crl| crl.issuer() == *issuer);
+            for crl in matching {
+                if !out
+                    .iter()
+                    .any(|seen| core::ptr::eq(*seen, crl))
+                {
+                    out.push(crl);
+                }
+            }
+        }
+    }
+}
+
+/// CRLs fetched from a [`CrlSource`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct RefreshedCrls {
+    source: Arc<dyn CrlSource>,
+    interval: Duration,
+    crls: Updatable<CrlIndex>,
+    last_refresh: Mutex<UnixTime>,
+}
+
+#[cfg(feature = "std")]
+impl RefreshedCrls {
+    fn get(&self, now: UnixTime) -> Arc<CrlIndex> {
+        self.refresh_if_due(now);
+        self.crls.get()
+    }
+
+    fn refresh_if_due(&self, now: UnixTime) {
+        {
+            let Some(mut last_refresh) = self.last_refresh.lock() else {
+                return;
+            };
+            if now
+                .as_secs()
+                .saturating_sub(last_refresh.as_secs())
+                < self.interval.as_secs()
+            {
+                return;
+            }
+            // Only one caller should refresh: others continue with the current CRLs.
+            *last_refresh = now;
+        }
+
+        #[cfg_attr(
+            not(any(feature = "log", feature = "tracing")),
+            allow(unused_variables)
+        )]
+        match self
+            .source
+            .crls()
+            .and_then(|crls| Ok(CrlIndex::parse(crls)?))
+        {
+            Ok(crls) => {
+                debug!("refreshed CRLs from {:?}", self.source);
+                self.crls.set(Arc::new(crls));
+            }
+            Err(err) => warn!("failed to refresh CRLs, keeping previous CRLs: {err}"),
+        }
+    }
+}
+
+/// A value which can be atomically replaced at runtime, if the `std` feature is enabled.
+#[derive(Debug)]
+pub(super) struct Updatable<T: ?Sized> {
+    #[cfg(feature = "std")]
+    current: std::sync::Mutex<Arc<T>>,
+    #[cfg(not(feature = "std"))]
+    current: Arc<T>,
+}
+
+impl<T: ?Sized> Updatable<T> {
+    pub(super) fn new(value: Arc<T>) -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            current: std::sync::Mutex::new(value),
+            #[cfg(not(feature = "std"))]
+            current: value,
+        }
+    }
+
+    pub(super) fn get(&self) -> Arc<T> {
+        // The lock only ever guards replacing the whole `Arc`, so a poisoned
+        // lock still holds the last good value.
+        #[cfg(feature = "std")]
+        return self
+            .current
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        #[cfg(not(feature = "std"))]
+        return self.current.clone();
+    }
+
+    #[cfg(feature = "std")]
+    pub(super) fn set(&self, value: Arc<T>) {
+        *self
+            .current
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = value;
+    }
+}
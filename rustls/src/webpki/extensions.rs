@@ -0,0 +1,344 @@
+use alloc::vec::Vec;
+
+/// Certificate extensions which are not exposed by `webpki`.
+pub(super) struct Extensions<'a> {
+    /// The extended key usage OIDs, if the extension is present.
+    pub(super) ekus: Option<Vec<&'a [u8]>>,
+    /// The certificate policy OIDs.
+    pub(super) policies: Vec<&'a [u8]>,
+    /// The `uniformResourceIdentifier` subject alternative names.
+    pub(super) uris: Vec<&'a [u8]>,
+    /// Whether basic constraints mark this as a CA certificate.
+    pub(super) is_ca: bool,
+}
+
+impl<'a> Extensions<'a> {
+    /// Parse the extensions of a DER-encoded certificate, returning `None` if it cannot
+    /// be parsed.
+    ///
+    /// Encodings which are valid BER but not DER are rejected, as are certificates which
+    /// include an extension more than once.
+    pub(super) fn parse(cert: &'a [u8]) -> Option<Self> {
+        let mut certificate = expect_all(cert, SEQUENCE)?;
+        let mut tbs_certificate = expect(&mut certificate, SEQUENCE)?;
+
+        let mut extensions = Self {
+            ekus: None,
+            policies: Vec::new(),
+            uris: Vec::new(),
+            is_ca: false,
+        };
+
+        // Skip to the optional `[3] Extensions` field, which comes last.
+        let mut list = loop {
+            if tbs_certificate.is_empty() {
+                return Some(extensions);
+            }
+            let (tag, value) = read(&mut tbs_certificate)?;
+            if tag == EXTENSIONS {
+                if !tbs_certificate.is_empty() {
+                    return None;
+                }
+                break expect_all(value, SEQUENCE)?;
+            }
+        };
+
+        let mut ids = Vec::new();
+        while !list.is_empty() {
+            let mut extension = expect(&mut list, SEQUENCE)?;
+            let id = expect(&mut extension, OID)?;
+            if ids.contains(&id) {
+                return None;
+            }
+            ids.push(id);
+
+            let (mut tag, mut value) = read(&mut extension)?;
+            if tag == BOOLEAN {
+                // `critical` is DEFAULT FALSE, so DER only encodes it when true.
+                if value != [TRUE] {
+                    return None;
+                }
+                (tag, value) = read(&mut extension)?;
+            }
+            if tag != OCTET_STRING || !extension.is_empty() {
+                return None;
+            }
+
+            match id {
+                ID_CE_EXT_KEY_USAGE => {
+                    let mut purposes = expect_all(value, SEQUENCE)?;
+                    let mut ekus = Vec::new();
+                    while !purposes.is_empty() {
+                        ekus.push(expect(&mut purposes, OID)?);
+                    }
+                    extensions.ekus = Some(ekus);
+                }
+                ID_CE_CERTIFICATE_POLICIES => {
+                    let mut policies = expect_all(value, SEQUENCE)?;
+                    while !policies.is_empty() {
+                        let mut information = expect(&mut policies, SEQUENCE)?;
+                        extensions
+                            .policies
+                            .push(expect(&mut information, OID)?);
+                    }
+                }
+                ID_CE_SUBJECT_ALT_NAME => {
+                    let mut names = expect_all(value, SEQUENCE)?;
+                    while !names.is_empty() {
+                        let (tag, name) = read(&mut names)?;
+                        if tag == URI_NAME {
+                            extensions.uris.push(name);
+                        }
+                    }
+                }
+                ID_CE_BASIC_CONSTRAINTS => {
+                    let mut constraints = expect_all(value, SEQUENCE)?;
+                    if constraints.first() == Some(&BOOLEAN) {
+                        // `cA` is DEFAULT FALSE, so DER only encodes it when true.
+                        if expect(&mut constraints, BOOLEAN)? != [TRUE] {
+                            return None;
+                        }
+                        extensions.is_ca = true;
+                    }
+                    if !constraints.is_empty() {
+                        expect_all(constraints, INTEGER)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Some(extensions)
+    }
+}
+
+/// Read a DER value with tag `expected` from `input`, returning its contents.
+fn expect<'a>(input: &mut &'a [u8], expected: u8) -> Option<&'a [u8]> {
+    match read(input)? {
+        (tag, value) if tag == expected => Some(value),
+        _ => None,
+    }
+}
+
+/// Read a DER value with tag `expected` which is all of `input`, returning its contents.
+fn expect_all(mut input: &[u8], expected: u8) -> Option<&[u8]> {
+    let value = expect(&mut input, expected)?;
+    input.is_empty().then_some(value)
+}
+
+/// Read a DER value from `input`, returning its tag and contents.
+///
+/// Only single-byte tags, and lengths of up to four bytes, are supported.  Lengths
+/// must use the shortest encoding, as DER requires.
+fn read<'a>(input: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+    let (&tag, rest) = input.split_first()?;
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let (&first, mut rest) = rest.split_first()?;
+
+    let len = match first {
+        0x00..=0x7f => usize::from(first),
+        0x81..=0x84 => {
+            let count = usize::from(first & 0x7f);
+            if rest.len() < count {
+                return None;
+            }
+            let (bytes, after) = rest.split_at(count);
+            rest = after;
+
+            // The long form is only for lengths which do not fit the short form, and
+            // has no leading zero bytes.
+            let len = bytes
+                .iter()
+                .fold(0usize, |len, byte| (len << 8) | usize::from(*byte));
+            if bytes[0] == 0 || len < 0x80 {
+                return None;
+            }
+            len
+        }
+        // The indefinite form, or a length which is too long.
+        _ => return None,
+    };
+
+    if rest.len() < len {
+        return None;
+    }
+    let (value, rest) = rest.split_at(len);
+    *input = rest;
+    Some((tag, value))
+}
+
+/// Decode the arcs of a DER-encoded OID value.
+pub(super) fn oid_values(oid: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    let mut arcs = oid
+        .split_inclusive(|byte| byte & 0x80 == 0)
+        .map(|bytes| {
+            bytes
+                .iter()
+                .fold(0usize, |arc, byte| (arc << 7) | usize::from(byte & 0x7f))
+        });
+
+    // The first two arcs are encoded together.
+    let first = arcs.next().map(|arc| match arc {
+        0..=39 => [0, arc],
+        40..=79 => [1, arc - 40],
+        _ => [2, arc - 80],
+    });
+    first.into_iter().flatten().chain(arcs)
+}
+
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const EXTENSIONS: u8 = 0xa3;
+// The DER encoding of a true BOOLEAN
+const TRUE: u8 = 0xff;
+// GeneralName uniformResourceIdentifier, [6] IMPLICIT IA5String
+const URI_NAME: u8 = 0x86;
+
+// id-ce-extKeyUsage, 2.5.29.37
+const ID_CE_EXT_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25];
+// id-ce-certificatePolicies, 2.5.29.32
+const ID_CE_CERTIFICATE_POLICIES: &[u8] = &[0x55, 0x1d, 0x20];
+// id-ce-subjectAltName, 2.5.29.17
+const ID_CE_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+// id-ce-basicConstraints, 2.5.29.19
+const ID_CE_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn decodes_oid_values() {
+        // 1.2.840.113549
+        assert_eq!(
+            oid_values(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d]).collect::<Vec<_>>(),
+            alloc::vec![1, 2, 840, 113549]
+        );
+    }
+
+    #[test]
+    fn reads_long_form_lengths() {
+        let mut der = alloc::vec![SEQUENCE, 0x81, 0x80];
+        der.extend([0u8; 0x80]);
+        der.push(0xff);
+        let mut input = &der[..];
+        let (tag, value) = read(&mut input).unwrap();
+        assert_eq!(tag, SEQUENCE);
+        assert_eq!(value.len(), 0x80);
+        assert_eq!(input, &[0xff]);
+
+        assert!(read(&mut &[SEQUENCE, 0x82, 0x01][..]).is_none());
+        assert!(read(&mut &[SEQUENCE, 0x02, 0x00][..]).is_none());
+    }
+
+    #[test]
+    fn rejects_non_minimal_lengths() {
+        assert!(read(&mut &[SEQUENCE, 0x81, 0x01, 0x00][..]).is_none());
+        let mut der = alloc::vec![SEQUENCE, 0x82, 0x00, 0x80];
+        der.extend([0u8; 0x80]);
+        assert!(read(&mut &der[..]).is_none());
+        // indefinite length
+        assert!(read(&mut &[SEQUENCE, 0x80, 0x00, 0x00][..]).is_none());
+    }
+
+    #[test]
+    fn parses_basic_constraints() {
+        let ca = extension(
+            ID_CE_BASIC_CONSTRAINTS,
+            None,
+            &der(SEQUENCE, &der(BOOLEAN, &[TRUE])),
+        );
+        assert!(
+            Extensions::parse(&certificate(&[&ca]))
+                .unwrap()
+                .is_ca
+        );
+
+        let not_ca = extension(ID_CE_BASIC_CONSTRAINTS, Some(TRUE), &der(SEQUENCE, &[]));
+        assert!(
+            !Extensions::parse(&certificate(&[&not_ca]))
+                .unwrap()
+                .is_ca
+        );
+
+        // an explicitly encoded DEFAULT FALSE is not DER
+        let explicit_false = extension(
+            ID_CE_BASIC_CONSTRAINTS,
+            None,
+            &der(SEQUENCE, &der(BOOLEAN, &[0x00])),
+        );
+        assert!(Extensions::parse(&certificate(&[&explicit_false])).is_none());
+    }
+
+    #[test]
+    fn rejects_explicit_non_critical() {
+        let value = der(SEQUENCE, &der(OID, &[0x2b, 0x06]));
+        let critical = extension(ID_CE_EXT_KEY_USAGE, Some(TRUE), &value);
+        assert_eq!(
+            Extensions::parse(&certificate(&[&critical]))
+                .unwrap()
+                .ekus,
+            Some(alloc::vec![&[0x2b, 0x06][..]])
+        );
+
+        let non_critical = extension(ID_CE_EXT_KEY_USAGE, Some(0x00), &value);
+        assert!(Extensions::parse(&certificate(&[&non_critical])).is_none());
+    }
+
+    #[test]
+    fn rejects_duplicate_extensions() {
+        let san = extension(
+            ID_CE_SUBJECT_ALT_NAME,
+            None,
+            &der(SEQUENCE, &der(URI_NAME, b"spiffe://example.org/a")),
+        );
+        assert_eq!(
+            Extensions::parse(&certificate(&[&san]))
+                .unwrap()
+                .uris,
+            alloc::vec![&b"spiffe://example.org/a"[..]]
+        );
+        assert!(Extensions::parse(&certificate(&[&san, &san])).is_none());
+    }
+
+    #[test]
+    fn rejects_trailing_data() {
+        let mut cert = certificate(&[]);
+        cert.push(0x00);
+        assert!(Extensions::parse(&cert).is_none());
+
+        let mut value = der(SEQUENCE, &[]);
+        value.push(0x00);
+        let extension = extension(ID_CE_CERTIFICATE_POLICIES, None, &value);
+        assert!(Extensions::parse(&certificate(&[&extension])).is_none());
+    }
+
+    /// Encode a certificate whose `TBSCertificate` has only an `[3] Extensions` field.
+    fn certificate(extensions: &[&[u8]]) -> Vec<u8> {
+        let extensions = der(EXTENSIONS, &der(SEQUENCE, &extensions.concat()));
+        der(SEQUENCE, &der(SEQUENCE, &extensions))
+    }
+
+    fn extension(id: &[u8], critical: Option<u8>, value: &[u8]) -> Vec<u8> {
+        let mut contents = der(OID, id);
+        if let Some(critical) = critical {
+            contents.extend(der(BOOLEAN, &[critical]));
+        }
+        contents.extend(der(OCTET_STRING, value));
+        der(SEQUENCE, &contents)
+    }
+
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        assert!(contents.len() < 0x80);
+        let mut der = alloc::vec![tag, contents.len() as u8];
+        der.extend_from_slice(contents);
+        der
+    }
+}
@@ -0,0 +1,141 @@
+use alloc::vec::Vec;
+
+use pki_types::CertificateDer;
+
+use super::verify::ParsedCertificate;
+use crate::error::Error;
+use crate::lock::Mutex;
+use crate::sync::Arc;
+
+/// A bounded pool of intermediate certificates, used to complete certificate chains.
+///
+/// Peers sometimes omit intermediate certificates from the chains they present, so that
+/// the chain cannot be verified.  Giving a pool to
+/// [`ServerCertVerifierBuilder::with_intermediate_pool()`] or
+/// [`ClientCertVerifierBuilder::with_intermediate_pool()`] lets the verifier use pooled
+/// certificates during path building, alongside the presented chain.
+///
+/// Pooled certificates are only ever used as intermediates: they must still chain to one
+/// of the verifier's trust anchors, and are never trusted themselves.
+///
+/// The pool can be populated explicitly with [`Self::add()`], and with
+/// [`Self::learn_from_verified_chains()`] it also learns the intermediates of chains that
+/// were successfully verified.  Once the pool is full, adding a certificate evicts the
+/// least recently added one.  Every pooled certificate may be considered during path
+/// building, so keep the pool small.
+///
+/// [`ServerCertVerifierBuilder::with_intermediate_pool()`]: crate::client::ServerCertVerifierBuilder::with_intermediate_pool
+/// [`ClientCertVerifierBuilder::with_intermediate_pool()`]: crate::server::ClientCertVerifierBuilder::with_intermediate_pool
+#[derive(Debug)]
+pub struct IntermediatePool {
+    /// Ordered from least to most recently added.
+    certs: Mutex<Arc<[CertificateDer<'static>]>>,
+    max_size: usize,
+    learning: bool,
+}
+
+impl IntermediatePool {
+    /// Create an empty pool holding at most `max_size` certificates.
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            certs: Mutex::new(Arc::from(Vec::new())),
+            max_size,
+            learning: false,
+        }
+    }
+
+    /// Add the intermediate certificates of successfully verified chains to the pool.
+    ///
+    /// Only certificates on the verified path to a trust anchor are added.
+    pub fn learn_from_verified_chains(mut self) -> Self {
+        self.learning = true;
+        self
+    }
+
+    /// Add `cert` to the pool.
+    ///
+    /// If `cert` is already pooled, it becomes the most recently added certificate.
+    /// This fails if `cert` cannot be parsed.
+    pub fn add(&self, cert: CertificateDer<'static>) -> Result<(), Error> {
+        ParsedCertificate::try_from(&cert)?;
+        self.insert([cert]);
+        Ok(())
+    }
+
+    /// Return the number of pooled certificates.
+    pub fn len(&self) -> usize {
+        self.certs().len()
+    }
+
+    /// Return true if no certificates are pooled.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add the intermediates of a verified path which are not yet pooled, if learning
+    /// is enabled.
+    pub(super) fn learn(&self, path: &webpki::VerifiedPath<'_>) {
+        if !self.learning {
+            return;
+        }
+
+        let pooled = self.certs();
+        let new = path
+            .intermediate_certificates()
+            .map(|cert| cert.der())
+            .filter(|cert| {
+                !pooled
+                    .iter()
+                    .any(|pooled| pooled.as_ref() == cert.as_ref())
+            })
+            .map(|cert| cert.into_owned())
+            .collect::<Vec<_>>();
+        if !new.is_empty() {
+            self.insert(new);
+        }
+    }
+
+    /// Return `presented` followed by the pooled certificates which were not presented.
+    pub(super) fn extend<'a>(
+        presented: &'a [CertificateDer<'a>],
+        pooled: &'a [CertificateDer<'static>],
+    ) -> Vec<CertificateDer<'a>> {
+        let mut intermediates = presented
+            .iter()
+            .map(|cert| CertificateDer::from(cert.as_ref()))
+            .collect::<Vec<_>>();
+        for cert in pooled {
+            if !presented
+                .iter()
+                .any(|presented| presented.as_ref() == cert.as_ref())
+            {
+                intermediates.push(CertificateDer::from(cert.as_ref()));
+            }
+        }
+        intermediates
+    }
+
+    pub(super) fn certs(&self) -> Arc<[CertificateDer<'static>]> {
+        let Some(certs) = self.certs.lock() else {
+            return Arc::from(Vec::new());
+        };
+        certs.clone()
+    }
+
+    fn insert(&self, new: impl IntoIterator<Item = CertificateDer<'static>>) {
+        let Some(mut certs) = self.certs.lock() else {
+            return;
+        };
+
+        let mut updated = certs.to_vec();
+        for cert in new {
+            updated.retain(|existing| *existing != cert);
+            updated.push(cert);
+        }
+        let excess = updated
+            .len()
+            .saturating_sub(self.max_size);
+        updated.drain(..excess);
+        *certs = Arc::from(updated);
+    }
+}
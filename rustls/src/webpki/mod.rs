@@ -12,12 +12,32 @@ use crate::sync::Arc;
 
 mod anchors;
 mod client_verifier;
+mod crls;
+mod extensions;
+#[cfg(feature = "std")]
+mod intermediates;
+mod pinning;
 mod server_verifier;
+mod spiffe;
+mod tofu;
+mod usage;
 mod verify;
 
 pub use anchors::RootCertStore;
 pub use client_verifier::{ClientCertVerifierBuilder, WebPkiClientVerifier};
+#[cfg(feature = "std")]
+pub use crls::CrlSource;
+#[cfg(feature = "std")]
+pub use intermediates::IntermediatePool;
+pub use pinning::{SpkiPinningVerifier, SpkiPins};
 pub use server_verifier::{ServerCertVerifierBuilder, WebPkiServerVerifier};
+pub use spiffe::{
+    InvalidSpiffeId, SpiffeClientVerifier, SpiffeId, SpiffeIdAuthorizer, SpiffeServerVerifier,
+    SpiffeVerifierBuilder,
+};
+#[cfg(feature = "std")]
+pub use tofu::{FileTofuStore, MemoryTofuStore};
+pub use tofu::{TofuOutcome, TofuStore, TofuVerifier};
 // Conditionally exported from crate.
 #[allow(unreachable_pub)]
 pub use verify::{
@@ -36,6 +56,11 @@ pub enum VerifierBuilderError {
     NoRootAnchors,
     /// A provided CRL could not be parsed.
     InvalidCrl(CertRevocationListError),
+    /// An empty set of required extended key usages was provided.
+    NoExtendedKeyUsages,
+    /// CRLs could not be fetched from a [`CrlSource`].
+    #[cfg(feature = "std")]
+    CrlSourceFailed(Error),
 }
 
 impl From<CertRevocationListError> for VerifierBuilderError {
@@ -49,6 +74,11 @@ impl fmt::Display for VerifierBuilderError {
         match self {
             Self::NoRootAnchors => write!(f, "no root trust anchors were provided"),
             Self::InvalidCrl(e) => write!(f, "provided CRL could not be parsed: {e:?}"),
+            Self::NoExtendedKeyUsages => {
+                write!(f, "no required extended key usages were provided")
+            }
+            #[cfg(feature = "std")]
+            Self::CrlSourceFailed(e) => write!(f, "CRLs could not be fetched: {e}"),
         }
     }
 }
@@ -183,7 +213,7 @@ fn crl_error(e: webpki::Error) -> CertRevocationListError {
 
 fn parse_crls(
     crls: Vec<CertificateRevocationListDer<'_>>,
-) -> Result<Vec<CertRevocationList<'_>>, CertRevocationListError> {
+) -> Result<Vec<CertRevocationList<'static>>, CertRevocationListError> {
     crls.iter()
         .map(|der| OwnedCertRevocationList::from_der(der.as_ref()).map(Into::into))
         .collect::<Result<Vec<_>, _>>()
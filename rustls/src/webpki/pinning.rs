@@ -0,0 +1,242 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use pki_types::{CertificateDer, ServerName, UnixTime};
+
+use super::WebPkiServerVerifier;
+use crate::crypto::CryptoProvider;
+use crate::crypto::hash::{Hash, HashAlgorithm};
+use crate::enums::SignatureScheme;
+use crate::error::{CertificateError, Error};
+use crate::log::debug;
+use crate::msgs::handshake::DistinguishedName;
+use crate::sync::Arc;
+use crate::verify::{
+    DigitallySignedStruct, HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use crate::x509::wrap_in_sequence;
+
+/// A server certificate verifier which additionally pins the public keys of servers.
+///
+/// This first verifies server certificates with a [`WebPkiServerVerifier`].  Then, if pins
+/// are configured for the server name, it requires that at least one certificate in the
+/// verified path has a public key matching one of those pins.  Otherwise, verification
+/// fails with [`CertificateError::PinMismatch`].
+///
+/// A pin is the SHA-256 hash of a DER-encoded `SubjectPublicKeyInfo`, as used by
+/// [RFC 7469].  The verified path is the end-entity certificate, the intermediate
+/// certificates used to reach a trust anchor, and that trust anchor.  Other certificates
+/// sent by the server are not checked.
+///
+/// Server names without pins, or whose pins have expired, are verified by the
+/// [`WebPkiServerVerifier`] alone.
+///
+/// [RFC 7469]: https://datatracker.ietf.org/doc/html/rfc7469#section-2.4
+pub struct SpkiPinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    sha256: &'static dyn Hash,
+    pins: Vec<(ServerName<'static>, SpkiPins)>,
+}
+
+impl SpkiPinningVerifier {
+    /// Create a verifier which uses `inner` to verify certificates, and has no pins.
+    ///
+    /// SHA-256 is taken from the cipher suites of `provider`.  This fails if none of them
+    /// uses SHA-256.
+    pub fn new(inner: Arc<WebPkiServerVerifier>, provider: &CryptoProvider) -> Result<Self, Error> {
+        let sha256 = provider
+            .cipher_suites
+            .iter()
+            .map(|suite| suite.hash_provider())
+            .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+            .ok_or_else(|| Error::General("SPKI pinning requires SHA-256".into()))?;
+
+        Ok(Self {
+            inner,
+            sha256,
+            pins: Vec::new(),
+        })
+    }
+
+    /// Pin the public keys of `server_name` to `pins`, replacing any existing pins for it.
+    pub fn add(&mut self, server_name: ServerName<'static>, pins: SpkiPins) {
+        match self
+            .pins
+            .iter_mut()
+            .find(|(name, _)| *name == server_name)
+        {
+            Some((_, existing)) => *existing = pins,
+            None => self.pins.push((server_name, pins)),
+        }
+    }
+
+    fn pins_for(&self, server_name: &ServerName<'_>, now: UnixTime) -> Option<&SpkiPins> {
+        let (_, pins) = self
+            .pins
+            .iter()
+            .find(|(name, _)| name == server_name)?;
+        if pins.expired(now) {
+            debug!("pins for {server_name:?} have expired");
+            return None;
+        }
+        Some(pins)
+    }
+
+    fn check_pins(&self, pins: &SpkiPins, path: &webpki::VerifiedPath<'_>) -> bool {
+        let anchor = wrap_in_sequence(
+            path.anchor()
+                .subject_public_key_info
+                .as_ref(),
+        );
+        let certs = Some(
+            path.end_entity()
+                .subject_public_key_info(),
+        )
+        .into_iter()
+        .chain(
+            path.intermediate_certificates()
+                .map(|cert| cert.subject_public_key_info()),
+        );
+
+        for spki in certs.chain(Some(anchor.into())) {
+            let hash = self.sha256.hash(spki.as_ref());
+
+            if pins
+                .pins
+                .iter()
+                .any(|pin| pin == hash.as_ref())
+            {
+                return true;
+            }
+            if pins
+                .backup_pins
+                .iter()
+                .any(|pin| pin == hash.as_ref())
+            {
+                debug!("server certificate matched a backup pin");
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl fmt::Debug for SpkiPinningVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpkiPinningVerifier")
+            .field("inner", &self.inner)
+            .field("pins", &self.pins)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ServerCertVerifier for SpkiPinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let Some(pins) = self.pins_for(server_name, now) else {
+            return self.inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            );
+        };
+
+        let mut pinned = false;
+        let path = self.inner.verify_server_cert_path(
+            end_entity,
+            intermediates,
+            server_name,
+            now,
+            |path| pinned = self.check_pins(pins, path),
+        )?;
+
+        match pinned {
+            true => Ok(ServerCertVerified::with_verified_path(path)),
+            false => Err(CertificateError::PinMismatch.into()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner
+            .verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner
+            .verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn request_ocsp_response(&self) -> bool {
+        self.inner.request_ocsp_response()
+    }
+
+    fn root_hint_subjects(&self) -> Option<Arc<[DistinguishedName]>> {
+        self.inner.root_hint_subjects()
+    }
+}
+
+/// A set of public key pins for a server, for use with [`SpkiPinningVerifier`].
+///
+/// Each pin is the SHA-256 hash of a DER-encoded `SubjectPublicKeyInfo`.
+#[derive(Clone, Debug, Default)]
+pub struct SpkiPins {
+    pins: Vec<[u8; 32]>,
+    backup_pins: Vec<[u8; 32]>,
+    expires: Option<UnixTime>,
+}
+
+impl SpkiPins {
+    /// Create a pin set from the pins of the keys currently in use.
+    pub fn new(pins: impl IntoIterator<Item = [u8; 32]>) -> Self {
+        Self {
+            pins: pins.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Add backup pins, for keys which are not yet in use.
+    ///
+    /// These are accepted in the same way as other pins.  They allow the server to
+    /// change keys without waiting for clients to learn new pins.
+    pub fn with_backup_pins(mut self, pins: impl IntoIterator<Item = [u8; 32]>) -> Self {
+        self.backup_pins.extend(pins);
+        self
+    }
+
+    /// Stop enforcing these pins from `expires` onwards.
+    ///
+    /// This limits the damage done by stale pins, for example in an application that is no
+    /// longer updated: after expiry, the server's certificates need only be valid.
+    pub fn with_expiry(mut self, expires: UnixTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    fn expired(&self, now: UnixTime) -> bool {
+        self.expires
+            .is_some_and(|expires| now.as_secs() >= expires.as_secs())
+    }
+}
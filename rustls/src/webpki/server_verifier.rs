@@ -1,20 +1,29 @@
 use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::time::Duration;
 
 use pki_types::{CertificateDer, CertificateRevocationListDer, ServerName, UnixTime};
-use webpki::{CertRevocationList, ExpirationPolicy, RevocationCheckDepth, UnknownStatusPolicy};
-
+use webpki::{ExpirationPolicy, RevocationCheckDepth, UnknownStatusPolicy};
+
+#[cfg(feature = "std")]
+use super::crls::CrlSource;
+use super::crls::{CrlIndex, Revocation, Updatable};
+#[cfg(feature = "std")]
+use super::intermediates::IntermediatePool;
+use super::usage::UsagePolicy;
 use crate::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
 use crate::sync::Arc;
 use crate::verify::{
     DigitallySignedStruct, HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+    VerifiedPath,
 };
 use crate::webpki::verify::{
     ParsedCertificate, verify_server_cert_signed_by_trust_anchor_impl, verify_tls12_signature,
     verify_tls13_signature,
 };
-use crate::webpki::{VerifierBuilderError, parse_crls, verify_server_name};
+use crate::webpki::{VerifierBuilderError, verify_server_name};
 #[cfg(doc)]
-use crate::{ConfigBuilder, ServerConfig, crypto};
+use crate::{CertificateError, ConfigBuilder, ServerConfig, crypto};
 use crate::{Error, RootCertStore, SignatureScheme};
 
 /// A builder for configuring a `webpki` server certificate verifier.
@@ -24,9 +33,14 @@ use crate::{Error, RootCertStore, SignatureScheme};
 pub struct ServerCertVerifierBuilder {
     roots: Arc<RootCertStore>,
     crls: Vec<CertificateRevocationListDer<'static>>,
+    #[cfg(feature = "std")]
+    crl_source: Option<(Arc<dyn CrlSource>, Duration)>,
+    #[cfg(feature = "std")]
+    intermediate_pool: Option<Arc<IntermediatePool>>,
     revocation_check_depth: RevocationCheckDepth,
     unknown_revocation_policy: UnknownStatusPolicy,
     revocation_expiration_policy: ExpirationPolicy,
+    usage: UsagePolicy,
     supported_algs: WebPkiSupportedAlgorithms,
 }
 
@@ -38,9 +52,14 @@ impl ServerCertVerifierBuilder {
         Self {
             roots,
             crls: Vec::new(),
+            #[cfg(feature = "std")]
+            crl_source: None,
+            #[cfg(feature = "std")]
+            intermediate_pool: None,
             revocation_check_depth: RevocationCheckDepth::Chain,
             unknown_revocation_policy: UnknownStatusPolicy::Deny,
             revocation_expiration_policy: ExpirationPolicy::Ignore,
+            usage: UsagePolicy::server_auth(),
             supported_algs,
         }
     }
@@ -56,6 +75,33 @@ impl ServerCertVerifierBuilder {
         self
     }
 
+    /// Verify the revocation state of presented server certificates against CRLs
+    /// fetched from `source`, in addition to any given with [`with_crls`][Self::with_crls].
+    ///
+    /// CRLs are fetched when the verifier is built, and then again when verifying
+    /// a certificate if `refresh_interval` has passed since they were last fetched.
+    /// See [`CrlSource`] for more details.
+    #[cfg(feature = "std")]
+    pub fn with_crl_source(
+        mut self,
+        source: Arc<dyn CrlSource>,
+        refresh_interval: Duration,
+    ) -> Self {
+        self.crl_source = Some((source, refresh_interval));
+        self
+    }
+
+    /// Complete the chains presented by servers with certificates from `pool`.
+    ///
+    /// Pooled certificates are used as intermediates during path building, so that
+    /// servers which omit intermediates from their chain can still be verified.  They are
+    /// never used as trust anchors.  See [`IntermediatePool`] for more details.
+    #[cfg(feature = "std")]
+    pub fn with_intermediate_pool(mut self, pool: Arc<IntermediatePool>) -> Self {
+        self.intermediate_pool = Some(pool);
+        self
+    }
+
     /// Only check the end entity certificate revocation status when using CRLs.
     ///
     /// If CRLs are provided using [`with_crls`][Self::with_crls] only check the end entity
@@ -96,6 +142,55 @@ impl ServerCertVerifierBuilder {
         self
     }
 
+    /// Require server certificates to allow each of `oids` as an extended key usage,
+    /// instead of only `id-kp-serverAuth`.
+    ///
+    /// Each OID is given as its DER-encoded value, without the tag and length: for example,
+    /// `&[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01]` for `id-kp-serverAuth`.
+    ///
+    /// As specified in [RFC 5280], certificates without an extended key usage extension
+    /// may be used for any purpose.  If the extension is present in the end-entity
+    /// certificate, it must contain all of `oids`; if it is present in an intermediate
+    /// certificate, it must contain the first of `oids`.  Otherwise verification fails
+    /// with [`CertificateError::InvalidPurposeContext`].
+    ///
+    /// [RFC 5280]: https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.12
+    pub fn with_required_extended_key_usages(
+        mut self,
+        oids: impl IntoIterator<Item = &'static [u8]>,
+    ) -> Self {
+        self.usage.set_ekus(oids);
+        self
+    }
+
+    /// Accept `anyExtendedKeyUsage` in place of the required extended key usages.
+    ///
+    /// By default, an end-entity certificate whose extended key usage extension contains
+    /// `anyExtendedKeyUsage` must still contain the required extended key usages.  With
+    /// this option, it need not; but any intermediate certificates with an extended key
+    /// usage extension must then also contain `anyExtendedKeyUsage`.
+    pub fn allow_any_extended_key_usage(mut self) -> Self {
+        self.usage.allow_any_eku();
+        self
+    }
+
+    /// Require server certificates to assert each of `oids` as a certificate policy.
+    ///
+    /// OIDs are encoded as for [`with_required_extended_key_usages`][Self::with_required_extended_key_usages].
+    /// Calling this multiple times adds to the required policies.
+    ///
+    /// Each certificate in the verified path, other than the trust anchor, must assert the
+    /// policies itself: policy mappings and constraints, and `anyPolicy`, are not considered.
+    /// If a policy is missing, verification fails with
+    /// [`CertificateError::MissingCertificatePolicy`].
+    pub fn with_required_certificate_policies(
+        mut self,
+        oids: impl IntoIterator<Item = &'static [u8]>,
+    ) -> Self {
+        self.usage.add_policies(oids);
+        self
+    }
+
     /// Build a server certificate verifier, allowing control over the root certificates to use as
     /// trust anchors, and to control how server certificate revocation checking is performed.
     ///
@@ -110,20 +205,38 @@ impl ServerCertVerifierBuilder {
     /// This function will return a [`VerifierBuilderError`] if:
     /// 1. No trust anchors have been provided.
     /// 2. DER encoded CRLs have been provided that can not be parsed successfully.
+    /// 3. A CRL source has been provided, and CRLs could not be fetched from it.
+    /// 4. An empty set of required extended key usages has been provided.
     pub fn build(self) -> Result<Arc<WebPkiServerVerifier>, VerifierBuilderError> {
         if self.roots.is_empty() {
             return Err(VerifierBuilderError::NoRootAnchors);
         }
+        if !self.usage.has_ekus() {
+            return Err(VerifierBuilderError::NoExtendedKeyUsages);
+        }
 
-        Ok(WebPkiServerVerifier::new(
-            self.roots,
-            parse_crls(self.crls)?,
+        let revocation = Revocation::new(
+            CrlIndex::parse(self.crls)?,
             self.revocation_check_depth,
             self.unknown_revocation_policy,
             self.revocation_expiration_policy,
-            self.supported_algs,
-        )
-        .into())
+        );
+        #[cfg(feature = "std")]
+        let revocation = match self.crl_source {
+            Some((source, interval)) => revocation
+                .with_source(source, interval, UnixTime::now())
+                .map_err(VerifierBuilderError::CrlSourceFailed)?,
+            None => revocation,
+        };
+
+        #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+        let mut verifier =
+            WebPkiServerVerifier::new(self.roots, revocation, self.usage, self.supported_algs);
+        #[cfg(feature = "std")]
+        {
+            verifier.intermediate_pool = self.intermediate_pool;
+        }
+        Ok(verifier.into())
     }
 }
 
@@ -131,11 +244,11 @@ impl ServerCertVerifierBuilder {
 #[allow(unreachable_pub)]
 #[derive(Debug)]
 pub struct WebPkiServerVerifier {
-    roots: Arc<RootCertStore>,
-    crls: Vec<CertRevocationList<'static>>,
-    revocation_check_depth: RevocationCheckDepth,
-    unknown_revocation_policy: UnknownStatusPolicy,
-    revocation_expiration_policy: ExpirationPolicy,
+    roots: Updatable<RootCertStore>,
+    revocation: Revocation,
+    usage: UsagePolicy,
+    #[cfg(feature = "std")]
+    intermediate_pool: Option<Arc<IntermediatePool>>,
     supported: WebPkiSupportedAlgorithms,
 }
 
@@ -179,10 +292,13 @@ impl WebPkiServerVerifier {
     ) -> Self {
         Self::new(
             roots,
-            Vec::default(),
-            RevocationCheckDepth::Chain,
-            UnknownStatusPolicy::Allow,
-            ExpirationPolicy::Ignore,
+            Revocation::new(
+                CrlIndex::default(),
+                RevocationCheckDepth::Chain,
+                UnknownStatusPolicy::Allow,
+                ExpirationPolicy::Ignore,
+            ),
+            UsagePolicy::server_auth(),
             supported_algs,
         )
     }
@@ -190,31 +306,121 @@ impl WebPkiServerVerifier {
     /// Constructs a new `WebPkiServerVerifier`.
     ///
     /// * `roots` is the set of trust anchors to trust for issuing server certs.
-    /// * `crls` are a vec of owned certificate revocation lists (CRLs) to use for
-    ///   client certificate validation.
-    /// * `revocation_check_depth` controls which certificates have their revocation status checked
-    ///   when `crls` are provided.
-    /// * `unknown_revocation_policy` controls how certificates with an unknown revocation status
-    ///   are handled when `crls` are provided.
+    /// * `revocation` controls how certificate revocation lists (CRLs) are used for
+    ///   server certificate validation.
+    /// * `usage` is the extended key usage and certificate policy requirements for
+    ///   server certificates.
     /// * `supported` is the set of supported algorithms that will be used for
     ///   certificate verification and TLS handshake signature verification.
-    pub(crate) fn new(
+    pub(super) fn new(
         roots: impl Into<Arc<RootCertStore>>,
-        crls: Vec<CertRevocationList<'static>>,
-        revocation_check_depth: RevocationCheckDepth,
-        unknown_revocation_policy: UnknownStatusPolicy,
-        revocation_expiration_policy: ExpirationPolicy,
+        revocation: Revocation,
+        usage: UsagePolicy,
         supported: WebPkiSupportedAlgorithms,
     ) -> Self {
         Self {
-            roots: roots.into(),
-            crls,
-            revocation_check_depth,
-            unknown_revocation_policy,
-            revocation_expiration_policy,
+            roots: Updatable::new(roots.into()),
+            revocation,
+            usage,
+            #[cfg(feature = "std")]
+            intermediate_pool: None,
             supported,
         }
     }
+
+    /// Replace the trust anchors used to verify server certificates.
+    ///
+    /// This takes effect for verifications that start after it returns; verifications
+    /// in progress continue with the previous trust anchors.
+    ///
+    /// This fails if `roots` is empty.
+    #[cfg(feature = "std")]
+    pub fn update_roots(&self, roots: Arc<RootCertStore>) -> Result<(), VerifierBuilderError> {
+        if roots.is_empty() {
+            return Err(VerifierBuilderError::NoRootAnchors);
+        }
+
+        self.roots.set(roots);
+        Ok(())
+    }
+
+    /// Replace the certificate revocation lists (CRLs) used to check the revocation
+    /// state of server certificates.
+    ///
+    /// This replaces all CRLs previously given to [`ServerCertVerifierBuilder::with_crls()`]
+    /// or this function, but not those fetched from a [`CrlSource`].  It takes effect for
+    /// verifications that start after it returns.  If any CRL cannot be parsed, this fails
+    /// and the previous CRLs remain in use.
+    ///
+    /// Giving no CRLs disables revocation checking, unless a [`CrlSource`] is also in use.
+    #[cfg(feature = "std")]
+    pub fn update_crls(
+        &self,
+        crls: impl IntoIterator<Item = CertificateRevocationListDer<'static>>,
+    ) -> Result<(), VerifierBuilderError> {
+        self.revocation
+            .update(CrlIndex::parse(crls.into_iter().collect())?);
+        Ok(())
+    }
+
+    /// Verify `end_entity` as [`ServerCertVerifier::verify_server_cert()`] does.
+    ///
+    /// `on_verified` is called with the path from `end_entity` to a trust anchor,
+    /// if path validation succeeds.  An owned copy of that path is returned.
+    pub(super) fn verify_server_cert_path(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        now: UnixTime,
+        on_verified: impl FnOnce(&webpki::VerifiedPath<'_>),
+    ) -> Result<VerifiedPath, Error> {
+        let cert = ParsedCertificate::try_from(end_entity)?;
+        let roots = self.roots.get();
+
+        #[cfg(feature = "std")]
+        let pooled = self
+            .intermediate_pool
+            .as_ref()
+            .map(|pool| pool.certs());
+        #[cfg(feature = "std")]
+        let extended = pooled
+            .as_ref()
+            .map(|pooled| IntermediatePool::extend(intermediates, pooled));
+        #[cfg(feature = "std")]
+        let intermediates = extended
+            .as_deref()
+            .unwrap_or(intermediates);
+
+        let on_verified = |path: &webpki::VerifiedPath<'_>| {
+            #[cfg(feature = "std")]
+            if let Some(pool) = &self.intermediate_pool {
+                pool.learn(path);
+            }
+            on_verified(path);
+        };
+
+        // Note: we use the crate-internal `_impl` fn here in order to provide revocation
+        // checking information, if applicable.
+        let path = self.usage.check(&cert, |usage| {
+            self.revocation
+                .check(&cert, intermediates, now, |revocation| {
+                    verify_server_cert_signed_by_trust_anchor_impl(
+                        &cert,
+                        &roots,
+                        intermediates,
+                        revocation,
+                        now,
+                        self.supported.all,
+                        usage,
+                        on_verified,
+                    )
+                })
+        })?;
+
+        verify_server_name(&cert, server_name)?;
+        Ok(path)
+    }
 }
 
 impl ServerCertVerifier for WebPkiServerVerifier {
@@ -236,40 +442,9 @@ impl ServerCertVerifier for WebPkiServerVerifier {
         _ocsp_response: &[u8],
         now: UnixTime,
     ) -> Result<ServerCertVerified, Error> {
-        let cert = ParsedCertificate::try_from(end_entity)?;
-
-        let crl_refs = self.crls.iter().collect::<Vec<_>>();
-
-        let revocation = if self.crls.is_empty() {
-            None
-        } else {
-            // Note: unwrap here is safe because RevocationOptionsBuilder only errors when given
-            //       empty CRLs.
-            Some(
-                webpki::RevocationOptionsBuilder::new(crl_refs.as_slice())
-                    // Note: safe to unwrap here - new is only fallible if no CRLs are provided
-                    //       and we verify this above.
-                    .unwrap()
-                    .with_depth(self.revocation_check_depth)
-                    .with_status_policy(self.unknown_revocation_policy)
-                    .with_expiration_policy(self.revocation_expiration_policy)
-                    .build(),
-            )
-        };
-
-        // Note: we use the crate-internal `_impl` fn here in order to provide revocation
-        // checking information, if applicable.
-        verify_server_cert_signed_by_trust_anchor_impl(
-            &cert,
-            &self.roots,
-            intermediates,
-            revocation,
-            now,
-            self.supported.all,
-        )?;
-
-        verify_server_name(&cert, server_name)?;
-        Ok(ServerCertVerified::assertion())
+        let path =
+            self.verify_server_cert_path(end_entity, intermediates, server_name, now, |_| {})?;
+        Ok(ServerCertVerified::with_verified_path(path))
     }
 
     fn verify_tls12_signature(
@@ -0,0 +1,461 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use pki_types::{CertificateDer, ServerName, UnixTime};
+
+use super::extensions::Extensions;
+use super::verify::{
+    ParsedCertificate, owned_path, verify_tls12_signature, verify_tls13_signature,
+};
+use super::{VerifierBuilderError, pki_error};
+use crate::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
+use crate::error::{CertificateError, Error};
+use crate::sync::Arc;
+use crate::verify::{
+    ClientCertVerified, ClientCertVerifier, DigitallySignedStruct, HandshakeSignatureValid,
+    ServerCertVerified, ServerCertVerifier, VerifiedPath,
+};
+use crate::{DistinguishedName, RootCertStore, SignatureScheme};
+
+/// A [SPIFFE ID], which identifies a workload.
+///
+/// This has the form `spiffe://<trust domain>/<path>`, where the path may be empty.
+///
+/// [SPIFFE ID]: https://github.com/spiffe/spiffe/blob/main/standards/SPIFFE-ID.md
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SpiffeId {
+    id: String,
+    path_start: usize,
+}
+
+impl SpiffeId {
+    /// Extract the SPIFFE ID from an [X509-SVID], which is an end-entity certificate.
+    ///
+    /// This fails with [`CertificateError::InvalidSvid`] unless the certificate has exactly
+    /// one URI subject alternative name, that name is a valid SPIFFE ID, and the certificate
+    /// is not a CA certificate.  It does not verify the certificate.
+    ///
+    /// [X509-SVID]: https://github.com/spiffe/spiffe/blob/main/standards/X509-SVID.md
+    pub fn from_certificate(cert: &CertificateDer<'_>) -> Result<Self, Error> {
+        let extensions = Extensions::parse(cert.as_ref())
+            .ok_or(Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        if extensions.is_ca {
+            return Err(CertificateError::InvalidSvid.into());
+        }
+
+        let [uri] = extensions.uris.as_slice() else {
+            return Err(CertificateError::InvalidSvid.into());
+        };
+        core::str::from_utf8(uri)
+            .ok()
+            .and_then(|uri| Self::try_from(uri).ok())
+            .ok_or(CertificateError::InvalidSvid.into())
+    }
+
+    /// The trust domain, which identifies the issuing authority.
+    pub fn trust_domain(&self) -> &str {
+        &self.id[SCHEME.len()..self.path_start]
+    }
+
+    /// The path, which identifies the workload within its trust domain.
+    ///
+    /// This is empty, or starts with `/`.
+    pub fn path(&self) -> &str {
+        &self.id[self.path_start..]
+    }
+
+    /// The whole SPIFFE ID, including the `spiffe://` scheme.
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+}
+
+impl TryFrom<&str> for SpiffeId {
+    type Error = InvalidSpiffeId;
+
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        if id.len() > MAX_ID_LEN {
+            return Err(InvalidSpiffeId);
+        }
+
+        let rest = id
+            .strip_prefix(SCHEME)
+            .ok_or(InvalidSpiffeId)?;
+        let (trust_domain, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        if !valid_trust_domain(trust_domain) {
+            return Err(InvalidSpiffeId);
+        }
+
+        if !path.is_empty() {
+            let valid = path[1..].split('/').all(|segment| {
+                !segment.is_empty()
+                    && segment != "."
+                    && segment != ".."
+                    && segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"-._".contains(&b))
+            });
+            if !valid {
+                return Err(InvalidSpiffeId);
+            }
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            path_start: SCHEME.len() + trust_domain.len(),
+        })
+    }
+}
+
+impl fmt::Display for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+/// The provided value was not a valid SPIFFE ID or trust domain.
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InvalidSpiffeId;
+
+impl fmt::Display for InvalidSpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid SPIFFE ID")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidSpiffeId {}
+
+fn valid_trust_domain(trust_domain: &str) -> bool {
+    !trust_domain.is_empty()
+        && trust_domain.len() <= MAX_TRUST_DOMAIN_LEN
+        && trust_domain
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"-._".contains(&b))
+}
+
+/// Decides whether a workload, identified by its verified SPIFFE ID, may connect.
+pub trait SpiffeIdAuthorizer: fmt::Debug + Send + Sync {
+    /// Return `true` if the workload identified by `id` is authorized.
+    ///
+    /// Returning `false` fails verification with
+    /// [`CertificateError::ApplicationVerificationFailure`].
+    fn authorize(&self, id: &SpiffeId) -> bool;
+}
+
+/// A builder for [`SpiffeServerVerifier`] and [`SpiffeClientVerifier`].
+#[derive(Debug, Clone)]
+pub struct SpiffeVerifierBuilder {
+    bundles: Vec<(String, Arc<RootCertStore>)>,
+    authorizer: Arc<dyn SpiffeIdAuthorizer>,
+    supported_algs: WebPkiSupportedAlgorithms,
+}
+
+impl SpiffeVerifierBuilder {
+    /// Create a builder for verifiers which authorize SPIFFE IDs with `authorizer`, and
+    /// verify signatures with the algorithms supported by `provider`.
+    ///
+    /// No trust domains are trusted until given to [`Self::with_trust_bundle()`].
+    pub fn new(authorizer: Arc<dyn SpiffeIdAuthorizer>, provider: Arc<CryptoProvider>) -> Self {
+        Self {
+            bundles: Vec::new(),
+            authorizer,
+            supported_algs: provider.signature_verification_algorithms,
+        }
+    }
+
+    /// Trust the certificate authorities in `roots` to issue SVIDs for `trust_domain`.
+    ///
+    /// Each trust domain has one trust bundle: this replaces any earlier bundle for
+    /// `trust_domain`.  This fails if `trust_domain` is not a valid SPIFFE trust domain
+    /// name, such as `example.org`.
+    pub fn with_trust_bundle(
+        mut self,
+        trust_domain: &str,
+        roots: Arc<RootCertStore>,
+    ) -> Result<Self, InvalidSpiffeId> {
+        if !valid_trust_domain(trust_domain) {
+            return Err(InvalidSpiffeId);
+        }
+
+        match self
+            .bundles
+            .iter_mut()
+            .find(|(name, _)| name == trust_domain)
+        {
+            Some((_, existing)) => *existing = roots,
+            None => self
+                .bundles
+                .push((trust_domain.to_string(), roots)),
+        }
+        Ok(self)
+    }
+
+    /// Build a verifier for SVIDs presented by servers.
+    ///
+    /// This fails with [`VerifierBuilderError::NoRootAnchors`] if no trust bundles were
+    /// given, or any of them is empty.
+    pub fn build_server_verifier(self) -> Result<Arc<SpiffeServerVerifier>, VerifierBuilderError> {
+        Ok(Arc::new(SpiffeServerVerifier(self.build()?)))
+    }
+
+    /// Build a verifier for SVIDs presented by clients.
+    ///
+    /// The verifier requires clients to authenticate.  This fails with
+    /// [`VerifierBuilderError::NoRootAnchors`] if no trust bundles were given, or any of
+    /// them is empty.
+    pub fn build_client_verifier(self) -> Result<Arc<SpiffeClientVerifier>, VerifierBuilderError> {
+        let root_hint_subjects = self
+            .bundles
+            .iter()
+            .flat_map(|(_, roots)| roots.subjects())
+            .collect::<Vec<_>>();
+        Ok(Arc::new(SpiffeClientVerifier {
+            inner: self.build()?,
+            root_hint_subjects: Arc::from(root_hint_subjects),
+        }))
+    }
+
+    fn build(self) -> Result<SpiffeVerifier, VerifierBuilderError> {
+        if self.bundles.is_empty()
+            || self
+                .bundles
+                .iter()
+                .any(|(_, roots)| roots.is_empty())
+        {
+            return Err(VerifierBuilderError::NoRootAnchors);
+        }
+
+        Ok(SpiffeVerifier {
+            bundles: self.bundles,
+            authorizer: self.authorizer,
+            supported_algs: self.supported_algs,
+        })
+    }
+}
+
+/// State shared by [`SpiffeServerVerifier`] and [`SpiffeClientVerifier`].
+#[derive(Debug)]
+struct SpiffeVerifier {
+    bundles: Vec<(String, Arc<RootCertStore>)>,
+    authorizer: Arc<dyn SpiffeIdAuthorizer>,
+    supported_algs: WebPkiSupportedAlgorithms,
+}
+
+impl SpiffeVerifier {
+    fn verify(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+        usage: webpki::KeyUsage,
+    ) -> Result<(SpiffeId, VerifiedPath), Error> {
+        let cert = ParsedCertificate::try_from(end_entity)?;
+        let id = SpiffeId::from_certificate(end_entity)?;
+
+        let Some((_, roots)) = self
+            .bundles
+            .iter()
+            .find(|(trust_domain, _)| trust_domain == id.trust_domain())
+        else {
+            return Err(CertificateError::UnknownIssuer.into());
+        };
+
+        let path = cert
+            .0
+            .verify_for_usage(
+                self.supported_algs.all,
+                &roots.roots,
+                intermediates,
+                now,
+                usage,
+                None,
+                None,
+            )
+            .map_err(pki_error)?;
+
+        if !self.authorizer.authorize(&id) {
+            return Err(CertificateError::ApplicationVerificationFailure.into());
+        }
+        Ok((id, owned_path(&path)))
+    }
+}
+
+/// A server certificate verifier for [SPIFFE] workload identities.
+///
+/// The server's certificate must be an X509-SVID: it must have exactly one URI subject
+/// alternative name, which is a SPIFFE ID, and must not be a CA certificate.  It must
+/// chain to the trust bundle for the SPIFFE ID's trust domain, and the ID must be accepted
+/// by the [`SpiffeIdAuthorizer`].
+///
+/// The server name the client connects to is not checked: the SPIFFE ID identifies
+/// the server instead.  After the handshake, it is available from
+/// [`CommonState::peer_spiffe_id()`].
+///
+/// Build this with [`SpiffeVerifierBuilder::build_server_verifier()`].
+///
+/// [SPIFFE]: https://spiffe.io/
+/// [`CommonState::peer_spiffe_id()`]: crate::CommonState::peer_spiffe_id
+#[derive(Debug)]
+pub struct SpiffeServerVerifier(SpiffeVerifier);
+
+impl ServerCertVerifier for SpiffeServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        self.0
+            .verify(
+                end_entity,
+                intermediates,
+                now,
+                webpki::KeyUsage::server_auth(),
+            )
+            .map(|(id, path)| ServerCertVerified::with_verified_path(path).with_spiffe_id(id))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.supported_algs)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.supported_algs)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0
+            .supported_algs
+            .supported_schemes()
+    }
+
+    fn request_ocsp_response(&self) -> bool {
+        false
+    }
+}
+
+/// A client certificate verifier for [SPIFFE] workload identities.
+///
+/// Clients must authenticate with an X509-SVID, which is checked in the same way as by
+/// [`SpiffeServerVerifier`].  After the handshake, the client's SPIFFE ID is available
+/// from [`CommonState::peer_spiffe_id()`].
+///
+/// Build this with [`SpiffeVerifierBuilder::build_client_verifier()`].
+///
+/// [SPIFFE]: https://spiffe.io/
+/// [`CommonState::peer_spiffe_id()`]: crate::CommonState::peer_spiffe_id
+#[derive(Debug)]
+pub struct SpiffeClientVerifier {
+    inner: SpiffeVerifier,
+    root_hint_subjects: Arc<[DistinguishedName]>,
+}
+
+impl ClientCertVerifier for SpiffeClientVerifier {
+    fn root_hint_subjects(&self) -> Arc<[DistinguishedName]> {
+        self.root_hint_subjects.clone()
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, Error> {
+        self.inner
+            .verify(
+                end_entity,
+                intermediates,
+                now,
+                webpki::KeyUsage::client_auth(),
+            )
+            .map(|(id, path)| ClientCertVerified::with_verified_path(path).with_spiffe_id(id))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(message, cert, dss, &self.inner.supported_algs)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(message, cert, dss, &self.inner.supported_algs)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner
+            .supported_algs
+            .supported_schemes()
+    }
+}
+
+const SCHEME: &str = "spiffe://";
+const MAX_ID_LEN: usize = 2048;
+const MAX_TRUST_DOMAIN_LEN: usize = 255;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spiffe_ids() {
+        let id = SpiffeId::try_from("spiffe://example.org/ns/prod/sa/web").unwrap();
+        assert_eq!(id.trust_domain(), "example.org");
+        assert_eq!(id.path(), "/ns/prod/sa/web");
+        assert_eq!(id.as_str(), "spiffe://example.org/ns/prod/sa/web");
+
+        let id = SpiffeId::try_from("spiffe://example.org").unwrap();
+        assert_eq!(id.trust_domain(), "example.org");
+        assert_eq!(id.path(), "");
+
+        for invalid in [
+            "",
+            "https://example.org/web",
+            "SPIFFE://example.org/web",
+            "spiffe://",
+            "spiffe:///web",
+            "spiffe://Example.org/web",
+            "spiffe://example.org:8080/web",
+            "spiffe://user@example.org/web",
+            "spiffe://example.org/",
+            "spiffe://example.org//web",
+            "spiffe://example.org/web/",
+            "spiffe://example.org/./web",
+            "spiffe://example.org/../web",
+            "spiffe://example.org/web?query",
+            "spiffe://example.org/web#fragment",
+            "spiffe://example.org/w%20b",
+        ] {
+            assert_eq!(
+                SpiffeId::try_from(invalid),
+                Err(InvalidSpiffeId),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,487 @@
+#[cfg(feature = "std")]
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, BufReader, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use pki_types::{CertificateDer, ServerName, SubjectPublicKeyInfoDer, UnixTime};
+
+use super::verify::{
+    ParsedCertificate, WebPkiSupportedAlgorithms, verify_tls12_signature, verify_tls13_signature,
+    verify_tls13_signature_with_raw_key,
+};
+use crate::crypto::CryptoProvider;
+use crate::crypto::hash::{Hash, HashAlgorithm};
+use crate::enums::SignatureScheme;
+use crate::error::{CertificateError, Error};
+#[cfg(feature = "std")]
+use crate::hash_map::HashMap;
+#[cfg(feature = "std")]
+use crate::lock::Mutex;
+use crate::log::debug;
+use crate::sync::Arc;
+use crate::verify::{
+    DigitallySignedStruct, HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+
+/// A server certificate verifier which trusts the first public key seen for each server name.
+///
+/// On first contact with a server name, the SHA-256 hash of the server's
+/// `SubjectPublicKeyInfo` is recorded in a [`TofuStore`] and the server is accepted.
+/// Later connections to that name are accepted only if the server presents the same
+/// public key; otherwise verification fails with [`CertificateError::KeyChanged`].
+///
+/// Nothing else about the server's certificate is checked: it need not chain to a trust
+/// anchor, be valid for the server name, or be currently valid.  This suits peers with
+/// self-signed certificates or raw public keys, but offers no protection if the first
+/// connection to a server is intercepted.
+///
+/// Handshake signatures are verified using the provider's
+/// [`CryptoProvider::signature_verification_algorithms`].
+///
+/// To accept a server's new key after a [`CertificateError::KeyChanged`] error, record it
+/// with [`TofuStore::insert()`].
+pub struct TofuVerifier {
+    store: Arc<dyn TofuStore>,
+    sha256: &'static dyn Hash,
+    supported_algs: WebPkiSupportedAlgorithms,
+    raw_public_keys: bool,
+}
+
+impl TofuVerifier {
+    /// Create a verifier which records the public keys of servers in `store`.
+    ///
+    /// SHA-256 is taken from the cipher suites of `provider`.  This fails if none of them
+    /// uses SHA-256.
+    pub fn new(store: Arc<dyn TofuStore>, provider: &CryptoProvider) -> Result<Self, Error> {
+        let sha256 = provider
+            .cipher_suites
+            .iter()
+            .map(|suite| suite.hash_provider())
+            .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+            .ok_or_else(|| Error::General("trust on first use requires SHA-256".into()))?;
+
+        Ok(Self {
+            store,
+            sha256,
+            supported_algs: provider.signature_verification_algorithms,
+            raw_public_keys: false,
+        })
+    }
+
+    /// Require servers to authenticate with raw public keys ([RFC 7250]), rather than
+    /// certificates.
+    ///
+    /// Raw public keys are only supported in TLS 1.3.
+    ///
+    /// [RFC 7250]: https://tools.ietf.org/html/rfc7250
+    pub fn with_raw_public_keys(mut self) -> Self {
+        self.raw_public_keys = true;
+        self
+    }
+
+    fn fingerprint(&self, end_entity: &CertificateDer<'_>) -> Result<[u8; 32], Error> {
+        let hash = if self.raw_public_keys {
+            self.sha256.hash(end_entity.as_ref())
+        } else {
+            let cert = ParsedCertificate::try_from(end_entity)?;
+            self.sha256.hash(
+                cert.0
+                    .subject_public_key_info()
+                    .as_ref(),
+            )
+        };
+
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(hash.as_ref());
+        Ok(fingerprint)
+    }
+}
+
+impl fmt::Debug for TofuVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TofuVerifier")
+            .field("store", &self.store)
+            .field("supported_algs", &self.supported_algs)
+            .field("raw_public_keys", &self.raw_public_keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ServerCertVerifier for TofuVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let fingerprint = self.fingerprint(end_entity)?;
+
+        match self
+            .store
+            .verify_or_insert(server_name, fingerprint)?
+        {
+            TofuOutcome::Known => Ok(ServerCertVerified::assertion()),
+            TofuOutcome::Inserted => {
+                debug!("trusting public key of {server_name:?} on first use");
+                Ok(ServerCertVerified::assertion())
+            }
+            TofuOutcome::Changed => Err(CertificateError::KeyChanged.into()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(message, cert, dss, &self.supported_algs)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        if self.raw_public_keys {
+            verify_tls13_signature_with_raw_key(
+                message,
+                &SubjectPublicKeyInfoDer::from(cert.as_ref()),
+                dss,
+                &self.supported_algs,
+            )
+        } else {
+            verify_tls13_signature(message, cert, dss, &self.supported_algs)
+        }
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.supported_algs.supported_schemes()
+    }
+
+    fn request_ocsp_response(&self) -> bool {
+        false
+    }
+
+    fn requires_raw_public_keys(&self) -> bool {
+        self.raw_public_keys
+    }
+}
+
+/// Storage for the public key fingerprints recorded by a [`TofuVerifier`].
+///
+/// A fingerprint is the SHA-256 hash of a DER-encoded `SubjectPublicKeyInfo`.
+/// DNS names are case-insensitive, so implementations should treat names which differ
+/// only in case as the same server.
+pub trait TofuStore: fmt::Debug + Send + Sync {
+    /// Return the fingerprint recorded for `server_name`, if any.
+    fn get(&self, server_name: &ServerName<'_>) -> Result<Option<[u8; 32]>, Error>;
+
+    /// Record `fingerprint` for `server_name`, replacing any existing fingerprint.
+    fn insert(&self, server_name: ServerName<'static>, fingerprint: [u8; 32]) -> Result<(), Error>;
+
+    /// Compare `fingerprint` with the one recorded for `server_name`, recording it if there
+    /// is none.
+    ///
+    /// This must be atomic: if several connections to a new server name race, exactly one
+    /// of their fingerprints is recorded, and the others are compared with it.
+    fn verify_or_insert(
+        &self,
+        server_name: &ServerName<'_>,
+        fingerprint: [u8; 32],
+    ) -> Result<TofuOutcome, Error>;
+}
+
+/// The result of [`TofuStore::verify_or_insert()`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TofuOutcome {
+    /// The fingerprint matches the one recorded for the server name.
+    Known,
+    /// No fingerprint was recorded for the server name, and this one now is.
+    Inserted,
+    /// A different fingerprint is recorded for the server name, and was kept.
+    Changed,
+}
+
+#[cfg(feature = "std")]
+impl TofuOutcome {
+    fn compare(known: &[u8; 32], fingerprint: &[u8; 32]) -> Self {
+        match known == fingerprint {
+            true => Self::Known,
+            false => Self::Changed,
+        }
+    }
+}
+
+/// A [`TofuStore`] which keeps fingerprints in memory.
+///
+/// Fingerprints are lost when the store is dropped.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MemoryTofuStore {
+    fingerprints: Mutex<HashMap<String, [u8; 32]>>,
+}
+
+#[cfg(feature = "std")]
+impl MemoryTofuStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self {
+            fingerprints: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for MemoryTofuStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TofuStore for MemoryTofuStore {
+    fn get(&self, server_name: &ServerName<'_>) -> Result<Option<[u8; 32]>, Error> {
+        let fingerprints = self
+            .fingerprints
+            .lock()
+            .ok_or_else(|| Error::General("TOFU store lock poisoned".into()))?;
+        Ok(fingerprints
+            .get(&store_key(server_name))
+            .copied())
+    }
+
+    fn insert(&self, server_name: ServerName<'static>, fingerprint: [u8; 32]) -> Result<(), Error> {
+        self.fingerprints
+            .lock()
+            .ok_or_else(|| Error::General("TOFU store lock poisoned".into()))?
+            .insert(store_key(&server_name), fingerprint);
+        Ok(())
+    }
+
+    fn verify_or_insert(
+        &self,
+        server_name: &ServerName<'_>,
+        fingerprint: [u8; 32],
+    ) -> Result<TofuOutcome, Error> {
+        let mut fingerprints = self
+            .fingerprints
+            .lock()
+            .ok_or_else(|| Error::General("TOFU store lock poisoned".into()))?;
+        let name = store_key(server_name);
+        if let Some(known) = fingerprints.get(&name) {
+            return Ok(TofuOutcome::compare(known, &fingerprint));
+        }
+
+        fingerprints.insert(name, fingerprint);
+        Ok(TofuOutcome::Inserted)
+    }
+}
+
+/// A [`TofuStore`] which keeps fingerprints in a file.
+///
+/// The file has one line per server name, containing the name and the hex-encoded
+/// fingerprint separated by a space.  New fingerprints are appended, and the last line
+/// for a name takes precedence.  Empty lines and lines starting with `#` are ignored.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct FileTofuStore {
+    inner: Mutex<FileTofuStoreInner>,
+}
+
+#[cfg(feature = "std")]
+impl FileTofuStore {
+    /// Open the store at `path`, creating the file if it does not exist.
+    ///
+    /// This fails if the file cannot be opened, or contains a malformed line.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut fingerprints = HashMap::new();
+        for line in BufReader::new(&file).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, fingerprint) = parse_line(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    alloc::format!("malformed TOFU store line: {line:?}"),
+                )
+            })?;
+            fingerprints.insert(name.to_ascii_lowercase(), fingerprint);
+        }
+
+        Ok(Self {
+            inner: Mutex::new(FileTofuStoreInner { file, fingerprints }),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl TofuStore for FileTofuStore {
+    fn get(&self, server_name: &ServerName<'_>) -> Result<Option<[u8; 32]>, Error> {
+        let inner = self
+            .inner
+            .lock()
+            .ok_or_else(|| Error::General("TOFU store lock poisoned".into()))?;
+        Ok(inner
+            .fingerprints
+            .get(&store_key(server_name))
+            .copied())
+    }
+
+    fn insert(&self, server_name: ServerName<'static>, fingerprint: [u8; 32]) -> Result<(), Error> {
+        self.inner
+            .lock()
+            .ok_or_else(|| Error::General("TOFU store lock poisoned".into()))?
+            .record(store_key(&server_name), fingerprint)
+    }
+
+    fn verify_or_insert(
+        &self,
+        server_name: &ServerName<'_>,
+        fingerprint: [u8; 32],
+    ) -> Result<TofuOutcome, Error> {
+        let mut inner = self
+            .inner
+            .lock()
+            .ok_or_else(|| Error::General("TOFU store lock poisoned".into()))?;
+        let name = store_key(server_name);
+        if let Some(known) = inner.fingerprints.get(&name) {
+            return Ok(TofuOutcome::compare(known, &fingerprint));
+        }
+
+        inner.record(name, fingerprint)?;
+        Ok(TofuOutcome::Inserted)
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct FileTofuStoreInner {
+    file: File,
+    fingerprints: HashMap<String, [u8; 32]>,
+}
+
+#[cfg(feature = "std")]
+impl FileTofuStoreInner {
+    fn record(&mut self, name: String, fingerprint: [u8; 32]) -> Result<(), Error> {
+        let mut line = alloc::format!("{name} ");
+        for b in fingerprint {
+            line.push_str(&alloc::format!("{b:02x}"));
+        }
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|()| self.file.flush())
+            .map_err(|e| Error::General(alloc::format!("cannot write to TOFU store: {e}")))?;
+
+        self.fingerprints
+            .insert(name, fingerprint);
+        Ok(())
+    }
+}
+
+/// The key under which the fingerprint for `server_name` is stored.
+///
+/// DNS names are compared case-insensitively, so are stored in lowercase.
+#[cfg(feature = "std")]
+fn store_key(server_name: &ServerName<'_>) -> String {
+    match server_name {
+        ServerName::DnsName(name) => String::from(name.to_lowercase_owned().as_ref()),
+        _ => server_name.to_str().into_owned(),
+    }
+}
+
+#[cfg(feature = "std")]
+fn parse_line(line: &str) -> Option<(&str, [u8; 32])> {
+    let (name, hex) = line.split_once(' ')?;
+    let hex = hex.trim_start().as_bytes();
+    if name.is_empty() || hex.len() != 64 {
+        return None;
+    }
+
+    let mut fingerprint = [0u8; 32];
+    for (byte, pair) in fingerprint
+        .iter_mut()
+        .zip(hex.chunks_exact(2))
+    {
+        let pair = core::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some((name, fingerprint))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn verify_or_insert_records_one_racing_fingerprint() {
+        let store = Arc::new(MemoryTofuStore::new());
+        let name = ServerName::try_from("example.com").unwrap();
+
+        let outcomes = (0..8u8)
+            .map(|i| {
+                let store = store.clone();
+                let name = name.clone();
+                thread::spawn(move || {
+                    store
+                        .verify_or_insert(&name, [i; 32])
+                        .map(|outcome| (i, outcome))
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap().unwrap())
+            .collect::<Vec<_>>();
+
+        let inserted = outcomes
+            .iter()
+            .filter(|(_, outcome)| *outcome == TofuOutcome::Inserted)
+            .map(|(i, _)| *i)
+            .collect::<Vec<_>>();
+        let [winner] = inserted[..] else {
+            panic!("expected one insertion: {outcomes:?}");
+        };
+        assert!(
+            outcomes
+                .iter()
+                .all(|(i, outcome)| *i == winner || *outcome == TofuOutcome::Changed)
+        );
+        assert_eq!(store.get(&name), Ok(Some([winner; 32])));
+        assert_eq!(
+            store.verify_or_insert(&name, [winner; 32]),
+            Ok(TofuOutcome::Known)
+        );
+    }
+
+    #[test]
+    fn parses_lines() {
+        let line = alloc::format!("example.com {}", "ab".repeat(32));
+        assert_eq!(parse_line(&line), Some(("example.com", [0xab; 32])));
+        assert_eq!(parse_line("example.com"), None);
+        assert_eq!(parse_line("example.com abcd"), None);
+        let line = alloc::format!("example.com {}", "zz".repeat(32));
+        assert_eq!(parse_line(&line), None);
+    }
+}
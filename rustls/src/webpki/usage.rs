@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+use super::extensions::{Extensions, oid_values};
+use super::verify::ParsedCertificate;
+use crate::error::{CertificateError, Error, ExtendedKeyPurpose};
+use crate::verify::VerifiedPath;
+
+/// Extended key usage and certificate policy requirements for end-entity certificates.
+///
+/// `webpki` checks a single extended key usage, for every certificate in the path.
+/// Other extended key usages are checked here, on the end-entity certificate only.
+/// Certificate policies are checked on every certificate in the path other than the
+/// trust anchor.
+#[derive(Clone, Debug)]
+pub(super) struct UsagePolicy {
+    /// Required extended key usages, as DER-encoded OID values.
+    ///
+    /// The first of these is also checked by `webpki`.
+    ekus: Vec<&'static [u8]>,
+    /// Whether `anyExtendedKeyUsage` satisfies `ekus`.
+    allow_any_eku: bool,
+    /// Required certificate policies, as DER-encoded OID values.
+    policies: Vec<&'static [u8]>,
+}
+
+impl UsagePolicy {
+    pub(super) fn client_auth() -> Self {
+        Self::new(EKU_CLIENT_AUTH)
+    }
+
+    pub(super) fn server_auth() -> Self {
+        Self::new(EKU_SERVER_AUTH)
+    }
+
+    fn new(eku: &'static [u8]) -> Self {
+        Self {
+            ekus: alloc::vec![eku],
+            allow_any_eku: false,
+            policies: Vec::new(),
+        }
+    }
+
+    pub(super) fn set_ekus(&mut self, ekus: impl IntoIterator<Item = &'static [u8]>) {
+        self.ekus = ekus.into_iter().collect();
+    }
+
+    pub(super) fn allow_any_eku(&mut self) {
+        self.allow_any_eku = true;
+    }
+
+    pub(super) fn add_policies(&mut self, policies: impl IntoIterator<Item = &'static [u8]>) {
+        self.policies.extend(policies);
+    }
+
+    pub(super) fn has_ekus(&self) -> bool {
+        !self.ekus.is_empty()
+    }
+
+    /// Call `f` with the key usage `webpki` should check for `end_entity`, then
+    /// check the remaining requirements against the path it verified.
+    ///
+    /// Errors from `f` take precedence, so that an untrusted certificate is reported as
+    /// such rather than as being badly encoded or used for the wrong purpose.
+    pub(super) fn check(
+        &self,
+        end_entity: &ParsedCertificate<'_>,
+        f: impl FnOnce(webpki::KeyUsage) -> Result<VerifiedPath, Error>,
+    ) -> Result<VerifiedPath, Error> {
+        let first = webpki::KeyUsage::required_if_present(self.ekus[0]);
+        if self.ekus.len() == 1 && !self.allow_any_eku && self.policies.is_empty() {
+            return f(first);
+        }
+
+        let der = end_entity.0.der();
+        let extensions = Extensions::parse(der.as_ref());
+
+        let (usage, eku_result) = match extensions
+            .as_ref()
+            .and_then(|extensions| extensions.ekus.as_ref())
+        {
+            // Without the extension, the certificate may be used for any purpose.
+            // If the extensions cannot be parsed, that is reported once `f` succeeds.
+            None => (first, Ok(())),
+            Some(present) => match self
+                .ekus
+                .iter()
+                .find(|eku| !present.contains(eku))
+            {
+                None => (first, Ok(())),
+                Some(_) if self.allow_any_eku && present.contains(&EKU_ANY) => {
+                    (webpki::KeyUsage::required_if_present(EKU_ANY), Ok(()))
+                }
+                Some(missing) => (
+                    first,
+                    Err(CertificateError::InvalidPurposeContext {
+                        required: ExtendedKeyPurpose::for_values(oid_values(missing)),
+                        presented: present
+                            .iter()
+                            .map(|eku| ExtendedKeyPurpose::for_values(oid_values(eku)))
+                            .collect(),
+                    }),
+                ),
+            },
+        };
+
+        let path = f(usage)?;
+        let extensions = extensions.ok_or(CertificateError::BadEncoding)?;
+        eku_result?;
+
+        self.check_policies(&extensions.policies)?;
+        for intermediate in path.intermediates() {
+            let extensions =
+                Extensions::parse(intermediate.as_ref()).ok_or(CertificateError::BadEncoding)?;
+            self.check_policies(&extensions.policies)?;
+        }
+
+        Ok(path)
+    }
+
+    /// Check that a certificate in the path asserts each required policy.
+    fn check_policies(&self, present: &[&[u8]]) -> Result<(), CertificateError> {
+        match self
+            .policies
+            .iter()
+            .find(|policy| !present.contains(policy))
+        {
+            Some(missing) => Err(CertificateError::MissingCertificatePolicy {
+                required: oid_values(missing).collect(),
+                presented: present
+                    .iter()
+                    .map(|policy| oid_values(policy).collect())
+                    .collect(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+// anyExtendedKeyUsage, 2.5.29.37.0
+const EKU_ANY: &[u8] = &[0x55, 0x1d, 0x25, 0x00];
+// id-kp-serverAuth, 1.3.6.1.5.5.7.3.1
+const EKU_SERVER_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01];
+// id-kp-clientAuth, 1.3.6.1.5.5.7.3.2
+const EKU_CLIENT_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02];
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn decodes_oid_values() {
+        assert_eq!(
+            oid_values(EKU_CLIENT_AUTH).collect::<Vec<_>>(),
+            alloc::vec![1, 3, 6, 1, 5, 5, 7, 3, 2]
+        );
+        assert_eq!(
+            oid_values(EKU_ANY).collect::<Vec<_>>(),
+            alloc::vec![2, 5, 29, 37, 0]
+        );
+    }
+}
@@ -9,7 +9,7 @@ use super::anchors::RootCertStore;
 use super::pki_error;
 use crate::enums::SignatureScheme;
 use crate::error::{Error, PeerMisbehaved};
-use crate::verify::{DigitallySignedStruct, HandshakeSignatureValid};
+use crate::verify::{DigitallySignedStruct, HandshakeSignatureValid, VerifiedPath};
 
 /// Verify that the end-entity certificate `end_entity` is a valid server cert
 /// and chains to at least one of the trust anchors in the `roots` [RootCertStore].
@@ -37,7 +37,10 @@ pub fn verify_server_cert_signed_by_trust_anchor(
         None, // No revocation checking supported with this API.
         now,
         supported_algs,
+        webpki::KeyUsage::server_auth(),
+        |_| {},
     )
+    .map(|_| ())
 }
 
 /// Verify that the `end_entity` has an alternative name matching the `server_name`.
@@ -158,6 +161,10 @@ pub fn verify_tls12_signature(
     dss: &DigitallySignedStruct,
     supported_schemes: &WebPkiSupportedAlgorithms,
 ) -> Result<HandshakeSignatureValid, Error> {
+    if !dss.scheme.supported_in_tls12() {
+        return Err(PeerMisbehaved::SignedHandshakeWithUnadvertisedSigScheme.into());
+    }
+
     let possible_algs = supported_schemes.convert_scheme(dss.scheme)?;
     let cert = webpki::EndEntityCert::try_from(cert).map_err(pki_error)?;
 
@@ -234,6 +241,11 @@ pub fn verify_tls13_signature_with_raw_key(
 ///
 /// `revocation` controls how revocation checking is performed, if at all.
 ///
+/// `usage` is the extended key usage required of each certificate in the path.
+///
+/// `on_verified` is called with the verified path, if verification succeeds.  An owned
+/// copy of that path is returned.
+///
 /// This function exists to be used by [`verify_server_cert_signed_by_trust_anchor`],
 /// and differs only in providing a `Option<webpki::RevocationOptions>` argument. We
 /// can't include this argument in `verify_server_cert_signed_by_trust_anchor` because
@@ -245,22 +257,38 @@ pub(crate) fn verify_server_cert_signed_by_trust_anchor_impl(
     revocation: Option<webpki::RevocationOptions<'_>>,
     now: UnixTime,
     supported_algs: &[&dyn SignatureVerificationAlgorithm],
-) -> Result<(), Error> {
+    usage: webpki::KeyUsage,
+    on_verified: impl FnOnce(&webpki::VerifiedPath<'_>),
+) -> Result<VerifiedPath, Error> {
     let result = cert.0.verify_for_usage(
         supported_algs,
         &roots.roots,
         intermediates,
         now,
-        webpki::KeyUsage::server_auth(),
+        usage,
         revocation,
         None,
     );
     match result {
-        Ok(_) => Ok(()),
+        Ok(path) => {
+            on_verified(&path);
+            Ok(owned_path(&path))
+        }
         Err(e) => Err(pki_error(e)),
     }
 }
 
+/// Copy a path verified by `webpki` into a [`VerifiedPath`].
+pub(crate) fn owned_path(path: &webpki::VerifiedPath<'_>) -> VerifiedPath {
+    VerifiedPath::new(
+        path.end_entity().der().into_owned(),
+        path.intermediate_certificates()
+            .map(|cert| cert.der().into_owned())
+            .collect(),
+        path.anchor().to_owned(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::format;
@@ -278,8 +306,12 @@ mod tests {
     #[cfg(feature = "ring")]
     #[test]
     fn webpki_supported_algorithms_is_debug() {
+        #[cfg(not(feature = "curve448"))]
+        let expected = "WebPkiSupportedAlgorithms { all: [ .. ], mapping: [ECDSA_NISTP384_SHA384, ECDSA_NISTP256_SHA256, ED25519, RSA_PSS_SHA512, RSA_PSS_SHA384, RSA_PSS_SHA256, RSA_PKCS1_SHA512, RSA_PKCS1_SHA384, RSA_PKCS1_SHA256] }";
+        #[cfg(feature = "curve448")]
+        let expected = "WebPkiSupportedAlgorithms { all: [ .. ], mapping: [ECDSA_NISTP384_SHA384, ECDSA_NISTP256_SHA256, ED25519, ED448, RSA_PSS_SHA512, RSA_PSS_SHA384, RSA_PSS_SHA256, RSA_PKCS1_SHA512, RSA_PKCS1_SHA384, RSA_PKCS1_SHA256] }";
         assert_eq!(
-            "WebPkiSupportedAlgorithms { all: [ .. ], mapping: [ECDSA_NISTP384_SHA384, ECDSA_NISTP256_SHA256, ED25519, RSA_PSS_SHA512, RSA_PSS_SHA384, RSA_PSS_SHA256, RSA_PKCS1_SHA512, RSA_PKCS1_SHA384, RSA_PKCS1_SHA256] }",
+            expected,
             format!(
                 "{:?}",
                 crate::crypto::ring::default_provider().signature_verification_algorithms
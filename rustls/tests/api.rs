@@ -7,23 +7,37 @@ use std::io::{self, BufRead, IoSlice, Read, Write};
 use std::ops::{Deref, DerefMut};
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use std::{fmt, mem};
 
-use pki_types::{CertificateDer, DnsName, IpAddr, ServerName, SubjectPublicKeyInfoDer, UnixTime};
-use rustls::client::{ResolvesClientCert, Resumption, verify_server_cert_signed_by_trust_anchor};
+use pki_types::{
+    CertificateDer, CertificateRevocationListDer, DnsName, IpAddr, PrivateKeyDer, ServerName,
+    SubjectPublicKeyInfoDer, UnixTime,
+};
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::{
+    ClientConnectionOptions, ClientHelloProfile, CrlSource, FileTofuStore, IntermediatePool,
+    InvalidSpiffeId, MemoryTofuStore, ResolvesClientCert, Resumption, SpiffeId, SpiffeIdAuthorizer,
+    SpiffeVerifierBuilder, SpkiPinningVerifier, SpkiPins, TofuOutcome, TofuStore, TofuVerifier,
+    VerifierBuilderError, WebPkiServerVerifier, verify_server_cert_signed_by_trust_anchor,
+};
 use rustls::crypto::{ActiveKeyExchange, CryptoProvider, SharedSecret, SupportedKxGroup};
 use rustls::internal::msgs::base::Payload;
 use rustls::internal::msgs::codec::Codec;
 use rustls::internal::msgs::enums::{AlertLevel, ExtensionType};
 use rustls::internal::msgs::message::{Message, MessagePayload, PlainMessage};
-use rustls::server::{CertificateType, ClientHello, ParsedCertificate, ResolvesServerCert};
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::{
+    CertificateType, ClientHello, ClientHelloFingerprint, ParsedCertificate, ResolvesServerCert,
+    WebPkiClientVerifier,
+};
 use rustls::version::TLS12;
 use rustls::{
     AlertDescription, CertificateError, CipherSuite, ClientConfig, ClientConnection,
     ConnectionCommon, ConnectionTrafficSecrets, ContentType, DistinguishedName, Error,
     ExtendedKeyPurpose, HandshakeKind, HandshakeType, InconsistentKeys, InvalidMessage, KeyLog,
     NamedGroup, PeerIncompatible, PeerMisbehaved, ProtocolVersion, RootCertStore, ServerConfig,
-    ServerConnection, SideData, SignatureScheme, Stream, StreamOwned, SupportedCipherSuite,
+    ServerConnection, Side, SideData, SignatureScheme, Stream, StreamOwned, SupportedCipherSuite,
     SupportedProtocolVersion, sign,
 };
 #[cfg(feature = "aws-lc-rs")]
@@ -152,6 +166,23 @@ mod test_raw_keys {
             }
         }
     }
+
+    #[test]
+    fn raw_key_connection_info() {
+        let provider = provider::default_provider();
+        for kt in KeyType::all_for_provider(&provider) {
+            let client_config = make_client_config_with_raw_key_support(*kt, &provider);
+            let server_config = make_server_config_with_raw_key_support(*kt, &provider);
+
+            let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+            do_handshake(&mut client, &mut server);
+
+            for info in [client.connection_info(), server.connection_info()] {
+                assert_eq!(info.client_certificate_type, CertificateType::RawPublicKey);
+                assert_eq!(info.server_certificate_type, CertificateType::RawPublicKey);
+            }
+        }
+    }
 }
 
 fn alpn_test_error(
@@ -595,6 +626,101 @@ fn client_can_get_server_cert_after_resumption() {
     }
 }
 
+#[test]
+fn connection_info_for_full_and_resumed_handshakes() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(
+            *kt, &provider,
+        ));
+        for version in rustls::ALL_VERSIONS {
+            let mut client_config =
+                make_client_config_with_versions_with_auth(*kt, &[version], &provider);
+            client_config.alpn_protocols = vec![b"proto".to_vec()];
+            let client_config = Arc::new(client_config);
+            let mut server_config = (*server_config).clone();
+            server_config.alpn_protocols = vec![b"proto".to_vec()];
+            let server_config = Arc::new(server_config);
+
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &server_config);
+            do_handshake(&mut client, &mut server);
+
+            let client_info = client.connection_info();
+            let server_info = server.connection_info();
+            assert_eq!(client_info.side, Side::Client);
+            assert_eq!(server_info.side, Side::Server);
+
+            let expected_ems = version.version() == ProtocolVersion::TLSv1_2;
+            for info in [&client_info, &server_info] {
+                assert_eq!(info.protocol_version, Some(version.version()));
+                assert_eq!(info.handshake_kind, Some(HandshakeKind::Full));
+                assert_eq!(
+                    info.key_exchange_group,
+                    Some(expected_kx_for_version(version))
+                );
+                assert_eq!(info.alpn_protocol.as_deref(), Some(&b"proto"[..]));
+                assert_eq!(
+                    info.server_name
+                        .as_ref()
+                        .unwrap()
+                        .as_ref(),
+                    "localhost"
+                );
+                assert_eq!(info.extended_master_secret, expected_ems);
+                assert_eq!(info.client_certificate_type, CertificateType::X509);
+                assert_eq!(info.server_certificate_type, CertificateType::X509);
+                assert!(!info.early_data_accepted);
+                assert!(info.peer_signature_scheme.is_some());
+            }
+            assert_eq!(
+                client_info
+                    .cipher_suite
+                    .unwrap()
+                    .suite(),
+                server_info
+                    .cipher_suite
+                    .unwrap()
+                    .suite()
+            );
+            assert_eq!(
+                client_info.peer_certificates.as_deref(),
+                Some(kt.get_chain().as_slice())
+            );
+            assert_eq!(
+                server_info.peer_certificates.as_deref(),
+                Some(kt.get_client_chain().as_slice())
+            );
+            assert_eq!(
+                client_info
+                    .local_certified_key
+                    .unwrap()
+                    .cert_chain,
+                kt.get_client_chain()
+            );
+            assert_eq!(
+                server_info
+                    .local_certified_key
+                    .unwrap()
+                    .cert_chain,
+                kt.get_chain()
+            );
+
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &server_config);
+            do_handshake(&mut client, &mut server);
+
+            for info in [client.connection_info(), server.connection_info()] {
+                assert_eq!(info.handshake_kind, Some(HandshakeKind::Resumed));
+                assert_eq!(info.extended_master_secret, expected_ems);
+                assert!(info.local_certified_key.is_none());
+                assert!(info.peer_signature_scheme.is_none());
+                assert!(info.peer_certificates.is_some());
+            }
+        }
+    }
+}
+
 #[test]
 fn client_only_attempts_resumption_with_compatible_security() {
     let provider = provider::default_provider();
@@ -1350,19 +1476,25 @@ fn server_cert_resolve_reduces_sigalgs_for_ecdsa_ciphersuite() {
     check_sigalgs_reduced_by_ciphersuite(
         KeyType::EcdsaP256,
         CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
-        if provider_is_aws_lc_rs() {
-            vec![
-                SignatureScheme::ECDSA_NISTP384_SHA384,
-                SignatureScheme::ECDSA_NISTP256_SHA256,
-                SignatureScheme::ECDSA_NISTP521_SHA512,
-                SignatureScheme::ED25519,
-            ]
-        } else {
-            vec![
-                SignatureScheme::ECDSA_NISTP384_SHA384,
-                SignatureScheme::ECDSA_NISTP256_SHA256,
-                SignatureScheme::ED25519,
-            ]
+        {
+            let mut v = if provider_is_aws_lc_rs() {
+                vec![
+                    SignatureScheme::ECDSA_NISTP384_SHA384,
+                    SignatureScheme::ECDSA_NISTP256_SHA256,
+                    SignatureScheme::ECDSA_NISTP521_SHA512,
+                    SignatureScheme::ED25519,
+                ]
+            } else {
+                vec![
+                    SignatureScheme::ECDSA_NISTP384_SHA384,
+                    SignatureScheme::ECDSA_NISTP256_SHA256,
+                    SignatureScheme::ED25519,
+                ]
+            };
+            if provider_supports_ed448() {
+                v.push(SignatureScheme::ED448);
+            }
+            v
         },
     );
 }
@@ -1700,7 +1832,9 @@ fn client_check_server_certificate_helper_api() {
                 &correct_roots,
                 &[chain.get(1).unwrap().clone()],
                 UnixTime::now(),
-                webpki::ALL_VERIFICATION_ALGS,
+                provider::default_provider()
+                    .signature_verification_algorithms
+                    .all,
             )
             .is_ok()
         );
@@ -1711,7 +1845,9 @@ fn client_check_server_certificate_helper_api() {
                 &incorrect_roots,
                 &[chain.get(1).unwrap().clone()],
                 UnixTime::now(),
-                webpki::ALL_VERIFICATION_ALGS,
+                provider::default_provider()
+                    .signature_verification_algorithms
+                    .all,
             )
             .unwrap_err(),
             Error::InvalidCertificate(CertificateError::UnknownIssuer)
@@ -1736,7 +1872,9 @@ fn client_check_server_valid_purpose() {
         &roots,
         &[chain.get(1).unwrap().clone()],
         UnixTime::now(),
-        webpki::ALL_VERIFICATION_ALGS,
+        provider::default_provider()
+            .signature_verification_algorithms
+            .all,
     )
     .unwrap_err();
     assert_eq!(
@@ -1754,141 +1892,656 @@ fn client_check_server_valid_purpose() {
     );
 }
 
-#[derive(Debug)]
-struct ClientCheckCertResolve {
-    query_count: AtomicUsize,
-    expect_queries: usize,
-    expect_root_hint_subjects: Vec<Vec<u8>>,
-    expect_sigschemes: Vec<SignatureScheme>,
-}
+// 1.3.6.1.4.1.99999.1, .2 and .3
+const TEST_OID_1: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x86, 0x8d, 0x1f, 0x01];
+const TEST_OID_2: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x86, 0x8d, 0x1f, 0x02];
+const TEST_OID_3: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x86, 0x8d, 0x1f, 0x03];
+
+/// Issue an end-entity certificate for "localhost" with the given extended key usages
+/// and certificate policies, returning it and the root store for its issuer.
+fn usage_test_cert(
+    ekus: Vec<rcgen::ExtendedKeyUsagePurpose>,
+    policies: &[&[u8]],
+) -> (Arc<RootCertStore>, CertificateDer<'static>) {
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let mut ca_params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+    let issuer = rcgen::Issuer::new(ca_params, ca_key);
+
+    let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+    params.extended_key_usages = ekus;
+    if !policies.is_empty() {
+        params
+            .custom_extensions
+            .push(policies_extension(policies));
+    }
+    let cert = params
+        .signed_by(&rcgen::KeyPair::generate().unwrap(), &issuer)
+        .unwrap();
 
-impl ClientCheckCertResolve {
-    fn new(
-        expect_queries: usize,
-        expect_root_hint_subjects: Vec<Vec<u8>>,
-        expect_sigschemes: Vec<SignatureScheme>,
-    ) -> Self {
-        Self {
-            query_count: AtomicUsize::new(0),
-            expect_queries,
-            expect_root_hint_subjects,
-            expect_sigschemes,
-        }
-    }
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(ca_cert.der().clone())
+        .unwrap();
+    (Arc::new(roots), cert.der().clone())
 }
 
-impl Drop for ClientCheckCertResolve {
-    fn drop(&mut self) {
-        if !std::thread::panicking() {
-            let count = self.query_count.load(Ordering::SeqCst);
-            assert_eq!(count, self.expect_queries);
-        }
+/// Encode a certificate policies extension asserting `policies`.
+fn policies_extension(policies: &[&[u8]]) -> rcgen::CustomExtension {
+    let mut information = Vec::new();
+    for policy in policies {
+        information.extend([0x30, policy.len() as u8 + 2, 0x06, policy.len() as u8]);
+        information.extend_from_slice(policy);
     }
+    let mut value = vec![0x30, information.len() as u8];
+    value.extend(information);
+    rcgen::CustomExtension::from_oid_content(&[2, 5, 29, 32], value)
 }
 
-impl ResolvesClientCert for ClientCheckCertResolve {
-    fn resolve(
-        &self,
-        root_hint_subjects: &[&[u8]],
-        sigschemes: &[SignatureScheme],
-    ) -> Option<Arc<sign::CertifiedKey>> {
-        self.query_count
-            .fetch_add(1, Ordering::SeqCst);
+fn test_oid_purpose(last: usize) -> ExtendedKeyPurpose {
+    ExtendedKeyPurpose::Other(vec![1, 3, 6, 1, 4, 1, 99999, last])
+}
 
-        if sigschemes.is_empty() {
-            panic!("no signature schemes shared by server");
-        }
+#[test]
+fn client_verifier_required_extended_key_usages() {
+    let provider = Arc::new(provider::default_provider());
+    let (roots, cert) = usage_test_cert(
+        vec![
+            rcgen::ExtendedKeyUsagePurpose::ClientAuth,
+            rcgen::ExtendedKeyUsagePurpose::Other(vec![1, 3, 6, 1, 4, 1, 99999, 1]),
+        ],
+        &[],
+    );
+    let verify = |builder: rustls::server::ClientCertVerifierBuilder| {
+        builder
+            .build()
+            .unwrap()
+            .verify_client_cert(&cert, &[], UnixTime::now())
+            .map(|_| ())
+    };
+    let builder = || WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone());
 
-        assert_eq!(sigschemes, self.expect_sigschemes);
-        assert_eq!(root_hint_subjects, self.expect_root_hint_subjects);
+    assert_eq!(verify(builder()), Ok(()));
+    assert_eq!(
+        verify(builder().with_required_extended_key_usages([TEST_OID_1])),
+        Ok(())
+    );
 
-        None
-    }
+    let error =
+        verify(builder().with_required_extended_key_usages([TEST_OID_1, TEST_OID_3])).unwrap_err();
+    assert_eq!(
+        error,
+        Error::InvalidCertificate(CertificateError::InvalidPurposeContext {
+            required: test_oid_purpose(3),
+            presented: vec![ExtendedKeyPurpose::ClientAuth, test_oid_purpose(1)],
+        })
+    );
+    assert_eq!(
+        format!("{error}"),
+        "invalid peer certificate: certificate does not allow extended key usage for \
+         1, 3, 6, 1, 4, 1, 99999, 3, allows client authentication, 1, 3, 6, 1, 4, 1, 99999, 1"
+    );
 
-    fn has_certs(&self) -> bool {
-        true
-    }
+    assert!(matches!(
+        builder()
+            .with_required_extended_key_usages([])
+            .build(),
+        Err(VerifierBuilderError::NoExtendedKeyUsages)
+    ));
 }
 
-fn test_client_cert_resolve(
-    key_type: KeyType,
-    server_config: Arc<ServerConfig>,
-    expected_root_hint_subjects: Vec<Vec<u8>>,
-) {
-    let provider = provider::default_provider();
-    for version in rustls::ALL_VERSIONS {
-        println!("{:?} {:?}:", version.version(), key_type);
-
-        let mut client_config = make_client_config_with_versions(key_type, &[version], &provider);
-        client_config.client_auth_cert_resolver = Arc::new(ClientCheckCertResolve::new(
-            1,
-            expected_root_hint_subjects.clone(),
-            default_signature_schemes(version.version()),
-        ));
-
-        let (mut client, mut server) =
-            make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+#[test]
+fn client_verifier_any_extended_key_usage() {
+    let provider = Arc::new(provider::default_provider());
+    let (roots, cert) = usage_test_cert(vec![rcgen::ExtendedKeyUsagePurpose::Any], &[]);
+    let verify = |builder: rustls::server::ClientCertVerifierBuilder| {
+        builder
+            .build()
+            .unwrap()
+            .verify_client_cert(&cert, &[], UnixTime::now())
+            .map(|_| ())
+    };
+    let builder = || WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone());
 
-        assert_eq!(
-            do_handshake_until_error(&mut client, &mut server),
-            Err(ErrorFromPeer::Server(Error::NoCertificatesPresented))
-        );
-    }
+    assert_eq!(
+        verify(builder()),
+        Err(Error::InvalidCertificate(
+            CertificateError::InvalidPurposeContext {
+                required: ExtendedKeyPurpose::ClientAuth,
+                presented: vec![ExtendedKeyPurpose::Other(vec![2, 5, 29, 37, 0])],
+            }
+        ))
+    );
+    assert_eq!(verify(builder().allow_any_extended_key_usage()), Ok(()));
+    assert_eq!(
+        verify(
+            builder()
+                .with_required_extended_key_usages([TEST_OID_1, TEST_OID_2])
+                .allow_any_extended_key_usage()
+        ),
+        Ok(())
+    );
 }
 
-fn default_signature_schemes(version: ProtocolVersion) -> Vec<SignatureScheme> {
-    let mut v = vec![];
-
-    v.extend_from_slice(&[
-        SignatureScheme::ECDSA_NISTP384_SHA384,
-        SignatureScheme::ECDSA_NISTP256_SHA256,
-        SignatureScheme::ED25519,
-        SignatureScheme::RSA_PSS_SHA512,
-        SignatureScheme::RSA_PSS_SHA384,
-        SignatureScheme::RSA_PSS_SHA256,
-    ]);
+#[test]
+fn server_verifier_required_certificate_policies() {
+    let provider = Arc::new(provider::default_provider());
+    let (roots, cert) = usage_test_cert(
+        vec![rcgen::ExtendedKeyUsagePurpose::Other(vec![
+            1, 3, 6, 1, 4, 1, 99999, 1,
+        ])],
+        &[TEST_OID_2],
+    );
+    let verify = |builder: rustls::client::ServerCertVerifierBuilder| {
+        builder
+            .build()
+            .unwrap()
+            .verify_server_cert(&cert, &[], &server_name("localhost"), &[], UnixTime::now())
+            .map(|_| ())
+    };
+    let builder = || {
+        WebPkiServerVerifier::builder_with_provider(roots.clone(), provider.clone())
+            .with_required_extended_key_usages([TEST_OID_1])
+    };
 
-    if provider_is_aws_lc_rs() {
-        v.insert(2, SignatureScheme::ECDSA_NISTP521_SHA512);
-    }
+    assert_eq!(verify(builder()), Ok(()));
+    assert_eq!(
+        verify(builder().with_required_certificate_policies([TEST_OID_2])),
+        Ok(())
+    );
 
-    if version == ProtocolVersion::TLSv1_2 {
-        v.extend_from_slice(&[
-            SignatureScheme::RSA_PKCS1_SHA512,
-            SignatureScheme::RSA_PKCS1_SHA384,
-            SignatureScheme::RSA_PKCS1_SHA256,
-        ]);
-    }
+    let error =
+        verify(builder().with_required_certificate_policies([TEST_OID_2, TEST_OID_3])).unwrap_err();
+    assert_eq!(
+        error,
+        Error::InvalidCertificate(CertificateError::MissingCertificatePolicy {
+            required: vec![1, 3, 6, 1, 4, 1, 99999, 3],
+            presented: vec![vec![1, 3, 6, 1, 4, 1, 99999, 2]],
+        })
+    );
+    assert_eq!(
+        format!("{error}"),
+        "invalid peer certificate: certificate does not assert required policy \
+         1.3.6.1.4.1.99999.3, asserts 1.3.6.1.4.1.99999.2"
+    );
 
-    v
+    // the default verifier requires serverAuth
+    assert!(matches!(
+        verify(WebPkiServerVerifier::builder_with_provider(
+            roots.clone(),
+            provider.clone()
+        )),
+        Err(Error::InvalidCertificate(
+            CertificateError::InvalidPurposeContext { .. }
+        ))
+    ));
 }
 
 #[test]
-fn client_cert_resolve_default() {
-    // Test that in the default configuration that a client cert resolver gets the expected
-    // CA subject hints, and supported signature algorithms.
-    let provider = provider::default_provider();
-    for key_type in KeyType::all_for_provider(&provider) {
-        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(
-            *key_type, &provider,
-        ));
-
-        // In a default configuration we expect that the verifier's trust anchors are used
-        // for the hint subjects.
-        let expected_root_hint_subjects = vec![
-            key_type
-                .ca_distinguished_name()
-                .to_vec(),
-        ];
+fn client_verifier_certificate_policies_checked_along_path() {
+    let provider = Arc::new(provider::default_provider());
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let mut ca_params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+    let ca = rcgen::Issuer::new(ca_params, ca_key);
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(ca_cert.der().clone())
+        .unwrap();
+    let roots = Arc::new(roots);
+
+    // Issue an end-entity certificate asserting TEST_OID_1 and TEST_OID_2, via an
+    // intermediate asserting `intermediate_policies`.
+    let issue = |intermediate_policies: &[&[u8]]| {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        params
+            .custom_extensions
+            .push(policies_extension(intermediate_policies));
+        let intermediate = params.signed_by(&key, &ca).unwrap();
+        let issuer = rcgen::Issuer::new(params, key);
+
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ClientAuth];
+        params
+            .custom_extensions
+            .push(policies_extension(&[TEST_OID_1, TEST_OID_2]));
+        let cert = params
+            .signed_by(&rcgen::KeyPair::generate().unwrap(), &issuer)
+            .unwrap();
+        (cert.der().clone(), intermediate.der().clone())
+    };
+    let verify = |(cert, intermediate): &(CertificateDer<'static>, CertificateDer<'static>)| {
+        WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone())
+            .with_required_certificate_policies([TEST_OID_1, TEST_OID_2])
+            .build()
+            .unwrap()
+            .verify_client_cert(cert, core::slice::from_ref(intermediate), UnixTime::now())
+            .map(|_| ())
+    };
 
-        test_client_cert_resolve(*key_type, server_config, expected_root_hint_subjects);
-    }
+    assert_eq!(verify(&issue(&[TEST_OID_2, TEST_OID_1])), Ok(()));
+    assert_eq!(
+        verify(&issue(&[TEST_OID_1, TEST_OID_3])),
+        Err(Error::InvalidCertificate(
+            CertificateError::MissingCertificatePolicy {
+                required: vec![1, 3, 6, 1, 4, 1, 99999, 2],
+                presented: vec![
+                    vec![1, 3, 6, 1, 4, 1, 99999, 1],
+                    vec![1, 3, 6, 1, 4, 1, 99999, 3]
+                ],
+            }
+        ))
+    );
+    // anyPolicy, 2.5.29.32.0
+    assert!(matches!(
+        verify(&issue(&[&[0x55, 0x1d, 0x20, 0x00]])),
+        Err(Error::InvalidCertificate(
+            CertificateError::MissingCertificatePolicy { .. }
+        ))
+    ));
 }
 
 #[test]
-fn client_cert_resolve_server_no_hints() {
-    // Test that a server can provide no hints and the client cert resolver gets the expected
-    // arguments.
+fn server_verifier_reports_untrusted_certificate_before_bad_extensions() {
+    let provider = Arc::new(provider::default_provider());
+    let (roots, _) = usage_test_cert(vec![], &[]);
+
+    // certificate policies with a non-minimal length, which `webpki` does not parse
+    let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+    params
+        .custom_extensions
+        .push(rcgen::CustomExtension::from_oid_content(
+            &[2, 5, 29, 32],
+            vec![0x30, 0x81, 0x00],
+        ));
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let mut ca_params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "policy test CA");
+    let trusted_ca = ca_params.self_signed(&ca_key).unwrap();
+    let cert = params
+        .signed_by(
+            &rcgen::KeyPair::generate().unwrap(),
+            &rcgen::Issuer::new(ca_params, ca_key),
+        )
+        .unwrap();
+    let mut trusted = RootCertStore::empty();
+    trusted
+        .add(trusted_ca.der().clone())
+        .unwrap();
+
+    let verify = |roots: Arc<RootCertStore>| {
+        WebPkiServerVerifier::builder_with_provider(roots, provider.clone())
+            .with_required_certificate_policies([TEST_OID_1])
+            .build()
+            .unwrap()
+            .verify_server_cert(
+                cert.der(),
+                &[],
+                &server_name("localhost"),
+                &[],
+                UnixTime::now(),
+            )
+            .map(|_| ())
+    };
+
+    assert_eq!(
+        verify(roots),
+        Err(Error::InvalidCertificate(CertificateError::UnknownIssuer))
+    );
+    assert_eq!(
+        verify(Arc::new(trusted)),
+        Err(Error::InvalidCertificate(CertificateError::BadEncoding))
+    );
+}
+
+/// A test certificate authority, for issuing SPIFFE X509-SVIDs.
+struct SvidIssuer {
+    issuer: rcgen::Issuer<'static, rcgen::KeyPair>,
+    roots: Arc<RootCertStore>,
+}
+
+impl SvidIssuer {
+    fn new(name: &str) -> Self {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, name);
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let cert = params.self_signed(&key).unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.der().clone()).unwrap();
+        Self {
+            issuer: rcgen::Issuer::new(params, key),
+            roots: Arc::new(roots),
+        }
+    }
+
+    /// Issue a certificate with a URI subject alternative name for each of `uris`.
+    fn issue(&self, uris: &[&str]) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params.subject_alt_names = uris
+            .iter()
+            .map(|uri| rcgen::SanType::URI(uri.to_string().try_into().unwrap()))
+            .collect();
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = params
+            .signed_by(&key, &self.issuer)
+            .unwrap();
+        (
+            vec![cert.der().clone()],
+            PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct AllowSpiffeIds(Vec<&'static str>);
+
+impl SpiffeIdAuthorizer for AllowSpiffeIds {
+    fn authorize(&self, id: &SpiffeId) -> bool {
+        self.0.contains(&id.as_str())
+    }
+}
+
+#[test]
+fn spiffe_verifiers() {
+    let provider = Arc::new(provider::default_provider());
+    let example = SvidIssuer::new("example.org CA");
+    let other = SvidIssuer::new("other.org CA");
+    let server_svid = example.issue(&["spiffe://example.org/server"]);
+
+    let verifiers = |allowed: Vec<&'static str>| {
+        SpiffeVerifierBuilder::new(Arc::new(AllowSpiffeIds(allowed)), provider.clone())
+            .with_trust_bundle("example.org", example.roots.clone())
+            .unwrap()
+            .with_trust_bundle("other.org", other.roots.clone())
+            .unwrap()
+    };
+    let handshake =
+        |allowed: Vec<&'static str>,
+         (chain, key): (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)| {
+            let server_config = server_config_builder(&provider)
+                .with_client_cert_verifier(
+                    verifiers(allowed.clone())
+                        .build_client_verifier()
+                        .unwrap(),
+                )
+                .with_single_cert(server_svid.0.clone(), server_svid.1.clone_key())
+                .unwrap();
+            let client_config = client_config_builder(&provider)
+                .dangerous()
+                .with_custom_certificate_verifier(
+                    verifiers(allowed)
+                        .build_server_verifier()
+                        .unwrap(),
+                )
+                .with_client_auth_cert(chain, key)
+                .unwrap();
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&Arc::new(client_config), &Arc::new(server_config));
+            do_handshake_until_error(&mut client, &mut server).map(|_| {
+                (
+                    client
+                        .peer_spiffe_id()
+                        .cloned()
+                        .unwrap(),
+                    server
+                        .peer_spiffe_id()
+                        .cloned()
+                        .unwrap(),
+                )
+            })
+        };
+
+    let (server_id, client_id) = handshake(
+        vec!["spiffe://example.org/server", "spiffe://other.org/client"],
+        other.issue(&["spiffe://other.org/client"]),
+    )
+    .unwrap();
+    assert_eq!(server_id.as_str(), "spiffe://example.org/server");
+    assert_eq!(client_id.trust_domain(), "other.org");
+    assert_eq!(client_id.path(), "/client");
+
+    // the authorizer rejects the client
+    assert_eq!(
+        handshake(
+            vec!["spiffe://example.org/server"],
+            other.issue(&["spiffe://other.org/client"]),
+        ),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::ApplicationVerificationFailure
+        )))
+    );
+
+    // the client's certificate is not issued by its trust domain's bundle
+    assert_eq!(
+        handshake(
+            vec!["spiffe://example.org/server", "spiffe://other.org/client"],
+            example.issue(&["spiffe://other.org/client"]),
+        ),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    );
+
+    // the client's trust domain is unknown
+    assert_eq!(
+        handshake(
+            vec!["spiffe://example.org/server", "spiffe://unknown.org/client"],
+            other.issue(&["spiffe://unknown.org/client"]),
+        ),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    );
+
+    // X509-SVIDs have exactly one URI subject alternative name
+    assert_eq!(
+        handshake(
+            vec!["spiffe://example.org/server", "spiffe://other.org/client"],
+            other.issue(&["spiffe://other.org/client", "spiffe://other.org/admin"]),
+        ),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::InvalidSvid
+        )))
+    );
+    assert_eq!(
+        handshake(
+            vec!["spiffe://example.org/server", "spiffe://other.org/client"],
+            other.issue(&["https://other.org/client"]),
+        ),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::InvalidSvid
+        )))
+    );
+
+    assert!(matches!(
+        SpiffeVerifierBuilder::new(Arc::new(AllowSpiffeIds(vec![])), provider.clone())
+            .with_trust_bundle("Example.org", example.roots.clone()),
+        Err(InvalidSpiffeId)
+    ));
+    assert!(matches!(
+        SpiffeVerifierBuilder::new(Arc::new(AllowSpiffeIds(vec![])), provider.clone())
+            .build_server_verifier(),
+        Err(VerifierBuilderError::NoRootAnchors)
+    ));
+}
+
+#[derive(Debug)]
+struct ClientCheckCertResolve {
+    query_count: AtomicUsize,
+    expect_queries: usize,
+    expect_root_hint_subjects: Vec<Vec<u8>>,
+    expect_sigschemes: Vec<SignatureScheme>,
+}
+
+impl ClientCheckCertResolve {
+    fn new(
+        expect_queries: usize,
+        expect_root_hint_subjects: Vec<Vec<u8>>,
+        expect_sigschemes: Vec<SignatureScheme>,
+    ) -> Self {
+        Self {
+            query_count: AtomicUsize::new(0),
+            expect_queries,
+            expect_root_hint_subjects,
+            expect_sigschemes,
+        }
+    }
+}
+
+impl Drop for ClientCheckCertResolve {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            let count = self.query_count.load(Ordering::SeqCst);
+            assert_eq!(count, self.expect_queries);
+        }
+    }
+}
+
+impl ResolvesClientCert for ClientCheckCertResolve {
+    fn resolve(
+        &self,
+        root_hint_subjects: &[&[u8]],
+        sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<sign::CertifiedKey>> {
+        self.query_count
+            .fetch_add(1, Ordering::SeqCst);
+
+        if sigschemes.is_empty() {
+            panic!("no signature schemes shared by server");
+        }
+
+        assert_eq!(sigschemes, self.expect_sigschemes);
+        assert_eq!(root_hint_subjects, self.expect_root_hint_subjects);
+
+        None
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+fn test_client_cert_resolve(
+    key_type: KeyType,
+    server_config: Arc<ServerConfig>,
+    expected_root_hint_subjects: Vec<Vec<u8>>,
+) {
+    let provider = provider::default_provider();
+    for version in rustls::ALL_VERSIONS {
+        println!("{:?} {:?}:", version.version(), key_type);
+
+        let mut client_config = make_client_config_with_versions(key_type, &[version], &provider);
+        client_config.client_auth_cert_resolver = Arc::new(ClientCheckCertResolve::new(
+            1,
+            expected_root_hint_subjects.clone(),
+            default_signature_schemes(version.version()),
+        ));
+
+        let (mut client, mut server) =
+            make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::NoCertificatesPresented))
+        );
+    }
+}
+
+fn default_signature_schemes(version: ProtocolVersion) -> Vec<SignatureScheme> {
+    let mut v = vec![];
+
+    v.extend_from_slice(&[
+        SignatureScheme::ECDSA_NISTP384_SHA384,
+        SignatureScheme::ECDSA_NISTP256_SHA256,
+        SignatureScheme::ED25519,
+        SignatureScheme::RSA_PSS_SHA512,
+        SignatureScheme::RSA_PSS_SHA384,
+        SignatureScheme::RSA_PSS_SHA256,
+    ]);
+
+    if provider_is_aws_lc_rs() {
+        v.insert(2, SignatureScheme::ECDSA_NISTP521_SHA512);
+    }
+
+    if provider_supports_ed448() {
+        let ed25519 = v
+            .iter()
+            .position(|scheme| *scheme == SignatureScheme::ED25519)
+            .unwrap();
+        v.insert(ed25519 + 1, SignatureScheme::ED448);
+    }
+
+    if version == ProtocolVersion::TLSv1_2 {
+        v.extend_from_slice(&[
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA256,
+        ]);
+    }
+
+    v
+}
+
+#[test]
+fn peer_spiffe_id_requires_spiffe_verifier() {
+    let provider = Arc::new(provider::default_provider());
+    let example = SvidIssuer::new("example.org CA");
+    let (chain, key) = example.issue(&["spiffe://example.org/server"]);
+
+    let server_config = server_config_builder(&provider)
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .unwrap();
+    let client_config = client_config_builder(&provider)
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(MockServerVerifier::accepts_anything()))
+        .with_no_client_auth();
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), &Arc::new(server_config));
+    do_handshake(&mut client, &mut server);
+
+    // the server's certificate is an X509-SVID, but was not verified as one
+    assert!(client.peer_certificates().is_some());
+    assert_eq!(client.peer_spiffe_id(), None);
+}
+
+#[test]
+fn client_cert_resolve_default() {
+    // Test that in the default configuration that a client cert resolver gets the expected
+    // CA subject hints, and supported signature algorithms.
+    let provider = provider::default_provider();
+    for key_type in KeyType::all_for_provider(&provider) {
+        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(
+            *key_type, &provider,
+        ));
+
+        // In a default configuration we expect that the verifier's trust anchors are used
+        // for the hint subjects.
+        let expected_root_hint_subjects = vec![
+            key_type
+                .ca_distinguished_name()
+                .to_vec(),
+        ];
+
+        test_client_cert_resolve(*key_type, server_config, expected_root_hint_subjects);
+    }
+}
+
+#[test]
+fn client_cert_resolve_server_no_hints() {
+    // Test that a server can provide no hints and the client cert resolver gets the expected
+    // arguments.
     let provider = provider::default_provider();
     for key_type in KeyType::all_for_provider(&provider) {
         // Build a verifier with no hint subjects.
@@ -1922,170 +2575,112 @@ fn client_cert_resolve_server_added_hint() {
     }
 }
 
+fn multi_identity_client_cert_resolver(
+    provider: &CryptoProvider,
+) -> rustls::client::MultiIdentityClientCertResolver {
+    let mut resolver = rustls::client::MultiIdentityClientCertResolver::new();
+    for kt in [KeyType::Rsa2048, KeyType::EcdsaP256, KeyType::Ed25519] {
+        let ck = sign::CertifiedKey::new_unchecked(
+            kt.get_client_chain(),
+            provider
+                .key_provider
+                .load_private_key(kt.get_client_key())
+                .unwrap(),
+        );
+        resolver.add(ck).unwrap();
+    }
+    resolver
+}
+
 #[test]
-fn client_auth_works() {
+fn multi_identity_client_cert_resolver_matches_root_hint_subjects() {
     let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(
-            *kt, &provider,
-        ));
+    let resolver = Arc::new(multi_identity_client_cert_resolver(&provider));
+
+    for kt in [KeyType::Rsa2048, KeyType::EcdsaP256, KeyType::Ed25519] {
+        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(kt, &provider));
 
         for version in rustls::ALL_VERSIONS {
-            let client_config =
-                make_client_config_with_versions_with_auth(*kt, &[version], &provider);
+            let mut client_config = make_client_config_with_versions(kt, &[version], &provider);
+            client_config.client_auth_cert_resolver = resolver.clone();
             let (mut client, mut server) =
                 make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
             do_handshake(&mut client, &mut server);
+            assert_eq!(
+                server.peer_certificates().unwrap(),
+                kt.get_client_chain(),
+                "{kt:?} {version:?}"
+            );
         }
     }
+
+    // no identity is issued by a hinted authority: continue without a client certificate
+    let server_config = Arc::new(make_server_config_with_optional_client_auth(
+        KeyType::EcdsaP384,
+        vec![],
+        &provider,
+    ));
+    let mut client_config = make_client_config(KeyType::EcdsaP384, &provider);
+    client_config.client_auth_cert_resolver = resolver;
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+    do_handshake(&mut client, &mut server);
+    assert_eq!(server.peer_certificates(), None);
 }
 
 #[test]
-fn client_mandatory_auth_client_revocation_works() {
+fn multi_identity_client_cert_resolver_matches_signature_schemes() {
     let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        // Create a server configuration that includes a CRL that specifies the client certificate
-        // is revoked.
-        let relevant_crls = vec![kt.client_crl()];
-        // Only check the EE certificate status. See client_mandatory_auth_intermediate_revocation_works
-        // for testing revocation status of the whole chain.
-        let ee_verifier_builder =
-            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
-                .with_crls(relevant_crls)
-                .only_check_end_entity_revocation();
-        let revoked_server_config = Arc::new(make_server_config_with_client_verifier(
-            *kt,
-            ee_verifier_builder,
-            &provider,
-        ));
-
-        // Create a server configuration that includes a CRL that doesn't cover the client certificate,
-        // and uses the default behaviour of treating unknown revocation status as an error.
-        let unrelated_crls = vec![kt.intermediate_crl()];
-        let ee_verifier_builder =
-            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
-                .with_crls(unrelated_crls.clone())
-                .only_check_end_entity_revocation();
-        let missing_client_crl_server_config = Arc::new(make_server_config_with_client_verifier(
-            *kt,
-            ee_verifier_builder,
-            &provider,
-        ));
+    let resolver = multi_identity_client_cert_resolver(&provider);
+    assert!(resolver.has_certs());
+    assert!(!rustls::client::MultiIdentityClientCertResolver::new().has_certs());
 
-        // Create a server configuration that includes a CRL that doesn't cover the client certificate,
-        // but change the builder to allow unknown revocation status.
-        let ee_verifier_builder =
-            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
-                .with_crls(unrelated_crls.clone())
-                .only_check_end_entity_revocation()
-                .allow_unknown_revocation_status();
-        let allow_missing_client_crl_server_config = Arc::new(
-            make_server_config_with_client_verifier(*kt, ee_verifier_builder, &provider),
+    for (sigschemes, expected) in [
+        // priority order breaks ties
+        (
+            &[SignatureScheme::ED25519, SignatureScheme::RSA_PSS_SHA256][..],
+            Some(KeyType::Rsa2048),
+        ),
+        (
+            &[SignatureScheme::ECDSA_NISTP256_SHA256][..],
+            Some(KeyType::EcdsaP256),
+        ),
+        (&[SignatureScheme::ED25519][..], Some(KeyType::Ed25519)),
+        (&[SignatureScheme::ECDSA_NISTP384_SHA384][..], None),
+    ] {
+        assert_eq!(
+            ResolvesClientCert::resolve(&resolver, &[], sigschemes).map(|ck| ck.cert_chain.clone()),
+            expected.map(|kt| kt.get_client_chain()),
+            "{sigschemes:?}"
         );
-
-        for version in rustls::ALL_VERSIONS {
-            // Connecting to the server with a CRL that indicates the client certificate is revoked
-            // should fail with the expected error.
-            let client_config = Arc::new(make_client_config_with_versions_with_auth(
-                *kt,
-                &[version],
-                &provider,
-            ));
-            let (mut client, mut server) =
-                make_pair_for_arc_configs(&client_config, &revoked_server_config);
-            let err = do_handshake_until_error(&mut client, &mut server);
-            assert_eq!(
-                err,
-                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
-                    CertificateError::Revoked
-                )))
-            );
-            // Connecting to the server missing CRL information for the client certificate should
-            // fail with the expected unknown revocation status error.
-            let (mut client, mut server) =
-                make_pair_for_arc_configs(&client_config, &missing_client_crl_server_config);
-            let res = do_handshake_until_error(&mut client, &mut server);
-            assert_eq!(
-                res,
-                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
-                    CertificateError::UnknownRevocationStatus
-                )))
-            );
-            // Connecting to the server missing CRL information for the client should not error
-            // if the server's verifier allows unknown revocation status.
-            let (mut client, mut server) =
-                make_pair_for_arc_configs(&client_config, &allow_missing_client_crl_server_config);
-            let res = do_handshake_until_error(&mut client, &mut server);
-            assert!(res.is_ok());
-        }
     }
-}
-
-#[test]
-fn client_mandatory_auth_intermediate_revocation_works() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        // Create a server configuration that includes a CRL that specifies the intermediate certificate
-        // is revoked. We check the full chain for revocation status (default), and allow unknown
-        // revocation status so the EE's unknown revocation status isn't an error.
-        let crls = vec![kt.intermediate_crl()];
-        let full_chain_verifier_builder =
-            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
-                .with_crls(crls.clone())
-                .allow_unknown_revocation_status();
-        let full_chain_server_config = Arc::new(make_server_config_with_client_verifier(
-            *kt,
-            full_chain_verifier_builder,
-            &provider,
-        ));
 
-        // Also create a server configuration that uses the same CRL, but that only checks the EE
-        // cert revocation status.
-        let ee_only_verifier_builder =
-            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
-                .with_crls(crls)
-                .only_check_end_entity_revocation()
-                .allow_unknown_revocation_status();
-        let ee_server_config = Arc::new(make_server_config_with_client_verifier(
-            *kt,
-            ee_only_verifier_builder,
-            &provider,
-        ));
-
-        for version in rustls::ALL_VERSIONS {
-            // When checking the full chain, we expect an error - the intermediate is revoked.
-            let client_config = Arc::new(make_client_config_with_versions_with_auth(
-                *kt,
-                &[version],
-                &provider,
-            ));
-            let (mut client, mut server) =
-                make_pair_for_arc_configs(&client_config, &full_chain_server_config);
-            let err = do_handshake_until_error(&mut client, &mut server);
-            assert_eq!(
-                err,
-                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
-                    CertificateError::Revoked
-                )))
-            );
-            // However, when checking just the EE cert we expect no error - the intermediate's
-            // revocation status should not be checked.
-            let (mut client, mut server) =
-                make_pair_for_arc_configs(&client_config, &ee_server_config);
-            assert!(do_handshake_until_error(&mut client, &mut server).is_ok());
-        }
-    }
+    // hints restrict the candidates, but must also be compatible with the signature schemes
+    let ecdsa_ca = KeyType::EcdsaP256.ca_distinguished_name();
+    assert_eq!(
+        ResolvesClientCert::resolve(
+            &resolver,
+            &[ecdsa_ca],
+            &[
+                SignatureScheme::RSA_PSS_SHA256,
+                SignatureScheme::ECDSA_NISTP256_SHA256
+            ],
+        )
+        .map(|ck| ck.cert_chain.clone()),
+        Some(KeyType::EcdsaP256.get_client_chain())
+    );
+    assert!(
+        ResolvesClientCert::resolve(&resolver, &[ecdsa_ca], &[SignatureScheme::RSA_PSS_SHA256])
+            .is_none()
+    );
 }
 
 #[test]
-fn client_optional_auth_client_revocation_works() {
+fn client_auth_works() {
     let provider = provider::default_provider();
     for kt in KeyType::all_for_provider(&provider) {
-        // Create a server configuration that includes a CRL that specifies the client certificate
-        // is revoked.
-        let crls = vec![kt.client_crl()];
-        let server_config = Arc::new(make_server_config_with_optional_client_auth(
-            *kt, crls, &provider,
+        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(
+            *kt, &provider,
         ));
 
         for version in rustls::ALL_VERSIONS {
@@ -2093,3261 +2688,5144 @@ fn client_optional_auth_client_revocation_works() {
                 make_client_config_with_versions_with_auth(*kt, &[version], &provider);
             let (mut client, mut server) =
                 make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
-            // Because the client certificate is revoked, the handshake should fail.
-            let err = do_handshake_until_error(&mut client, &mut server);
-            assert_eq!(
-                err,
-                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
-                    CertificateError::Revoked
-                )))
-            );
+            do_handshake(&mut client, &mut server);
         }
     }
 }
 
-#[test]
-fn client_error_is_sticky() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    client
-        .read_tls(&mut b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk".as_ref())
-        .unwrap();
-    let mut err = client.process_new_packets();
-    assert!(err.is_err());
-    err = client.process_new_packets();
-    assert!(err.is_err());
-}
+/// The default provider, verifying only ML-DSA signatures.
+///
+/// The aws-lc-rs provider can load ML-DSA keys, but does not verify ML-DSA by default.
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
+fn ml_dsa_provider() -> CryptoProvider {
+    use rustls::crypto::WebPkiSupportedAlgorithms;
+    use webpki::aws_lc_rs::{ML_DSA_44, ML_DSA_65, ML_DSA_87};
 
-#[test]
-fn server_error_is_sticky() {
-    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    server
-        .read_tls(&mut b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk".as_ref())
-        .unwrap();
-    let mut err = server.process_new_packets();
-    assert!(err.is_err());
-    err = server.process_new_packets();
-    assert!(err.is_err());
-}
+    static ML_DSA_SIG_ALGS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms {
+        all: &[ML_DSA_44, ML_DSA_65, ML_DSA_87],
+        mapping: &[
+            (SignatureScheme::ML_DSA_44, &[ML_DSA_44]),
+            (SignatureScheme::ML_DSA_65, &[ML_DSA_65]),
+            (SignatureScheme::ML_DSA_87, &[ML_DSA_87]),
+        ],
+    };
 
-#[test]
-fn server_flush_does_nothing() {
-    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(matches!(server.writer().flush(), Ok(())));
+    CryptoProvider {
+        signature_verification_algorithms: ML_DSA_SIG_ALGS,
+        ..provider::default_provider()
+    }
 }
 
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
 #[test]
-fn client_flush_does_nothing() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(matches!(client.writer().flush(), Ok(())));
-}
+fn ml_dsa_client_auth_works_in_tls13() {
+    if !provider_is_aws_lc_rs() {
+        return;
+    }
 
-#[allow(clippy::no_effect)]
-#[test]
-fn server_is_send_and_sync() {
-    let (_, server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    &server as &dyn Send;
-    &server as &dyn Sync;
+    let provider = ml_dsa_provider();
+    for kt in [KeyType::MlDsa44, KeyType::MlDsa65, KeyType::MlDsa87] {
+        let server_config = make_server_config_with_mandatory_client_auth(kt, &provider);
+        let client_config = make_client_config_with_auth(kt, &provider);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
+
+        assert_eq!(client.protocol_version(), Some(ProtocolVersion::TLSv1_3));
+        assert_eq!(client.peer_certificates(), Some(&kt.get_chain()[..]));
+        assert_eq!(server.peer_certificates(), Some(&kt.get_client_chain()[..]));
+    }
 }
 
-#[allow(clippy::no_effect)]
+#[cfg(all(feature = "aws-lc-rs-unstable", not(feature = "fips")))]
 #[test]
-fn client_is_send_and_sync() {
-    let (client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    &client as &dyn Send;
-    &client as &dyn Sync;
+fn ml_dsa_is_not_used_in_tls12() {
+    if !provider_is_aws_lc_rs() {
+        return;
+    }
+
+    let provider = ml_dsa_provider();
+    for kt in [KeyType::MlDsa44, KeyType::MlDsa65, KeyType::MlDsa87] {
+        // An ML-DSA server certificate cannot be used with any TLS1.2 cipher suite.
+        let server_config = make_server_config(kt, &provider);
+        let client_config =
+            make_client_config_with_versions(kt, &[&rustls::version::TLS12], &provider);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        assert!(do_handshake_until_error(&mut client, &mut server).is_err());
+
+        // An ML-DSA client certificate is not offered in TLS1.2.
+        let default_provider = provider::default_provider();
+        let server_config =
+            make_server_config_with_mandatory_client_auth(KeyType::EcdsaP256, &default_provider);
+        let mut client_config = make_client_config_with_versions(
+            KeyType::EcdsaP256,
+            &[&rustls::version::TLS12],
+            &default_provider,
+        );
+        client_config.client_auth_cert_resolver =
+            make_client_config_with_auth(kt, &provider).client_auth_cert_resolver;
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::NoCertificatesPresented))
+        );
+    }
 }
 
 #[test]
-fn server_respects_buffer_limit_pre_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-
-    server.set_buffer_limit(Some(32));
+fn ed448_server_and_client_auth() {
+    if !provider_supports_ed448() {
+        return;
+    }
 
-    assert_eq!(
-        server
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        20
-    );
-    assert_eq!(
-        server
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        12
-    );
+    let provider = provider::default_provider();
+    for version in rustls::ALL_VERSIONS {
+        let server_config =
+            make_server_config_with_mandatory_client_auth(KeyType::Ed448, &provider);
+        let client_config =
+            make_client_config_with_versions_with_auth(KeyType::Ed448, &[version], &provider);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
 
-    do_handshake(&mut client, &mut server);
-    transfer(&mut server, &mut client);
-    client.process_new_packets().unwrap();
+        assert_eq!(client.protocol_version(), Some(version.version()));
+        assert_eq!(
+            client.peer_certificates(),
+            Some(&KeyType::Ed448.get_chain()[..])
+        );
+        assert_eq!(
+            server.peer_certificates(),
+            Some(&KeyType::Ed448.get_client_chain()[..])
+        );
+    }
+}
 
-    check_read(&mut client.reader(), b"01234567890123456789012345678901");
+/// Ed448 is supported by the RustCrypto provider, and by the others with the
+/// `curve448` crate feature (except for verification with `fips`).
+fn provider_supports_ed448() -> bool {
+    provider::default_provider()
+        .signature_verification_algorithms
+        .supported_schemes()
+        .contains(&SignatureScheme::ED448)
 }
 
 #[test]
-fn server_respects_buffer_limit_pre_handshake_with_vectored_write() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+fn x448_key_exchange() {
+    let provider = provider::default_provider();
+    let Some(x448) = provider::ALL_KX_GROUPS
+        .iter()
+        .find(|group| group.name() == NamedGroup::X448)
+    else {
+        assert!(!provider_is_rustcrypto() && !cfg!(feature = "curve448"));
+        return;
+    };
+    let provider = CryptoProvider {
+        kx_groups: vec![*x448],
+        ..provider
+    };
 
-    server.set_buffer_limit(Some(32));
+    for version in rustls::ALL_VERSIONS {
+        let server_config = make_server_config(KeyType::Ed25519, &provider);
+        let client_config =
+            make_client_config_with_versions(KeyType::Ed25519, &[version], &provider);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
+
+        assert_eq!(client.protocol_version(), Some(version.version()));
+        assert_eq!(
+            client
+                .negotiated_key_exchange_group()
+                .unwrap()
+                .name(),
+            NamedGroup::X448
+        );
+        assert_eq!(
+            server
+                .negotiated_key_exchange_group()
+                .unwrap()
+                .name(),
+            NamedGroup::X448
+        );
+    }
+}
+
+fn client_handshake_result(
+    verifier: Arc<dyn ServerCertVerifier>,
+    server_config: &Arc<ServerConfig>,
+    provider: &CryptoProvider,
+) -> Result<(), ErrorFromPeer> {
+    let mut client_config = client_config_builder(provider)
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+    client_config.resumption = Resumption::disabled();
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), server_config);
+    do_handshake_until_error(&mut client, &mut server)
+}
 
+#[test]
+fn server_verifier_roots_and_crls_can_be_updated() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let server_config = Arc::new(make_server_config(kt, &provider));
+    let verifier = webpki_server_verifier_builder(get_client_root_store(kt), &provider)
+        .only_check_end_entity_revocation()
+        .build()
+        .unwrap();
     assert_eq!(
-        server
-            .writer()
-            .write_vectored(&[
-                IoSlice::new(b"01234567890123456789"),
-                IoSlice::new(b"01234567890123456789")
-            ])
-            .unwrap(),
-        32
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Ok(())
     );
 
-    do_handshake(&mut client, &mut server);
-    transfer(&mut server, &mut client);
-    client.process_new_packets().unwrap();
+    verifier
+        .update_crls([kt.end_entity_crl()])
+        .unwrap();
+    assert_eq!(
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::Revoked
+        )))
+    );
 
-    check_read(&mut client.reader(), b"01234567890123456789012345678901");
+    // unparsable CRLs are rejected, leaving the previous CRLs in use
+    assert!(matches!(
+        verifier.update_crls([CertificateRevocationListDer::from(b"bad".to_vec())]),
+        Err(VerifierBuilderError::InvalidCrl(_))
+    ));
+    assert_eq!(
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::Revoked
+        )))
+    );
+
+    verifier.update_crls([]).unwrap();
+    assert_eq!(
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Ok(())
+    );
+
+    verifier
+        .update_roots(get_client_root_store(KeyType::Rsa2048))
+        .unwrap();
+    assert_eq!(
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    );
+    assert!(matches!(
+        verifier.update_roots(Arc::new(RootCertStore::empty())),
+        Err(VerifierBuilderError::NoRootAnchors)
+    ));
 }
 
 #[test]
-fn server_respects_buffer_limit_post_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+fn client_verifier_roots_and_crls_can_be_updated() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let extra_name = DistinguishedName::from(b"extra".to_vec());
+    let verifier = webpki_client_verifier_builder(get_client_root_store(kt), &provider)
+        .add_root_hint_subjects([extra_name.clone()])
+        .only_check_end_entity_revocation()
+        .build()
+        .unwrap();
+    let server_config = Arc::new(
+        server_config_builder(&provider)
+            .with_client_cert_verifier(verifier.clone())
+            .with_single_cert(kt.get_chain(), kt.get_key())
+            .unwrap(),
+    );
+    let handshake = || {
+        let mut client_config = make_client_config_with_auth(kt, &provider);
+        client_config.resumption = Resumption::disabled();
+        let (mut client, mut server) =
+            make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+        do_handshake_until_error(&mut client, &mut server)
+    };
+    assert_eq!(handshake(), Ok(()));
 
-    // this test will vary in behaviour depending on the default suites
-    do_handshake(&mut client, &mut server);
-    server.set_buffer_limit(Some(48));
+    verifier
+        .update_crls([kt.client_crl()])
+        .unwrap();
+    assert_eq!(
+        handshake(),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::Revoked
+        )))
+    );
+    verifier.update_crls([]).unwrap();
+    assert_eq!(handshake(), Ok(()));
 
+    let hints = |verifier: &WebPkiClientVerifier| {
+        verifier
+            .root_hint_subjects()
+            .iter()
+            .map(|name| name.as_ref().to_vec())
+            .collect::<Vec<_>>()
+    };
     assert_eq!(
-        server
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        20
+        hints(&verifier),
+        vec![
+            kt.ca_distinguished_name().to_vec(),
+            extra_name.as_ref().to_vec()
+        ]
     );
+    verifier
+        .update_roots(get_client_root_store(KeyType::Rsa2048))
+        .unwrap();
     assert_eq!(
-        server
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        6
+        hints(&verifier),
+        vec![
+            KeyType::Rsa2048
+                .ca_distinguished_name()
+                .to_vec(),
+            extra_name.as_ref().to_vec()
+        ]
     );
+    assert_eq!(
+        handshake(),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    );
+}
 
-    transfer(&mut server, &mut client);
-    client.process_new_packets().unwrap();
+#[derive(Debug)]
+struct TestCrlSource {
+    crls: Mutex<Result<Vec<CertificateRevocationListDer<'static>>, Error>>,
+    fetches: AtomicUsize,
+}
 
-    check_read(&mut client.reader(), b"01234567890123456789012345");
+impl TestCrlSource {
+    fn new() -> Self {
+        Self {
+            crls: Mutex::new(Ok(vec![])),
+            fetches: AtomicUsize::new(0),
+        }
+    }
+
+    fn set(&self, crls: Result<Vec<CertificateRevocationListDer<'static>>, Error>) {
+        *self.crls.lock().unwrap() = crls;
+    }
+}
+
+impl CrlSource for TestCrlSource {
+    fn crls(&self) -> Result<Vec<CertificateRevocationListDer<'static>>, Error> {
+        self.fetches
+            .fetch_add(1, Ordering::SeqCst);
+        self.crls.lock().unwrap().clone()
+    }
 }
 
 #[test]
-fn client_respects_buffer_limit_pre_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+fn server_verifier_refreshes_crl_source() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let server_config = Arc::new(make_server_config(kt, &provider));
+    let source = Arc::new(TestCrlSource::new());
 
-    client.set_buffer_limit(Some(32));
+    // refreshed on every verification
+    let verifier = webpki_server_verifier_builder(get_client_root_store(kt), &provider)
+        .with_crl_source(source.clone(), Duration::ZERO)
+        .only_check_end_entity_revocation()
+        .build()
+        .unwrap();
+    assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Ok(())
+    );
+    assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
 
+    source.set(Ok(vec![kt.end_entity_crl()]));
     assert_eq!(
-        client
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        20
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::Revoked
+        )))
+    );
+
+    // failures keep the previously fetched CRLs
+    source.set(Err(Error::General("offline".into())));
+    assert_eq!(
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::Revoked
+        )))
     );
+    source.set(Ok(vec![CertificateRevocationListDer::from(
+        b"bad".to_vec(),
+    )]));
     assert_eq!(
-        client
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        12
+        client_handshake_result(verifier.clone(), &server_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::Revoked
+        )))
     );
 
-    do_handshake(&mut client, &mut server);
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
+    // not refreshed until the interval passes
+    source.set(Ok(vec![]));
+    let verifier = webpki_server_verifier_builder(get_client_root_store(kt), &provider)
+        .with_crl_source(source.clone(), Duration::from_secs(3600))
+        .only_check_end_entity_revocation()
+        .build()
+        .unwrap();
+    source.set(Ok(vec![kt.end_entity_crl()]));
+    let fetches = source.fetches.load(Ordering::SeqCst);
+    assert_eq!(
+        client_handshake_result(verifier, &server_config, &provider),
+        Ok(())
+    );
+    assert_eq!(source.fetches.load(Ordering::SeqCst), fetches);
 
-    check_read(&mut server.reader(), b"01234567890123456789012345678901");
+    // the initial fetch must succeed
+    source.set(Err(Error::General("offline".into())));
+    assert!(matches!(
+        webpki_server_verifier_builder(get_client_root_store(kt), &provider)
+            .with_crl_source(source, Duration::ZERO)
+            .build(),
+        Err(VerifierBuilderError::CrlSourceFailed(Error::General(_)))
+    ));
 }
 
 #[test]
-fn client_respects_buffer_limit_pre_handshake_with_vectored_write() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+fn server_verifier_intermediate_pool() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let chain = kt.get_chain();
+    let (end_entity, intermediate, root) = (&chain[0], &chain[1], &chain[2]);
+    let full_chain_config = Arc::new(make_server_config(kt, &provider));
+    let incomplete_chain_config = Arc::new(
+        server_config_builder(&provider)
+            .with_no_client_auth()
+            .with_single_cert(vec![end_entity.clone()], kt.get_key())
+            .unwrap(),
+    );
+    let verifier = |roots, pool| {
+        webpki_server_verifier_builder(roots, &provider)
+            .with_intermediate_pool(pool)
+            .build()
+            .unwrap()
+    };
+    let unknown_issuer = Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+        CertificateError::UnknownIssuer,
+    )));
 
-    client.set_buffer_limit(Some(32));
+    let pool = Arc::new(IntermediatePool::new(8));
+    assert_eq!(
+        client_handshake_result(
+            verifier(get_client_root_store(kt), pool.clone()),
+            &incomplete_chain_config,
+            &provider
+        ),
+        unknown_issuer
+    );
+    pool.add(intermediate.clone()).unwrap();
+    assert_eq!(
+        client_handshake_result(
+            verifier(get_client_root_store(kt), pool.clone()),
+            &incomplete_chain_config,
+            &provider
+        ),
+        Ok(())
+    );
 
+    // pooled certificates are never trust anchors
+    pool.add(root.clone()).unwrap();
     assert_eq!(
-        client
-            .writer()
-            .write_vectored(&[
-                IoSlice::new(b"01234567890123456789"),
-                IoSlice::new(b"01234567890123456789")
-            ])
-            .unwrap(),
-        32
+        client_handshake_result(
+            verifier(get_client_root_store(KeyType::Rsa2048), pool.clone()),
+            &full_chain_config,
+            &provider
+        ),
+        unknown_issuer
     );
 
-    do_handshake(&mut client, &mut server);
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
+    // the least recently added certificate is evicted
+    let pool = Arc::new(IntermediatePool::new(1));
+    pool.add(intermediate.clone()).unwrap();
+    pool.add(root.clone()).unwrap();
+    assert_eq!(pool.len(), 1);
+    assert_eq!(
+        client_handshake_result(
+            verifier(get_client_root_store(kt), pool.clone()),
+            &incomplete_chain_config,
+            &provider
+        ),
+        unknown_issuer
+    );
 
-    check_read(&mut server.reader(), b"01234567890123456789012345678901");
+    // intermediates are learned from verified chains
+    let pool = Arc::new(IntermediatePool::new(8).learn_from_verified_chains());
+    let learning = verifier(get_client_root_store(kt), pool.clone());
+    assert_eq!(
+        client_handshake_result(learning.clone(), &full_chain_config, &provider),
+        Ok(())
+    );
+    assert_eq!(pool.len(), 1);
+    assert_eq!(
+        client_handshake_result(learning, &incomplete_chain_config, &provider),
+        Ok(())
+    );
 }
 
 #[test]
-fn client_respects_buffer_limit_post_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-
-    do_handshake(&mut client, &mut server);
-    client.set_buffer_limit(Some(48));
+fn client_verifier_intermediate_pool() {
+    let provider = Arc::new(provider::default_provider());
+    let kt = KeyType::EcdsaP256;
+    let chain = kt.get_client_chain();
+    let pool = Arc::new(IntermediatePool::new(8));
+    let verifier =
+        WebPkiClientVerifier::builder_with_provider(get_client_root_store(kt), provider.clone())
+            .with_intermediate_pool(pool.clone())
+            .build()
+            .unwrap();
 
     assert_eq!(
-        client
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        20
+        verifier
+            .verify_client_cert(&chain[0], &[], UnixTime::now())
+            .map(|_| ()),
+        Err(Error::InvalidCertificate(CertificateError::UnknownIssuer))
     );
+    pool.add(chain[1].clone()).unwrap();
     assert_eq!(
-        client
-            .writer()
-            .write(b"01234567890123456789")
-            .unwrap(),
-        6
+        verifier
+            .verify_client_cert(&chain[0], &[], UnixTime::now())
+            .map(|_| ()),
+        Ok(())
     );
+}
 
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
-
-    check_read(&mut server.reader(), b"01234567890123456789012345");
+fn spki_pin(spki: &SubjectPublicKeyInfoDer<'_>) -> [u8; 32] {
+    cipher_suite::TLS13_AES_128_GCM_SHA256
+        .tls13()
+        .unwrap()
+        .common
+        .hash_provider
+        .hash(spki.as_ref())
+        .as_ref()
+        .try_into()
+        .unwrap()
 }
 
 #[test]
-fn client_detects_broken_write_vectored_impl() {
-    // see https://github.com/rustls/rustls/issues/2316
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    let err = client
-        .write_tls(&mut BrokenWriteVectored)
-        .unwrap_err();
-    assert_eq!(err.kind(), io::ErrorKind::Other);
-    assert!(format!("{err:?}").starts_with(
-        "Custom { kind: Other, error: \"illegal write_vectored return value (9999 > "
-    ));
-
-    struct BrokenWriteVectored;
+fn spki_pinning_verifier() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let server_config = Arc::new(make_server_config(kt, &provider));
+    let pin = spki_pin(&kt.get_spki());
+    let other_pin = spki_pin(&KeyType::Rsa2048.get_spki());
+    let localhost = || server_name("localhost");
 
-    impl io::Write for BrokenWriteVectored {
-        fn write_vectored(&mut self, _bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-            Ok(9999)
+    let handshake = |pins: Vec<(ServerName<'static>, SpkiPins)>| {
+        let inner = webpki_server_verifier_builder(get_client_root_store(kt), &provider)
+            .build()
+            .unwrap();
+        let mut verifier = SpkiPinningVerifier::new(inner, &provider).unwrap();
+        for (name, pins) in pins {
+            verifier.add(name, pins);
         }
+        let client_config = client_config_builder(&provider)
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        let (mut client, mut server) =
+            make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+        do_handshake_until_error(&mut client, &mut server)
+    };
+    let mismatch = Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+        CertificateError::PinMismatch,
+    )));
 
-        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
-            unreachable!()
-        }
+    assert_eq!(handshake(vec![]), Ok(()));
+    assert_eq!(handshake(vec![(localhost(), SpkiPins::new([pin]))]), Ok(()));
+    assert_eq!(
+        handshake(vec![(localhost(), SpkiPins::new([other_pin]))]),
+        mismatch
+    );
+    assert_eq!(
+        handshake(vec![(
+            localhost(),
+            SpkiPins::new([other_pin]).with_backup_pins([pin])
+        )]),
+        Ok(())
+    );
 
-        fn flush(&mut self) -> io::Result<()> {
-            unreachable!()
-        }
-    }
+    // pins only apply to their server name
+    assert_eq!(
+        handshake(vec![(
+            server_name("testserver.com"),
+            SpkiPins::new([other_pin])
+        )]),
+        Ok(())
+    );
+
+    // later pins for a name replace earlier ones
+    assert_eq!(
+        handshake(vec![
+            (localhost(), SpkiPins::new([pin])),
+            (localhost(), SpkiPins::new([other_pin])),
+        ]),
+        mismatch
+    );
+
+    // expired pins are not enforced
+    let past = UnixTime::since_unix_epoch(Duration::from_secs(1_000_000));
+    let future = UnixTime::since_unix_epoch(Duration::from_secs(u32::MAX as u64 * 4));
+    assert_eq!(
+        handshake(vec![(
+            localhost(),
+            SpkiPins::new([other_pin]).with_expiry(past)
+        )]),
+        Ok(())
+    );
+    assert_eq!(
+        handshake(vec![(
+            localhost(),
+            SpkiPins::new([other_pin]).with_expiry(future)
+        )]),
+        mismatch
+    );
+
+    // matching pins do not bypass normal validation
+    let inner = webpki_server_verifier_builder(get_client_root_store(KeyType::Rsa2048), &provider)
+        .build()
+        .unwrap();
+    let mut verifier = SpkiPinningVerifier::new(inner, &provider).unwrap();
+    verifier.add(localhost(), SpkiPins::new([pin]));
+    let client_config = client_config_builder(&provider)
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+    assert_eq!(
+        do_handshake_until_error(&mut client, &mut server),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    );
 }
 
 #[test]
-fn buf_read() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+fn tofu_verifier() {
+    let provider = provider::default_provider();
+    let store = Arc::new(MemoryTofuStore::new());
+    let verifier = || TofuVerifier::new(store.clone(), &provider).unwrap();
+    let ecdsa_config = Arc::new(make_server_config(KeyType::EcdsaP256, &provider));
+    let rsa_config = Arc::new(make_server_config(KeyType::Rsa2048, &provider));
 
-    do_handshake(&mut client, &mut server);
+    // the first key is trusted, and recorded
+    assert_eq!(store.get(&server_name("localhost")), Ok(None));
+    assert_eq!(
+        client_handshake_result(Arc::new(verifier()), &ecdsa_config, &provider),
+        Ok(())
+    );
+    let pin = spki_pin(&KeyType::EcdsaP256.get_spki());
+    assert_eq!(store.get(&server_name("localhost")), Ok(Some(pin)));
+    // DNS names are case-insensitive
+    assert_eq!(store.get(&server_name("LocalHost")), Ok(Some(pin)));
+    assert_eq!(
+        client_handshake_result(Arc::new(verifier()), &ecdsa_config, &provider),
+        Ok(())
+    );
 
-    // Write two separate messages
-    assert_eq!(client.writer().write(b"hello").unwrap(), 5);
-    transfer(&mut client, &mut server);
-    assert_eq!(client.writer().write(b"world").unwrap(), 5);
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
+    // a different key is rejected
+    assert_eq!(
+        client_handshake_result(Arc::new(verifier()), &rsa_config, &provider),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::KeyChanged
+        )))
+    );
+    assert_eq!(store.get(&server_name("localhost")), Ok(Some(pin)));
 
-    let mut reader = server.reader();
-    // fill_buf() returns each record separately (this is an implementation detail)
-    assert_eq!(reader.fill_buf().unwrap(), b"hello");
-    // partially consuming the buffer is OK
-    reader.consume(1);
-    assert_eq!(reader.fill_buf().unwrap(), b"ello");
-    // Read::read is compatible with BufRead
-    let mut b = [0u8; 2];
-    reader.read_exact(&mut b).unwrap();
-    assert_eq!(b, *b"el");
-    assert_eq!(reader.fill_buf().unwrap(), b"lo");
-    reader.consume(2);
-    // once the first packet is consumed, the next one is available
-    assert_eq!(reader.fill_buf().unwrap(), b"world");
-    reader.consume(5);
-    check_fill_buf_err(&mut reader, io::ErrorKind::WouldBlock);
+    // until it is explicitly accepted
+    store
+        .insert(
+            server_name("localhost"),
+            spki_pin(&KeyType::Rsa2048.get_spki()),
+        )
+        .unwrap();
+    assert_eq!(
+        client_handshake_result(Arc::new(verifier()), &rsa_config, &provider),
+        Ok(())
+    );
 }
 
-struct OtherSession<'a, C, S>
-where
-    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
-    S: SideData,
-{
-    sess: &'a mut C,
-    pub reads: usize,
-    pub writevs: Vec<Vec<usize>>,
-    fail_ok: bool,
-    pub short_writes: bool,
-    pub last_error: Option<rustls::Error>,
-    pub buffered: bool,
-    buffer: Vec<Vec<u8>>,
+#[test]
+fn tofu_verifier_raw_public_keys() {
+    let provider = provider::default_provider();
+    let store = Arc::new(MemoryTofuStore::new());
+    let handshake = |kt: KeyType| {
+        let mut client_config = make_client_config_with_raw_key_support(kt, &provider);
+        client_config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(
+                TofuVerifier::new(store.clone(), &provider)
+                    .unwrap()
+                    .with_raw_public_keys(),
+            ));
+        let server_config = make_server_config_with_raw_key_support(kt, &provider);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake_until_error(&mut client, &mut server)
+    };
+
+    assert_eq!(handshake(KeyType::EcdsaP256), Ok(()));
+    assert_eq!(
+        store.get(&server_name("localhost")),
+        Ok(Some(spki_pin(&KeyType::EcdsaP256.get_spki())))
+    );
+    assert_eq!(handshake(KeyType::EcdsaP256), Ok(()));
+    assert_eq!(
+        handshake(KeyType::Ed25519),
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::KeyChanged
+        )))
+    );
 }
 
-impl<'a, C, S> OtherSession<'a, C, S>
-where
-    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
-    S: SideData,
-{
-    fn new(sess: &'a mut C) -> OtherSession<'a, C, S> {
-        OtherSession {
-            sess,
-            reads: 0,
-            writevs: vec![],
-            fail_ok: false,
-            short_writes: false,
-            last_error: None,
-            buffered: false,
-            buffer: vec![],
-        }
-    }
+#[test]
+fn tofu_file_store() {
+    // this test runs once per provider, in the same process
+    let path = std::env::temp_dir().join(format!(
+        "rustls-tofu-{}-{}",
+        std::process::id(),
+        module_path!().replace("::", "-")
+    ));
+    let _ = std::fs::remove_file(&path);
+    let localhost = server_name("localhost");
+    let other = server_name("192.0.2.1");
+
+    let store = FileTofuStore::open(&path).unwrap();
+    assert_eq!(store.get(&localhost), Ok(None));
+    store
+        .insert(localhost.clone(), [1; 32])
+        .unwrap();
+    store
+        .insert(other.clone(), [2; 32])
+        .unwrap();
+    store
+        .insert(localhost.clone(), [3; 32])
+        .unwrap();
+    assert_eq!(store.get(&localhost), Ok(Some([3; 32])));
+    drop(store);
 
-    fn new_buffered(sess: &'a mut C) -> OtherSession<'a, C, S> {
-        let mut os = OtherSession::new(sess);
-        os.buffered = true;
-        os
-    }
+    // fingerprints persist, and the latest one for a name wins
+    let store = FileTofuStore::open(&path).unwrap();
+    assert_eq!(store.get(&localhost), Ok(Some([3; 32])));
+    assert_eq!(store.get(&other), Ok(Some([2; 32])));
+    assert_eq!(
+        store.verify_or_insert(&localhost, [3; 32]),
+        Ok(TofuOutcome::Known)
+    );
+    assert_eq!(
+        store.verify_or_insert(&localhost, [4; 32]),
+        Ok(TofuOutcome::Changed)
+    );
+    let new = server_name("testserver.com");
+    assert_eq!(
+        store.verify_or_insert(&new, [5; 32]),
+        Ok(TofuOutcome::Inserted)
+    );
+    drop(store);
 
-    fn new_fails(sess: &'a mut C) -> OtherSession<'a, C, S> {
-        let mut os = OtherSession::new(sess);
-        os.fail_ok = true;
-        os
-    }
+    // fingerprints recorded on first use persist, and changed ones are not recorded
+    let store = FileTofuStore::open(&path).unwrap();
+    assert_eq!(store.get(&localhost), Ok(Some([3; 32])));
+    assert_eq!(store.get(&new), Ok(Some([5; 32])));
+    drop(store);
 
-    fn flush_vectored(&mut self, b: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        let mut total = 0;
-        let mut lengths = vec![];
-        for bytes in b {
-            let write_len = if self.short_writes {
-                if bytes.len() > 5 {
-                    bytes.len() / 2
-                } else {
-                    bytes.len()
-                }
-            } else {
-                bytes.len()
-            };
+    // DNS names are case-insensitive, including in the file
+    std::fs::write(&path, format!("LocalHost {}\n", "06".repeat(32))).unwrap();
+    let store = FileTofuStore::open(&path).unwrap();
+    assert_eq!(store.get(&localhost), Ok(Some([6; 32])));
+    assert_eq!(
+        store.verify_or_insert(&server_name("TestServer.com"), [5; 32]),
+        Ok(TofuOutcome::Inserted)
+    );
+    assert_eq!(store.get(&new), Ok(Some([5; 32])));
+    drop(store);
 
-            let l = self
-                .sess
-                .read_tls(&mut io::Cursor::new(&bytes[..write_len]))?;
-            lengths.push(l);
-            total += l;
-            if bytes.len() != l {
-                break;
-            }
-        }
+    std::fs::write(&path, "localhost 1234\n").unwrap();
+    assert_eq!(
+        FileTofuStore::open(&path)
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidData
+    );
+    std::fs::remove_file(&path).unwrap();
+}
 
-        let rc = self.sess.process_new_packets();
-        if !self.fail_ok {
-            rc.unwrap();
-        } else if rc.is_err() {
-            self.last_error = rc.err();
-        }
+#[test]
+fn spki_pinning_verifier_checks_only_verified_path() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let verify = |pins: SpkiPins, intermediates: &[CertificateDer<'_>]| {
+        let inner = webpki_server_verifier_builder(get_client_root_store(kt), &provider)
+            .build()
+            .unwrap();
+        let mut verifier = SpkiPinningVerifier::new(inner, &provider).unwrap();
+        verifier.add(server_name("localhost"), pins);
+        verifier
+            .verify_server_cert(
+                &kt.get_chain()[0],
+                intermediates,
+                &server_name("localhost"),
+                &[],
+                UnixTime::now(),
+            )
+            .map(|_| ())
+    };
+    let cert_pin = |cert: &CertificateDer<'_>| {
+        spki_pin(
+            &ParsedCertificate::try_from(cert)
+                .unwrap()
+                .subject_public_key_info(),
+        )
+    };
 
-        self.writevs.push(lengths);
-        Ok(total)
-    }
-}
+    // an intermediate which is sent, but not used to reach the trust anchor, is not checked
+    let chain = kt.get_chain();
+    let unused = KeyType::Rsa2048.get_chain()[1].clone();
+    let with_unused = [chain[1].clone(), unused.clone()];
+    assert_eq!(
+        verify(SpkiPins::new([cert_pin(&unused)]), &with_unused),
+        Err(Error::InvalidCertificate(CertificateError::PinMismatch))
+    );
+    assert_eq!(
+        verify(SpkiPins::new([cert_pin(&chain[1])]), &with_unused),
+        Ok(())
+    );
 
-impl<C, S> io::Read for OtherSession<'_, C, S>
-where
-    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
-    S: SideData,
-{
-    fn read(&mut self, mut b: &mut [u8]) -> io::Result<usize> {
-        self.reads += 1;
-        self.sess.write_tls(b.by_ref())
-    }
+    // the trust anchor is checked, even when the server does not send it
+    assert_eq!(
+        verify(SpkiPins::new([cert_pin(&kt.ca_cert())]), &chain[1..2]),
+        Ok(())
+    );
 }
 
-impl<C, S> io::Write for OtherSession<'_, C, S>
-where
-    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
-    S: SideData,
-{
-    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
-        unreachable!()
-    }
+#[test]
+fn peer_verified_path() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let server_config = Arc::new(make_server_config_with_mandatory_client_auth(kt, &provider));
 
-    fn flush(&mut self) -> io::Result<()> {
-        if !self.buffer.is_empty() {
-            let buffer = mem::take(&mut self.buffer);
-            let slices = buffer
-                .iter()
-                .map(|b| io::IoSlice::new(b))
-                .collect::<Vec<_>>();
-            self.flush_vectored(&slices)?;
-        }
-        Ok(())
-    }
+    for version in [&rustls::version::TLS12, &rustls::version::TLS13] {
+        let client_config = Arc::new(make_client_config_with_versions_with_auth(
+            kt,
+            &[version],
+            &provider,
+        ));
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert_eq!(client.peer_verified_path(), None);
+        do_handshake(&mut client, &mut server);
 
-    fn write_vectored(&mut self, b: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        if self.buffered {
-            self.buffer
-                .extend(b.iter().map(|s| s.to_vec()));
-            return Ok(b.iter().map(|s| s.len()).sum());
-        }
-        self.flush_vectored(b)
+        let anchor = anchor_from_trusted_cert(&kt.ca_cert())
+            .unwrap()
+            .to_owned();
+        let chain = kt.get_chain();
+        let path = client.peer_verified_path().unwrap();
+        assert_eq!(path.end_entity(), &chain[0]);
+        assert_eq!(path.intermediates(), &chain[1..2]);
+        assert_eq!(path.trust_anchor(), &anchor);
+        assert_eq!(
+            client
+                .connection_info()
+                .peer_verified_path
+                .as_ref(),
+            Some(path)
+        );
+
+        let chain = kt.get_client_chain();
+        let path = server.peer_verified_path().unwrap();
+        assert_eq!(path.end_entity(), &chain[0]);
+        assert_eq!(path.intermediates(), &chain[1..chain.len() - 1]);
+        assert_eq!(
+            path.trust_anchor(),
+            &anchor_from_trusted_cert(chain.last().unwrap())
+                .unwrap()
+                .to_owned()
+        );
+
+        // resumed handshakes do not verify certificates
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        do_handshake(&mut client, &mut server);
+        assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
+        assert_eq!(client.peer_verified_path(), None);
     }
 }
 
-#[test]
-fn server_read_returns_wouldblock_when_no_data() {
-    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(matches!(server.reader().read(&mut [0u8; 1]),
-                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
+fn client_options_handshake(
+    client_config: &Arc<ClientConfig>,
+    server_config: &Arc<ServerConfig>,
+    name: &'static str,
+    options: ClientConnectionOptions,
+) -> (
+    ClientConnection,
+    ServerConnection,
+    Result<(), ErrorFromPeer>,
+) {
+    let mut client =
+        ClientConnection::new_with_options(client_config.clone(), server_name(name), options)
+            .unwrap();
+    let mut server = ServerConnection::new(server_config.clone()).unwrap();
+    let result = do_handshake_until_error(&mut client, &mut server);
+    (client, server, result)
 }
 
 #[test]
-fn client_read_returns_wouldblock_when_no_data() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(matches!(client.reader().read(&mut [0u8; 1]),
-                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
-}
-
-#[test]
-fn server_fill_buf_returns_wouldblock_when_no_data() {
-    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(matches!(server.reader().fill_buf(),
-                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
-}
-
-#[test]
-fn client_fill_buf_returns_wouldblock_when_no_data() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(matches!(client.reader().fill_buf(),
-                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
-}
-
-#[test]
-fn new_server_returns_initial_io_state() {
-    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    let io_state = server.process_new_packets().unwrap();
-    println!("IoState is Debug {io_state:?}");
-    assert_eq!(io_state.plaintext_bytes_to_read(), 0);
-    assert!(!io_state.peer_has_closed());
-    assert_eq!(io_state.tls_bytes_to_write(), 0);
-}
-
-#[test]
-fn new_client_returns_initial_io_state() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    let io_state = client.process_new_packets().unwrap();
-    println!("IoState is Debug {io_state:?}");
-    assert_eq!(io_state.plaintext_bytes_to_read(), 0);
-    assert!(!io_state.peer_has_closed());
-    assert!(io_state.tls_bytes_to_write() > 200);
-}
-
-#[test]
-fn client_complete_io_for_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+fn client_options_verification_name_and_sni() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let client_config = Arc::new(make_client_config(kt, &provider));
+    let server_config = Arc::new(make_server_config(kt, &provider));
+    let options = ClientConnectionOptions::new;
 
-    assert!(client.is_handshaking());
-    let (rdlen, wrlen) = client
-        .complete_io(&mut OtherSession::new(&mut server))
-        .unwrap();
-    assert!(rdlen > 0 && wrlen > 0);
-    assert!(!client.is_handshaking());
-    assert!(!client.wants_write());
-}
+    // the certificate is not valid for this address
+    let (_, _, result) =
+        client_options_handshake(&client_config, &server_config, "192.0.2.1", options());
+    assert!(matches!(
+        result,
+        Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+            CertificateError::NotValidForNameContext { .. }
+        )))
+    ));
 
-#[test]
-fn buffered_client_complete_io_for_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    // but it is for this name, which is sent as SNI
+    let (client, server, result) = client_options_handshake(
+        &client_config,
+        &server_config,
+        "192.0.2.1",
+        options()
+            .with_verification_name(server_name("testserver.com"))
+            .with_server_name_indication(DnsName::try_from("sni.example.com").unwrap()),
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        server.server_name(),
+        Some(&DnsName::try_from("sni.example.com").unwrap())
+    );
+    assert_eq!(
+        client.connection_info().server_name,
+        Some(DnsName::try_from("sni.example.com").unwrap())
+    );
 
-    assert!(client.is_handshaking());
-    let (rdlen, wrlen) = client
-        .complete_io(&mut OtherSession::new_buffered(&mut server))
-        .unwrap();
-    assert!(rdlen > 0 && wrlen > 0);
-    assert!(!client.is_handshaking());
-    assert!(!client.wants_write());
-}
+    // the verification name is not sent by default
+    let (_, server, result) = client_options_handshake(
+        &client_config,
+        &server_config,
+        "192.0.2.1",
+        options().with_verification_name(server_name("testserver.com")),
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(server.server_name(), None);
 
-#[test]
-fn client_complete_io_for_handshake_eof() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    let mut input = io::Cursor::new(Vec::new());
+    let (_, server, result) = client_options_handshake(
+        &client_config,
+        &server_config,
+        "localhost",
+        options().without_server_name_indication(),
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(server.server_name(), None);
 
-    assert!(client.is_handshaking());
-    let err = client
-        .complete_io(&mut input)
-        .unwrap_err();
-    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    // ALPN can still be overridden
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.alpn_protocols = vec![b"proto".to_vec()];
+    let (client, _, result) = client_options_handshake(
+        &client_config,
+        &Arc::new(server_config),
+        "localhost",
+        options().with_alpn_protocols(vec![b"proto".to_vec()]),
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(client.alpn_protocol(), Some(&b"proto"[..]));
 }
 
 #[test]
-fn client_complete_io_for_write() {
+fn client_options_client_cert_resolver() {
     let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
+    let kt = KeyType::EcdsaP256;
+    let client_config = Arc::new(make_client_config(kt, &provider));
+    let server_config = Arc::new(make_server_config_with_mandatory_client_auth(kt, &provider));
 
-        do_handshake(&mut client, &mut server);
+    let (_, _, result) = client_options_handshake(
+        &client_config,
+        &server_config,
+        "localhost",
+        ClientConnectionOptions::new(),
+    );
+    assert_eq!(
+        result,
+        Err(ErrorFromPeer::Server(Error::NoCertificatesPresented))
+    );
 
-        client
-            .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        client
-            .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        {
-            let mut pipe = OtherSession::new(&mut server);
-            let (rdlen, wrlen) = client.complete_io(&mut pipe).unwrap();
-            assert!(rdlen == 0 && wrlen > 0);
-            println!("{:?}", pipe.writevs);
-            assert_eq!(pipe.writevs, vec![vec![42, 42]]);
-        }
-        check_read(
-            &mut server.reader(),
-            b"0123456789012345678901234567890123456789",
-        );
-    }
+    let resolver = make_client_config_with_auth(kt, &provider).client_auth_cert_resolver;
+    let (_, server, result) = client_options_handshake(
+        &client_config,
+        &server_config,
+        "localhost",
+        ClientConnectionOptions::new().with_client_cert_resolver(resolver),
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(server.peer_certificates(), Some(&kt.get_client_chain()[..]));
 }
 
 #[test]
-fn client_complete_io_with_nonblocking_io() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-
-    // absolutely no progress writing ClientHello
-    assert_eq!(
-        client
-            .complete_io(&mut TestNonBlockIo::default())
-            .unwrap_err()
-            .kind(),
-        io::ErrorKind::WouldBlock
-    );
+fn client_options_session_key() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+    let client_config = Arc::new(make_client_config(kt, &provider));
+    let server_config = Arc::new(make_server_config(kt, &provider));
+    let handshake_kind = |name, options| {
+        let (client, _, result) =
+            client_options_handshake(&client_config, &server_config, name, options);
+        assert_eq!(result, Ok(()));
+        client.handshake_kind().unwrap()
+    };
+    let verify_as_localhost =
+        || ClientConnectionOptions::new().with_verification_name(server_name("localhost"));
 
-    // a little progress writing ClientHello
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    // sessions are stored under the verification name by default.  (SNI is omitted
+    // where it would differ, because servers only resume sessions with the same SNI.)
     assert_eq!(
-        client
-            .complete_io(&mut TestNonBlockIo {
-                writes: vec![1],
-                reads: vec![],
-            })
-            .unwrap(),
-        (0, 1)
+        handshake_kind("192.0.2.1", verify_as_localhost()),
+        HandshakeKind::Full
     );
-
-    // complete writing ClientHello
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
     assert_eq!(
-        client
-            .complete_io(&mut TestNonBlockIo {
-                writes: vec![4096],
-                reads: vec![],
-            })
-            .unwrap_err()
-            .kind(),
-        io::ErrorKind::WouldBlock
+        handshake_kind("192.0.2.2", verify_as_localhost()),
+        HandshakeKind::Resumed
     );
-
-    // complete writing ClientHello, partial read of ServerHello
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    let (rd, wr) = dbg!(client.complete_io(&mut TestNonBlockIo {
-        writes: vec![4096],
-        reads: vec![vec![ContentType::Handshake.into()]],
-    }))
-    .unwrap();
-    assert_eq!(rd, 1);
-    assert!(wr > 1);
-
-    // data phase:
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    do_handshake(&mut client, &mut server);
-
-    // read
     assert_eq!(
-        client
-            .complete_io(&mut TestNonBlockIo {
-                reads: vec![vec![ContentType::ApplicationData.into()]],
-                writes: vec![],
-            })
-            .unwrap(),
-        (1, 0)
+        handshake_kind(
+            "localhost",
+            ClientConnectionOptions::new().without_server_name_indication()
+        ),
+        HandshakeKind::Resumed
     );
 
-    // write
-    client
-        .writer()
-        .write_all(b"hello")
-        .unwrap();
-
-    // no progress
+    // but can be stored under another key
     assert_eq!(
-        client
-            .complete_io(&mut TestNonBlockIo {
-                reads: vec![],
-                writes: vec![],
-            })
-            .unwrap_err()
-            .kind(),
-        io::ErrorKind::WouldBlock
+        handshake_kind(
+            "localhost",
+            ClientConnectionOptions::new()
+                .without_server_name_indication()
+                .with_session_key(server_name("pool-1"))
+        ),
+        HandshakeKind::Full
     );
-
-    // some write progress
     assert_eq!(
-        client
-            .complete_io(&mut TestNonBlockIo {
-                reads: vec![],
-                writes: vec![1],
-            })
-            .unwrap(),
-        (0, 1)
+        handshake_kind(
+            "testserver.com",
+            ClientConnectionOptions::new()
+                .without_server_name_indication()
+                .with_session_key(server_name("pool-1"))
+        ),
+        HandshakeKind::Resumed
     );
 }
 
 #[test]
-fn buffered_client_complete_io_for_write() {
+fn client_mandatory_auth_client_revocation_works() {
     let provider = provider::default_provider();
     for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
-
-        do_handshake(&mut client, &mut server);
+        // Create a server configuration that includes a CRL that specifies the client certificate
+        // is revoked.
+        let relevant_crls = vec![kt.client_crl()];
+        // Only check the EE certificate status. See client_mandatory_auth_intermediate_revocation_works
+        // for testing revocation status of the whole chain.
+        let ee_verifier_builder =
+            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
+                .with_crls(relevant_crls)
+                .only_check_end_entity_revocation();
+        let revoked_server_config = Arc::new(make_server_config_with_client_verifier(
+            *kt,
+            ee_verifier_builder,
+            &provider,
+        ));
 
-        client
-            .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        client
-            .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        {
-            let mut pipe = OtherSession::new_buffered(&mut server);
-            let (rdlen, wrlen) = client.complete_io(&mut pipe).unwrap();
-            assert!(rdlen == 0 && wrlen > 0);
-            println!("{:?}", pipe.writevs);
-            assert_eq!(pipe.writevs, vec![vec![42, 42]]);
-        }
-        check_read(
-            &mut server.reader(),
-            b"0123456789012345678901234567890123456789",
-        );
-    }
-}
+        // Create a server configuration that includes a CRL that doesn't cover the client certificate,
+        // and uses the default behaviour of treating unknown revocation status as an error.
+        let unrelated_crls = vec![kt.intermediate_crl()];
+        let ee_verifier_builder =
+            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
+                .with_crls(unrelated_crls.clone())
+                .only_check_end_entity_revocation();
+        let missing_client_crl_server_config = Arc::new(make_server_config_with_client_verifier(
+            *kt,
+            ee_verifier_builder,
+            &provider,
+        ));
 
-#[test]
-fn client_complete_io_for_read() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
+        // Create a server configuration that includes a CRL that doesn't cover the client certificate,
+        // but change the builder to allow unknown revocation status.
+        let ee_verifier_builder =
+            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
+                .with_crls(unrelated_crls.clone())
+                .only_check_end_entity_revocation()
+                .allow_unknown_revocation_status();
+        let allow_missing_client_crl_server_config = Arc::new(
+            make_server_config_with_client_verifier(*kt, ee_verifier_builder, &provider),
+        );
 
-        do_handshake(&mut client, &mut server);
+        for version in rustls::ALL_VERSIONS {
+            // Connecting to the server with a CRL that indicates the client certificate is revoked
+            // should fail with the expected error.
+            let client_config = Arc::new(make_client_config_with_versions_with_auth(
+                *kt,
+                &[version],
+                &provider,
+            ));
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &revoked_server_config);
+            let err = do_handshake_until_error(&mut client, &mut server);
+            assert_eq!(
+                err,
+                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                    CertificateError::Revoked
+                )))
+            );
+            // Connecting to the server missing CRL information for the client certificate should
+            // fail with the expected unknown revocation status error.
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &missing_client_crl_server_config);
+            let res = do_handshake_until_error(&mut client, &mut server);
+            assert_eq!(
+                res,
+                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                    CertificateError::UnknownRevocationStatus
+                )))
+            );
+            // Connecting to the server missing CRL information for the client should not error
+            // if the server's verifier allows unknown revocation status.
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &allow_missing_client_crl_server_config);
+            let res = do_handshake_until_error(&mut client, &mut server);
+            assert!(res.is_ok());
+        }
+    }
+}
 
-        server
-            .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        {
-            let mut pipe = OtherSession::new(&mut server);
-            let (rdlen, wrlen) = client.complete_io(&mut pipe).unwrap();
-            assert!(rdlen > 0 && wrlen == 0);
-            assert_eq!(pipe.reads, 1);
+#[test]
+fn client_mandatory_auth_intermediate_revocation_works() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        // Create a server configuration that includes a CRL that specifies the intermediate certificate
+        // is revoked. We check the full chain for revocation status (default), and allow unknown
+        // revocation status so the EE's unknown revocation status isn't an error.
+        let crls = vec![kt.intermediate_crl()];
+        let full_chain_verifier_builder =
+            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
+                .with_crls(crls.clone())
+                .allow_unknown_revocation_status();
+        let full_chain_server_config = Arc::new(make_server_config_with_client_verifier(
+            *kt,
+            full_chain_verifier_builder,
+            &provider,
+        ));
+
+        // Also create a server configuration that uses the same CRL, but that only checks the EE
+        // cert revocation status.
+        let ee_only_verifier_builder =
+            webpki_client_verifier_builder(get_client_root_store(*kt), &provider)
+                .with_crls(crls)
+                .only_check_end_entity_revocation()
+                .allow_unknown_revocation_status();
+        let ee_server_config = Arc::new(make_server_config_with_client_verifier(
+            *kt,
+            ee_only_verifier_builder,
+            &provider,
+        ));
+
+        for version in rustls::ALL_VERSIONS {
+            // When checking the full chain, we expect an error - the intermediate is revoked.
+            let client_config = Arc::new(make_client_config_with_versions_with_auth(
+                *kt,
+                &[version],
+                &provider,
+            ));
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &full_chain_server_config);
+            let err = do_handshake_until_error(&mut client, &mut server);
+            assert_eq!(
+                err,
+                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                    CertificateError::Revoked
+                )))
+            );
+            // However, when checking just the EE cert we expect no error - the intermediate's
+            // revocation status should not be checked.
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &ee_server_config);
+            assert!(do_handshake_until_error(&mut client, &mut server).is_ok());
         }
-        check_read(&mut client.reader(), b"01234567890123456789");
     }
 }
 
 #[test]
-fn server_complete_io_for_handshake() {
+fn client_optional_auth_client_revocation_works() {
     let provider = provider::default_provider();
     for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
+        // Create a server configuration that includes a CRL that specifies the client certificate
+        // is revoked.
+        let crls = vec![kt.client_crl()];
+        let server_config = Arc::new(make_server_config_with_optional_client_auth(
+            *kt, crls, &provider,
+        ));
 
-        assert!(server.is_handshaking());
-        let (rdlen, wrlen) = server
-            .complete_io(&mut OtherSession::new(&mut client))
-            .unwrap();
-        assert!(rdlen > 0 && wrlen > 0);
-        assert!(!server.is_handshaking());
-        assert!(!server.wants_write());
+        for version in rustls::ALL_VERSIONS {
+            let client_config =
+                make_client_config_with_versions_with_auth(*kt, &[version], &provider);
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+            // Because the client certificate is revoked, the handshake should fail.
+            let err = do_handshake_until_error(&mut client, &mut server);
+            assert_eq!(
+                err,
+                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                    CertificateError::Revoked
+                )))
+            );
+        }
     }
 }
 
 #[test]
-fn server_complete_io_for_handshake_eof() {
+fn client_error_is_sticky() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    client
+        .read_tls(&mut &b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk"[..])
+        .unwrap();
+    let mut err = client.process_new_packets();
+    assert!(err.is_err());
+    err = client.process_new_packets();
+    assert!(err.is_err());
+}
+
+#[test]
+fn server_error_is_sticky() {
     let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    let mut input = io::Cursor::new(Vec::new());
+    server
+        .read_tls(&mut &b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk"[..])
+        .unwrap();
+    let mut err = server.process_new_packets();
+    assert!(err.is_err());
+    err = server.process_new_packets();
+    assert!(err.is_err());
+}
 
-    assert!(server.is_handshaking());
-    let err = server
-        .complete_io(&mut input)
-        .unwrap_err();
-    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+#[test]
+fn server_flush_does_nothing() {
+    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(matches!(server.writer().flush(), Ok(())));
 }
 
 #[test]
-fn server_complete_io_for_write() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
+fn client_flush_does_nothing() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(matches!(client.writer().flush(), Ok(())));
+}
 
-        do_handshake(&mut client, &mut server);
+#[allow(clippy::no_effect)]
+#[test]
+fn server_is_send_and_sync() {
+    let (_, server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    &server as &dyn Send;
+    &server as &dyn Sync;
+}
+
+#[allow(clippy::no_effect)]
+#[test]
+fn client_is_send_and_sync() {
+    let (client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    &client as &dyn Send;
+    &client as &dyn Sync;
+}
+
+#[test]
+fn server_respects_buffer_limit_pre_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+
+    server.set_buffer_limit(Some(32));
 
+    assert_eq!(
         server
             .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
+            .write(b"01234567890123456789")
+            .unwrap(),
+        20
+    );
+    assert_eq!(
         server
             .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        {
-            let mut pipe = OtherSession::new(&mut client);
-            let (rdlen, wrlen) = server.complete_io(&mut pipe).unwrap();
-            assert!(rdlen == 0 && wrlen > 0);
-            assert_eq!(pipe.writevs, vec![vec![42, 42]]);
-        }
-        check_read(
-            &mut client.reader(),
-            b"0123456789012345678901234567890123456789",
-        );
-    }
+            .write(b"01234567890123456789")
+            .unwrap(),
+        12
+    );
+
+    do_handshake(&mut client, &mut server);
+    transfer(&mut server, &mut client);
+    client.process_new_packets().unwrap();
+
+    check_read(&mut client.reader(), b"01234567890123456789012345678901");
 }
 
 #[test]
-fn server_complete_io_for_write_eof() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
+fn server_respects_buffer_limit_pre_handshake_with_vectored_write() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-        do_handshake(&mut client, &mut server);
+    server.set_buffer_limit(Some(32));
 
-        // Queue 20 bytes to write.
+    assert_eq!(
         server
             .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        {
-            const BYTES_BEFORE_EOF: usize = 5;
-            let mut eof_writer = EofWriter::<BYTES_BEFORE_EOF>::default();
+            .write_vectored(&[
+                IoSlice::new(b"01234567890123456789"),
+                IoSlice::new(b"01234567890123456789")
+            ])
+            .unwrap(),
+        32
+    );
 
-            // Only BYTES_BEFORE_EOF should be written.
-            let (rdlen, wrlen) = server
-                .complete_io(&mut eof_writer)
-                .unwrap();
-            assert_eq!(rdlen, 0);
-            assert_eq!(wrlen, BYTES_BEFORE_EOF);
+    do_handshake(&mut client, &mut server);
+    transfer(&mut server, &mut client);
+    client.process_new_packets().unwrap();
 
-            // Now nothing should be written.
-            let (rdlen, wrlen) = server
-                .complete_io(&mut eof_writer)
-                .unwrap();
-            assert_eq!(rdlen, 0);
-            assert_eq!(wrlen, 0);
-        }
-    }
+    check_read(&mut client.reader(), b"01234567890123456789012345678901");
 }
 
-#[derive(Default)]
-struct EofWriter<const N: usize> {
-    written: usize,
-}
+#[test]
+fn server_respects_buffer_limit_post_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-impl<const N: usize> std::io::Write for EofWriter<N> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let prev = self.written;
-        self.written = N.min(self.written + buf.len());
-        Ok(self.written - prev)
-    }
+    // this test will vary in behaviour depending on the default suites
+    do_handshake(&mut client, &mut server);
+    server.set_buffer_limit(Some(48));
 
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
-    }
-}
+    assert_eq!(
+        server
+            .writer()
+            .write(b"01234567890123456789")
+            .unwrap(),
+        20
+    );
+    assert_eq!(
+        server
+            .writer()
+            .write(b"01234567890123456789")
+            .unwrap(),
+        6
+    );
 
-impl<const N: usize> std::io::Read for EofWriter<N> {
-    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
-        panic!() // This is a writer, it should not be read from.
-    }
+    transfer(&mut server, &mut client);
+    client.process_new_packets().unwrap();
+
+    check_read(&mut client.reader(), b"01234567890123456789012345");
 }
 
 #[test]
-fn server_complete_io_for_read() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
+fn client_respects_buffer_limit_pre_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-        do_handshake(&mut client, &mut server);
+    client.set_buffer_limit(Some(32));
 
+    assert_eq!(
         client
             .writer()
-            .write_all(b"01234567890123456789")
-            .unwrap();
-        {
-            let mut pipe = OtherSession::new(&mut client);
-            let (rdlen, wrlen) = server.complete_io(&mut pipe).unwrap();
-            assert!(rdlen > 0 && wrlen == 0);
-            assert_eq!(pipe.reads, 1);
-        }
-        check_read(&mut server.reader(), b"01234567890123456789");
-    }
-}
+            .write(b"01234567890123456789")
+            .unwrap(),
+        20
+    );
+    assert_eq!(
+        client
+            .writer()
+            .write(b"01234567890123456789")
+            .unwrap(),
+        12
+    );
 
-#[test]
-fn client_stream_write() {
-    test_client_stream_write(StreamKind::Ref);
-    test_client_stream_write(StreamKind::Owned);
+    do_handshake(&mut client, &mut server);
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+
+    check_read(&mut server.reader(), b"01234567890123456789012345678901");
 }
 
 #[test]
-fn server_stream_write() {
-    test_server_stream_write(StreamKind::Ref);
-    test_server_stream_write(StreamKind::Owned);
-}
+fn client_respects_buffer_limit_pre_handshake_with_vectored_write() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-#[derive(Debug, Copy, Clone)]
-enum StreamKind {
-    Owned,
-    Ref,
-}
+    client.set_buffer_limit(Some(32));
 
-fn test_client_stream_write(stream_kind: StreamKind) {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
-        let data = b"hello";
-        {
-            let mut pipe = OtherSession::new(&mut server);
-            let mut stream: Box<dyn Write> = match stream_kind {
-                StreamKind::Ref => Box::new(Stream::new(&mut client, &mut pipe)),
-                StreamKind::Owned => Box::new(StreamOwned::new(client, pipe)),
-            };
-            assert_eq!(stream.write(data).unwrap(), 5);
-        }
-        check_read(&mut server.reader(), data);
-    }
-}
+    assert_eq!(
+        client
+            .writer()
+            .write_vectored(&[
+                IoSlice::new(b"01234567890123456789"),
+                IoSlice::new(b"01234567890123456789")
+            ])
+            .unwrap(),
+        32
+    );
 
-fn test_server_stream_write(stream_kind: StreamKind) {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
-        let data = b"hello";
-        {
-            let mut pipe = OtherSession::new(&mut client);
-            let mut stream: Box<dyn Write> = match stream_kind {
-                StreamKind::Ref => Box::new(Stream::new(&mut server, &mut pipe)),
-                StreamKind::Owned => Box::new(StreamOwned::new(server, pipe)),
-            };
-            assert_eq!(stream.write(data).unwrap(), 5);
-        }
-        check_read(&mut client.reader(), data);
-    }
-}
+    do_handshake(&mut client, &mut server);
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
 
-#[test]
-fn client_stream_read() {
-    test_client_stream_read(StreamKind::Ref, ReadKind::Buf);
-    test_client_stream_read(StreamKind::Owned, ReadKind::Buf);
-    test_client_stream_read(StreamKind::Ref, ReadKind::BufRead);
-    test_client_stream_read(StreamKind::Owned, ReadKind::BufRead);
+    check_read(&mut server.reader(), b"01234567890123456789012345678901");
 }
 
 #[test]
-fn server_stream_read() {
-    test_server_stream_read(StreamKind::Ref, ReadKind::Buf);
-    test_server_stream_read(StreamKind::Owned, ReadKind::Buf);
-    test_server_stream_read(StreamKind::Ref, ReadKind::BufRead);
-    test_server_stream_read(StreamKind::Owned, ReadKind::BufRead);
-}
+fn client_respects_buffer_limit_post_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-#[derive(Debug, Copy, Clone)]
-enum ReadKind {
-    Buf,
-    BufRead,
-}
+    do_handshake(&mut client, &mut server);
+    client.set_buffer_limit(Some(48));
 
-fn test_stream_read(read_kind: ReadKind, mut stream: impl BufRead, data: &[u8]) {
-    match read_kind {
-        ReadKind::Buf => {
-            check_read(&mut stream, data);
-            check_read_err(&mut stream, io::ErrorKind::UnexpectedEof)
-        }
-        ReadKind::BufRead => {
-            check_fill_buf(&mut stream, data);
-            check_fill_buf_err(&mut stream, io::ErrorKind::UnexpectedEof)
-        }
-    }
+    assert_eq!(
+        client
+            .writer()
+            .write(b"01234567890123456789")
+            .unwrap(),
+        20
+    );
+    assert_eq!(
+        client
+            .writer()
+            .write(b"01234567890123456789")
+            .unwrap(),
+        6
+    );
+
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+
+    check_read(&mut server.reader(), b"01234567890123456789012345");
 }
 
-fn test_client_stream_read(stream_kind: StreamKind, read_kind: ReadKind) {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
-        let data = b"world";
-        server.writer().write_all(data).unwrap();
+#[test]
+fn client_detects_broken_write_vectored_impl() {
+    // see https://github.com/rustls/rustls/issues/2316
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    let err = client
+        .write_tls(&mut BrokenWriteVectored)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(format!("{err:?}").starts_with(
+        "Custom { kind: Other, error: \"illegal write_vectored return value (9999 > "
+    ));
 
-        {
-            let mut pipe = OtherSession::new(&mut server);
-            transfer_eof(&mut client);
+    struct BrokenWriteVectored;
 
-            let stream: Box<dyn BufRead> = match stream_kind {
-                StreamKind::Ref => Box::new(Stream::new(&mut client, &mut pipe)),
-                StreamKind::Owned => Box::new(StreamOwned::new(client, pipe)),
-            };
+    impl io::Write for BrokenWriteVectored {
+        fn write_vectored(&mut self, _bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            Ok(9999)
+        }
 
-            test_stream_read(read_kind, stream, data)
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            unreachable!()
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            unreachable!()
         }
     }
 }
 
-fn test_server_stream_read(stream_kind: StreamKind, read_kind: ReadKind) {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let (mut client, mut server) = make_pair(*kt, &provider);
-        let data = b"world";
-        client.writer().write_all(data).unwrap();
+#[test]
+fn buf_read() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-        {
-            let mut pipe = OtherSession::new(&mut client);
-            transfer_eof(&mut server);
+    do_handshake(&mut client, &mut server);
 
-            let stream: Box<dyn BufRead> = match stream_kind {
-                StreamKind::Ref => Box::new(Stream::new(&mut server, &mut pipe)),
-                StreamKind::Owned => Box::new(StreamOwned::new(server, pipe)),
-            };
+    // Write two separate messages
+    assert_eq!(client.writer().write(b"hello").unwrap(), 5);
+    transfer(&mut client, &mut server);
+    assert_eq!(client.writer().write(b"world").unwrap(), 5);
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
 
-            test_stream_read(read_kind, stream, data)
+    let mut reader = server.reader();
+    // fill_buf() returns each record separately (this is an implementation detail)
+    assert_eq!(reader.fill_buf().unwrap(), b"hello");
+    // partially consuming the buffer is OK
+    reader.consume(1);
+    assert_eq!(reader.fill_buf().unwrap(), b"ello");
+    // Read::read is compatible with BufRead
+    let mut b = [0u8; 2];
+    reader.read_exact(&mut b).unwrap();
+    assert_eq!(b, *b"el");
+    assert_eq!(reader.fill_buf().unwrap(), b"lo");
+    reader.consume(2);
+    // once the first packet is consumed, the next one is available
+    assert_eq!(reader.fill_buf().unwrap(), b"world");
+    reader.consume(5);
+    check_fill_buf_err(&mut reader, io::ErrorKind::WouldBlock);
+}
+
+struct OtherSession<'a, C, S>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    sess: &'a mut C,
+    pub reads: usize,
+    pub writevs: Vec<Vec<usize>>,
+    fail_ok: bool,
+    pub short_writes: bool,
+    pub last_error: Option<rustls::Error>,
+    pub buffered: bool,
+    buffer: Vec<Vec<u8>>,
+}
+
+impl<'a, C, S> OtherSession<'a, C, S>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    fn new(sess: &'a mut C) -> OtherSession<'a, C, S> {
+        OtherSession {
+            sess,
+            reads: 0,
+            writevs: vec![],
+            fail_ok: false,
+            short_writes: false,
+            last_error: None,
+            buffered: false,
+            buffer: vec![],
         }
     }
-}
 
-#[test]
-fn test_client_write_and_vectored_write_equivalence() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    do_handshake(&mut client, &mut server);
+    fn new_buffered(sess: &'a mut C) -> OtherSession<'a, C, S> {
+        let mut os = OtherSession::new(sess);
+        os.buffered = true;
+        os
+    }
 
-    const N: usize = 1000;
+    fn new_fails(sess: &'a mut C) -> OtherSession<'a, C, S> {
+        let mut os = OtherSession::new(sess);
+        os.fail_ok = true;
+        os
+    }
 
-    let data_chunked: Vec<IoSlice> = std::iter::repeat(IoSlice::new(b"A"))
-        .take(N)
-        .collect();
-    let bytes_written_chunked = client
-        .writer()
-        .write_vectored(&data_chunked)
-        .unwrap();
-    let bytes_sent_chunked = transfer(&mut client, &mut server);
-    println!("write_vectored returned {bytes_written_chunked} and sent {bytes_sent_chunked}");
+    fn flush_vectored(&mut self, b: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let mut total = 0;
+        let mut lengths = vec![];
+        for bytes in b {
+            let write_len = if self.short_writes {
+                if bytes.len() > 5 {
+                    bytes.len() / 2
+                } else {
+                    bytes.len()
+                }
+            } else {
+                bytes.len()
+            };
 
-    let data_contiguous = &[b'A'; N];
-    let bytes_written_contiguous = client
-        .writer()
-        .write(data_contiguous)
-        .unwrap();
-    let bytes_sent_contiguous = transfer(&mut client, &mut server);
-    println!("write returned {bytes_written_contiguous} and sent {bytes_sent_contiguous}");
+            let l = self
+                .sess
+                .read_tls(&mut io::Cursor::new(&bytes[..write_len]))?;
+            lengths.push(l);
+            total += l;
+            if bytes.len() != l {
+                break;
+            }
+        }
 
-    assert_eq!(bytes_written_chunked, bytes_written_contiguous);
-    assert_eq!(bytes_sent_chunked, bytes_sent_contiguous);
-}
+        let rc = self.sess.process_new_packets();
+        if !self.fail_ok {
+            rc.unwrap();
+        } else if rc.is_err() {
+            self.last_error = rc.err();
+        }
 
-struct FailsWrites {
-    errkind: io::ErrorKind,
-    after: usize,
+        self.writevs.push(lengths);
+        Ok(total)
+    }
 }
 
-impl io::Read for FailsWrites {
-    fn read(&mut self, _b: &mut [u8]) -> io::Result<usize> {
-        Ok(0)
+impl<C, S> io::Read for OtherSession<'_, C, S>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    fn read(&mut self, mut b: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        self.sess.write_tls(b.by_ref())
     }
 }
 
-impl io::Write for FailsWrites {
-    fn write(&mut self, b: &[u8]) -> io::Result<usize> {
-        if self.after > 0 {
-            self.after -= 1;
-            Ok(b.len())
-        } else {
-            Err(io::Error::new(self.errkind, "oops"))
-        }
+impl<C, S> io::Write for OtherSession<'_, C, S>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        unreachable!()
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let buffer = mem::take(&mut self.buffer);
+            let slices = buffer
+                .iter()
+                .map(|b| io::IoSlice::new(b))
+                .collect::<Vec<_>>();
+            self.flush_vectored(&slices)?;
+        }
         Ok(())
     }
+
+    fn write_vectored(&mut self, b: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        if self.buffered {
+            self.buffer
+                .extend(b.iter().map(|s| s.to_vec()));
+            return Ok(b.iter().map(|s| s.len()).sum());
+        }
+        self.flush_vectored(b)
+    }
 }
 
 #[test]
-fn stream_write_reports_underlying_io_error_before_plaintext_processed() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    do_handshake(&mut client, &mut server);
-
-    let mut pipe = FailsWrites {
-        errkind: io::ErrorKind::ConnectionAborted,
-        after: 0,
-    };
-    client
-        .writer()
-        .write_all(b"hello")
-        .unwrap();
-    let mut client_stream = Stream::new(&mut client, &mut pipe);
-    let rc = client_stream.write(b"world");
-    assert!(rc.is_err());
-    let err = rc.err().unwrap();
-    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+fn server_read_returns_wouldblock_when_no_data() {
+    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(matches!(server.reader().read(&mut [0u8; 1]),
+                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
 }
 
 #[test]
-fn stream_write_swallows_underlying_io_error_after_plaintext_processed() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    do_handshake(&mut client, &mut server);
-
-    let mut pipe = FailsWrites {
-        errkind: io::ErrorKind::ConnectionAborted,
-        after: 1,
-    };
-    client
-        .writer()
-        .write_all(b"hello")
-        .unwrap();
-    let mut client_stream = Stream::new(&mut client, &mut pipe);
-    let rc = client_stream.write(b"world");
-    assert_eq!(format!("{rc:?}"), "Ok(5)");
+fn client_read_returns_wouldblock_when_no_data() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(matches!(client.reader().read(&mut [0u8; 1]),
+                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
 }
 
-fn make_disjoint_suite_configs() -> (ClientConfig, ServerConfig) {
-    let kt = KeyType::Rsa2048;
-    let client_provider = CryptoProvider {
-        cipher_suites: vec![cipher_suite::TLS13_CHACHA20_POLY1305_SHA256],
-        ..provider::default_provider()
-    };
-    let server_config = finish_server_config(
-        kt,
-        ServerConfig::builder_with_provider(client_provider.into())
-            .with_safe_default_protocol_versions()
-            .unwrap(),
-    );
+#[test]
+fn server_fill_buf_returns_wouldblock_when_no_data() {
+    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(matches!(server.reader().fill_buf(),
+                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
+}
 
-    let server_provider = CryptoProvider {
-        cipher_suites: vec![cipher_suite::TLS13_AES_256_GCM_SHA384],
-        ..provider::default_provider()
-    };
-    let client_config = finish_client_config(
-        kt,
-        ClientConfig::builder_with_provider(server_provider.into())
-            .with_safe_default_protocol_versions()
-            .unwrap(),
-    );
+#[test]
+fn client_fill_buf_returns_wouldblock_when_no_data() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(matches!(client.reader().fill_buf(),
+                     Err(err) if err.kind() == io::ErrorKind::WouldBlock));
+}
 
-    (client_config, server_config)
+#[test]
+fn new_server_returns_initial_io_state() {
+    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    let io_state = server.process_new_packets().unwrap();
+    println!("IoState is Debug {io_state:?}");
+    assert_eq!(io_state.plaintext_bytes_to_read(), 0);
+    assert!(!io_state.peer_has_closed());
+    assert_eq!(io_state.tls_bytes_to_write(), 0);
 }
 
 #[test]
-fn client_stream_handshake_error() {
-    let (client_config, server_config) = make_disjoint_suite_configs();
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+fn new_client_returns_initial_io_state() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    let io_state = client.process_new_packets().unwrap();
+    println!("IoState is Debug {io_state:?}");
+    assert_eq!(io_state.plaintext_bytes_to_read(), 0);
+    assert!(!io_state.peer_has_closed());
+    assert!(io_state.tls_bytes_to_write() > 200);
+}
 
-    {
-        let mut pipe = OtherSession::new_fails(&mut server);
-        let mut client_stream = Stream::new(&mut client, &mut pipe);
-        let rc = client_stream.write(b"hello");
-        assert!(rc.is_err());
-        assert_eq!(
-            format!("{rc:?}"),
-            "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
-        );
-        let rc = client_stream.write(b"hello");
-        assert!(rc.is_err());
-        assert_eq!(
-            format!("{rc:?}"),
-            "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
-        );
-    }
+#[test]
+fn client_complete_io_for_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+
+    assert!(client.is_handshaking());
+    let (rdlen, wrlen) = client
+        .complete_io(&mut OtherSession::new(&mut server))
+        .unwrap();
+    assert!(rdlen > 0 && wrlen > 0);
+    assert!(!client.is_handshaking());
+    assert!(!client.wants_write());
 }
 
 #[test]
-fn client_streamowned_handshake_error() {
-    let (client_config, server_config) = make_disjoint_suite_configs();
-    let (client, mut server) = make_pair_for_configs(client_config, server_config);
+fn buffered_client_complete_io_for_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-    let pipe = OtherSession::new_fails(&mut server);
-    let mut client_stream = StreamOwned::new(client, pipe);
-    let rc = client_stream.write(b"hello");
-    assert!(rc.is_err());
-    assert_eq!(
-        format!("{rc:?}"),
-        "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
-    );
-    let rc = client_stream.write(b"hello");
-    assert!(rc.is_err());
-    assert_eq!(
-        format!("{rc:?}"),
-        "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
-    );
+    assert!(client.is_handshaking());
+    let (rdlen, wrlen) = client
+        .complete_io(&mut OtherSession::new_buffered(&mut server))
+        .unwrap();
+    assert!(rdlen > 0 && wrlen > 0);
+    assert!(!client.is_handshaking());
+    assert!(!client.wants_write());
+}
 
-    let (_, _) = client_stream.into_parts();
+#[test]
+fn client_complete_io_for_handshake_eof() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    let mut input = io::Cursor::new(Vec::new());
+
+    assert!(client.is_handshaking());
+    let err = client
+        .complete_io(&mut input)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
 }
 
 #[test]
-fn server_stream_handshake_error() {
-    let (client_config, server_config) = make_disjoint_suite_configs();
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+fn client_complete_io_for_write() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
 
-    client
-        .writer()
-        .write_all(b"world")
-        .unwrap();
+        do_handshake(&mut client, &mut server);
 
-    {
-        let mut pipe = OtherSession::new_fails(&mut client);
-        let mut server_stream = Stream::new(&mut server, &mut pipe);
-        let mut bytes = [0u8; 5];
-        let rc = server_stream.read(&mut bytes);
-        assert!(rc.is_err());
-        assert_eq!(
-            format!("{rc:?}"),
-            "Err(Custom { kind: InvalidData, error: PeerIncompatible(NoCipherSuitesInCommon) })"
+        client
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        client
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        {
+            let mut pipe = OtherSession::new(&mut server);
+            let (rdlen, wrlen) = client.complete_io(&mut pipe).unwrap();
+            assert!(rdlen == 0 && wrlen > 0);
+            println!("{:?}", pipe.writevs);
+            assert_eq!(pipe.writevs, vec![vec![42, 42]]);
+        }
+        check_read(
+            &mut server.reader(),
+            b"0123456789012345678901234567890123456789",
         );
     }
 }
 
 #[test]
-fn server_streamowned_handshake_error() {
-    let (client_config, server_config) = make_disjoint_suite_configs();
-    let (mut client, server) = make_pair_for_configs(client_config, server_config);
+fn client_complete_io_with_nonblocking_io() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
 
-    client
-        .writer()
-        .write_all(b"world")
-        .unwrap();
+    // absolutely no progress writing ClientHello
+    assert_eq!(
+        client
+            .complete_io(&mut TestNonBlockIo::default())
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::WouldBlock
+    );
 
-    let pipe = OtherSession::new_fails(&mut client);
-    let mut server_stream = StreamOwned::new(server, pipe);
-    let mut bytes = [0u8; 5];
-    let rc = server_stream.read(&mut bytes);
-    assert!(rc.is_err());
+    // a little progress writing ClientHello
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
     assert_eq!(
-        format!("{rc:?}"),
-        "Err(Custom { kind: InvalidData, error: PeerIncompatible(NoCipherSuitesInCommon) })"
+        client
+            .complete_io(&mut TestNonBlockIo {
+                writes: vec![1],
+                reads: vec![],
+            })
+            .unwrap(),
+        (0, 1)
     );
-}
 
-#[test]
-fn server_config_is_clone() {
-    let _ = make_server_config(KeyType::Rsa2048, &provider::default_provider());
-}
+    // complete writing ClientHello
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert_eq!(
+        client
+            .complete_io(&mut TestNonBlockIo {
+                writes: vec![4096],
+                reads: vec![],
+            })
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::WouldBlock
+    );
 
-#[test]
-fn client_config_is_clone() {
-    let _ = make_client_config(KeyType::Rsa2048, &provider::default_provider());
-}
+    // complete writing ClientHello, partial read of ServerHello
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    let (rd, wr) = dbg!(client.complete_io(&mut TestNonBlockIo {
+        writes: vec![4096],
+        reads: vec![vec![ContentType::Handshake.into()]],
+    }))
+    .unwrap();
+    assert_eq!(rd, 1);
+    assert!(wr > 1);
 
-#[test]
-fn client_connection_is_debug() {
-    let (client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    println!("{client:?}");
-}
+    // data phase:
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    do_handshake(&mut client, &mut server);
 
-#[test]
-fn server_connection_is_debug() {
-    let (_, server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    println!("{server:?}");
-}
+    // read
+    assert_eq!(
+        client
+            .complete_io(&mut TestNonBlockIo {
+                reads: vec![vec![ContentType::ApplicationData.into()]],
+                writes: vec![],
+            })
+            .unwrap(),
+        (1, 0)
+    );
 
-#[test]
-fn server_complete_io_for_handshake_ending_with_alert() {
-    let (client_config, server_config) = make_disjoint_suite_configs();
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    // write
+    client
+        .writer()
+        .write_all(b"hello")
+        .unwrap();
 
-    assert!(server.is_handshaking());
+    // no progress
+    assert_eq!(
+        client
+            .complete_io(&mut TestNonBlockIo {
+                reads: vec![],
+                writes: vec![],
+            })
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::WouldBlock
+    );
 
-    let mut pipe = OtherSession::new_fails(&mut client);
-    let rc = server.complete_io(&mut pipe);
-    assert!(rc.is_err(), "server io failed due to handshake failure");
-    assert!(!server.wants_write(), "but server did send its alert");
+    // some write progress
     assert_eq!(
-        format!("{:?}", pipe.last_error),
-        "Some(AlertReceived(HandshakeFailure))",
-        "which was received by client"
+        client
+            .complete_io(&mut TestNonBlockIo {
+                reads: vec![],
+                writes: vec![1],
+            })
+            .unwrap(),
+        (0, 1)
     );
 }
 
 #[test]
-fn server_exposes_offered_sni() {
-    let kt = KeyType::Rsa2048;
+fn buffered_client_complete_io_for_write() {
     let provider = provider::default_provider();
-    for version in rustls::ALL_VERSIONS {
-        let client_config = make_client_config_with_versions(kt, &[version], &provider);
-        let mut client = ClientConnection::new(
-            Arc::new(client_config),
-            server_name("second.testserver.com"),
-        )
-        .unwrap();
-        let mut server =
-            ServerConnection::new(Arc::new(make_server_config(kt, &provider))).unwrap();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
 
-        assert_eq!(None, server.server_name());
         do_handshake(&mut client, &mut server);
-        assert_eq!(
-            Some(&DnsName::try_from("second.testserver.com").unwrap()),
-            server.server_name()
-        );
-    }
-}
+
+        client
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        client
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        {
+            let mut pipe = OtherSession::new_buffered(&mut server);
+            let (rdlen, wrlen) = client.complete_io(&mut pipe).unwrap();
+            assert!(rdlen == 0 && wrlen > 0);
+            println!("{:?}", pipe.writevs);
+            assert_eq!(pipe.writevs, vec![vec![42, 42]]);
+        }
+        check_read(
+            &mut server.reader(),
+            b"0123456789012345678901234567890123456789",
+        );
+    }
+}
 
 #[test]
-fn server_exposes_offered_sni_smashed_to_lowercase() {
-    // webpki actually does this for us in its DnsName type
-    let kt = KeyType::Rsa2048;
+fn client_complete_io_for_read() {
     let provider = provider::default_provider();
-    for version in rustls::ALL_VERSIONS {
-        let client_config = make_client_config_with_versions(kt, &[version], &provider);
-        let mut client = ClientConnection::new(
-            Arc::new(client_config),
-            server_name("SECOND.TESTServer.com"),
-        )
-        .unwrap();
-        let mut server =
-            ServerConnection::new(Arc::new(make_server_config(kt, &provider))).unwrap();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
 
-        assert_eq!(None, server.server_name());
         do_handshake(&mut client, &mut server);
-        assert_eq!(
-            Some(&DnsName::try_from("second.testserver.com").unwrap()),
-            server.server_name()
-        );
+
+        server
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        {
+            let mut pipe = OtherSession::new(&mut server);
+            let (rdlen, wrlen) = client.complete_io(&mut pipe).unwrap();
+            assert!(rdlen > 0 && wrlen == 0);
+            assert_eq!(pipe.reads, 1);
+        }
+        check_read(&mut client.reader(), b"01234567890123456789");
     }
 }
 
 #[test]
-fn server_exposes_offered_sni_even_if_resolver_fails() {
-    let kt = KeyType::Rsa2048;
+fn server_complete_io_for_handshake() {
     let provider = provider::default_provider();
-    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
 
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.cert_resolver = Arc::new(resolver);
-    let server_config = Arc::new(server_config);
+        assert!(server.is_handshaking());
+        let (rdlen, wrlen) = server
+            .complete_io(&mut OtherSession::new(&mut client))
+            .unwrap();
+        assert!(rdlen > 0 && wrlen > 0);
+        assert!(!server.is_handshaking());
+        assert!(!server.wants_write());
+    }
+}
 
-    for version in rustls::ALL_VERSIONS {
-        let client_config = make_client_config_with_versions(kt, &[version], &provider);
-        let mut server = ServerConnection::new(server_config.clone()).unwrap();
-        let mut client =
-            ClientConnection::new(Arc::new(client_config), server_name("thisdoesNOTexist.com"))
-                .unwrap();
+#[test]
+fn server_complete_io_for_handshake_eof() {
+    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    let mut input = io::Cursor::new(Vec::new());
 
-        assert_eq!(None, server.server_name());
-        transfer(&mut client, &mut server);
-        assert_eq!(
-            server.process_new_packets(),
-            Err(Error::General(
-                "no server certificate chain resolved".to_string()
-            ))
-        );
-        assert_eq!(
-            Some(&DnsName::try_from("thisdoesnotexist.com").unwrap()),
-            server.server_name()
+    assert!(server.is_handshaking());
+    let err = server
+        .complete_io(&mut input)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+}
+
+#[test]
+fn server_complete_io_for_write() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
+
+        do_handshake(&mut client, &mut server);
+
+        server
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        server
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        {
+            let mut pipe = OtherSession::new(&mut client);
+            let (rdlen, wrlen) = server.complete_io(&mut pipe).unwrap();
+            assert!(rdlen == 0 && wrlen > 0);
+            assert_eq!(pipe.writevs, vec![vec![42, 42]]);
+        }
+        check_read(
+            &mut client.reader(),
+            b"0123456789012345678901234567890123456789",
         );
     }
 }
 
 #[test]
-fn sni_resolver_works() {
-    let kt = KeyType::Rsa2048;
+fn server_complete_io_for_write_eof() {
     let provider = provider::default_provider();
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
-    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
-    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
-    resolver
-        .add(
-            DnsName::try_from("localhost").unwrap(),
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match"),
-        )
-        .unwrap();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
 
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.cert_resolver = Arc::new(resolver);
-    let server_config = Arc::new(server_config);
+        do_handshake(&mut client, &mut server);
 
-    let mut server1 = ServerConnection::new(server_config.clone()).unwrap();
-    let mut client1 = ClientConnection::new(
-        Arc::new(make_client_config(kt, &provider)),
-        server_name("localhost"),
-    )
-    .unwrap();
-    let err = do_handshake_until_error(&mut client1, &mut server1);
-    assert_eq!(err, Ok(()));
+        // Queue 20 bytes to write.
+        server
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        {
+            const BYTES_BEFORE_EOF: usize = 5;
+            let mut eof_writer = EofWriter::<BYTES_BEFORE_EOF>::default();
 
-    let mut server2 = ServerConnection::new(server_config.clone()).unwrap();
-    let mut client2 = ClientConnection::new(
-        Arc::new(make_client_config(kt, &provider)),
-        server_name("notlocalhost"),
-    )
-    .unwrap();
-    let err = do_handshake_until_error(&mut client2, &mut server2);
-    assert_eq!(
-        err,
-        Err(ErrorFromPeer::Server(Error::General(
-            "no server certificate chain resolved".into()
-        )))
-    );
+            // Only BYTES_BEFORE_EOF should be written.
+            let (rdlen, wrlen) = server
+                .complete_io(&mut eof_writer)
+                .unwrap();
+            assert_eq!(rdlen, 0);
+            assert_eq!(wrlen, BYTES_BEFORE_EOF);
+
+            // Now nothing should be written.
+            let (rdlen, wrlen) = server
+                .complete_io(&mut eof_writer)
+                .unwrap();
+            assert_eq!(rdlen, 0);
+            assert_eq!(wrlen, 0);
+        }
+    }
 }
 
-#[test]
-fn sni_resolver_rejects_wrong_names() {
-    let kt = KeyType::Rsa2048;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
-    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
-    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
+#[derive(Default)]
+struct EofWriter<const N: usize> {
+    written: usize,
+}
 
-    assert_eq!(
-        Ok(()),
-        resolver.add(
-            DnsName::try_from("localhost").unwrap(),
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
-        )
-    );
-    assert_eq!(
-        Err(Error::InvalidCertificate(certificate_error_expecting_name(
-            "not-localhost"
-        ))),
-        resolver.add(
-            DnsName::try_from("not-localhost").unwrap(),
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
-        )
-    );
+impl<const N: usize> std::io::Write for EofWriter<N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let prev = self.written;
+        self.written = N.min(self.written + buf.len());
+        Ok(self.written - prev)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
-fn certificate_error_expecting_name(expected: &str) -> CertificateError {
-    CertificateError::NotValidForNameContext {
-        expected: ServerName::try_from(expected)
-            .unwrap()
-            .to_owned(),
-        presented: vec![
-            // ref. examples/internal/test_ca.rs
-            r#"DnsName("testserver.com")"#.into(),
-            r#"DnsName("second.testserver.com")"#.into(),
-            r#"DnsName("localhost")"#.into(),
-            "IpAddress(198.51.100.1)".into(),
-            "IpAddress(2001:db8::1)".into(),
-        ],
+impl<const N: usize> std::io::Read for EofWriter<N> {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        panic!() // This is a writer, it should not be read from.
     }
 }
 
 #[test]
-fn sni_resolver_lower_cases_configured_names() {
-    let kt = KeyType::Rsa2048;
+fn server_complete_io_for_read() {
     let provider = provider::default_provider();
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
-    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
-    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
 
-    assert_eq!(
-        Ok(()),
-        resolver.add(
-            DnsName::try_from("LOCALHOST").unwrap(),
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
-        )
-    );
+        do_handshake(&mut client, &mut server);
 
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.cert_resolver = Arc::new(resolver);
-    let server_config = Arc::new(server_config);
+        client
+            .writer()
+            .write_all(b"01234567890123456789")
+            .unwrap();
+        {
+            let mut pipe = OtherSession::new(&mut client);
+            let (rdlen, wrlen) = server.complete_io(&mut pipe).unwrap();
+            assert!(rdlen > 0 && wrlen == 0);
+            assert_eq!(pipe.reads, 1);
+        }
+        check_read(&mut server.reader(), b"01234567890123456789");
+    }
+}
 
-    let mut server1 = ServerConnection::new(server_config.clone()).unwrap();
-    let mut client1 = ClientConnection::new(
-        Arc::new(make_client_config(kt, &provider)),
-        server_name("localhost"),
-    )
-    .unwrap();
-    let err = do_handshake_until_error(&mut client1, &mut server1);
-    assert_eq!(err, Ok(()));
+#[test]
+fn client_stream_write() {
+    test_client_stream_write(StreamKind::Ref);
+    test_client_stream_write(StreamKind::Owned);
 }
 
 #[test]
-fn sni_resolver_lower_cases_queried_names() {
-    // actually, the handshake parser does this, but the effect is the same.
-    let kt = KeyType::Rsa2048;
-    let provider = provider::default_provider();
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
-    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
-    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
+fn server_stream_write() {
+    test_server_stream_write(StreamKind::Ref);
+    test_server_stream_write(StreamKind::Owned);
+}
 
-    assert_eq!(
-        Ok(()),
-        resolver.add(
-            DnsName::try_from("localhost").unwrap(),
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
-        )
-    );
+#[derive(Debug, Copy, Clone)]
+enum StreamKind {
+    Owned,
+    Ref,
+}
 
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.cert_resolver = Arc::new(resolver);
-    let server_config = Arc::new(server_config);
+fn test_client_stream_write(stream_kind: StreamKind) {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
+        let data = b"hello";
+        {
+            let mut pipe = OtherSession::new(&mut server);
+            let mut stream: Box<dyn Write> = match stream_kind {
+                StreamKind::Ref => Box::new(Stream::new(&mut client, &mut pipe)),
+                StreamKind::Owned => Box::new(StreamOwned::new(client, pipe)),
+            };
+            assert_eq!(stream.write(data).unwrap(), 5);
+        }
+        check_read(&mut server.reader(), data);
+    }
+}
 
-    let mut server1 = ServerConnection::new(server_config.clone()).unwrap();
-    let mut client1 = ClientConnection::new(
-        Arc::new(make_client_config(kt, &provider)),
-        server_name("LOCALHOST"),
-    )
-    .unwrap();
-    let err = do_handshake_until_error(&mut client1, &mut server1);
-    assert_eq!(err, Ok(()));
+fn test_server_stream_write(stream_kind: StreamKind) {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
+        let data = b"hello";
+        {
+            let mut pipe = OtherSession::new(&mut client);
+            let mut stream: Box<dyn Write> = match stream_kind {
+                StreamKind::Ref => Box::new(Stream::new(&mut server, &mut pipe)),
+                StreamKind::Owned => Box::new(StreamOwned::new(server, pipe)),
+            };
+            assert_eq!(stream.write(data).unwrap(), 5);
+        }
+        check_read(&mut client.reader(), data);
+    }
 }
 
 #[test]
-fn sni_resolver_rejects_bad_certs() {
-    let kt = KeyType::Rsa2048;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
-    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
-    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
-
-    assert_eq!(
-        Err(Error::NoCertificatesPresented),
-        resolver.add(
-            DnsName::try_from("localhost").unwrap(),
-            sign::CertifiedKey::new_unchecked(vec![], signing_key.clone())
-        )
-    );
-
-    let bad_chain = vec![CertificateDer::from(vec![0xa0])];
-    assert_eq!(
-        Err(Error::InvalidCertificate(CertificateError::BadEncoding)),
-        resolver.add(
-            DnsName::try_from("localhost").unwrap(),
-            sign::CertifiedKey::new_unchecked(bad_chain, signing_key.clone())
-        )
-    );
+fn client_stream_read() {
+    test_client_stream_read(StreamKind::Ref, ReadKind::Buf);
+    test_client_stream_read(StreamKind::Owned, ReadKind::Buf);
+    test_client_stream_read(StreamKind::Ref, ReadKind::BufRead);
+    test_client_stream_read(StreamKind::Owned, ReadKind::BufRead);
 }
 
 #[test]
-fn test_keys_match() {
-    // Consistent: Both of these should have the same SPKI values
-    let expect_consistent =
-        sign::CertifiedKey::new(KeyType::Rsa2048.get_chain(), Arc::new(SigningKeySomeSpki));
-    assert!(expect_consistent.is_ok());
+fn server_stream_read() {
+    test_server_stream_read(StreamKind::Ref, ReadKind::Buf);
+    test_server_stream_read(StreamKind::Owned, ReadKind::Buf);
+    test_server_stream_read(StreamKind::Ref, ReadKind::BufRead);
+    test_server_stream_read(StreamKind::Owned, ReadKind::BufRead);
+}
 
-    // Inconsistent: These should not have the same SPKI values
-    let expect_inconsistent =
-        sign::CertifiedKey::new(KeyType::EcdsaP256.get_chain(), Arc::new(SigningKeySomeSpki));
-    assert!(matches!(
-        expect_inconsistent,
-        Err(Error::InconsistentKeys(InconsistentKeys::KeyMismatch))
-    ));
+#[derive(Debug, Copy, Clone)]
+enum ReadKind {
+    Buf,
+    BufRead,
+}
 
-    // Unknown: This signing key returns None for its SPKI, so we can't tell if the certified key is consistent
-    assert!(matches!(
-        sign::CertifiedKey::new(KeyType::Rsa2048.get_chain(), Arc::new(SigningKeyNoneSpki)),
-        Err(Error::InconsistentKeys(InconsistentKeys::Unknown))
-    ));
+fn test_stream_read(read_kind: ReadKind, mut stream: impl BufRead, data: &[u8]) {
+    match read_kind {
+        ReadKind::Buf => {
+            check_read(&mut stream, data);
+            check_read_err(&mut stream, io::ErrorKind::UnexpectedEof)
+        }
+        ReadKind::BufRead => {
+            check_fill_buf(&mut stream, data);
+            check_fill_buf_err(&mut stream, io::ErrorKind::UnexpectedEof)
+        }
+    }
 }
 
-/// Represents a SigningKey that returns None for its SPKI via the default impl.
-#[derive(Debug)]
-struct SigningKeyNoneSpki;
+fn test_client_stream_read(stream_kind: StreamKind, read_kind: ReadKind) {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
+        let data = b"world";
+        server.writer().write_all(data).unwrap();
 
-impl sign::SigningKey for SigningKeyNoneSpki {
-    fn choose_scheme(&self, _offered: &[SignatureScheme]) -> Option<Box<dyn sign::Signer>> {
-        unimplemented!("Not meant to be called during tests")
-    }
+        {
+            let mut pipe = OtherSession::new(&mut server);
+            transfer_eof(&mut client);
 
-    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
-        None
-    }
+            let stream: Box<dyn BufRead> = match stream_kind {
+                StreamKind::Ref => Box::new(Stream::new(&mut client, &mut pipe)),
+                StreamKind::Owned => Box::new(StreamOwned::new(client, pipe)),
+            };
 
-    fn algorithm(&self) -> rustls::SignatureAlgorithm {
-        unimplemented!("Not meant to be called during tests")
+            test_stream_read(read_kind, stream, data)
+        }
     }
 }
 
-/// Represents a SigningKey that returns Some for its SPKI.
-#[derive(Debug)]
-struct SigningKeySomeSpki;
+fn test_server_stream_read(stream_kind: StreamKind, read_kind: ReadKind) {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let (mut client, mut server) = make_pair(*kt, &provider);
+        let data = b"world";
+        client.writer().write_all(data).unwrap();
 
-impl sign::SigningKey for SigningKeySomeSpki {
-    fn public_key(&self) -> Option<pki_types::SubjectPublicKeyInfoDer> {
-        let chain = KeyType::Rsa2048.get_chain();
-        let cert = ParsedCertificate::try_from(chain.first().unwrap()).unwrap();
-        Some(
-            cert.subject_public_key_info()
-                .into_owned(),
-        )
-    }
+        {
+            let mut pipe = OtherSession::new(&mut client);
+            transfer_eof(&mut server);
 
-    fn choose_scheme(&self, _offered: &[SignatureScheme]) -> Option<Box<dyn sign::Signer>> {
-        unimplemented!("Not meant to be called during tests")
-    }
+            let stream: Box<dyn BufRead> = match stream_kind {
+                StreamKind::Ref => Box::new(Stream::new(&mut server, &mut pipe)),
+                StreamKind::Owned => Box::new(StreamOwned::new(server, pipe)),
+            };
 
-    fn algorithm(&self) -> rustls::SignatureAlgorithm {
-        unimplemented!("Not meant to be called during tests")
+            test_stream_read(read_kind, stream, data)
+        }
     }
 }
 
-fn do_exporter_test(client_config: ClientConfig, server_config: ServerConfig) {
-    let mut client_secret = [0u8; 64];
-    let mut server_secret = [0u8; 64];
-
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-
-    assert_eq!(
-        Err(Error::HandshakeNotComplete),
-        client.export_keying_material(&mut client_secret, b"label", Some(b"context"))
-    );
-    assert_eq!(
-        Err(Error::HandshakeNotComplete),
-        server.export_keying_material(&mut server_secret, b"label", Some(b"context"))
-    );
+#[test]
+fn test_client_write_and_vectored_write_equivalence() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
     do_handshake(&mut client, &mut server);
 
-    assert!(
-        client
-            .export_keying_material(&mut client_secret, b"label", Some(b"context"))
-            .is_ok()
-    );
-    assert!(
-        server
-            .export_keying_material(&mut server_secret, b"label", Some(b"context"))
-            .is_ok()
-    );
-    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
+    const N: usize = 1000;
 
-    let mut empty = vec![];
-    assert_eq!(
-        client
-            .export_keying_material(&mut empty, b"label", Some(b"context"))
-            .err(),
-        Some(Error::General(
-            "export_keying_material with zero-length output".into()
-        ))
-    );
-    assert_eq!(
-        server
-            .export_keying_material(&mut empty, b"label", Some(b"context"))
-            .err(),
-        Some(Error::General(
-            "export_keying_material with zero-length output".into()
-        ))
-    );
+    let data_chunked: Vec<IoSlice> = std::iter::repeat(IoSlice::new(b"A"))
+        .take(N)
+        .collect();
+    let bytes_written_chunked = client
+        .writer()
+        .write_vectored(&data_chunked)
+        .unwrap();
+    let bytes_sent_chunked = transfer(&mut client, &mut server);
+    println!("write_vectored returned {bytes_written_chunked} and sent {bytes_sent_chunked}");
 
-    assert!(
-        client
-            .export_keying_material(&mut client_secret, b"label", None)
-            .is_ok()
-    );
-    assert_ne!(client_secret.to_vec(), server_secret.to_vec());
-    assert!(
-        server
-            .export_keying_material(&mut server_secret, b"label", None)
-            .is_ok()
-    );
-    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
+    let data_contiguous = &[b'A'; N];
+    let bytes_written_contiguous = client
+        .writer()
+        .write(data_contiguous)
+        .unwrap();
+    let bytes_sent_contiguous = transfer(&mut client, &mut server);
+    println!("write returned {bytes_written_contiguous} and sent {bytes_sent_contiguous}");
+
+    assert_eq!(bytes_written_chunked, bytes_written_contiguous);
+    assert_eq!(bytes_sent_chunked, bytes_sent_contiguous);
 }
 
-#[test]
-fn test_tls12_exporter() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let client_config =
-            make_client_config_with_versions(*kt, &[&rustls::version::TLS12], &provider);
-        let server_config = make_server_config(*kt, &provider);
+struct FailsWrites {
+    errkind: io::ErrorKind,
+    after: usize,
+}
 
-        do_exporter_test(client_config, server_config);
+impl io::Read for FailsWrites {
+    fn read(&mut self, _b: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
     }
 }
 
-#[test]
-fn test_tls13_exporter() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        let client_config =
-            make_client_config_with_versions(*kt, &[&rustls::version::TLS13], &provider);
-        let server_config = make_server_config(*kt, &provider);
+impl io::Write for FailsWrites {
+    fn write(&mut self, b: &[u8]) -> io::Result<usize> {
+        if self.after > 0 {
+            self.after -= 1;
+            Ok(b.len())
+        } else {
+            Err(io::Error::new(self.errkind, "oops"))
+        }
+    }
 
-        do_exporter_test(client_config, server_config);
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
 #[test]
-fn test_tls13_exporter_maximum_output_length() {
-    let provider = provider::default_provider();
-    let client_config =
-        make_client_config_with_versions(KeyType::EcdsaP256, &[&rustls::version::TLS13], &provider);
-    let server_config = make_server_config(KeyType::EcdsaP256, &provider);
-
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+fn stream_write_reports_underlying_io_error_before_plaintext_processed() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
     do_handshake(&mut client, &mut server);
 
-    assert_eq!(
-        client.negotiated_cipher_suite(),
-        Some(find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384))
-    );
-
-    let mut maximum_allowed_output_client = [0u8; 255 * 48];
-    let mut maximum_allowed_output_server = [0u8; 255 * 48];
+    let mut pipe = FailsWrites {
+        errkind: io::ErrorKind::ConnectionAborted,
+        after: 0,
+    };
     client
-        .export_keying_material(
-            &mut maximum_allowed_output_client,
-            b"label",
-            Some(b"context"),
-        )
+        .writer()
+        .write_all(b"hello")
         .unwrap();
-    server
-        .export_keying_material(
-            &mut maximum_allowed_output_server,
-            b"label",
-            Some(b"context"),
-        )
+    let mut client_stream = Stream::new(&mut client, &mut pipe);
+    let rc = client_stream.write(b"world");
+    assert!(rc.is_err());
+    let err = rc.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+}
+
+#[test]
+fn stream_write_swallows_underlying_io_error_after_plaintext_processed() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    do_handshake(&mut client, &mut server);
+
+    let mut pipe = FailsWrites {
+        errkind: io::ErrorKind::ConnectionAborted,
+        after: 1,
+    };
+    client
+        .writer()
+        .write_all(b"hello")
         .unwrap();
+    let mut client_stream = Stream::new(&mut client, &mut pipe);
+    let rc = client_stream.write(b"world");
+    assert_eq!(format!("{rc:?}"), "Ok(5)");
+}
 
-    assert_eq!(maximum_allowed_output_client, maximum_allowed_output_server);
+fn make_disjoint_suite_configs() -> (ClientConfig, ServerConfig) {
+    let kt = KeyType::Rsa2048;
+    let client_provider = CryptoProvider {
+        cipher_suites: vec![cipher_suite::TLS13_CHACHA20_POLY1305_SHA256],
+        ..provider::default_provider()
+    };
+    let server_config = finish_server_config(
+        kt,
+        ServerConfig::builder_with_provider(client_provider.into())
+            .with_safe_default_protocol_versions()
+            .unwrap(),
+    );
 
-    let mut too_long_output = [0u8; 255 * 48 + 1];
+    let server_provider = CryptoProvider {
+        cipher_suites: vec![cipher_suite::TLS13_AES_256_GCM_SHA384],
+        ..provider::default_provider()
+    };
+    let client_config = finish_client_config(
+        kt,
+        ClientConfig::builder_with_provider(server_provider.into())
+            .with_safe_default_protocol_versions()
+            .unwrap(),
+    );
+
+    (client_config, server_config)
+}
+
+#[test]
+fn client_stream_handshake_error() {
+    let (client_config, server_config) = make_disjoint_suite_configs();
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+
+    {
+        let mut pipe = OtherSession::new_fails(&mut server);
+        let mut client_stream = Stream::new(&mut client, &mut pipe);
+        let rc = client_stream.write(b"hello");
+        assert!(rc.is_err());
+        assert_eq!(
+            format!("{rc:?}"),
+            "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
+        );
+        let rc = client_stream.write(b"hello");
+        assert!(rc.is_err());
+        assert_eq!(
+            format!("{rc:?}"),
+            "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
+        );
+    }
+}
+
+#[test]
+fn client_streamowned_handshake_error() {
+    let (client_config, server_config) = make_disjoint_suite_configs();
+    let (client, mut server) = make_pair_for_configs(client_config, server_config);
+
+    let pipe = OtherSession::new_fails(&mut server);
+    let mut client_stream = StreamOwned::new(client, pipe);
+    let rc = client_stream.write(b"hello");
+    assert!(rc.is_err());
     assert_eq!(
-        client
-            .export_keying_material(&mut too_long_output, b"label", Some(b"context"),)
-            .err(),
-        Some(Error::General("exporting too much".into()))
+        format!("{rc:?}"),
+        "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
     );
+    let rc = client_stream.write(b"hello");
+    assert!(rc.is_err());
     assert_eq!(
-        server
-            .export_keying_material(&mut too_long_output, b"label", Some(b"context"),)
-            .err(),
-        Some(Error::General("exporting too much".into()))
+        format!("{rc:?}"),
+        "Err(Custom { kind: InvalidData, error: AlertReceived(HandshakeFailure) })"
     );
+
+    let (_, _) = client_stream.into_parts();
 }
 
-fn find_suite(suite: CipherSuite) -> SupportedCipherSuite {
-    for scs in provider::ALL_CIPHER_SUITES
-        .iter()
-        .copied()
+#[test]
+fn server_stream_handshake_error() {
+    let (client_config, server_config) = make_disjoint_suite_configs();
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+
+    client
+        .writer()
+        .write_all(b"world")
+        .unwrap();
+
     {
-        if scs.suite() == suite {
-            return scs;
-        }
+        let mut pipe = OtherSession::new_fails(&mut client);
+        let mut server_stream = Stream::new(&mut server, &mut pipe);
+        let mut bytes = [0u8; 5];
+        let rc = server_stream.read(&mut bytes);
+        assert!(rc.is_err());
+        assert_eq!(
+            format!("{rc:?}"),
+            "Err(Custom { kind: InvalidData, error: PeerIncompatible(NoCipherSuitesInCommon) })"
+        );
     }
+}
 
-    panic!("find_suite given unsupported suite");
+#[test]
+fn server_streamowned_handshake_error() {
+    let (client_config, server_config) = make_disjoint_suite_configs();
+    let (mut client, server) = make_pair_for_configs(client_config, server_config);
+
+    client
+        .writer()
+        .write_all(b"world")
+        .unwrap();
+
+    let pipe = OtherSession::new_fails(&mut client);
+    let mut server_stream = StreamOwned::new(server, pipe);
+    let mut bytes = [0u8; 5];
+    let rc = server_stream.read(&mut bytes);
+    assert!(rc.is_err());
+    assert_eq!(
+        format!("{rc:?}"),
+        "Err(Custom { kind: InvalidData, error: PeerIncompatible(NoCipherSuitesInCommon) })"
+    );
 }
 
-fn test_ciphersuites() -> Vec<(
-    &'static rustls::SupportedProtocolVersion,
-    KeyType,
-    CipherSuite,
-)> {
-    let mut v = vec![
-        (
-            &rustls::version::TLS13,
-            KeyType::Rsa2048,
-            CipherSuite::TLS13_AES_256_GCM_SHA384,
-        ),
-        (
-            &rustls::version::TLS13,
-            KeyType::Rsa2048,
-            CipherSuite::TLS13_AES_128_GCM_SHA256,
-        ),
-        (
-            &rustls::version::TLS12,
-            KeyType::EcdsaP384,
-            CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
-        ),
-        (
-            &rustls::version::TLS12,
-            KeyType::EcdsaP384,
-            CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
-        ),
-        (
-            &rustls::version::TLS12,
-            KeyType::Rsa2048,
-            CipherSuite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
-        ),
-        (
-            &rustls::version::TLS12,
-            KeyType::Rsa2048,
-            CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
-        ),
-    ];
+#[test]
+fn server_config_is_clone() {
+    let _ = make_server_config(KeyType::Rsa2048, &provider::default_provider());
+}
 
-    if !provider_is_fips() {
-        v.extend_from_slice(&[
-            (
-                &rustls::version::TLS13,
-                KeyType::Rsa2048,
-                CipherSuite::TLS13_CHACHA20_POLY1305_SHA256,
-            ),
-            (
-                &rustls::version::TLS12,
-                KeyType::EcdsaP256,
-                CipherSuite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
-            ),
-            (
-                &rustls::version::TLS12,
-                KeyType::Rsa2048,
-                CipherSuite::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
-            ),
-        ]);
-    }
+#[test]
+fn client_config_is_clone() {
+    let _ = make_client_config(KeyType::Rsa2048, &provider::default_provider());
+}
 
-    v
+#[test]
+fn client_connection_is_debug() {
+    let (client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    println!("{client:?}");
 }
 
 #[test]
-fn negotiated_ciphersuite_default() {
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        do_suite_and_kx_test(
-            make_client_config(*kt, &provider),
-            make_server_config(*kt, &provider),
-            find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384),
-            expected_kx_for_version(&rustls::version::TLS13),
-            ProtocolVersion::TLSv1_3,
-        );
-    }
+fn server_connection_is_debug() {
+    let (_, server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    println!("{server:?}");
 }
 
 #[test]
-fn all_suites_covered() {
+fn server_complete_io_for_handshake_ending_with_alert() {
+    let (client_config, server_config) = make_disjoint_suite_configs();
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+
+    assert!(server.is_handshaking());
+
+    let mut pipe = OtherSession::new_fails(&mut client);
+    let rc = server.complete_io(&mut pipe);
+    assert!(rc.is_err(), "server io failed due to handshake failure");
+    assert!(!server.wants_write(), "but server did send its alert");
     assert_eq!(
-        provider::DEFAULT_CIPHER_SUITES.len(),
-        test_ciphersuites().len()
+        format!("{:?}", pipe.last_error),
+        "Some(AlertReceived(HandshakeFailure))",
+        "which was received by client"
     );
 }
 
 #[test]
-fn negotiated_ciphersuite_client() {
-    for (version, kt, suite) in test_ciphersuites() {
-        let scs = find_suite(suite);
-        let client_config = finish_client_config(
-            kt,
-            ClientConfig::builder_with_provider(
-                CryptoProvider {
-                    cipher_suites: vec![scs],
-                    ..provider::default_provider()
-                }
-                .into(),
-            )
-            .with_protocol_versions(&[version])
-            .unwrap(),
-        );
+fn server_exposes_offered_sni() {
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    for version in rustls::ALL_VERSIONS {
+        let client_config = make_client_config_with_versions(kt, &[version], &provider);
+        let mut client = ClientConnection::new(
+            Arc::new(client_config),
+            server_name("second.testserver.com"),
+        )
+        .unwrap();
+        let mut server =
+            ServerConnection::new(Arc::new(make_server_config(kt, &provider))).unwrap();
 
-        do_suite_and_kx_test(
-            client_config,
-            make_server_config(kt, &provider::default_provider()),
-            scs,
-            expected_kx_for_version(version),
-            version.version(),
+        assert_eq!(None, server.server_name());
+        do_handshake(&mut client, &mut server);
+        assert_eq!(
+            Some(&DnsName::try_from("second.testserver.com").unwrap()),
+            server.server_name()
         );
     }
 }
 
 #[test]
-fn negotiated_ciphersuite_server() {
-    for (version, kt, suite) in test_ciphersuites() {
-        let scs = find_suite(suite);
-        let server_config = finish_server_config(
-            kt,
-            ServerConfig::builder_with_provider(
-                CryptoProvider {
-                    cipher_suites: vec![scs],
-                    ..provider::default_provider()
-                }
-                .into(),
-            )
-            .with_protocol_versions(&[version])
-            .unwrap(),
-        );
+fn server_exposes_offered_sni_smashed_to_lowercase() {
+    // webpki actually does this for us in its DnsName type
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    for version in rustls::ALL_VERSIONS {
+        let client_config = make_client_config_with_versions(kt, &[version], &provider);
+        let mut client = ClientConnection::new(
+            Arc::new(client_config),
+            server_name("SECOND.TESTServer.com"),
+        )
+        .unwrap();
+        let mut server =
+            ServerConnection::new(Arc::new(make_server_config(kt, &provider))).unwrap();
 
-        do_suite_and_kx_test(
-            make_client_config(kt, &provider::default_provider()),
-            server_config,
-            scs,
-            expected_kx_for_version(version),
-            version.version(),
+        assert_eq!(None, server.server_name());
+        do_handshake(&mut client, &mut server);
+        assert_eq!(
+            Some(&DnsName::try_from("second.testserver.com").unwrap()),
+            server.server_name()
         );
     }
 }
 
 #[test]
-fn negotiated_ciphersuite_server_ignoring_client_preference() {
-    for (version, kt, suite) in test_ciphersuites() {
-        let scs = find_suite(suite);
-        let scs_other = if scs.suite() == CipherSuite::TLS13_AES_256_GCM_SHA384 {
-            find_suite(CipherSuite::TLS13_AES_128_GCM_SHA256)
-        } else {
-            find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384)
-        };
-        let mut server_config = finish_server_config(
-            kt,
-            ServerConfig::builder_with_provider(
-                CryptoProvider {
-                    cipher_suites: vec![scs, scs_other],
-                    ..provider::default_provider()
-                }
-                .into(),
-            )
-            .with_protocol_versions(&[version])
-            .unwrap(),
-        );
-        server_config.ignore_client_order = true;
+fn server_exposes_offered_sni_even_if_resolver_fails() {
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
 
-        let client_config = finish_client_config(
-            kt,
-            ClientConfig::builder_with_provider(
-                CryptoProvider {
-                    cipher_suites: vec![scs_other, scs],
-                    ..provider::default_provider()
-                }
-                .into(),
-            )
-            .with_safe_default_protocol_versions()
-            .unwrap(),
-        );
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.cert_resolver = Arc::new(resolver);
+    let server_config = Arc::new(server_config);
 
-        do_suite_and_kx_test(
-            client_config,
-            server_config,
-            scs,
-            expected_kx_for_version(version),
-            version.version(),
-        );
-    }
-}
+    for version in rustls::ALL_VERSIONS {
+        let client_config = make_client_config_with_versions(kt, &[version], &provider);
+        let mut server = ServerConnection::new(server_config.clone()).unwrap();
+        let mut client =
+            ClientConnection::new(Arc::new(client_config), server_name("thisdoesNOTexist.com"))
+                .unwrap();
 
-fn expected_kx_for_version(version: &SupportedProtocolVersion) -> NamedGroup {
-    match (
-        version.version(),
-        provider_is_aws_lc_rs(),
-        provider_is_fips(),
-    ) {
-        (ProtocolVersion::TLSv1_3, true, _) => NamedGroup::X25519MLKEM768,
-        (_, _, true) => NamedGroup::secp256r1,
-        (_, _, _) => NamedGroup::X25519,
-    }
-}
-
-#[derive(Debug, PartialEq)]
-struct KeyLogItem {
-    label: String,
-    client_random: Vec<u8>,
-    secret: Vec<u8>,
-}
-
-#[derive(Debug)]
-struct KeyLogToVec {
-    label: &'static str,
-    items: Mutex<Vec<KeyLogItem>>,
-}
-
-impl KeyLogToVec {
-    fn new(who: &'static str) -> Self {
-        Self {
-            label: who,
-            items: Mutex::new(vec![]),
-        }
-    }
-
-    fn take(&self) -> Vec<KeyLogItem> {
-        std::mem::take(&mut self.items.lock().unwrap())
-    }
-}
-
-impl KeyLog for KeyLogToVec {
-    fn log(&self, label: &str, client: &[u8], secret: &[u8]) {
-        let value = KeyLogItem {
-            label: label.into(),
-            client_random: client.into(),
-            secret: secret.into(),
-        };
-
-        println!("key log {:?}: {:?}", self.label, value);
-
-        self.items.lock().unwrap().push(value);
+        assert_eq!(None, server.server_name());
+        transfer(&mut client, &mut server);
+        assert_eq!(
+            server.process_new_packets(),
+            Err(Error::General(
+                "no server certificate chain resolved".to_string()
+            ))
+        );
+        assert_eq!(
+            Some(&DnsName::try_from("thisdoesnotexist.com").unwrap()),
+            server.server_name()
+        );
     }
 }
 
 #[test]
-fn key_log_for_tls12() {
-    let client_key_log = Arc::new(KeyLogToVec::new("client"));
-    let server_key_log = Arc::new(KeyLogToVec::new("server"));
-
-    let provider = provider::default_provider();
+fn sni_resolver_works() {
     let kt = KeyType::Rsa2048;
-    let mut client_config =
-        make_client_config_with_versions(kt, &[&rustls::version::TLS12], &provider);
-    client_config.key_log = client_key_log.clone();
-    let client_config = Arc::new(client_config);
+    let provider = provider::default_provider();
+    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
+    resolver
+        .add(
+            DnsName::try_from("localhost").unwrap(),
+            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match"),
+        )
+        .unwrap();
 
     let mut server_config = make_server_config(kt, &provider);
-    server_config.key_log = server_key_log.clone();
+    server_config.cert_resolver = Arc::new(resolver);
     let server_config = Arc::new(server_config);
 
-    // full handshake
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
-
-    let client_full_log = client_key_log.take();
-    let server_full_log = server_key_log.take();
-    assert_eq!(client_full_log, server_full_log);
-    assert_eq!(1, client_full_log.len());
-    assert_eq!("CLIENT_RANDOM", client_full_log[0].label);
-
-    // resumed
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
+    let mut server1 = ServerConnection::new(server_config.clone()).unwrap();
+    let mut client1 = ClientConnection::new(
+        Arc::new(make_client_config(kt, &provider)),
+        server_name("localhost"),
+    )
+    .unwrap();
+    let err = do_handshake_until_error(&mut client1, &mut server1);
+    assert_eq!(err, Ok(()));
 
-    let client_resume_log = client_key_log.take();
-    let server_resume_log = server_key_log.take();
-    assert_eq!(client_resume_log, server_resume_log);
-    assert_eq!(1, client_resume_log.len());
-    assert_eq!("CLIENT_RANDOM", client_resume_log[0].label);
-    assert_eq!(client_full_log[0].secret, client_resume_log[0].secret);
+    let mut server2 = ServerConnection::new(server_config.clone()).unwrap();
+    let mut client2 = ClientConnection::new(
+        Arc::new(make_client_config(kt, &provider)),
+        server_name("notlocalhost"),
+    )
+    .unwrap();
+    let err = do_handshake_until_error(&mut client2, &mut server2);
+    assert_eq!(
+        err,
+        Err(ErrorFromPeer::Server(Error::General(
+            "no server certificate chain resolved".into()
+        )))
+    );
 }
 
 #[test]
-fn key_log_for_tls13() {
-    let client_key_log = Arc::new(KeyLogToVec::new("client"));
-    let server_key_log = Arc::new(KeyLogToVec::new("server"));
-
-    let provider = provider::default_provider();
+fn sni_resolver_rejects_wrong_names() {
     let kt = KeyType::Rsa2048;
-    let mut client_config =
-        make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-    client_config.key_log = client_key_log.clone();
-    let client_config = Arc::new(client_config);
+    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.key_log = server_key_log.clone();
-    let server_config = Arc::new(server_config);
+    assert_eq!(
+        Ok(()),
+        resolver.add(
+            DnsName::try_from("localhost").unwrap(),
+            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
+        )
+    );
+    assert_eq!(
+        Err(Error::InvalidCertificate(certificate_error_expecting_name(
+            "not-localhost"
+        ))),
+        resolver.add(
+            DnsName::try_from("not-localhost").unwrap(),
+            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
+        )
+    );
+}
 
-    // full handshake
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
+fn multi_key_sni_resolver() -> rustls::server::MultiKeySniResolver {
+    let provider = provider::default_provider();
+    let mut resolver = rustls::server::MultiKeySniResolver::new();
+    for kt in [KeyType::Rsa2048, KeyType::EcdsaP256, KeyType::Ed25519] {
+        let ck = Arc::unwrap_or_clone(
+            kt.certified_key_with_cert_chain(&provider)
+                .unwrap(),
+        );
+        resolver
+            .add(DnsName::try_from("localhost").unwrap(), ck)
+            .unwrap();
+    }
+    resolver
+}
 
-    let client_full_log = client_key_log.take();
-    let server_full_log = server_key_log.take();
+fn server_chain_for_verifier(
+    resolver: &Arc<rustls::server::MultiKeySniResolver>,
+    verifier: MockServerVerifier,
+    name: &'static str,
+) -> Vec<CertificateDer<'static>> {
+    let provider = provider::default_provider();
+    let server_config = server_config_builder(&provider)
+        .with_no_client_auth()
+        .with_cert_resolver(resolver.clone());
+    let client_config = client_config_builder(&provider)
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
 
-    assert_eq!(5, client_full_log.len());
-    assert_eq!("CLIENT_HANDSHAKE_TRAFFIC_SECRET", client_full_log[0].label);
-    assert_eq!("SERVER_HANDSHAKE_TRAFFIC_SECRET", client_full_log[1].label);
-    assert_eq!("CLIENT_TRAFFIC_SECRET_0", client_full_log[2].label);
-    assert_eq!("SERVER_TRAFFIC_SECRET_0", client_full_log[3].label);
-    assert_eq!("EXPORTER_SECRET", client_full_log[4].label);
+    let mut client = ClientConnection::new(Arc::new(client_config), server_name(name)).unwrap();
+    let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+    do_handshake(&mut client, &mut server);
+    client
+        .peer_certificates()
+        .unwrap()
+        .to_vec()
+}
 
-    assert_eq!(client_full_log[0], server_full_log[0]);
-    assert_eq!(client_full_log[1], server_full_log[1]);
-    assert_eq!(client_full_log[2], server_full_log[2]);
-    assert_eq!(client_full_log[3], server_full_log[3]);
-    assert_eq!(client_full_log[4], server_full_log[4]);
+#[test]
+fn multi_key_sni_resolver_picks_key_by_signature_scheme() {
+    let resolver = Arc::new(multi_key_sni_resolver());
 
-    // resumed
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
+    for (schemes, kt) in [
+        (
+            vec![
+                SignatureScheme::RSA_PSS_SHA256,
+                SignatureScheme::ECDSA_NISTP256_SHA256,
+            ],
+            KeyType::Rsa2048,
+        ),
+        (
+            vec![
+                SignatureScheme::ECDSA_NISTP256_SHA256,
+                SignatureScheme::RSA_PSS_SHA256,
+            ],
+            KeyType::EcdsaP256,
+        ),
+        (
+            vec![
+                SignatureScheme::ECDSA_NISTP384_SHA384,
+                SignatureScheme::ED25519,
+                SignatureScheme::RSA_PSS_SHA256,
+            ],
+            KeyType::Ed25519,
+        ),
+    ] {
+        let verifier = MockServerVerifier::offers_signature_schemes(schemes);
+        assert_eq!(
+            server_chain_for_verifier(&resolver, verifier, "localhost"),
+            kt.get_chain()
+        );
+    }
+}
 
-    let client_resume_log = client_key_log.take();
-    let server_resume_log = server_key_log.take();
+#[test]
+fn multi_key_sni_resolver_prefers_named_certificate_authorities() {
+    let resolver = Arc::new(multi_key_sni_resolver());
 
-    assert_eq!(5, client_resume_log.len());
-    assert_eq!(
-        "CLIENT_HANDSHAKE_TRAFFIC_SECRET",
-        client_resume_log[0].label
-    );
+    let verifier = MockServerVerifier::accepts_anything().with_root_hint_subjects(vec![
+        DistinguishedName::from(
+            KeyType::EcdsaP256
+                .ca_distinguished_name()
+                .to_vec(),
+        ),
+    ]);
     assert_eq!(
-        "SERVER_HANDSHAKE_TRAFFIC_SECRET",
-        client_resume_log[1].label
+        server_chain_for_verifier(&resolver, verifier, "localhost"),
+        KeyType::EcdsaP256.get_chain()
     );
-    assert_eq!("CLIENT_TRAFFIC_SECRET_0", client_resume_log[2].label);
-    assert_eq!("SERVER_TRAFFIC_SECRET_0", client_resume_log[3].label);
-    assert_eq!("EXPORTER_SECRET", client_resume_log[4].label);
 
-    assert_eq!(6, server_resume_log.len());
-    assert_eq!("CLIENT_EARLY_TRAFFIC_SECRET", server_resume_log[0].label);
-    assert_eq!(
-        "CLIENT_HANDSHAKE_TRAFFIC_SECRET",
-        server_resume_log[1].label
-    );
+    // an unknown authority is ignored
+    let verifier = MockServerVerifier::accepts_anything()
+        .with_root_hint_subjects(vec![DistinguishedName::in_sequence(b"unknown")]);
     assert_eq!(
-        "SERVER_HANDSHAKE_TRAFFIC_SECRET",
-        server_resume_log[2].label
+        server_chain_for_verifier(&resolver, verifier, "localhost"),
+        KeyType::Rsa2048.get_chain()
     );
-    assert_eq!("CLIENT_TRAFFIC_SECRET_0", server_resume_log[3].label);
-    assert_eq!("SERVER_TRAFFIC_SECRET_0", server_resume_log[4].label);
-    assert_eq!("EXPORTER_SECRET", server_resume_log[5].label);
-
-    assert_eq!(client_resume_log[0], server_resume_log[1]);
-    assert_eq!(client_resume_log[1], server_resume_log[2]);
-    assert_eq!(client_resume_log[2], server_resume_log[3]);
-    assert_eq!(client_resume_log[3], server_resume_log[4]);
-    assert_eq!(client_resume_log[4], server_resume_log[5]);
 }
 
-#[test]
-fn vectored_write_for_server_appdata() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    do_handshake(&mut client, &mut server);
-
-    server
-        .writer()
-        .write_all(b"01234567890123456789")
+/// Issue a self-signed certificate for `*.testserver.com`.
+fn wildcard_certified_key(provider: &CryptoProvider) -> sign::CertifiedKey {
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = rcgen::CertificateParams::new(vec!["*.testserver.com".to_string()])
+        .unwrap()
+        .self_signed(&key)
         .unwrap();
-    server
-        .writer()
-        .write_all(b"01234567890123456789")
+    let key = provider
+        .key_provider
+        .load_private_key(PrivateKeyDer::try_from(key.serialize_der()).unwrap())
         .unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        assert_eq!(84, wrlen);
-        assert_eq!(pipe.writevs, vec![vec![42, 42]]);
-    }
-    check_read(
-        &mut client.reader(),
-        b"0123456789012345678901234567890123456789",
-    );
+    sign::CertifiedKey::new(vec![cert.der().clone()], key).unwrap()
 }
 
 #[test]
-fn vectored_write_for_client_appdata() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    do_handshake(&mut client, &mut server);
+fn multi_key_sni_resolver_wildcard_and_default_names() {
+    let provider = provider::default_provider();
+    let certified_key = |kt: KeyType| {
+        Arc::unwrap_or_clone(
+            kt.certified_key_with_cert_chain(&provider)
+                .unwrap(),
+        )
+    };
 
-    client
-        .writer()
-        .write_all(b"01234567890123456789")
+    let mut resolver = rustls::server::MultiKeySniResolver::new();
+    resolver
+        .add(
+            DnsName::try_from("testserver.com").unwrap(),
+            certified_key(KeyType::Rsa2048),
+        )
         .unwrap();
-    client
-        .writer()
-        .write_all(b"01234567890123456789")
+    let wildcard = wildcard_certified_key(&provider);
+    let wildcard_chain = wildcard.cert_chain.clone();
+    resolver
+        .add_wildcard(DnsName::try_from("testserver.com").unwrap(), wildcard)
         .unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut server);
-        let wrlen = client.write_tls(&mut pipe).unwrap();
-        assert_eq!(84, wrlen);
-        assert_eq!(pipe.writevs, vec![vec![42, 42]]);
-    }
-    check_read(
-        &mut server.reader(),
-        b"0123456789012345678901234567890123456789",
+    resolver
+        .add_default(certified_key(KeyType::Ed25519))
+        .unwrap();
+    assert_eq!(
+        resolver.add(
+            DnsName::try_from("not-localhost").unwrap(),
+            certified_key(KeyType::Rsa2048)
+        ),
+        Err(Error::InvalidCertificate(certificate_error_expecting_name(
+            "not-localhost"
+        )))
     );
+    let resolver = Arc::new(resolver);
+
+    for (name, chain) in [
+        ("testserver.com", KeyType::Rsa2048.get_chain()),
+        ("second.testserver.com", wildcard_chain),
+        ("third.second.testserver.com", KeyType::Ed25519.get_chain()),
+        ("localhost", KeyType::Ed25519.get_chain()),
+        ("198.51.100.1", KeyType::Ed25519.get_chain()),
+    ] {
+        assert_eq!(
+            server_chain_for_verifier(&resolver, MockServerVerifier::accepts_anything(), name),
+            chain,
+            "{name}"
+        );
+    }
 }
 
 #[test]
-fn vectored_write_for_server_handshake_with_half_rtt_data() {
+fn multi_key_sni_resolver_rejects_wildcard_not_covered_by_certificate() {
     let provider = provider::default_provider();
-    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
-    server_config.send_half_rtt_data = true;
-    let (mut client, mut server) = make_pair_for_configs(
-        make_client_config_with_auth(KeyType::Rsa2048, &provider),
-        server_config,
+    let mut resolver = rustls::server::MultiKeySniResolver::new();
+
+    // the test certificates name `second.testserver.com`, but not `*.testserver.com`
+    assert_eq!(
+        resolver.add_wildcard(
+            DnsName::try_from("testserver.com").unwrap(),
+            Arc::unwrap_or_clone(
+                KeyType::EcdsaP256
+                    .certified_key_with_cert_chain(&provider)
+                    .unwrap()
+            ),
+        ),
+        Err(Error::InvalidCertificate(CertificateError::NotValidForName))
+    );
+    assert_eq!(
+        resolver.add_wildcard(
+            DnsName::try_from("example.com").unwrap(),
+            wildcard_certified_key(&provider),
+        ),
+        Err(Error::InvalidCertificate(CertificateError::NotValidForName))
     );
+}
 
-    server
-        .writer()
-        .write_all(b"01234567890123456789")
-        .unwrap();
-    server
-        .writer()
-        .write_all(b"0123456789")
+#[test]
+fn multi_key_sni_resolver_filters_by_offered_certificate_types() {
+    let provider = provider::default_provider();
+    let kt = KeyType::EcdsaP256;
+
+    let mut raw_keys = rustls::server::MultiKeySniResolver::new_raw_public_keys();
+    raw_keys
+        .add_default(Arc::unwrap_or_clone(
+            kt.certified_key_with_raw_pub_key(&provider)
+                .unwrap(),
+        ))
         .unwrap();
+    let raw_keys = Arc::new(raw_keys);
+    let certificates = Arc::new(multi_key_sni_resolver());
+
+    let server_config = |resolver: Arc<dyn ResolvesServerCert>| {
+        Arc::new(
+            server_config_builder_with_versions(&[&rustls::version::TLS13], &provider)
+                .with_no_client_auth()
+                .with_cert_resolver(resolver),
+        )
+    };
+    let raw_key_client_config = Arc::new(
+        client_config_builder_with_versions(&[&rustls::version::TLS13], &provider)
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(
+                MockServerVerifier::expects_raw_public_keys(&provider),
+            ))
+            .with_no_client_auth(),
+    );
+    let certificate_client_config = Arc::new(make_client_config(kt, &provider));
 
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        // don't assert exact sizes here, to avoid a brittle test
-        assert!(wrlen > 2400); // its pretty big (contains cert chain)
-        assert_eq!(pipe.writevs.len(), 1); // only one writev
-        assert_eq!(pipe.writevs[0].len(), 5); // at least a server hello/ccs/cert/serverkx/0.5rtt data
-    }
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&raw_key_client_config, &server_config(raw_keys.clone()));
+    do_handshake_until_error(&mut client, &mut server).unwrap();
+    assert_eq!(
+        client.peer_certificates().unwrap(),
+        &[CertificateDer::from(kt.get_spki().to_vec())][..]
+    );
 
-    client.process_new_packets().unwrap();
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        // 2 tickets (in one flight)
-        assert_eq!(wrlen, 184);
-        assert_eq!(pipe.writevs, vec![vec![184]]);
-    }
+    // a client which does not offer raw public keys gets nothing from a raw key resolver
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&certificate_client_config, &server_config(raw_keys));
+    assert!(do_handshake_until_error(&mut client, &mut server).is_err());
 
-    assert!(!server.is_handshaking());
-    assert!(!client.is_handshaking());
-    check_read(&mut client.reader(), b"012345678901234567890123456789");
+    // and a client which only offers raw public keys gets nothing from a certificate resolver
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&raw_key_client_config, &server_config(certificates));
+    assert!(do_handshake_until_error(&mut client, &mut server).is_err());
 }
 
-fn check_half_rtt_does_not_work(server_config: ServerConfig) {
-    let (mut client, mut server) = make_pair_for_configs(
-        make_client_config_with_auth(KeyType::Rsa2048, &provider::default_provider()),
-        server_config,
-    );
-
-    server
-        .writer()
-        .write_all(b"01234567890123456789")
+fn acme_tls_alpn_resolver() -> Arc<rustls::server::AcmeTlsAlpnResolver> {
+    let provider = provider::default_provider();
+    let mut inner = rustls::server::MultiKeySniResolver::new();
+    inner
+        .add_default(Arc::unwrap_or_clone(
+            KeyType::Rsa2048
+                .certified_key_with_cert_chain(&provider)
+                .unwrap(),
+        ))
         .unwrap();
-    server
-        .writer()
-        .write_all(b"0123456789")
+
+    let resolver = rustls::server::AcmeTlsAlpnResolver::new(Arc::new(inner));
+    resolver
+        .add_challenge(
+            DnsName::try_from("localhost").unwrap(),
+            Arc::unwrap_or_clone(
+                KeyType::EcdsaP256
+                    .certified_key_with_cert_chain(&provider)
+                    .unwrap(),
+            ),
+        )
         .unwrap();
+    Arc::new(resolver)
+}
 
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        // don't assert exact sizes here, to avoid a brittle test
-        assert!(wrlen > 2400); // its pretty big (contains cert chain)
-        assert_eq!(pipe.writevs.len(), 1); // only one writev
-        assert_eq!(pipe.writevs[0].len(), 3); // at least a server hello/ccs/cert/serverkx data, in one message
-    }
+#[test]
+fn acme_tls_alpn_resolver_answers_challenge() {
+    let provider = provider::default_provider();
+    let resolver = acme_tls_alpn_resolver();
+    let server_config = Arc::new(
+        server_config_builder(&provider)
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone()),
+    );
 
-    // client second flight
-    client.process_new_packets().unwrap();
-    transfer(&mut client, &mut server);
+    for version in rustls::ALL_VERSIONS {
+        let mut client_config = client_config_builder_with_versions(&[version], &provider)
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(MockServerVerifier::accepts_anything()))
+            .with_no_client_auth();
+        client_config.alpn_protocols = vec![rustls::server::ACME_TLS_ALPN_PROTOCOL.to_vec()];
+        let client_config = Arc::new(client_config);
 
-    // when client auth is enabled, we don't sent 0.5-rtt data, as we'd be sending
-    // it to an unauthenticated peer. so it happens here, in the server's second
-    // flight (42 and 32 are lengths of appdata sent above).
-    server.process_new_packets().unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        assert_eq!(wrlen, 258);
-        assert_eq!(pipe.writevs, vec![vec![184, 42, 32]]);
+        for _ in 0..2 {
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &server_config);
+            do_handshake(&mut client, &mut server);
+            assert_eq!(
+                client.peer_certificates().unwrap(),
+                KeyType::EcdsaP256.get_chain(),
+            );
+            assert_eq!(
+                server.alpn_protocol(),
+                Some(rustls::server::ACME_TLS_ALPN_PROTOCOL)
+            );
+            // no tickets are issued, so the second handshake is not resumed
+            assert_eq!(client.handshake_kind(), Some(HandshakeKind::Full));
+        }
     }
 
-    assert!(!server.is_handshaking());
-    assert!(!client.is_handshaking());
-    check_read(&mut client.reader(), b"012345678901234567890123456789");
-}
-
-#[test]
-fn vectored_write_for_server_handshake_no_half_rtt_with_client_auth() {
-    let mut server_config = make_server_config_with_mandatory_client_auth(
-        KeyType::Rsa2048,
-        &provider::default_provider(),
+    // once removed, the challenge is no longer answered
+    assert!(resolver.remove_challenge(&DnsName::try_from("localhost").unwrap()));
+    let mut client_config = client_config_builder(&provider)
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(MockServerVerifier::accepts_anything()))
+        .with_no_client_auth();
+    client_config.alpn_protocols = vec![rustls::server::ACME_TLS_ALPN_PROTOCOL.to_vec()];
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+    do_handshake(&mut client, &mut server);
+    assert_eq!(
+        client.peer_certificates().unwrap(),
+        KeyType::Rsa2048.get_chain()
     );
-    server_config.send_half_rtt_data = true; // ask even though it will be ignored
-    check_half_rtt_does_not_work(server_config);
+    assert_eq!(server.alpn_protocol(), None);
 }
 
 #[test]
-fn vectored_write_for_server_handshake_no_half_rtt_by_default() {
-    let server_config = make_server_config(KeyType::Rsa2048, &provider::default_provider());
-    assert!(!server_config.send_half_rtt_data);
-    check_half_rtt_does_not_work(server_config);
-}
+fn acme_tls_alpn_resolver_defers_other_handshakes() {
+    let provider = provider::default_provider();
+    let server_config = Arc::new(
+        server_config_builder(&provider)
+            .with_no_client_auth()
+            .with_cert_resolver(acme_tls_alpn_resolver()),
+    );
 
-#[test]
-fn vectored_write_for_client_handshake() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    for (alpn_protocols, name) in [
+        (vec![], "localhost"),
+        (vec![b"h2".to_vec()], "localhost"),
+        (
+            vec![rustls::server::ACME_TLS_ALPN_PROTOCOL.to_vec()],
+            "testserver.com",
+        ),
+    ] {
+        let mut client_config = client_config_builder(&provider)
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(MockServerVerifier::accepts_anything()))
+            .with_no_client_auth();
+        client_config.alpn_protocols = alpn_protocols;
 
-    client
-        .writer()
-        .write_all(b"01234567890123456789")
-        .unwrap();
-    client
-        .writer()
-        .write_all(b"0123456789")
-        .unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut server);
-        let wrlen = client.write_tls(&mut pipe).unwrap();
-        // don't assert exact sizes here, to avoid a brittle test
-        assert!(wrlen > 200); // just the client hello
-        assert_eq!(pipe.writevs.len(), 1); // only one writev
-        assert!(pipe.writevs[0].len() == 1); // only a client hello
+        let mut client = ClientConnection::new(Arc::new(client_config), server_name(name)).unwrap();
+        let mut server = ServerConnection::new(server_config.clone()).unwrap();
+        do_handshake(&mut client, &mut server);
+        assert_eq!(
+            client.peer_certificates().unwrap(),
+            KeyType::Rsa2048.get_chain(),
+            "{name}"
+        );
+        assert_eq!(server.alpn_protocol(), None);
     }
+}
 
-    transfer(&mut server, &mut client);
-    client.process_new_packets().unwrap();
+/// Answers every handshake as an ACME challenge, and fails if asked to resolve normally.
+#[derive(Debug)]
+struct ChallengeOnlyResolver(Arc<sign::CertifiedKey>);
 
-    {
-        let mut pipe = OtherSession::new(&mut server);
-        let wrlen = client.write_tls(&mut pipe).unwrap();
-        assert_eq!(wrlen, 154);
-        // CCS, finished, then two application data records
-        assert_eq!(pipe.writevs, vec![vec![6, 74, 42, 32]]);
+impl ResolvesServerCert for ChallengeOnlyResolver {
+    fn resolve(&self, _client_hello: &ClientHello<'_>) -> Option<Arc<sign::CertifiedKey>> {
+        panic!("resolve() called after a challenge was resolved");
     }
 
-    assert!(!server.is_handshaking());
-    assert!(!client.is_handshaking());
-    check_read(&mut server.reader(), b"012345678901234567890123456789");
+    fn resolve_acme_tls_alpn_challenge(
+        &self,
+        _client_hello: &ClientHello<'_>,
+    ) -> Option<Arc<sign::CertifiedKey>> {
+        Some(self.0.clone())
+    }
 }
 
 #[test]
-fn vectored_write_with_slow_client() {
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-
-    client.set_buffer_limit(Some(32));
+fn acme_tls_alpn_challenge_is_resolved_once() {
+    let provider = provider::default_provider();
+    let server_config = Arc::new(
+        server_config_builder(&provider)
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(ChallengeOnlyResolver(
+                KeyType::EcdsaP256
+                    .certified_key_with_cert_chain(&provider)
+                    .unwrap(),
+            ))),
+    );
+    let mut client_config = client_config_builder(&provider)
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(MockServerVerifier::accepts_anything()))
+        .with_no_client_auth();
+    client_config.alpn_protocols = vec![rustls::server::ACME_TLS_ALPN_PROTOCOL.to_vec()];
 
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
     do_handshake(&mut client, &mut server);
-    server
-        .writer()
-        .write_all(b"01234567890123456789")
-        .unwrap();
+    assert_eq!(
+        client.peer_certificates().unwrap(),
+        KeyType::EcdsaP256.get_chain(),
+    );
+    assert_eq!(
+        server.alpn_protocol(),
+        Some(rustls::server::ACME_TLS_ALPN_PROTOCOL)
+    );
+}
 
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        pipe.short_writes = true;
-        let wrlen = server.write_tls(&mut pipe).unwrap()
-            + server.write_tls(&mut pipe).unwrap()
-            + server.write_tls(&mut pipe).unwrap()
-            + server.write_tls(&mut pipe).unwrap()
-            + server.write_tls(&mut pipe).unwrap()
-            + server.write_tls(&mut pipe).unwrap();
-        assert_eq!(42, wrlen);
-        assert_eq!(
-            pipe.writevs,
-            vec![vec![21], vec![10], vec![5], vec![3], vec![3]]
-        );
+fn certificate_error_expecting_name(expected: &str) -> CertificateError {
+    CertificateError::NotValidForNameContext {
+        expected: ServerName::try_from(expected)
+            .unwrap()
+            .to_owned(),
+        presented: vec![
+            // ref. examples/internal/test_ca.rs
+            r#"DnsName("testserver.com")"#.into(),
+            r#"DnsName("second.testserver.com")"#.into(),
+            r#"DnsName("localhost")"#.into(),
+            "IpAddress(198.51.100.1)".into(),
+            "IpAddress(2001:db8::1)".into(),
+        ],
     }
-    check_read(&mut client.reader(), b"01234567890123456789");
 }
 
-struct ServerStorage {
-    storage: Arc<dyn rustls::server::StoresServerSessions>,
-    put_count: AtomicUsize,
-    get_count: AtomicUsize,
-    take_count: AtomicUsize,
-}
+#[test]
+fn sni_resolver_lower_cases_configured_names() {
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
-impl ServerStorage {
-    fn new() -> Self {
-        Self {
-            storage: rustls::server::ServerSessionMemoryCache::new(1024),
-            put_count: AtomicUsize::new(0),
-            get_count: AtomicUsize::new(0),
-            take_count: AtomicUsize::new(0),
-        }
-    }
+    assert_eq!(
+        Ok(()),
+        resolver.add(
+            DnsName::try_from("LOCALHOST").unwrap(),
+            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
+        )
+    );
 
-    fn puts(&self) -> usize {
-        self.put_count.load(Ordering::SeqCst)
-    }
-    fn gets(&self) -> usize {
-        self.get_count.load(Ordering::SeqCst)
-    }
-    fn takes(&self) -> usize {
-        self.take_count.load(Ordering::SeqCst)
-    }
-}
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.cert_resolver = Arc::new(resolver);
+    let server_config = Arc::new(server_config);
 
-impl fmt::Debug for ServerStorage {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "(put: {:?}, get: {:?}, take: {:?})",
-            self.put_count, self.get_count, self.take_count
-        )
-    }
+    let mut server1 = ServerConnection::new(server_config.clone()).unwrap();
+    let mut client1 = ClientConnection::new(
+        Arc::new(make_client_config(kt, &provider)),
+        server_name("localhost"),
+    )
+    .unwrap();
+    let err = do_handshake_until_error(&mut client1, &mut server1);
+    assert_eq!(err, Ok(()));
 }
 
-impl rustls::server::StoresServerSessions for ServerStorage {
-    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
-        self.put_count
-            .fetch_add(1, Ordering::SeqCst);
-        self.storage.put(key, value)
-    }
+#[test]
+fn sni_resolver_lower_cases_queried_names() {
+    // actually, the handshake parser does this, but the effect is the same.
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
-    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
-        self.get_count
-            .fetch_add(1, Ordering::SeqCst);
-        self.storage.get(key)
-    }
+    assert_eq!(
+        Ok(()),
+        resolver.add(
+            DnsName::try_from("localhost").unwrap(),
+            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()).expect("keys match")
+        )
+    );
 
-    fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
-        self.take_count
-            .fetch_add(1, Ordering::SeqCst);
-        self.storage.take(key)
-    }
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.cert_resolver = Arc::new(resolver);
+    let server_config = Arc::new(server_config);
 
-    fn can_cache(&self) -> bool {
-        true
-    }
+    let mut server1 = ServerConnection::new(server_config.clone()).unwrap();
+    let mut client1 = ClientConnection::new(
+        Arc::new(make_client_config(kt, &provider)),
+        server_name("LOCALHOST"),
+    )
+    .unwrap();
+    let err = do_handshake_until_error(&mut client1, &mut server1);
+    assert_eq!(err, Ok(()));
 }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)] // complete mock, but not 100% used in tests
-enum ClientStorageOp {
-    SetKxHint(ServerName<'static>, rustls::NamedGroup),
-    GetKxHint(ServerName<'static>, Option<rustls::NamedGroup>),
-    SetTls12Session(ServerName<'static>),
-    GetTls12Session(ServerName<'static>, bool),
-    RemoveTls12Session(ServerName<'static>),
-    InsertTls13Ticket(ServerName<'static>),
-    TakeTls13Ticket(ServerName<'static>, bool),
+#[test]
+fn sni_resolver_rejects_bad_certs() {
+    let kt = KeyType::Rsa2048;
+    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let signing_key = RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
+
+    assert_eq!(
+        Err(Error::NoCertificatesPresented),
+        resolver.add(
+            DnsName::try_from("localhost").unwrap(),
+            sign::CertifiedKey::new_unchecked(vec![], signing_key.clone())
+        )
+    );
+
+    let bad_chain = vec![CertificateDer::from(vec![0xa0])];
+    assert_eq!(
+        Err(Error::InvalidCertificate(CertificateError::BadEncoding)),
+        resolver.add(
+            DnsName::try_from("localhost").unwrap(),
+            sign::CertifiedKey::new_unchecked(bad_chain, signing_key.clone())
+        )
+    );
 }
 
-struct ClientStorage {
-    storage: Arc<dyn rustls::client::ClientSessionStore>,
-    ops: Mutex<Vec<ClientStorageOp>>,
-    alter_max_early_data_size: Option<(u32, u32)>,
+#[test]
+fn test_keys_match() {
+    // Consistent: Both of these should have the same SPKI values
+    let expect_consistent =
+        sign::CertifiedKey::new(KeyType::Rsa2048.get_chain(), Arc::new(SigningKeySomeSpki));
+    assert!(expect_consistent.is_ok());
+
+    // Inconsistent: These should not have the same SPKI values
+    let expect_inconsistent =
+        sign::CertifiedKey::new(KeyType::EcdsaP256.get_chain(), Arc::new(SigningKeySomeSpki));
+    assert!(matches!(
+        expect_inconsistent,
+        Err(Error::InconsistentKeys(InconsistentKeys::KeyMismatch))
+    ));
+
+    // Unknown: This signing key returns None for its SPKI, so we can't tell if the certified key is consistent
+    assert!(matches!(
+        sign::CertifiedKey::new(KeyType::Rsa2048.get_chain(), Arc::new(SigningKeyNoneSpki)),
+        Err(Error::InconsistentKeys(InconsistentKeys::Unknown))
+    ));
 }
 
-impl ClientStorage {
-    fn new() -> Self {
-        Self {
-            storage: Arc::new(rustls::client::ClientSessionMemoryCache::new(1024)),
-            ops: Mutex::new(Vec::new()),
-            alter_max_early_data_size: None,
-        }
-    }
+/// Represents a SigningKey that returns None for its SPKI via the default impl.
+#[derive(Debug)]
+struct SigningKeyNoneSpki;
 
-    fn alter_max_early_data_size(&mut self, expected: u32, altered: u32) {
-        self.alter_max_early_data_size = Some((expected, altered));
+impl sign::SigningKey for SigningKeyNoneSpki {
+    fn choose_scheme(&self, _offered: &[SignatureScheme]) -> Option<Box<dyn sign::Signer>> {
+        unimplemented!("Not meant to be called during tests")
     }
 
-    fn ops(&self) -> Vec<ClientStorageOp> {
-        self.ops.lock().unwrap().clone()
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        None
     }
 
-    fn ops_and_reset(&self) -> Vec<ClientStorageOp> {
-        std::mem::take(&mut self.ops.lock().unwrap())
+    fn algorithm(&self) -> rustls::SignatureAlgorithm {
+        unimplemented!("Not meant to be called during tests")
     }
 }
 
-impl fmt::Debug for ClientStorage {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(ops: {:?})", self.ops.lock().unwrap())
-    }
-}
+/// Represents a SigningKey that returns Some for its SPKI.
+#[derive(Debug)]
+struct SigningKeySomeSpki;
 
-impl rustls::client::ClientSessionStore for ClientStorage {
-    fn set_kx_hint(&self, server_name: ServerName<'static>, group: rustls::NamedGroup) {
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::SetKxHint(server_name.clone(), group));
-        self.storage
-            .set_kx_hint(server_name, group)
+impl sign::SigningKey for SigningKeySomeSpki {
+    fn public_key(&self) -> Option<pki_types::SubjectPublicKeyInfoDer> {
+        let chain = KeyType::Rsa2048.get_chain();
+        let cert = ParsedCertificate::try_from(chain.first().unwrap()).unwrap();
+        Some(
+            cert.subject_public_key_info()
+                .into_owned(),
+        )
     }
 
-    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<rustls::NamedGroup> {
-        let rc = self.storage.kx_hint(server_name);
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::GetKxHint(server_name.to_owned(), rc));
-        rc
+    fn choose_scheme(&self, _offered: &[SignatureScheme]) -> Option<Box<dyn sign::Signer>> {
+        unimplemented!("Not meant to be called during tests")
     }
 
-    fn set_tls12_session(
-        &self,
-        server_name: ServerName<'static>,
-        value: rustls::client::Tls12ClientSessionValue,
-    ) {
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::SetTls12Session(server_name.clone()));
-        self.storage
-            .set_tls12_session(server_name, value)
+    fn algorithm(&self) -> rustls::SignatureAlgorithm {
+        unimplemented!("Not meant to be called during tests")
     }
+}
 
-    fn tls12_session(
-        &self,
-        server_name: &ServerName<'_>,
-    ) -> Option<rustls::client::Tls12ClientSessionValue> {
-        let rc = self.storage.tls12_session(server_name);
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::GetTls12Session(
-                server_name.to_owned(),
-                rc.is_some(),
-            ));
-        rc
-    }
+fn do_exporter_test(client_config: ClientConfig, server_config: ServerConfig) {
+    let mut client_secret = [0u8; 64];
+    let mut server_secret = [0u8; 64];
 
-    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::RemoveTls12Session(server_name.clone()));
-        self.storage
-            .remove_tls12_session(server_name);
-    }
-
-    fn insert_tls13_ticket(
-        &self,
-        server_name: ServerName<'static>,
-        mut value: rustls::client::Tls13ClientSessionValue,
-    ) {
-        if let Some((expected, desired)) = self.alter_max_early_data_size {
-            assert_eq!(value.max_early_data_size(), expected);
-            value._private_set_max_early_data_size(desired);
-        }
-
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::InsertTls13Ticket(server_name.clone()));
-        self.storage
-            .insert_tls13_ticket(server_name, value);
-    }
-
-    fn take_tls13_ticket(
-        &self,
-        server_name: &ServerName<'static>,
-    ) -> Option<rustls::client::Tls13ClientSessionValue> {
-        let rc = self
-            .storage
-            .take_tls13_ticket(server_name);
-        self.ops
-            .lock()
-            .unwrap()
-            .push(ClientStorageOp::TakeTls13Ticket(
-                server_name.clone(),
-                rc.is_some(),
-            ));
-        rc
-    }
-}
-
-#[test]
-fn tls13_stateful_resumption() {
-    let kt = KeyType::Rsa2048;
-    let provider = provider::default_provider();
-    let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-    let client_config = Arc::new(client_config);
-
-    let mut server_config = make_server_config(kt, &provider);
-    let storage = Arc::new(ServerStorage::new());
-    server_config.session_storage = storage.clone();
-    let server_config = Arc::new(server_config);
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
 
-    // full handshake
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    let (full_c2s, full_s2c) = do_handshake(&mut client, &mut server);
-    assert_eq!(client.tls13_tickets_received(), 2);
-    assert_eq!(storage.puts(), 2);
-    assert_eq!(storage.gets(), 0);
-    assert_eq!(storage.takes(), 0);
     assert_eq!(
-        client
-            .peer_certificates()
-            .map(|certs| certs.len()),
-        Some(3)
+        Err(Error::HandshakeNotComplete),
+        client.export_keying_material(&mut client_secret, b"label", Some(b"context"))
     );
-    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Full));
-    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Full));
-
-    // resumed
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
-    assert!(resume_c2s > full_c2s);
-    assert!(resume_s2c < full_s2c);
-    assert_eq!(storage.puts(), 4);
-    assert_eq!(storage.gets(), 0);
-    assert_eq!(storage.takes(), 1);
     assert_eq!(
-        client
-            .peer_certificates()
-            .map(|certs| certs.len()),
-        Some(3)
+        Err(Error::HandshakeNotComplete),
+        server.export_keying_material(&mut server_secret, b"label", Some(b"context"))
     );
-    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
-    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
+    do_handshake(&mut client, &mut server);
 
-    // resumed again
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    let (resume2_c2s, resume2_s2c) = do_handshake(&mut client, &mut server);
-    assert_eq!(resume_s2c, resume2_s2c);
-    assert_eq!(resume_c2s, resume2_c2s);
-    assert_eq!(storage.puts(), 6);
-    assert_eq!(storage.gets(), 0);
-    assert_eq!(storage.takes(), 2);
-    assert_eq!(
+    assert!(
         client
-            .peer_certificates()
-            .map(|certs| certs.len()),
-        Some(3)
+            .export_keying_material(&mut client_secret, b"label", Some(b"context"))
+            .is_ok()
     );
-    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
-    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
-}
-
-#[test]
-fn tls13_stateless_resumption() {
-    let kt = KeyType::Rsa2048;
-    let provider = provider::default_provider();
-    let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-    let client_config = Arc::new(client_config);
-
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.ticketer = provider::Ticketer::new().unwrap();
-    let storage = Arc::new(ServerStorage::new());
-    server_config.session_storage = storage.clone();
-    let server_config = Arc::new(server_config);
+    assert!(
+        server
+            .export_keying_material(&mut server_secret, b"label", Some(b"context"))
+            .is_ok()
+    );
+    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
 
-    // full handshake
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    let (full_c2s, full_s2c) = do_handshake(&mut client, &mut server);
-    assert_eq!(storage.puts(), 0);
-    assert_eq!(storage.gets(), 0);
-    assert_eq!(storage.takes(), 0);
+    let mut empty = vec![];
     assert_eq!(
         client
-            .peer_certificates()
-            .map(|certs| certs.len()),
-        Some(3)
+            .export_keying_material(&mut empty, b"label", Some(b"context"))
+            .err(),
+        Some(Error::General(
+            "export_keying_material with zero-length output".into()
+        ))
     );
-    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Full));
-    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Full));
-
-    // resumed
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
-    assert!(resume_c2s > full_c2s);
-    assert!(resume_s2c < full_s2c);
-    assert_eq!(storage.puts(), 0);
-    assert_eq!(storage.gets(), 0);
-    assert_eq!(storage.takes(), 0);
     assert_eq!(
-        client
-            .peer_certificates()
-            .map(|certs| certs.len()),
-        Some(3)
+        server
+            .export_keying_material(&mut empty, b"label", Some(b"context"))
+            .err(),
+        Some(Error::General(
+            "export_keying_material with zero-length output".into()
+        ))
     );
-    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
-    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
 
-    // resumed again
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    let (resume2_c2s, resume2_s2c) = do_handshake(&mut client, &mut server);
-    assert_eq!(resume_s2c, resume2_s2c);
-    assert_eq!(resume_c2s, resume2_c2s);
-    assert_eq!(storage.puts(), 0);
-    assert_eq!(storage.gets(), 0);
-    assert_eq!(storage.takes(), 0);
-    assert_eq!(
+    assert!(
         client
-            .peer_certificates()
-            .map(|certs| certs.len()),
-        Some(3)
+            .export_keying_material(&mut client_secret, b"label", None)
+            .is_ok()
     );
-    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
-    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
+    assert_ne!(client_secret.to_vec(), server_secret.to_vec());
+    assert!(
+        server
+            .export_keying_material(&mut server_secret, b"label", None)
+            .is_ok()
+    );
+    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
 }
 
 #[test]
-fn early_data_not_available() {
-    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    assert!(client.early_data().is_none());
+fn test_tls12_exporter() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let client_config =
+            make_client_config_with_versions(*kt, &[&rustls::version::TLS12], &provider);
+        let server_config = make_server_config(*kt, &provider);
+
+        do_exporter_test(client_config, server_config);
+    }
 }
 
-fn early_data_configs() -> (Arc<ClientConfig>, Arc<ServerConfig>) {
-    let kt = KeyType::Rsa2048;
+#[test]
+fn test_tls13_exporter() {
     let provider = provider::default_provider();
-    let mut client_config = make_client_config(kt, &provider);
-    client_config.enable_early_data = true;
-    client_config.resumption = Resumption::store(Arc::new(ClientStorage::new()));
+    for kt in KeyType::all_for_provider(&provider) {
+        let client_config =
+            make_client_config_with_versions(*kt, &[&rustls::version::TLS13], &provider);
+        let server_config = make_server_config(*kt, &provider);
 
-    let mut server_config = make_server_config(kt, &provider);
-    server_config.max_early_data_size = 1234;
-    (Arc::new(client_config), Arc::new(server_config))
+        do_exporter_test(client_config, server_config);
+    }
 }
 
 #[test]
-fn early_data_is_available_on_resumption() {
-    let (client_config, server_config) = early_data_configs();
+fn test_tls13_exporter_maximum_output_length() {
+    let provider = provider::default_provider();
+    let client_config =
+        make_client_config_with_versions(KeyType::EcdsaP256, &[&rustls::version::TLS13], &provider);
+    let server_config = make_server_config(KeyType::EcdsaP256, &provider);
 
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
     do_handshake(&mut client, &mut server);
 
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    assert!(client.early_data().is_some());
     assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .bytes_left(),
-        1234
+        client.negotiated_cipher_suite(),
+        Some(find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384))
     );
+
+    let mut maximum_allowed_output_client = [0u8; 255 * 48];
+    let mut maximum_allowed_output_server = [0u8; 255 * 48];
     client
-        .early_data()
-        .unwrap()
-        .flush()
+        .export_keying_material(
+            &mut maximum_allowed_output_client,
+            b"label",
+            Some(b"context"),
+        )
+        .unwrap();
+    server
+        .export_keying_material(
+            &mut maximum_allowed_output_server,
+            b"label",
+            Some(b"context"),
+        )
         .unwrap();
+
+    assert_eq!(maximum_allowed_output_client, maximum_allowed_output_server);
+
+    let mut too_long_output = [0u8; 255 * 48 + 1];
     assert_eq!(
         client
-            .early_data()
-            .unwrap()
-            .write(b"hello")
-            .unwrap(),
-        5
-    );
-    do_handshake(&mut client, &mut server);
-
-    let mut received_early_data = [0u8; 5];
+            .export_keying_material(&mut too_long_output, b"label", Some(b"context"),)
+            .err(),
+        Some(Error::General("exporting too much".into()))
+    );
     assert_eq!(
         server
-            .early_data()
-            .expect("early_data didn't happen")
-            .read(&mut received_early_data)
-            .expect("early_data failed unexpectedly"),
-        5
+            .export_keying_material(&mut too_long_output, b"label", Some(b"context"),)
+            .err(),
+        Some(Error::General("exporting too much".into()))
     );
-    assert_eq!(&received_early_data[..], b"hello");
 }
 
-#[test]
-fn early_data_not_available_on_server_before_client_hello() {
-    let mut server = ServerConnection::new(Arc::new(make_server_config(
-        KeyType::Rsa2048,
-        &provider::default_provider(),
-    )))
-    .unwrap();
-    assert!(server.early_data().is_none());
-}
+fn find_suite(suite: CipherSuite) -> SupportedCipherSuite {
+    for scs in provider::ALL_CIPHER_SUITES
+        .iter()
+        .copied()
+    {
+        if scs.suite() == suite {
+            return scs;
+        }
+    }
 
-#[test]
-fn early_data_can_be_rejected_by_server() {
-    let (client_config, server_config) = early_data_configs();
+    panic!("find_suite given unsupported suite");
+}
 
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
+fn test_ciphersuites() -> Vec<(
+    &'static rustls::SupportedProtocolVersion,
+    KeyType,
+    CipherSuite,
+)> {
+    let mut v = vec![
+        (
+            &rustls::version::TLS13,
+            KeyType::Rsa2048,
+            CipherSuite::TLS13_AES_256_GCM_SHA384,
+        ),
+        (
+            &rustls::version::TLS13,
+            KeyType::Rsa2048,
+            CipherSuite::TLS13_AES_128_GCM_SHA256,
+        ),
+        (
+            &rustls::version::TLS12,
+            KeyType::EcdsaP384,
+            CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+        ),
+        (
+            &rustls::version::TLS12,
+            KeyType::EcdsaP384,
+            CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+        ),
+        (
+            &rustls::version::TLS12,
+            KeyType::Rsa2048,
+            CipherSuite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+        ),
+        (
+            &rustls::version::TLS12,
+            KeyType::Rsa2048,
+            CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+        ),
+    ];
 
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    assert!(client.early_data().is_some());
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .bytes_left(),
-        1234
-    );
-    client
-        .early_data()
-        .unwrap()
-        .flush()
-        .unwrap();
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .write(b"hello")
-            .unwrap(),
-        5
-    );
-    server.reject_early_data();
-    do_handshake(&mut client, &mut server);
+    if !provider_is_fips() {
+        v.extend_from_slice(&[
+            (
+                &rustls::version::TLS13,
+                KeyType::Rsa2048,
+                CipherSuite::TLS13_CHACHA20_POLY1305_SHA256,
+            ),
+            (
+                &rustls::version::TLS12,
+                KeyType::EcdsaP256,
+                CipherSuite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+            ),
+            (
+                &rustls::version::TLS12,
+                KeyType::Rsa2048,
+                CipherSuite::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+            ),
+        ]);
+    }
 
-    assert!(!client.is_early_data_accepted());
+    v
 }
 
 #[test]
-fn early_data_is_limited_on_client() {
-    let (client_config, server_config) = early_data_configs();
-
-    // warm up
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
-
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    assert!(client.early_data().is_some());
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .bytes_left(),
-        1234
-    );
-    client
-        .early_data()
-        .unwrap()
-        .flush()
-        .unwrap();
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .write(&[0xaa; 1234 + 1])
-            .unwrap(),
-        1234
-    );
-    do_handshake(&mut client, &mut server);
+fn negotiated_ciphersuite_default() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        do_suite_and_kx_test(
+            make_client_config(*kt, &provider),
+            make_server_config(*kt, &provider),
+            find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384),
+            expected_kx_for_version(&rustls::version::TLS13),
+            ProtocolVersion::TLSv1_3,
+        );
+    }
+}
 
-    let mut received_early_data = [0u8; 1234];
+#[test]
+fn all_suites_covered() {
     assert_eq!(
-        server
-            .early_data()
-            .expect("early_data didn't happen")
-            .read(&mut received_early_data)
-            .expect("early_data failed unexpectedly"),
-        1234
+        provider::DEFAULT_CIPHER_SUITES.len(),
+        test_ciphersuites().len()
     );
-    assert_eq!(&received_early_data[..], [0xaa; 1234]);
 }
 
-fn early_data_configs_allowing_client_to_send_excess_data() -> (Arc<ClientConfig>, Arc<ServerConfig>)
-{
-    let (client_config, server_config) = early_data_configs();
-
-    // adjust client session storage to corrupt received max_early_data_size
-    let mut client_config = Arc::into_inner(client_config).unwrap();
-    let mut storage = ClientStorage::new();
-    storage.alter_max_early_data_size(1234, 2024);
-    client_config.resumption = Resumption::store(Arc::new(storage));
-    let client_config = Arc::new(client_config);
+#[test]
+fn aes_ccm_suites_are_opt_in() {
+    if !provider_is_rustcrypto() {
+        return;
+    }
 
-    // warm up
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake(&mut client, &mut server);
-    (client_config, server_config)
-}
+    for suite in [
+        CipherSuite::TLS13_AES_128_CCM_SHA256,
+        CipherSuite::TLS13_AES_128_CCM_8_SHA256,
+    ] {
+        assert!(
+            !provider::DEFAULT_CIPHER_SUITES
+                .iter()
+                .any(|scs| scs.suite() == suite)
+        );
 
-#[test]
-fn server_detects_excess_early_data() {
-    let (client_config, server_config) = early_data_configs_allowing_client_to_send_excess_data();
+        let scs = find_suite(suite);
+        let provider = CryptoProvider {
+            cipher_suites: vec![scs],
+            ..provider::default_provider()
+        };
+        let client_config = make_client_config_with_versions(
+            KeyType::EcdsaP256,
+            &[&rustls::version::TLS13],
+            &provider,
+        );
+        let server_config = make_server_config(KeyType::EcdsaP256, &provider);
+        do_suite_and_kx_test(
+            client_config.clone(),
+            server_config.clone(),
+            scs,
+            expected_kx_for_version(&rustls::version::TLS13),
+            ProtocolVersion::TLSv1_3,
+        );
 
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    assert!(client.early_data().is_some());
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .bytes_left(),
-        2024
-    );
-    client
-        .early_data()
-        .unwrap()
-        .flush()
-        .unwrap();
-    assert_eq!(
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
         client
-            .early_data()
-            .unwrap()
-            .write(&[0xaa; 2024])
-            .unwrap(),
-        2024
-    );
-    assert_eq!(
-        do_handshake_until_error(&mut client, &mut server),
-        Err(ErrorFromPeer::Server(Error::PeerMisbehaved(
-            PeerMisbehaved::TooMuchEarlyDataReceived
-        ))),
-    );
+            .writer()
+            .write_all(b"hello")
+            .unwrap();
+        transfer(&mut client, &mut server);
+        server.process_new_packets().unwrap();
+        check_read(&mut server.reader(), b"hello");
+    }
 }
 
-// regression test for https://github.com/rustls/rustls/issues/2096
 #[test]
-fn server_detects_excess_streamed_early_data() {
-    let (client_config, server_config) = early_data_configs_allowing_client_to_send_excess_data();
-
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    assert!(client.early_data().is_some());
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .bytes_left(),
-        2024
-    );
-    client
-        .early_data()
-        .unwrap()
-        .flush()
-        .unwrap();
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .write(&[0xaa; 1024])
+fn negotiated_ciphersuite_client() {
+    for (version, kt, suite) in test_ciphersuites() {
+        let scs = find_suite(suite);
+        let client_config = finish_client_config(
+            kt,
+            ClientConfig::builder_with_provider(
+                CryptoProvider {
+                    cipher_suites: vec![scs],
+                    ..provider::default_provider()
+                }
+                .into(),
+            )
+            .with_protocol_versions(&[version])
             .unwrap(),
-        1024
-    );
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
+        );
 
-    let mut received_early_data = [0u8; 1024];
-    assert_eq!(
-        server
-            .early_data()
-            .expect("early_data didn't happen")
-            .read(&mut received_early_data)
-            .expect("early_data failed unexpectedly"),
-        1024
-    );
-    assert_eq!(&received_early_data[..], [0xaa; 1024]);
+        do_suite_and_kx_test(
+            client_config,
+            make_server_config(kt, &provider::default_provider()),
+            scs,
+            expected_kx_for_version(version),
+            version.version(),
+        );
+    }
+}
 
-    assert_eq!(
-        client
-            .early_data()
-            .unwrap()
-            .write(&[0xbb; 1000])
+#[test]
+fn negotiated_ciphersuite_server() {
+    for (version, kt, suite) in test_ciphersuites() {
+        let scs = find_suite(suite);
+        let server_config = finish_server_config(
+            kt,
+            ServerConfig::builder_with_provider(
+                CryptoProvider {
+                    cipher_suites: vec![scs],
+                    ..provider::default_provider()
+                }
+                .into(),
+            )
+            .with_protocol_versions(&[version])
             .unwrap(),
-        1000
-    );
-    transfer(&mut client, &mut server);
-    assert_eq!(
-        server.process_new_packets(),
-        Err(Error::PeerMisbehaved(
-            PeerMisbehaved::TooMuchEarlyDataReceived
-        ))
-    );
+        );
+
+        do_suite_and_kx_test(
+            make_client_config(kt, &provider::default_provider()),
+            server_config,
+            scs,
+            expected_kx_for_version(version),
+            version.version(),
+        );
+    }
 }
 
-mod test_quic {
-    use rustls::quic::{self, ConnectionCommon};
+#[test]
+fn negotiated_ciphersuite_server_ignoring_client_preference() {
+    for (version, kt, suite) in test_ciphersuites() {
+        let scs = find_suite(suite);
+        let scs_other = if scs.suite() == CipherSuite::TLS13_AES_256_GCM_SHA384 {
+            find_suite(CipherSuite::TLS13_AES_128_GCM_SHA256)
+        } else {
+            find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384)
+        };
+        let mut server_config = finish_server_config(
+            kt,
+            ServerConfig::builder_with_provider(
+                CryptoProvider {
+                    cipher_suites: vec![scs, scs_other],
+                    ..provider::default_provider()
+                }
+                .into(),
+            )
+            .with_protocol_versions(&[version])
+            .unwrap(),
+        );
+        server_config.ignore_client_order = true;
 
-    use super::*;
+        let client_config = finish_client_config(
+            kt,
+            ClientConfig::builder_with_provider(
+                CryptoProvider {
+                    cipher_suites: vec![scs_other, scs],
+                    ..provider::default_provider()
+                }
+                .into(),
+            )
+            .with_safe_default_protocol_versions()
+            .unwrap(),
+        );
 
-    // Returns the sender's next secrets to use, or the receiver's error.
-    fn step<L: SideData, R: SideData>(
-        send: &mut ConnectionCommon<L>,
-        recv: &mut ConnectionCommon<R>,
-    ) -> Result<Option<quic::KeyChange>, Error> {
-        let mut buf = Vec::new();
-        let change = loop {
-            let prev = buf.len();
-            if let Some(x) = send.write_hs(&mut buf) {
-                break Some(x);
-            }
-            if prev == buf.len() {
-                break None;
-            }
-        };
+        do_suite_and_kx_test(
+            client_config,
+            server_config,
+            scs,
+            expected_kx_for_version(version),
+            version.version(),
+        );
+    }
+}
 
-        recv.read_hs(&buf)?;
-        assert_eq!(recv.alert(), None);
-        Ok(change)
+fn expected_kx_for_version(version: &SupportedProtocolVersion) -> NamedGroup {
+    match (
+        version.version(),
+        provider_is_aws_lc_rs() || provider_is_rustcrypto(),
+        provider_is_fips(),
+    ) {
+        (ProtocolVersion::TLSv1_3, true, _) => NamedGroup::X25519MLKEM768,
+        (_, _, true) => NamedGroup::secp256r1,
+        (_, _, _) => NamedGroup::X25519,
     }
+}
 
-    #[test]
-    fn test_quic_handshake() {
-        fn equal_packet_keys(x: &dyn quic::PacketKey, y: &dyn quic::PacketKey) -> bool {
-            // Check that these two sets of keys are equal.
-            let mut buf = [0; 32];
-            let (header, payload_tag) = buf.split_at_mut(8);
-            let (payload, tag_buf) = payload_tag.split_at_mut(8);
-            let tag = x
-                .encrypt_in_place(42, header, payload)
-                .unwrap();
-            tag_buf.copy_from_slice(tag.as_ref());
+#[derive(Debug, PartialEq)]
+struct KeyLogItem {
+    label: String,
+    client_random: Vec<u8>,
+    secret: Vec<u8>,
+}
 
-            let result = y.decrypt_in_place(42, header, payload_tag);
-            match result {
-                Ok(payload) => payload == [0; 8],
-                Err(_) => false,
-            }
+#[derive(Debug)]
+struct KeyLogToVec {
+    label: &'static str,
+    items: Mutex<Vec<KeyLogItem>>,
+}
+
+impl KeyLogToVec {
+    fn new(who: &'static str) -> Self {
+        Self {
+            label: who,
+            items: Mutex::new(vec![]),
         }
+    }
 
-        fn compatible_keys(x: &quic::KeyChange, y: &quic::KeyChange) -> bool {
-            fn keys(kc: &quic::KeyChange) -> &quic::Keys {
-                match kc {
-                    quic::KeyChange::Handshake { keys } => keys,
-                    quic::KeyChange::OneRtt { keys, .. } => keys,
-                }
-            }
+    fn take(&self) -> Vec<KeyLogItem> {
+        std::mem::take(&mut self.items.lock().unwrap())
+    }
+}
 
-            let (x, y) = (keys(x), keys(y));
-            equal_packet_keys(x.local.packet.as_ref(), y.remote.packet.as_ref())
-                && equal_packet_keys(x.remote.packet.as_ref(), y.local.packet.as_ref())
-        }
+impl KeyLog for KeyLogToVec {
+    fn log(&self, label: &str, client: &[u8], secret: &[u8]) {
+        let value = KeyLogItem {
+            label: label.into(),
+            client_random: client.into(),
+            secret: secret.into(),
+        };
 
-        let kt = KeyType::Rsa2048;
-        let provider = provider::default_provider();
-        let mut client_config =
-            make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-        client_config.enable_early_data = true;
-        let client_config = Arc::new(client_config);
-        let mut server_config =
-            make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-        server_config.max_early_data_size = 0xffffffff;
-        let server_config = Arc::new(server_config);
-        let client_params = &b"client params"[..];
-        let server_params = &b"server params"[..];
+        println!("key log {:?}: {:?}", self.label, value);
 
-        // full handshake
-        let mut client = quic::ClientConnection::new(
-            client_config.clone(),
-            quic::Version::V1,
-            server_name("localhost"),
-            client_params.into(),
-        )
-        .unwrap();
+        self.items.lock().unwrap().push(value);
+    }
+}
 
-        let mut server = quic::ServerConnection::new(
-            server_config.clone(),
-            quic::Version::V1,
-            server_params.into(),
-        )
-        .unwrap();
+#[test]
+fn key_log_for_tls12() {
+    let client_key_log = Arc::new(KeyLogToVec::new("client"));
+    let server_key_log = Arc::new(KeyLogToVec::new("server"));
 
-        let client_initial = step(&mut client, &mut server).unwrap();
-        assert!(client_initial.is_none());
-        assert!(client.zero_rtt_keys().is_none());
-        assert_eq!(server.quic_transport_parameters(), Some(client_params));
-        let server_hs = step(&mut server, &mut client)
-            .unwrap()
-            .unwrap();
-        assert!(server.zero_rtt_keys().is_none());
-        let client_hs = step(&mut client, &mut server)
-            .unwrap()
-            .unwrap();
-        assert!(compatible_keys(&server_hs, &client_hs));
-        assert!(client.is_handshaking());
-        let server_1rtt = step(&mut server, &mut client)
-            .unwrap()
-            .unwrap();
-        assert!(!client.is_handshaking());
-        assert_eq!(client.quic_transport_parameters(), Some(server_params));
-        assert!(server.is_handshaking());
-        let client_1rtt = step(&mut client, &mut server)
-            .unwrap()
-            .unwrap();
-        assert!(!server.is_handshaking());
-        assert!(compatible_keys(&server_1rtt, &client_1rtt));
-        assert!(!compatible_keys(&server_hs, &server_1rtt));
-
-        assert!(
-            step(&mut client, &mut server)
-                .unwrap()
-                .is_none()
-        );
-        assert!(
-            step(&mut server, &mut client)
-                .unwrap()
-                .is_none()
-        );
-        assert_eq!(client.tls13_tickets_received(), 2);
+    let provider = provider::default_provider();
+    let kt = KeyType::Rsa2048;
+    let mut client_config =
+        make_client_config_with_versions(kt, &[&rustls::version::TLS12], &provider);
+    client_config.key_log = client_key_log.clone();
+    let client_config = Arc::new(client_config);
 
-        // 0-RTT handshake
-        let mut client = quic::ClientConnection::new(
-            client_config.clone(),
-            quic::Version::V1,
-            server_name("localhost"),
-            client_params.into(),
-        )
-        .unwrap();
-        assert!(
-            client
-                .negotiated_cipher_suite()
-                .is_some()
-        );
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.key_log = server_key_log.clone();
+    let server_config = Arc::new(server_config);
 
-        let mut server = quic::ServerConnection::new(
-            server_config.clone(),
-            quic::Version::V1,
-            server_params.into(),
-        )
-        .unwrap();
+    // full handshake
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
 
-        step(&mut client, &mut server).unwrap();
-        assert_eq!(client.quic_transport_parameters(), Some(server_params));
-        {
-            let client_early = client.zero_rtt_keys().unwrap();
-            let server_early = server.zero_rtt_keys().unwrap();
-            assert!(equal_packet_keys(
-                client_early.packet.as_ref(),
-                server_early.packet.as_ref()
-            ));
-        }
-        step(&mut server, &mut client)
-            .unwrap()
-            .unwrap();
-        step(&mut client, &mut server)
-            .unwrap()
-            .unwrap();
-        step(&mut server, &mut client)
-            .unwrap()
-            .unwrap();
-        assert!(client.is_early_data_accepted());
-        // 0-RTT rejection
-        {
-            let client_config = (*client_config).clone();
-            let mut client = quic::ClientConnection::new(
-                Arc::new(client_config),
-                quic::Version::V1,
-                server_name("localhost"),
-                client_params.into(),
-            )
-            .unwrap();
+    let client_full_log = client_key_log.take();
+    let server_full_log = server_key_log.take();
+    assert_eq!(client_full_log, server_full_log);
+    assert_eq!(1, client_full_log.len());
+    assert_eq!("CLIENT_RANDOM", client_full_log[0].label);
 
-            let mut server = quic::ServerConnection::new(
-                server_config.clone(),
-                quic::Version::V1,
-                server_params.into(),
-            )
-            .unwrap();
-            server.reject_early_data();
+    // resumed
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
 
-            step(&mut client, &mut server).unwrap();
-            assert_eq!(client.quic_transport_parameters(), Some(server_params));
-            assert!(client.zero_rtt_keys().is_some());
-            assert!(server.zero_rtt_keys().is_none());
-            step(&mut server, &mut client)
-                .unwrap()
-                .unwrap();
-            step(&mut client, &mut server)
-                .unwrap()
-                .unwrap();
-            step(&mut server, &mut client)
-                .unwrap()
-                .unwrap();
-            assert!(!client.is_early_data_accepted());
-        }
+    let client_resume_log = client_key_log.take();
+    let server_resume_log = server_key_log.take();
+    assert_eq!(client_resume_log, server_resume_log);
+    assert_eq!(1, client_resume_log.len());
+    assert_eq!("CLIENT_RANDOM", client_resume_log[0].label);
+    assert_eq!(client_full_log[0].secret, client_resume_log[0].secret);
+}
 
-        // failed handshake
-        let mut client = quic::ClientConnection::new(
-            client_config,
-            quic::Version::V1,
-            server_name("example.com"),
-            client_params.into(),
-        )
-        .unwrap();
+#[test]
+fn key_log_for_tls13() {
+    let client_key_log = Arc::new(KeyLogToVec::new("client"));
+    let server_key_log = Arc::new(KeyLogToVec::new("server"));
 
-        let mut server =
-            quic::ServerConnection::new(server_config, quic::Version::V1, server_params.into())
-                .unwrap();
+    let provider = provider::default_provider();
+    let kt = KeyType::Rsa2048;
+    let mut client_config =
+        make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+    client_config.key_log = client_key_log.clone();
+    let client_config = Arc::new(client_config);
 
-        step(&mut client, &mut server).unwrap();
-        step(&mut server, &mut client)
-            .unwrap()
-            .unwrap();
-        assert!(step(&mut server, &mut client).is_err());
-        assert_eq!(
-            client.alert(),
-            Some(rustls::AlertDescription::BadCertificate)
-        );
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.key_log = server_key_log.clone();
+    let server_config = Arc::new(server_config);
 
-        // Key updates
+    // full handshake
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
 
-        let (
-            quic::KeyChange::OneRtt {
-                next: mut client_secrets,
-                ..
-            },
-            quic::KeyChange::OneRtt {
-                next: mut server_secrets,
-                ..
-            },
-        ) = (client_1rtt, server_1rtt)
-        else {
-            unreachable!();
-        };
+    let client_full_log = client_key_log.take();
+    let server_full_log = server_key_log.take();
 
-        let mut client_next = client_secrets.next_packet_keys();
-        let mut server_next = server_secrets.next_packet_keys();
-        assert!(equal_packet_keys(
-            client_next.local.as_ref(),
-            server_next.remote.as_ref()
-        ));
-        assert!(equal_packet_keys(
-            server_next.local.as_ref(),
-            client_next.remote.as_ref()
-        ));
+    assert_eq!(5, client_full_log.len());
+    assert_eq!("CLIENT_HANDSHAKE_TRAFFIC_SECRET", client_full_log[0].label);
+    assert_eq!("SERVER_HANDSHAKE_TRAFFIC_SECRET", client_full_log[1].label);
+    assert_eq!("CLIENT_TRAFFIC_SECRET_0", client_full_log[2].label);
+    assert_eq!("SERVER_TRAFFIC_SECRET_0", client_full_log[3].label);
+    assert_eq!("EXPORTER_SECRET", client_full_log[4].label);
 
-        client_next = client_secrets.next_packet_keys();
-        server_next = server_secrets.next_packet_keys();
-        assert!(equal_packet_keys(
-            client_next.local.as_ref(),
-            server_next.remote.as_ref()
-        ));
-        assert!(equal_packet_keys(
-            server_next.local.as_ref(),
-            client_next.remote.as_ref()
-        ));
-    }
+    assert_eq!(client_full_log[0], server_full_log[0]);
+    assert_eq!(client_full_log[1], server_full_log[1]);
+    assert_eq!(client_full_log[2], server_full_log[2]);
+    assert_eq!(client_full_log[3], server_full_log[3]);
+    assert_eq!(client_full_log[4], server_full_log[4]);
 
-    #[test]
-    fn test_quic_rejects_missing_alpn() {
-        let client_params = &b"client params"[..];
-        let server_params = &b"server params"[..];
-        let provider = provider::default_provider();
+    // resumed
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
 
-        for &kt in KeyType::all_for_provider(&provider) {
-            let client_config =
-                make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-            let client_config = Arc::new(client_config);
+    let client_resume_log = client_key_log.take();
+    let server_resume_log = server_key_log.take();
 
-            let mut server_config =
-                make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-            server_config.alpn_protocols = vec!["foo".into()];
-            let server_config = Arc::new(server_config);
+    assert_eq!(5, client_resume_log.len());
+    assert_eq!(
+        "CLIENT_HANDSHAKE_TRAFFIC_SECRET",
+        client_resume_log[0].label
+    );
+    assert_eq!(
+        "SERVER_HANDSHAKE_TRAFFIC_SECRET",
+        client_resume_log[1].label
+    );
+    assert_eq!("CLIENT_TRAFFIC_SECRET_0", client_resume_log[2].label);
+    assert_eq!("SERVER_TRAFFIC_SECRET_0", client_resume_log[3].label);
+    assert_eq!("EXPORTER_SECRET", client_resume_log[4].label);
 
-            let mut client = quic::ClientConnection::new(
-                client_config,
-                quic::Version::V1,
-                server_name("localhost"),
-                client_params.into(),
-            )
-            .unwrap();
-            let mut server =
-                quic::ServerConnection::new(server_config, quic::Version::V1, server_params.into())
-                    .unwrap();
+    assert_eq!(6, server_resume_log.len());
+    assert_eq!("CLIENT_EARLY_TRAFFIC_SECRET", server_resume_log[0].label);
+    assert_eq!(
+        "CLIENT_HANDSHAKE_TRAFFIC_SECRET",
+        server_resume_log[1].label
+    );
+    assert_eq!(
+        "SERVER_HANDSHAKE_TRAFFIC_SECRET",
+        server_resume_log[2].label
+    );
+    assert_eq!("CLIENT_TRAFFIC_SECRET_0", server_resume_log[3].label);
+    assert_eq!("SERVER_TRAFFIC_SECRET_0", server_resume_log[4].label);
+    assert_eq!("EXPORTER_SECRET", server_resume_log[5].label);
 
-            assert_eq!(
-                step(&mut client, &mut server)
-                    .err()
-                    .unwrap(),
-                Error::NoApplicationProtocol
-            );
+    assert_eq!(client_resume_log[0], server_resume_log[1]);
+    assert_eq!(client_resume_log[1], server_resume_log[2]);
+    assert_eq!(client_resume_log[2], server_resume_log[3]);
+    assert_eq!(client_resume_log[3], server_resume_log[4]);
+    assert_eq!(client_resume_log[4], server_resume_log[5]);
+}
 
-            assert_eq!(
-                server.alert(),
-                Some(rustls::AlertDescription::NoApplicationProtocol)
-            );
-        }
-    }
+#[cfg(feature = "aws-lc-rs")]
+#[test]
+fn key_log_for_ech() {
+    use aws_lc_rs::{aead, hmac};
 
-    #[test]
-    fn test_quic_no_tls13_error() {
-        let provider = provider::default_provider();
-        let mut client_config = make_client_config_with_versions(
-            KeyType::Ed25519,
-            &[&rustls::version::TLS12],
-            &provider,
-        );
-        client_config.alpn_protocols = vec!["foo".into()];
-        let client_config = Arc::new(client_config);
+    let client_key_log = Arc::new(KeyLogToVec::new("client"));
 
-        assert!(
-            quic::ClientConnection::new(
-                client_config,
-                quic::Version::V1,
-                server_name("localhost"),
-                b"client params".to_vec(),
-            )
-            .is_err()
-        );
+    let ech_config_list =
+        std::fs::read("tests/data/DHKEM_P256_HKDF_SHA256-HKDF_SHA256-AES_128_GCM-echconfigs.bin")
+            .unwrap();
+    let ech_config = EchConfig::new(
+        EchConfigListBytes::from(ech_config_list.clone()),
+        &[rustls::crypto::aws_lc_rs::hpke::DH_KEM_P256_HKDF_SHA256_AES_128],
+    )
+    .unwrap();
 
-        let mut server_config = make_server_config_with_versions(
-            KeyType::Ed25519,
-            &[&rustls::version::TLS12],
-            &provider,
-        );
-        server_config.alpn_protocols = vec!["foo".into()];
-        let server_config = Arc::new(server_config);
+    let config = ClientConfig::builder_with_provider(provider::default_provider().into())
+        .with_ech(EchMode::Enable(ech_config))
+        .unwrap();
+    let mut client_config = finish_client_config(KeyType::Rsa2048, config);
+    client_config.key_log = client_key_log.clone();
 
-        assert!(
-            quic::ServerConnection::new(
-                server_config,
-                quic::Version::V1,
-                b"server params".to_vec(),
-            )
-            .is_err()
-        );
-    }
+    let mut client =
+        ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+    let mut record = Vec::new();
+    client.write_tls(&mut record).unwrap();
+
+    // Dig out the outer hello random and the ECH extension payload.
+    let mut outer_hello = record[5 + 4..].to_vec();
+    let outer_random = outer_hello[2..34].to_vec();
+    let mut offs = 34;
+    offs += 1 + outer_hello[offs] as usize; // session_id
+    offs += 2 + u16::from_be_bytes([outer_hello[offs], outer_hello[offs + 1]]) as usize; // cipher_suites
+    offs += 1 + outer_hello[offs] as usize; // compression_methods
+    offs += 2; // extensions length
+    let payload = loop {
+        let typ = u16::from_be_bytes([outer_hello[offs], outer_hello[offs + 1]]);
+        let len = u16::from_be_bytes([outer_hello[offs + 2], outer_hello[offs + 3]]) as usize;
+        offs += 4;
+        if typ == 0xfe0d {
+            // type, cipher_suite, config_id
+            let mut ech = offs + 1 + 4 + 1;
+            ech += 2 + u16::from_be_bytes([outer_hello[ech], outer_hello[ech + 1]]) as usize; // enc
+            let payload_len = u16::from_be_bytes([outer_hello[ech], outer_hello[ech + 1]]) as usize;
+            break ech + 2..ech + 2 + payload_len;
+        }
+        offs += len;
+    };
+    let mut ciphertext = outer_hello[payload.clone()].to_vec();
+    // ClientHelloOuterAAD has the payload replaced with zeroes.
+    outer_hello[payload].fill(0);
+
+    let log = client_key_log.take();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].label, "ECH_SECRET");
+    assert_eq!(log[0].client_random, outer_random);
+    assert_eq!(log[1].label, "ECH_CONFIG");
+    assert_eq!(log[1].client_random, outer_random);
+    assert!(
+        ech_config_list
+            .windows(log[1].secret.len())
+            .any(|config| config == log[1].secret)
+    );
+
+    // HPKE base mode key schedule, with DHKEM(P-256, HKDF-SHA256), HKDF-SHA256, AES-128-GCM.
+    // See RFC 9180 section 5.1.
+    let suite_id = b"HPKE\x00\x10\x00\x01\x00\x01";
+    let labeled_extract = |salt: &[u8], label: &[u8], ikm: &[u8]| {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, salt);
+        hmac::sign(&key, &[b"HPKE-v1", &suite_id[..], label, ikm].concat())
+            .as_ref()
+            .to_vec()
+    };
+    let labeled_expand = |prk: &[u8], label: &[u8], info: &[u8], len: u16| {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, prk);
+        let info = [
+            &len.to_be_bytes()[..],
+            b"HPKE-v1",
+            suite_id,
+            label,
+            info,
+            &[1],
+        ]
+        .concat();
+        hmac::sign(&key, &info).as_ref()[..len as usize].to_vec()
+    };
 
-    #[test]
-    fn test_quic_invalid_early_data_size() {
-        let provider = provider::default_provider();
-        let mut server_config = make_server_config_with_versions(
-            KeyType::Ed25519,
-            &[&rustls::version::TLS13],
-            &provider,
+    let info = [&b"tls ech\0"[..], &log[1].secret].concat();
+    let key_schedule_context = [
+        &[0][..],
+        &labeled_extract(b"", b"psk_id_hash", b""),
+        &labeled_extract(b"", b"info_hash", &info),
+    ]
+    .concat();
+    let secret = labeled_extract(&log[0].secret, b"secret", b"");
+    let key = labeled_expand(&secret, b"key", &key_schedule_context, 16);
+    let base_nonce = labeled_expand(&secret, b"base_nonce", &key_schedule_context, 12);
+
+    let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &key).unwrap());
+    let inner_hello = key
+        .open_in_place(
+            aead::Nonce::try_assume_unique_for_key(&base_nonce).unwrap(),
+            aead::Aad::from(&outer_hello),
+            &mut ciphertext,
+        )
+        .unwrap();
+
+    // EncodedClientHelloInner starts with legacy_version and a fresh random.
+    assert_eq!(&inner_hello[..2], &[0x03, 0x03]);
+    assert_ne!(&inner_hello[2..34], &outer_random[..]);
+}
+
+#[cfg(feature = "ring-hpke")]
+#[test]
+fn ech_client_hello_with_ring_hpke_suites() {
+    use rustls::client::{EchConfig, EchMode};
+    use rustls::crypto::ring::hpke::ALL_SUPPORTED_SUITES;
+    use rustls::pki_types::EchConfigListBytes;
+
+    let mut tested = 0;
+    for suite in ALL_SUPPORTED_SUITES {
+        let suite_id = suite.suite();
+        let config_path = format!(
+            "tests/data/{:?}-{:?}-{:?}-echconfigs.bin",
+            suite_id.kem, suite_id.sym.kdf_id, suite_id.sym.aead_id
         );
-        server_config.alpn_protocols = vec!["foo".into()];
+        let Ok(ech_config_list) = std::fs::read(&config_path) else {
+            continue;
+        };
 
-        let cases = [
-            (None, true),
-            (Some(0u32), true),
-            (Some(5), false),
-            (Some(0xffff_ffff), true),
-        ];
+        let ech_config =
+            EchConfig::new(EchConfigListBytes::from(ech_config_list), &[*suite]).unwrap();
+        let config = ClientConfig::builder_with_provider(provider::default_provider().into())
+            .with_ech(EchMode::Enable(ech_config))
+            .unwrap();
+        let mut client_config = finish_client_config(KeyType::Rsa2048, config);
+        let client_key_log = Arc::new(KeyLogToVec::new("client"));
+        client_config.key_log = client_key_log.clone();
 
-        for &(size, ok) in cases.iter() {
-            println!("early data size case: {size:?}");
-            if let Some(new) = size {
-                server_config.max_early_data_size = new;
-            }
+        let mut client =
+            ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+        let mut record = Vec::new();
+        client.write_tls(&mut record).unwrap();
 
-            let wrapped = Arc::new(server_config.clone());
-            assert_eq!(
-                quic::ServerConnection::new(wrapped, quic::Version::V1, b"server params".to_vec(),)
-                    .is_ok(),
-                ok
-            );
-        }
+        let log = client_key_log.take();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].label, "ECH_SECRET");
+        assert_eq!(log[1].label, "ECH_CONFIG");
+        tested += 1;
     }
+    assert!(tested > 0);
+}
 
-    #[test]
-    fn test_quic_server_no_params_received() {
-        let provider = provider::default_provider();
-        let server_config = make_server_config_with_versions(
-            KeyType::Ed25519,
-            &[&rustls::version::TLS13],
-            &provider,
-        );
-        let server_config = Arc::new(server_config);
+#[test]
+fn vectored_write_for_server_appdata() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    do_handshake(&mut client, &mut server);
 
-        let mut server = quic::ServerConnection::new(
-            server_config,
-            quic::Version::V1,
-            b"server params".to_vec(),
-        )
+    server
+        .writer()
+        .write_all(b"01234567890123456789")
         .unwrap();
-
-        let buf = encoding::basic_client_hello(vec![]);
-        assert_eq!(
-            server.read_hs(buf.as_slice()).err(),
-            Some(Error::PeerMisbehaved(
-                PeerMisbehaved::MissingQuicTransportParameters
-            ))
-        );
+    server
+        .writer()
+        .write_all(b"01234567890123456789")
+        .unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        assert_eq!(84, wrlen);
+        assert_eq!(pipe.writevs, vec![vec![42, 42]]);
     }
+    check_read(
+        &mut client.reader(),
+        b"0123456789012345678901234567890123456789",
+    );
+}
 
-    #[test]
-    fn test_quic_server_no_tls12() {
-        let provider = provider::default_provider();
-        let mut server_config = make_server_config_with_versions(
-            KeyType::Ed25519,
-            &[&rustls::version::TLS13],
-            &provider,
-        );
-        server_config.alpn_protocols = vec!["foo".into()];
-        let server_config = Arc::new(server_config);
+#[test]
+fn vectored_write_for_client_appdata() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    do_handshake(&mut client, &mut server);
 
-        let mut server = quic::ServerConnection::new(
-            server_config,
-            quic::Version::V1,
-            b"server params".to_vec(),
-        )
+    client
+        .writer()
+        .write_all(b"01234567890123456789")
         .unwrap();
-
-        let buf = encoding::client_hello_with_extensions(vec![
-            encoding::Extension::new_sig_algs(),
-            encoding::Extension::new_dummy_key_share(),
-            encoding::Extension::new_kx_groups(),
-        ]);
-        assert_eq!(
-            server.read_hs(buf.as_slice()).err(),
-            Some(Error::PeerIncompatible(
-                PeerIncompatible::SupportedVersionsExtensionRequired
-            )),
-        );
+    client
+        .writer()
+        .write_all(b"01234567890123456789")
+        .unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut server);
+        let wrlen = client.write_tls(&mut pipe).unwrap();
+        assert_eq!(84, wrlen);
+        assert_eq!(pipe.writevs, vec![vec![42, 42]]);
     }
+    check_read(
+        &mut server.reader(),
+        b"0123456789012345678901234567890123456789",
+    );
+}
 
-    #[test]
-    fn packet_key_api() {
-        use cipher_suite::TLS13_AES_128_GCM_SHA256;
-        use rustls::Side;
-        use rustls::quic::{Keys, Version};
+#[test]
+fn vectored_write_for_server_handshake_with_half_rtt_data() {
+    let provider = provider::default_provider();
+    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+    server_config.send_half_rtt_data = true;
+    let (mut client, mut server) = make_pair_for_configs(
+        make_client_config_with_auth(KeyType::Rsa2048, &provider),
+        server_config,
+    );
 
-        // Test vectors: https://www.rfc-editor.org/rfc/rfc9001.html#name-client-initial
-        const CONNECTION_ID: &[u8] = &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
-        const PACKET_NUMBER: u64 = 2;
-        const PLAIN_HEADER: &[u8] = &[
-            0xc3, 0x00, 0x00, 0x00, 0x01, 0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08,
-            0x00, 0x00, 0x44, 0x9e, 0x00, 0x00, 0x00, 0x02,
-        ];
+    server
+        .writer()
+        .write_all(b"01234567890123456789")
+        .unwrap();
+    server
+        .writer()
+        .write_all(b"0123456789")
+        .unwrap();
 
-        const PAYLOAD: &[u8] = &[
-            0x06, 0x00, 0x40, 0xf1, 0x01, 0x00, 0x00, 0xed, 0x03, 0x03, 0xeb, 0xf8, 0xfa, 0x56,
-            0xf1, 0x29, 0x39, 0xb9, 0x58, 0x4a, 0x38, 0x96, 0x47, 0x2e, 0xc4, 0x0b, 0xb8, 0x63,
-            0xcf, 0xd3, 0xe8, 0x68, 0x04, 0xfe, 0x3a, 0x47, 0xf0, 0x6a, 0x2b, 0x69, 0x48, 0x4c,
-            0x00, 0x00, 0x04, 0x13, 0x01, 0x13, 0x02, 0x01, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00,
-            0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e,
-            0x63, 0x6f, 0x6d, 0xff, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0a, 0x00, 0x08, 0x00, 0x06,
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        // don't assert exact sizes here, to avoid a brittle test
+        assert!(wrlen > 2400); // its pretty big (contains cert chain)
+        assert_eq!(pipe.writevs.len(), 1); // only one writev
+        assert_eq!(pipe.writevs[0].len(), 5); // at least a server hello/ccs/cert/serverkx/0.5rtt data
+    }
+
+    client.process_new_packets().unwrap();
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        // 2 tickets (in one flight)
+        assert_eq!(wrlen, 184);
+        assert_eq!(pipe.writevs, vec![vec![184]]);
+    }
+
+    assert!(!server.is_handshaking());
+    assert!(!client.is_handshaking());
+    check_read(&mut client.reader(), b"012345678901234567890123456789");
+}
+
+fn check_half_rtt_does_not_work(server_config: ServerConfig) {
+    let (mut client, mut server) = make_pair_for_configs(
+        make_client_config_with_auth(KeyType::Rsa2048, &provider::default_provider()),
+        server_config,
+    );
+
+    server
+        .writer()
+        .write_all(b"01234567890123456789")
+        .unwrap();
+    server
+        .writer()
+        .write_all(b"0123456789")
+        .unwrap();
+
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        // don't assert exact sizes here, to avoid a brittle test
+        assert!(wrlen > 2400); // its pretty big (contains cert chain)
+        assert_eq!(pipe.writevs.len(), 1); // only one writev
+        assert_eq!(pipe.writevs[0].len(), 3); // at least a server hello/ccs/cert/serverkx data, in one message
+    }
+
+    // client second flight
+    client.process_new_packets().unwrap();
+    transfer(&mut client, &mut server);
+
+    // when client auth is enabled, we don't sent 0.5-rtt data, as we'd be sending
+    // it to an unauthenticated peer. so it happens here, in the server's second
+    // flight (42 and 32 are lengths of appdata sent above).
+    server.process_new_packets().unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        assert_eq!(wrlen, 258);
+        assert_eq!(pipe.writevs, vec![vec![184, 42, 32]]);
+    }
+
+    assert!(!server.is_handshaking());
+    assert!(!client.is_handshaking());
+    check_read(&mut client.reader(), b"012345678901234567890123456789");
+}
+
+#[test]
+fn vectored_write_for_server_handshake_no_half_rtt_with_client_auth() {
+    let mut server_config = make_server_config_with_mandatory_client_auth(
+        KeyType::Rsa2048,
+        &provider::default_provider(),
+    );
+    server_config.send_half_rtt_data = true; // ask even though it will be ignored
+    check_half_rtt_does_not_work(server_config);
+}
+
+#[test]
+fn vectored_write_for_server_handshake_no_half_rtt_by_default() {
+    let server_config = make_server_config(KeyType::Rsa2048, &provider::default_provider());
+    assert!(!server_config.send_half_rtt_data);
+    check_half_rtt_does_not_work(server_config);
+}
+
+#[test]
+fn vectored_write_for_client_handshake() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+
+    client
+        .writer()
+        .write_all(b"01234567890123456789")
+        .unwrap();
+    client
+        .writer()
+        .write_all(b"0123456789")
+        .unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut server);
+        let wrlen = client.write_tls(&mut pipe).unwrap();
+        // don't assert exact sizes here, to avoid a brittle test
+        assert!(wrlen > 200); // just the client hello
+        assert_eq!(pipe.writevs.len(), 1); // only one writev
+        assert!(pipe.writevs[0].len() == 1); // only a client hello
+    }
+
+    transfer(&mut server, &mut client);
+    client.process_new_packets().unwrap();
+
+    {
+        let mut pipe = OtherSession::new(&mut server);
+        let wrlen = client.write_tls(&mut pipe).unwrap();
+        assert_eq!(wrlen, 154);
+        // CCS, finished, then two application data records
+        assert_eq!(pipe.writevs, vec![vec![6, 74, 42, 32]]);
+    }
+
+    assert!(!server.is_handshaking());
+    assert!(!client.is_handshaking());
+    check_read(&mut server.reader(), b"012345678901234567890123456789");
+}
+
+#[test]
+fn vectored_write_with_slow_client() {
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+
+    client.set_buffer_limit(Some(32));
+
+    do_handshake(&mut client, &mut server);
+    server
+        .writer()
+        .write_all(b"01234567890123456789")
+        .unwrap();
+
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        pipe.short_writes = true;
+        let wrlen = server.write_tls(&mut pipe).unwrap()
+            + server.write_tls(&mut pipe).unwrap()
+            + server.write_tls(&mut pipe).unwrap()
+            + server.write_tls(&mut pipe).unwrap()
+            + server.write_tls(&mut pipe).unwrap()
+            + server.write_tls(&mut pipe).unwrap();
+        assert_eq!(42, wrlen);
+        assert_eq!(
+            pipe.writevs,
+            vec![vec![21], vec![10], vec![5], vec![3], vec![3]]
+        );
+    }
+    check_read(&mut client.reader(), b"01234567890123456789");
+}
+
+struct ServerStorage {
+    storage: Arc<dyn rustls::server::StoresServerSessions>,
+    put_count: AtomicUsize,
+    get_count: AtomicUsize,
+    take_count: AtomicUsize,
+}
+
+impl ServerStorage {
+    fn new() -> Self {
+        Self {
+            storage: rustls::server::ServerSessionMemoryCache::new(1024),
+            put_count: AtomicUsize::new(0),
+            get_count: AtomicUsize::new(0),
+            take_count: AtomicUsize::new(0),
+        }
+    }
+
+    fn puts(&self) -> usize {
+        self.put_count.load(Ordering::SeqCst)
+    }
+    fn gets(&self) -> usize {
+        self.get_count.load(Ordering::SeqCst)
+    }
+    fn takes(&self) -> usize {
+        self.take_count.load(Ordering::SeqCst)
+    }
+}
+
+impl fmt::Debug for ServerStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "(put: {:?}, get: {:?}, take: {:?})",
+            self.put_count, self.get_count, self.take_count
+        )
+    }
+}
+
+impl rustls::server::StoresServerSessions for ServerStorage {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.put_count
+            .fetch_add(1, Ordering::SeqCst);
+        self.storage.put(key, value)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.get_count
+            .fetch_add(1, Ordering::SeqCst);
+        self.storage.get(key)
+    }
+
+    fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.take_count
+            .fetch_add(1, Ordering::SeqCst);
+        self.storage.take(key)
+    }
+
+    fn can_cache(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // complete mock, but not 100% used in tests
+enum ClientStorageOp {
+    SetKxHint(ServerName<'static>, rustls::NamedGroup),
+    GetKxHint(ServerName<'static>, Option<rustls::NamedGroup>),
+    SetTls12Session(ServerName<'static>),
+    GetTls12Session(ServerName<'static>, bool),
+    RemoveTls12Session(ServerName<'static>),
+    InsertTls13Ticket(ServerName<'static>),
+    TakeTls13Ticket(ServerName<'static>, bool),
+}
+
+struct ClientStorage {
+    storage: Arc<dyn rustls::client::ClientSessionStore>,
+    ops: Mutex<Vec<ClientStorageOp>>,
+    alter_max_early_data_size: Option<(u32, u32)>,
+}
+
+impl ClientStorage {
+    fn new() -> Self {
+        Self {
+            storage: Arc::new(rustls::client::ClientSessionMemoryCache::new(1024)),
+            ops: Mutex::new(Vec::new()),
+            alter_max_early_data_size: None,
+        }
+    }
+
+    fn alter_max_early_data_size(&mut self, expected: u32, altered: u32) {
+        self.alter_max_early_data_size = Some((expected, altered));
+    }
+
+    fn ops(&self) -> Vec<ClientStorageOp> {
+        self.ops.lock().unwrap().clone()
+    }
+
+    fn ops_and_reset(&self) -> Vec<ClientStorageOp> {
+        std::mem::take(&mut self.ops.lock().unwrap())
+    }
+}
+
+impl fmt::Debug for ClientStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(ops: {:?})", self.ops.lock().unwrap())
+    }
+}
+
+impl rustls::client::ClientSessionStore for ClientStorage {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: rustls::NamedGroup) {
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::SetKxHint(server_name.clone(), group));
+        self.storage
+            .set_kx_hint(server_name, group)
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<rustls::NamedGroup> {
+        let rc = self.storage.kx_hint(server_name);
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::GetKxHint(server_name.to_owned(), rc));
+        rc
+    }
+
+    fn set_tls12_session(
+        &self,
+        server_name: ServerName<'static>,
+        value: rustls::client::Tls12ClientSessionValue,
+    ) {
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::SetTls12Session(server_name.clone()));
+        self.storage
+            .set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(
+        &self,
+        server_name: &ServerName<'_>,
+    ) -> Option<rustls::client::Tls12ClientSessionValue> {
+        let rc = self.storage.tls12_session(server_name);
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::GetTls12Session(
+                server_name.to_owned(),
+                rc.is_some(),
+            ));
+        rc
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::RemoveTls12Session(server_name.clone()));
+        self.storage
+            .remove_tls12_session(server_name);
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        mut value: rustls::client::Tls13ClientSessionValue,
+    ) {
+        if let Some((expected, desired)) = self.alter_max_early_data_size {
+            assert_eq!(value.max_early_data_size(), expected);
+            value._private_set_max_early_data_size(desired);
+        }
+
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::InsertTls13Ticket(server_name.clone()));
+        self.storage
+            .insert_tls13_ticket(server_name, value);
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<rustls::client::Tls13ClientSessionValue> {
+        let rc = self
+            .storage
+            .take_tls13_ticket(server_name);
+        self.ops
+            .lock()
+            .unwrap()
+            .push(ClientStorageOp::TakeTls13Ticket(
+                server_name.clone(),
+                rc.is_some(),
+            ));
+        rc
+    }
+}
+
+#[test]
+fn tls13_stateful_resumption() {
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+    let client_config = Arc::new(client_config);
+
+    let mut server_config = make_server_config(kt, &provider);
+    let storage = Arc::new(ServerStorage::new());
+    server_config.session_storage = storage.clone();
+    let server_config = Arc::new(server_config);
+
+    // full handshake
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (full_c2s, full_s2c) = do_handshake(&mut client, &mut server);
+    assert_eq!(client.tls13_tickets_received(), 2);
+    assert_eq!(storage.puts(), 2);
+    assert_eq!(storage.gets(), 0);
+    assert_eq!(storage.takes(), 0);
+    assert_eq!(
+        client
+            .peer_certificates()
+            .map(|certs| certs.len()),
+        Some(3)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Full));
+    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Full));
+
+    // resumed
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
+    assert!(resume_c2s > full_c2s);
+    assert!(resume_s2c < full_s2c);
+    assert_eq!(storage.puts(), 4);
+    assert_eq!(storage.gets(), 0);
+    assert_eq!(storage.takes(), 1);
+    assert_eq!(
+        client
+            .peer_certificates()
+            .map(|certs| certs.len()),
+        Some(3)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
+    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
+
+    // resumed again
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (resume2_c2s, resume2_s2c) = do_handshake(&mut client, &mut server);
+    assert_eq!(resume_s2c, resume2_s2c);
+    assert_eq!(resume_c2s, resume2_c2s);
+    assert_eq!(storage.puts(), 6);
+    assert_eq!(storage.gets(), 0);
+    assert_eq!(storage.takes(), 2);
+    assert_eq!(
+        client
+            .peer_certificates()
+            .map(|certs| certs.len()),
+        Some(3)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
+    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
+}
+
+#[test]
+fn tls13_stateless_resumption() {
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+    let client_config = Arc::new(client_config);
+
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.ticketer = provider::Ticketer::new().unwrap();
+    let storage = Arc::new(ServerStorage::new());
+    server_config.session_storage = storage.clone();
+    let server_config = Arc::new(server_config);
+
+    // full handshake
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (full_c2s, full_s2c) = do_handshake(&mut client, &mut server);
+    assert_eq!(storage.puts(), 0);
+    assert_eq!(storage.gets(), 0);
+    assert_eq!(storage.takes(), 0);
+    assert_eq!(
+        client
+            .peer_certificates()
+            .map(|certs| certs.len()),
+        Some(3)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Full));
+    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Full));
+
+    // resumed
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
+    assert!(resume_c2s > full_c2s);
+    assert!(resume_s2c < full_s2c);
+    assert_eq!(storage.puts(), 0);
+    assert_eq!(storage.gets(), 0);
+    assert_eq!(storage.takes(), 0);
+    assert_eq!(
+        client
+            .peer_certificates()
+            .map(|certs| certs.len()),
+        Some(3)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
+    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
+
+    // resumed again
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let (resume2_c2s, resume2_s2c) = do_handshake(&mut client, &mut server);
+    assert_eq!(resume_s2c, resume2_s2c);
+    assert_eq!(resume_c2s, resume2_c2s);
+    assert_eq!(storage.puts(), 0);
+    assert_eq!(storage.gets(), 0);
+    assert_eq!(storage.takes(), 0);
+    assert_eq!(
+        client
+            .peer_certificates()
+            .map(|certs| certs.len()),
+        Some(3)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Resumed));
+    assert_eq!(server.handshake_kind(), Some(HandshakeKind::Resumed));
+}
+
+#[test]
+fn early_data_not_available() {
+    let (mut client, _) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    assert!(client.early_data().is_none());
+}
+
+fn early_data_configs() -> (Arc<ClientConfig>, Arc<ServerConfig>) {
+    let kt = KeyType::Rsa2048;
+    let provider = provider::default_provider();
+    let mut client_config = make_client_config(kt, &provider);
+    client_config.enable_early_data = true;
+    client_config.resumption = Resumption::store(Arc::new(ClientStorage::new()));
+
+    let mut server_config = make_server_config(kt, &provider);
+    server_config.max_early_data_size = 1234;
+    (Arc::new(client_config), Arc::new(server_config))
+}
+
+#[test]
+fn early_data_is_available_on_resumption() {
+    let (client_config, server_config) = early_data_configs();
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert!(client.early_data().is_some());
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .bytes_left(),
+        1234
+    );
+    client
+        .early_data()
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(b"hello")
+            .unwrap(),
+        5
+    );
+    do_handshake(&mut client, &mut server);
+
+    let mut received_early_data = [0u8; 5];
+    assert_eq!(
+        server
+            .early_data()
+            .expect("early_data didn't happen")
+            .read(&mut received_early_data)
+            .expect("early_data failed unexpectedly"),
+        5
+    );
+    assert_eq!(&received_early_data[..], b"hello");
+}
+
+#[test]
+fn early_data_not_available_on_server_before_client_hello() {
+    let mut server = ServerConnection::new(Arc::new(make_server_config(
+        KeyType::Rsa2048,
+        &provider::default_provider(),
+    )))
+    .unwrap();
+    assert!(server.early_data().is_none());
+}
+
+#[test]
+fn early_data_can_be_rejected_by_server() {
+    let (client_config, server_config) = early_data_configs();
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert!(client.early_data().is_some());
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .bytes_left(),
+        1234
+    );
+    client
+        .early_data()
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(b"hello")
+            .unwrap(),
+        5
+    );
+    server.reject_early_data();
+    do_handshake(&mut client, &mut server);
+
+    assert!(!client.is_early_data_accepted());
+}
+
+#[test]
+fn early_data_is_limited_on_client() {
+    let (client_config, server_config) = early_data_configs();
+
+    // warm up
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert!(client.early_data().is_some());
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .bytes_left(),
+        1234
+    );
+    client
+        .early_data()
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(&[0xaa; 1234 + 1])
+            .unwrap(),
+        1234
+    );
+    do_handshake(&mut client, &mut server);
+
+    let mut received_early_data = [0u8; 1234];
+    assert_eq!(
+        server
+            .early_data()
+            .expect("early_data didn't happen")
+            .read(&mut received_early_data)
+            .expect("early_data failed unexpectedly"),
+        1234
+    );
+    assert_eq!(&received_early_data[..], [0xaa; 1234]);
+}
+
+fn early_data_configs_allowing_client_to_send_excess_data() -> (Arc<ClientConfig>, Arc<ServerConfig>)
+{
+    let (client_config, server_config) = early_data_configs();
+
+    // adjust client session storage to corrupt received max_early_data_size
+    let mut client_config = Arc::into_inner(client_config).unwrap();
+    let mut storage = ClientStorage::new();
+    storage.alter_max_early_data_size(1234, 2024);
+    client_config.resumption = Resumption::store(Arc::new(storage));
+    let client_config = Arc::new(client_config);
+
+    // warm up
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
+    (client_config, server_config)
+}
+
+#[test]
+fn server_detects_excess_early_data() {
+    let (client_config, server_config) = early_data_configs_allowing_client_to_send_excess_data();
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert!(client.early_data().is_some());
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .bytes_left(),
+        2024
+    );
+    client
+        .early_data()
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(&[0xaa; 2024])
+            .unwrap(),
+        2024
+    );
+    assert_eq!(
+        do_handshake_until_error(&mut client, &mut server),
+        Err(ErrorFromPeer::Server(Error::PeerMisbehaved(
+            PeerMisbehaved::TooMuchEarlyDataReceived
+        ))),
+    );
+}
+
+// regression test for https://github.com/rustls/rustls/issues/2096
+#[test]
+fn server_detects_excess_streamed_early_data() {
+    let (client_config, server_config) = early_data_configs_allowing_client_to_send_excess_data();
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert!(client.early_data().is_some());
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .bytes_left(),
+        2024
+    );
+    client
+        .early_data()
+        .unwrap()
+        .flush()
+        .unwrap();
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(&[0xaa; 1024])
+            .unwrap(),
+        1024
+    );
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+
+    let mut received_early_data = [0u8; 1024];
+    assert_eq!(
+        server
+            .early_data()
+            .expect("early_data didn't happen")
+            .read(&mut received_early_data)
+            .expect("early_data failed unexpectedly"),
+        1024
+    );
+    assert_eq!(&received_early_data[..], [0xaa; 1024]);
+
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(&[0xbb; 1000])
+            .unwrap(),
+        1000
+    );
+    transfer(&mut client, &mut server);
+    assert_eq!(
+        server.process_new_packets(),
+        Err(Error::PeerMisbehaved(
+            PeerMisbehaved::TooMuchEarlyDataReceived
+        ))
+    );
+}
+
+mod test_quic {
+    use rustls::quic::{self, ConnectionCommon};
+
+    use super::*;
+
+    // Returns the sender's next secrets to use, or the receiver's error.
+    fn step<L: SideData, R: SideData>(
+        send: &mut ConnectionCommon<L>,
+        recv: &mut ConnectionCommon<R>,
+    ) -> Result<Option<quic::KeyChange>, Error> {
+        let mut buf = Vec::new();
+        let change = loop {
+            let prev = buf.len();
+            if let Some(x) = send.write_hs(&mut buf) {
+                break Some(x);
+            }
+            if prev == buf.len() {
+                break None;
+            }
+        };
+
+        recv.read_hs(&buf)?;
+        assert_eq!(recv.alert(), None);
+        Ok(change)
+    }
+
+    #[test]
+    fn test_quic_handshake() {
+        fn equal_packet_keys(x: &dyn quic::PacketKey, y: &dyn quic::PacketKey) -> bool {
+            // Check that these two sets of keys are equal.
+            let mut buf = [0; 32];
+            let (header, payload_tag) = buf.split_at_mut(8);
+            let (payload, tag_buf) = payload_tag.split_at_mut(8);
+            let tag = x
+                .encrypt_in_place(42, header, payload)
+                .unwrap();
+            tag_buf.copy_from_slice(tag.as_ref());
+
+            let result = y.decrypt_in_place(42, header, payload_tag);
+            match result {
+                Ok(payload) => payload == [0; 8],
+                Err(_) => false,
+            }
+        }
+
+        fn compatible_keys(x: &quic::KeyChange, y: &quic::KeyChange) -> bool {
+            fn keys(kc: &quic::KeyChange) -> &quic::Keys {
+                match kc {
+                    quic::KeyChange::Handshake { keys } => keys,
+                    quic::KeyChange::OneRtt { keys, .. } => keys,
+                }
+            }
+
+            let (x, y) = (keys(x), keys(y));
+            equal_packet_keys(x.local.packet.as_ref(), y.remote.packet.as_ref())
+                && equal_packet_keys(x.remote.packet.as_ref(), y.local.packet.as_ref())
+        }
+
+        let kt = KeyType::Rsa2048;
+        let provider = provider::default_provider();
+        let mut client_config =
+            make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+        client_config.enable_early_data = true;
+        let client_config = Arc::new(client_config);
+        let mut server_config =
+            make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+        server_config.max_early_data_size = 0xffffffff;
+        let server_config = Arc::new(server_config);
+        let client_params = &b"client params"[..];
+        let server_params = &b"server params"[..];
+
+        // full handshake
+        let mut client = quic::ClientConnection::new(
+            client_config.clone(),
+            quic::Version::V1,
+            server_name("localhost"),
+            client_params.into(),
+        )
+        .unwrap();
+
+        let mut server = quic::ServerConnection::new(
+            server_config.clone(),
+            quic::Version::V1,
+            server_params.into(),
+        )
+        .unwrap();
+
+        let client_initial = step(&mut client, &mut server).unwrap();
+        assert!(client_initial.is_none());
+        assert!(client.zero_rtt_keys().is_none());
+        assert_eq!(server.quic_transport_parameters(), Some(client_params));
+        let server_hs = step(&mut server, &mut client)
+            .unwrap()
+            .unwrap();
+        assert!(server.zero_rtt_keys().is_none());
+        let client_hs = step(&mut client, &mut server)
+            .unwrap()
+            .unwrap();
+        assert!(compatible_keys(&server_hs, &client_hs));
+        assert!(client.is_handshaking());
+        let server_1rtt = step(&mut server, &mut client)
+            .unwrap()
+            .unwrap();
+        assert!(!client.is_handshaking());
+        assert_eq!(client.quic_transport_parameters(), Some(server_params));
+        assert!(server.is_handshaking());
+        let client_1rtt = step(&mut client, &mut server)
+            .unwrap()
+            .unwrap();
+        assert!(!server.is_handshaking());
+        assert!(compatible_keys(&server_1rtt, &client_1rtt));
+        assert!(!compatible_keys(&server_hs, &server_1rtt));
+
+        assert!(
+            step(&mut client, &mut server)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            step(&mut server, &mut client)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(client.tls13_tickets_received(), 2);
+
+        // 0-RTT handshake
+        let mut client = quic::ClientConnection::new(
+            client_config.clone(),
+            quic::Version::V1,
+            server_name("localhost"),
+            client_params.into(),
+        )
+        .unwrap();
+        assert!(
+            client
+                .negotiated_cipher_suite()
+                .is_some()
+        );
+
+        let mut server = quic::ServerConnection::new(
+            server_config.clone(),
+            quic::Version::V1,
+            server_params.into(),
+        )
+        .unwrap();
+
+        step(&mut client, &mut server).unwrap();
+        assert_eq!(client.quic_transport_parameters(), Some(server_params));
+        {
+            let client_early = client.zero_rtt_keys().unwrap();
+            let server_early = server.zero_rtt_keys().unwrap();
+            assert!(equal_packet_keys(
+                client_early.packet.as_ref(),
+                server_early.packet.as_ref()
+            ));
+        }
+        step(&mut server, &mut client)
+            .unwrap()
+            .unwrap();
+        step(&mut client, &mut server)
+            .unwrap()
+            .unwrap();
+        step(&mut server, &mut client)
+            .unwrap()
+            .unwrap();
+        assert!(client.is_early_data_accepted());
+        assert!(
+            client
+                .connection_info()
+                .early_data_accepted
+        );
+        assert!(
+            server
+                .connection_info()
+                .early_data_accepted
+        );
+        // 0-RTT rejection
+        {
+            let client_config = (*client_config).clone();
+            let mut client = quic::ClientConnection::new(
+                Arc::new(client_config),
+                quic::Version::V1,
+                server_name("localhost"),
+                client_params.into(),
+            )
+            .unwrap();
+
+            let mut server = quic::ServerConnection::new(
+                server_config.clone(),
+                quic::Version::V1,
+                server_params.into(),
+            )
+            .unwrap();
+            server.reject_early_data();
+
+            step(&mut client, &mut server).unwrap();
+            assert_eq!(client.quic_transport_parameters(), Some(server_params));
+            assert!(client.zero_rtt_keys().is_some());
+            assert!(server.zero_rtt_keys().is_none());
+            step(&mut server, &mut client)
+                .unwrap()
+                .unwrap();
+            step(&mut client, &mut server)
+                .unwrap()
+                .unwrap();
+            step(&mut server, &mut client)
+                .unwrap()
+                .unwrap();
+            assert!(!client.is_early_data_accepted());
+            assert!(
+                !client
+                    .connection_info()
+                    .early_data_accepted
+            );
+            assert!(
+                !server
+                    .connection_info()
+                    .early_data_accepted
+            );
+        }
+
+        // failed handshake
+        let mut client = quic::ClientConnection::new(
+            client_config,
+            quic::Version::V1,
+            server_name("example.com"),
+            client_params.into(),
+        )
+        .unwrap();
+
+        let mut server =
+            quic::ServerConnection::new(server_config, quic::Version::V1, server_params.into())
+                .unwrap();
+
+        step(&mut client, &mut server).unwrap();
+        step(&mut server, &mut client)
+            .unwrap()
+            .unwrap();
+        assert!(step(&mut server, &mut client).is_err());
+        assert_eq!(
+            client.alert(),
+            Some(rustls::AlertDescription::BadCertificate)
+        );
+
+        // Key updates
+
+        let (
+            quic::KeyChange::OneRtt {
+                next: mut client_secrets,
+                ..
+            },
+            quic::KeyChange::OneRtt {
+                next: mut server_secrets,
+                ..
+            },
+        ) = (client_1rtt, server_1rtt)
+        else {
+            unreachable!();
+        };
+
+        let mut client_next = client_secrets.next_packet_keys();
+        let mut server_next = server_secrets.next_packet_keys();
+        assert!(equal_packet_keys(
+            client_next.local.as_ref(),
+            server_next.remote.as_ref()
+        ));
+        assert!(equal_packet_keys(
+            server_next.local.as_ref(),
+            client_next.remote.as_ref()
+        ));
+
+        client_next = client_secrets.next_packet_keys();
+        server_next = server_secrets.next_packet_keys();
+        assert!(equal_packet_keys(
+            client_next.local.as_ref(),
+            server_next.remote.as_ref()
+        ));
+        assert!(equal_packet_keys(
+            server_next.local.as_ref(),
+            client_next.remote.as_ref()
+        ));
+    }
+
+    #[test]
+    fn test_quic_rejects_missing_alpn() {
+        let client_params = &b"client params"[..];
+        let server_params = &b"server params"[..];
+        let provider = provider::default_provider();
+
+        for &kt in KeyType::all_for_provider(&provider) {
+            let client_config =
+                make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+            let client_config = Arc::new(client_config);
+
+            let mut server_config =
+                make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+            server_config.alpn_protocols = vec!["foo".into()];
+            let server_config = Arc::new(server_config);
+
+            let mut client = quic::ClientConnection::new(
+                client_config,
+                quic::Version::V1,
+                server_name("localhost"),
+                client_params.into(),
+            )
+            .unwrap();
+            let mut server =
+                quic::ServerConnection::new(server_config, quic::Version::V1, server_params.into())
+                    .unwrap();
+
+            assert_eq!(
+                step(&mut client, &mut server)
+                    .err()
+                    .unwrap(),
+                Error::NoApplicationProtocol
+            );
+
+            assert_eq!(
+                server.alert(),
+                Some(rustls::AlertDescription::NoApplicationProtocol)
+            );
+        }
+    }
+
+    #[test]
+    fn test_quic_no_tls13_error() {
+        let provider = provider::default_provider();
+        let mut client_config = make_client_config_with_versions(
+            KeyType::Ed25519,
+            &[&rustls::version::TLS12],
+            &provider,
+        );
+        client_config.alpn_protocols = vec!["foo".into()];
+        let client_config = Arc::new(client_config);
+
+        assert!(
+            quic::ClientConnection::new(
+                client_config,
+                quic::Version::V1,
+                server_name("localhost"),
+                b"client params".to_vec(),
+            )
+            .is_err()
+        );
+
+        let mut server_config = make_server_config_with_versions(
+            KeyType::Ed25519,
+            &[&rustls::version::TLS12],
+            &provider,
+        );
+        server_config.alpn_protocols = vec!["foo".into()];
+        let server_config = Arc::new(server_config);
+
+        assert!(
+            quic::ServerConnection::new(
+                server_config,
+                quic::Version::V1,
+                b"server params".to_vec(),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_quic_invalid_early_data_size() {
+        let provider = provider::default_provider();
+        let mut server_config = make_server_config_with_versions(
+            KeyType::Ed25519,
+            &[&rustls::version::TLS13],
+            &provider,
+        );
+        server_config.alpn_protocols = vec!["foo".into()];
+
+        let cases = [
+            (None, true),
+            (Some(0u32), true),
+            (Some(5), false),
+            (Some(0xffff_ffff), true),
+        ];
+
+        for &(size, ok) in cases.iter() {
+            println!("early data size case: {size:?}");
+            if let Some(new) = size {
+                server_config.max_early_data_size = new;
+            }
+
+            let wrapped = Arc::new(server_config.clone());
+            assert_eq!(
+                quic::ServerConnection::new(wrapped, quic::Version::V1, b"server params".to_vec(),)
+                    .is_ok(),
+                ok
+            );
+        }
+    }
+
+    #[test]
+    fn test_quic_server_no_params_received() {
+        let provider = provider::default_provider();
+        let server_config = make_server_config_with_versions(
+            KeyType::Ed25519,
+            &[&rustls::version::TLS13],
+            &provider,
+        );
+        let server_config = Arc::new(server_config);
+
+        let mut server = quic::ServerConnection::new(
+            server_config,
+            quic::Version::V1,
+            b"server params".to_vec(),
+        )
+        .unwrap();
+
+        let buf = encoding::basic_client_hello(vec![]);
+        assert_eq!(
+            server.read_hs(buf.as_slice()).err(),
+            Some(Error::PeerMisbehaved(
+                PeerMisbehaved::MissingQuicTransportParameters
+            ))
+        );
+    }
+
+    #[test]
+    fn test_quic_server_no_tls12() {
+        let provider = provider::default_provider();
+        let mut server_config = make_server_config_with_versions(
+            KeyType::Ed25519,
+            &[&rustls::version::TLS13],
+            &provider,
+        );
+        server_config.alpn_protocols = vec!["foo".into()];
+        let server_config = Arc::new(server_config);
+
+        let mut server = quic::ServerConnection::new(
+            server_config,
+            quic::Version::V1,
+            b"server params".to_vec(),
+        )
+        .unwrap();
+
+        let buf = encoding::client_hello_with_extensions(vec![
+            encoding::Extension::new_sig_algs(),
+            encoding::Extension::new_dummy_key_share(),
+            encoding::Extension::new_kx_groups(),
+        ]);
+        assert_eq!(
+            server.read_hs(buf.as_slice()).err(),
+            Some(Error::PeerIncompatible(
+                PeerIncompatible::SupportedVersionsExtensionRequired
+            )),
+        );
+    }
+
+    #[test]
+    fn test_quic_aes_ccm() {
+        if !provider_is_rustcrypto() {
+            return;
+        }
+
+        let ccm = find_suite(CipherSuite::TLS13_AES_128_CCM_SHA256);
+        let ccm_8 = find_suite(CipherSuite::TLS13_AES_128_CCM_8_SHA256);
+
+        // AES-128-CCM is usable with QUIC, but AES-128-CCM-8 is not.
+        let provider = CryptoProvider {
+            cipher_suites: vec![ccm_8, ccm],
+            ..provider::default_provider()
+        };
+        let kt = KeyType::EcdsaP256;
+        let mut client_config =
+            make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+        client_config.alpn_protocols = vec!["foo".into()];
+        let mut server_config =
+            make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+        server_config.alpn_protocols = vec!["foo".into()];
+
+        let mut client = quic::ClientConnection::new(
+            Arc::new(client_config),
+            quic::Version::V1,
+            server_name("localhost"),
+            b"client params".to_vec(),
+        )
+        .unwrap();
+        let mut server = quic::ServerConnection::new(
+            Arc::new(server_config),
+            quic::Version::V1,
+            b"server params".to_vec(),
+        )
+        .unwrap();
+
+        step(&mut client, &mut server).unwrap();
+        let Some(quic::KeyChange::Handshake { keys }) = step(&mut server, &mut client).unwrap()
+        else {
+            panic!("expected handshake keys");
+        };
+        assert_eq!(client.negotiated_cipher_suite(), Some(ccm));
+        assert_eq!(keys.local.packet.tag_len(), 16);
+        assert_eq!(
+            keys.local
+                .packet
+                .confidentiality_limit(),
+            2_965_820
+        );
+        assert_eq!(keys.local.packet.integrity_limit(), 2_965_820);
+        assert_eq!(keys.local.header.sample_len(), 16);
+
+        let provider = CryptoProvider {
+            cipher_suites: vec![ccm_8],
+            ..provider::default_provider()
+        };
+        let client_config =
+            make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+        assert_eq!(
+            quic::ClientConnection::new(
+                Arc::new(client_config),
+                quic::Version::V1,
+                server_name("localhost"),
+                b"client params".to_vec(),
+            )
+            .err(),
+            Some(Error::General(
+                "at least one ciphersuite must support QUIC".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn packet_key_api() {
+        use cipher_suite::TLS13_AES_128_GCM_SHA256;
+        use rustls::Side;
+        use rustls::quic::{Keys, Version};
+
+        // Test vectors: https://www.rfc-editor.org/rfc/rfc9001.html#name-client-initial
+        const CONNECTION_ID: &[u8] = &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+        const PACKET_NUMBER: u64 = 2;
+        const PLAIN_HEADER: &[u8] = &[
+            0xc3, 0x00, 0x00, 0x00, 0x01, 0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08,
+            0x00, 0x00, 0x44, 0x9e, 0x00, 0x00, 0x00, 0x02,
+        ];
+
+        const PAYLOAD: &[u8] = &[
+            0x06, 0x00, 0x40, 0xf1, 0x01, 0x00, 0x00, 0xed, 0x03, 0x03, 0xeb, 0xf8, 0xfa, 0x56,
+            0xf1, 0x29, 0x39, 0xb9, 0x58, 0x4a, 0x38, 0x96, 0x47, 0x2e, 0xc4, 0x0b, 0xb8, 0x63,
+            0xcf, 0xd3, 0xe8, 0x68, 0x04, 0xfe, 0x3a, 0x47, 0xf0, 0x6a, 0x2b, 0x69, 0x48, 0x4c,
+            0x00, 0x00, 0x04, 0x13, 0x01, 0x13, 0x02, 0x01, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e,
+            0x63, 0x6f, 0x6d, 0xff, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0a, 0x00, 0x08, 0x00, 0x06,
             0x00, 0x1d, 0x00, 0x17, 0x00, 0x18, 0x00, 0x10, 0x00, 0x07, 0x00, 0x05, 0x04, 0x61,
             0x6c, 0x70, 0x6e, 0x00, 0x05, 0x00, 0x05, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x33,
             0x00, 0x26, 0x00, 0x24, 0x00, 0x1d, 0x00, 0x20, 0x93, 0x70, 0xb2, 0xc9, 0xca, 0xa4,
@@ -5362,1013 +7840,1687 @@ mod test_quic {
             0x08, 0x06, 0x04, 0x80, 0x00, 0xff, 0xff,
         ];
 
-        let client_keys = Keys::initial(
-            Version::V1,
-            TLS13_AES_128_GCM_SHA256
-                .tls13()
-                .unwrap(),
-            TLS13_AES_128_GCM_SHA256
-                .tls13()
-                .unwrap()
-                .quic
-                .unwrap(),
-            CONNECTION_ID,
-            Side::Client,
-        );
-        assert_eq!(client_keys.local.packet.tag_len(), 16);
+        let client_keys = Keys::initial(
+            Version::V1,
+            TLS13_AES_128_GCM_SHA256
+                .tls13()
+                .unwrap(),
+            TLS13_AES_128_GCM_SHA256
+                .tls13()
+                .unwrap()
+                .quic
+                .unwrap(),
+            CONNECTION_ID,
+            Side::Client,
+        );
+        assert_eq!(client_keys.local.packet.tag_len(), 16);
+
+        let mut buf = Vec::new();
+        buf.extend(PLAIN_HEADER);
+        buf.extend(PAYLOAD);
+        let header_len = PLAIN_HEADER.len();
+        let tag_len = client_keys.local.packet.tag_len();
+        let padding_len = 1200 - header_len - PAYLOAD.len() - tag_len;
+        buf.extend(std::iter::repeat(0).take(padding_len));
+        let (header, payload) = buf.split_at_mut(header_len);
+        let tag = client_keys
+            .local
+            .packet
+            .encrypt_in_place(PACKET_NUMBER, header, payload)
+            .unwrap();
+
+        let sample_len = client_keys.local.header.sample_len();
+        let sample = &payload[..sample_len];
+        let (first, rest) = header.split_at_mut(1);
+        client_keys
+            .local
+            .header
+            .encrypt_in_place(sample, &mut first[0], &mut rest[17..21])
+            .unwrap();
+        buf.extend_from_slice(tag.as_ref());
+
+        const PROTECTED: &[u8] = &[
+            0xc0, 0x00, 0x00, 0x00, 0x01, 0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08,
+            0x00, 0x00, 0x44, 0x9e, 0x7b, 0x9a, 0xec, 0x34, 0xd1, 0xb1, 0xc9, 0x8d, 0xd7, 0x68,
+            0x9f, 0xb8, 0xec, 0x11, 0xd2, 0x42, 0xb1, 0x23, 0xdc, 0x9b, 0xd8, 0xba, 0xb9, 0x36,
+            0xb4, 0x7d, 0x92, 0xec, 0x35, 0x6c, 0x0b, 0xab, 0x7d, 0xf5, 0x97, 0x6d, 0x27, 0xcd,
+            0x44, 0x9f, 0x63, 0x30, 0x00, 0x99, 0xf3, 0x99, 0x1c, 0x26, 0x0e, 0xc4, 0xc6, 0x0d,
+            0x17, 0xb3, 0x1f, 0x84, 0x29, 0x15, 0x7b, 0xb3, 0x5a, 0x12, 0x82, 0xa6, 0x43, 0xa8,
+            0xd2, 0x26, 0x2c, 0xad, 0x67, 0x50, 0x0c, 0xad, 0xb8, 0xe7, 0x37, 0x8c, 0x8e, 0xb7,
+            0x53, 0x9e, 0xc4, 0xd4, 0x90, 0x5f, 0xed, 0x1b, 0xee, 0x1f, 0xc8, 0xaa, 0xfb, 0xa1,
+            0x7c, 0x75, 0x0e, 0x2c, 0x7a, 0xce, 0x01, 0xe6, 0x00, 0x5f, 0x80, 0xfc, 0xb7, 0xdf,
+            0x62, 0x12, 0x30, 0xc8, 0x37, 0x11, 0xb3, 0x93, 0x43, 0xfa, 0x02, 0x8c, 0xea, 0x7f,
+            0x7f, 0xb5, 0xff, 0x89, 0xea, 0xc2, 0x30, 0x82, 0x49, 0xa0, 0x22, 0x52, 0x15, 0x5e,
+            0x23, 0x47, 0xb6, 0x3d, 0x58, 0xc5, 0x45, 0x7a, 0xfd, 0x84, 0xd0, 0x5d, 0xff, 0xfd,
+            0xb2, 0x03, 0x92, 0x84, 0x4a, 0xe8, 0x12, 0x15, 0x46, 0x82, 0xe9, 0xcf, 0x01, 0x2f,
+            0x90, 0x21, 0xa6, 0xf0, 0xbe, 0x17, 0xdd, 0xd0, 0xc2, 0x08, 0x4d, 0xce, 0x25, 0xff,
+            0x9b, 0x06, 0xcd, 0xe5, 0x35, 0xd0, 0xf9, 0x20, 0xa2, 0xdb, 0x1b, 0xf3, 0x62, 0xc2,
+            0x3e, 0x59, 0x6d, 0x11, 0xa4, 0xf5, 0xa6, 0xcf, 0x39, 0x48, 0x83, 0x8a, 0x3a, 0xec,
+            0x4e, 0x15, 0xda, 0xf8, 0x50, 0x0a, 0x6e, 0xf6, 0x9e, 0xc4, 0xe3, 0xfe, 0xb6, 0xb1,
+            0xd9, 0x8e, 0x61, 0x0a, 0xc8, 0xb7, 0xec, 0x3f, 0xaf, 0x6a, 0xd7, 0x60, 0xb7, 0xba,
+            0xd1, 0xdb, 0x4b, 0xa3, 0x48, 0x5e, 0x8a, 0x94, 0xdc, 0x25, 0x0a, 0xe3, 0xfd, 0xb4,
+            0x1e, 0xd1, 0x5f, 0xb6, 0xa8, 0xe5, 0xeb, 0xa0, 0xfc, 0x3d, 0xd6, 0x0b, 0xc8, 0xe3,
+            0x0c, 0x5c, 0x42, 0x87, 0xe5, 0x38, 0x05, 0xdb, 0x05, 0x9a, 0xe0, 0x64, 0x8d, 0xb2,
+            0xf6, 0x42, 0x64, 0xed, 0x5e, 0x39, 0xbe, 0x2e, 0x20, 0xd8, 0x2d, 0xf5, 0x66, 0xda,
+            0x8d, 0xd5, 0x99, 0x8c, 0xca, 0xbd, 0xae, 0x05, 0x30, 0x60, 0xae, 0x6c, 0x7b, 0x43,
+            0x78, 0xe8, 0x46, 0xd2, 0x9f, 0x37, 0xed, 0x7b, 0x4e, 0xa9, 0xec, 0x5d, 0x82, 0xe7,
+            0x96, 0x1b, 0x7f, 0x25, 0xa9, 0x32, 0x38, 0x51, 0xf6, 0x81, 0xd5, 0x82, 0x36, 0x3a,
+            0xa5, 0xf8, 0x99, 0x37, 0xf5, 0xa6, 0x72, 0x58, 0xbf, 0x63, 0xad, 0x6f, 0x1a, 0x0b,
+            0x1d, 0x96, 0xdb, 0xd4, 0xfa, 0xdd, 0xfc, 0xef, 0xc5, 0x26, 0x6b, 0xa6, 0x61, 0x17,
+            0x22, 0x39, 0x5c, 0x90, 0x65, 0x56, 0xbe, 0x52, 0xaf, 0xe3, 0xf5, 0x65, 0x63, 0x6a,
+            0xd1, 0xb1, 0x7d, 0x50, 0x8b, 0x73, 0xd8, 0x74, 0x3e, 0xeb, 0x52, 0x4b, 0xe2, 0x2b,
+            0x3d, 0xcb, 0xc2, 0xc7, 0x46, 0x8d, 0x54, 0x11, 0x9c, 0x74, 0x68, 0x44, 0x9a, 0x13,
+            0xd8, 0xe3, 0xb9, 0x58, 0x11, 0xa1, 0x98, 0xf3, 0x49, 0x1d, 0xe3, 0xe7, 0xfe, 0x94,
+            0x2b, 0x33, 0x04, 0x07, 0xab, 0xf8, 0x2a, 0x4e, 0xd7, 0xc1, 0xb3, 0x11, 0x66, 0x3a,
+            0xc6, 0x98, 0x90, 0xf4, 0x15, 0x70, 0x15, 0x85, 0x3d, 0x91, 0xe9, 0x23, 0x03, 0x7c,
+            0x22, 0x7a, 0x33, 0xcd, 0xd5, 0xec, 0x28, 0x1c, 0xa3, 0xf7, 0x9c, 0x44, 0x54, 0x6b,
+            0x9d, 0x90, 0xca, 0x00, 0xf0, 0x64, 0xc9, 0x9e, 0x3d, 0xd9, 0x79, 0x11, 0xd3, 0x9f,
+            0xe9, 0xc5, 0xd0, 0xb2, 0x3a, 0x22, 0x9a, 0x23, 0x4c, 0xb3, 0x61, 0x86, 0xc4, 0x81,
+            0x9e, 0x8b, 0x9c, 0x59, 0x27, 0x72, 0x66, 0x32, 0x29, 0x1d, 0x6a, 0x41, 0x82, 0x11,
+            0xcc, 0x29, 0x62, 0xe2, 0x0f, 0xe4, 0x7f, 0xeb, 0x3e, 0xdf, 0x33, 0x0f, 0x2c, 0x60,
+            0x3a, 0x9d, 0x48, 0xc0, 0xfc, 0xb5, 0x69, 0x9d, 0xbf, 0xe5, 0x89, 0x64, 0x25, 0xc5,
+            0xba, 0xc4, 0xae, 0xe8, 0x2e, 0x57, 0xa8, 0x5a, 0xaf, 0x4e, 0x25, 0x13, 0xe4, 0xf0,
+            0x57, 0x96, 0xb0, 0x7b, 0xa2, 0xee, 0x47, 0xd8, 0x05, 0x06, 0xf8, 0xd2, 0xc2, 0x5e,
+            0x50, 0xfd, 0x14, 0xde, 0x71, 0xe6, 0xc4, 0x18, 0x55, 0x93, 0x02, 0xf9, 0x39, 0xb0,
+            0xe1, 0xab, 0xd5, 0x76, 0xf2, 0x79, 0xc4, 0xb2, 0xe0, 0xfe, 0xb8, 0x5c, 0x1f, 0x28,
+            0xff, 0x18, 0xf5, 0x88, 0x91, 0xff, 0xef, 0x13, 0x2e, 0xef, 0x2f, 0xa0, 0x93, 0x46,
+            0xae, 0xe3, 0x3c, 0x28, 0xeb, 0x13, 0x0f, 0xf2, 0x8f, 0x5b, 0x76, 0x69, 0x53, 0x33,
+            0x41, 0x13, 0x21, 0x19, 0x96, 0xd2, 0x00, 0x11, 0xa1, 0x98, 0xe3, 0xfc, 0x43, 0x3f,
+            0x9f, 0x25, 0x41, 0x01, 0x0a, 0xe1, 0x7c, 0x1b, 0xf2, 0x02, 0x58, 0x0f, 0x60, 0x47,
+            0x47, 0x2f, 0xb3, 0x68, 0x57, 0xfe, 0x84, 0x3b, 0x19, 0xf5, 0x98, 0x40, 0x09, 0xdd,
+            0xc3, 0x24, 0x04, 0x4e, 0x84, 0x7a, 0x4f, 0x4a, 0x0a, 0xb3, 0x4f, 0x71, 0x95, 0x95,
+            0xde, 0x37, 0x25, 0x2d, 0x62, 0x35, 0x36, 0x5e, 0x9b, 0x84, 0x39, 0x2b, 0x06, 0x10,
+            0x85, 0x34, 0x9d, 0x73, 0x20, 0x3a, 0x4a, 0x13, 0xe9, 0x6f, 0x54, 0x32, 0xec, 0x0f,
+            0xd4, 0xa1, 0xee, 0x65, 0xac, 0xcd, 0xd5, 0xe3, 0x90, 0x4d, 0xf5, 0x4c, 0x1d, 0xa5,
+            0x10, 0xb0, 0xff, 0x20, 0xdc, 0xc0, 0xc7, 0x7f, 0xcb, 0x2c, 0x0e, 0x0e, 0xb6, 0x05,
+            0xcb, 0x05, 0x04, 0xdb, 0x87, 0x63, 0x2c, 0xf3, 0xd8, 0xb4, 0xda, 0xe6, 0xe7, 0x05,
+            0x76, 0x9d, 0x1d, 0xe3, 0x54, 0x27, 0x01, 0x23, 0xcb, 0x11, 0x45, 0x0e, 0xfc, 0x60,
+            0xac, 0x47, 0x68, 0x3d, 0x7b, 0x8d, 0x0f, 0x81, 0x13, 0x65, 0x56, 0x5f, 0xd9, 0x8c,
+            0x4c, 0x8e, 0xb9, 0x36, 0xbc, 0xab, 0x8d, 0x06, 0x9f, 0xc3, 0x3b, 0xd8, 0x01, 0xb0,
+            0x3a, 0xde, 0xa2, 0xe1, 0xfb, 0xc5, 0xaa, 0x46, 0x3d, 0x08, 0xca, 0x19, 0x89, 0x6d,
+            0x2b, 0xf5, 0x9a, 0x07, 0x1b, 0x85, 0x1e, 0x6c, 0x23, 0x90, 0x52, 0x17, 0x2f, 0x29,
+            0x6b, 0xfb, 0x5e, 0x72, 0x40, 0x47, 0x90, 0xa2, 0x18, 0x10, 0x14, 0xf3, 0xb9, 0x4a,
+            0x4e, 0x97, 0xd1, 0x17, 0xb4, 0x38, 0x13, 0x03, 0x68, 0xcc, 0x39, 0xdb, 0xb2, 0xd1,
+            0x98, 0x06, 0x5a, 0xe3, 0x98, 0x65, 0x47, 0x92, 0x6c, 0xd2, 0x16, 0x2f, 0x40, 0xa2,
+            0x9f, 0x0c, 0x3c, 0x87, 0x45, 0xc0, 0xf5, 0x0f, 0xba, 0x38, 0x52, 0xe5, 0x66, 0xd4,
+            0x45, 0x75, 0xc2, 0x9d, 0x39, 0xa0, 0x3f, 0x0c, 0xda, 0x72, 0x19, 0x84, 0xb6, 0xf4,
+            0x40, 0x59, 0x1f, 0x35, 0x5e, 0x12, 0xd4, 0x39, 0xff, 0x15, 0x0a, 0xab, 0x76, 0x13,
+            0x49, 0x9d, 0xbd, 0x49, 0xad, 0xab, 0xc8, 0x67, 0x6e, 0xef, 0x02, 0x3b, 0x15, 0xb6,
+            0x5b, 0xfc, 0x5c, 0xa0, 0x69, 0x48, 0x10, 0x9f, 0x23, 0xf3, 0x50, 0xdb, 0x82, 0x12,
+            0x35, 0x35, 0xeb, 0x8a, 0x74, 0x33, 0xbd, 0xab, 0xcb, 0x90, 0x92, 0x71, 0xa6, 0xec,
+            0xbc, 0xb5, 0x8b, 0x93, 0x6a, 0x88, 0xcd, 0x4e, 0x8f, 0x2e, 0x6f, 0xf5, 0x80, 0x01,
+            0x75, 0xf1, 0x13, 0x25, 0x3d, 0x8f, 0xa9, 0xca, 0x88, 0x85, 0xc2, 0xf5, 0x52, 0xe6,
+            0x57, 0xdc, 0x60, 0x3f, 0x25, 0x2e, 0x1a, 0x8e, 0x30, 0x8f, 0x76, 0xf0, 0xbe, 0x79,
+            0xe2, 0xfb, 0x8f, 0x5d, 0x5f, 0xbb, 0xe2, 0xe3, 0x0e, 0xca, 0xdd, 0x22, 0x07, 0x23,
+            0xc8, 0xc0, 0xae, 0xa8, 0x07, 0x8c, 0xdf, 0xcb, 0x38, 0x68, 0x26, 0x3f, 0xf8, 0xf0,
+            0x94, 0x00, 0x54, 0xda, 0x48, 0x78, 0x18, 0x93, 0xa7, 0xe4, 0x9a, 0xd5, 0xaf, 0xf4,
+            0xaf, 0x30, 0x0c, 0xd8, 0x04, 0xa6, 0xb6, 0x27, 0x9a, 0xb3, 0xff, 0x3a, 0xfb, 0x64,
+            0x49, 0x1c, 0x85, 0x19, 0x4a, 0xab, 0x76, 0x0d, 0x58, 0xa6, 0x06, 0x65, 0x4f, 0x9f,
+            0x44, 0x00, 0xe8, 0xb3, 0x85, 0x91, 0x35, 0x6f, 0xbf, 0x64, 0x25, 0xac, 0xa2, 0x6d,
+            0xc8, 0x52, 0x44, 0x25, 0x9f, 0xf2, 0xb1, 0x9c, 0x41, 0xb9, 0xf9, 0x6f, 0x3c, 0xa9,
+            0xec, 0x1d, 0xde, 0x43, 0x4d, 0xa7, 0xd2, 0xd3, 0x92, 0xb9, 0x05, 0xdd, 0xf3, 0xd1,
+            0xf9, 0xaf, 0x93, 0xd1, 0xaf, 0x59, 0x50, 0xbd, 0x49, 0x3f, 0x5a, 0xa7, 0x31, 0xb4,
+            0x05, 0x6d, 0xf3, 0x1b, 0xd2, 0x67, 0xb6, 0xb9, 0x0a, 0x07, 0x98, 0x31, 0xaa, 0xf5,
+            0x79, 0xbe, 0x0a, 0x39, 0x01, 0x31, 0x37, 0xaa, 0xc6, 0xd4, 0x04, 0xf5, 0x18, 0xcf,
+            0xd4, 0x68, 0x40, 0x64, 0x7e, 0x78, 0xbf, 0xe7, 0x06, 0xca, 0x4c, 0xf5, 0xe9, 0xc5,
+            0x45, 0x3e, 0x9f, 0x7c, 0xfd, 0x2b, 0x8b, 0x4c, 0x8d, 0x16, 0x9a, 0x44, 0xe5, 0x5c,
+            0x88, 0xd4, 0xa9, 0xa7, 0xf9, 0x47, 0x42, 0x41, 0xe2, 0x21, 0xaf, 0x44, 0x86, 0x00,
+            0x18, 0xab, 0x08, 0x56, 0x97, 0x2e, 0x19, 0x4c, 0xd9, 0x34,
+        ];
+
+        assert_eq!(&buf, PROTECTED);
+
+        let (header, payload) = buf.split_at_mut(header_len);
+        let (first, rest) = header.split_at_mut(1);
+        let sample = &payload[..sample_len];
+
+        let server_keys = Keys::initial(
+            Version::V1,
+            TLS13_AES_128_GCM_SHA256
+                .tls13()
+                .unwrap(),
+            TLS13_AES_128_GCM_SHA256
+                .tls13()
+                .unwrap()
+                .quic
+                .unwrap(),
+            CONNECTION_ID,
+            Side::Server,
+        );
+        server_keys
+            .remote
+            .header
+            .decrypt_in_place(sample, &mut first[0], &mut rest[17..21])
+            .unwrap();
+        let payload = server_keys
+            .remote
+            .packet
+            .decrypt_in_place(PACKET_NUMBER, header, payload)
+            .unwrap();
+
+        assert_eq!(&payload[..PAYLOAD.len()], PAYLOAD);
+        assert_eq!(payload.len(), buf.len() - header_len - tag_len);
+    }
+
+    #[test]
+    fn test_quic_exporter() {
+        let provider = provider::default_provider();
+        for &kt in KeyType::all_for_provider(&provider) {
+            let client_config =
+                make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+            let server_config =
+                make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+
+            do_exporter_test(client_config, server_config);
+        }
+    }
+
+    #[test]
+    fn test_fragmented_append() {
+        // Create a QUIC client connection.
+        let client_config = make_client_config_with_versions(
+            KeyType::Rsa2048,
+            &[&rustls::version::TLS13],
+            &provider::default_provider(),
+        );
+        let client_config = Arc::new(client_config);
+        let mut client = quic::ClientConnection::new(
+            client_config.clone(),
+            quic::Version::V1,
+            server_name("localhost"),
+            b"client params"[..].into(),
+        )
+        .unwrap();
+
+        // Construct a message that is too large to fit in a single QUIC packet.
+        // We want the partial pieces to be large enough to overflow the deframer's
+        // 4096 byte buffer if mishandled.
+        let mut out = vec![0; 4096];
+        let len_bytes = u32::to_be_bytes(9266_u32);
+        out[1..4].copy_from_slice(&len_bytes[1..]);
+
+        // Read the message - this will put us into a joining handshake message state, buffering
+        // 4096 bytes into the deframer buffer.
+        client.read_hs(&out).unwrap();
+
+        // Read the message again - once more it isn't a complete message, so we'll try to
+        // append another 4096 bytes into the deframer buffer.
+        //
+        // If the deframer mishandles writing into the used buffer space this will panic with
+        // an index out of range error:
+        //   range end index 8192 out of range for slice of length 4096
+        client.read_hs(&out).unwrap();
+    }
+
+    #[test]
+    fn test_quic_client_options() {
+        let kt = KeyType::Rsa2048;
+        let provider = provider::default_provider();
+        let client_config =
+            make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+        let server_config =
+            make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+
+        let mut client = quic::ClientConnection::new_with_options(
+            Arc::new(client_config),
+            quic::Version::V1,
+            server_name("192.0.2.1"),
+            b"client params".to_vec(),
+            ClientConnectionOptions::new()
+                .with_verification_name(server_name("testserver.com"))
+                .with_server_name_indication(DnsName::try_from("sni.example.com").unwrap()),
+        )
+        .unwrap();
+        let mut server = quic::ServerConnection::new(
+            Arc::new(server_config),
+            quic::Version::V1,
+            b"server params".to_vec(),
+        )
+        .unwrap();
+
+        step(&mut client, &mut server).unwrap();
+        step(&mut server, &mut client).unwrap();
+        step(&mut client, &mut server).unwrap();
+        step(&mut server, &mut client).unwrap();
+        step(&mut client, &mut server).unwrap();
+        assert!(!client.is_handshaking());
+        assert!(!server.is_handshaking());
+        assert_eq!(
+            server.server_name(),
+            Some(&DnsName::try_from("sni.example.com").unwrap())
+        );
+    }
+} // mod test_quic
+
+#[test]
+fn test_client_config_keyshare() {
+    let provider = provider::default_provider();
+    let kx_groups = vec![provider::kx_group::SECP384R1];
+    let client_config =
+        make_client_config_with_kx_groups(KeyType::Rsa2048, kx_groups.clone(), &provider);
+    let server_config = make_server_config_with_kx_groups(KeyType::Rsa2048, kx_groups, &provider);
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    do_handshake_until_error(&mut client, &mut server).unwrap();
+}
+
+#[test]
+fn test_client_config_keyshare_mismatch() {
+    let provider = provider::default_provider();
+    let client_config = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::SECP384R1],
+        &provider,
+    );
+    let server_config = make_server_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::X25519],
+        &provider,
+    );
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    assert!(do_handshake_until_error(&mut client, &mut server).is_err());
+}
+
+#[test]
+fn exercise_all_key_exchange_methods() {
+    for version in rustls::ALL_VERSIONS {
+        for kx_group in provider::ALL_KX_GROUPS {
+            if !kx_group
+                .name()
+                .usable_for_version(version.version())
+            {
+                continue;
+            }
+
+            let provider = provider::default_provider();
+            let client_config =
+                make_client_config_with_kx_groups(KeyType::Rsa2048, vec![*kx_group], &provider);
+            let server_config =
+                make_server_config_with_kx_groups(KeyType::Rsa2048, vec![*kx_group], &provider);
+            let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+            assert!(do_handshake_until_error(&mut client, &mut server).is_ok());
+            println!("kx_group {:?} is self-consistent", kx_group.name());
+        }
+    }
+}
+
+#[test]
+fn test_client_sends_helloretryrequest() {
+    let provider = provider::default_provider();
+    // client sends a secp384r1 key share
+    let mut client_config = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::SECP384R1, provider::kx_group::X25519],
+        &provider,
+    );
+
+    let storage = Arc::new(ClientStorage::new());
+    client_config.resumption = Resumption::store(storage.clone());
+
+    // but server only accepts x25519, so a HRR is required
+    let server_config = make_server_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::X25519],
+        &provider,
+    );
+
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+
+    assert_eq!(client.handshake_kind(), None);
+    assert_eq!(server.handshake_kind(), None);
+
+    // client sends hello
+    {
+        let mut pipe = OtherSession::new(&mut server);
+        let wrlen = client.write_tls(&mut pipe).unwrap();
+        assert!(wrlen > 200);
+        assert_eq!(pipe.writevs.len(), 1);
+        assert!(pipe.writevs[0].len() == 1);
+    }
+
+    assert_eq!(client.handshake_kind(), None);
+    assert_eq!(
+        server.handshake_kind(),
+        Some(HandshakeKind::FullWithHelloRetryRequest)
+    );
+
+    // server sends HRR
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        assert!(wrlen < 100); // just the hello retry request
+        assert_eq!(pipe.writevs.len(), 1); // only one writev
+        assert!(pipe.writevs[0].len() == 2); // hello retry request and CCS
+    }
+
+    assert_eq!(
+        client.handshake_kind(),
+        Some(HandshakeKind::FullWithHelloRetryRequest)
+    );
+    assert_eq!(
+        server.handshake_kind(),
+        Some(HandshakeKind::FullWithHelloRetryRequest)
+    );
+
+    // client sends fixed hello
+    {
+        let mut pipe = OtherSession::new(&mut server);
+        let wrlen = client.write_tls(&mut pipe).unwrap();
+        assert!(wrlen > 200); // just the client hello retry
+        assert_eq!(pipe.writevs.len(), 1); // only one writev
+        assert!(pipe.writevs[0].len() == 2); // only a CCS & client hello retry
+    }
+
+    // server completes handshake
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        let wrlen = server.write_tls(&mut pipe).unwrap();
+        assert!(wrlen > 200);
+        assert_eq!(pipe.writevs.len(), 1);
+        assert_eq!(pipe.writevs[0].len(), 2); // { server hello / encrypted exts / cert / cert-verify } / finished
+    }
+
+    assert_eq!(
+        client.handshake_kind(),
+        Some(HandshakeKind::FullWithHelloRetryRequest)
+    );
+    assert_eq!(
+        server.handshake_kind(),
+        Some(HandshakeKind::FullWithHelloRetryRequest)
+    );
+
+    do_handshake_until_error(&mut client, &mut server).unwrap();
+
+    // client only did following storage queries:
+    println!("storage {:#?}", storage.ops());
+    assert_eq!(storage.ops().len(), 7);
+    assert!(matches!(
+        storage.ops()[0],
+        ClientStorageOp::TakeTls13Ticket(_, false)
+    ));
+    assert!(matches!(
+        storage.ops()[1],
+        ClientStorageOp::GetTls12Session(_, false)
+    ));
+    assert!(matches!(
+        storage.ops()[2],
+        ClientStorageOp::GetKxHint(_, None)
+    ));
+    assert!(matches!(
+        storage.ops()[3],
+        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::X25519)
+    ));
+    assert!(matches!(
+        storage.ops()[4],
+        ClientStorageOp::RemoveTls12Session(_)
+    ));
+    // server sends 2 tickets by default
+    assert!(matches!(
+        storage.ops()[5],
+        ClientStorageOp::InsertTls13Ticket(_)
+    ));
+    assert!(matches!(
+        storage.ops()[6],
+        ClientStorageOp::InsertTls13Ticket(_)
+    ));
+}
+
+#[test]
+fn test_client_attempts_to_use_unsupported_kx_group() {
+    // common to both client configs
+    let shared_storage = Arc::new(ClientStorage::new());
+    let provider = provider::default_provider();
+
+    // first, client sends a secp-256 share and server agrees. secp-256 is inserted
+    //   into kx group cache.
+    let mut client_config_1 = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::SECP256R1],
+        &provider,
+    );
+    client_config_1.resumption = Resumption::store(shared_storage.clone());
+
+    // second, client only supports secp-384 and so kx group cache
+    //   contains an unusable value.
+    let mut client_config_2 = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::SECP384R1],
+        &provider,
+    );
+    client_config_2.resumption = Resumption::store(shared_storage.clone());
+
+    let server_config = make_server_config(KeyType::Rsa2048, &provider);
+
+    // first handshake
+    let (mut client_1, mut server) = make_pair_for_configs(client_config_1, server_config.clone());
+    do_handshake_until_error(&mut client_1, &mut server).unwrap();
+
+    let ops = shared_storage.ops();
+    println!("storage {ops:#?}");
+    assert_eq!(ops.len(), 7);
+    assert!(matches!(
+        ops[3],
+        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::secp256r1)
+    ));
+
+    // second handshake
+    let (mut client_2, mut server) = make_pair_for_configs(client_config_2, server_config);
+    do_handshake_until_error(&mut client_2, &mut server).unwrap();
+
+    let ops = shared_storage.ops();
+    println!("storage {:?} {:#?}", ops.len(), ops);
+    assert_eq!(ops.len(), 13);
+    assert!(matches!(ops[7], ClientStorageOp::TakeTls13Ticket(_, true)));
+    assert!(matches!(
+        ops[8],
+        ClientStorageOp::GetKxHint(_, Some(rustls::NamedGroup::secp256r1))
+    ));
+    assert!(matches!(
+        ops[9],
+        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::secp384r1)
+    ));
+}
+
+#[test]
+fn test_client_sends_share_for_less_preferred_group() {
+    // this is a test for the case described in:
+    // https://datatracker.ietf.org/doc/draft-davidben-tls-key-share-prediction/
+
+    // common to both client configs
+    let shared_storage = Arc::new(ClientStorage::new());
+    let provider = provider::default_provider();
+
+    // first, client sends a secp384r1 share and server agrees. secp384r1 is inserted
+    //   into kx group cache.
+    let mut client_config_1 = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::SECP384R1],
+        &provider,
+    );
+    client_config_1.resumption = Resumption::store(shared_storage.clone());
+
+    // second, client supports (x25519, secp384r1) and so kx group cache
+    //   contains a supported but less-preferred group.
+    let mut client_config_2 = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::X25519, provider::kx_group::SECP384R1],
+        &provider,
+    );
+    client_config_2.resumption = Resumption::store(shared_storage.clone());
+
+    let server_config = make_server_config_with_kx_groups(
+        KeyType::Rsa2048,
+        provider::ALL_KX_GROUPS.to_vec(),
+        &provider,
+    );
+
+    // first handshake
+    let (mut client_1, mut server) = make_pair_for_configs(client_config_1, server_config.clone());
+    do_handshake_until_error(&mut client_1, &mut server).unwrap();
+    assert_eq!(
+        client_1
+            .negotiated_key_exchange_group()
+            .map(|kxg| kxg.name()),
+        Some(NamedGroup::secp384r1)
+    );
+    assert_eq!(client_1.handshake_kind(), Some(HandshakeKind::Full));
+
+    let ops = shared_storage.ops();
+    println!("storage {ops:#?}");
+    assert_eq!(ops.len(), 7);
+    assert!(matches!(
+        ops[3],
+        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::secp384r1)
+    ));
 
-        let mut buf = Vec::new();
-        buf.extend(PLAIN_HEADER);
-        buf.extend(PAYLOAD);
-        let header_len = PLAIN_HEADER.len();
-        let tag_len = client_keys.local.packet.tag_len();
-        let padding_len = 1200 - header_len - PAYLOAD.len() - tag_len;
-        buf.extend(std::iter::repeat(0).take(padding_len));
-        let (header, payload) = buf.split_at_mut(header_len);
-        let tag = client_keys
-            .local
-            .packet
-            .encrypt_in_place(PACKET_NUMBER, header, payload)
-            .unwrap();
+    // second handshake; HRR'd from secp384r1 to X25519
+    let (mut client_2, mut server) = make_pair_for_configs(client_config_2, server_config);
+    do_handshake(&mut client_2, &mut server);
+    assert_eq!(
+        client_2
+            .negotiated_key_exchange_group()
+            .map(|kxg| kxg.name()),
+        Some(NamedGroup::X25519)
+    );
+    assert_eq!(
+        client_2.handshake_kind(),
+        Some(HandshakeKind::FullWithHelloRetryRequest)
+    );
+}
 
-        let sample_len = client_keys.local.header.sample_len();
-        let sample = &payload[..sample_len];
-        let (first, rest) = header.split_at_mut(1);
-        client_keys
-            .local
-            .header
-            .encrypt_in_place(sample, &mut first[0], &mut rest[17..21])
-            .unwrap();
-        buf.extend_from_slice(tag.as_ref());
+#[test]
+fn test_tls13_client_resumption_does_not_reuse_tickets() {
+    let shared_storage = Arc::new(ClientStorage::new());
+    let provider = provider::default_provider();
 
-        const PROTECTED: &[u8] = &[
-            0xc0, 0x00, 0x00, 0x00, 0x01, 0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08,
-            0x00, 0x00, 0x44, 0x9e, 0x7b, 0x9a, 0xec, 0x34, 0xd1, 0xb1, 0xc9, 0x8d, 0xd7, 0x68,
-            0x9f, 0xb8, 0xec, 0x11, 0xd2, 0x42, 0xb1, 0x23, 0xdc, 0x9b, 0xd8, 0xba, 0xb9, 0x36,
-            0xb4, 0x7d, 0x92, 0xec, 0x35, 0x6c, 0x0b, 0xab, 0x7d, 0xf5, 0x97, 0x6d, 0x27, 0xcd,
-            0x44, 0x9f, 0x63, 0x30, 0x00, 0x99, 0xf3, 0x99, 0x1c, 0x26, 0x0e, 0xc4, 0xc6, 0x0d,
-            0x17, 0xb3, 0x1f, 0x84, 0x29, 0x15, 0x7b, 0xb3, 0x5a, 0x12, 0x82, 0xa6, 0x43, 0xa8,
-            0xd2, 0x26, 0x2c, 0xad, 0x67, 0x50, 0x0c, 0xad, 0xb8, 0xe7, 0x37, 0x8c, 0x8e, 0xb7,
-            0x53, 0x9e, 0xc4, 0xd4, 0x90, 0x5f, 0xed, 0x1b, 0xee, 0x1f, 0xc8, 0xaa, 0xfb, 0xa1,
-            0x7c, 0x75, 0x0e, 0x2c, 0x7a, 0xce, 0x01, 0xe6, 0x00, 0x5f, 0x80, 0xfc, 0xb7, 0xdf,
-            0x62, 0x12, 0x30, 0xc8, 0x37, 0x11, 0xb3, 0x93, 0x43, 0xfa, 0x02, 0x8c, 0xea, 0x7f,
-            0x7f, 0xb5, 0xff, 0x89, 0xea, 0xc2, 0x30, 0x82, 0x49, 0xa0, 0x22, 0x52, 0x15, 0x5e,
-            0x23, 0x47, 0xb6, 0x3d, 0x58, 0xc5, 0x45, 0x7a, 0xfd, 0x84, 0xd0, 0x5d, 0xff, 0xfd,
-            0xb2, 0x03, 0x92, 0x84, 0x4a, 0xe8, 0x12, 0x15, 0x46, 0x82, 0xe9, 0xcf, 0x01, 0x2f,
-            0x90, 0x21, 0xa6, 0xf0, 0xbe, 0x17, 0xdd, 0xd0, 0xc2, 0x08, 0x4d, 0xce, 0x25, 0xff,
-            0x9b, 0x06, 0xcd, 0xe5, 0x35, 0xd0, 0xf9, 0x20, 0xa2, 0xdb, 0x1b, 0xf3, 0x62, 0xc2,
-            0x3e, 0x59, 0x6d, 0x11, 0xa4, 0xf5, 0xa6, 0xcf, 0x39, 0x48, 0x83, 0x8a, 0x3a, 0xec,
-            0x4e, 0x15, 0xda, 0xf8, 0x50, 0x0a, 0x6e, 0xf6, 0x9e, 0xc4, 0xe3, 0xfe, 0xb6, 0xb1,
-            0xd9, 0x8e, 0x61, 0x0a, 0xc8, 0xb7, 0xec, 0x3f, 0xaf, 0x6a, 0xd7, 0x60, 0xb7, 0xba,
-            0xd1, 0xdb, 0x4b, 0xa3, 0x48, 0x5e, 0x8a, 0x94, 0xdc, 0x25, 0x0a, 0xe3, 0xfd, 0xb4,
-            0x1e, 0xd1, 0x5f, 0xb6, 0xa8, 0xe5, 0xeb, 0xa0, 0xfc, 0x3d, 0xd6, 0x0b, 0xc8, 0xe3,
-            0x0c, 0x5c, 0x42, 0x87, 0xe5, 0x38, 0x05, 0xdb, 0x05, 0x9a, 0xe0, 0x64, 0x8d, 0xb2,
-            0xf6, 0x42, 0x64, 0xed, 0x5e, 0x39, 0xbe, 0x2e, 0x20, 0xd8, 0x2d, 0xf5, 0x66, 0xda,
-            0x8d, 0xd5, 0x99, 0x8c, 0xca, 0xbd, 0xae, 0x05, 0x30, 0x60, 0xae, 0x6c, 0x7b, 0x43,
-            0x78, 0xe8, 0x46, 0xd2, 0x9f, 0x37, 0xed, 0x7b, 0x4e, 0xa9, 0xec, 0x5d, 0x82, 0xe7,
-            0x96, 0x1b, 0x7f, 0x25, 0xa9, 0x32, 0x38, 0x51, 0xf6, 0x81, 0xd5, 0x82, 0x36, 0x3a,
-            0xa5, 0xf8, 0x99, 0x37, 0xf5, 0xa6, 0x72, 0x58, 0xbf, 0x63, 0xad, 0x6f, 0x1a, 0x0b,
-            0x1d, 0x96, 0xdb, 0xd4, 0xfa, 0xdd, 0xfc, 0xef, 0xc5, 0x26, 0x6b, 0xa6, 0x61, 0x17,
-            0x22, 0x39, 0x5c, 0x90, 0x65, 0x56, 0xbe, 0x52, 0xaf, 0xe3, 0xf5, 0x65, 0x63, 0x6a,
-            0xd1, 0xb1, 0x7d, 0x50, 0x8b, 0x73, 0xd8, 0x74, 0x3e, 0xeb, 0x52, 0x4b, 0xe2, 0x2b,
-            0x3d, 0xcb, 0xc2, 0xc7, 0x46, 0x8d, 0x54, 0x11, 0x9c, 0x74, 0x68, 0x44, 0x9a, 0x13,
-            0xd8, 0xe3, 0xb9, 0x58, 0x11, 0xa1, 0x98, 0xf3, 0x49, 0x1d, 0xe3, 0xe7, 0xfe, 0x94,
-            0x2b, 0x33, 0x04, 0x07, 0xab, 0xf8, 0x2a, 0x4e, 0xd7, 0xc1, 0xb3, 0x11, 0x66, 0x3a,
-            0xc6, 0x98, 0x90, 0xf4, 0x15, 0x70, 0x15, 0x85, 0x3d, 0x91, 0xe9, 0x23, 0x03, 0x7c,
-            0x22, 0x7a, 0x33, 0xcd, 0xd5, 0xec, 0x28, 0x1c, 0xa3, 0xf7, 0x9c, 0x44, 0x54, 0x6b,
-            0x9d, 0x90, 0xca, 0x00, 0xf0, 0x64, 0xc9, 0x9e, 0x3d, 0xd9, 0x79, 0x11, 0xd3, 0x9f,
-            0xe9, 0xc5, 0xd0, 0xb2, 0x3a, 0x22, 0x9a, 0x23, 0x4c, 0xb3, 0x61, 0x86, 0xc4, 0x81,
-            0x9e, 0x8b, 0x9c, 0x59, 0x27, 0x72, 0x66, 0x32, 0x29, 0x1d, 0x6a, 0x41, 0x82, 0x11,
-            0xcc, 0x29, 0x62, 0xe2, 0x0f, 0xe4, 0x7f, 0xeb, 0x3e, 0xdf, 0x33, 0x0f, 0x2c, 0x60,
-            0x3a, 0x9d, 0x48, 0xc0, 0xfc, 0xb5, 0x69, 0x9d, 0xbf, 0xe5, 0x89, 0x64, 0x25, 0xc5,
-            0xba, 0xc4, 0xae, 0xe8, 0x2e, 0x57, 0xa8, 0x5a, 0xaf, 0x4e, 0x25, 0x13, 0xe4, 0xf0,
-            0x57, 0x96, 0xb0, 0x7b, 0xa2, 0xee, 0x47, 0xd8, 0x05, 0x06, 0xf8, 0xd2, 0xc2, 0x5e,
-            0x50, 0xfd, 0x14, 0xde, 0x71, 0xe6, 0xc4, 0x18, 0x55, 0x93, 0x02, 0xf9, 0x39, 0xb0,
-            0xe1, 0xab, 0xd5, 0x76, 0xf2, 0x79, 0xc4, 0xb2, 0xe0, 0xfe, 0xb8, 0x5c, 0x1f, 0x28,
-            0xff, 0x18, 0xf5, 0x88, 0x91, 0xff, 0xef, 0x13, 0x2e, 0xef, 0x2f, 0xa0, 0x93, 0x46,
-            0xae, 0xe3, 0x3c, 0x28, 0xeb, 0x13, 0x0f, 0xf2, 0x8f, 0x5b, 0x76, 0x69, 0x53, 0x33,
-            0x41, 0x13, 0x21, 0x19, 0x96, 0xd2, 0x00, 0x11, 0xa1, 0x98, 0xe3, 0xfc, 0x43, 0x3f,
-            0x9f, 0x25, 0x41, 0x01, 0x0a, 0xe1, 0x7c, 0x1b, 0xf2, 0x02, 0x58, 0x0f, 0x60, 0x47,
-            0x47, 0x2f, 0xb3, 0x68, 0x57, 0xfe, 0x84, 0x3b, 0x19, 0xf5, 0x98, 0x40, 0x09, 0xdd,
-            0xc3, 0x24, 0x04, 0x4e, 0x84, 0x7a, 0x4f, 0x4a, 0x0a, 0xb3, 0x4f, 0x71, 0x95, 0x95,
-            0xde, 0x37, 0x25, 0x2d, 0x62, 0x35, 0x36, 0x5e, 0x9b, 0x84, 0x39, 0x2b, 0x06, 0x10,
-            0x85, 0x34, 0x9d, 0x73, 0x20, 0x3a, 0x4a, 0x13, 0xe9, 0x6f, 0x54, 0x32, 0xec, 0x0f,
-            0xd4, 0xa1, 0xee, 0x65, 0xac, 0xcd, 0xd5, 0xe3, 0x90, 0x4d, 0xf5, 0x4c, 0x1d, 0xa5,
-            0x10, 0xb0, 0xff, 0x20, 0xdc, 0xc0, 0xc7, 0x7f, 0xcb, 0x2c, 0x0e, 0x0e, 0xb6, 0x05,
-            0xcb, 0x05, 0x04, 0xdb, 0x87, 0x63, 0x2c, 0xf3, 0xd8, 0xb4, 0xda, 0xe6, 0xe7, 0x05,
-            0x76, 0x9d, 0x1d, 0xe3, 0x54, 0x27, 0x01, 0x23, 0xcb, 0x11, 0x45, 0x0e, 0xfc, 0x60,
-            0xac, 0x47, 0x68, 0x3d, 0x7b, 0x8d, 0x0f, 0x81, 0x13, 0x65, 0x56, 0x5f, 0xd9, 0x8c,
-            0x4c, 0x8e, 0xb9, 0x36, 0xbc, 0xab, 0x8d, 0x06, 0x9f, 0xc3, 0x3b, 0xd8, 0x01, 0xb0,
-            0x3a, 0xde, 0xa2, 0xe1, 0xfb, 0xc5, 0xaa, 0x46, 0x3d, 0x08, 0xca, 0x19, 0x89, 0x6d,
-            0x2b, 0xf5, 0x9a, 0x07, 0x1b, 0x85, 0x1e, 0x6c, 0x23, 0x90, 0x52, 0x17, 0x2f, 0x29,
-            0x6b, 0xfb, 0x5e, 0x72, 0x40, 0x47, 0x90, 0xa2, 0x18, 0x10, 0x14, 0xf3, 0xb9, 0x4a,
-            0x4e, 0x97, 0xd1, 0x17, 0xb4, 0x38, 0x13, 0x03, 0x68, 0xcc, 0x39, 0xdb, 0xb2, 0xd1,
-            0x98, 0x06, 0x5a, 0xe3, 0x98, 0x65, 0x47, 0x92, 0x6c, 0xd2, 0x16, 0x2f, 0x40, 0xa2,
-            0x9f, 0x0c, 0x3c, 0x87, 0x45, 0xc0, 0xf5, 0x0f, 0xba, 0x38, 0x52, 0xe5, 0x66, 0xd4,
-            0x45, 0x75, 0xc2, 0x9d, 0x39, 0xa0, 0x3f, 0x0c, 0xda, 0x72, 0x19, 0x84, 0xb6, 0xf4,
-            0x40, 0x59, 0x1f, 0x35, 0x5e, 0x12, 0xd4, 0x39, 0xff, 0x15, 0x0a, 0xab, 0x76, 0x13,
-            0x49, 0x9d, 0xbd, 0x49, 0xad, 0xab, 0xc8, 0x67, 0x6e, 0xef, 0x02, 0x3b, 0x15, 0xb6,
-            0x5b, 0xfc, 0x5c, 0xa0, 0x69, 0x48, 0x10, 0x9f, 0x23, 0xf3, 0x50, 0xdb, 0x82, 0x12,
-            0x35, 0x35, 0xeb, 0x8a, 0x74, 0x33, 0xbd, 0xab, 0xcb, 0x90, 0x92, 0x71, 0xa6, 0xec,
-            0xbc, 0xb5, 0x8b, 0x93, 0x6a, 0x88, 0xcd, 0x4e, 0x8f, 0x2e, 0x6f, 0xf5, 0x80, 0x01,
-            0x75, 0xf1, 0x13, 0x25, 0x3d, 0x8f, 0xa9, 0xca, 0x88, 0x85, 0xc2, 0xf5, 0x52, 0xe6,
-            0x57, 0xdc, 0x60, 0x3f, 0x25, 0x2e, 0x1a, 0x8e, 0x30, 0x8f, 0x76, 0xf0, 0xbe, 0x79,
-            0xe2, 0xfb, 0x8f, 0x5d, 0x5f, 0xbb, 0xe2, 0xe3, 0x0e, 0xca, 0xdd, 0x22, 0x07, 0x23,
-            0xc8, 0xc0, 0xae, 0xa8, 0x07, 0x8c, 0xdf, 0xcb, 0x38, 0x68, 0x26, 0x3f, 0xf8, 0xf0,
-            0x94, 0x00, 0x54, 0xda, 0x48, 0x78, 0x18, 0x93, 0xa7, 0xe4, 0x9a, 0xd5, 0xaf, 0xf4,
-            0xaf, 0x30, 0x0c, 0xd8, 0x04, 0xa6, 0xb6, 0x27, 0x9a, 0xb3, 0xff, 0x3a, 0xfb, 0x64,
-            0x49, 0x1c, 0x85, 0x19, 0x4a, 0xab, 0x76, 0x0d, 0x58, 0xa6, 0x06, 0x65, 0x4f, 0x9f,
-            0x44, 0x00, 0xe8, 0xb3, 0x85, 0x91, 0x35, 0x6f, 0xbf, 0x64, 0x25, 0xac, 0xa2, 0x6d,
-            0xc8, 0x52, 0x44, 0x25, 0x9f, 0xf2, 0xb1, 0x9c, 0x41, 0xb9, 0xf9, 0x6f, 0x3c, 0xa9,
-            0xec, 0x1d, 0xde, 0x43, 0x4d, 0xa7, 0xd2, 0xd3, 0x92, 0xb9, 0x05, 0xdd, 0xf3, 0xd1,
-            0xf9, 0xaf, 0x93, 0xd1, 0xaf, 0x59, 0x50, 0xbd, 0x49, 0x3f, 0x5a, 0xa7, 0x31, 0xb4,
-            0x05, 0x6d, 0xf3, 0x1b, 0xd2, 0x67, 0xb6, 0xb9, 0x0a, 0x07, 0x98, 0x31, 0xaa, 0xf5,
-            0x79, 0xbe, 0x0a, 0x39, 0x01, 0x31, 0x37, 0xaa, 0xc6, 0xd4, 0x04, 0xf5, 0x18, 0xcf,
-            0xd4, 0x68, 0x40, 0x64, 0x7e, 0x78, 0xbf, 0xe7, 0x06, 0xca, 0x4c, 0xf5, 0xe9, 0xc5,
-            0x45, 0x3e, 0x9f, 0x7c, 0xfd, 0x2b, 0x8b, 0x4c, 0x8d, 0x16, 0x9a, 0x44, 0xe5, 0x5c,
-            0x88, 0xd4, 0xa9, 0xa7, 0xf9, 0x47, 0x42, 0x41, 0xe2, 0x21, 0xaf, 0x44, 0x86, 0x00,
-            0x18, 0xab, 0x08, 0x56, 0x97, 0x2e, 0x19, 0x4c, 0xd9, 0x34,
-        ];
+    let mut client_config = make_client_config(KeyType::Rsa2048, &provider);
+    client_config.resumption = Resumption::store(shared_storage.clone());
+    let client_config = Arc::new(client_config);
 
-        assert_eq!(&buf, PROTECTED);
+    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+    server_config.send_tls13_tickets = 5;
+    let server_config = Arc::new(server_config);
 
-        let (header, payload) = buf.split_at_mut(header_len);
-        let (first, rest) = header.split_at_mut(1);
-        let sample = &payload[..sample_len];
+    // first handshake: client obtains 5 tickets from server.
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake_until_error(&mut client, &mut server).unwrap();
 
-        let server_keys = Keys::initial(
-            Version::V1,
-            TLS13_AES_128_GCM_SHA256
-                .tls13()
-                .unwrap(),
-            TLS13_AES_128_GCM_SHA256
-                .tls13()
-                .unwrap()
-                .quic
-                .unwrap(),
-            CONNECTION_ID,
-            Side::Server,
-        );
-        server_keys
-            .remote
-            .header
-            .decrypt_in_place(sample, &mut first[0], &mut rest[17..21])
-            .unwrap();
-        let payload = server_keys
-            .remote
-            .packet
-            .decrypt_in_place(PACKET_NUMBER, header, payload)
+    let ops = shared_storage.ops_and_reset();
+    println!("storage {ops:#?}");
+    assert_eq!(ops.len(), 10);
+    assert!(matches!(ops[5], ClientStorageOp::InsertTls13Ticket(_)));
+    assert!(matches!(ops[6], ClientStorageOp::InsertTls13Ticket(_)));
+    assert!(matches!(ops[7], ClientStorageOp::InsertTls13Ticket(_)));
+    assert!(matches!(ops[8], ClientStorageOp::InsertTls13Ticket(_)));
+    assert!(matches!(ops[9], ClientStorageOp::InsertTls13Ticket(_)));
+
+    // 5 subsequent handshakes: all are resumptions
+
+    // Note: we don't do complete the handshakes, because that means
+    // we get five additional tickets per connection which is unhelpful
+    // in this test.  It also acts to record a "Happy Eyeballs"-type use
+    // case, where a client speculatively makes many connection attempts
+    // in parallel without knowledge of which will work due to underlying
+    // connectivity uncertainty.
+    for _ in 0..5 {
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        transfer(&mut client, &mut server);
+        server.process_new_packets().unwrap();
+
+        let ops = shared_storage.ops_and_reset();
+        assert!(matches!(ops[0], ClientStorageOp::TakeTls13Ticket(_, true)));
+    }
+
+    // 6th subsequent handshake: cannot be resumed; we ran out of tickets
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+
+    let ops = shared_storage.ops_and_reset();
+    println!("last {ops:?}");
+    assert!(matches!(ops[0], ClientStorageOp::TakeTls13Ticket(_, false)));
+}
+
+#[test]
+fn test_client_mtu_reduction() {
+    struct CollectWrites {
+        writevs: Vec<Vec<usize>>,
+    }
+
+    impl io::Write for CollectWrites {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            panic!()
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            panic!()
+        }
+        fn write_vectored(&mut self, b: &[io::IoSlice<'_>]) -> io::Result<usize> {
+            let writes = b
+                .iter()
+                .map(|slice| slice.len())
+                .collect::<Vec<usize>>();
+            let len = writes.iter().sum();
+            self.writevs.push(writes);
+            Ok(len)
+        }
+    }
+
+    fn collect_write_lengths(client: &mut ClientConnection) -> Vec<usize> {
+        let mut collector = CollectWrites { writevs: vec![] };
+
+        client
+            .write_tls(&mut collector)
             .unwrap();
+        assert_eq!(collector.writevs.len(), 1);
+        collector.writevs[0].clone()
+    }
 
-        assert_eq!(&payload[..PAYLOAD.len()], PAYLOAD);
-        assert_eq!(payload.len(), buf.len() - header_len - tag_len);
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let mut client_config = make_client_config(*kt, &provider);
+        client_config.max_fragment_size = Some(64);
+        let mut client =
+            ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+        let writes = collect_write_lengths(&mut client);
+        println!("writes at mtu=64: {writes:?}");
+        assert!(writes.iter().all(|x| *x <= 64));
+        assert!(writes.len() > 1);
     }
+}
 
-    #[test]
-    fn test_quic_exporter() {
-        let provider = provider::default_provider();
-        for &kt in KeyType::all_for_provider(&provider) {
-            let client_config =
-                make_client_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
-            let server_config =
-                make_server_config_with_versions(kt, &[&rustls::version::TLS13], &provider);
+#[test]
+fn test_server_mtu_reduction() {
+    let provider = provider::default_provider();
+    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+    server_config.max_fragment_size = Some(64);
+    server_config.send_half_rtt_data = true;
+    let (mut client, mut server) = make_pair_for_configs(
+        make_client_config(KeyType::Rsa2048, &provider),
+        server_config,
+    );
 
-            do_exporter_test(client_config, server_config);
-        }
+    let big_data = [0u8; 2048];
+    server
+        .writer()
+        .write_all(&big_data)
+        .unwrap();
+
+    let encryption_overhead = 20; // FIXME: see issue #991
+
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        server.write_tls(&mut pipe).unwrap();
+
+        assert_eq!(pipe.writevs.len(), 1);
+        assert!(
+            pipe.writevs[0]
+                .iter()
+                .all(|x| *x <= 64 + encryption_overhead)
+        );
     }
 
-    #[test]
-    fn test_fragmented_append() {
-        // Create a QUIC client connection.
-        let client_config = make_client_config_with_versions(
-            KeyType::Rsa2048,
-            &[&rustls::version::TLS13],
-            &provider::default_provider(),
+    client.process_new_packets().unwrap();
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+    {
+        let mut pipe = OtherSession::new(&mut client);
+        server.write_tls(&mut pipe).unwrap();
+        assert_eq!(pipe.writevs.len(), 1);
+        assert!(
+            pipe.writevs[0]
+                .iter()
+                .all(|x| *x <= 64 + encryption_overhead)
         );
-        let client_config = Arc::new(client_config);
-        let mut client = quic::ClientConnection::new(
-            client_config.clone(),
-            quic::Version::V1,
-            server_name("localhost"),
-            b"client params"[..].into(),
-        )
-        .unwrap();
+    }
 
-        // Construct a message that is too large to fit in a single QUIC packet.
-        // We want the partial pieces to be large enough to overflow the deframer's
-        // 4096 byte buffer if mishandled.
-        let mut out = vec![0; 4096];
-        let len_bytes = u32::to_be_bytes(9266_u32);
-        out[1..4].copy_from_slice(&len_bytes[1..]);
+    client.process_new_packets().unwrap();
+    check_read(&mut client.reader(), &big_data);
+}
 
-        // Read the message - this will put us into a joining handshake message state, buffering
-        // 4096 bytes into the deframer buffer.
-        client.read_hs(&out).unwrap();
+fn check_client_max_fragment_size(size: usize) -> Option<Error> {
+    let provider = provider::default_provider();
+    let mut client_config = make_client_config(KeyType::Ed25519, &provider);
+    client_config.max_fragment_size = Some(size);
+    ClientConnection::new(Arc::new(client_config), server_name("localhost")).err()
+}
 
-        // Read the message again - once more it isn't a complete message, so we'll try to
-        // append another 4096 bytes into the deframer buffer.
-        //
-        // If the deframer mishandles writing into the used buffer space this will panic with
-        // an index out of range error:
-        //   range end index 8192 out of range for slice of length 4096
-        client.read_hs(&out).unwrap();
-    }
-} // mod test_quic
+#[test]
+fn bad_client_max_fragment_sizes() {
+    assert_eq!(
+        check_client_max_fragment_size(31),
+        Some(Error::BadMaxFragmentSize)
+    );
+    assert_eq!(check_client_max_fragment_size(32), None);
+    assert_eq!(check_client_max_fragment_size(64), None);
+    assert_eq!(check_client_max_fragment_size(1460), None);
+    assert_eq!(check_client_max_fragment_size(0x4000), None);
+    assert_eq!(check_client_max_fragment_size(0x4005), None);
+    assert_eq!(
+        check_client_max_fragment_size(0x4006),
+        Some(Error::BadMaxFragmentSize)
+    );
+    assert_eq!(
+        check_client_max_fragment_size(0xffff),
+        Some(Error::BadMaxFragmentSize)
+    );
+}
 
 #[test]
-fn test_client_config_keyshare() {
+fn handshakes_complete_and_data_flows_with_gratuitious_max_fragment_sizes() {
+    // general exercising of msgs::fragmenter and msgs::deframer
     let provider = provider::default_provider();
-    let kx_groups = vec![provider::kx_group::SECP384R1];
-    let client_config =
-        make_client_config_with_kx_groups(KeyType::Rsa2048, kx_groups.clone(), &provider);
-    let server_config = make_server_config_with_kx_groups(KeyType::Rsa2048, kx_groups, &provider);
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-    do_handshake_until_error(&mut client, &mut server).unwrap();
+    for kt in KeyType::all_for_provider(&provider) {
+        for version in rustls::ALL_VERSIONS {
+            // no hidden significance to these numbers
+            for frag_size in [37, 61, 101, 257] {
+                println!("test kt={kt:?} version={version:?} frag={frag_size:?}");
+                let mut client_config =
+                    make_client_config_with_versions(*kt, &[version], &provider);
+                client_config.max_fragment_size = Some(frag_size);
+                let mut server_config = make_server_config(*kt, &provider);
+                server_config.max_fragment_size = Some(frag_size);
+
+                let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+                do_handshake(&mut client, &mut server);
+
+                // check server -> client data flow
+                let pattern = (0x00..=0xffu8).collect::<Vec<u8>>();
+                assert_eq!(pattern.len(), server.writer().write(&pattern).unwrap());
+                transfer(&mut server, &mut client);
+                client.process_new_packets().unwrap();
+                check_read(&mut client.reader(), &pattern);
+
+                // and client -> server
+                assert_eq!(pattern.len(), client.writer().write(&pattern).unwrap());
+                transfer(&mut client, &mut server);
+                server.process_new_packets().unwrap();
+                check_read(&mut server.reader(), &pattern);
+            }
+        }
+    }
+}
+
+fn assert_lt(left: usize, right: usize) {
+    if left >= right {
+        panic!("expected {left} < {right}");
+    }
 }
 
 #[test]
-fn test_client_config_keyshare_mismatch() {
-    let provider = provider::default_provider();
-    let client_config = make_client_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::SECP384R1],
-        &provider,
+fn connection_types_are_not_huge() {
+    // Arbitrary sizes
+    assert_lt(mem::size_of::<ServerConnection>(), 1600);
+    assert_lt(mem::size_of::<ClientConnection>(), 1600);
+    assert_lt(
+        mem::size_of::<rustls::server::UnbufferedServerConnection>(),
+        1600,
     );
-    let server_config = make_server_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::X25519],
-        &provider,
+    assert_lt(
+        mem::size_of::<rustls::client::UnbufferedClientConnection>(),
+        1600,
     );
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-    assert!(do_handshake_until_error(&mut client, &mut server).is_err());
 }
 
 #[test]
-fn exercise_all_key_exchange_methods() {
-    for version in rustls::ALL_VERSIONS {
-        for kx_group in provider::ALL_KX_GROUPS {
-            if !kx_group
-                .name()
-                .usable_for_version(version.version())
-            {
-                continue;
-            }
-
-            let provider = provider::default_provider();
-            let client_config =
-                make_client_config_with_kx_groups(KeyType::Rsa2048, vec![*kx_group], &provider);
-            let server_config =
-                make_server_config_with_kx_groups(KeyType::Rsa2048, vec![*kx_group], &provider);
-            let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-            assert!(do_handshake_until_error(&mut client, &mut server).is_ok());
-            println!("kx_group {:?} is self-consistent", kx_group.name());
+fn test_server_rejects_clients_without_any_kx_groups() {
+    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    server
+        .read_tls(
+            &mut encoding::message_framing(
+                ContentType::Handshake,
+                ProtocolVersion::TLSv1_2,
+                encoding::client_hello_with_extensions(vec![
+                    encoding::Extension::new_sig_algs(),
+                    encoding::Extension {
+                        typ: ExtensionType::EllipticCurves,
+                        body: encoding::len_u16(vec![]),
+                    },
+                    encoding::Extension {
+                        typ: ExtensionType::KeyShare,
+                        body: encoding::len_u16(vec![]),
+                    },
+                ]),
+            )
+            .as_slice(),
+        )
+        .unwrap();
+    assert_eq!(
+        server.process_new_packets(),
+        Err(Error::InvalidMessage(InvalidMessage::IllegalEmptyList(
+            "NamedGroups"
+        )))
+    );
+}
+
+#[test]
+fn test_server_rejects_clients_without_any_kx_group_overlap() {
+    for version in rustls::ALL_VERSIONS {
+        let (mut client, mut server) = make_pair_for_configs(
+            make_client_config_with_kx_groups(
+                KeyType::Rsa2048,
+                vec![provider::kx_group::X25519],
+                &provider::default_provider(),
+            ),
+            finish_server_config(
+                KeyType::Rsa2048,
+                ServerConfig::builder_with_provider(
+                    CryptoProvider {
+                        kx_groups: vec![provider::kx_group::SECP384R1],
+                        ..provider::default_provider()
+                    }
+                    .into(),
+                )
+                .with_protocol_versions(&[version])
+                .unwrap(),
+            ),
+        );
+        transfer(&mut client, &mut server);
+        assert_eq!(
+            server.process_new_packets(),
+            Err(Error::PeerIncompatible(
+                PeerIncompatible::NoKxGroupsInCommon
+            ))
+        );
+        transfer(&mut server, &mut client);
+        assert_eq!(
+            client.process_new_packets(),
+            Err(Error::AlertReceived(AlertDescription::HandshakeFailure))
+        );
+    }
+}
+
+#[test]
+fn test_client_rejects_illegal_tls13_ccs() {
+    fn corrupt_ccs(msg: &mut Message) -> Altered {
+        if let MessagePayload::ChangeCipherSpec(_) = &mut msg.payload {
+            println!("seen CCS {msg:?}");
+            return Altered::Raw(encoding::message_framing(
+                ContentType::ChangeCipherSpec,
+                ProtocolVersion::TLSv1_2,
+                vec![0x01, 0x02],
+            ));
         }
+        Altered::InPlace
     }
+
+    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
+    transfer(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+
+    let (mut server, mut client) = (server.into(), client.into());
+
+    transfer_altered(&mut server, corrupt_ccs, &mut client);
+    assert_eq!(
+        client.process_new_packets(),
+        Err(Error::PeerMisbehaved(
+            PeerMisbehaved::IllegalMiddleboxChangeCipherSpec
+        ))
+    );
 }
 
+/// https://github.com/rustls/rustls/issues/797
 #[test]
-fn test_client_sends_helloretryrequest() {
+fn test_client_tls12_no_resume_after_server_downgrade() {
     let provider = provider::default_provider();
-    // client sends a secp384r1 key share
-    let mut client_config = make_client_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::SECP384R1, provider::kx_group::X25519],
-        &provider,
-    );
+    let mut client_config = common::make_client_config(KeyType::Ed25519, &provider);
+    let client_storage = Arc::new(ClientStorage::new());
+    client_config.resumption = Resumption::store(client_storage.clone());
+    let client_config = Arc::new(client_config);
 
-    let storage = Arc::new(ClientStorage::new());
-    client_config.resumption = Resumption::store(storage.clone());
+    let server_config_1 = Arc::new(common::finish_server_config(
+        KeyType::Ed25519,
+        server_config_builder_with_versions(&[&rustls::version::TLS13], &provider),
+    ));
 
-    // but server only accepts x25519, so a HRR is required
-    let server_config = make_server_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::X25519],
-        &provider,
+    let mut server_config_2 = common::finish_server_config(
+        KeyType::Ed25519,
+        server_config_builder_with_versions(&[&rustls::version::TLS12], &provider),
     );
+    server_config_2.session_storage = Arc::new(rustls::server::NoServerSessionStorage {});
 
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    dbg!("handshake 1");
+    let mut client_1 =
+        ClientConnection::new(client_config.clone(), "localhost".try_into().unwrap()).unwrap();
+    let mut server_1 = ServerConnection::new(server_config_1).unwrap();
+    common::do_handshake(&mut client_1, &mut server_1);
 
-    assert_eq!(client.handshake_kind(), None);
-    assert_eq!(server.handshake_kind(), None);
+    assert_eq!(client_storage.ops().len(), 7);
+    println!("hs1 storage ops: {:#?}", client_storage.ops());
+    assert!(matches!(
+        client_storage.ops()[3],
+        ClientStorageOp::SetKxHint(_, _)
+    ));
+    assert!(matches!(
+        client_storage.ops()[4],
+        ClientStorageOp::RemoveTls12Session(_)
+    ));
+    assert!(matches!(
+        client_storage.ops()[5],
+        ClientStorageOp::InsertTls13Ticket(_)
+    ));
 
-    // client sends hello
-    {
-        let mut pipe = OtherSession::new(&mut server);
-        let wrlen = client.write_tls(&mut pipe).unwrap();
-        assert!(wrlen > 200);
-        assert_eq!(pipe.writevs.len(), 1);
-        assert!(pipe.writevs[0].len() == 1);
-    }
+    dbg!("handshake 2");
+    let mut client_2 =
+        ClientConnection::new(client_config, "localhost".try_into().unwrap()).unwrap();
+    let mut server_2 = ServerConnection::new(Arc::new(server_config_2)).unwrap();
+    common::do_handshake(&mut client_2, &mut server_2);
+    println!("hs2 storage ops: {:#?}", client_storage.ops());
+    assert_eq!(client_storage.ops().len(), 9);
 
-    assert_eq!(client.handshake_kind(), None);
+    // attempt consumes a TLS1.3 ticket
+    assert!(matches!(
+        client_storage.ops()[7],
+        ClientStorageOp::TakeTls13Ticket(_, true)
+    ));
+
+    // but ends up with TLS1.2
     assert_eq!(
-        server.handshake_kind(),
-        Some(HandshakeKind::FullWithHelloRetryRequest)
+        client_2.protocol_version(),
+        Some(rustls::ProtocolVersion::TLSv1_2)
     );
+}
 
-    // server sends HRR
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        assert!(wrlen < 100); // just the hello retry request
-        assert_eq!(pipe.writevs.len(), 1); // only one writev
-        assert!(pipe.writevs[0].len() == 2); // hello retry request and CCS
-    }
+#[test]
+fn test_acceptor() {
+    use rustls::server::Acceptor;
+
+    let provider = provider::default_provider();
+    let client_config = Arc::new(make_client_config(KeyType::Ed25519, &provider));
+    let mut client = ClientConnection::new(client_config, server_name("localhost")).unwrap();
+    let mut buf = Vec::new();
+    client.write_tls(&mut buf).unwrap();
 
+    let server_config = Arc::new(make_server_config(KeyType::Ed25519, &provider));
+    let mut acceptor = Acceptor::default();
+    acceptor
+        .read_tls(&mut buf.as_slice())
+        .unwrap();
+    let accepted = acceptor.accept().unwrap().unwrap();
+    let ch = accepted.client_hello();
     assert_eq!(
-        client.handshake_kind(),
-        Some(HandshakeKind::FullWithHelloRetryRequest)
+        ch.server_name(),
+        Some(&DnsName::try_from("localhost").unwrap())
     );
     assert_eq!(
-        server.handshake_kind(),
-        Some(HandshakeKind::FullWithHelloRetryRequest)
+        ch.named_groups().unwrap(),
+        provider::default_provider()
+            .kx_groups
+            .iter()
+            .map(|kx| kx.name())
+            .collect::<Vec<NamedGroup>>()
     );
 
-    // client sends fixed hello
-    {
-        let mut pipe = OtherSession::new(&mut server);
-        let wrlen = client.write_tls(&mut pipe).unwrap();
-        assert!(wrlen > 200); // just the client hello retry
-        assert_eq!(pipe.writevs.len(), 1); // only one writev
-        assert!(pipe.writevs[0].len() == 2); // only a CCS & client hello retry
-    }
+    let server = accepted
+        .into_connection(server_config)
+        .unwrap();
+    assert!(server.wants_write());
 
-    // server completes handshake
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        let wrlen = server.write_tls(&mut pipe).unwrap();
-        assert!(wrlen > 200);
-        assert_eq!(pipe.writevs.len(), 1);
-        assert_eq!(pipe.writevs[0].len(), 2); // { server hello / encrypted exts / cert / cert-verify } / finished
-    }
+    // Reusing an acceptor is not allowed
+    assert_eq!(
+        acceptor
+            .read_tls(&mut &[0u8][..])
+            .err()
+            .unwrap()
+            .kind(),
+        io::ErrorKind::Other,
+    );
+    assert_eq!(
+        acceptor.accept().err().unwrap().0,
+        Error::General("Acceptor polled after completion".into())
+    );
+
+    let mut acceptor = Acceptor::default();
+    assert!(acceptor.accept().unwrap().is_none());
+    acceptor
+        .read_tls(&mut &buf[..3])
+        .unwrap(); // incomplete message
+    assert!(acceptor.accept().unwrap().is_none());
+
+    acceptor
+        .read_tls(&mut &[0x80, 0x00][..])
+        .unwrap(); // invalid message (len = 32k bytes)
+    let (err, mut alert) = acceptor.accept().unwrap_err();
+    assert_eq!(err, Error::InvalidMessage(InvalidMessage::MessageTooLarge));
+    let mut alert_content = Vec::new();
+    let _ = alert.write(&mut alert_content);
+    let expected = build_alert(AlertLevel::Fatal, AlertDescription::DecodeError, &[]);
+    assert_eq!(alert_content, expected);
+
+    let mut acceptor = Acceptor::default();
+    // Minimal valid 1-byte application data message is not a handshake message
+    acceptor
+        .read_tls(
+            &mut encoding::message_framing(
+                ContentType::ApplicationData,
+                ProtocolVersion::TLSv1_2,
+                vec![0x00],
+            )
+            .as_slice(),
+        )
+        .unwrap();
+    let (err, mut alert) = acceptor.accept().unwrap_err();
+    assert!(matches!(err, Error::InappropriateMessage { .. }));
+    let mut alert_content = Vec::new();
+    let _ = alert.write(&mut alert_content);
+    assert!(alert_content.is_empty()); // We do not expect an alert for this condition.
+
+    let mut acceptor = Acceptor::default();
+    // Minimal 1-byte ClientHello message is not a legal handshake message
+    acceptor
+        .read_tls(
+            &mut encoding::message_framing(
+                ContentType::Handshake,
+                ProtocolVersion::TLSv1_2,
+                encoding::handshake_framing(HandshakeType::ClientHello, vec![0x00]),
+            )
+            .as_slice(),
+        )
+        .unwrap();
+    let (err, mut alert) = acceptor.accept().unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InvalidMessage(InvalidMessage::MissingData(_))
+    ));
+    let mut alert_content = Vec::new();
+    let _ = alert.write(&mut alert_content);
+    let expected = build_alert(AlertLevel::Fatal, AlertDescription::DecodeError, &[]);
+    assert_eq!(alert_content, expected);
+}
+
+struct PemFiles {
+    cert_chain: std::path::PathBuf,
+    key: std::path::PathBuf,
+}
 
-    assert_eq!(
-        client.handshake_kind(),
-        Some(HandshakeKind::FullWithHelloRetryRequest)
-    );
-    assert_eq!(
-        server.handshake_kind(),
-        Some(HandshakeKind::FullWithHelloRetryRequest)
-    );
+impl PemFiles {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir();
+        let prefix = format!("rustls-{name}-{}", std::process::id());
+        Self {
+            cert_chain: dir.join(format!("{prefix}.chain.pem")),
+            key: dir.join(format!("{prefix}.key.pem")),
+        }
+    }
 
-    do_handshake_until_error(&mut client, &mut server).unwrap();
+    fn write(&self, cert_chain: &[u8], key: &[u8]) {
+        std::fs::write(&self.cert_chain, cert_chain).unwrap();
+        std::fs::write(&self.key, key).unwrap();
+    }
+}
 
-    // client only did following storage queries:
-    println!("storage {:#?}", storage.ops());
-    assert_eq!(storage.ops().len(), 7);
-    assert!(matches!(
-        storage.ops()[0],
-        ClientStorageOp::TakeTls13Ticket(_, false)
-    ));
-    assert!(matches!(
-        storage.ops()[1],
-        ClientStorageOp::GetTls12Session(_, false)
-    ));
-    assert!(matches!(
-        storage.ops()[2],
-        ClientStorageOp::GetKxHint(_, None)
-    ));
-    assert!(matches!(
-        storage.ops()[3],
-        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::X25519)
-    ));
-    assert!(matches!(
-        storage.ops()[4],
-        ClientStorageOp::RemoveTls12Session(_)
-    ));
-    // server sends 2 tickets by default
-    assert!(matches!(
-        storage.ops()[5],
-        ClientStorageOp::InsertTls13Ticket(_)
-    ));
-    assert!(matches!(
-        storage.ops()[6],
-        ClientStorageOp::InsertTls13Ticket(_)
-    ));
+impl Drop for PemFiles {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.cert_chain);
+        let _ = std::fs::remove_file(&self.key);
+    }
 }
 
 #[test]
-fn test_client_attempts_to_use_unsupported_kx_group() {
-    // common to both client configs
-    let shared_storage = Arc::new(ClientStorage::new());
+fn test_pem_file_cert_and_key_reloads_for_server() {
     let provider = provider::default_provider();
+    let (old, new) = (KeyType::EcdsaP256, KeyType::Rsa2048);
+    let files = PemFiles::new("server-reload");
+    files.write(old.bytes_for("end.fullchain"), old.bytes_for("end.key"));
 
-    // first, client sends a secp-256 share and server agrees. secp-256 is inserted
-    //   into kx group cache.
-    let mut client_config_1 = make_client_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::SECP256R1],
-        &provider,
+    let resolver = Arc::new(
+        sign::PemFileCertAndKey::new(&files.cert_chain, &files.key, provider.clone().into())
+            .unwrap(),
     );
-    client_config_1.resumption = Resumption::store(shared_storage.clone());
-
-    // second, client only supports secp-384 and so kx group cache
-    //   contains an unusable value.
-    let mut client_config_2 = make_client_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::SECP384R1],
-        &provider,
+    let server_config = Arc::new(
+        server_config_builder(&provider)
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone()),
     );
-    client_config_2.resumption = Resumption::store(shared_storage.clone());
-
-    let server_config = make_server_config(KeyType::Rsa2048, &provider);
+    let old_client_config = Arc::new(make_client_config(old, &provider));
+    let new_client_config = Arc::new(make_client_config(new, &provider));
+    let handshake = |client_config: &Arc<ClientConfig>| {
+        let (mut client, mut server) = make_pair_for_arc_configs(client_config, &server_config);
+        do_handshake(&mut client, &mut server);
+        client
+            .peer_certificates()
+            .unwrap()
+            .to_vec()
+    };
 
-    // first handshake
-    let (mut client_1, mut server) = make_pair_for_configs(client_config_1, server_config.clone());
-    do_handshake_until_error(&mut client_1, &mut server).unwrap();
+    assert_eq!(handshake(&old_client_config), old.get_chain());
 
-    let ops = shared_storage.ops();
-    println!("storage {ops:#?}");
-    assert_eq!(ops.len(), 7);
-    assert!(matches!(
-        ops[3],
-        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::secp256r1)
-    ));
+    // a key which does not match the certificate is rejected, and the old one kept
+    files.write(new.bytes_for("end.fullchain"), old.bytes_for("end.key"));
+    assert_eq!(
+        resolver.reload(),
+        Err(Error::InconsistentKeys(InconsistentKeys::KeyMismatch))
+    );
+    assert_eq!(handshake(&old_client_config), old.get_chain());
 
-    // second handshake
-    let (mut client_2, mut server) = make_pair_for_configs(client_config_2, server_config);
-    do_handshake_until_error(&mut client_2, &mut server).unwrap();
+    // as is a missing file
+    std::fs::remove_file(&files.key).unwrap();
+    assert!(matches!(resolver.reload(), Err(Error::General(_))));
+    assert_eq!(handshake(&old_client_config), old.get_chain());
 
-    let ops = shared_storage.ops();
-    println!("storage {:?} {:#?}", ops.len(), ops);
-    assert_eq!(ops.len(), 13);
-    assert!(matches!(ops[7], ClientStorageOp::TakeTls13Ticket(_, true)));
-    assert!(matches!(
-        ops[8],
-        ClientStorageOp::GetKxHint(_, Some(rustls::NamedGroup::secp256r1))
-    ));
-    assert!(matches!(
-        ops[9],
-        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::secp384r1)
-    ));
+    files.write(new.bytes_for("end.fullchain"), new.bytes_for("end.key"));
+    resolver.reload().unwrap();
+    assert_eq!(handshake(&new_client_config), new.get_chain());
 }
 
 #[test]
-fn test_client_sends_share_for_less_preferred_group() {
-    // this is a test for the case described in:
-    // https://datatracker.ietf.org/doc/draft-davidben-tls-key-share-prediction/
+fn test_pem_file_cert_and_key_reloads_on_interval() {
+    #[derive(Debug)]
+    struct FakeTime(Mutex<u64>);
 
-    // common to both client configs
-    let shared_storage = Arc::new(ClientStorage::new());
-    let provider = provider::default_provider();
+    impl rustls::time_provider::TimeProvider for FakeTime {
+        fn current_time(&self) -> Option<UnixTime> {
+            Some(UnixTime::since_unix_epoch(Duration::from_secs(
+                *self.0.lock().unwrap(),
+            )))
+        }
+    }
 
-    // first, client sends a secp384r1 share and server agrees. secp384r1 is inserted
-    //   into kx group cache.
-    let mut client_config_1 = make_client_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::SECP384R1],
-        &provider,
-    );
-    client_config_1.resumption = Resumption::store(shared_storage.clone());
+    let provider = provider::default_provider();
+    let (old, new) = (KeyType::EcdsaP256, KeyType::Ed25519);
+    let files = PemFiles::new("interval-reload");
+    files.write(old.bytes_for("end.fullchain"), old.bytes_for("end.key"));
 
-    // second, client supports (x25519, secp384r1) and so kx group cache
-    //   contains a supported but less-preferred group.
-    let mut client_config_2 = make_client_config_with_kx_groups(
-        KeyType::Rsa2048,
-        vec![provider::kx_group::X25519, provider::kx_group::SECP384R1],
-        &provider,
-    );
-    client_config_2.resumption = Resumption::store(shared_storage.clone());
+    let time = Arc::new(FakeTime(Mutex::new(1_000)));
+    let resolver =
+        sign::PemFileCertAndKey::new(&files.cert_chain, &files.key, provider.clone().into())
+            .unwrap()
+            .with_reload_interval(Duration::from_secs(60), time.clone());
 
-    let server_config = make_server_config_with_kx_groups(
-        KeyType::Rsa2048,
-        provider::ALL_KX_GROUPS.to_vec(),
-        &provider,
-    );
+    files.write(new.bytes_for("end.fullchain"), new.bytes_for("end.key"));
+    *time.0.lock().unwrap() = 1_059;
+    assert_eq!(resolver.current().cert_chain, old.get_chain());
 
-    // first handshake
-    let (mut client_1, mut server) = make_pair_for_configs(client_config_1, server_config.clone());
-    do_handshake_until_error(&mut client_1, &mut server).unwrap();
-    assert_eq!(
-        client_1
-            .negotiated_key_exchange_group()
-            .map(|kxg| kxg.name()),
-        Some(NamedGroup::secp384r1)
-    );
-    assert_eq!(client_1.handshake_kind(), Some(HandshakeKind::Full));
+    *time.0.lock().unwrap() = 1_060;
+    assert_eq!(resolver.current().cert_chain, new.get_chain());
 
-    let ops = shared_storage.ops();
-    println!("storage {ops:#?}");
-    assert_eq!(ops.len(), 7);
-    assert!(matches!(
-        ops[3],
-        ClientStorageOp::SetKxHint(_, rustls::NamedGroup::secp384r1)
-    ));
+    // a failed reload keeps the previous key, and is retried after another interval
+    files.write(old.bytes_for("end.fullchain"), new.bytes_for("end.key"));
+    *time.0.lock().unwrap() = 1_120;
+    assert_eq!(resolver.current().cert_chain, new.get_chain());
 
-    // second handshake; HRR'd from secp384r1 to X25519
-    let (mut client_2, mut server) = make_pair_for_configs(client_config_2, server_config);
-    do_handshake(&mut client_2, &mut server);
-    assert_eq!(
-        client_2
-            .negotiated_key_exchange_group()
-            .map(|kxg| kxg.name()),
-        Some(NamedGroup::X25519)
-    );
-    assert_eq!(
-        client_2.handshake_kind(),
-        Some(HandshakeKind::FullWithHelloRetryRequest)
-    );
+    files.write(old.bytes_for("end.fullchain"), old.bytes_for("end.key"));
+    *time.0.lock().unwrap() = 1_150;
+    assert_eq!(resolver.current().cert_chain, new.get_chain());
+    *time.0.lock().unwrap() = 1_180;
+    assert_eq!(resolver.current().cert_chain, old.get_chain());
 }
 
 #[test]
-fn test_tls13_client_resumption_does_not_reuse_tickets() {
-    let shared_storage = Arc::new(ClientStorage::new());
+fn test_pem_file_cert_and_key_for_client() {
     let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let files = PemFiles::new("client-reload");
+        files.write(kt.bytes_for("client.fullchain"), kt.bytes_for("client.key"));
 
-    let mut client_config = make_client_config(KeyType::Rsa2048, &provider);
-    client_config.resumption = Resumption::store(shared_storage.clone());
-    let client_config = Arc::new(client_config);
+        let resolver =
+            sign::PemFileCertAndKey::new(&files.cert_chain, &files.key, provider.clone().into())
+                .unwrap();
+        let mut root_store = RootCertStore::empty();
+        root_store
+            .add(kt.ca_cert().into_owned())
+            .unwrap();
+        let client_config = client_config_builder(&provider)
+            .with_root_certificates(root_store)
+            .with_client_cert_resolver(Arc::new(resolver));
+        let server_config = make_server_config_with_mandatory_client_auth(*kt, &provider);
 
-    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
-    server_config.send_tls13_tickets = 5;
-    let server_config = Arc::new(server_config);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
+        assert_eq!(server.peer_certificates(), Some(&kt.get_client_chain()[..]));
+    }
+}
 
-    // first handshake: client obtains 5 tickets from server.
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    do_handshake_until_error(&mut client, &mut server).unwrap();
+#[test]
+fn test_client_hello_fingerprint() {
+    use rustls::server::Acceptor;
 
-    let ops = shared_storage.ops_and_reset();
-    println!("storage {ops:#?}");
-    assert_eq!(ops.len(), 10);
-    assert!(matches!(ops[5], ClientStorageOp::InsertTls13Ticket(_)));
-    assert!(matches!(ops[6], ClientStorageOp::InsertTls13Ticket(_)));
-    assert!(matches!(ops[7], ClientStorageOp::InsertTls13Ticket(_)));
-    assert!(matches!(ops[8], ClientStorageOp::InsertTls13Ticket(_)));
-    assert!(matches!(ops[9], ClientStorageOp::InsertTls13Ticket(_)));
+    let provider = provider::default_provider();
+    let mut client_config = make_client_config(KeyType::Ed25519, &provider);
+    client_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    let mut client =
+        ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+    let mut buf = Vec::new();
+    client.write_tls(&mut buf).unwrap();
 
-    // 5 subsequent handshakes: all are resumptions
+    let mut acceptor = Acceptor::default();
+    acceptor
+        .read_tls(&mut buf.as_slice())
+        .unwrap();
+    let accepted = acceptor.accept().unwrap().unwrap();
+    let fingerprint = accepted
+        .client_hello()
+        .fingerprint()
+        .unwrap()
+        .clone();
 
-    // Note: we don't do complete the handshakes, because that means
-    // we get five additional tickets per connection which is unhelpful
-    // in this test.  It also acts to record a "Happy Eyeballs"-type use
-    // case, where a client speculatively makes many connection attempts
-    // in parallel without knowledge of which will work due to underlying
-    // connectivity uncertainty.
-    for _ in 0..5 {
-        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-        transfer(&mut client, &mut server);
-        server.process_new_packets().unwrap();
+    // legacy_version is TLS1.2; the cipher suites are the provider's, in order,
+    // followed by TLS_EMPTY_RENEGOTIATION_INFO_SCSV.
+    let ciphers = provider
+        .cipher_suites
+        .iter()
+        .map(|cs| u16::from(cs.suite()).to_string())
+        .collect::<Vec<_>>()
+        .join("-");
+    assert!(
+        fingerprint
+            .ja3()
+            .starts_with(&format!("771,{ciphers}-255,"))
+    );
+    assert!(
+        fingerprint
+            .extensions()
+            .contains(&0x0000)
+    );
+    assert!(
+        fingerprint
+            .extensions()
+            .contains(&0x0010)
+    );
 
-        let ops = shared_storage.ops_and_reset();
-        assert!(matches!(ops[0], ClientStorageOp::TakeTls13Ticket(_, true)));
-    }
+    let ja4_r = fingerprint.ja4_r();
+    assert!(ja4_r.starts_with(&format!("t13d{:02}", provider.cipher_suites.len() + 1)));
+    let (ja4_a, rest) = ja4_r.split_once('_').unwrap();
+    let (ja4_r_b, ja4_r_c) = rest.split_once('_').unwrap();
+    assert!(ja4_a.ends_with("h2"));
 
-    // 6th subsequent handshake: cannot be resumed; we ran out of tickets
-    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
+    let sha256 = provider::cipher_suite::TLS13_AES_128_GCM_SHA256
+        .tls13()
+        .unwrap()
+        .common
+        .hash_provider;
+    let truncated = |input: &str| {
+        sha256.hash(input.as_bytes()).as_ref()[..6]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    };
+    assert_eq!(
+        fingerprint.ja4(sha256).unwrap(),
+        format!("{ja4_a}_{}_{}", truncated(ja4_r_b), truncated(ja4_r_c))
+    );
+
+    let server_config = Arc::new(make_server_config(KeyType::Ed25519, &provider));
+    let mut server = accepted
+        .into_connection(server_config)
+        .unwrap();
+    do_handshake(&mut client, &mut server);
+    assert_eq!(server.client_hello_fingerprint(), Some(&fingerprint));
+}
+
+fn client_hello_fingerprint(client_config: &Arc<ClientConfig>) -> (usize, ClientHelloFingerprint) {
+    let mut client =
+        ClientConnection::new(client_config.clone(), server_name("localhost")).unwrap();
+    let mut buf = Vec::new();
+    client.write_tls(&mut buf).unwrap();
+
+    let mut acceptor = rustls::server::Acceptor::default();
+    acceptor
+        .read_tls(&mut buf.as_slice())
+        .unwrap();
+    let accepted = acceptor.accept().unwrap().unwrap();
+    let fingerprint = accepted
+        .client_hello()
+        .fingerprint()
+        .unwrap()
+        .clone();
+    (buf.len(), fingerprint)
+}
 
-    let ops = shared_storage.ops_and_reset();
-    println!("last {ops:?}");
-    assert!(matches!(ops[0], ClientStorageOp::TakeTls13Ticket(_, false)));
+fn client_config_with_profile(
+    kt: KeyType,
+    provider: &CryptoProvider,
+    profile: ClientHelloProfile,
+) -> Result<ClientConfig, Error> {
+    let mut root_store = RootCertStore::empty();
+    root_store
+        .add(kt.ca_cert().into_owned())
+        .unwrap();
+    Ok(client_config_builder(provider)
+        .with_root_certificates(root_store)
+        .with_client_hello_profile(profile)?
+        .with_no_client_auth())
 }
 
 #[test]
-fn test_client_mtu_reduction() {
-    struct CollectWrites {
-        writevs: Vec<Vec<usize>>,
-    }
+fn test_client_hello_profile() {
+    let provider = provider::default_provider();
 
-    impl io::Write for CollectWrites {
-        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
-            panic!()
-        }
-        fn flush(&mut self) -> io::Result<()> {
-            panic!()
-        }
-        fn write_vectored(&mut self, b: &[io::IoSlice<'_>]) -> io::Result<usize> {
-            let writes = b
-                .iter()
-                .map(|slice| slice.len())
-                .collect::<Vec<usize>>();
-            let len = writes.iter().sum();
-            self.writevs.push(writes);
-            Ok(len)
-        }
-    }
+    let mut profile = ClientHelloProfile::default();
+    profile.cipher_suites = Some(
+        [CipherSuite::Unknown(0x0a0a)]
+            .into_iter()
+            .chain(
+                provider
+                    .cipher_suites
+                    .iter()
+                    .rev()
+                    .map(|cs| cs.suite()),
+            )
+            .collect(),
+    );
+    profile.named_groups = Some(
+        [NamedGroup::Unknown(0x1a1a)]
+            .into_iter()
+            .chain(
+                provider
+                    .kx_groups
+                    .iter()
+                    .rev()
+                    .map(|kx| kx.name()),
+            )
+            .collect(),
+    );
+    profile.extension_order = Some(vec![0x2a2a, 0x0000, 0x0015, 0x002b, 0x000a]);
+    profile.pad_to = Some(512);
 
-    fn collect_write_lengths(client: &mut ClientConnection) -> Vec<usize> {
-        let mut collector = CollectWrites { writevs: vec![] };
+    let client_config =
+        Arc::new(client_config_with_profile(KeyType::Ed25519, &provider, profile).unwrap());
+    let (len, fingerprint) = client_hello_fingerprint(&client_config);
 
-        client
-            .write_tls(&mut collector)
-            .unwrap();
-        assert_eq!(collector.writevs.len(), 1);
-        collector.writevs[0].clone()
+    // the record header, then a 512 byte handshake message
+    assert_eq!(len, 5 + 512);
+    assert_eq!(
+        fingerprint.extensions()[..5],
+        [0x2a2a, 0x0000, 0x0015, 0x002b, 0x000a]
+    );
+
+    let ciphers = provider
+        .cipher_suites
+        .iter()
+        .rev()
+        .map(|cs| u16::from(cs.suite()).to_string())
+        .collect::<Vec<_>>()
+        .join("-");
+    let groups = provider
+        .kx_groups
+        .iter()
+        .rev()
+        .map(|kx| u16::from(kx.name()).to_string())
+        .collect::<Vec<_>>()
+        .join("-");
+    let ja3 = fingerprint.ja3();
+    assert!(ja3.starts_with(&format!("771,{ciphers}-255,")));
+    assert!(ja3.ends_with(&format!(",{groups},0")));
+
+    // apart from the random values and key shares, the shape is the same every time
+    for _ in 0..8 {
+        assert_eq!(client_hello_fingerprint(&client_config).1, fingerprint);
     }
 
-    let provider = provider::default_provider();
     for kt in KeyType::all_for_provider(&provider) {
-        let mut client_config = make_client_config(*kt, &provider);
-        client_config.max_fragment_size = Some(64);
-        let mut client =
-            ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
-        let writes = collect_write_lengths(&mut client);
-        println!("writes at mtu=64: {writes:?}");
-        assert!(writes.iter().all(|x| *x <= 64));
-        assert!(writes.len() > 1);
+        let mut profile = ClientHelloProfile::default();
+        profile.extension_order = Some(vec![0x2a2a, 0x0015]);
+        profile.pad_to = Some(1024);
+        let client_config = client_config_with_profile(*kt, &provider, profile).unwrap();
+        let server_config = make_server_config(*kt, &provider);
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
     }
 }
 
 #[test]
-fn test_server_mtu_reduction() {
+fn test_client_hello_profile_padding_never_overshoots() {
     let provider = provider::default_provider();
-    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
-    server_config.max_fragment_size = Some(64);
-    server_config.send_half_rtt_data = true;
-    let (mut client, mut server) = make_pair_for_configs(
-        make_client_config(KeyType::Rsa2048, &provider),
-        server_config,
-    );
-
-    let big_data = [0u8; 2048];
-    server
-        .writer()
-        .write_all(&big_data)
-        .unwrap();
-
-    let encryption_overhead = 20; // FIXME: see issue #991
+    let hello_len = |pad_to: u16| {
+        let mut profile = ClientHelloProfile::default();
+        profile.pad_to = Some(pad_to);
+        let client_config =
+            Arc::new(client_config_with_profile(KeyType::Ed25519, &provider, profile).unwrap());
+        let (len, fingerprint) = client_hello_fingerprint(&client_config);
+        let padded = fingerprint
+            .extensions()
+            .contains(&u16::from(ExtensionType::Padding));
+        (len, padded)
+    };
 
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        server.write_tls(&mut pipe).unwrap();
+    // less the five byte record header
+    let (unpadded_len, padded) = hello_len(1);
+    assert!(!padded);
+    let unpadded_len = unpadded_len - 5;
 
-        assert_eq!(pipe.writevs.len(), 1);
-        assert!(
-            pipe.writevs[0]
-                .iter()
-                .all(|x| *x <= 64 + encryption_overhead)
-        );
+    for short_by in 1..4 {
+        let pad_to = (unpadded_len + short_by) as u16;
+        assert_eq!(hello_len(pad_to), (5 + unpadded_len, false));
     }
-
-    client.process_new_packets().unwrap();
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
-    {
-        let mut pipe = OtherSession::new(&mut client);
-        server.write_tls(&mut pipe).unwrap();
-        assert_eq!(pipe.writevs.len(), 1);
-        assert!(
-            pipe.writevs[0]
-                .iter()
-                .all(|x| *x <= 64 + encryption_overhead)
-        );
+    for short_by in 4..8 {
+        let pad_to = (unpadded_len + short_by) as u16;
+        assert_eq!(hello_len(pad_to), (5 + pad_to as usize, true));
     }
-
-    client.process_new_packets().unwrap();
-    check_read(&mut client.reader(), &big_data);
 }
 
-fn check_client_max_fragment_size(size: usize) -> Option<Error> {
+#[test]
+fn test_client_hello_profile_is_validated() {
     let provider = provider::default_provider();
-    let mut client_config = make_client_config(KeyType::Ed25519, &provider);
-    client_config.max_fragment_size = Some(size);
-    ClientConnection::new(Arc::new(client_config), server_name("localhost")).err()
-}
+    let check = |profile: ClientHelloProfile, err: &str| {
+        assert_eq!(
+            client_config_with_profile(KeyType::Rsa2048, &provider, profile).err(),
+            Some(Error::General(err.into()))
+        );
+    };
 
-#[test]
-fn bad_client_max_fragment_sizes() {
-    assert_eq!(
-        check_client_max_fragment_size(31),
-        Some(Error::BadMaxFragmentSize)
+    let mut profile = ClientHelloProfile::default();
+    profile.extension_order = Some(vec![0x0029, 0x0000]);
+    check(
+        profile,
+        "client hello profile must order pre_shared_key extension last",
     );
-    assert_eq!(check_client_max_fragment_size(32), None);
-    assert_eq!(check_client_max_fragment_size(64), None);
-    assert_eq!(check_client_max_fragment_size(1460), None);
-    assert_eq!(check_client_max_fragment_size(0x4000), None);
-    assert_eq!(check_client_max_fragment_size(0x4005), None);
-    assert_eq!(
-        check_client_max_fragment_size(0x4006),
-        Some(Error::BadMaxFragmentSize)
+
+    let mut profile = ClientHelloProfile::default();
+    profile.extension_order = Some(vec![0x0000, 0x0000]);
+    check(
+        profile,
+        "client hello profile names extension type 0x0000 more than once",
     );
-    assert_eq!(
-        check_client_max_fragment_size(0xffff),
-        Some(Error::BadMaxFragmentSize)
+
+    let mut profile = ClientHelloProfile::default();
+    profile.cipher_suites = Some(vec![CipherSuite::Unknown(0x1234)]);
+    check(
+        profile,
+        "client hello profile names unsupported cipher suite 0x1234",
     );
-}
 
-#[test]
-fn handshakes_complete_and_data_flows_with_gratuitious_max_fragment_sizes() {
-    // general exercising of msgs::fragmenter and msgs::deframer
-    let provider = provider::default_provider();
-    for kt in KeyType::all_for_provider(&provider) {
-        for version in rustls::ALL_VERSIONS {
-            // no hidden significance to these numbers
-            for frag_size in [37, 61, 101, 257] {
-                println!("test kt={kt:?} version={version:?} frag={frag_size:?}");
-                let mut client_config =
-                    make_client_config_with_versions(*kt, &[version], &provider);
-                client_config.max_fragment_size = Some(frag_size);
-                let mut server_config = make_server_config(*kt, &provider);
-                server_config.max_fragment_size = Some(frag_size);
+    let mut profile = ClientHelloProfile::default();
+    profile.cipher_suites = Some(vec![
+        CipherSuite::Unknown(0x0a0a),
+        CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV,
+    ]);
+    check(profile, "client hello profile offers no cipher suites");
 
-                let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-                do_handshake(&mut client, &mut server);
+    let mut profile = ClientHelloProfile::default();
+    profile.named_groups = Some(vec![NamedGroup::Unknown(0x1234)]);
+    check(
+        profile,
+        "client hello profile names unsupported named group 0x1234",
+    );
 
-                // check server -> client data flow
-                let pattern = (0x00..=0xffu8).collect::<Vec<u8>>();
-                assert_eq!(pattern.len(), server.writer().write(&pattern).unwrap());
-                transfer(&mut server, &mut client);
-                client.process_new_packets().unwrap();
-                check_read(&mut client.reader(), &pattern);
+    let mut profile = ClientHelloProfile::default();
+    profile.signature_schemes = Some(vec![SignatureScheme::Unknown(0x1234)]);
+    check(
+        profile,
+        "client hello profile names unsupported signature scheme 0x1234",
+    );
 
-                // and client -> server
-                assert_eq!(pattern.len(), client.writer().write(&pattern).unwrap());
-                transfer(&mut client, &mut server);
-                server.process_new_packets().unwrap();
-                check_read(&mut server.reader(), &pattern);
-            }
-        }
-    }
+    let mut profile = ClientHelloProfile::default();
+    profile.extension_order = Some(vec![0x0a0a, 0x0029]);
+    profile.signature_schemes = Some(vec![
+        SignatureScheme::Unknown(0x0a0a),
+        SignatureScheme::ED25519,
+    ]);
+    client_config_with_profile(KeyType::Rsa2048, &provider, profile).unwrap();
 }
 
-fn assert_lt(left: usize, right: usize) {
-    if left >= right {
-        panic!("expected {left} < {right}");
+#[derive(Debug)]
+struct TestCustomExtension {
+    typ: u16,
+    contexts: Vec<rustls::ExtensionContext>,
+    received: Mutex<Vec<rustls::ExtensionContext>>,
+}
+
+impl TestCustomExtension {
+    fn new(typ: u16, contexts: &[rustls::ExtensionContext]) -> Arc<Self> {
+        Arc::new(Self {
+            typ,
+            contexts: contexts.to_vec(),
+            received: Mutex::new(Vec::new()),
+        })
     }
 }
 
-#[test]
-fn connection_types_are_not_huge() {
-    // Arbitrary sizes
-    assert_lt(mem::size_of::<ServerConnection>(), 1600);
-    assert_lt(mem::size_of::<ClientConnection>(), 1600);
-    assert_lt(
-        mem::size_of::<rustls::server::UnbufferedServerConnection>(),
-        1600,
-    );
-    assert_lt(
-        mem::size_of::<rustls::client::UnbufferedClientConnection>(),
-        1600,
-    );
-}
+impl rustls::CustomExtension for TestCustomExtension {
+    fn extension_type(&self) -> u16 {
+        self.typ
+    }
 
-#[test]
-fn test_server_rejects_clients_without_any_kx_groups() {
-    let (_, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    server
-        .read_tls(
-            &mut encoding::message_framing(
-                ContentType::Handshake,
-                ProtocolVersion::TLSv1_2,
-                encoding::client_hello_with_extensions(vec![
-                    encoding::Extension::new_sig_algs(),
-                    encoding::Extension {
-                        typ: ExtensionType::EllipticCurves,
-                        body: encoding::len_u16(vec![]),
-                    },
-                    encoding::Extension {
-                        typ: ExtensionType::KeyShare,
-                        body: encoding::len_u16(vec![]),
-                    },
-                ]),
-            )
-            .as_slice(),
-        )
-        .unwrap();
-    assert_eq!(
-        server.process_new_packets(),
-        Err(Error::InvalidMessage(InvalidMessage::IllegalEmptyList(
-            "NamedGroups"
-        )))
-    );
+    fn contexts(&self) -> &[rustls::ExtensionContext] {
+        &self.contexts
+    }
+
+    fn send(
+        &self,
+        context: rustls::ExtensionContext,
+        peer_payload: Option<&[u8]>,
+    ) -> Option<Vec<u8>> {
+        let mut payload = format!("{context:?}").into_bytes();
+        if let Some(peer_payload) = peer_payload {
+            payload.push(b'<');
+            payload.extend_from_slice(peer_payload);
+        }
+        Some(payload)
+    }
+
+    fn receive(&self, context: rustls::ExtensionContext, _payload: &[u8]) -> Result<(), Error> {
+        self.received
+            .lock()
+            .unwrap()
+            .push(context);
+        Ok(())
+    }
 }
 
 #[test]
-fn test_server_rejects_clients_without_any_kx_group_overlap() {
-    for version in rustls::ALL_VERSIONS {
-        let (mut client, mut server) = make_pair_for_configs(
-            make_client_config_with_kx_groups(
-                KeyType::Rsa2048,
-                vec![provider::kx_group::X25519],
-                &provider::default_provider(),
-            ),
-            finish_server_config(
-                KeyType::Rsa2048,
-                ServerConfig::builder_with_provider(
-                    CryptoProvider {
-                        kx_groups: vec![provider::kx_group::SECP384R1],
-                        ..provider::default_provider()
-                    }
-                    .into(),
-                )
-                .with_protocol_versions(&[version])
-                .unwrap(),
-            ),
+fn test_custom_extensions_in_all_tls13_messages() {
+    use rustls::ExtensionContext::*;
+
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider) {
+        let client_ext = TestCustomExtension::new(
+            0xfe01,
+            &[
+                ClientHello,
+                EncryptedExtensions,
+                CertificateRequest,
+                Certificate,
+            ],
         );
-        transfer(&mut client, &mut server);
+        let server_ext = TestCustomExtension::new(
+            0xfe01,
+            &[
+                ClientHello,
+                EncryptedExtensions,
+                CertificateRequest,
+                Certificate,
+            ],
+        );
+
+        let mut client_config =
+            make_client_config_with_versions_with_auth(*kt, &[&rustls::version::TLS13], &provider);
+        client_config.custom_extensions = vec![client_ext.clone()];
+        let mut server_config = make_server_config_with_mandatory_client_auth(*kt, &provider);
+        server_config.custom_extensions = vec![server_ext.clone()];
+
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        do_handshake(&mut client, &mut server);
+
         assert_eq!(
-            server.process_new_packets(),
-            Err(Error::PeerIncompatible(
-                PeerIncompatible::NoKxGroupsInCommon
-            ))
+            server.received_custom_extension(ClientHello, 0xfe01),
+            Some(&b"ClientHello"[..])
         );
-        transfer(&mut server, &mut client);
         assert_eq!(
-            client.process_new_packets(),
-            Err(Error::AlertReceived(AlertDescription::HandshakeFailure))
+            client.received_custom_extension(EncryptedExtensions, 0xfe01),
+            Some(&b"EncryptedExtensions<ClientHello"[..])
+        );
+        assert_eq!(
+            client.received_custom_extension(CertificateRequest, 0xfe01),
+            Some(&b"CertificateRequest"[..])
+        );
+        assert_eq!(
+            client.received_custom_extension(Certificate, 0xfe01),
+            Some(&b"Certificate<ClientHello"[..])
+        );
+        assert_eq!(
+            server.received_custom_extension(Certificate, 0xfe01),
+            Some(&b"Certificate<CertificateRequest"[..])
+        );
+        assert_eq!(client.received_custom_extension(ClientHello, 0xfe01), None);
+        assert_eq!(server.received_custom_extension(Certificate, 0xfe02), None);
+
+        assert_eq!(
+            *client_ext.received.lock().unwrap(),
+            vec![EncryptedExtensions, CertificateRequest, Certificate]
+        );
+        assert_eq!(
+            *server_ext.received.lock().unwrap(),
+            vec![ClientHello, Certificate]
         );
     }
 }
 
 #[test]
-fn test_client_rejects_illegal_tls13_ccs() {
-    fn corrupt_ccs(msg: &mut Message) -> Altered {
-        if let MessagePayload::ChangeCipherSpec(_) = &mut msg.payload {
-            println!("seen CCS {msg:?}");
-            return Altered::Raw(encoding::message_framing(
-                ContentType::ChangeCipherSpec,
-                ProtocolVersion::TLSv1_2,
-                vec![0x01, 0x02],
-            ));
-        }
-        Altered::InPlace
-    }
+fn test_custom_extensions_in_tls12_client_hello() {
+    use rustls::ExtensionContext::*;
 
-    let (mut client, mut server) = make_pair(KeyType::Rsa2048, &provider::default_provider());
-    transfer(&mut client, &mut server);
-    server.process_new_packets().unwrap();
+    let provider = provider::default_provider();
+    let contexts = [ClientHello, EncryptedExtensions];
+    let mut client_config =
+        make_client_config_with_versions(KeyType::Rsa2048, &[&TLS12], &provider);
+    client_config.custom_extensions = vec![TestCustomExtension::new(0xfe01, &contexts)];
+    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+    server_config.custom_extensions = vec![TestCustomExtension::new(0xfe01, &contexts)];
 
-    let (mut server, mut client) = (server.into(), client.into());
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    do_handshake(&mut client, &mut server);
 
-    transfer_altered(&mut server, corrupt_ccs, &mut client);
     assert_eq!(
-        client.process_new_packets(),
-        Err(Error::PeerMisbehaved(
-            PeerMisbehaved::IllegalMiddleboxChangeCipherSpec
-        ))
+        server.received_custom_extension(ClientHello, 0xfe01),
+        Some(&b"ClientHello"[..])
+    );
+    assert_eq!(
+        client.received_custom_extension(EncryptedExtensions, 0xfe01),
+        None
     );
 }
 
-/// https://github.com/rustls/rustls/issues/797
 #[test]
-fn test_client_tls12_no_resume_after_server_downgrade() {
+fn test_unregistered_custom_extension_is_ignored_by_server() {
     let provider = provider::default_provider();
-    let mut client_config = common::make_client_config(KeyType::Ed25519, &provider);
-    let client_storage = Arc::new(ClientStorage::new());
-    client_config.resumption = Resumption::store(client_storage.clone());
-    let client_config = Arc::new(client_config);
-
-    let server_config_1 = Arc::new(common::finish_server_config(
-        KeyType::Ed25519,
-        server_config_builder_with_versions(&[&rustls::version::TLS13], &provider),
-    ));
-
-    let mut server_config_2 = common::finish_server_config(
-        KeyType::Ed25519,
-        server_config_builder_with_versions(&[&rustls::version::TLS12], &provider),
-    );
-    server_config_2.session_storage = Arc::new(rustls::server::NoServerSessionStorage {});
-
-    dbg!("handshake 1");
-    let mut client_1 =
-        ClientConnection::new(client_config.clone(), "localhost".try_into().unwrap()).unwrap();
-    let mut server_1 = ServerConnection::new(server_config_1).unwrap();
-    common::do_handshake(&mut client_1, &mut server_1);
-
-    assert_eq!(client_storage.ops().len(), 7);
-    println!("hs1 storage ops: {:#?}", client_storage.ops());
-    assert!(matches!(
-        client_storage.ops()[3],
-        ClientStorageOp::SetKxHint(_, _)
-    ));
-    assert!(matches!(
-        client_storage.ops()[4],
-        ClientStorageOp::RemoveTls12Session(_)
-    ));
-    assert!(matches!(
-        client_storage.ops()[5],
-        ClientStorageOp::InsertTls13Ticket(_)
-    ));
-
-    dbg!("handshake 2");
-    let mut client_2 =
-        ClientConnection::new(client_config, "localhost".try_into().unwrap()).unwrap();
-    let mut server_2 = ServerConnection::new(Arc::new(server_config_2)).unwrap();
-    common::do_handshake(&mut client_2, &mut server_2);
-    println!("hs2 storage ops: {:#?}", client_storage.ops());
-    assert_eq!(client_storage.ops().len(), 9);
-
-    // attempt consumes a TLS1.3 ticket
-    assert!(matches!(
-        client_storage.ops()[7],
-        ClientStorageOp::TakeTls13Ticket(_, true)
-    ));
+    let mut client_config = make_client_config(KeyType::Rsa2048, &provider);
+    client_config.custom_extensions = vec![TestCustomExtension::new(
+        0xfe01,
+        &[rustls::ExtensionContext::ClientHello],
+    )];
+    let server_config = make_server_config(KeyType::Rsa2048, &provider);
 
-    // but ends up with TLS1.2
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    do_handshake(&mut client, &mut server);
     assert_eq!(
-        client_2.protocol_version(),
-        Some(rustls::ProtocolVersion::TLSv1_2)
+        server.received_custom_extension(rustls::ExtensionContext::ClientHello, 0xfe01),
+        None
     );
 }
 
 #[test]
-fn test_acceptor() {
-    use rustls::server::Acceptor;
+fn test_client_rejects_unexpected_custom_extension() {
+    use rustls::ExtensionContext::*;
 
     let provider = provider::default_provider();
-    let client_config = Arc::new(make_client_config(KeyType::Ed25519, &provider));
-    let mut client = ClientConnection::new(client_config, server_name("localhost")).unwrap();
-    let mut buf = Vec::new();
-    client.write_tls(&mut buf).unwrap();
-
-    let server_config = Arc::new(make_server_config(KeyType::Ed25519, &provider));
-    let mut acceptor = Acceptor::default();
-    acceptor
-        .read_tls(&mut buf.as_slice())
-        .unwrap();
-    let accepted = acceptor.accept().unwrap().unwrap();
-    let ch = accepted.client_hello();
-    assert_eq!(
-        ch.server_name(),
-        Some(&DnsName::try_from("localhost").unwrap())
-    );
-    assert_eq!(
-        ch.named_groups().unwrap(),
-        provider::default_provider()
-            .kx_groups
-            .iter()
-            .map(|kx| kx.name())
-            .collect::<Vec<NamedGroup>>()
-    );
-
-    let server = accepted
-        .into_connection(server_config)
-        .unwrap();
-    assert!(server.wants_write());
+    let mut client_config = make_client_config(KeyType::Rsa2048, &provider);
+    client_config.custom_extensions = vec![TestCustomExtension::new(0xfe01, &[ClientHello])];
+    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+    server_config.custom_extensions = vec![TestCustomExtension::new(
+        0xfe01,
+        &[ClientHello, EncryptedExtensions],
+    )];
 
-    // Reusing an acceptor is not allowed
-    assert_eq!(
-        acceptor
-            .read_tls(&mut [0u8].as_ref())
-            .err()
-            .unwrap()
-            .kind(),
-        io::ErrorKind::Other,
-    );
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
     assert_eq!(
-        acceptor.accept().err().unwrap().0,
-        Error::General("Acceptor polled after completion".into())
+        do_handshake_until_error(&mut client, &mut server),
+        Err(ErrorFromPeer::Client(Error::PeerMisbehaved(
+            PeerMisbehaved::UnsolicitedEncryptedExtension
+        )))
     );
+}
 
-    let mut acceptor = Acceptor::default();
-    assert!(acceptor.accept().unwrap().is_none());
-    acceptor
-        .read_tls(&mut &buf[..3])
-        .unwrap(); // incomplete message
-    assert!(acceptor.accept().unwrap().is_none());
-
-    acceptor
-        .read_tls(&mut [0x80, 0x00].as_ref())
-        .unwrap(); // invalid message (len = 32k bytes)
-    let (err, mut alert) = acceptor.accept().unwrap_err();
-    assert_eq!(err, Error::InvalidMessage(InvalidMessage::MessageTooLarge));
-    let mut alert_content = Vec::new();
-    let _ = alert.write(&mut alert_content);
-    let expected = build_alert(AlertLevel::Fatal, AlertDescription::DecodeError, &[]);
-    assert_eq!(alert_content, expected);
-
-    let mut acceptor = Acceptor::default();
-    // Minimal valid 1-byte application data message is not a handshake message
-    acceptor
-        .read_tls(
-            &mut encoding::message_framing(
-                ContentType::ApplicationData,
-                ProtocolVersion::TLSv1_2,
-                vec![0x00],
-            )
-            .as_slice(),
-        )
-        .unwrap();
-    let (err, mut alert) = acceptor.accept().unwrap_err();
-    assert!(matches!(err, Error::InappropriateMessage { .. }));
-    let mut alert_content = Vec::new();
-    let _ = alert.write(&mut alert_content);
-    assert!(alert_content.is_empty()); // We do not expect an alert for this condition.
+#[test]
+fn test_custom_extension_types_are_validated() {
+    let provider = provider::default_provider();
+    for types in [&[0x0010][..], &[0xfe01, 0xfe01][..]] {
+        let mut client_config = make_client_config(KeyType::Rsa2048, &provider);
+        client_config.custom_extensions = types
+            .iter()
+            .map(|typ| {
+                TestCustomExtension::new(*typ, &[rustls::ExtensionContext::ClientHello])
+                    as Arc<dyn rustls::CustomExtension>
+            })
+            .collect();
+        assert!(matches!(
+            ClientConnection::new(Arc::new(client_config), server_name("localhost")),
+            Err(Error::General(_))
+        ));
 
-    let mut acceptor = Acceptor::default();
-    // Minimal 1-byte ClientHello message is not a legal handshake message
-    acceptor
-        .read_tls(
-            &mut encoding::message_framing(
-                ContentType::Handshake,
-                ProtocolVersion::TLSv1_2,
-                encoding::handshake_framing(HandshakeType::ClientHello, vec![0x00]),
-            )
-            .as_slice(),
-        )
-        .unwrap();
-    let (err, mut alert) = acceptor.accept().unwrap_err();
-    assert!(matches!(
-        err,
-        Error::InvalidMessage(InvalidMessage::MissingData(_))
-    ));
-    let mut alert_content = Vec::new();
-    let _ = alert.write(&mut alert_content);
-    let expected = build_alert(AlertLevel::Fatal, AlertDescription::DecodeError, &[]);
-    assert_eq!(alert_content, expected);
+        let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+        server_config.custom_extensions = types
+            .iter()
+            .map(|typ| {
+                TestCustomExtension::new(*typ, &[rustls::ExtensionContext::ClientHello])
+                    as Arc<dyn rustls::CustomExtension>
+            })
+            .collect();
+        assert!(matches!(
+            ServerConnection::new(Arc::new(server_config)),
+            Err(Error::General(_))
+        ));
+    }
 }
 
 #[test]
@@ -6439,6 +9591,13 @@ fn test_no_warning_logging_during_successful_sessions() {
             assert!(c.borrow().info.is_empty());
             assert!(!c.borrow().trace.is_empty());
             assert!(!c.borrow().debug.is_empty());
+            // structured fields are appended to the message
+            assert!(
+                c.borrow()
+                    .debug
+                    .iter()
+                    .any(|msg| msg.starts_with("handshake complete: version=Some("))
+            );
         });
     } else {
         COUNTS.with(|c| {
@@ -8024,6 +11183,40 @@ fn hybrid_kx_component_share_offered_but_server_chooses_something_else() {
     );
 }
 
+#[test]
+fn hybrid_kx_component_share_selected_by_server() {
+    // *ring* only has X25519MLKEM768 with the `ring-mlkem` feature.
+    let Some(&hybrid) = provider::ALL_KX_GROUPS
+        .iter()
+        .find(|kxg| kxg.name() == NamedGroup::X25519MLKEM768)
+    else {
+        return;
+    };
+
+    let provider = provider::default_provider();
+    let client_config = make_client_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![hybrid, provider::kx_group::X25519],
+        &provider,
+    );
+    let server_config = make_server_config_with_kx_groups(
+        KeyType::Rsa2048,
+        vec![provider::kx_group::X25519],
+        &provider,
+    );
+
+    // The server can use the X25519 component share directly, without a HelloRetryRequest.
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    do_handshake_until_error(&mut client, &mut server).unwrap();
+    assert_eq!(
+        client
+            .negotiated_key_exchange_group()
+            .map(|kxg| kxg.name()),
+        Some(NamedGroup::X25519)
+    );
+    assert_eq!(client.handshake_kind(), Some(HandshakeKind::Full));
+}
+
 #[derive(Debug)]
 struct FakeHybrid;
 
@@ -14,7 +14,8 @@ use common::{
 };
 use rustls::server::danger::ClientCertVerified;
 use rustls::{
-    AlertDescription, ClientConnection, Error, InvalidMessage, ServerConfig, ServerConnection,
+    AlertDescription, CertificateError, ClientConnection, Error, InvalidMessage, ServerConfig,
+    ServerConnection,
 };
 
 // Client is authorized!
@@ -140,3 +141,35 @@ fn client_verifier_fails_properly() {
         }
     }
 }
+
+#[test]
+// A rejected CertificateVerify signature does not leave its scheme in the connection info
+fn client_verifier_bad_signature_scheme_not_recorded() {
+    let provider = provider::default_provider();
+    for kt in KeyType::all_for_provider(&provider).iter() {
+        let mut client_verifier = MockClientVerifier::new(ver_ok, *kt, &provider);
+        client_verifier.signature_error =
+            Some(Error::InvalidCertificate(CertificateError::BadSignature));
+        let server_config = Arc::new(server_config_with_verifier(*kt, client_verifier));
+
+        for version in rustls::ALL_VERSIONS {
+            let client_config =
+                make_client_config_with_versions_with_auth(*kt, &[version], &provider);
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+            let err = do_handshake_until_error(&mut client, &mut server);
+            assert_eq!(
+                err,
+                Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                    CertificateError::BadSignature
+                )))
+            );
+            assert_eq!(
+                server
+                    .connection_info()
+                    .peer_signature_scheme,
+                None
+            );
+        }
+    }
+}
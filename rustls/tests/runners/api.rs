@@ -25,6 +25,17 @@ mod tests_with_aws_lc_rs {
     mod tests;
 }
 
+#[cfg(feature = "rustcrypto-rsa-signing")]
+#[path = "."]
+mod tests_with_rustcrypto {
+    use super::*;
+
+    provider_rustcrypto!();
+
+    #[path = "../api.rs"]
+    mod tests;
+}
+
 // this must be outside tests_with_*, as we want
 // one thread_local!, not one per provider.
 thread_local!(static COUNTS: RefCell<LogCounts> = RefCell::new(LogCounts::new()));
@@ -18,3 +18,12 @@ mod tests_with_aws_lc_rs {
     #[path = "../api_ffdhe.rs"]
     mod tests;
 }
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+#[path = "."]
+mod tests_with_rustcrypto {
+    provider_rustcrypto!();
+
+    #[path = "../api_ffdhe.rs"]
+    mod tests;
+}
@@ -28,6 +28,17 @@ mod tests_with_aws_lc_rs {
     mod tests;
 }
 
+#[cfg(feature = "rustcrypto-rsa-signing")]
+#[path = "."]
+mod tests_with_rustcrypto {
+    use super::serialized;
+
+    provider_rustcrypto!();
+
+    #[path = "../key_log_file_env.rs"]
+    mod tests;
+}
+
 /// Approximates `#[serial]` from the `serial_test` crate.
 ///
 /// No attempt is made to recover from a poisoned mutex, which will
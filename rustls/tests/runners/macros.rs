@@ -1,8 +1,8 @@
 //! Macros that bring a provider into the current scope.
 //!
 //! The selected provider module is bound as `provider`; you can rely on this
-//! having the union of the public items common to the `rustls::crypto::ring`
-//! and `rustls::crypto::aws_lc_rs` modules.
+//! having the union of the public items common to the `rustls::crypto::ring`,
+//! `rustls::crypto::aws_lc_rs` and `rustls::crypto::rustcrypto` modules.
 
 #[allow(unused_macros)]
 macro_rules! provider_ring {
@@ -18,6 +18,10 @@ macro_rules! provider_ring {
             true
         }
         #[allow(dead_code)]
+        const fn provider_is_rustcrypto() -> bool {
+            false
+        }
+        #[allow(dead_code)]
         const fn provider_is_fips() -> bool {
             false
         }
@@ -38,8 +42,36 @@ macro_rules! provider_aws_lc_rs {
             false
         }
         #[allow(dead_code)]
+        const fn provider_is_rustcrypto() -> bool {
+            false
+        }
+        #[allow(dead_code)]
         const fn provider_is_fips() -> bool {
             cfg!(feature = "fips")
         }
     };
 }
+
+#[allow(unused_macros)]
+macro_rules! provider_rustcrypto {
+    () => {
+        #[allow(unused_imports)]
+        use rustls::crypto::rustcrypto as provider;
+        #[allow(dead_code)]
+        const fn provider_is_aws_lc_rs() -> bool {
+            false
+        }
+        #[allow(dead_code)]
+        const fn provider_is_ring() -> bool {
+            false
+        }
+        #[allow(dead_code)]
+        const fn provider_is_rustcrypto() -> bool {
+            true
+        }
+        #[allow(dead_code)]
+        const fn provider_is_fips() -> bool {
+            false
+        }
+    };
+}
@@ -18,3 +18,12 @@ mod tests_with_aws_lc_rs {
     #[path = "../server_cert_verifier.rs"]
     mod tests;
 }
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+#[path = "."]
+mod tests_with_rustcrypto {
+    provider_rustcrypto!();
+
+    #[path = "../server_cert_verifier.rs"]
+    mod tests;
+}
@@ -0,0 +1,29 @@
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "ring")]
+#[path = "."]
+mod tests_with_ring {
+    provider_ring!();
+
+    #[path = "../tracing.rs"]
+    mod tests;
+}
+
+#[cfg(feature = "aws-lc-rs")]
+#[path = "."]
+mod tests_with_aws_lc_rs {
+    provider_aws_lc_rs!();
+
+    #[path = "../tracing.rs"]
+    mod tests;
+}
+
+#[cfg(feature = "rustcrypto-rsa-signing")]
+#[path = "."]
+mod tests_with_rustcrypto {
+    provider_rustcrypto!();
+
+    #[path = "../tracing.rs"]
+    mod tests;
+}
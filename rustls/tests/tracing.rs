@@ -0,0 +1,164 @@
+//! Tests of the `tracing` integration.
+//!
+//! These are kept apart from the main API tests because installing a `tracing`
+//! dispatcher, even a scoped one, disables forwarding of events to `log`, which
+//! the API tests rely on.
+
+#![allow(clippy::disallowed_types, clippy::duplicate_mod)]
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use super::*;
+
+mod common;
+use common::{
+    Arc, KeyType, do_handshake, make_client_config_with_versions, make_pair_for_configs,
+    make_server_config, transfer,
+};
+
+#[test]
+fn test_tracing_events_during_handshake() {
+    let provider = provider::default_provider();
+    for version in rustls::ALL_VERSIONS {
+        let subscriber = RecordingSubscriber::default();
+        let events = subscriber.events.clone();
+        let spans = subscriber.spans.clone();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let client_config =
+                make_client_config_with_versions(KeyType::EcdsaP256, &[version], &provider);
+            let (mut client, mut server) = make_pair_for_configs(
+                client_config,
+                make_server_config(KeyType::EcdsaP256, &provider),
+            );
+            do_handshake(&mut client, &mut server);
+            server.send_close_notify();
+            transfer(&mut server, &mut client);
+            client.process_new_packets().unwrap();
+        });
+
+        let events = events.lock().unwrap();
+        let spans = spans.lock().unwrap();
+
+        // both sides record the server name on their connection span
+        assert_eq!(spans.len(), 2, "spans: {spans:#?}");
+        for span in spans.iter() {
+            assert_eq!(span.name, "tls_connection");
+            assert!(
+                span.fields["server_name"].contains("localhost"),
+                "spans: {spans:#?}"
+            );
+        }
+
+        let completions = events
+            .iter()
+            .filter(|ev| ev.message == "handshake complete")
+            .collect::<Vec<_>>();
+        assert_eq!(completions.len(), 2, "events: {events:#?}");
+        for ev in completions {
+            assert_eq!(ev.span, Some("tls_connection"));
+            assert_eq!(
+                ev.fields["version"],
+                format!("{:?}", Some(version.version()))
+            );
+            assert!(ev.fields["suite"].starts_with("Some("));
+            assert!(ev.fields["group"].starts_with("Some("));
+        }
+
+        assert!(
+            events.iter().any(|ev| {
+                ev.message == "sending alert" && ev.fields["alert.description"] == "CloseNotify"
+            }),
+            "events: {events:#?}"
+        );
+    }
+}
+
+#[derive(Default)]
+struct RecordingSubscriber {
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+    entered: Mutex<Vec<u64>>,
+}
+
+#[derive(Debug)]
+struct RecordedSpan {
+    name: &'static str,
+    fields: HashMap<&'static str, String>,
+}
+
+#[derive(Debug)]
+struct RecordedEvent {
+    message: String,
+    span: Option<&'static str>,
+    fields: HashMap<&'static str, String>,
+}
+
+impl tracing::Subscriber for RecordingSubscriber {
+    fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut recorded = RecordedSpan {
+            name: span.metadata().name(),
+            fields: HashMap::new(),
+        };
+        span.record(&mut FieldVisitor(&mut recorded.fields));
+
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(recorded);
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let span = &mut spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(&mut span.fields));
+    }
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let span = self
+            .entered
+            .lock()
+            .unwrap()
+            .last()
+            .map(|id| self.spans.lock().unwrap()[*id as usize - 1].name);
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.events
+            .lock()
+            .unwrap()
+            .push(RecordedEvent {
+                message: fields
+                    .remove("message")
+                    .unwrap_or_default(),
+                span,
+                fields,
+            });
+    }
+
+    fn enter(&self, span: &tracing::span::Id) {
+        self.entered
+            .lock()
+            .unwrap()
+            .push(span.into_u64());
+    }
+
+    fn exit(&self, _span: &tracing::span::Id) {
+        self.entered.lock().unwrap().pop();
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl tracing::field::Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name(), format!("{value:?}"));
+    }
+}
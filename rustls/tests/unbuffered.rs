@@ -47,7 +47,7 @@ fn tls12_handshake_fragmented() {
 
     let mut expected_client = TLS12_CLIENT_TRANSCRIPT_FRAGMENTED.to_vec();
     let mut expected_server = TLS12_SERVER_TRANSCRIPT_FRAGMENTED.to_vec();
-    if provider_is_aws_lc_rs() {
+    if provider_is_aws_lc_rs() || provider_is_rustcrypto() {
         // client hello is larger for X25519MLKEM768
         expected_client.splice(0..0, ["EncodeTlsData", "EncodeTlsData"]);
         expected_server.splice(0..0, ["BlockedHandshake", "BlockedHandshake"]);
@@ -87,7 +87,7 @@ fn tls13_handshake_fragmented() {
     let mut expected_client = TLS13_CLIENT_TRANSCRIPT_FRAGMENTED.to_vec();
     let mut expected_server = TLS13_SERVER_TRANSCRIPT_FRAGMENTED.to_vec();
 
-    if provider_is_aws_lc_rs() {
+    if provider_is_aws_lc_rs() || provider_is_rustcrypto() {
         // client hello is larger for X25519MLKEM768
         expected_client.splice(0..0, ["EncodeTlsData", "EncodeTlsData"]);
         expected_server.splice(0..0, ["BlockedHandshake", "BlockedHandshake"]);
@@ -1779,6 +1779,54 @@ fn kernel_key_updates_tls12() {
     assert!(server.update_rx_secret().is_err());
 }
 
+#[test]
+fn kernel_connection_info_matches_unbuffered() {
+    let provider = provider::default_provider();
+    let mut server_config = make_server_config(KeyType::Rsa2048, &provider);
+    server_config.enable_secret_extraction = true;
+    let server_config = Arc::new(server_config);
+
+    let mut client_config = make_client_config(KeyType::Rsa2048, &provider);
+    client_config.enable_secret_extraction = true;
+    let client_config = Arc::new(client_config);
+
+    let mut server = UnbufferedServerConnection::new(server_config).unwrap();
+    let mut client =
+        UnbufferedClientConnection::new(client_config, "localhost".try_into().unwrap()).unwrap();
+
+    do_unbuffered_handshake(&mut client, &mut server);
+
+    let before = client.connection_info();
+    assert_eq!(
+        before
+            .server_name
+            .as_ref()
+            .unwrap()
+            .as_ref(),
+        "localhost"
+    );
+    assert!(before.peer_signature_scheme.is_some());
+
+    let (_, client) = client
+        .dangerous_into_kernel_connection()
+        .expect("failed to convert client connection to an KernelConnection");
+    let after = client.connection_info();
+
+    assert_eq!(after.protocol_version, before.protocol_version);
+    assert_eq!(
+        after
+            .cipher_suite
+            .map(|suite| suite.suite()),
+        before
+            .cipher_suite
+            .map(|suite| suite.suite())
+    );
+    assert_eq!(after.key_exchange_group, before.key_exchange_group);
+    assert_eq!(after.server_name, before.server_name);
+    assert_eq!(after.peer_certificates, before.peer_certificates);
+    assert_eq!(after.peer_signature_scheme, before.peer_signature_scheme);
+}
+
 fn assert_secrets_equal(
     (l_seq, l_sec): (u64, ConnectionTrafficSecrets),
     (r_seq, r_sec): (u64, ConnectionTrafficSecrets),
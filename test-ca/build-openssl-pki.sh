@@ -0,0 +1,96 @@
+#!/bin/sh
+#
+# Generates test PKIs for key types that `rcgen` (and so
+# `rustls/examples/internal/test_ca.rs`) cannot yet produce.
+#
+# The layout and certificate contents mirror those produced by `test_ca.rs`,
+# except that no CRLs are generated.
+#
+# Requires OpenSSL 3.5 or later.  Run from the `test-ca` directory, optionally
+# naming the algorithms to (re)generate:
+#
+#   $ ./build-openssl-pki.sh [ML-DSA-44 ML-DSA-65 ML-DSA-87 ED448]
+#
+set -eu
+
+SAN="DNS:testserver.com,DNS:second.testserver.com,DNS:localhost,IP:198.51.100.1,IP:2001:db8::1"
+ISSUER_KU="critical,cRLSign,keyCertSign,digitalSignature,nonRepudiation,keyEncipherment,dataEncipherment,keyAgreement"
+EE_KU="critical,digitalSignature,nonRepudiation"
+
+serial=1
+
+# issue <dir> <alg> <label> <cn> <issuer label|-> <extensions...>
+issue() {
+  dir=$1 alg=$2 label=$3 cn=$4 issuer=$5
+  shift 5
+
+  # shellcheck disable=SC2086
+  openssl genpkey -algorithm "$alg" $GENPKEY_OPTS -out "$dir/$label.key"
+
+  ext=""
+  for e in "$@"; do
+    ext="$ext -addext $e"
+  done
+
+  if [ "$issuer" = "-" ]; then
+    # shellcheck disable=SC2086
+    openssl req -x509 -new -key "$dir/$label.key" -subj "/CN=$cn" \
+      -days 36500 -set_serial "$serial" $ext -out "$dir/$label.cert"
+  else
+    # shellcheck disable=SC2086
+    openssl req -x509 -new -key "$dir/$label.key" -subj "/CN=$cn" \
+      -CA "$dir/$issuer.cert" -CAkey "$dir/$issuer.key" \
+      -days 36500 -set_serial "$serial" $ext -out "$dir/$label.cert"
+  fi
+  serial=$((serial + 1))
+
+  openssl x509 -in "$dir/$label.cert" -outform DER -out "$dir/$label.der"
+}
+
+if [ $# -eq 0 ]; then
+  set -- ML-DSA-44 ML-DSA-65 ML-DSA-87 ED448
+fi
+
+for alg in "$@"; do
+  case "$alg" in
+    ML-DSA-*)
+      # aws-lc only accepts the seed-only and expanded-key forms of ML-DSA private keys.
+      GENPKEY_OPTS="-provparam ml-dsa.output_formats=seed-only"
+      ;;
+    *)
+      GENPKEY_OPTS=""
+      ;;
+  esac
+
+  dir=$(echo "$alg" | tr '[:upper:]' '[:lower:]')
+  mkdir -p "$dir"
+
+  issue "$dir" "$alg" ca "ponytown $alg CA" - \
+    "basicConstraints=critical,CA:true" \
+    "keyUsage=$ISSUER_KU" \
+    "extendedKeyUsage=serverAuth,clientAuth" \
+    "subjectKeyIdentifier=hash"
+  issue "$dir" "$alg" inter "ponytown $alg level 2 intermediate" ca \
+    "basicConstraints=critical,CA:true" \
+    "keyUsage=$ISSUER_KU" \
+    "extendedKeyUsage=serverAuth,clientAuth" \
+    "subjectKeyIdentifier=hash" \
+    "authorityKeyIdentifier=keyid"
+  issue "$dir" "$alg" end "testserver.com" inter \
+    "basicConstraints=critical,CA:false" \
+    "keyUsage=$EE_KU" \
+    "subjectAltName=$SAN" \
+    "authorityKeyIdentifier=keyid"
+  issue "$dir" "$alg" client "ponytown client" inter \
+    "basicConstraints=critical,CA:false" \
+    "keyUsage=$EE_KU" \
+    "extendedKeyUsage=clientAuth" \
+    "subjectAltName=$SAN" \
+    "authorityKeyIdentifier=keyid"
+
+  for role in end client; do
+    cat "$dir/inter.cert" "$dir/ca.cert" > "$dir/$role.chain"
+    cat "$dir/$role.cert" "$dir/inter.cert" "$dir/ca.cert" > "$dir/$role.fullchain"
+    openssl pkey -in "$dir/$role.key" -pubout -out "$dir/$role.spki.pem"
+  done
+done